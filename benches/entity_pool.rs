@@ -0,0 +1,49 @@
+//! Ticks of a crowded 60x60 world where organisms are born and die every tick, with
+//! and without an entity pool, run with `cargo bench --bench entity_pool`.
+
+use std::time::Instant;
+
+use evolution::{build_app, Config};
+
+const WARMUP_TICKS: usize = 20;
+const TICKS: usize = 300;
+
+fn ms_per_tick(entity_pool_size: usize) -> f64 {
+    let config = Config::builder()
+        .width(60)
+        .height(60)
+        .seed(11)
+        .headless(true)
+        .printing(false)
+        .log_data(false)
+        .initial_organisms(1500)
+        .initial_predators(60)
+        .max_total_entities(3000)
+        .entity_pool_size(entity_pool_size)
+        .build()
+        .expect("valid benchmark config");
+
+    let mut app = build_app(config);
+    app.finish();
+    app.cleanup();
+    for _ in 0..WARMUP_TICKS {
+        app.update();
+    }
+
+    let start = Instant::now();
+    for _ in 0..TICKS {
+        app.update();
+    }
+    start.elapsed().as_secs_f64() * 1000.0 / TICKS as f64
+}
+
+fn main() {
+    for entity_pool_size in [0, 1000] {
+        println!(
+            "entity_pool_size {:>4}: {} ticks at {:.3} ms/tick",
+            entity_pool_size,
+            TICKS,
+            ms_per_tick(entity_pool_size)
+        );
+    }
+}
@@ -0,0 +1,55 @@
+//! Heatmap refresh of a 300x300 world repainting every chunk, as before chunked
+//! updates, against repainting only the chunks a tick of grazing touches, run with
+//! `cargo bench --bench heatmap_refresh`.
+
+use std::time::{Duration, Instant};
+
+use evolution::plugins::rendering::{paint_heatmap, Occupancy};
+use evolution::{default_config, World};
+
+const SIZE: usize = 300;
+const REFRESHES: usize = 500;
+/// Tiles grazed between two refreshes, about what a hundred organisms eat.
+const GRAZED_TILES: usize = 100;
+
+fn main() {
+    let config = default_config();
+    let (world, mut food_grid) = World::new(SIZE, SIZE, 42);
+    let occupancy = Occupancy {
+        organisms: vec![0; SIZE * SIZE],
+        predators: vec![0; SIZE * SIZE],
+        tints: Vec::new(),
+    };
+    let mut data = vec![0u8; SIZE * SIZE * 4];
+
+    let mut time = |food_grid: &mut evolution::FoodGrid, full: bool| {
+        let mut elapsed = Duration::ZERO;
+        for refresh in 0..REFRESHES {
+            for k in 0..GRAZED_TILES {
+                let i = (refresh * 7919 + k * 104_729) % (SIZE * SIZE);
+                food_grid.set(i, food_grid.get(i) * 0.9);
+            }
+            let repaint: Vec<bool> = (0..food_grid.chunk_count())
+                .map(|chunk| full || food_grid.is_dirty(chunk))
+                .collect();
+            let start = Instant::now();
+            paint_heatmap(
+                &mut data, &repaint, &occupancy, false, food_grid, &world, &config,
+            );
+            elapsed += start.elapsed();
+            food_grid.clear_dirty();
+        }
+        elapsed
+    };
+
+    for (label, full) in [("every chunk", true), ("dirty chunks", false)] {
+        let elapsed = time(&mut food_grid, full);
+        println!(
+            "{}: {} refreshes in {:.2?} ({:.3} ms/refresh)",
+            label,
+            REFRESHES,
+            elapsed,
+            elapsed.as_secs_f64() * 1000.0 / REFRESHES as f64
+        );
+    }
+}
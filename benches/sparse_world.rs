@@ -0,0 +1,42 @@
+//! Ticks of a sparse 400x400 world with 100 entities, run with
+//! `cargo bench --bench sparse_world`.
+
+use std::time::Instant;
+
+use evolution::{build_app, Config};
+
+const WARMUP_TICKS: usize = 20;
+const TICKS: usize = 200;
+
+fn main() {
+    let config = Config::builder()
+        .width(400)
+        .height(400)
+        .headless(true)
+        .printing(false)
+        .log_data(false)
+        .initial_organisms(90)
+        .initial_predators(10)
+        .max_total_entities(100)
+        .build()
+        .expect("valid benchmark config");
+
+    let mut app = build_app(config);
+    app.finish();
+    app.cleanup();
+    for _ in 0..WARMUP_TICKS {
+        app.update();
+    }
+
+    let start = Instant::now();
+    for _ in 0..TICKS {
+        app.update();
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{} ticks in {:.2?} ({:.3} ms/tick)",
+        TICKS,
+        elapsed,
+        elapsed.as_secs_f64() * 1000.0 / TICKS as f64
+    );
+}
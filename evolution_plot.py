@@ -1,4 +1,7 @@
 import json
+import os
+from collections import defaultdict
+
 import numpy as np
 import pandas as pd
 import matplotlib.pyplot as plt
@@ -6,6 +9,8 @@ import seaborn as sns
 
 SUMMARY_FILE = "summary_data.jsonl"
 WORLD_FILE = "world_data.jsonl"
+EVENTS_FILE = "events.jsonl"
+HERITABILITY_FILE = "heritability.jsonl"
 
 BIOME_NAMES = ["Forest", "Desert", "Water", "Grassland"]
 BIOME_IDX = {name: i for i, name in enumerate(BIOME_NAMES)}
@@ -17,17 +22,33 @@ BIOME_COLORS = {0: "green", 1: "yellow", 2: "blue", 3: "brown"}
 gen_list = []
 organism_counts = []
 predator_counts = []
+organisms_in_reserves = []
+predators_in_reserves = []
+juvenile_predators = []
 organism_avg_size_list = []
 organism_avg_speed_list = []
 organism_avg_energy_list = []
 organism_avg_reproduction_threshold_list = []
+organism_avg_boldness_list = []
+organism_avg_exploration_list = []
+organism_avg_parental_investment_list = []
 predator_avg_size_list = []
 predator_avg_speed_list = []
 predator_avg_energy_list = []
 predator_avg_reproduction_threshold_list = []
 predator_avg_hunting_efficiency_list = []
 predator_avg_satiation_threshold_list = []
+predator_avg_kills_list = []
+organisms_panicked_list = []
+energy_intake_per_capita_list = []
+morph_counts_list = []
+species_list = []
+# spatial stats are only written every spatial_interval generations
+spatial_gen_list = []
+organism_morisita_list = []
+predator_morisita_list = []
 average_food_per_generation = []
+average_water_food_list = []
 # biome_tally values are avg biome tolerance sums per generation
 biome_tolerance_avg = {name: [] for name in BIOME_NAMES}
 
@@ -38,21 +59,40 @@ with open(SUMMARY_FILE) as f:
         d = json.loads(line)
         gen_list.append(d["generation"])
         organism_counts.append(d["organism_count"])
-        predator_counts.append(d["predator_count"])
+        # prey-only runs (schema 46) leave every predator field out
+        predator_counts.append(d.get("predator_count", 0))
+        organisms_in_reserves.append(d.get("organisms_in_reserves", 0))
+        predators_in_reserves.append(d.get("predators_in_reserves", 0))
+        juvenile_predators.append(d.get("juvenile_predators", 0))
         organism_avg_size_list.append(d["organism_avg_size"])
         organism_avg_speed_list.append(d["organism_avg_speed"])
         organism_avg_energy_list.append(max(d["organism_avg_energy"], 0))
         organism_avg_reproduction_threshold_list.append(d["organism_avg_reproduction_threshold"])
-        predator_avg_size_list.append(d["predator_avg_size"])
-        predator_avg_speed_list.append(d["predator_avg_speed"])
-        predator_avg_energy_list.append(max(d["predator_avg_energy"], 0))
-        predator_avg_reproduction_threshold_list.append(d["predator_avg_reproduction_threshold"])
-        predator_avg_hunting_efficiency_list.append(d["predator_avg_hunting_efficiency"])
-        predator_avg_satiation_threshold_list.append(d["predator_avg_satiation_threshold"])
+        organism_avg_boldness_list.append(d.get("organism_avg_boldness", np.nan))
+        organism_avg_exploration_list.append(d.get("organism_avg_exploration", np.nan))
+        organism_avg_parental_investment_list.append(d.get("organism_avg_parental_investment", np.nan))
+        predator_avg_size_list.append(d.get("predator_avg_size", np.nan))
+        predator_avg_speed_list.append(d.get("predator_avg_speed", np.nan))
+        predator_avg_energy_list.append(max(d.get("predator_avg_energy", 0), 0))
+        predator_avg_reproduction_threshold_list.append(d.get("predator_avg_reproduction_threshold", np.nan))
+        predator_avg_hunting_efficiency_list.append(d.get("predator_avg_hunting_efficiency", np.nan))
+        predator_avg_satiation_threshold_list.append(d.get("predator_avg_satiation_threshold", np.nan))
+        predator_avg_kills_list.append(d.get("predator_avg_kills", np.nan))
+        organisms_panicked_list.append(d.get("organisms_panicked", 0))
+        energy_intake_per_capita_list.append(d.get("organism_energy_intake_per_capita", np.nan))
         average_food_per_generation.append(d["average_food"])
+        average_water_food_list.append(d.get("average_water_food", np.nan))
+        morph_counts_list.append(d.get("morph_counts", []))
+        species_list.append(d.get("species", []))
+        spatial = d.get("spatial")
+        if spatial:
+            spatial_gen_list.append(d["generation"])
+            organism_morisita_list.append(spatial["organisms"]["morisita_index"] or np.nan)
+            predator_morisita_list.append(spatial["predators"]["morisita_index"] or np.nan)
         tally = d["biome_tally"]
         for name in BIOME_NAMES:
-            biome_tolerance_avg[name].append(tally.get(name, 0.0))
+            # keyed by lowercase field since schema 36, by the variant name before
+            biome_tolerance_avg[name].append(tally.get(name.lower(), tally.get(name, 0.0)))
 
 print(f"Loaded {len(gen_list)} generations from {SUMMARY_FILE}")
 
@@ -131,6 +171,34 @@ plt.grid(True)
 plt.savefig("population_trends.png")
 plt.close()
 
+# Population inside vs outside reserves
+if any(organisms_in_reserves) or any(predators_in_reserves):
+    plt.figure(figsize=(10, 5))
+    plt.plot(gen_list, organisms_in_reserves, label="Preys (reserve)", color="lime", linewidth=2)
+    plt.plot(gen_list, np.subtract(organism_counts, organisms_in_reserves), label="Preys (outside)", color="lime", linestyle="--")
+    plt.plot(gen_list, predators_in_reserves, label="Predators (reserve)", color="red", linewidth=2)
+    plt.plot(gen_list, np.subtract(predator_counts, predators_in_reserves), label="Predators (outside)", color="red", linestyle="--")
+    plt.xlabel("Generation")
+    plt.ylabel("Population")
+    plt.title("Population Inside vs Outside Reserves")
+    plt.legend()
+    plt.grid(True)
+    plt.savefig("reserve_population.png")
+    plt.close()
+
+# Juvenile vs adult predators
+if any(juvenile_predators):
+    plt.figure(figsize=(10, 5))
+    plt.plot(gen_list, juvenile_predators, label="Juvenile predators", color="salmon", linewidth=2)
+    plt.plot(gen_list, np.subtract(predator_counts, juvenile_predators), label="Adult predators", color="darkred", linewidth=2)
+    plt.xlabel("Generation")
+    plt.ylabel("Population")
+    plt.title("Predator Age Structure")
+    plt.legend()
+    plt.grid(True)
+    plt.savefig("predator_age_structure.png")
+    plt.close()
+
 # Population heatmap
 plt.figure(figsize=(8, 6))
 sns.heatmap(heatmap_grid, cmap="hot", square=True)
@@ -166,6 +234,7 @@ plt.close()
 # Food availability over time
 plt.figure(figsize=(10, 5))
 plt.plot(gen_list, average_food_per_generation, label="Avg Food", color="orange", linewidth=2)
+plt.plot(gen_list, average_water_food_list, label="Avg Water Food", color="blue", linewidth=2)
 plt.xlabel("Generation")
 plt.ylabel("Average Food Availability")
 plt.title("Food Availability Trends Over Generations")
@@ -235,6 +304,111 @@ plt.grid(True)
 plt.savefig("hunting_efficiency_trends.png")
 plt.close()
 
+# Kills per predator per generation
+plt.figure(figsize=(10, 5))
+plt.plot(gen_list, predator_avg_kills_list, label="Kills per Predator", color="darkred", linewidth=2)
+plt.xlabel("Generation")
+plt.ylabel("Kills")
+plt.title("Kills per Predator per Generation")
+plt.legend()
+plt.grid(True)
+plt.savefig("predator_kills.png")
+plt.close()
+
+# Organisms panicked by kills per generation
+plt.figure(figsize=(10, 5))
+plt.plot(gen_list, organisms_panicked_list, label="Panicked Preys", color="orange", linewidth=2)
+plt.xlabel("Generation")
+plt.ylabel("Organisms")
+plt.title("Organisms Panicked per Generation")
+plt.legend()
+plt.grid(True)
+plt.savefig("panic_trends.png")
+plt.close()
+
+# Energy organisms take in from food
+plt.figure(figsize=(10, 5))
+plt.plot(gen_list, energy_intake_per_capita_list, label="Preys - Energy Intake", color="olive", linewidth=2)
+plt.xlabel("Generation")
+plt.ylabel("Energy per Organism")
+plt.title("Organism Energy Intake per Capita")
+plt.legend()
+plt.grid(True)
+plt.savefig("energy_intake_trends.png")
+plt.close()
+
+# Share of each color morph; with no morph_fitness the changes are drift
+morph_total = max((len(counts) for counts in morph_counts_list), default=0)
+if morph_total:
+    plt.figure(figsize=(10, 5))
+    for morph in range(morph_total):
+        shares = [
+            counts[morph] / max(sum(counts), 1) if morph < len(counts) else 0.0
+            for counts in morph_counts_list
+        ]
+        plt.plot(gen_list, shares, label=f"Morph {morph}", linewidth=2)
+    plt.xlabel("Generation")
+    plt.ylabel("Frequency")
+    plt.title("Morph Frequencies Over Generations")
+    plt.legend()
+    plt.grid(True)
+    plt.savefig("morph_frequencies.png")
+    plt.close()
+
+# Competing organism species, only in runs with [species_b]
+if any(species_list):
+    plt.figure(figsize=(10, 5))
+    for species, color in [(0, "green"), (1, "magenta")]:
+        counts = [
+            next((s["count"] for s in stats if s["species"] == species), 0)
+            for stats in species_list
+        ]
+        plt.plot(gen_list, counts, label=f"Species {'AB'[species]}", color=color, linewidth=2)
+    plt.xlabel("Generation")
+    plt.ylabel("Population")
+    plt.title("Organism Species Over Generations")
+    plt.legend()
+    plt.grid(True)
+    plt.savefig("species_counts.png")
+    plt.close()
+
+# Clustering of each population; 1 means randomly placed
+if spatial_gen_list:
+    plt.figure(figsize=(10, 5))
+    plt.plot(spatial_gen_list, organism_morisita_list, label="Preys", color="green", linewidth=2)
+    plt.plot(spatial_gen_list, predator_morisita_list, label="Predators", color="red", linewidth=2)
+    plt.axhline(1.0, color="gray", linestyle="--", linewidth=1)
+    plt.xlabel("Generation")
+    plt.ylabel("Morisita Index")
+    plt.title("Spatial Clustering Over Generations")
+    plt.legend()
+    plt.grid(True)
+    plt.savefig("spatial_clustering.png")
+    plt.close()
+
+# Organism exploration temperature
+plt.figure(figsize=(10, 5))
+plt.plot(gen_list, organism_avg_exploration_list, label="Preys - Exploration", color="purple", linewidth=2)
+plt.xlabel("Generation")
+plt.ylabel("Exploration Temperature")
+plt.title("Organism Exploration Over Generations")
+plt.legend()
+plt.grid(True)
+plt.savefig("exploration_trends.png")
+plt.close()
+
+# Organism parental investment
+plt.figure(figsize=(10, 5))
+plt.plot(gen_list, organism_avg_parental_investment_list, label="Preys - Parental Investment", color="teal", linewidth=2)
+plt.xlabel("Generation")
+plt.ylabel("Share of Energy Given to Offspring")
+plt.title("Organism Parental Investment Over Generations")
+plt.ylim(0, 1)
+plt.legend()
+plt.grid(True)
+plt.savefig("parental_investment_trends.png")
+plt.close()
+
 df = pd.DataFrame({
     "Generation": gen_list,
     "Organism Size": organism_avg_size_list,
@@ -245,6 +419,91 @@ df = pd.DataFrame({
     "Predator Energy": predator_avg_energy_list,
     "Organism Reproduction Threshold": organism_avg_reproduction_threshold_list,
     "Predator Hunting Efficiency": predator_avg_hunting_efficiency_list,
+    "Organism Boldness": organism_avg_boldness_list,
+    "Organism Exploration": organism_avg_exploration_list,
 })
 print("DataFrame head:\n", df.head())
+
+# Correlation matrix of the per-generation trait averages
+plt.figure(figsize=(10, 8))
+sns.heatmap(df.drop(columns=["Generation"]).corr(), annot=True, fmt=".2f", cmap="coolwarm", vmin=-1, vmax=1)
+plt.title("Trait Correlation Matrix")
+plt.tight_layout()
+plt.savefig("correlation_matrix.png")
+plt.close()
+
+# ---------------------------------------------------------------------------
+# 4. Biome change timeline from events.jsonl
+# ---------------------------------------------------------------------------
+if os.path.exists(EVENTS_FILE):
+    # generation -> cause -> number of converted tiles
+    biome_changes = defaultdict(lambda: defaultdict(int))
+    converted_area = defaultdict(int)
+
+    with open(EVENTS_FILE) as f:
+        for line in f:
+            if not line.strip():
+                continue
+            e = json.loads(line)
+            if e["event"] != "BiomeChanged":
+                continue
+            biome_changes[e["generation"]][e["cause"]] += 1
+            converted_area[e["cause"]] += 1
+
+    for cause, area in sorted(converted_area.items()):
+        print(f"Converted area ({cause}): {area} tiles")
+
+    if biome_changes:
+        df_changes = pd.DataFrame(biome_changes).T.fillna(0).sort_index().cumsum()
+        df_changes.plot(figsize=(10, 5), linewidth=2)
+        plt.xlabel("Generation")
+        plt.ylabel("Converted Tiles (cumulative)")
+        plt.title("Biome Change Timeline")
+        plt.legend(title="Cause")
+        plt.grid(True)
+        plt.savefig("biome_change_timeline.png")
+        plt.close()
+
+# ---------------------------------------------------------------------------
+# 5. Heritability estimates from heritability.jsonl
+# ---------------------------------------------------------------------------
+if os.path.exists(HERITABILITY_FILE):
+    # kind -> list of {generation, trait: slope}
+    heritability = defaultdict(list)
+    with open(HERITABILITY_FILE) as f:
+        for line in f:
+            if not line.strip():
+                continue
+            h = json.loads(line)
+            row = {"generation": h["generation"]}
+            row.update({k: (np.nan if v is None else v) for k, v in h["slopes"].items()})
+            heritability[h["kind"]].append(row)
+
+    for kind, rows in heritability.items():
+        df_h = pd.DataFrame(rows).set_index("generation")
+        if df_h.isna().all().all():
+            continue
+        df_h.plot(figsize=(10, 5), marker="o", linewidth=1)
+        plt.xlabel("Generation")
+        plt.ylabel("Offspring-on-parent slope")
+        plt.title(f"{kind} Trait Heritability")
+        plt.grid(True)
+        plt.savefig(f"heritability_{kind.lower()}.png")
+        plt.close()
+
+# ---------------------------------------------------------------------------
+# 6. Trait distributions from trait_distributions.csv (`evolution analyze`)
+# ---------------------------------------------------------------------------
+TRAIT_DISTRIBUTIONS_FILE = "trait_distributions.csv"
+if os.path.exists(TRAIT_DISTRIBUTIONS_FILE):
+    df_t = pd.read_csv(TRAIT_DISTRIBUTIONS_FILE)
+    for (kind, trait), group in df_t.groupby(["kind", "trait"]):
+        plt.figure(figsize=(10, 5))
+        sns.violinplot(data=group, x="window_start", y="value", color="lightblue", cut=0)
+        plt.xlabel("Generation")
+        plt.ylabel(trait)
+        plt.title(f"{kind} {trait} Distribution")
+        plt.grid(True)
+        plt.savefig(f"trait_distribution_{kind.lower()}_{trait}.png")
+        plt.close()
 print("Done. Plots saved.")
@@ -0,0 +1,57 @@
+//! A rule of its own joining the tick: a heat wave that costs every organism on a
+//! desert tile energy, run with the crate's metabolism so the organisms it drains
+//! are despawned by the simulation like any that starved.
+//!
+//! `cargo run --example custom_rule`
+
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use evolution::prelude::*;
+// the simulation's grid, not the ECS world of the same name in bevy's prelude
+use evolution::prelude::World;
+
+/// Energy an organism in the desert loses each tick.
+const HEAT_COST: f32 = 2.0;
+
+fn main() {
+    let config = Config::builder()
+        .width(40)
+        .height(40)
+        .seed(7)
+        .headless(true)
+        .generation_limit(Some(100))
+        .build()
+        .unwrap_or_else(|errors| {
+            for error in errors {
+                eprintln!("{}", error);
+            }
+            std::process::exit(1);
+        });
+
+    App::new()
+        .add_plugins((MinimalPlugins, StatesPlugin))
+        .add_plugins(EvolutionPlugin { config })
+        .add_systems(Update, heat_wave.in_set(SimulationSet::Metabolism))
+        .add_systems(Update, print_populations.after(SimulationSet::End))
+        .run();
+}
+
+fn heat_wave(world: Res<World>, mut organisms: Query<(&Position, &mut Organism), Active>) {
+    for (position, mut organism) in &mut organisms {
+        if world.grid[position.y * world.width + position.x].biome == Biome::Desert {
+            organism.energy -= HEAT_COST;
+        }
+    }
+}
+
+fn print_populations(population: Res<PopulationCount>, mut stats: EventReader<GenerationStats>) {
+    for stats in stats.read() {
+        println!(
+            "generation {:>4}: {:>4} organisms ({} when it started), {:>3} predators",
+            stats.generation,
+            stats.organism_count,
+            population.organisms,
+            stats.predator_count()
+        );
+    }
+}
@@ -0,0 +1,41 @@
+//! A Bevy app of its own running the simulation headless for 200 generations and
+//! printing the population of every generation as its stats come in.
+//!
+//! `cargo run --example embed`
+
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use evolution::prelude::*;
+
+fn main() {
+    let config = Config::builder()
+        .width(40)
+        .height(40)
+        .seed(7)
+        .headless(true)
+        .generation_limit(Some(200))
+        .build()
+        .unwrap_or_else(|errors| {
+            for error in errors {
+                eprintln!("{}", error);
+            }
+            std::process::exit(1);
+        });
+
+    App::new()
+        .add_plugins((MinimalPlugins, StatesPlugin))
+        .add_plugins(EvolutionPlugin { config })
+        .add_systems(Update, print_populations.after(SimulationSet::End))
+        .run();
+}
+
+fn print_populations(mut stats: EventReader<GenerationStats>) {
+    for stats in stats.read() {
+        println!(
+            "generation {:>4}: {:>4} organisms, {:>3} predators",
+            stats.generation,
+            stats.organism_count,
+            stats.predator_count()
+        );
+    }
+}
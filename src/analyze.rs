@@ -0,0 +1,1757 @@
+//! Offline analysis of the logs written by the logging plugin, run with
+//! `evolution analyze [log_dir] [--svg] [--emit-vega [--vega-max-generations N]
+//! [--vega-scripts DIR]]`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::utils::hashbrown::{HashMap, HashSet};
+
+use crate::components::{EntityKind, TraitSnapshot};
+use crate::events::{Born, Died, EventRecord, Survived};
+use crate::provenance::{mixed_runs, write_artifact, Provenance};
+use crate::resources::{PercentileRank, Season, PARENTAL_INVESTMENT_BOUNDS};
+use crate::schema::{
+    read_generation_stats, read_percentiles, read_samples, read_trace, Distribution, EnergyStats,
+    GenerationStats, PercentileRecord, SampleRecord, SchemaError, TraceRecord, TraceState,
+    PERCENTILES,
+};
+use crate::vega::{vega_html, VegaOptions};
+
+/// Fraction of a birth cohort still alive `age` generations after it was born.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurvivalPoint {
+    pub cohort: usize,
+    pub age: usize,
+    pub survivors: usize,
+    pub fraction: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CohortSummary {
+    pub cohort: usize,
+    pub size: usize,
+    /// Members still alive when the run ended; their lifespans are right-censored.
+    pub alive_at_end: usize,
+    /// `None` when more than half of the cohort outlived the end of the run.
+    pub median_lifespan: Option<usize>,
+}
+
+pub fn read_events(path: &Path) -> Result<Vec<EventRecord>, SchemaError> {
+    fs::read_to_string(path)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|source| SchemaError::Json {
+                line: i + 1,
+                source,
+            })
+        })
+        .collect()
+}
+
+/// Survivorship curves per birth cohort of `kind`. An individual survives to age `k`
+/// if it died more than `k` generations after its birth, or was still alive at
+/// `final_generation`; each cohort's curve stops at the age it reached when the run ended.
+pub fn cohort_survival(
+    births: &[Born],
+    deaths: &[Died],
+    final_generation: usize,
+    kind: EntityKind,
+) -> (Vec<SurvivalPoint>, Vec<CohortSummary>) {
+    let lifespans: HashMap<u64, usize> = deaths
+        .iter()
+        .filter(|death| death.kind == kind)
+        .map(|death| (death.id, death.age))
+        .collect();
+
+    // cohort -> lifespans of its members, `None` for the ones still alive
+    let mut cohorts: HashMap<usize, Vec<Option<usize>>> = HashMap::new();
+    for birth in births.iter().filter(|birth| birth.kind == kind) {
+        cohorts
+            .entry(birth.generation)
+            .or_default()
+            .push(lifespans.get(&birth.id).copied());
+    }
+
+    let mut cohort_ids: Vec<_> = cohorts.keys().copied().collect();
+    cohort_ids.sort_unstable();
+
+    let mut points = Vec::new();
+    let mut summaries = Vec::new();
+    for cohort in cohort_ids {
+        let members = &cohorts[&cohort];
+        let size = members.len();
+        let censor_age = final_generation.saturating_sub(cohort);
+        let mut median_lifespan = None;
+
+        for age in 0..=censor_age {
+            let survivors = members
+                .iter()
+                .filter(|lifespan| lifespan.is_none_or(|lifespan| lifespan > age))
+                .count();
+            if median_lifespan.is_none() && survivors * 2 <= size {
+                median_lifespan = Some(age);
+            }
+            points.push(SurvivalPoint {
+                cohort,
+                age,
+                survivors,
+                fraction: survivors as f32 / size as f32,
+            });
+        }
+
+        summaries.push(CohortSummary {
+            cohort,
+            size,
+            alive_at_end: members.iter().filter(|lifespan| lifespan.is_none()).count(),
+            median_lifespan,
+        });
+    }
+
+    (points, summaries)
+}
+
+fn survivorship_csv(points: &[SurvivalPoint]) -> String {
+    let mut csv = String::from("cohort,age,survivors,fraction\n");
+    for point in points {
+        writeln!(
+            csv,
+            "{},{},{},{}",
+            point.cohort, point.age, point.survivors, point.fraction
+        )
+        .unwrap();
+    }
+    csv
+}
+
+fn cohort_summary_csv(summaries: &[CohortSummary]) -> String {
+    let mut csv = String::from("cohort,size,alive_at_end,median_lifespan,censored\n");
+    for summary in summaries {
+        let median = summary
+            .median_lifespan
+            .map_or(String::new(), |median| median.to_string());
+        writeln!(
+            csv,
+            "{},{},{},{},{}",
+            summary.cohort,
+            summary.size,
+            summary.alive_at_end,
+            median,
+            summary.alive_at_end > 0
+        )
+        .unwrap();
+    }
+    csv
+}
+
+/// Age in generations an offspring must reach to count as having survived, when
+/// relating survival to how much its parent invested in it.
+pub const OFFSPRING_SURVIVAL_AGE: usize = 5;
+
+/// The parent's `parental_investment` for every organism offspring whose fate at
+/// `survival_age` is known, and whether it lived that long. Offspring still alive
+/// at `final_generation` but younger than `survival_age` are left out, as are those
+/// whose parent's traits were not logged.
+pub fn investment_outcomes(
+    births: &[Born],
+    deaths: &[Died],
+    final_generation: usize,
+    survival_age: usize,
+) -> Vec<(f32, bool)> {
+    let lifespans: HashMap<u64, usize> = deaths
+        .iter()
+        .filter(|death| death.kind == EntityKind::Organism)
+        .map(|death| (death.id, death.age))
+        .collect();
+
+    births
+        .iter()
+        .filter(|birth| birth.kind == EntityKind::Organism)
+        .filter_map(|birth| {
+            let investment = *birth.parent_traits.as_ref()?.get("parental_investment")?;
+            let survived = match lifespans.get(&birth.id) {
+                Some(&lifespan) => lifespan >= survival_age,
+                None if final_generation.saturating_sub(birth.generation) >= survival_age => true,
+                None => return None,
+            };
+            Some((investment, survived))
+        })
+        .collect()
+}
+
+/// Pearson correlation between a trait value and survival counted as 1 or 0. `None`
+/// with fewer than two outcomes or when either side never varies.
+pub fn survival_correlation(outcomes: &[(f32, bool)]) -> Option<f32> {
+    if outcomes.len() < 2 {
+        return None;
+    }
+    let n = outcomes.len() as f32;
+    let mean_investment = outcomes
+        .iter()
+        .map(|&(investment, _)| investment)
+        .sum::<f32>()
+        / n;
+    let mean_survival = outcomes.iter().filter(|&&(_, survived)| survived).count() as f32 / n;
+
+    let mut covariance = 0.0;
+    let mut investment_variance = 0.0;
+    let mut survival_variance = 0.0;
+    for &(investment, survived) in outcomes {
+        let di = investment - mean_investment;
+        let ds = survived as u8 as f32 - mean_survival;
+        covariance += di * ds;
+        investment_variance += di * di;
+        survival_variance += ds * ds;
+    }
+    if investment_variance <= f32::EPSILON || survival_variance <= f32::EPSILON {
+        return None;
+    }
+    Some(covariance / (investment_variance * survival_variance).sqrt())
+}
+
+/// Mean `altruism` of the members of every organism lineage, and whether any member
+/// was still alive when the run ended. Lineages whose members' traits were not logged
+/// are left out.
+pub fn lineage_altruism_outcomes(births: &[Born], deaths: &[Died]) -> Vec<(f32, bool)> {
+    let dead: HashSet<u64> = deaths
+        .iter()
+        .filter(|death| death.kind == EntityKind::Organism)
+        .map(|death| death.id)
+        .collect();
+
+    // lineage -> (altruism sum, members, survivors)
+    let mut lineages: BTreeMap<u64, (f32, usize, usize)> = BTreeMap::new();
+    for birth in births
+        .iter()
+        .filter(|birth| birth.kind == EntityKind::Organism)
+    {
+        let Some(&altruism) = birth.traits.get("altruism") else {
+            continue;
+        };
+        let lineage = lineages.entry(birth.lineage).or_default();
+        lineage.0 += altruism;
+        lineage.1 += 1;
+        lineage.2 += !dead.contains(&birth.id) as usize;
+    }
+    lineages
+        .into_values()
+        .map(|(altruism, members, survivors)| (altruism / members as f32, survivors > 0))
+        .collect()
+}
+
+/// What mutation did to an offspring, joined with how its life turned out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MutationEffect {
+    pub id: u64,
+    pub kind: EntityKind,
+    pub generation: usize,
+    /// Age at death, or the age reached by the end of the run when `alive_at_end`.
+    pub lifespan: usize,
+    pub alive_at_end: bool,
+    pub offspring: usize,
+    pub mutations: TraitSnapshot,
+}
+
+/// Every offspring whose mutations were logged, joined by id with its death and
+/// with the births it parented. Offspring still alive at `final_generation` get the
+/// age they had reached by then.
+pub fn mutation_effects(
+    births: &[Born],
+    deaths: &[Died],
+    final_generation: usize,
+) -> Vec<MutationEffect> {
+    let lifespans: HashMap<u64, usize> = deaths.iter().map(|death| (death.id, death.age)).collect();
+    let mut offspring: HashMap<u64, usize> = HashMap::new();
+    for parent in births.iter().filter_map(|birth| birth.parent) {
+        *offspring.entry(parent).or_default() += 1;
+    }
+
+    births
+        .iter()
+        .filter_map(|birth| {
+            let mutations = birth.mutations.clone()?;
+            let lifespan = lifespans.get(&birth.id).copied();
+            Some(MutationEffect {
+                id: birth.id,
+                kind: birth.kind,
+                generation: birth.generation,
+                lifespan: lifespan
+                    .unwrap_or_else(|| final_generation.saturating_sub(birth.generation)),
+                alive_at_end: lifespan.is_none(),
+                offspring: offspring.get(&birth.id).copied().unwrap_or(0),
+                mutations,
+            })
+        })
+        .collect()
+}
+
+/// One row per offspring with its lifespan, offspring count and the mutation of
+/// every trait, left blank for the traits of the other kind.
+fn mutation_effects_csv(effects: &[MutationEffect]) -> String {
+    let traits: BTreeSet<&str> = effects
+        .iter()
+        .flat_map(|effect| effect.mutations.keys().map(String::as_str))
+        .collect();
+
+    let mut csv = String::from("id,kind,generation,lifespan,alive_at_end,offspring");
+    for name in &traits {
+        write!(csv, ",delta_{}", name).unwrap();
+    }
+    csv.push('\n');
+    for effect in effects {
+        write!(
+            csv,
+            "{},{:?},{},{},{},{}",
+            effect.id,
+            effect.kind,
+            effect.generation,
+            effect.lifespan,
+            effect.alive_at_end,
+            effect.offspring
+        )
+        .unwrap();
+        for name in &traits {
+            match effect.mutations.get(*name) {
+                Some(delta) => write!(csv, ",{}", delta).unwrap(),
+                None => csv.push(','),
+            }
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+/// How many offspring an individual had over its life, from its death or, if it
+/// outlived the run, from its `Survived` record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReproductiveSuccess {
+    pub id: u64,
+    pub kind: EntityKind,
+    /// The generation it was born in.
+    pub cohort: usize,
+    /// `None` when its birth was not logged.
+    pub lineage: Option<u64>,
+    pub offspring: u32,
+    pub alive_at_end: bool,
+}
+
+/// Lifetime reproductive success of every individual that died or outlived the run,
+/// with the lineage its birth was logged with.
+pub fn reproductive_success(
+    births: &[Born],
+    deaths: &[Died],
+    survivors: &[Survived],
+) -> Vec<ReproductiveSuccess> {
+    let lineages: HashMap<u64, u64> = births
+        .iter()
+        .map(|birth| (birth.id, birth.lineage))
+        .collect();
+    let success = |id, kind, generation: usize, age, offspring, alive_at_end| ReproductiveSuccess {
+        id,
+        kind,
+        cohort: generation.saturating_sub(age),
+        lineage: lineages.get(&id).copied(),
+        offspring,
+        alive_at_end,
+    };
+
+    deaths
+        .iter()
+        .map(|death| {
+            success(
+                death.id,
+                death.kind,
+                death.generation,
+                death.age,
+                death.offspring,
+                false,
+            )
+        })
+        .chain(survivors.iter().map(|survivor| {
+            success(
+                survivor.id,
+                survivor.kind,
+                survivor.generation,
+                survivor.age,
+                survivor.offspring,
+                true,
+            )
+        }))
+        .collect()
+}
+
+/// Organisms that lived through one age and the offspring born to parents of that
+/// age.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FertilityPoint {
+    pub age: usize,
+    pub alive: usize,
+    pub births: usize,
+    /// Births per organism alive at the age.
+    pub fertility: f32,
+}
+
+/// Organism fertility at every age up to the oldest one reached, from the parent
+/// age of births and the ages organisms died at or had reached when the run ended.
+/// Births logged without a parent age are left out.
+pub fn fertility_by_age(
+    births: &[Born],
+    deaths: &[Died],
+    survivors: &[Survived],
+) -> Vec<FertilityPoint> {
+    let ages: Vec<usize> = deaths
+        .iter()
+        .filter(|death| death.kind == EntityKind::Organism)
+        .map(|death| death.age)
+        .chain(
+            survivors
+                .iter()
+                .filter(|survivor| survivor.kind == EntityKind::Organism)
+                .map(|survivor| survivor.age),
+        )
+        .collect();
+    let Some(&oldest) = ages.iter().max() else {
+        return Vec::new();
+    };
+
+    let mut alive = vec![0; oldest + 1];
+    for &age in &ages {
+        for count in &mut alive[..=age] {
+            *count += 1;
+        }
+    }
+    let mut born = vec![0; oldest + 1];
+    for birth in births
+        .iter()
+        .filter(|birth| birth.kind == EntityKind::Organism)
+    {
+        if let Some(count) = birth.parent_age.and_then(|age| born.get_mut(age)) {
+            *count += 1;
+        }
+    }
+
+    alive
+        .into_iter()
+        .zip(born)
+        .enumerate()
+        .map(|(age, (alive, births))| FertilityPoint {
+            age,
+            alive,
+            births,
+            fertility: births as f32 / alive.max(1) as f32,
+        })
+        .collect()
+}
+
+/// Lifetime reproductive success of the individuals of one kind sharing a cohort or
+/// a lineage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuccessSummary {
+    pub kind: EntityKind,
+    pub group: u64,
+    pub individuals: usize,
+    pub mean: f32,
+    pub distribution: Distribution,
+}
+
+/// The individuals grouped by kind and by `group`, which leaves out the ones it
+/// gives `None`, and how their offspring counts are spread.
+pub fn success_by(
+    records: &[ReproductiveSuccess],
+    group: impl Fn(&ReproductiveSuccess) -> Option<u64>,
+) -> Vec<SuccessSummary> {
+    let mut groups: BTreeMap<(EntityKind, u64), Vec<f32>> = BTreeMap::new();
+    for record in records {
+        let Some(key) = group(record) else {
+            continue;
+        };
+        groups
+            .entry((record.kind, key))
+            .or_default()
+            .push(record.offspring as f32);
+    }
+    groups
+        .into_iter()
+        .map(|((kind, group), mut offspring)| SuccessSummary {
+            kind,
+            group,
+            individuals: offspring.len(),
+            mean: offspring.iter().sum::<f32>() / offspring.len() as f32,
+            distribution: Distribution::of(&mut offspring),
+        })
+        .collect()
+}
+
+fn success_csv(group: &str, summaries: &[SuccessSummary]) -> String {
+    let mut csv = format!(
+        "kind,{},individuals,mean,min,lower_quartile,median,upper_quartile,max\n",
+        group
+    );
+    for summary in summaries {
+        let distribution = &summary.distribution;
+        writeln!(
+            csv,
+            "{:?},{},{},{},{},{},{},{},{}",
+            summary.kind,
+            summary.group,
+            summary.individuals,
+            summary.mean,
+            distribution.min,
+            distribution.lower_quartile,
+            distribution.median,
+            distribution.upper_quartile,
+            distribution.max
+        )
+        .unwrap();
+    }
+    csv
+}
+
+/// Offspring and survivors per tenth of parental investment.
+fn investment_survival_csv(outcomes: &[(f32, bool)]) -> String {
+    let (low, high) = PARENTAL_INVESTMENT_BOUNDS;
+    let bins = ((high - low) * 10.0).round() as usize;
+    let mut counts = vec![(0, 0); bins];
+    for &(investment, survived) in outcomes {
+        // the nudge keeps bin edges such as 0.2 from rounding into the bin below
+        let bin = (((investment - low) * 10.0 + 1e-4).floor().max(0.0) as usize).min(bins - 1);
+        counts[bin].0 += 1;
+        counts[bin].1 += survived as usize;
+    }
+
+    let mut csv = String::from("investment_from,investment_to,offspring,survivors,fraction\n");
+    for (bin, (offspring, survivors)) in counts.into_iter().enumerate() {
+        let from = low + bin as f32 / 10.0;
+        writeln!(
+            csv,
+            "{:.1},{:.1},{},{},{}",
+            from,
+            from + 0.1,
+            offspring,
+            survivors,
+            survivors as f32 / offspring.max(1) as f32
+        )
+        .unwrap();
+    }
+    csv
+}
+
+/// Count and share of every morph per generation, one row each, for following drift.
+fn morph_frequency_csv(stats: &[GenerationStats]) -> String {
+    let mut csv = String::from("generation,morph,count,frequency\n");
+    for record in stats {
+        let total: usize = record.morph_counts.iter().sum();
+        for (morph, &count) in record.morph_counts.iter().enumerate() {
+            writeln!(
+                csv,
+                "{},{},{},{}",
+                record.generation,
+                morph,
+                count,
+                count as f32 / total.max(1) as f32
+            )
+            .unwrap();
+        }
+    }
+    csv
+}
+
+/// Energy held and moved in every recorded generation, one row each.
+fn energy_flux_csv(stats: &[GenerationStats]) -> String {
+    let mut csv = String::from(
+        "generation,organisms,predators,food,food_regenerated,food_consumed,upkeep,removed_by_deaths,passive\n",
+    );
+    for record in stats {
+        let energy = &record.energy;
+        writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{},{}",
+            record.generation,
+            energy.organisms,
+            energy.predators,
+            energy.food,
+            energy.food_regenerated,
+            energy.food_consumed,
+            energy.upkeep,
+            energy.removed_by_deaths,
+            energy.passive
+        )
+        .unwrap();
+    }
+    csv
+}
+
+/// Births added up over a stretch of recorded generations in the same season.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeasonBirths {
+    pub first_generation: u32,
+    pub season: Season,
+    pub organism_births: usize,
+    pub predator_births: usize,
+}
+
+/// Births of the recorded generations added up per season, one entry for each run
+/// of consecutive records in the same season, so that breeding windows show up as
+/// pulses. Empty for runs without seasons.
+pub fn births_per_season(stats: &[GenerationStats]) -> Vec<SeasonBirths> {
+    let mut seasons: Vec<SeasonBirths> = Vec::new();
+    for record in stats {
+        let Some(season) = record.season else {
+            continue;
+        };
+        let predator_births = record
+            .predators
+            .as_ref()
+            .map_or(0, |predators| predators.predator_births);
+        match seasons.last_mut() {
+            Some(last) if last.season == season => {
+                last.organism_births += record.organism_births;
+                last.predator_births += predator_births;
+            }
+            _ => seasons.push(SeasonBirths {
+                first_generation: record.generation,
+                season,
+                organism_births: record.organism_births,
+                predator_births,
+            }),
+        }
+    }
+    seasons
+}
+
+fn births_per_season_csv(seasons: &[SeasonBirths]) -> String {
+    let mut csv = String::from("first_generation,season,organism_births,predator_births\n");
+    for stretch in seasons {
+        writeln!(
+            csv,
+            "{},{:?},{},{}",
+            stretch.first_generation,
+            stretch.season,
+            stretch.organism_births,
+            stretch.predator_births
+        )
+        .unwrap();
+    }
+    csv
+}
+
+/// Spans of generations the sampled trait values are grouped into.
+const TRAIT_WINDOWS: usize = 10;
+
+/// Sampled trait values in long form, one row per individual and trait, each tagged
+/// with the first generation of the one of `windows` equal spans it falls in, so
+/// every span of a trait can be drawn as one violin.
+fn trait_distribution_csv(samples: &[SampleRecord], windows: usize) -> String {
+    let last = samples.iter().map(|sample| sample.generation).max();
+    let span = last.unwrap_or(0) / windows.max(1) + 1;
+
+    let mut csv = String::from("window_start,generation,kind,pick,trait,value\n");
+    for sample in samples {
+        for (name, value) in &sample.traits {
+            writeln!(
+                csv,
+                "{},{},{:?},{:?},{},{}",
+                sample.generation / span * span,
+                sample.generation,
+                sample.kind,
+                sample.pick,
+                name,
+                value
+            )
+            .unwrap();
+        }
+    }
+    csv
+}
+
+/// The percentile tracks in long form, one row per individual and trait.
+fn percentile_tracks_csv(records: &[PercentileRecord]) -> String {
+    let mut csv = String::from("generation,kind,rank,percentile,id,trait,value\n");
+    for record in records {
+        let id = record.id.map(|id| id.to_string()).unwrap_or_default();
+        for (name, value) in &record.traits {
+            writeln!(
+                csv,
+                "{},{:?},{:?},{},{},{},{}",
+                record.generation, record.kind, record.rank, record.percentile, id, name, value
+            )
+            .unwrap();
+        }
+    }
+    csv
+}
+
+/// One panel per kind, ranking and trait, stacked, each with a line per percentile
+/// from the poorest in red to the richest in blue and its own value range.
+fn percentile_tracks_svg(records: &[PercentileRecord]) -> String {
+    const WIDTH: f32 = 800.0;
+    const PANEL: f32 = 120.0;
+    const GAP: f32 = 24.0;
+    const COLOURS: [&str; 5] = ["crimson", "orange", "gray", "steelblue", "navy"];
+
+    type Track = Vec<(usize, f32)>;
+    let mut panels: BTreeMap<(EntityKind, PercentileRank, &str), [Track; 5]> = BTreeMap::new();
+    for record in records {
+        let Some(track) = PERCENTILES.iter().position(|&p| p == record.percentile) else {
+            continue;
+        };
+        for (name, &value) in &record.traits {
+            let panel = panels
+                .entry((record.kind, record.rank, name.as_str()))
+                .or_default();
+            panel[track].push((record.generation, value));
+        }
+    }
+    let first = records.iter().map(|record| record.generation).min();
+    let last = records.iter().map(|record| record.generation).max();
+    let first = first.unwrap_or(0);
+    let span = last.unwrap_or(0).saturating_sub(first).max(1) as f32;
+
+    let height = panels.len() as f32 * (PANEL + GAP);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{height}\">\n"
+    );
+    for (row, ((kind, rank, name), tracks)) in panels.iter().enumerate() {
+        let top = row as f32 * (PANEL + GAP) + GAP;
+        let values = tracks.iter().flatten().map(|&(_, value)| value);
+        let low = values.clone().fold(f32::INFINITY, f32::min);
+        let high = values.fold(f32::NEG_INFINITY, f32::max);
+        let range = (high - low).max(f32::EPSILON);
+        writeln!(
+            svg,
+            "  <text x=\"0\" y=\"{:.1}\" font-size=\"12\">{:?} {} by {:?}, {:.3} to {:.3}</text>",
+            top - 6.0,
+            kind,
+            name,
+            rank,
+            low,
+            high
+        )
+        .unwrap();
+        for (colour, track) in COLOURS.iter().zip(tracks) {
+            let points: Vec<String> = track
+                .iter()
+                .map(|&(generation, value)| {
+                    format!(
+                        "{:.1},{:.1}",
+                        (generation - first) as f32 / span * WIDTH,
+                        top + (1.0 - (value - low) / range) * PANEL
+                    )
+                })
+                .collect();
+            writeln!(
+                svg,
+                "  <polyline fill=\"none\" stroke=\"{}\" points=\"{}\"/>",
+                colour,
+                points.join(" ")
+            )
+            .unwrap();
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn survivorship_svg(points: &[SurvivalPoint]) -> String {
+    const WIDTH: f32 = 800.0;
+    const HEIGHT: f32 = 400.0;
+    let max_age = points
+        .iter()
+        .map(|point| point.age)
+        .max()
+        .unwrap_or(0)
+        .max(1) as f32;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{HEIGHT}\">\n"
+    );
+    let mut start = 0;
+    while start < points.len() {
+        let cohort = points[start].cohort;
+        let end = points[start..]
+            .iter()
+            .position(|point| point.cohort != cohort)
+            .map_or(points.len(), |offset| start + offset);
+        let polyline: Vec<String> = points[start..end]
+            .iter()
+            .map(|point| {
+                format!(
+                    "{:.1},{:.1}",
+                    point.age as f32 / max_age * WIDTH,
+                    (1.0 - point.fraction) * HEIGHT
+                )
+            })
+            .collect();
+        writeln!(
+            svg,
+            "  <polyline fill=\"none\" stroke=\"green\" stroke-opacity=\"0.3\" points=\"{}\"/>",
+            polyline.join(" ")
+        )
+        .unwrap();
+        start = end;
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn fertility_csv(points: &[FertilityPoint]) -> String {
+    let mut csv = String::from("age,alive,births,fertility\n");
+    for point in points {
+        writeln!(
+            csv,
+            "{},{},{},{}",
+            point.age, point.alive, point.births, point.fertility
+        )
+        .unwrap();
+    }
+    csv
+}
+
+/// Fertility against age as one line, scaled to the most fertile age.
+fn fertility_svg(points: &[FertilityPoint]) -> String {
+    const WIDTH: f32 = 800.0;
+    const HEIGHT: f32 = 400.0;
+    let max_age = points.len().saturating_sub(1).max(1) as f32;
+    let max_fertility = points
+        .iter()
+        .map(|point| point.fertility)
+        .fold(0.0, f32::max)
+        .max(f32::EPSILON);
+
+    let polyline: Vec<String> = points
+        .iter()
+        .map(|point| {
+            format!(
+                "{:.1},{:.1}",
+                point.age as f32 / max_age * WIDTH,
+                (1.0 - point.fertility / max_fertility) * HEIGHT
+            )
+        })
+        .collect();
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{HEIGHT}\">\n"
+    );
+    writeln!(
+        svg,
+        "  <polyline fill=\"none\" stroke=\"green\" points=\"{}\"/>",
+        polyline.join(" ")
+    )
+    .unwrap();
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Reads one flux out of a generation's energy numbers.
+type Flux = fn(&EnergyStats) -> f32;
+
+/// The energy fluxes of every generation stacked on top of each other, from the
+/// bottom: food regrown, food eaten, upkeep, energy lost with the dead and the
+/// size of the net passive gain or loss.
+fn energy_flux_svg(stats: &[GenerationStats]) -> String {
+    const WIDTH: f32 = 800.0;
+    const HEIGHT: f32 = 400.0;
+    let layers: [(&str, Flux); 5] = [
+        ("green", |energy| energy.food_regenerated),
+        ("orange", |energy| energy.food_consumed),
+        ("steelblue", |energy| energy.upkeep),
+        ("crimson", |energy| energy.removed_by_deaths),
+        ("purple", |energy| energy.passive.abs()),
+    ];
+
+    // the running total below and on top of each layer, per generation
+    let mut bottoms = vec![0.0f32; stats.len()];
+    let mut tops = Vec::with_capacity(layers.len());
+    for (_, flux) in &layers {
+        let top: Vec<f32> = stats
+            .iter()
+            .zip(&bottoms)
+            .map(|(record, bottom)| bottom + flux(&record.energy).max(0.0))
+            .collect();
+        tops.push((bottoms, top.clone()));
+        bottoms = top;
+    }
+    let max_total = bottoms.iter().copied().fold(0.0, f32::max).max(1.0);
+    let first = stats.first().map_or(0, |record| record.generation);
+    let last = stats.last().map_or(0, |record| record.generation);
+    let span = last.saturating_sub(first).max(1) as f32;
+    let point = |record: &GenerationStats, value: f32| {
+        format!(
+            "{:.1},{:.1}",
+            (record.generation - first) as f32 / span * WIDTH,
+            (1.0 - value / max_total) * HEIGHT
+        )
+    };
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{HEIGHT}\">\n"
+    );
+    for ((colour, _), (bottom, top)) in layers.iter().zip(&tops) {
+        let upper = stats.iter().zip(top).map(|(record, &y)| point(record, y));
+        let lower = stats
+            .iter()
+            .zip(bottom)
+            .rev()
+            .map(|(record, &y)| point(record, y));
+        let points: Vec<String> = upper.chain(lower).collect();
+        writeln!(
+            svg,
+            "  <polygon fill=\"{}\" fill-opacity=\"0.6\" points=\"{}\"/>",
+            colour,
+            points.join(" ")
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Reads one population count out of a generation's stats.
+type Count = fn(&GenerationStats) -> usize;
+
+/// Organism and predator counts of every generation as two lines, drawn over bands
+/// shaded by season in runs with seasons, so that booms and busts line up with them.
+fn population_svg(stats: &[GenerationStats]) -> String {
+    const WIDTH: f32 = 800.0;
+    const HEIGHT: f32 = 400.0;
+    let lines: [(&str, Count); 2] = [
+        ("green", |record| record.organism_count),
+        ("crimson", GenerationStats::predator_count),
+    ];
+    // a prey-only run has no predator line to draw
+    let drawn = if stats.iter().any(|record| record.predators.is_some()) {
+        lines.len()
+    } else {
+        1
+    };
+
+    let max_count = stats
+        .iter()
+        .map(|record| record.organism_count.max(record.predator_count()))
+        .max()
+        .unwrap_or(0)
+        .max(1) as f32;
+    let first = stats.first().map_or(0, |record| record.generation);
+    let last = stats.last().map_or(0, |record| record.generation);
+    let span = last.saturating_sub(first).max(1) as f32;
+    let x = |generation: u32| (generation - first) as f32 / span * WIDTH;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{HEIGHT}\">\n"
+    );
+    let seasons = births_per_season(stats);
+    for (i, stretch) in seasons.iter().enumerate() {
+        let end = seasons
+            .get(i + 1)
+            .map_or(last, |next| next.first_generation);
+        let colour = match stretch.season {
+            Season::Spring => "palegreen",
+            Season::Summer => "khaki",
+            Season::Autumn => "burlywood",
+            Season::Winter => "lightsteelblue",
+        };
+        writeln!(
+            svg,
+            "  <rect x=\"{:.1}\" y=\"0\" width=\"{:.1}\" height=\"{HEIGHT}\" fill=\"{}\" fill-opacity=\"0.4\"/>",
+            x(stretch.first_generation),
+            x(end) - x(stretch.first_generation),
+            colour
+        )
+        .unwrap();
+    }
+    for (colour, count) in &lines[..drawn] {
+        let points: Vec<String> = stats
+            .iter()
+            .map(|record| {
+                format!(
+                    "{:.1},{:.1}",
+                    x(record.generation),
+                    (1.0 - count(record) as f32 / max_count) * HEIGHT
+                )
+            })
+            .collect();
+        writeln!(
+            svg,
+            "  <polyline fill=\"none\" stroke=\"{}\" points=\"{}\"/>",
+            colour,
+            points.join(" ")
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// `trace.jsonl` laid out for reading: a block per tick, and per move a table of
+/// the tiles weighed with the terms of their cost, the one taken marked with `>`.
+pub fn trace_text(records: &[TraceRecord]) -> String {
+    let mut text = String::new();
+    for record in records {
+        let state = match record.state {
+            TraceState::Foraging => "foraging",
+            TraceState::Panicked => "panicked",
+            TraceState::Dispersing => "dispersing",
+            TraceState::Grazing => "grazing",
+        };
+        writeln!(
+            text,
+            "generation {}: organism {} {} with {:.2} energy",
+            record.generation, record.id, state, record.energy
+        )
+        .unwrap();
+        for (i, step) in record.steps.iter().enumerate() {
+            writeln!(
+                text,
+                "  move {} from ({}, {}) at temperature {:.2}",
+                i + 1,
+                step.from.x,
+                step.from.y,
+                step.temperature
+            )
+            .unwrap();
+            writeln!(
+                text,
+                "      step     biome       terrain      fear      food     scent migration      flee     noise     total"
+            )
+            .unwrap();
+            for (j, candidate) in step.candidates.iter().enumerate() {
+                let marker = if j == step.chosen { '>' } else { ' ' };
+                // a term without weight can be -0, which would print as -0.00
+                let term = |value: f32| value + 0.0;
+                writeln!(
+                    text,
+                    "    {} {:>3},{:<3} {:<10}{:>9.2} {:>9.2} {:>9.2} {:>9.2} {:>9.2} {:>9.2} {:>9.2} {:>9.2}",
+                    marker,
+                    candidate.dx,
+                    candidate.dy,
+                    candidate.biome.to_string(),
+                    term(candidate.terrain),
+                    term(candidate.fear),
+                    term(candidate.food),
+                    term(candidate.kill_scent),
+                    term(candidate.migration),
+                    term(candidate.flee),
+                    term(candidate.noise),
+                    term(candidate.total)
+                )
+                .unwrap();
+            }
+        }
+    }
+    text
+}
+
+/// What `analyze` reads and writes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalyzeOptions {
+    pub log_dir: PathBuf,
+    pub svg: bool,
+    /// With `--trace`, print `trace.jsonl` instead of writing anything.
+    pub trace: bool,
+    /// With `--emit-vega`, the settings of `summary.html`.
+    pub vega: Option<VegaOptions>,
+}
+
+impl Default for AnalyzeOptions {
+    fn default() -> Self {
+        Self {
+            log_dir: ".".into(),
+            svg: false,
+            trace: false,
+            vega: None,
+        }
+    }
+}
+
+impl AnalyzeOptions {
+    /// Parses the arguments following `analyze`.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut vega = VegaOptions::default();
+        let mut emit_vega = false;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--svg" => options.svg = true,
+                "--trace" => options.trace = true,
+                "--emit-vega" => emit_vega = true,
+                "--vega-max-generations" => {
+                    let value = args
+                        .next()
+                        .ok_or("missing value for --vega-max-generations")?;
+                    vega.max_generations = value
+                        .parse()
+                        .ok()
+                        .filter(|max: &usize| *max > 0)
+                        .ok_or_else(|| {
+                            format!(
+                                "--vega-max-generations must be a positive integer, got `{}`",
+                                value
+                            )
+                        })?;
+                }
+                "--vega-scripts" => {
+                    let value = args.next().ok_or("missing value for --vega-scripts")?;
+                    vega.scripts = Some(value.into());
+                }
+                other if other.starts_with("--") => {
+                    return Err(format!("unexpected argument `{}`", other))
+                }
+                log_dir => options.log_dir = log_dir.into(),
+            }
+        }
+        options.vega = emit_vega.then_some(vega);
+        Ok(options)
+    }
+}
+
+/// Logs of a run `analyze` reads, whose provenance its outputs take on.
+const INPUTS: [&str; 5] = [
+    "summary_data.jsonl",
+    "events.jsonl",
+    "samples.jsonl",
+    "percentile_individuals.jsonl",
+    "report.json",
+];
+
+/// The provenance of the logs in `log_dir` at the latest generation any of them
+/// reached, warning when they come from different runs. `None` for logs written
+/// before runs kept their provenance.
+pub fn logs_provenance(log_dir: &Path) -> Option<Provenance> {
+    let found: Vec<(String, Provenance)> = INPUTS
+        .iter()
+        .filter_map(|name| match Provenance::read(&log_dir.join(name)) {
+            Ok(provenance) => provenance.map(|provenance| (name.to_string(), provenance)),
+            Err(err) => {
+                eprintln!("Cannot read the provenance of {}: {}", name, err);
+                None
+            }
+        })
+        .collect();
+    if let Some(warning) = mixed_runs(&found) {
+        eprintln!("Warning: {}", warning);
+    }
+    found
+        .into_iter()
+        .map(|(_, provenance)| provenance)
+        .max_by_key(|provenance| provenance.generation)
+}
+
+/// Writes the outputs of `analyze` into the log directory, with the provenance of the
+/// logs they were made from.
+struct Outputs<'a> {
+    dir: &'a Path,
+    provenance: Option<Provenance>,
+}
+
+impl Outputs<'_> {
+    fn write(&self, name: &str, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        let path = self.dir.join(name);
+        match &self.provenance {
+            Some(provenance) => write_artifact(&path, contents, provenance),
+            None => fs::write(path, contents),
+        }
+    }
+}
+
+pub fn run(options: &AnalyzeOptions) -> Result<(), Box<dyn Error>> {
+    let (log_dir, svg) = (options.log_dir.as_path(), options.svg);
+    if options.trace {
+        print!("{}", trace_text(&read_trace(&log_dir.join("trace.jsonl"))?));
+        return Ok(());
+    }
+    let outputs = Outputs {
+        dir: log_dir,
+        provenance: logs_provenance(log_dir),
+    };
+    // samples and percentile tracks are written without `log_data`, so they may be
+    // all there is
+    let samples_path = log_dir.join("samples.jsonl");
+    let percentiles_path = log_dir.join("percentile_individuals.jsonl");
+    let events_path = log_dir.join("events.jsonl");
+    if samples_path.exists() {
+        let samples = read_samples(&samples_path)?;
+        outputs.write(
+            "trait_distributions.csv",
+            trait_distribution_csv(&samples, TRAIT_WINDOWS),
+        )?;
+        println!("Wrote trait distributions of {} samples", samples.len());
+    }
+    if percentiles_path.exists() {
+        let records = read_percentiles(&percentiles_path)?;
+        outputs.write("percentile_tracks.csv", percentile_tracks_csv(&records))?;
+        if svg {
+            outputs.write("percentile_tracks.svg", percentile_tracks_svg(&records))?;
+        }
+        println!("Wrote {} percentile track records", records.len());
+    }
+    if (samples_path.exists() || percentiles_path.exists()) && !events_path.exists() {
+        return Ok(());
+    }
+
+    let events = read_events(&events_path)?;
+
+    let mut births = Vec::new();
+    let mut deaths = Vec::new();
+    let mut survivors = Vec::new();
+    let mut final_generation = 0;
+    for event in events {
+        match event {
+            EventRecord::Born(born) => {
+                final_generation = final_generation.max(born.generation);
+                births.push(born);
+            }
+            EventRecord::Died(died) => {
+                final_generation = final_generation.max(died.generation);
+                deaths.push(died);
+            }
+            EventRecord::Survived(survived) => {
+                final_generation = final_generation.max(survived.generation);
+                survivors.push(survived);
+            }
+            _ => {}
+        }
+    }
+
+    let summary_path = log_dir.join("summary_data.jsonl");
+    if summary_path.exists() {
+        let stats = read_generation_stats(&summary_path)?;
+        if let Some(last) = stats.last() {
+            final_generation = final_generation.max(last.generation as usize);
+        }
+        outputs.write("morph_frequencies.csv", morph_frequency_csv(&stats))?;
+        outputs.write("energy_flux.csv", energy_flux_csv(&stats))?;
+        if let Some(vega) = &options.vega {
+            outputs.write("summary.html", vega_html(&stats, vega)?)?;
+            println!(
+                "Wrote charts of {} generations to summary.html",
+                stats.len().min(vega.max_generations)
+            );
+        }
+        if svg {
+            outputs.write("energy_flux.svg", energy_flux_svg(&stats))?;
+            outputs.write("population.svg", population_svg(&stats))?;
+        }
+        let seasons = births_per_season(&stats);
+        if !seasons.is_empty() {
+            outputs.write("births_per_season.csv", births_per_season_csv(&seasons))?;
+            println!("Wrote births of {} seasons", seasons.len());
+        }
+    } else if options.vega.is_some() {
+        eprintln!("No summary_data.jsonl to chart, so no summary.html");
+    }
+
+    let (points, summaries) =
+        cohort_survival(&births, &deaths, final_generation, EntityKind::Organism);
+    outputs.write("survivorship.csv", survivorship_csv(&points))?;
+    outputs.write("cohort_lifespans.csv", cohort_summary_csv(&summaries))?;
+    if svg {
+        outputs.write("survivorship.svg", survivorship_svg(&points))?;
+    }
+
+    let outcomes = investment_outcomes(&births, &deaths, final_generation, OFFSPRING_SURVIVAL_AGE);
+    if !outcomes.is_empty() {
+        outputs.write(
+            "parental_investment.csv",
+            investment_survival_csv(&outcomes),
+        )?;
+        match survival_correlation(&outcomes) {
+            Some(r) => println!(
+                "Parental investment vs offspring survival to age {}: r = {:.3} over {} offspring",
+                OFFSPRING_SURVIVAL_AGE,
+                r,
+                outcomes.len()
+            ),
+            None => println!(
+                "Parental investment vs offspring survival: no variation across {} offspring",
+                outcomes.len()
+            ),
+        }
+    }
+
+    let lineages = lineage_altruism_outcomes(&births, &deaths);
+    if !lineages.is_empty() {
+        match survival_correlation(&lineages) {
+            Some(r) => println!(
+                "Lineage altruism vs lineage survival: r = {:.3} over {} lineages",
+                r,
+                lineages.len()
+            ),
+            None => println!(
+                "Lineage altruism vs lineage survival: no variation across {} lineages",
+                lineages.len()
+            ),
+        }
+    }
+
+    let effects = mutation_effects(&births, &deaths, final_generation);
+    if !effects.is_empty() {
+        outputs.write("mutation_effects.csv", mutation_effects_csv(&effects))?;
+        println!("Wrote mutation effects of {} offspring", effects.len());
+    }
+
+    let success = reproductive_success(&births, &deaths, &survivors);
+    if !success.is_empty() {
+        let cohorts = success_by(&success, |record| Some(record.cohort as u64));
+        outputs.write(
+            "reproductive_success_cohorts.csv",
+            success_csv("cohort", &cohorts),
+        )?;
+        let lineages = success_by(&success, |record| record.lineage);
+        outputs.write(
+            "reproductive_success_lineages.csv",
+            success_csv("lineage", &lineages),
+        )?;
+        println!(
+            "Wrote lifetime reproductive success of {} individuals",
+            success.len()
+        );
+    }
+
+    let fertility = fertility_by_age(&births, &deaths, &survivors);
+    if fertility.iter().any(|point| point.births > 0) {
+        outputs.write("fertility_by_age.csv", fertility_csv(&fertility))?;
+        if svg {
+            outputs.write("fertility_by_age.svg", fertility_svg(&fertility))?;
+        }
+        println!("Wrote fertility of {} ages", fertility.len());
+    }
+
+    println!(
+        "Wrote survivorship for {} cohorts up to generation {}",
+        summaries.len(),
+        final_generation
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::Position;
+    use crate::resources::Biome;
+    use crate::schema::{PredatorStats, TraceCandidate, TraceStep, SCHEMA_VERSION};
+
+    const EVENTS: &str = include_str!("../tests/fixtures/events_lifespans.jsonl");
+
+    fn fixture() -> (Vec<Born>, Vec<Died>) {
+        let mut births = Vec::new();
+        let mut deaths = Vec::new();
+        for line in EVENTS.lines() {
+            match serde_json::from_str(line).unwrap() {
+                EventRecord::Born(born) => births.push(born),
+                EventRecord::Died(died) => deaths.push(died),
+                _ => {}
+            }
+        }
+        (births, deaths)
+    }
+
+    #[test]
+    fn options_parse_the_log_dir_and_vega_settings() {
+        let args =
+            |line: &str| -> Vec<String> { line.split_whitespace().map(String::from).collect() };
+        assert_eq!(
+            AnalyzeOptions::from_args(&[]),
+            Ok(AnalyzeOptions::default())
+        );
+
+        let options =
+            AnalyzeOptions::from_args(&args("--vega-max-generations 200 logs --emit-vega"))
+                .unwrap();
+        assert_eq!(options.log_dir, PathBuf::from("logs"));
+        assert!(!options.svg);
+        assert_eq!(
+            options.vega,
+            Some(VegaOptions {
+                max_generations: 200,
+                scripts: None
+            })
+        );
+        // vega settings alone do not ask for the page
+        let options = AnalyzeOptions::from_args(&args("--svg --vega-scripts js")).unwrap();
+        assert!(options.svg && options.vega.is_none());
+        let options = AnalyzeOptions::from_args(&args("logs --trace")).unwrap();
+        assert!(options.trace && !options.svg);
+
+        assert!(AnalyzeOptions::from_args(&args("--emit-vega --vega-max-generations 0")).is_err());
+        assert!(AnalyzeOptions::from_args(&args("--vega-scripts")).is_err());
+        assert!(AnalyzeOptions::from_args(&args("--pdf")).is_err());
+    }
+
+    #[test]
+    fn traces_mark_the_direction_taken() {
+        let candidate = |dx, total| TraceCandidate {
+            dx,
+            dy: 0,
+            biome: Biome::Grassland,
+            base_cost: 10.0,
+            tolerance: 1.0,
+            terrain: 10.0,
+            fear: 0.0,
+            food: total - 10.0,
+            kill_scent: 0.0,
+            migration: 0.0,
+            flee: 0.0,
+            noise: 0.0,
+            total,
+        };
+        let record = TraceRecord {
+            schema_version: SCHEMA_VERSION,
+            generation: 4,
+            id: 7,
+            state: TraceState::Foraging,
+            energy: 12.5,
+            steps: vec![TraceStep {
+                from: Position { x: 3, y: 2 },
+                temperature: 0.5,
+                candidates: vec![candidate(-1, 9.0), candidate(1, 6.25)],
+                chosen: 1,
+            }],
+        };
+        let text = trace_text(&[record]);
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(
+            lines[0],
+            "generation 4: organism 7 foraging with 12.50 energy"
+        );
+        assert_eq!(lines[1], "  move 1 from (3, 2) at temperature 0.50");
+        assert!(lines[3].starts_with("       -1,0   Grassland"));
+        assert!(lines[4].starts_with("    >   1,0   Grassland"));
+        assert!(lines[4].ends_with("-3.75      0.00      0.00      0.00      0.00      6.25"));
+    }
+
+    #[test]
+    fn survival_curve_matches_known_lifespans() {
+        let (births, deaths) = fixture();
+        let (points, summaries) = cohort_survival(&births, &deaths, 6, EntityKind::Organism);
+
+        // cohort 0: lifespans 1, 2 and 4 (the predator is ignored)
+        let cohort_zero: Vec<_> = points
+            .iter()
+            .filter(|point| point.cohort == 0)
+            .map(|point| point.survivors)
+            .collect();
+        assert_eq!(cohort_zero, vec![3, 2, 1, 1, 0, 0, 0]);
+        assert_eq!(summaries[0].size, 3);
+        assert_eq!(summaries[0].median_lifespan, Some(2));
+        assert_eq!(summaries[0].alive_at_end, 0);
+    }
+
+    #[test]
+    fn morph_frequencies_are_shares_of_each_generation() {
+        let stats = [
+            GenerationStats {
+                generation: 1,
+                morph_counts: vec![3, 1],
+                ..Default::default()
+            },
+            GenerationStats {
+                generation: 2,
+                morph_counts: vec![0, 0],
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            morph_frequency_csv(&stats),
+            "generation,morph,count,frequency\n1,0,3,0.75\n1,1,1,0.25\n2,0,0,0\n2,1,0,0\n"
+        );
+    }
+
+    #[test]
+    fn births_add_up_over_each_season() {
+        let record = |generation, season, organism_births| GenerationStats {
+            generation,
+            season,
+            organism_births,
+            predators: Some(PredatorStats {
+                predator_count: 1,
+                predator_births: 1,
+                ..Default::default()
+            }),
+            organism_count: 10,
+            ..Default::default()
+        };
+        let stats = [
+            record(0, Some(Season::Spring), 4),
+            record(1, Some(Season::Spring), 6),
+            record(2, Some(Season::Summer), 0),
+            record(3, Some(Season::Spring), 2),
+        ];
+        assert_eq!(
+            births_per_season_csv(&births_per_season(&stats)),
+            "first_generation,season,organism_births,predator_births\n\
+             0,Spring,10,2\n2,Summer,0,1\n3,Spring,2,1\n"
+        );
+        assert!(births_per_season(&[record(0, None, 4)]).is_empty());
+        assert_eq!(population_svg(&stats).matches("<rect").count(), 3);
+    }
+
+    #[test]
+    fn trait_samples_are_grouped_into_generation_windows() {
+        use crate::components::Position;
+        use crate::schema::{SamplePick, SCHEMA_VERSION};
+
+        let sample = |generation, size| SampleRecord {
+            schema_version: SCHEMA_VERSION,
+            generation,
+            kind: EntityKind::Organism,
+            pick: SamplePick::Random,
+            id: Some(generation as u64),
+            position: Position { x: 0, y: 0 },
+            energy: 1.0,
+            traits: [("size".to_string(), size), ("speed".to_string(), 1.0)].into(),
+        };
+        let samples = [sample(0, 1.5), sample(9, 2.0), sample(19, 2.5)];
+        assert_eq!(
+            trait_distribution_csv(&samples, 2),
+            "window_start,generation,kind,pick,trait,value\n\
+             0,0,Organism,Random,size,1.5\n0,0,Organism,Random,speed,1\n\
+             0,9,Organism,Random,size,2\n0,9,Organism,Random,speed,1\n\
+             10,19,Organism,Random,size,2.5\n10,19,Organism,Random,speed,1\n"
+        );
+    }
+
+    #[test]
+    fn percentile_tracks_follow_each_percentile() {
+        use crate::components::Position;
+        use crate::schema::SCHEMA_VERSION;
+
+        let record = |generation, percentile, size| PercentileRecord {
+            schema_version: SCHEMA_VERSION,
+            generation,
+            kind: EntityKind::Predator,
+            rank: PercentileRank::Energy,
+            percentile,
+            id: (percentile == 50).then_some(7),
+            position: Position { x: 0, y: 0 },
+            energy: 1.0,
+            size,
+            traits: [("size".to_string(), size)].into(),
+        };
+        let records: Vec<_> = [0, 10]
+            .into_iter()
+            .flat_map(|generation| PERCENTILES.map(|p| record(generation, p, p as f32)))
+            .collect();
+        let csv = percentile_tracks_csv(&records);
+        assert_eq!(csv.lines().count(), 11);
+        assert_eq!(
+            csv.lines().nth(3).unwrap(),
+            "0,Predator,Energy,50,7,size,50"
+        );
+        assert_eq!(csv.lines().nth(2).unwrap(), "0,Predator,Energy,25,,size,25");
+
+        let svg = percentile_tracks_svg(&records);
+        assert_eq!(svg.matches("<polyline").count(), 5);
+        assert!(svg.contains("Predator size by Energy"));
+    }
+
+    fn birth(id: u64, generation: usize, investment: f32) -> Born {
+        Born {
+            id,
+            parent: Some(0),
+            lineage: 0,
+            kind: EntityKind::Organism,
+            generation,
+            traits: Default::default(),
+            parent_traits: Some([("parental_investment".to_string(), investment)].into()),
+            mutations: None,
+            parent_age: None,
+        }
+    }
+
+    fn death(id: u64, generation: usize, age: usize) -> Died {
+        Died {
+            id,
+            kind: EntityKind::Organism,
+            generation,
+            age,
+            offspring: 0,
+            traits: Default::default(),
+        }
+    }
+
+    #[test]
+    fn investment_correlates_with_offspring_survival() {
+        // generous parents' offspring outlive the survival age, stingy ones' do not
+        let births = [
+            birth(1, 0, 0.9),
+            birth(2, 0, 0.8),
+            birth(3, 0, 0.2),
+            birth(4, 0, 0.1),
+            birth(5, 18, 0.1),
+        ];
+        let deaths = [death(2, 10, 10), death(3, 2, 2), death(4, 1, 1)];
+        let outcomes = investment_outcomes(&births, &deaths, 20, 5);
+
+        // offspring 5 is too young to judge
+        assert_eq!(
+            outcomes,
+            vec![(0.9, true), (0.8, true), (0.2, false), (0.1, false)]
+        );
+        let r = survival_correlation(&outcomes).unwrap();
+        assert!(r > 0.9, "{}", r);
+        assert_eq!(survival_correlation(&outcomes[..2]), None);
+
+        let csv = investment_survival_csv(&outcomes);
+        assert!(csv.contains("\n0.1,0.2,1,0,0\n"), "{}", csv);
+        assert!(csv.ends_with("0.8,0.9,2,2,1\n"), "{}", csv);
+    }
+
+    #[test]
+    fn survivors_at_end_are_censored_not_dead() {
+        let (births, deaths) = fixture();
+        let (points, summaries) = cohort_survival(&births, &deaths, 6, EntityKind::Organism);
+
+        // cohort 3: one member died at age 1, two were still alive at generation 6
+        let cohort_three: Vec<_> = points
+            .iter()
+            .filter(|point| point.cohort == 3)
+            .map(|point| (point.age, point.survivors))
+            .collect();
+        assert_eq!(cohort_three, vec![(0, 3), (1, 2), (2, 2), (3, 2)]);
+        assert_eq!(summaries[1].alive_at_end, 2);
+        assert_eq!(summaries[1].median_lifespan, None);
+    }
+
+    #[test]
+    fn lineage_altruism_is_paired_with_lineage_survival() {
+        let born = |id, lineage, altruism| Born {
+            lineage,
+            traits: [("altruism".to_string(), altruism)].into(),
+            ..birth(id, 0, 0.5)
+        };
+        // lineage 1 averages 0.8 and has a survivor, lineage 2 averages 0.2 and died out
+        let births = [
+            born(1, 1, 0.7),
+            born(2, 1, 0.9),
+            born(3, 2, 0.1),
+            born(4, 2, 0.3),
+        ];
+        let deaths = [death(1, 4, 4), death(3, 2, 2), death(4, 5, 5)];
+        let outcomes = lineage_altruism_outcomes(&births, &deaths);
+        assert_eq!(outcomes.len(), 2);
+        assert!((outcomes[0].0 - 0.8).abs() < 1e-6 && outcomes[0].1);
+        assert!((outcomes[1].0 - 0.2).abs() < 1e-6 && !outcomes[1].1);
+        let r = survival_correlation(&outcomes).unwrap();
+        assert!((r - 1.0).abs() < 1e-5, "{}", r);
+    }
+
+    #[test]
+    fn mutations_are_joined_with_lifespan_and_offspring() {
+        let mut births = Vec::new();
+        let mut deaths = Vec::new();
+        for line in include_str!("../tests/fixtures/events_mutations.jsonl").lines() {
+            match serde_json::from_str(line).unwrap() {
+                EventRecord::Born(born) => births.push(born),
+                EventRecord::Died(died) => deaths.push(died),
+                _ => {}
+            }
+        }
+
+        let effects = mutation_effects(&births, &deaths, 6);
+        let outcomes: Vec<_> = effects
+            .iter()
+            .map(|effect| {
+                (
+                    effect.id,
+                    effect.lifespan,
+                    effect.alive_at_end,
+                    effect.offspring,
+                )
+            })
+            .collect();
+        // 2 died at 5 after parenting 4, 3 died at 1, and 4 was still alive at 2
+        assert_eq!(
+            outcomes,
+            vec![(2, 5, false, 1), (3, 1, false, 0), (4, 2, true, 0)]
+        );
+        assert_eq!(
+            mutation_effects_csv(&effects),
+            "id,kind,generation,lifespan,alive_at_end,offspring,delta_size,delta_speed\n\
+             2,Organism,1,5,false,1,0.1,-0.2\n\
+             3,Organism,2,1,false,0,-0.1,0\n\
+             4,Organism,4,2,true,0,0,0.3\n"
+        );
+    }
+
+    #[test]
+    fn reproductive_success_is_spread_per_cohort_and_lineage() {
+        let born = |id, lineage, generation| Born {
+            lineage,
+            ..birth(id, generation, 0.5)
+        };
+        let births = [born(1, 1, 0), born(2, 1, 0), born(3, 3, 0), born(4, 1, 2)];
+        let died = |id, generation, age, offspring| Died {
+            offspring,
+            ..death(id, generation, age)
+        };
+        // 5 was born before the log started, so its lineage is unknown
+        let deaths = [died(1, 4, 4, 3), died(3, 2, 2, 0), died(5, 6, 6, 2)];
+        let survivors = [Survived {
+            id: 2,
+            kind: EntityKind::Organism,
+            generation: 6,
+            age: 6,
+            offspring: 1,
+            traits: Default::default(),
+        }];
+        let success = reproductive_success(&births, &deaths, &survivors);
+        assert_eq!(success.len(), 4);
+        assert_eq!(success[2].lineage, None);
+        assert!(success[3].alive_at_end);
+
+        let cohorts = success_by(&success, |record| Some(record.cohort as u64));
+        assert_eq!(
+            success_csv("cohort", &cohorts),
+            "kind,cohort,individuals,mean,min,lower_quartile,median,upper_quartile,max\n\
+             Organism,0,4,1.5,0,0,1,2,3\n"
+        );
+        let lineages = success_by(&success, |record| record.lineage);
+        let per_lineage: Vec<_> = lineages
+            .iter()
+            .map(|summary| (summary.group, summary.individuals, summary.distribution.max))
+            .collect();
+        assert_eq!(per_lineage, vec![(1, 2, 3.0), (3, 1, 0.0)]);
+        assert_eq!(lineages[0].mean, 2.0);
+    }
+
+    #[test]
+    fn fertility_is_births_over_the_organisms_alive_at_each_age() {
+        let born = |id, parent_age| Born {
+            parent_age,
+            ..birth(id, 0, 0.5)
+        };
+        // two born to parents of age 1 and one to a parent of age 2; a founder's
+        // birth has no parent age
+        let births = [
+            born(10, Some(1)),
+            born(11, Some(1)),
+            born(12, Some(2)),
+            born(13, None),
+        ];
+        let deaths = [death(1, 1, 1), death(2, 3, 3)];
+        let survivors = [Survived {
+            id: 3,
+            kind: EntityKind::Organism,
+            generation: 2,
+            age: 2,
+            offspring: 0,
+            traits: Default::default(),
+        }];
+        let points = fertility_by_age(&births, &deaths, &survivors);
+        let rows: Vec<_> = points
+            .iter()
+            .map(|point| (point.age, point.alive, point.births))
+            .collect();
+        assert_eq!(rows, vec![(0, 3, 0), (1, 3, 2), (2, 2, 1), (3, 1, 0)]);
+        assert_eq!(points[2].fertility, 0.5);
+        assert!(fertility_csv(&points).starts_with("age,alive,births,fertility\n0,3,0,0\n"));
+        assert!(fertility_svg(&points).contains("<polyline"));
+    }
+}
@@ -0,0 +1,172 @@
+use bevy::ecs::world::World as Ecs;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::utils::tracing::dispatcher;
+use rand::prelude::*;
+
+use crate::config::{ConfigError, DensityAdjustment};
+use crate::plugins::watchdog::recent_logs_layer;
+#[cfg(feature = "gui")]
+use crate::plugins::RenderingPlugin;
+use crate::plugins::{LoggingPlugin, SimulationPlugin, WatchdogPlugin};
+use crate::provenance::RunId;
+use crate::resources::{
+    ActiveTiles, AppState, CarrionGrid, ClampTally, ClampWarnings, Config, DeathTally,
+    DesertificationPressure, EnergyFlux, EnergyIntake, FoodPatches, Generation, KillScentGrid,
+    MovementTrace, NewbornSurvival, NextId, PopulationCount, PredatorSpatialIndex, ResidenceTally,
+    RngStreams, SpatialIndex, StatsHistory, TerritoryMap, TickClock, World,
+};
+use crate::seed_bank::SeedBank;
+
+/// Builds the simulation app for `config` without running it, so callers can either
+/// `run()` it or drive it step by step with `update()`.
+///
+/// Panics when the initial populations break the `error` density policy; see
+/// [`try_build_app`].
+pub fn build_app(config: Config) -> App {
+    try_build_app(config).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// [`build_app`], failing instead when the initial populations are denser than
+/// `max_initial_density` allows under the `error` density policy. Under `cap` the
+/// populations shrink to fit, with a warning and a
+/// [`DensityAdjustment`](crate::config::DensityAdjustment) resource. Also fails for
+/// `headless = false` in a build without the `gui` feature, and for a world with no
+/// tiles.
+///
+/// A headless app logs nowhere until a subscriber is set, as with [`init_logging`];
+/// the GUI sets one up with [`log_plugin`] unless there already is one.
+pub fn try_build_app(config: Config) -> Result<App, ConfigError> {
+    let mut app = App::new();
+
+    if config.world.headless {
+        app.add_plugins((MinimalPlugins, StatesPlugin));
+    } else {
+        #[cfg(feature = "gui")]
+        if dispatcher::has_been_set() {
+            app.add_plugins(DefaultPlugins.build().disable::<LogPlugin>());
+        } else {
+            app.add_plugins(DefaultPlugins.set(log_plugin(&config)));
+        }
+    }
+
+    add_simulation(&mut app, config)?;
+    Ok(app)
+}
+
+/// A [`LogPlugin`] showing messages at the config's
+/// [`verbosity`](crate::resources::WorldConfig::verbosity) and above, or those
+/// `RUST_LOG` picks when it is set. The last lines are kept for the watchdog's
+/// diagnostics.
+pub fn log_plugin(config: &Config) -> LogPlugin {
+    LogPlugin {
+        level: config.world.verbosity().into(),
+        custom_layer: recent_logs_layer,
+        ..default()
+    }
+}
+
+/// Sends the logs of headless runs to stderr as [`log_plugin`] does for the GUI,
+/// since `MinimalPlugins` leave logging out. Does nothing once a subscriber is set,
+/// so the first config logging is set up for decides the level of the process.
+pub fn init_logging(config: &Config) {
+    if !dispatcher::has_been_set() {
+        log_plugin(config).build(&mut App::new());
+    }
+}
+
+/// The simulation for an app that brings its own Bevy plugins: `MinimalPlugins` and
+/// `StatesPlugin` to run it headless, `DefaultPlugins` to draw it.
+///
+/// Panics when the initial populations break the `error` density policy, like
+/// [`build_app`].
+pub struct EvolutionPlugin {
+    pub config: Config,
+}
+
+impl Plugin for EvolutionPlugin {
+    fn build(&self, app: &mut App) {
+        add_simulation(app, self.config.clone()).unwrap_or_else(|err| panic!("{}", err));
+    }
+}
+
+/// Generates the world for `config` and adds it, the resources the systems share and
+/// the simulation, rendering and logging plugins to `app`.
+fn add_simulation(app: &mut App, config: Config) -> Result<(), ConfigError> {
+    insert_simulation_state(app.world_mut(), config)?;
+    app.init_state::<AppState>().add_plugins(SimulationPlugin);
+    #[cfg(feature = "gui")]
+    app.add_plugins(RenderingPlugin);
+    app.add_plugins((LoggingPlugin, WatchdogPlugin));
+
+    Ok(())
+}
+
+/// Generates the world for `config` and inserts it and the resources the systems
+/// share into `ecs`, in place of those of any run before.
+pub(crate) fn insert_simulation_state(
+    ecs: &mut Ecs,
+    mut config: Config,
+) -> Result<(), ConfigError> {
+    config.check_supported()?;
+    config.check_world_size()?;
+    let (mut world, food_grid) = World::generate(&config.world);
+    let adjustment = config.apply_density_policy(world.habitable_tiles())?;
+
+    let seed = config.world.seed;
+    let mut base_rng = StdRng::seed_from_u64(seed);
+
+    ecs.remove_resource::<SeedBank>();
+    if let Some(path) = &config.world.seed_population {
+        match SeedBank::load(path) {
+            Ok(bank) => {
+                ecs.insert_resource(bank);
+            }
+            Err(err) => warn!(
+                target: "sim::config",
+                "Failed to load seed population {}: {}. Spawning founders from the config.",
+                path.display(),
+                err
+            ),
+        }
+    }
+
+    ecs.remove_resource::<DensityAdjustment>();
+    if let Some(adjustment) = adjustment {
+        warn!(target: "sim::config", "{}.", adjustment);
+        ecs.insert_resource(adjustment);
+    }
+
+    world.mark_reserves(&config.world.reserves, &mut base_rng);
+    ecs.insert_resource(ActiveTiles::all(world.grid.len()));
+    ecs.insert_resource(CarrionGrid::new(world.grid.len()));
+    ecs.insert_resource(KillScentGrid::new(world.grid.len()));
+    ecs.insert_resource(MovementTrace::default());
+    ecs.insert_resource(TerritoryMap::new(world.width, world.height));
+    ecs.insert_resource(FoodPatches::default());
+    ecs.insert_resource(DesertificationPressure::default());
+    ecs.insert_resource(world);
+    ecs.insert_resource(food_grid);
+    ecs.insert_resource(RngStreams::new(seed));
+    ecs.insert_resource(RunId::new(seed));
+    ecs.insert_resource(DeathTally::default());
+    ecs.insert_resource(NewbornSurvival::default());
+    ecs.insert_resource(ClampTally::default());
+    ecs.insert_resource(ClampWarnings::default());
+    ecs.insert_resource(EnergyIntake::default());
+    ecs.insert_resource(ResidenceTally::default());
+    ecs.insert_resource(EnergyFlux::default());
+    ecs.insert_resource(NextId::default());
+    ecs.insert_resource(SpatialIndex::new(config.world.width, config.world.height));
+    ecs.insert_resource(PredatorSpatialIndex::new(
+        config.world.width,
+        config.world.height,
+    ));
+    ecs.insert_resource(PopulationCount::default());
+    ecs.insert_resource(StatsHistory::new(config.logging.stats_history_cap));
+    ecs.insert_resource(config);
+    ecs.insert_resource(Generation(0));
+    ecs.insert_resource(TickClock::default());
+    Ok(())
+}
@@ -0,0 +1,183 @@
+//! One config run headless once per seed of its `seeds`, or `replicates` times with
+//! the seeds `seed`, `seed + 1`, ..., one after another in the same app. Each run
+//! goes to the `generation_limit` and logs to `seed_NNNN/`, and between runs every
+//! individual is despawned and a fresh world generated, so no state carries over;
+//! `batch.csv` collects how each run ended.
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use bevy::app::PluginsState;
+use bevy::prelude::*;
+use bevy::state::state::StateTransition;
+
+use crate::app::{insert_simulation_state, try_build_app};
+use crate::components::{Organism, Predator};
+use crate::config::{ConfigError, ConfigErrors, EFFECTIVE_CONFIG_FILE};
+use crate::events::Died;
+use crate::plugins::logging::close_logs;
+use crate::plugins::watchdog::Watchdog;
+use crate::provenance::{Provenance, RunId};
+use crate::resources::{AppState, Config, LogLevel};
+use crate::universes::Populations;
+
+/// How one run of a batch ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replicate {
+    pub seed: u64,
+    pub generations: usize,
+    pub populations: Populations,
+    /// The state hash the run finished with, in hex.
+    pub state_hash: String,
+}
+
+/// Whether `config` asks for a batch rather than a single run.
+pub fn is_batch(config: &Config) -> bool {
+    !config.world.seeds.is_empty() || config.world.replicates > 0
+}
+
+/// The seeds of the batch in the order they run.
+pub fn seeds(config: &Config) -> Vec<u64> {
+    let world = &config.world;
+    match world.seeds.is_empty() {
+        true => (0..world.replicates as u64)
+            .map(|k| world.seed.wrapping_add(k))
+            .collect(),
+        false => world.seeds.clone(),
+    }
+}
+
+/// Directory the run with `seed` writes its logs to.
+pub fn seed_dir(dir: &Path, seed: u64) -> PathBuf {
+    dir.join(format!("seed_{:04}", seed))
+}
+
+/// The config of the run with `seed`: `config` with that seed and no batch of its
+/// own, without progress lines unless its `log_level` asks for them, and logging
+/// into its own directory.
+pub fn replicate_config(config: &Config, seed: u64, dir: &Path) -> Config {
+    let mut config = config.clone();
+    config.world.seed = seed;
+    config.world.seeds.clear();
+    config.world.replicates = 0;
+    config.world.printing = false;
+    config.world.log_level.get_or_insert(LogLevel::Warn);
+    config.world.target_ticks_per_second = None;
+    config.logging.output_dir = Some(seed_dir(dir, seed));
+    config
+}
+
+fn finished(app: &App) -> bool {
+    *app.world().resource::<State<AppState>>().get() == AppState::Finished
+}
+
+/// Tears down the run `app` holds and sets it up afresh for `config`: closes its
+/// logs, despawns every organism and predator, pooled ones too, replaces the world
+/// and the resources the systems share, and spawns the founders of the new run.
+pub fn reset(app: &mut App, config: Config, run_id: RunId) -> Result<(), ConfigError> {
+    let ecs = app.world_mut();
+    // stops the last seed's watchdog, Startup starts the next one's
+    ecs.remove_resource::<Watchdog>();
+    close_logs(ecs);
+    let individuals: Vec<Entity> = ecs
+        .query_filtered::<Entity, Or<(With<Organism>, With<Predator>)>>()
+        .iter(ecs)
+        .collect();
+    for entity in individuals {
+        ecs.entity_mut(entity).despawn_recursive();
+    }
+    // the run ended with them alive, they did not die
+    ecs.resource_mut::<Events<Died>>().clear();
+
+    insert_simulation_state(ecs, config)?;
+    ecs.insert_resource(run_id);
+    ecs.resource_mut::<NextState<AppState>>()
+        .set(AppState::Simulate);
+    ecs.run_schedule(StateTransition);
+    ecs.run_schedule(Startup);
+    Ok(())
+}
+
+/// Writes the config of a run without `log_data`, which the logging plugin leaves
+/// out, next to its other files.
+fn save_config(config: &Config, run_id: &RunId) -> Result<(), Box<dyn Error>> {
+    config.logging.create_output_dir()?;
+    if !config.logging.log_data {
+        config.save(
+            &config.logging.output_path(EFFECTIVE_CONFIG_FILE),
+            &Provenance::new(run_id, config.world.seed, 0, None),
+        )?;
+    }
+    Ok(())
+}
+
+/// Runs `config` once per seed of its batch, each to its `generation_limit`,
+/// printing and appending to `dir/batch.csv` how each run ended. The table is headed
+/// by the provenance of the whole batch, and the run with seed `s` runs as
+/// `<batch run id>-seed_s`.
+pub fn run(config: &Config, dir: &Path) -> Result<Vec<Replicate>, Box<dyn Error>> {
+    config.validate().map_err(ConfigErrors)?;
+    let batch = RunId::new(config.world.seed);
+    let run_id = |seed| RunId(format!("{}-seed_{}", batch.0, seed));
+
+    fs::create_dir_all(dir)?;
+    let mut csv = BufWriter::new(File::create(dir.join("batch.csv"))?);
+    let provenance = Provenance::new(&batch, config.world.seed, 0, None);
+    write!(csv, "{}", provenance.header())?;
+    writeln!(csv, "seed,generations,organisms,predators,state_hash")?;
+
+    let mut app: Option<App> = None;
+    let mut replicates = Vec::new();
+    for seed in seeds(config) {
+        let config = replicate_config(config, seed, dir);
+        save_config(&config, &run_id(seed))?;
+        let app = match app.as_mut() {
+            Some(app) => {
+                reset(app, config, run_id(seed))?;
+                app
+            }
+            None => {
+                let mut built = try_build_app(config)?;
+                built.insert_resource(run_id(seed));
+                if built.plugins_state() != PluginsState::Cleaned {
+                    built.finish();
+                    built.cleanup();
+                }
+                app.insert(built)
+            }
+        };
+        while !finished(app) {
+            app.update();
+        }
+
+        let provenance = Provenance::of_app(app);
+        let replicate = Replicate {
+            seed,
+            generations: provenance.generation,
+            populations: Populations::count(app),
+            state_hash: provenance.state_hash.unwrap_or_default(),
+        };
+        println!(
+            "seed {}: {} generations, {}/{} left, state hash {}",
+            seed,
+            replicate.generations,
+            replicate.populations.organisms,
+            replicate.populations.predators,
+            replicate.state_hash
+        );
+        writeln!(
+            csv,
+            "{},{},{},{},{}",
+            seed,
+            replicate.generations,
+            replicate.populations.organisms,
+            replicate.populations.predators,
+            replicate.state_hash
+        )?;
+        csv.flush()?;
+        replicates.push(replicate);
+    }
+    Ok(replicates)
+}
@@ -0,0 +1,304 @@
+//! Speed estimate for a config before committing to a long run, started with
+//! `evolution bench [--seconds s] [--ticks n] [--json]`.
+
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use bevy::app::PluginsState;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::app::try_build_app;
+use crate::config::ConfigError;
+use crate::resources::{Config, Generation, PopulationCount};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchOptions {
+    /// Wall-clock measurement budget, ignored when `ticks` is set.
+    pub seconds: f64,
+    /// Fixed number of measured ticks instead of a time budget.
+    pub ticks: Option<usize>,
+    /// Ticks run for this long before measuring, so startup costs stay out of it.
+    pub warmup_seconds: f64,
+    pub json: bool,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            seconds: 10.0,
+            ticks: None,
+            warmup_seconds: 1.0,
+            json: false,
+        }
+    }
+}
+
+impl BenchOptions {
+    /// Parses the arguments following `bench`.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seconds" => {
+                    let value = args.next().ok_or("missing value for --seconds")?;
+                    options.seconds = value
+                        .parse()
+                        .ok()
+                        .filter(|seconds: &f64| seconds.is_finite() && *seconds > 0.0)
+                        .ok_or_else(|| {
+                            format!("--seconds must be a positive number, got `{}`", value)
+                        })?;
+                }
+                "--ticks" => {
+                    let value = args.next().ok_or("missing value for --ticks")?;
+                    let ticks = value.parse().map_err(|_| {
+                        format!("--ticks must be a non-negative integer, got `{}`", value)
+                    })?;
+                    options.ticks = Some(ticks);
+                }
+                "--json" => options.json = true,
+                // read by `main` before the bench starts
+                "--config" | "--seed" | "--generations" => {
+                    args.next()
+                        .ok_or_else(|| format!("missing value for {}", arg))?;
+                }
+                "--headless" => {}
+                other => return Err(format!("unexpected argument `{}`", other)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// What `bench` measured. Every tick of a running simulation is one generation, so
+/// the two rates only part ways if the simulation finishes mid-measurement.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    pub ticks: usize,
+    pub generations: usize,
+    pub seconds: f64,
+    pub ticks_per_second: f64,
+    pub generations_per_second: f64,
+    /// Most organisms and predators alive together at the start of a measured tick.
+    pub peak_entities: usize,
+    pub generation_limit: Option<usize>,
+    /// Wall time `generation_limit` generations would take at the measured rate.
+    pub estimated_seconds: Option<f64>,
+}
+
+impl Display for BenchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "ticks:                  {}", self.ticks)?;
+        writeln!(f, "seconds:                {:.2}", self.seconds)?;
+        writeln!(f, "ticks/second:           {:.2}", self.ticks_per_second)?;
+        writeln!(
+            f,
+            "generations/second:     {:.2}",
+            self.generations_per_second
+        )?;
+        write!(f, "peak entities:          {}", self.peak_entities)?;
+        match (self.generation_limit, self.estimated_seconds) {
+            (Some(limit), Some(seconds)) => write!(
+                f,
+                "\nestimated for {} generations: {}",
+                limit,
+                format_duration(seconds)
+            ),
+            (Some(limit), None) => write!(f, "\nno estimate for {} generations", limit),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn format_duration(seconds: f64) -> String {
+    let total = seconds.round() as u64;
+    format!(
+        "{}h {:02}m {:02}s",
+        total / 3600,
+        total / 60 % 60,
+        total % 60
+    )
+}
+
+/// `config` made safe to measure: headless, quiet, writing no files and never
+/// finishing on its own, since a finished run writes its report and stops ticking.
+pub fn bench_config(config: &Config) -> Config {
+    let mut config = config.clone();
+    config.world.headless = true;
+    config.world.printing = false;
+    config.world.generation_limit = None;
+    config.logging.log_data = false;
+    config.logging.export_population = None;
+    config.logging.sample_individuals_per_generation = 0;
+    config.logging.percentile_individuals.clear();
+    config.logging.trace_entity = None;
+    config.logging.watchdog_timeout_seconds = None;
+    config
+}
+
+/// Builds the app for [`bench_config`], warms it up and measures it.
+pub fn run(config: &Config, options: &BenchOptions) -> Result<BenchReport, ConfigError> {
+    let mut app = try_build_app(bench_config(config))?;
+    Ok(measure(&mut app, options, config.world.generation_limit))
+}
+
+/// Runs `app` for the warm-up and then the measurement `options` ask for, projecting
+/// the rate onto `generation_limit`.
+pub fn measure(
+    app: &mut App,
+    options: &BenchOptions,
+    generation_limit: Option<usize>,
+) -> BenchReport {
+    if app.plugins_state() != PluginsState::Cleaned {
+        app.finish();
+        app.cleanup();
+    }
+
+    let warmup = Duration::from_secs_f64(options.warmup_seconds);
+    let started = Instant::now();
+    while started.elapsed() < warmup {
+        app.update();
+    }
+
+    let budget = Duration::from_secs_f64(options.seconds);
+    let first_generation = app.world().resource::<Generation>().0;
+    let mut ticks = 0;
+    let mut peak_entities = 0;
+    let started = Instant::now();
+    loop {
+        let done = match options.ticks {
+            Some(limit) => ticks >= limit,
+            None => started.elapsed() >= budget,
+        };
+        if done {
+            break;
+        }
+        app.update();
+        ticks += 1;
+        let pop = app.world().resource::<PopulationCount>();
+        peak_entities = peak_entities.max(pop.organisms + pop.predators);
+    }
+    let seconds = started.elapsed().as_secs_f64();
+    let generations = app.world().resource::<Generation>().0 - first_generation;
+
+    let rate = |count: usize| match seconds > 0.0 {
+        true => count as f64 / seconds,
+        false => 0.0,
+    };
+    let generations_per_second = rate(generations);
+    BenchReport {
+        ticks,
+        generations,
+        seconds,
+        ticks_per_second: rate(ticks),
+        generations_per_second,
+        peak_entities,
+        generation_limit,
+        estimated_seconds: generation_limit
+            .filter(|_| generations_per_second > 0.0)
+            .map(|limit| limit as f64 / generations_per_second),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::PercentileRank;
+    use crate::utils::default_config;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn options_parse_budget_ticks_and_json() {
+        assert_eq!(BenchOptions::from_args(&[]), Ok(BenchOptions::default()));
+
+        let options = BenchOptions::from_args(&args("--ticks 50 --json --seconds 2.5")).unwrap();
+        assert_eq!(options.ticks, Some(50));
+        assert_eq!(options.seconds, 2.5);
+        assert!(options.json);
+
+        assert!(BenchOptions::from_args(&args("--seconds 0")).is_err());
+        assert!(BenchOptions::from_args(&args("--ticks")).is_err());
+        assert!(BenchOptions::from_args(&args("--fast")).is_err());
+    }
+
+    #[test]
+    fn measured_config_writes_no_files() {
+        let mut config = default_config();
+        config.world.headless = false;
+        config.world.printing = true;
+        config.world.generation_limit = Some(3);
+        config.logging.log_data = true;
+        config.logging.export_population = Some("population.json".into());
+        config.logging.sample_individuals_per_generation = 5;
+        config.logging.percentile_individuals = vec![PercentileRank::Energy];
+        config.logging.trace_entity = Some(0);
+        config.logging.watchdog_timeout_seconds = Some(0.001);
+
+        let measured = bench_config(&config);
+        assert!(measured.world.headless);
+        assert!(!measured.world.printing);
+        assert!(!measured.logging.log_data);
+        assert_eq!(measured.logging.export_population, None);
+        assert_eq!(measured.logging.sample_individuals_per_generation, 0);
+        assert!(measured.logging.percentile_individuals.is_empty());
+        assert_eq!(measured.logging.trace_entity, None);
+        assert_eq!(measured.logging.watchdog_timeout_seconds, None);
+        // a finished run would write report.json
+        assert_eq!(measured.world.generation_limit, None);
+
+        let dir = std::env::temp_dir().join("evolution_bench_no_files");
+        std::fs::remove_dir_all(&dir).ok();
+        config.logging.output_dir = Some(dir.clone());
+        let options = BenchOptions {
+            ticks: Some(3),
+            warmup_seconds: 0.0,
+            ..BenchOptions::default()
+        };
+        run(&config, &options).unwrap();
+        let written: Vec<_> = std::fs::read_dir(&dir)
+            .map(|entries| entries.map(|entry| entry.unwrap().file_name()).collect())
+            .unwrap_or_default();
+        assert!(written.is_empty(), "{:?}", written);
+    }
+
+    #[test]
+    fn report_counts_ticks_and_projects_the_generation_limit() {
+        let mut config = default_config();
+        config.organism.initial_organisms = 20;
+        config.predator.initial_predators = 2;
+        config.world.generation_limit = Some(5);
+        let options = BenchOptions {
+            ticks: Some(8),
+            warmup_seconds: 0.0,
+            ..BenchOptions::default()
+        };
+
+        let report = run(&config, &options).unwrap();
+        // the limit of 5 does not stop the measurement
+        assert_eq!(report.ticks, 8);
+        assert_eq!(report.generations, 8);
+        assert!(report.peak_entities >= 22);
+        assert_eq!(report.generation_limit, Some(5));
+        let estimate = report.estimated_seconds.unwrap();
+        assert!((estimate - 5.0 / report.generations_per_second).abs() < 1e-9);
+
+        let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+        for key in [
+            "ticks",
+            "generations",
+            "seconds",
+            "ticks_per_second",
+            "generations_per_second",
+            "peak_entities",
+            "generation_limit",
+            "estimated_seconds",
+        ] {
+            assert!(json.get(key).is_some(), "missing {}", key);
+        }
+    }
+}
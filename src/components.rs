@@ -1,10 +1,13 @@
+use std::collections::BTreeMap;
+
 use bevy::prelude::*;
 use rand::rngs::SmallRng;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use crate::resources::Biome;
+use crate::resources::{Biome, FatStorageConfig, GivingUpConfig, StarvationDispersalConfig};
 
-#[derive(Component, Serialize, Clone)]
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+#[require(IntakeHistory, Residence)]
 pub struct Organism {
     pub energy: f32,
     pub speed: f32,
@@ -12,9 +15,81 @@ pub struct Organism {
     pub reproduction_threshold: f32,
     pub reproduction_cooldown: f32,
     pub biome_tolerance: [f32; 4],
+    pub boldness: f32,
+    /// Softmax temperature of movement choices: near 0 always takes the cheapest
+    /// direction, large values wander almost at random.
+    #[serde(default = "default_exploration")]
+    pub exploration: f32,
+    /// Heritable color marker with no effect on fitness unless `morph_fitness` gives
+    /// it one.
+    #[serde(default)]
+    pub morph: u8,
+    /// Share of its energy a parent hands to each offspring, within
+    /// `PARENTAL_INVESTMENT_BOUNDS`.
+    #[serde(default = "default_parental_investment")]
+    pub parental_investment: f32,
+    /// Farthest in steps its offspring land, with the `dispersal_trait` offspring
+    /// placement.
+    #[serde(default = "default_dispersal")]
+    pub dispersal: f32,
+    #[serde(default = "default_preferred_temperature")]
+    pub preferred_temperature: f32,
+    /// Pull towards more comfortable temperatures, in units of movement cost, once
+    /// the tile is more than `temperature_tolerance` off `preferred_temperature`.
+    #[serde(default)]
+    pub migratory_tendency: f32,
+    /// Share of the `kin_sharing` rate it hands each tick to poorer kin on its tile,
+    /// between 0 and 1.
+    #[serde(default)]
+    pub altruism: f32,
+    /// Energy per tick from food, averaged over the `starvation_dispersal` window,
+    /// below which it sets off dispersing.
+    #[serde(default = "default_starvation_trigger")]
+    pub starvation_trigger: f32,
+    /// Ticks a dispersal burst lasts.
+    #[serde(default = "default_burst_duration")]
+    pub burst_duration: f32,
+    /// Most energy it can store under `fat_storage`.
+    #[serde(default = "default_reserve_capacity")]
+    pub reserve_capacity: f32,
+    /// Chance of breeding outside the `breeding` seasons, between 0 and 1.
+    #[serde(default)]
+    pub breeding_flexibility: f32,
+    /// Food on its tile above which it stays to graze under `giving_up`.
+    #[serde(default = "default_giving_up_density")]
+    pub giving_up_density: f32,
+}
+
+fn default_exploration() -> f32 {
+    2.0
+}
+
+fn default_parental_investment() -> f32 {
+    0.5
+}
+
+fn default_dispersal() -> f32 {
+    1.0
+}
+
+fn default_starvation_trigger() -> f32 {
+    StarvationDispersalConfig::default().initial_trigger
+}
+
+fn default_burst_duration() -> f32 {
+    StarvationDispersalConfig::default().initial_duration
+}
+
+fn default_reserve_capacity() -> f32 {
+    FatStorageConfig::default().initial_capacity
+}
+
+fn default_giving_up_density() -> f32 {
+    GivingUpConfig::default().initial_density
 }
 
-#[derive(Component, Serialize, Copy, Clone)]
+#[derive(Component, Serialize, Deserialize, Debug, Copy, Clone)]
+#[require(PredatorSight)]
 pub struct Predator {
     pub energy: f32,
     pub speed: f32,
@@ -23,18 +98,379 @@ pub struct Predator {
     pub hunting_efficiency: f32,
     pub satiation_threshold: f32,
     pub reproduction_cooldown: f32,
+    #[serde(default = "default_preferred_temperature")]
+    pub preferred_temperature: f32,
+    /// Ticks a predator spends digesting after a kill before it can kill again.
+    #[serde(default)]
+    pub hunt_cooldown_ticks: f32,
+    /// Ticks left until the next kill is allowed.
+    #[serde(default)]
+    pub hunt_cooldown_remaining: u32,
+    /// Chance of lying in ambush on a tick with no prey in sight.
+    #[serde(default)]
+    pub patience: f32,
+    #[serde(default)]
+    pub stance: Stance,
+    /// Direction in radians it wanders in under `wander`.
+    #[serde(default)]
+    pub heading: f32,
+    /// Odds of winning a territorial contest, between 0 and 1.
+    #[serde(default)]
+    pub aggression: f32,
+    /// Chance of breeding outside the `breeding` seasons, between 0 and 1.
+    #[serde(default)]
+    pub breeding_flexibility: f32,
+    /// Generations of its latest kills, for `min_kills_for_reproduction`.
+    #[serde(default)]
+    pub recent_kills: KillMemory,
 }
 
-#[derive(Component, Debug, Serialize, Copy, Clone)]
+/// Most kills a predator remembers.
+pub const KILL_MEMORY: usize = 8;
+
+/// Generations of the last [`KILL_MEMORY`] kills, a new kill taking the place of
+/// the oldest once it is full.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KillMemory {
+    generations: [usize; KILL_MEMORY],
+    len: usize,
+    next: usize,
+}
+
+impl KillMemory {
+    pub fn record(&mut self, generation: usize) {
+        self.generations[self.next] = generation;
+        self.next = (self.next + 1) % KILL_MEMORY;
+        self.len = (self.len + 1).min(KILL_MEMORY);
+    }
+
+    /// Remembered kills made less than `ticks` generations before `generation`.
+    pub fn within(&self, generation: usize, ticks: usize) -> usize {
+        self.generations[..self.len]
+            .iter()
+            .filter(|&&kill| generation.saturating_sub(kill) < ticks)
+            .count()
+    }
+}
+
+/// Whether a predator roams in search of prey or sits still waiting for it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Stance {
+    #[default]
+    Roaming,
+    /// Sitting still, paying less upkeep and striking prey on or next to its tile.
+    Ambush,
+}
+
+fn default_preferred_temperature() -> f32 {
+    20.0
+}
+
+/// What a predator saw on its last move, kept up to date by `predator_movement` so
+/// the debug overlay can draw it without scanning again.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default)]
+pub struct PredatorSight {
+    /// Half the side in tiles of the square scanned for prey.
+    pub radius: usize,
+    /// Tile of the closest prey it moved towards.
+    pub target: Option<(usize, usize)>,
+    pub state: HuntState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HuntState {
+    /// No prey in sight.
+    #[default]
+    Searching,
+    /// Closing in on `PredatorSight::target`.
+    Pursuing,
+    /// Too full to hunt, whether or not prey is in sight.
+    Sated,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Copy, Clone)]
 pub struct Position {
     pub x: usize,
     pub y: usize,
 }
 
+/// Heritable trait values of an individual, keyed by trait name.
+pub type TraitSnapshot = BTreeMap<String, f32>;
+
+/// How far each trait of `child` moved from `parent` in mutation, for the traits
+/// both have.
+pub fn trait_deltas(child: &TraitSnapshot, parent: &TraitSnapshot) -> TraitSnapshot {
+    child
+        .iter()
+        .filter_map(|(name, value)| Some((name.clone(), value - parent.get(name)?)))
+        .collect()
+}
+
+impl Organism {
+    pub const TRAITS: [&'static str; 15] = [
+        "size",
+        "speed",
+        "reproduction_threshold",
+        "boldness",
+        "exploration",
+        "parental_investment",
+        "dispersal",
+        "preferred_temperature",
+        "migratory_tendency",
+        "altruism",
+        "starvation_trigger",
+        "burst_duration",
+        "reserve_capacity",
+        "breeding_flexibility",
+        "giving_up_density",
+    ];
+
+    pub fn traits(&self) -> TraitSnapshot {
+        let values = [
+            self.size,
+            self.speed,
+            self.reproduction_threshold,
+            self.boldness,
+            self.exploration,
+            self.parental_investment,
+            self.dispersal,
+            self.preferred_temperature,
+            self.migratory_tendency,
+            self.altruism,
+            self.starvation_trigger,
+            self.burst_duration,
+            self.reserve_capacity,
+            self.breeding_flexibility,
+            self.giving_up_density,
+        ];
+        Self::TRAITS
+            .map(String::from)
+            .into_iter()
+            .zip(values)
+            .collect()
+    }
+}
+
+impl Predator {
+    pub const TRAITS: [&'static str; 10] = [
+        "size",
+        "speed",
+        "reproduction_threshold",
+        "hunting_efficiency",
+        "satiation_threshold",
+        "preferred_temperature",
+        "hunt_cooldown_ticks",
+        "patience",
+        "aggression",
+        "breeding_flexibility",
+    ];
+
+    pub fn traits(&self) -> TraitSnapshot {
+        let values = [
+            self.size,
+            self.speed,
+            self.reproduction_threshold,
+            self.hunting_efficiency,
+            self.satiation_threshold,
+            self.preferred_temperature,
+            self.hunt_cooldown_ticks,
+            self.patience,
+            self.aggression,
+            self.breeding_flexibility,
+        ];
+        Self::TRAITS
+            .map(String::from)
+            .into_iter()
+            .zip(values)
+            .collect()
+    }
+}
+
+/// A dead organism or predator kept for reuse under `entity_pool_size`: the next
+/// birth of its kind overwrites its components instead of spawning an entity.
+/// Systems leave it out with the [`Active`] filter. Sparse storage keeps marking and
+/// recycling it from moving the rest of its components around.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[component(storage = "SparseSet")]
+pub struct Inactive;
+
+/// Query filter for the living organisms and predators, leaving out pooled ones.
+pub type Active = Without<Inactive>;
+
+/// An organism fleeing from the kill at `(x, y)` for `ticks` more ticks. While
+/// panicked it neither eats nor reproduces.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Panicked {
+    pub ticks: u32,
+    pub x: usize,
+    pub y: usize,
+}
+
+/// Energy an organism gained from food in each of its last few ticks, the oldest
+/// overwritten first, plus what it has eaten so far this tick.
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+pub struct IntakeHistory {
+    ticks: Vec<f32>,
+    next: usize,
+    pub meal: f32,
+    /// The meal of the last tick ended, which the generation stats read.
+    pub last_meal: f32,
+}
+
+impl IntakeHistory {
+    /// Ends this tick's `meal` without keeping it among the recent ticks.
+    pub fn end_meal(&mut self) -> f32 {
+        self.last_meal = std::mem::take(&mut self.meal);
+        self.last_meal
+    }
+
+    /// Adds this tick's `meal` to the last `window` ticks and starts the next meal.
+    pub fn record(&mut self, window: usize) {
+        let window = window.max(1);
+        let meal = self.end_meal();
+        if self.ticks.len() < window {
+            self.ticks.push(meal);
+        } else {
+            self.ticks[self.next % window] = meal;
+        }
+        self.next = (self.next + 1) % window;
+    }
+
+    /// Average intake over the last `window` ticks, once that many are recorded.
+    pub fn average(&self, window: usize) -> Option<f32> {
+        (window > 0 && self.ticks.len() >= window)
+            .then(|| self.ticks.iter().sum::<f32>() / self.ticks.len() as f32)
+    }
+
+    pub fn clear(&mut self) {
+        self.ticks.clear();
+        self.next = 0;
+    }
+}
+
+/// The tile an organism is on and the ticks it has spent there since it arrived.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Residence {
+    pub tile: Option<(usize, usize)>,
+    pub ticks: u32,
+}
+
+impl Residence {
+    /// Counts a tick on `tile`. The length of the visit that ended, if the organism
+    /// moved there from another tile.
+    pub fn stay(&mut self, tile: (usize, usize)) -> Option<u32> {
+        if self.tile == Some(tile) {
+            self.ticks += 1;
+            return None;
+        }
+        let ended = self.tile.map(|_| self.ticks);
+        *self = Self {
+            tile: Some(tile),
+            ticks: 1,
+        };
+        ended
+    }
+}
+
+/// An organism leaving a depleted area for `ticks` more ticks along `heading`, an
+/// index into [`COMPASS`](crate::utils::COMPASS).
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dispersing {
+    pub ticks: u32,
+    pub heading: usize,
+}
+
+/// An organism that escaped a predator's attack with an injury of `severity`,
+/// healed after `ticks_remaining` more ticks.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Injured {
+    pub severity: f32,
+    pub ticks_remaining: u32,
+}
+
+/// The organism species an individual belongs to: [`SpeciesId::A`] for the organisms
+/// of the main config and [`SpeciesId::B`] for those of `[species_b]`. Offspring
+/// always belong to their parent's species.
+#[derive(Component, Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct SpeciesId(pub u8);
+
+impl SpeciesId {
+    pub const A: Self = SpeciesId(0);
+    pub const B: Self = SpeciesId(1);
+
+    /// The species of an organism, which is A when it was spawned without one.
+    pub fn of(species: Option<&SpeciesId>) -> Self {
+        species.copied().unwrap_or_default()
+    }
+}
+
+/// The parent's traits, carried by offspring until its `Born` event is sent.
+#[derive(Component)]
+pub struct ParentTraits(pub TraitSnapshot);
+
+/// The parent's age in generations when it gave birth, carried by offspring until
+/// its `Born` event is sent.
+#[derive(Component)]
+pub struct ParentAge(pub usize);
+
 #[derive(Component)]
 pub struct TileComponent {
     pub biome: Biome,
+    pub x: usize,
+    pub y: usize,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub struct EntityRng(pub SmallRng);
+
+/// Stable identity of an organism or predator. `lineage` is the id of the founder
+/// the individual descends from.
+#[derive(Component, Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub id: u64,
+    pub parent: Option<u64>,
+    pub lineage: u64,
+    pub birth_generation: usize,
+    /// Offspring born to the individual so far, its lifetime reproductive success
+    /// once it dies.
+    #[serde(default)]
+    pub offspring_count: u32,
+}
+
+impl Identity {
+    pub fn founder(id: u64, generation: usize) -> Self {
+        Self {
+            id,
+            parent: None,
+            lineage: id,
+            birth_generation: generation,
+            offspring_count: 0,
+        }
+    }
+
+    pub fn child(&self, id: u64, generation: usize) -> Self {
+        Self {
+            id,
+            parent: Some(self.id),
+            lineage: self.lineage,
+            birth_generation: generation,
+            offspring_count: 0,
+        }
+    }
+
+    /// Whether `other` is a parent, child or sibling, or of the same lineage and born
+    /// at most `generations` generations apart.
+    pub fn is_kin(&self, other: &Identity, generations: usize) -> bool {
+        self.parent == Some(other.id)
+            || other.parent == Some(self.id)
+            || (self.parent.is_some() && self.parent == other.parent)
+            || (self.lineage == other.lineage
+                && self.birth_generation.abs_diff(other.birth_generation) <= generations)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum EntityKind {
+    Organism,
+    Predator,
+}
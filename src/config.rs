@@ -0,0 +1,2032 @@
+//! Programmatic construction, validation and TOML round-tripping of [`Config`].
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{SpeciesId, KILL_MEMORY};
+use crate::presets::Preset;
+use crate::provenance::{write_artifact, Provenance};
+use crate::resources::{
+    AgingConfig, AmbushConfig, Biome, BiomeDataConfig, BiomeModel, BreedingConfig, CarrionConfig,
+    CatastropheConfig, Config, DensityPolicy, DesertificationConfig, DisplayConfig,
+    FatStorageConfig, FoodAllocation, FoodPatchConfig, GivingUpConfig, InjuryConfig,
+    KillScentConfig, KinSharingConfig, LogLevel, OffspringPlacement, OrganismConfig, PanicConfig,
+    PercentileRank, ReproductionModel, ReserveConfig, SeasonsConfig, SpeciesConfig,
+    StarvationDispersalConfig, TerritoryConfig, ToleranceCostConfig, WanderConfig, WatchdogAction,
+    WhittakerConfig, ENTITY_SCALE_BOUNDS, PARENTAL_INVESTMENT_BOUNDS,
+};
+use crate::utils::default_config;
+
+/// A single problem found by [`Config::validate`], naming the offending field by its
+/// key in `config.toml`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    pub field: String,
+    pub message: String,
+}
+
+impl ConfigError {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Every value [`Config::validate`] rejected, one a line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigErrors(pub Vec<ConfigError>);
+
+impl Display for ConfigErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let values = if self.0.len() == 1 { "value" } else { "values" };
+        write!(f, "{} invalid config {}:", self.0.len(), values)?;
+        for error in &self.0 {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+/// Initial populations shrunk by the `cap` density policy.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DensityAdjustment {
+    pub habitable_tiles: usize,
+    pub requested_organisms: usize,
+    pub requested_predators: usize,
+    pub organisms: usize,
+    pub predators: usize,
+    /// Organisms of `[species_b]`; `organisms` only counts the first species.
+    #[serde(default)]
+    pub requested_species_b: usize,
+    #[serde(default)]
+    pub species_b: usize,
+}
+
+impl Display for DensityAdjustment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} organisms and {} predators are too many for {} habitable tiles, starting with {} and {} instead",
+            self.requested_organisms,
+            self.requested_predators,
+            self.habitable_tiles,
+            self.organisms,
+            self.predators
+        )?;
+        if self.requested_species_b > 0 {
+            write!(
+                f,
+                ", and {} of the {} organisms of species B",
+                self.species_b, self.requested_species_b
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Keys, or whole tables, of `config.toml` that only change what a run shows or
+/// writes, never how the simulation unfolds.
+pub const COSMETIC_FIELDS: [&str; 24] = [
+    "headless",
+    "printing",
+    "log_level",
+    "screenshot_interval",
+    "target_ticks_per_second",
+    "display",
+    "log_data",
+    "log_interval",
+    "heritability_interval",
+    "heritability_window",
+    "stats_history_cap",
+    "spatial_interval",
+    "spatial_sample",
+    "variance_interval",
+    "export_population",
+    "clamp_warning_fraction",
+    "sample_individuals_per_generation",
+    "percentile_individuals",
+    "output_dir",
+    "watchdog_timeout_seconds",
+    "watchdog_action",
+    "trace_entity",
+    "seeds",
+    "replicates",
+];
+
+/// Keys a config got from the serde defaults because its TOML left them out, in
+/// dotted form like `panic.radius`. A table left out entirely is listed once, and
+/// optional fields left unset are not listed at all.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DefaultedFields(pub Vec<String>);
+
+impl DefaultedFields {
+    /// Diffs the keys of `raw`, the table `config` was read from, against those of
+    /// `config` serialized back.
+    pub fn find(raw: &toml::Table, config: &Config) -> Self {
+        let full = toml::Table::try_from(config).expect("Failed to serialize config");
+        let mut missing = Vec::new();
+        collect_missing(raw, &full, "", &mut missing);
+        missing.sort();
+        Self(missing)
+    }
+
+    /// The defaulted fields that change how the simulation unfolds.
+    pub fn dynamic(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str).filter(|field| {
+            !COSMETIC_FIELDS.iter().any(|cosmetic| {
+                field == cosmetic
+                    || field
+                        .strip_prefix(cosmetic)
+                        .is_some_and(|rest| rest.starts_with('.'))
+            })
+        })
+    }
+
+    /// A warning that results may not match runs with other versions, when any
+    /// defaulted field is dynamic.
+    pub fn warning(&self) -> Option<String> {
+        let dynamic: Vec<_> = self.dynamic().collect();
+        if dynamic.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "{} simulation settings were not in the config and took this version's defaults ({}); results may not be comparable with runs of other versions.",
+            dynamic.len(),
+            dynamic.join(", ")
+        ))
+    }
+}
+
+fn collect_missing(raw: &toml::Table, full: &toml::Table, prefix: &str, missing: &mut Vec<String>) {
+    for (key, value) in full {
+        let path = match prefix {
+            "" => key.clone(),
+            _ => format!("{}.{}", prefix, key),
+        };
+        match (raw.get(key), value) {
+            (None, _) => missing.push(path),
+            (Some(toml::Value::Table(raw)), toml::Value::Table(full)) => {
+                collect_missing(raw, full, &path, missing)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Overlays `top` on `base`: tables are merged key by key, and any other value in
+/// `top` replaces the one in `base`.
+pub fn merge_toml(base: &mut toml::Table, top: toml::Table) {
+    for (key, value) in top {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(top)) => merge_toml(base, top),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Parses a `--set key=value` override into the table it stands for. `key` is
+/// dotted for nested tables, like `ambush.enabled`, and `value` is read as TOML,
+/// or as a string when it is not valid TOML.
+pub fn parse_override(arg: &str) -> Result<toml::Table, ConfigError> {
+    let Some((key, value)) = arg.split_once('=') else {
+        return Err(ConfigError::new(arg, "must be written as key=value"));
+    };
+    let (key, value) = (key.trim(), value.trim());
+    if key.split('.').any(str::is_empty) {
+        return Err(ConfigError::new(key, "is not a valid key"));
+    }
+    let value = format!("value = {}", value)
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_string()));
+
+    let mut parts = key.rsplit('.');
+    let last = parts.next().expect("split yields at least one part");
+    let mut table = toml::Table::from_iter([(last.to_string(), value)]);
+    for part in parts {
+        table = toml::Table::from_iter([(part.to_string(), toml::Value::Table(table))]);
+    }
+    Ok(table)
+}
+
+/// The shorthand flags among `args`: `--seed <u64>`, `--generations <usize>` for
+/// `generation_limit` and `--headless`. They are set on the config once its layers
+/// are read, so they win over all of them and need no config file under them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigFlags {
+    pub seed: Option<u64>,
+    pub generations: Option<usize>,
+    pub headless: bool,
+}
+
+impl ConfigFlags {
+    pub fn from_args(args: &[String]) -> Result<Self, ConfigError> {
+        let mut flags = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let value = args.next().map(String::as_str).unwrap_or_default();
+                    flags.seed = Some(value.parse().map_err(|_| {
+                        ConfigError::new(
+                            "--seed",
+                            format!("must be a non-negative integer, got `{}`", value),
+                        )
+                    })?);
+                }
+                "--generations" => {
+                    let value = args.next().map(String::as_str).unwrap_or_default();
+                    flags.generations = Some(value.parse().map_err(|_| {
+                        ConfigError::new(
+                            "--generations",
+                            format!("must be a non-negative integer, got `{}`", value),
+                        )
+                    })?);
+                }
+                "--headless" => flags.headless = true,
+                _ => {}
+            }
+        }
+        Ok(flags)
+    }
+
+    /// Sets the flags on `config`, which no longer took what they set from the
+    /// defaults.
+    pub fn apply(&self, config: &mut Config, defaulted: &mut DefaultedFields) {
+        if let Some(seed) = self.seed {
+            config.world.seed = seed;
+        }
+        if let Some(generations) = self.generations {
+            config.world.generation_limit = Some(generations);
+        }
+        if self.headless {
+            config.world.headless = true;
+            defaulted.0.retain(|field| field != "headless");
+        }
+    }
+}
+
+/// Prefix of the environment variables that override config values.
+pub const ENV_PREFIX: &str = "ECA_";
+
+/// A config value set by an environment variable, like `organism_mutability` by
+/// `ECA_ORGANISM_MUTABILITY` or `forest.food_availability` by
+/// `ECA_FOREST_FOOD_AVAILABILITY`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvOverride {
+    pub variable: String,
+    /// Dotted like the keys of `--set`.
+    pub key: String,
+    pub value: String,
+}
+
+impl Display for EnvOverride {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={} ({})", self.variable, self.value, self.key)
+    }
+}
+
+/// The [`ENV_PREFIX`] variables among `vars`, sorted by name, each mapped to the
+/// config key it names. The rest of a name is matched against the tables of the
+/// default config, so `FOREST_FOOD_AVAILABILITY` is `food_availability` of the
+/// `forest` table, and a name no table claims is taken as a top-level key.
+pub fn env_overrides(
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<Vec<EnvOverride>, ConfigError> {
+    let defaults = toml::Table::try_from(default_config()).expect("Failed to serialize config");
+    let mut overrides = Vec::new();
+    for (variable, value) in vars {
+        let Some(name) = variable.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let name = name.to_lowercase();
+        if name.is_empty() {
+            return Err(ConfigError::new(variable, "names no config field"));
+        }
+        let key = env_key(&name, &defaults).unwrap_or(name);
+        overrides.push(EnvOverride {
+            variable,
+            key,
+            value,
+        });
+    }
+    overrides.sort_by(|a, b| a.variable.cmp(&b.variable));
+    Ok(overrides)
+}
+
+/// The dotted key `name` stands for in `table`, with the table names it starts
+/// with joined to the rest by `_`.
+fn env_key(name: &str, table: &toml::Table) -> Option<String> {
+    for (key, value) in table {
+        if let (Some(rest), toml::Value::Table(nested)) = (name.strip_prefix(key.as_str()), value) {
+            if let Some(rest) = rest.strip_prefix('_') {
+                if let Some(nested_key) = env_key(rest, nested) {
+                    return Some(format!("{}.{}", key, nested_key));
+                }
+            }
+        }
+    }
+    match table.get(name) {
+        Some(toml::Value::Table(_)) | None => None,
+        Some(_) => Some(name.to_string()),
+    }
+}
+
+/// Whether `table` has a value at the dotted `key`.
+fn has_key(table: &toml::Table, key: &str) -> bool {
+    match key.split_once('.') {
+        Some((first, rest)) => match table.get(first) {
+            Some(toml::Value::Table(nested)) => has_key(nested, rest),
+            _ => false,
+        },
+        None => table.contains_key(key),
+    }
+}
+
+/// Resolves a config from layers that each override the ones before: the `preset`
+/// over the defaults, then the TOML `file`, then the `--set` `overrides` from the
+/// command line. Without a preset the file only needs `width`, `height` and `seed`,
+/// and the fields it left to the serde defaults are reported; a preset is complete
+/// with the defaults under it, so nothing is reported as defaulted.
+pub fn layer_config(
+    preset: Option<&Preset>,
+    file: Option<&str>,
+    overrides: &[String],
+) -> Result<(Config, DefaultedFields), Box<dyn Error>> {
+    layer_config_with_env(preset, file, &[], overrides, &ConfigFlags::default())
+}
+
+/// [`layer_config`] with the `env` overrides layered between the file and the
+/// `--set` overrides, and the shorthand `flags` set on the result before it is
+/// checked. A value of the wrong type, a value out of range and a name that is no
+/// config field all fail naming the variable.
+pub fn layer_config_with_env(
+    preset: Option<&Preset>,
+    file: Option<&str>,
+    env: &[EnvOverride],
+    overrides: &[String],
+    flags: &ConfigFlags,
+) -> Result<(Config, DefaultedFields), Box<dyn Error>> {
+    let mut raw = match preset {
+        Some(preset) => {
+            let mut raw = toml::Table::try_from(default_config())?;
+            merge_toml(&mut raw, preset.toml.parse()?);
+            raw
+        }
+        None => toml::Table::new(),
+    };
+    if let Some(file) = file {
+        merge_toml(&mut raw, file.parse()?);
+    }
+    // with a complete config under them, each variable can be tried on its own so
+    // that a bad value is blamed on the variable that set it
+    let complete = toml::Value::Table(raw.clone()).try_into::<Config>().is_ok();
+    for var in env {
+        let table = parse_override(&format!("{}={}", var.key, var.value))
+            .map_err(|err| ConfigError::new(&var.variable, err.message))?;
+        if complete {
+            let mut single = raw.clone();
+            merge_toml(&mut single, table.clone());
+            if let Err(err) = toml::Value::Table(single).try_into::<Config>() {
+                return Err(ConfigError::new(&var.variable, err.message()).into());
+            }
+        }
+        merge_toml(&mut raw, table);
+    }
+    for arg in overrides {
+        merge_toml(&mut raw, parse_override(arg)?);
+    }
+
+    let mut config: Config = toml::Value::Table(raw.clone()).try_into()?;
+    let full = toml::Table::try_from(&config)?;
+    if let Some(var) = env.iter().find(|var| !has_key(&full, &var.key)) {
+        return Err(ConfigError::new(&var.variable, "names no config field").into());
+    }
+    let mut defaulted = DefaultedFields::find(&raw, &config);
+    // after the diff, which would fail to write a seed past `MAX_SEED` as TOML before
+    // `validate` could name it
+    flags.apply(&mut config, &mut defaulted);
+    config.check_supported()?;
+    config.validate().map_err(|errors| {
+        ConfigErrors(
+            errors
+                .into_iter()
+                .map(|mut error| {
+                    if let Some(var) = env.iter().find(|var| var.key == error.field) {
+                        error.field = format!("{} ({})", var.variable, error.field);
+                    }
+                    error
+                })
+                .collect(),
+        )
+    })?;
+    Ok((config, defaulted))
+}
+
+/// One of the checks below.
+type Check = fn(&mut Vec<ConfigError>, &str, f32);
+
+fn check_positive(errors: &mut Vec<ConfigError>, field: &str, value: f32) {
+    if value.is_nan() || value <= 0.0 {
+        errors.push(ConfigError::new(
+            field,
+            format!("must be positive, got {}", value),
+        ));
+    }
+}
+
+fn check_non_negative(errors: &mut Vec<ConfigError>, field: &str, value: f32) {
+    if value.is_nan() || value < 0.0 {
+        errors.push(ConfigError::new(
+            field,
+            format!("must not be negative, got {}", value),
+        ));
+    }
+}
+
+fn check_fraction(errors: &mut Vec<ConfigError>, field: &str, value: f32) {
+    if !(0.0..=1.0).contains(&value) {
+        errors.push(ConfigError::new(
+            field,
+            format!("must be between 0 and 1, got {}", value),
+        ));
+    }
+}
+
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder {
+            config: default_config(),
+        }
+    }
+
+    pub fn from_toml_str(toml: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(toml)
+    }
+
+    /// Fails for a config that opens a window in a binary built without the `gui`
+    /// feature, which has nothing to draw it with.
+    pub fn check_supported(&self) -> Result<(), ConfigError> {
+        if self.world.headless || cfg!(feature = "gui") {
+            return Ok(());
+        }
+        Err(ConfigError::new(
+            "headless",
+            "must be true, this binary was built without the `gui` feature",
+        ))
+    }
+
+    /// Fails for a world without tiles. One tile across is fine: a 1xN or Nx1 world
+    /// is a corridor its individuals move up and down.
+    pub fn check_world_size(&self) -> Result<(), ConfigError> {
+        let world = &self.world;
+        if world.width > 0 && world.height > 0 {
+            return Ok(());
+        }
+        Err(ConfigError::new(
+            "width",
+            format!(
+                "the world must not be empty, got {}x{}",
+                world.width, world.height
+            ),
+        ))
+    }
+
+    pub fn to_toml_string(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+
+    /// Writes the config as TOML to `path`, so that loading it with `--config`
+    /// reproduces the run, headed by the `provenance` of that run in comments.
+    pub fn save(&self, path: &Path, provenance: &Provenance) -> Result<(), Box<dyn Error>> {
+        write_artifact(path, self.to_toml_string()?, provenance)?;
+        Ok(())
+    }
+
+    /// Initial organisms of both species and predators together per tile that is not
+    /// water.
+    pub fn initial_density(&self, habitable_tiles: usize) -> f32 {
+        let population = self.organism.initial_organisms
+            + self.initial_species_b()
+            + self.predator.initial_predators;
+        population as f32 / habitable_tiles.max(1) as f32
+    }
+
+    fn initial_species_b(&self) -> usize {
+        self.species_b
+            .as_ref()
+            .map_or(0, |species| species.initial_organisms)
+    }
+
+    /// The organism config of `species`: the main one, or for species B the main one
+    /// with the `[species_b]` overrides.
+    pub fn species_organism(&self, species: SpeciesId) -> Cow<'_, OrganismConfig> {
+        match (&self.species_b, species) {
+            (Some(species_b), SpeciesId::B) => {
+                Cow::Owned(species_b.organism_config(&self.organism))
+            }
+            _ => Cow::Borrowed(&self.organism),
+        }
+    }
+
+    /// The species with organisms in this run.
+    pub fn species(&self) -> Vec<SpeciesId> {
+        match self.species_b {
+            Some(_) => vec![SpeciesId::A, SpeciesId::B],
+            None => vec![SpeciesId::A],
+        }
+    }
+
+    /// Holds the initial populations to `max_initial_density` on a world with
+    /// `habitable_tiles` tiles that are not water, the way `density_policy` says.
+    /// Only `cap` changes the config, and then returns what it changed.
+    pub fn apply_density_policy(
+        &mut self,
+        habitable_tiles: usize,
+    ) -> Result<Option<DensityAdjustment>, ConfigError> {
+        let max_density = self.world.max_initial_density;
+        let density = self.initial_density(habitable_tiles);
+        if density <= max_density {
+            return Ok(None);
+        }
+
+        match self.world.density_policy {
+            DensityPolicy::Allow => Ok(None),
+            DensityPolicy::Error => Err(ConfigError::new(
+                "max_initial_density",
+                format!(
+                    "{} organisms and {} predators on {} habitable tiles are {:.1} per tile, above {}",
+                    self.organism.initial_organisms,
+                    self.predator.initial_predators,
+                    habitable_tiles,
+                    density,
+                    max_density
+                ),
+            )),
+            DensityPolicy::Cap => {
+                let requested_organisms = self.organism.initial_organisms;
+                let requested_predators = self.predator.initial_predators;
+                let requested_species_b = self.initial_species_b();
+                let allowed = (max_density * habitable_tiles as f32) as usize;
+                let share = allowed as f32
+                    / (requested_organisms + requested_species_b + requested_predators) as f32;
+                // keep at least one predator, and one of species B, around when there
+                // were any
+                let predators = ((requested_predators as f32 * share).round() as usize)
+                    .max(requested_predators.min(1))
+                    .min(allowed);
+                let species_b = ((requested_species_b as f32 * share).round() as usize)
+                    .max(requested_species_b.min(1))
+                    .min(allowed - predators);
+                let organisms = (allowed - predators - species_b).min(requested_organisms);
+
+                self.organism.initial_organisms = organisms;
+                self.predator.initial_predators = predators;
+                if let Some(species) = &mut self.species_b {
+                    species.initial_organisms = species_b;
+                }
+                Ok(Some(DensityAdjustment {
+                    habitable_tiles,
+                    requested_organisms,
+                    requested_predators,
+                    organisms,
+                    predators,
+                    requested_species_b,
+                    species_b,
+                }))
+            }
+        }
+    }
+
+    /// Flags everything that needs predators in a run with `predators_enabled` off:
+    /// predator founders, the predator mechanics and the organism responses to
+    /// attacks, none of which would ever happen.
+    fn check_prey_only(&self, errors: &mut Vec<ConfigError>) {
+        let predator = &self.predator;
+        if predator.initial_predators > 0 {
+            errors.push(ConfigError::new(
+                "initial_predators",
+                "must be 0 with predators_enabled = false",
+            ));
+        }
+        let mechanics = [
+            ("ambush.enabled", predator.ambush.enabled),
+            ("territory.enabled", predator.territory.enabled),
+            ("wander.enabled", predator.wander.enabled),
+            ("kill_scent.enabled", predator.kill_scent.enabled),
+            (
+                "carrion.food_per_size",
+                predator.carrion.food_per_size > 0.0,
+            ),
+            ("panic.enabled", self.organism.panic.enabled),
+            ("injury.enabled", self.organism.injury.enabled),
+        ];
+        for (field, configured) in mechanics {
+            if configured {
+                errors.push(ConfigError::new(
+                    field,
+                    "needs predators, which predators_enabled = false leaves out",
+                ));
+            }
+        }
+    }
+
+    /// Collects every value the simulation cannot run with, rather than stopping at
+    /// the first one.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+        let world = &self.world;
+
+        if let Err(err) = self.check_world_size() {
+            errors.push(err);
+        }
+        if world.max_total_entities == 0 {
+            errors.push(ConfigError::new("max_total_entities", "must be at least 1"));
+        }
+        if world.generation_limit == Some(0) {
+            errors.push(ConfigError::new(
+                "generation_limit",
+                "must be at least 1 when set",
+            ));
+        }
+        if !world.seeds.is_empty() || world.replicates > 0 {
+            if !world.seeds.is_empty() && world.replicates > 0 {
+                errors.push(ConfigError::new(
+                    "replicates",
+                    "cannot be set together with seeds",
+                ));
+            }
+            if world.generation_limit.is_none() {
+                errors.push(ConfigError::new(
+                    "generation_limit",
+                    "must be set for a batch of seeds or replicates",
+                ));
+            }
+            if !world.headless {
+                errors.push(ConfigError::new(
+                    "seeds",
+                    "batch runs are headless only, set headless = true",
+                ));
+            }
+            if let Some(seed) = world.seeds.iter().find(|&&seed| seed > MAX_SEED) {
+                errors.push(ConfigError::new(
+                    "seeds",
+                    format!("must each be at most {}, got {}", MAX_SEED, seed),
+                ));
+            }
+            let last = world
+                .seed
+                .saturating_add(world.replicates.saturating_sub(1) as u64);
+            if world.replicates > 0 && world.seed <= MAX_SEED && last > MAX_SEED {
+                errors.push(ConfigError::new(
+                    "replicates",
+                    format!("would run seeds past {} from seed {}", MAX_SEED, world.seed),
+                ));
+            }
+        }
+        if world.seed > MAX_SEED {
+            errors.push(ConfigError::new(
+                "seed",
+                format!("must be at most {}, got {}", MAX_SEED, world.seed),
+            ));
+        }
+        // predators on top of a full cap are culled down to it in the first tick, but
+        // organisms alone over it would leave the run nothing to start from but a cull
+        let organisms = self.organism.initial_organisms + self.initial_species_b();
+        if organisms > world.max_total_entities {
+            errors.push(ConfigError::new(
+                "initial_organisms",
+                format!(
+                    "{} organisms to start with do not fit in max_total_entities = {}",
+                    organisms, world.max_total_entities
+                ),
+            ));
+        }
+        if let Some(target) = world.target_ticks_per_second {
+            check_positive(&mut errors, "target_ticks_per_second", target);
+        }
+        for biome in [Biome::Forest, Biome::Desert, Biome::Water, Biome::Grassland] {
+            let data = world.biome(biome);
+            let name = biome.to_string().to_lowercase();
+            check_non_negative(
+                &mut errors,
+                &format!("{}.food_availability", name),
+                data.food_availability,
+            );
+            check_non_negative(
+                &mut errors,
+                &format!("{}.max_food_availability", name),
+                data.max_food_availability,
+            );
+            check_non_negative(
+                &mut errors,
+                &format!("{}.predator_decay_factor", name),
+                data.predator_decay_factor,
+            );
+            check_positive(
+                &mut errors,
+                &format!("{}.food_nutrition", name),
+                data.food_nutrition,
+            );
+            check_non_negative(
+                &mut errors,
+                &format!("{}.predator_vision", name),
+                data.predator_vision,
+            );
+            check_non_negative(
+                &mut errors,
+                &format!("{}.predator_catch", name),
+                data.predator_catch,
+            );
+            if data
+                .passive_energy_delta
+                .is_some_and(|delta| !delta.is_finite())
+            {
+                errors.push(ConfigError::new(
+                    format!("{}.passive_energy_delta", name),
+                    "must be a finite number",
+                ));
+            }
+            if let Some(cost) = data.organism_movement_cost {
+                check_non_negative(
+                    &mut errors,
+                    &format!("{}.organism_movement_cost", name),
+                    cost,
+                );
+            }
+            if let Some(cost) = data.predator_movement_cost {
+                check_non_negative(
+                    &mut errors,
+                    &format!("{}.predator_movement_cost", name),
+                    cost,
+                );
+            }
+        }
+
+        let catastrophes = &world.catastrophes;
+        check_fraction(
+            &mut errors,
+            "catastrophes.probability",
+            catastrophes.probability,
+        );
+        check_fraction(
+            &mut errors,
+            "catastrophes.mortality",
+            catastrophes.mortality,
+        );
+        check_fraction(
+            &mut errors,
+            "catastrophes.food_destruction",
+            catastrophes.food_destruction,
+        );
+        check_fraction(
+            &mut errors,
+            "catastrophes.flood_share",
+            catastrophes.flood_share,
+        );
+        if catastrophes.min_radius > catastrophes.max_radius {
+            errors.push(ConfigError::new(
+                "catastrophes.min_radius",
+                format!(
+                    "must not exceed max_radius ({} > {})",
+                    catastrophes.min_radius, catastrophes.max_radius
+                ),
+            ));
+        }
+        if world.seasons.length == 0 {
+            errors.push(ConfigError::new("seasons.length", "must be at least 1"));
+        }
+        let food_patches = &world.food_patches;
+        check_fraction(
+            &mut errors,
+            "food_patches.probability",
+            food_patches.probability,
+        );
+        check_non_negative(
+            &mut errors,
+            "food_patches.bonus_food",
+            food_patches.bonus_food,
+        );
+        check_fraction(
+            &mut errors,
+            "food_patches.decay_rate",
+            food_patches.decay_rate,
+        );
+        check_fraction(
+            &mut errors,
+            "reserves.random_fraction",
+            world.reserves.random_fraction,
+        );
+
+        let whittaker = &world.whittaker;
+        if whittaker.table.is_empty() || whittaker.table.iter().any(Vec::is_empty) {
+            errors.push(ConfigError::new(
+                "whittaker.table",
+                "needs at least one row and every row needs a biome",
+            ));
+        }
+        if whittaker.min_temperature > whittaker.max_temperature {
+            errors.push(ConfigError::new(
+                "whittaker.min_temperature",
+                "must not exceed max_temperature",
+            ));
+        }
+        check_fraction(
+            &mut errors,
+            "whittaker.latitude_weight",
+            whittaker.latitude_weight,
+        );
+
+        check_non_negative(
+            &mut errors,
+            "max_initial_density",
+            world.max_initial_density,
+        );
+        check_positive(&mut errors, "reproduction_scale", world.reproduction_scale);
+
+        let (min_scale, max_scale) = ENTITY_SCALE_BOUNDS;
+        if !(min_scale..=max_scale).contains(&world.display.entity_scale) {
+            errors.push(ConfigError::new(
+                "display.entity_scale",
+                format!(
+                    "must be between {} and {}, got {}",
+                    min_scale, max_scale, world.display.entity_scale
+                ),
+            ));
+        }
+        if world.display.food_dots_per_tile == 0 {
+            errors.push(ConfigError::new(
+                "display.food_dots_per_tile",
+                "must be at least 1",
+            ));
+        }
+        if world.display.history_interval == 0 {
+            errors.push(ConfigError::new(
+                "display.history_interval",
+                "must be at least 1",
+            ));
+        }
+
+        let organism = &self.organism;
+        check_positive(
+            &mut errors,
+            "initial_organism_speed",
+            organism.initial_organism_speed,
+        );
+        check_positive(
+            &mut errors,
+            "initial_organism_size",
+            organism.initial_organism_size,
+        );
+        check_fraction(
+            &mut errors,
+            "organism_mutability",
+            organism.organism_mutability,
+        );
+        if organism.overcrowding_threshold_for_organisms == 0 {
+            errors.push(ConfigError::new(
+                "overcrowding_threshold_for_organisms",
+                "must be at least 1",
+            ));
+        }
+        check_fraction(
+            &mut errors,
+            "initial_organism_boldness",
+            organism.initial_organism_boldness,
+        );
+        check_non_negative(
+            &mut errors,
+            "initial_organism_exploration",
+            organism.initial_organism_exploration,
+        );
+        check_positive(
+            &mut errors,
+            "food_energy_conversion",
+            organism.food_energy_conversion,
+        );
+        check_positive(
+            &mut errors,
+            "max_organism_energy",
+            organism.max_organism_energy,
+        );
+        if organism.morphs == 0 {
+            errors.push(ConfigError::new("morphs", "must be at least 1"));
+        }
+        check_fraction(
+            &mut errors,
+            "morph_mutation_probability",
+            organism.morph_mutation_probability,
+        );
+        check_non_negative(
+            &mut errors,
+            "shoreline_grazing_rate",
+            organism.shoreline_grazing_rate,
+        );
+        let (low, high) = PARENTAL_INVESTMENT_BOUNDS;
+        if !(low..=high).contains(&organism.initial_parental_investment) {
+            errors.push(ConfigError::new(
+                "initial_parental_investment",
+                format!(
+                    "must be between {} and {}, got {}",
+                    low, high, organism.initial_parental_investment
+                ),
+            ));
+        }
+        check_non_negative(
+            &mut errors,
+            "initial_organism_dispersal",
+            organism.initial_organism_dispersal,
+        );
+        check_non_negative(
+            &mut errors,
+            "initial_organism_migratory_tendency",
+            organism.initial_organism_migratory_tendency,
+        );
+        check_non_negative(
+            &mut errors,
+            "temperature_tolerance",
+            organism.temperature_tolerance,
+        );
+        check_positive(
+            &mut errors,
+            "panic.speed_multiplier",
+            organism.panic.speed_multiplier,
+        );
+        check_fraction(
+            &mut errors,
+            "panic.chain_probability",
+            organism.panic.chain_probability,
+        );
+        check_non_negative(&mut errors, "kin_sharing.rate", organism.kin_sharing.rate);
+        check_fraction(
+            &mut errors,
+            "kin_sharing.initial_altruism",
+            organism.kin_sharing.initial_altruism,
+        );
+        let dispersal = &organism.starvation_dispersal;
+        if dispersal.window == 0 {
+            errors.push(ConfigError::new(
+                "starvation_dispersal.window",
+                "must be at least 1",
+            ));
+        }
+        check_non_negative(
+            &mut errors,
+            "starvation_dispersal.initial_trigger",
+            dispersal.initial_trigger,
+        );
+        check_positive(
+            &mut errors,
+            "starvation_dispersal.initial_duration",
+            dispersal.initial_duration,
+        );
+        check_non_negative(
+            &mut errors,
+            "starvation_dispersal.energy_cost_multiplier",
+            dispersal.energy_cost_multiplier,
+        );
+        check_fraction(
+            &mut errors,
+            "starvation_dispersal.heading_persistence",
+            dispersal.heading_persistence,
+        );
+        let injury = &organism.injury;
+        check_fraction(&mut errors, "injury.min_severity", injury.min_severity);
+        check_fraction(&mut errors, "injury.max_severity", injury.max_severity);
+        if injury.min_severity > injury.max_severity {
+            errors.push(ConfigError::new(
+                "injury.max_severity",
+                format!(
+                    "must be at least min_severity ({}), got {}",
+                    injury.min_severity, injury.max_severity
+                ),
+            ));
+        }
+        check_non_negative(
+            &mut errors,
+            "injury.heal_ticks_per_severity",
+            injury.heal_ticks_per_severity,
+        );
+        check_fraction(
+            &mut errors,
+            "injury.movement_penalty",
+            injury.movement_penalty,
+        );
+        check_non_negative(&mut errors, "injury.energy_drain", injury.energy_drain);
+        check_non_negative(&mut errors, "injury.catch_bonus", injury.catch_bonus);
+        check_positive(
+            &mut errors,
+            "fat_storage.initial_capacity",
+            organism.fat_storage.initial_capacity,
+        );
+        check_non_negative(
+            &mut errors,
+            "fat_storage.weight_coupling",
+            organism.fat_storage.weight_coupling,
+        );
+        check_non_negative(
+            &mut errors,
+            "giving_up.initial_density",
+            organism.giving_up.initial_density,
+        );
+        let tolerance_cost = &organism.tolerance_cost;
+        check_non_negative(&mut errors, "tolerance_cost.rate", tolerance_cost.rate);
+        check_non_negative(
+            &mut errors,
+            "tolerance_cost.baseline",
+            tolerance_cost.baseline,
+        );
+        if let Some(species) = &self.species_b {
+            let overrides: [(&str, Option<f32>, Check); 6] = [
+                (
+                    "initial_organism_energy",
+                    species.initial_organism_energy,
+                    check_positive,
+                ),
+                (
+                    "initial_organism_speed",
+                    species.initial_organism_speed,
+                    check_positive,
+                ),
+                (
+                    "initial_organism_size",
+                    species.initial_organism_size,
+                    check_positive,
+                ),
+                (
+                    "organism_mutability",
+                    species.organism_mutability,
+                    check_fraction,
+                ),
+                (
+                    "initial_organism_boldness",
+                    species.initial_organism_boldness,
+                    check_fraction,
+                ),
+                (
+                    "initial_organism_exploration",
+                    species.initial_organism_exploration,
+                    check_non_negative,
+                ),
+            ];
+            for (name, value, check) in overrides {
+                if let Some(value) = value {
+                    check(&mut errors, &format!("species_b.{}", name), value);
+                }
+            }
+        }
+
+        let predator = &self.predator;
+        if !predator.predators_enabled {
+            self.check_prey_only(&mut errors);
+        }
+        check_positive(
+            &mut errors,
+            "initial_predator_speed",
+            predator.initial_predator_speed,
+        );
+        check_positive(
+            &mut errors,
+            "initial_predator_size",
+            predator.initial_predator_size,
+        );
+        check_fraction(
+            &mut errors,
+            "predator_mutability",
+            predator.predator_mutability,
+        );
+        if predator.overcrowding_threshold_for_predators == 0 {
+            errors.push(ConfigError::new(
+                "overcrowding_threshold_for_predators",
+                "must be at least 1",
+            ));
+        }
+        check_non_negative(
+            &mut errors,
+            "predator_energy_decay_rate",
+            predator.predator_energy_decay_rate,
+        );
+        check_positive(
+            &mut errors,
+            "max_predator_energy",
+            predator.max_predator_energy,
+        );
+        check_fraction(&mut errors, "juvenile_factor", predator.juvenile_factor);
+        if predator.min_kills_for_reproduction > KILL_MEMORY {
+            errors.push(ConfigError::new(
+                "min_kills_for_reproduction",
+                format!(
+                    "predators remember at most {} kills, got {}",
+                    KILL_MEMORY, predator.min_kills_for_reproduction
+                ),
+            ));
+        }
+        if predator.kill_memory_ticks == 0 {
+            errors.push(ConfigError::new("kill_memory_ticks", "must be at least 1"));
+        }
+        check_non_negative(
+            &mut errors,
+            "carrion.food_per_size",
+            predator.carrion.food_per_size,
+        );
+        check_fraction(
+            &mut errors,
+            "carrion.decay_rate",
+            predator.carrion.decay_rate,
+        );
+        let kill_scent = &predator.kill_scent;
+        check_non_negative(&mut errors, "kill_scent.deposit", kill_scent.deposit);
+        if kill_scent.decay_ticks == 0 {
+            errors.push(ConfigError::new(
+                "kill_scent.decay_ticks",
+                "must be at least 1",
+            ));
+        }
+        check_non_negative(
+            &mut errors,
+            "kill_scent.predator_attraction",
+            kill_scent.predator_attraction,
+        );
+        check_non_negative(
+            &mut errors,
+            "kill_scent.organism_avoidance",
+            kill_scent.organism_avoidance,
+        );
+        let ambush = &predator.ambush;
+        check_fraction(
+            &mut errors,
+            "ambush.initial_patience",
+            ambush.initial_patience,
+        );
+        check_non_negative(&mut errors, "ambush.decay_factor", ambush.decay_factor);
+        check_non_negative(&mut errors, "ambush.catch_bonus", ambush.catch_bonus);
+        let territory = &predator.territory;
+        check_non_negative(&mut errors, "territory.radius", territory.radius);
+        check_non_negative(
+            &mut errors,
+            "territory.radius_per_size",
+            territory.radius_per_size,
+        );
+        check_non_negative(
+            &mut errors,
+            "territory.intrusion_cost",
+            territory.intrusion_cost,
+        );
+        check_fraction(
+            &mut errors,
+            "territory.initial_aggression",
+            territory.initial_aggression,
+        );
+        check_non_negative(
+            &mut errors,
+            "territory.contest_cost",
+            territory.contest_cost,
+        );
+        check_non_negative(
+            &mut errors,
+            "territory.aggression_cost",
+            territory.aggression_cost,
+        );
+        check_non_negative(
+            &mut errors,
+            "territory.home_catch_bonus",
+            territory.home_catch_bonus,
+        );
+        let wander = &predator.wander;
+        check_non_negative(&mut errors, "wander.turn_noise", wander.turn_noise);
+        check_fraction(&mut errors, "wander.turn_chance", wander.turn_chance);
+        check_non_negative(&mut errors, "wander.cost_repulsion", wander.cost_repulsion);
+        for (name, breeding) in [
+            ("breeding", &organism.breeding),
+            ("predator_breeding", &predator.predator_breeding),
+        ] {
+            check_fraction(
+                &mut errors,
+                &format!("{}.initial_flexibility", name),
+                breeding.initial_flexibility,
+            );
+            check_fraction(
+                &mut errors,
+                &format!("{}.off_season_cost", name),
+                breeding.off_season_cost,
+            );
+        }
+        check_fraction(
+            &mut errors,
+            "aging.fertility_decline",
+            organism.aging.fertility_decline,
+        );
+        check_non_negative(
+            &mut errors,
+            "aging.metabolic_increase",
+            organism.aging.metabolic_increase,
+        );
+        check_fraction(
+            &mut errors,
+            "clamp_warning_fraction",
+            self.logging.clamp_warning_fraction,
+        );
+        if let Some(timeout) = self.logging.watchdog_timeout_seconds {
+            check_positive(&mut errors, "watchdog_timeout_seconds", timeout);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// File the resolved config of a run is saved to before its first tick.
+pub const EFFECTIVE_CONFIG_FILE: &str = "effective_config.toml";
+
+/// Largest seed a run takes, the largest TOML integer, so that its
+/// [`EFFECTIVE_CONFIG_FILE`] can be written.
+pub const MAX_SEED: u64 = i64::MAX as u64;
+
+/// Fluent construction of a [`Config`], starting from [`default_config`].
+#[derive(Debug, Clone)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+macro_rules! setters {
+    ($($section:ident { $($field:ident: $ty:ty),* $(,)? })*) => {
+        impl ConfigBuilder {
+            $($(
+                #[doc = concat!("Sets `", stringify!($field), "`.")]
+                pub fn $field(mut self, $field: $ty) -> Self {
+                    self.config.$section.$field = $field;
+                    self
+                }
+            )*)*
+        }
+    };
+}
+
+setters! {
+    world {
+        width: usize,
+        height: usize,
+        seed: u64,
+        headless: bool,
+        printing: bool,
+        log_level: Option<LogLevel>,
+        generation_limit: Option<usize>,
+        seeds: Vec<u64>,
+        replicates: usize,
+        screenshot_interval: Option<usize>,
+        target_ticks_per_second: Option<f32>,
+        seed_population: Option<PathBuf>,
+        max_total_entities: usize,
+        forest: BiomeDataConfig,
+        desert: BiomeDataConfig,
+        water: BiomeDataConfig,
+        grassland: BiomeDataConfig,
+        desertification: DesertificationConfig,
+        catastrophes: CatastropheConfig,
+        seasons: SeasonsConfig,
+        food_patches: FoodPatchConfig,
+        reserves: ReserveConfig,
+        biome_model: BiomeModel,
+        whittaker: WhittakerConfig,
+        display: DisplayConfig,
+        density_policy: DensityPolicy,
+        max_initial_density: f32,
+        offspring_placement: OffspringPlacement,
+        offspring_search_radius: usize,
+        reproduction_model: ReproductionModel,
+        reproduction_scale: f32,
+        entity_pool_size: usize,
+        passive_energy: bool,
+    }
+    organism {
+        initial_organisms: usize,
+        initial_organism_energy: f32,
+        initial_organism_speed: f32,
+        initial_organism_size: f32,
+        initial_organism_reproduction_threshold: f32,
+        organism_mutability: f32,
+        overcrowding_threshold_for_organisms: usize,
+        organism_reproduction_cooldown: f32,
+        initial_organism_boldness: f32,
+        predator_fear_weight: f32,
+        food_attraction_weight: f32,
+        boldness_exposure: f32,
+        initial_organism_exploration: f32,
+        legacy_movement_jitter: bool,
+        panic: PanicConfig,
+        food_energy_conversion: f32,
+        max_organism_energy: f32,
+        relative_reproduction_threshold: bool,
+        morphs: u8,
+        morph_mutation_probability: f32,
+        morph_fitness: Vec<f32>,
+        shoreline_grazing_rate: f32,
+        food_allocation: FoodAllocation,
+        initial_parental_investment: f32,
+        evolve_parental_investment: bool,
+        initial_organism_dispersal: f32,
+        initial_organism_preferred_temperature: f32,
+        initial_organism_migratory_tendency: f32,
+        temperature_tolerance: f32,
+        kin_sharing: KinSharingConfig,
+        starvation_dispersal: StarvationDispersalConfig,
+        injury: InjuryConfig,
+        fat_storage: FatStorageConfig,
+        giving_up: GivingUpConfig,
+        tolerance_cost: ToleranceCostConfig,
+        breeding: BreedingConfig,
+        aging: AgingConfig,
+    }
+    predator {
+        predators_enabled: bool,
+        initial_predators: usize,
+        initial_predator_energy: f32,
+        initial_predator_speed: f32,
+        initial_predator_size: f32,
+        initial_predator_reproduction_threshold: f32,
+        initial_predator_hunting_efficiency: f32,
+        initial_predator_satiation_threshold: f32,
+        predator_mutability: f32,
+        overcrowding_threshold_for_predators: usize,
+        max_predator_energy: f32,
+        predator_energy_decay_rate: f32,
+        predator_reproduction_cooldown: f32,
+        predator_seek_radius: usize,
+        initial_predator_preferred_temperature: f32,
+        predator_thermoregulation_cost: f32,
+        hunt_cooldown_ticks: usize,
+        hunt_cooldown_evolvable: bool,
+        predator_maturity_age: usize,
+        juvenile_factor: f32,
+        min_kills_for_reproduction: usize,
+        kill_memory_ticks: usize,
+        carrion: CarrionConfig,
+        kill_scent: KillScentConfig,
+        ambush: AmbushConfig,
+        territory: TerritoryConfig,
+        wander: WanderConfig,
+        predator_breeding: BreedingConfig,
+    }
+    logging {
+        log_data: bool,
+        log_interval: usize,
+        heritability_interval: usize,
+        heritability_window: usize,
+        stats_history_cap: usize,
+        spatial_interval: usize,
+        spatial_sample: usize,
+        variance_interval: usize,
+        export_population: Option<PathBuf>,
+        clamp_warning_fraction: f32,
+        sample_individuals_per_generation: usize,
+        percentile_individuals: Vec<PercentileRank>,
+        output_dir: Option<PathBuf>,
+        watchdog_timeout_seconds: Option<f32>,
+        watchdog_action: WatchdogAction,
+        trace_entity: Option<u64>,
+    }
+}
+
+impl ConfigBuilder {
+    /// Sets the food and upkeep settings of one biome.
+    pub fn biome_data(mut self, biome: Biome, data: BiomeDataConfig) -> Self {
+        let slot = match biome {
+            Biome::Forest => &mut self.config.world.forest,
+            Biome::Desert => &mut self.config.world.desert,
+            Biome::Water => &mut self.config.world.water,
+            Biome::Grassland => &mut self.config.world.grassland,
+        };
+        *slot = data;
+        self
+    }
+
+    /// Adds a second organism species.
+    pub fn species_b(mut self, species: SpeciesConfig) -> Self {
+        self.config.species_b = Some(species);
+        self
+    }
+
+    pub fn build(self) -> Result<Config, Vec<ConfigError>> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provenance::RunId;
+
+    #[test]
+    fn defaulted_fields_are_found_and_cosmetic_ones_do_not_warn() {
+        let full = default_config().to_toml_string().unwrap();
+        let toml: String = full
+            .lines()
+            .filter(|line| {
+                ![
+                    "boldness_exposure = ",
+                    "shoreline_grazing_rate = ",
+                    "spatial_sample = ",
+                ]
+                .iter()
+                .any(|key| line.starts_with(key))
+            })
+            .map(|line| format!("{}\n", line))
+            .collect();
+        let raw: toml::Table = toml.parse().unwrap();
+        let config = Config::from_toml_str(&toml).unwrap();
+
+        let defaulted = DefaultedFields::find(&raw, &config);
+        assert_eq!(
+            defaulted.0,
+            [
+                "boldness_exposure",
+                "shoreline_grazing_rate",
+                "spatial_sample"
+            ]
+        );
+        assert_eq!(
+            defaulted.dynamic().collect::<Vec<_>>(),
+            ["boldness_exposure", "shoreline_grazing_rate"]
+        );
+        let warning = defaulted.warning().unwrap();
+        assert!(warning.starts_with("2 simulation settings"));
+        assert!(!warning.contains("spatial_sample"));
+
+        let full_raw: toml::Table = full.parse().unwrap();
+        assert_eq!(
+            DefaultedFields::find(&full_raw, &default_config()),
+            DefaultedFields::default()
+        );
+        let cosmetic = DefaultedFields(vec!["display.entity_scale".into(), "printing".into()]);
+        assert_eq!(cosmetic.warning(), None);
+    }
+
+    #[test]
+    fn builder_defaults_match_default_config() {
+        assert_eq!(Config::builder().build().unwrap(), default_config());
+    }
+
+    #[test]
+    fn builder_sets_top_level_and_nested_fields() {
+        let desert = BiomeDataConfig {
+            food_availability: 0.0,
+            max_food_availability: 5.0,
+            predator_decay_factor: 2.0,
+            food_nutrition: 0.5,
+            predator_vision: 0.7,
+            predator_catch: 1.2,
+            passive_energy_delta: Some(-0.3),
+            organism_movement_cost: None,
+            predator_movement_cost: None,
+        };
+        let config = Config::builder()
+            .width(64)
+            .initial_predators(7)
+            .log_interval(10)
+            .biome_data(Biome::Desert, desert.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(config.world.width, 64);
+        assert_eq!(config.predator.initial_predators, 7);
+        assert_eq!(config.logging.log_interval, 10);
+        assert_eq!(config.world.desert, desert);
+    }
+
+    #[test]
+    fn printing_and_headless_pick_the_log_level_unless_it_is_set() {
+        let verbosity = |printing, headless, log_level| {
+            Config::builder()
+                .printing(printing)
+                .headless(headless)
+                .log_level(log_level)
+                .build()
+                .unwrap()
+                .world
+                .verbosity()
+        };
+        assert_eq!(verbosity(true, true, None), LogLevel::Debug);
+        assert_eq!(verbosity(false, true, None), LogLevel::Info);
+        assert_eq!(verbosity(false, false, None), LogLevel::Warn);
+        assert_eq!(
+            verbosity(true, false, Some(LogLevel::Error)),
+            LogLevel::Error
+        );
+
+        let config: Config = toml::from_str(&default_config().to_toml_string().unwrap().replace(
+            "printing = false",
+            "printing = false\nlog_level = \"debug\"",
+        ))
+        .unwrap();
+        assert_eq!(config.world.log_level, Some(LogLevel::Debug));
+    }
+
+    #[test]
+    fn build_reports_every_validation_failure() {
+        let errors = Config::builder()
+            .width(0)
+            .organism_mutability(-1.0)
+            .catastrophes(CatastropheConfig {
+                min_radius: 9,
+                max_radius: 2,
+                ..Default::default()
+            })
+            .build()
+            .unwrap_err();
+
+        let fields: Vec<_> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            ["width", "catastrophes.min_radius", "organism_mutability"]
+        );
+    }
+
+    #[test]
+    fn dimensions_mutability_and_entity_counts_are_checked() {
+        let fields = |config: Config| -> Vec<String> {
+            let errors = config.validate().unwrap_err();
+            errors.into_iter().map(|error| error.field).collect()
+        };
+
+        let mut config = default_config();
+        config.world.height = 0;
+        assert_eq!(fields(config), ["width"]);
+
+        let mut config = default_config();
+        config.organism.organism_mutability = 1.5;
+        config.predator.predator_mutability = -0.1;
+        assert_eq!(
+            fields(config),
+            ["organism_mutability", "predator_mutability"]
+        );
+        let mut config = default_config();
+        config.organism.organism_mutability = 1.0;
+        assert_eq!(config.validate(), Ok(()));
+
+        let mut config = default_config();
+        config.world.max_total_entities = 10;
+        config.organism.initial_organisms = 11;
+        assert_eq!(fields(config.clone()), ["initial_organisms"]);
+        // predators past the cap are culled rather than refused
+        config.organism.initial_organisms = 10;
+        config.predator.initial_predators = 2;
+        assert_eq!(config.validate(), Ok(()));
+
+        let mut config = default_config();
+        config.world.generation_limit = Some(0);
+        config.organism.overcrowding_threshold_for_organisms = 0;
+        config.predator.overcrowding_threshold_for_predators = 0;
+        assert_eq!(
+            fields(config),
+            [
+                "generation_limit",
+                "overcrowding_threshold_for_organisms",
+                "overcrowding_threshold_for_predators"
+            ]
+        );
+    }
+
+    #[test]
+    fn prey_only_runs_reject_predator_settings() {
+        let mut config = default_config();
+        config.predator.predators_enabled = false;
+        config.predator.ambush.enabled = true;
+        let fields: Vec<_> = config
+            .validate()
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.field)
+            .collect();
+        assert_eq!(fields, ["initial_predators", "ambush.enabled"]);
+
+        config.predator.initial_predators = 0;
+        config.predator.ambush.enabled = false;
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn a_loaded_config_reports_every_violation_together() {
+        let toml = default_config().to_toml_string().unwrap();
+        let overrides = ["width=0", "organism_mutability=-1.0"].map(String::from);
+        let err = layer_config(None, Some(&toml), &overrides)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "2 invalid config values:\n  width: the world must not be empty, got 0x10\n  \
+             organism_mutability: must be between 0 and 1, got -1"
+        );
+    }
+
+    #[test]
+    fn non_positive_food_conversion_is_rejected() {
+        let mut forest = default_config().world.forest;
+        forest.food_nutrition = 0.0;
+        let errors = Config::builder()
+            .food_energy_conversion(-2.0)
+            .forest(forest)
+            .build()
+            .unwrap_err();
+
+        let fields: Vec<_> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, ["forest.food_nutrition", "food_energy_conversion"]);
+    }
+
+    #[test]
+    fn batches_need_a_generation_limit_and_no_window() {
+        let fields = |builder: ConfigBuilder| -> Vec<String> {
+            let errors = builder.build().unwrap_err();
+            errors.into_iter().map(|error| error.field).collect()
+        };
+
+        let batch = || Config::builder().headless(true).seeds(vec![1, 2]);
+        assert_eq!(fields(batch()), ["generation_limit"]);
+        assert_eq!(
+            fields(batch().replicates(3).headless(false)),
+            ["replicates", "generation_limit", "seeds"]
+        );
+        assert!(batch().generation_limit(Some(10)).build().is_ok());
+        assert!(Config::builder()
+            .headless(true)
+            .replicates(3)
+            .generation_limit(Some(10))
+            .build()
+            .is_ok());
+
+        // every seed a batch runs is written to its effective config as well
+        let batch = || batch().generation_limit(Some(10));
+        assert_eq!(fields(batch().seeds(vec![1, MAX_SEED + 1])), ["seeds"]);
+        let replicates = |seed| batch().seeds(Vec::new()).seed(seed).replicates(3);
+        assert_eq!(fields(replicates(MAX_SEED - 1)), ["replicates"]);
+        assert!(replicates(MAX_SEED - 2).build().is_ok());
+    }
+
+    #[test]
+    fn toml_round_trip_preserves_every_field() {
+        let config = Config::builder()
+            .generation_limit(Some(250))
+            .biome_model(BiomeModel::Whittaker)
+            .hunt_cooldown_ticks(3)
+            .build()
+            .unwrap();
+
+        let toml = config.to_toml_string().unwrap();
+        assert_eq!(Config::from_toml_str(&toml).unwrap(), config);
+    }
+
+    #[test]
+    fn saved_config_loads_back_equal() {
+        let config = Config::builder()
+            .generation_limit(None)
+            .screenshot_interval(Some(50))
+            .export_population(Some(PathBuf::from("population.json")))
+            .species_b(SpeciesConfig {
+                initial_organisms: 5,
+                initial_organism_size: Some(0.6),
+                ..SpeciesConfig::default()
+            })
+            .build()
+            .unwrap();
+        let path = std::env::temp_dir().join("evolution_effective_config.toml");
+        let provenance = Provenance::new(&RunId("1-0".into()), 1, 0, None);
+        config.save(&path, &provenance).unwrap();
+
+        let (loaded, defaulted) = crate::utils::load_config(Some(&path)).unwrap();
+        assert_eq!(Provenance::read(&path).unwrap(), Some(provenance));
+        std::fs::remove_file(&path).ok();
+        assert_eq!(loaded, config);
+        // unset optional fields are left out rather than defaulted
+        assert!(defaulted.dynamic().next().is_none(), "{:?}", defaulted);
+    }
+
+    #[test]
+    fn a_missing_config_file_is_named_in_the_error() {
+        let err = crate::utils::load_config(Some(Path::new("experiments/missing.toml")))
+            .unwrap_err()
+            .to_string();
+        let attempted = std::env::current_dir()
+            .unwrap()
+            .join("experiments/missing.toml");
+        assert!(
+            err.starts_with(&format!("cannot read {}: ", attempted.display())),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn sample_config_is_valid() {
+        let config = Config::from_toml_str(include_str!("../config.toml")).unwrap();
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn a_minimal_config_takes_the_defaults_for_everything_else() {
+        let config = Config::from_toml_str("width = 10\nheight = 10\nseed = 0\n").unwrap();
+        assert_eq!(config, default_config());
+
+        let config = Config::from_toml_str("width = 30\nheight = 20\nseed = 7\n").unwrap();
+        assert_eq!((config.world.width, config.world.height), (30, 20));
+        assert_eq!(config.world.seed, 7);
+        assert_eq!(config.organism, default_config().organism);
+        assert_eq!(config.predator, default_config().predator);
+        assert_eq!(config.logging, default_config().logging);
+
+        // a biome table that is there but partial keeps that biome's defaults
+        let config = Config::from_toml_str(
+            "width = 10\nheight = 10\nseed = 0\n[desert]\nfood_nutrition = 0.5\n",
+        )
+        .unwrap();
+        let (world, defaults) = (&config.world, &default_config().world);
+        assert_eq!(world.desert.food_nutrition, 0.5);
+        assert_eq!(
+            world.desert.food_availability,
+            defaults.desert.food_availability
+        );
+        assert_eq!(
+            world.passive_energy_delta(Biome::Desert),
+            defaults.passive_energy_delta(Biome::Desert)
+        );
+        assert_eq!(
+            world.organism_movement_cost(Biome::Desert),
+            defaults.organism_movement_cost(Biome::Desert)
+        );
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn species_b_overrides_only_the_traits_it_sets() {
+        let mut toml = include_str!("../config.toml").to_string();
+        toml.push_str("\n[species_b]\ninitial_organisms = 7\ninitial_organism_size = 0.4\n");
+        let config = Config::from_toml_str(&toml).unwrap();
+        assert_eq!(config.species(), [SpeciesId::A, SpeciesId::B]);
+
+        let species_b = config.species_organism(SpeciesId::B);
+        assert_eq!(species_b.initial_organisms, 7);
+        assert_eq!(species_b.initial_organism_size, 0.4);
+        assert_eq!(
+            species_b.initial_organism_speed,
+            config.organism.initial_organism_speed
+        );
+        assert_eq!(*config.species_organism(SpeciesId::A), config.organism);
+
+        let errors = Config::builder()
+            .species_b(SpeciesConfig {
+                initial_organism_boldness: Some(1.5),
+                ..SpeciesConfig::default()
+            })
+            .build()
+            .unwrap_err();
+        let fields: Vec<_> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, ["species_b.initial_organism_boldness"]);
+    }
+
+    fn overcrowded(policy: DensityPolicy) -> Config {
+        Config::builder()
+            .initial_organisms(5000)
+            .initial_predators(100)
+            .max_total_entities(10_000)
+            .density_policy(policy)
+            .max_initial_density(5.0)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn density_policy_allows_errors_or_caps_crowded_worlds() {
+        let mut config = overcrowded(DensityPolicy::Allow);
+        assert_eq!(config.apply_density_policy(80), Ok(None));
+        assert_eq!(config.organism.initial_organisms, 5000);
+
+        let mut config = overcrowded(DensityPolicy::Error);
+        let error = config.apply_density_policy(80).unwrap_err();
+        assert_eq!(error.field, "max_initial_density");
+        assert_eq!(config.apply_density_policy(2000), Ok(None));
+
+        let mut config = overcrowded(DensityPolicy::Cap);
+        let adjustment = config.apply_density_policy(80).unwrap().unwrap();
+        assert_eq!((adjustment.organisms, adjustment.predators), (392, 8));
+        assert_eq!(adjustment.requested_organisms, 5000);
+        assert_eq!(config.organism.initial_organisms, 392);
+        assert_eq!(config.predator.initial_predators, 8);
+        assert!(config.initial_density(80) <= 5.0);
+    }
+
+    #[test]
+    fn capping_keeps_a_predator_when_there_were_any() {
+        let mut config = overcrowded(DensityPolicy::Cap);
+        config.predator.initial_predators = 1;
+        let adjustment = config.apply_density_policy(4).unwrap().unwrap();
+        assert_eq!((adjustment.organisms, adjustment.predators), (19, 1));
+    }
+
+    #[test]
+    fn overrides_parse_into_nested_tables() {
+        let table = parse_override("ambush.enabled=true").unwrap();
+        assert_eq!(table["ambush"]["enabled"], toml::Value::Boolean(true));
+        assert_eq!(
+            parse_override("width = 32").unwrap()["width"].as_integer(),
+            Some(32)
+        );
+        assert_eq!(
+            parse_override("biome_model=whittaker").unwrap()["biome_model"].as_str(),
+            Some("whittaker")
+        );
+        assert!(parse_override("width").is_err());
+        assert!(parse_override("ambush..enabled=true").is_err());
+    }
+
+    #[test]
+    fn presets_files_and_overrides_layer_in_order() {
+        let preset = crate::presets::find("balanced").unwrap();
+        let (config, _) = layer_config(Some(preset), None, &[]).unwrap();
+        assert_eq!(
+            (config.world.width, config.organism.initial_organisms),
+            (60, 200)
+        );
+        assert_eq!(config.world.seed, default_config().world.seed);
+
+        // the file wins over the preset, and keeps what it leaves out of it
+        let file = "initial_organisms = 50\nseed = 9\n[ambush]\nenabled = true\n";
+        let (config, _) = layer_config(Some(preset), Some(file), &[]).unwrap();
+        assert_eq!(
+            (config.world.width, config.organism.initial_organisms),
+            (60, 50)
+        );
+        assert_eq!(config.world.seed, 9);
+        assert!(config.predator.ambush.enabled);
+        assert_eq!(config.predator.ambush.decay_factor, 0.2);
+
+        // the command line wins over both
+        let overrides = ["seed=11".to_string(), "ambush.decay_factor=0.5".to_string()];
+        let (config, _) = layer_config(Some(preset), Some(file), &overrides).unwrap();
+        assert_eq!(config.organism.initial_organisms, 50);
+        assert_eq!(config.world.seed, 11);
+        assert!(config.predator.ambush.enabled);
+        assert_eq!(config.predator.ambush.decay_factor, 0.5);
+    }
+
+    #[test]
+    fn shorthand_flags_win_over_the_file_and_set() {
+        let args: Vec<String> =
+            "--config a.toml --set seed=3 --seed 42 --generations 50 --headless"
+                .split_whitespace()
+                .map(String::from)
+                .collect();
+        let flags = ConfigFlags::from_args(&args).unwrap();
+        assert_eq!(
+            flags,
+            ConfigFlags {
+                seed: Some(42),
+                generations: Some(50),
+                headless: true,
+            }
+        );
+
+        // set before the checks, so a GUI config still loads headless without one
+        let file = "width = 8\nheight = 8\nseed = 9\nheadless = false\ngeneration_limit = 1000\n";
+        let overrides = ["seed=3".to_string()];
+        let (config, defaulted) =
+            layer_config_with_env(None, Some(file), &[], &overrides, &flags).unwrap();
+        assert_eq!(config.world.seed, 42);
+        assert_eq!(config.world.generation_limit, Some(50));
+        assert!(config.world.headless);
+        assert!(!defaulted.0.contains(&"headless".to_string()));
+
+        // a seed past what a TOML integer holds could not be written back out
+        let seed = ConfigFlags::from_args(&["--seed".to_string(), u64::MAX.to_string()]).unwrap();
+        let flags = ConfigFlags {
+            seed: seed.seed,
+            ..flags
+        };
+        let err = layer_config_with_env(None, Some(file), &[], &[], &flags).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "1 invalid config value:\n  seed: must be at most {}, got {}",
+                MAX_SEED,
+                u64::MAX
+            )
+        );
+        let flags = ConfigFlags {
+            seed: Some(MAX_SEED),
+            ..flags
+        };
+        let (config, _) = layer_config_with_env(None, Some(file), &[], &[], &flags).unwrap();
+        assert_eq!(config.world.seed, MAX_SEED);
+
+        let err = ConfigFlags::from_args(&["--seed".to_string(), "-1".to_string()]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--seed: must be a non-negative integer, got `-1`"
+        );
+        assert!(ConfigFlags::from_args(&["--generations".to_string()]).is_err());
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn environment_variables_name_top_level_and_nested_fields() {
+        let env = env_overrides(vars(&[
+            ("PATH", "/usr/bin"),
+            ("ECA_SEED", "42"),
+            ("ECA_ORGANISM_MUTABILITY", "0.3"),
+            ("ECA_FOREST_FOOD_AVAILABILITY", "2.0"),
+            ("ECA_PANIC_RADIUS", "4"),
+            ("ECA_GENERATION_LIMIT", "300"),
+        ]))
+        .unwrap();
+        let keys: Vec<_> = env.iter().map(|var| var.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "forest.food_availability",
+                "generation_limit",
+                "organism_mutability",
+                "panic.radius",
+                "seed"
+            ]
+        );
+
+        let file = default_config().to_toml_string().unwrap();
+        let (config, _) =
+            layer_config_with_env(None, Some(&file), &env, &[], &ConfigFlags::default()).unwrap();
+        assert_eq!(config.world.seed, 42);
+        assert_eq!(config.organism.organism_mutability, 0.3);
+        assert_eq!(config.world.forest.food_availability, 2.0);
+        assert_eq!(config.organism.panic.radius, 4);
+        assert_eq!(config.world.generation_limit, Some(300));
+
+        // the command line wins over the environment
+        let (config, _) = layer_config_with_env(
+            None,
+            Some(&file),
+            &env,
+            &["seed=7".into()],
+            &ConfigFlags::default(),
+        )
+        .unwrap();
+        assert_eq!(config.world.seed, 7);
+    }
+
+    #[test]
+    fn bad_environment_variables_are_named_in_the_error() {
+        let file = default_config().to_toml_string().unwrap();
+        let err = |pairs: &[(&str, &str)]| {
+            let env = env_overrides(vars(pairs)).unwrap();
+            layer_config_with_env(None, Some(&file), &env, &[], &ConfigFlags::default())
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert!(err(&[("ECA_SEED", "forty")]).starts_with("ECA_SEED: "));
+        assert_eq!(
+            err(&[("ECA_ORGANISM_MUTABILITY", "1.5")]),
+            "1 invalid config value:\n  ECA_ORGANISM_MUTABILITY (organism_mutability): \
+             must be between 0 and 1, got 1.5"
+        );
+        assert_eq!(
+            err(&[("ECA_ORGANISM_MUTABILTY", "0.3")]),
+            "ECA_ORGANISM_MUTABILTY: names no config field"
+        );
+    }
+
+    #[test]
+    fn without_a_preset_the_file_stays_the_base() {
+        let full = default_config().to_toml_string().unwrap();
+        let (config, defaulted) = layer_config(None, Some(&full), &["seed=3".into()]).unwrap();
+        assert_eq!(config.world.seed, 3);
+        assert_eq!(config.world.width, 10);
+        assert_eq!(defaulted, DefaultedFields::default());
+        assert!(layer_config(None, Some("width = 10"), &[]).is_err());
+    }
+}
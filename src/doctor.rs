@@ -0,0 +1,518 @@
+//! Quick check of a config for the usual ways a run goes wrong, started with
+//! `evolution doctor [--generations n] [--seconds s]`. A short headless burn fills
+//! the [`StatsHistory`] and [`diagnose`] reads it for signs of trouble, naming the
+//! config fields most likely behind each.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use bevy::app::PluginsState;
+
+use crate::app::try_build_app;
+use crate::bench::bench_config;
+use crate::config::ConfigError;
+use crate::resources::{Biome, Config, Generation, PopulationCount, StatsHistory};
+use crate::schema::GenerationStats;
+
+/// Predators dying out before this generation are taken for a config that cannot
+/// feed them, rather than the prey having evolved away from them.
+pub const PREDATOR_EXTINCTION_GENERATION: u32 = 200;
+/// Share of `max_total_entities` from which the population counts as at the cap.
+const CAP_SHARE: f32 = 0.95;
+/// Share of the highest `max_food_availability` from which food counts as at its
+/// maximum.
+const FOOD_SHARE: f32 = 0.9;
+/// Share of the predators ready to reproduce that `min_kills_for_reproduction` may
+/// hold back before it is the reason they do not.
+const BLOCKED_RATIO: f32 = 0.5;
+/// Share of the newborns with a trait clamped at a bound from which the bounds,
+/// rather than selection, shape that trait.
+const CLAMP_RATIO: f32 = 0.2;
+/// Fewest births a ratio is judged on.
+const MIN_BIRTHS: usize = 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoctorOptions {
+    /// Generations the burn runs for at most.
+    pub generations: usize,
+    /// Wall-clock budget of the burn, which ends at whichever limit comes first.
+    pub seconds: f64,
+}
+
+impl Default for DoctorOptions {
+    fn default() -> Self {
+        Self {
+            generations: 300,
+            seconds: 30.0,
+        }
+    }
+}
+
+impl DoctorOptions {
+    /// Parses the arguments following `doctor`.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--generations" => {
+                    let value = args.next().ok_or("missing value for --generations")?;
+                    options.generations = value
+                        .parse()
+                        .ok()
+                        .filter(|generations| *generations > 0)
+                        .ok_or_else(|| {
+                            format!("--generations must be a positive integer, got `{}`", value)
+                        })?;
+                }
+                "--seconds" => {
+                    let value = args.next().ok_or("missing value for --seconds")?;
+                    options.seconds = value
+                        .parse()
+                        .ok()
+                        .filter(|seconds: &f64| seconds.is_finite() && *seconds > 0.0)
+                        .ok_or_else(|| {
+                            format!("--seconds must be a positive number, got `{}`", value)
+                        })?;
+                }
+                // read by `main` before the burn starts
+                "--config" | "--seed" => {
+                    args.next()
+                        .ok_or_else(|| format!("missing value for {}", arg))?;
+                }
+                "--headless" => {}
+                other => return Err(format!("unexpected argument `{}`", other)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Something the burn suggests is wrong with the config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub message: String,
+    /// Config fields most likely responsible, as `--set` names them.
+    pub fields: Vec<String>,
+}
+
+impl Warning {
+    fn new(message: String, fields: &[&str]) -> Self {
+        Self {
+            message,
+            fields: fields.iter().map(|field| field.to_string()).collect(),
+        }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "warning: {}", self.message)?;
+        if !self.fields.is_empty() {
+            write!(f, "\n  look at: {}", self.fields.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoctorReport {
+    pub generations: usize,
+    pub seconds: f64,
+    pub warnings: Vec<Warning>,
+}
+
+impl Display for DoctorReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ran {} generations in {:.1}s",
+            self.generations, self.seconds
+        )?;
+        if self.warnings.is_empty() {
+            return write!(f, "\nno warnings");
+        }
+        for warning in &self.warnings {
+            write!(f, "\n{}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+/// Burns [`bench_config`] of `config` for as long as `options` allow, or until
+/// both populations are gone, and diagnoses the stats it kept.
+pub fn run(config: &Config, options: &DoctorOptions) -> Result<DoctorReport, ConfigError> {
+    let mut app = try_build_app(bench_config(config))?;
+    if app.plugins_state() != PluginsState::Cleaned {
+        app.finish();
+        app.cleanup();
+    }
+
+    let budget = Duration::from_secs_f64(options.seconds);
+    let started = Instant::now();
+    while app.world().resource::<Generation>().0 < options.generations && started.elapsed() < budget
+    {
+        app.update();
+        let pop = app.world().resource::<PopulationCount>();
+        if pop.organisms + pop.predators == 0 {
+            break;
+        }
+    }
+
+    Ok(DoctorReport {
+        generations: app.world().resource::<Generation>().0,
+        seconds: started.elapsed().as_secs_f64(),
+        warnings: diagnose(app.world().resource::<StatsHistory>(), config),
+    })
+}
+
+/// Warnings for the run `history` kept, in a fixed order: extinctions, the entity
+/// cap, food, predator reproduction and then clamped traits by name.
+pub fn diagnose(history: &StatsHistory, config: &Config) -> Vec<Warning> {
+    let records: Vec<&GenerationStats> = history.records().collect();
+    let mut warnings = Vec::new();
+    if records.is_empty() {
+        return warnings;
+    }
+    // the second half, once the populations have left their starting values
+    let settled = &records[records.len() / 2..];
+
+    if let Some(generation) = first_extinction(&records, |stats| stats.organism_count) {
+        warnings.push(Warning::new(
+            format!("organisms died out at generation {}", generation),
+            &[
+                "initial_organisms",
+                "food_energy_conversion",
+                "initial_organism_reproduction_threshold",
+                "initial_predators",
+            ],
+        ));
+    }
+
+    let hunted = config.predator.predators_enabled && config.predator.initial_predators > 0;
+    if let Some(generation) = first_extinction(&records, GenerationStats::predator_count)
+        .filter(|generation| hunted && *generation < PREDATOR_EXTINCTION_GENERATION)
+    {
+        warnings.push(Warning::new(
+            format!(
+                "predators died out at generation {}, before generation {}",
+                generation, PREDATOR_EXTINCTION_GENERATION
+            ),
+            &[
+                "initial_predators",
+                "predator_energy_decay_rate",
+                "initial_predator_hunting_efficiency",
+                "initial_predator_reproduction_threshold",
+                "min_kills_for_reproduction",
+            ],
+        ));
+    }
+
+    let cap = config.world.max_total_entities as f32 * CAP_SHARE;
+    if mostly(settled, |stats| {
+        (stats.organism_count + stats.predator_count()) as f32 >= cap
+    }) {
+        warnings.push(Warning::new(
+            format!(
+                "the population stays at the cap of {} individuals",
+                config.world.max_total_entities
+            ),
+            &[
+                "max_total_entities",
+                "overcrowding_threshold_for_organisms",
+                "organism_reproduction_cooldown",
+            ],
+        ));
+    }
+
+    // tiles of a biome with a lower maximum never reach this, so a map of mixed
+    // biomes is only caught when it is pinned on every tile
+    let growing: Vec<(String, f32)> = Biome::ALL
+        .into_iter()
+        .map(|biome| (biome.to_string().to_lowercase(), config.world.biome(biome)))
+        .filter(|(_, data)| data.food_availability > 0.0)
+        .map(|(name, data)| (name, data.max_food_availability))
+        .collect();
+    let ceiling = growing.iter().map(|(_, max)| *max).fold(0.0, f32::max);
+    if ceiling > 0.0 && mostly(settled, |stats| stats.average_food >= ceiling * FOOD_SHARE) {
+        let fields: Vec<String> = growing
+            .iter()
+            .map(|(name, _)| format!("{}.food_availability", name))
+            .chain(["initial_organisms".to_string()])
+            .collect();
+        warnings.push(Warning {
+            message: format!(
+                "average food stays at its maximum of {}, food regrows faster than it is eaten",
+                ceiling
+            ),
+            fields,
+        });
+    }
+
+    let blocked: usize = records
+        .iter()
+        .filter_map(|stats| stats.predators.as_ref())
+        .map(|predators| predators.predator_births_blocked)
+        .sum();
+    let predator_births: usize = records
+        .iter()
+        .filter_map(|stats| stats.predators.as_ref())
+        .map(|predators| predators.predator_births)
+        .sum();
+    let ready = blocked + predator_births;
+    if ready >= MIN_BIRTHS && blocked as f32 > ready as f32 * BLOCKED_RATIO {
+        warnings.push(Warning::new(
+            format!(
+                "{:.0}% of the predators ready to reproduce had too few recent kills",
+                blocked as f32 / ready as f32 * 100.0
+            ),
+            &["min_kills_for_reproduction", "kill_memory_ticks"],
+        ));
+    }
+
+    let organism_births: usize = records.iter().map(|stats| stats.organism_births).sum();
+    let mut hits: BTreeMap<&str, usize> = BTreeMap::new();
+    for stats in &records {
+        for (key, (low, high)) in &stats.clamp_hits {
+            *hits.entry(key.as_str()).or_insert(0) += low + high;
+        }
+    }
+    for (key, count) in hits {
+        let (births, mutability) = match key.starts_with("predator.") {
+            true => (predator_births, "predator_mutability"),
+            false => (organism_births, "organism_mutability"),
+        };
+        if births >= MIN_BIRTHS && count as f32 > births as f32 * CLAMP_RATIO {
+            warnings.push(Warning::new(
+                format!(
+                    "{:.0}% of the newborns had {} clamped at a bound",
+                    count as f32 / births as f32 * 100.0,
+                    key
+                ),
+                &[mutability],
+            ));
+        }
+    }
+
+    warnings
+}
+
+/// Generation of the first record where a population that was alive is gone.
+fn first_extinction(
+    records: &[&GenerationStats],
+    count: impl Fn(&GenerationStats) -> usize,
+) -> Option<u32> {
+    records
+        .windows(2)
+        .find(|pair| count(pair[0]) > 0 && count(pair[1]) == 0)
+        .map(|pair| pair[1].generation)
+}
+
+/// Whether at least four in five of `records` satisfy `condition`.
+fn mostly(records: &[&GenerationStats], condition: impl Fn(&GenerationStats) -> bool) -> bool {
+    let count = records.iter().filter(|stats| condition(stats)).count();
+    !records.is_empty() && count * 5 >= records.len() * 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::PredatorStats;
+    use crate::utils::default_config;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn config() -> Config {
+        let mut config = default_config();
+        config.world.max_total_entities = 1_000;
+        config.predator.initial_predators = 5;
+        config
+    }
+
+    /// A history of `generations` healthy generations, each passed through `edit`.
+    fn history(generations: u32, edit: impl Fn(u32, &mut GenerationStats)) -> StatsHistory {
+        let mut history = StatsHistory::new(1_000);
+        for generation in 0..generations {
+            let mut stats = GenerationStats {
+                generation,
+                organism_count: 300,
+                predators: Some(PredatorStats {
+                    predator_count: 20,
+                    predator_births: 2,
+                    ..PredatorStats::default()
+                }),
+                organism_births: 10,
+                average_food: 20.0,
+                ..GenerationStats::default()
+            };
+            edit(generation, &mut stats);
+            history.push(stats);
+        }
+        history
+    }
+
+    fn messages(warnings: &[Warning]) -> Vec<&str> {
+        warnings
+            .iter()
+            .map(|warning| warning.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn options_parse_generations_and_seconds() {
+        assert_eq!(DoctorOptions::from_args(&[]), Ok(DoctorOptions::default()));
+
+        let options =
+            DoctorOptions::from_args(&args("--generations 50 --seconds 2.5 --config a.toml"))
+                .unwrap();
+        assert_eq!(options.generations, 50);
+        assert_eq!(options.seconds, 2.5);
+
+        assert!(DoctorOptions::from_args(&args("--generations 0")).is_err());
+        assert!(DoctorOptions::from_args(&args("--seconds")).is_err());
+        assert!(DoctorOptions::from_args(&args("--json")).is_err());
+    }
+
+    #[test]
+    fn a_healthy_run_gets_no_warnings() {
+        assert!(diagnose(&history(300, |_, _| {}), &config()).is_empty());
+        assert!(diagnose(&StatsHistory::new(10), &config()).is_empty());
+    }
+
+    #[test]
+    fn early_predator_extinction_is_flagged() {
+        let history = history(300, |generation, stats| {
+            if generation >= 120 {
+                stats.predators.as_mut().unwrap().predator_count = 0;
+            }
+        });
+        let warnings = diagnose(&history, &config());
+        assert_eq!(
+            messages(&warnings),
+            ["predators died out at generation 120, before generation 200"]
+        );
+        assert!(warnings[0]
+            .fields
+            .contains(&"predator_energy_decay_rate".to_string()));
+
+        // dying out later, or in a run that starts without them, is not a config problem
+        let late = self::history(300, |generation, stats| {
+            if generation >= 250 {
+                stats.predators.as_mut().unwrap().predator_count = 0;
+            }
+        });
+        assert!(diagnose(&late, &config()).is_empty());
+        let mut unhunted = config();
+        unhunted.predator.initial_predators = 0;
+        assert!(diagnose(&history, &unhunted).is_empty());
+    }
+
+    #[test]
+    fn organism_extinction_is_flagged() {
+        let history = history(100, |generation, stats| {
+            if generation >= 40 {
+                stats.organism_count = 0;
+            }
+        });
+        let warnings = diagnose(&history, &config());
+        assert_eq!(messages(&warnings), ["organisms died out at generation 40"]);
+        assert!(warnings[0]
+            .fields
+            .contains(&"initial_organisms".to_string()));
+    }
+
+    #[test]
+    fn a_population_pinned_at_the_cap_is_flagged() {
+        let history = history(300, |generation, stats| {
+            if generation >= 100 {
+                stats.organism_count = 980;
+            }
+        });
+        let warnings = diagnose(&history, &config());
+        assert_eq!(
+            messages(&warnings),
+            ["the population stays at the cap of 1000 individuals"]
+        );
+        assert_eq!(warnings[0].fields[0], "max_total_entities");
+
+        // reaching it now and then is fine
+        let brushing = self::history(300, |generation, stats| {
+            if generation % 3 == 0 {
+                stats.organism_count = 980;
+            }
+        });
+        assert!(diagnose(&brushing, &config()).is_empty());
+    }
+
+    #[test]
+    fn food_pinned_at_its_maximum_is_flagged() {
+        let mut config = config();
+        config.world.forest.max_food_availability = 50.0;
+        config.world.desert.max_food_availability = 50.0;
+        config.world.grassland.max_food_availability = 50.0;
+        config.world.water.food_availability = 0.0;
+        let history = history(300, |_, stats| stats.average_food = 49.0);
+        let warnings = diagnose(&history, &config);
+        assert_eq!(
+            messages(&warnings),
+            ["average food stays at its maximum of 50, food regrows faster than it is eaten"]
+        );
+        assert!(warnings[0]
+            .fields
+            .contains(&"forest.food_availability".to_string()));
+        assert!(!warnings[0]
+            .fields
+            .contains(&"water.food_availability".to_string()));
+    }
+
+    #[test]
+    fn predators_held_back_by_too_few_kills_are_flagged() {
+        let history = history(100, |_, stats| {
+            stats.predators.as_mut().unwrap().predator_births_blocked = 6;
+        });
+        let warnings = diagnose(&history, &config());
+        assert_eq!(
+            messages(&warnings),
+            ["75% of the predators ready to reproduce had too few recent kills"]
+        );
+        assert_eq!(warnings[0].fields[0], "min_kills_for_reproduction");
+    }
+
+    #[test]
+    fn traits_often_clamped_are_flagged_with_their_mutability() {
+        let history = history(100, |_, stats| {
+            stats.clamp_hits.insert("organism.size".into(), (4, 1));
+            stats.clamp_hits.insert("organism.boldness".into(), (0, 1));
+            stats.clamp_hits.insert("predator.size".into(), (0, 1));
+        });
+        let warnings = diagnose(&history, &config());
+        assert_eq!(
+            messages(&warnings),
+            [
+                "50% of the newborns had organism.size clamped at a bound",
+                "50% of the newborns had predator.size clamped at a bound",
+            ]
+        );
+        assert_eq!(warnings[0].fields, ["organism_mutability"]);
+        assert_eq!(warnings[1].fields, ["predator_mutability"]);
+    }
+
+    #[test]
+    fn a_short_burn_reports_on_the_real_run() {
+        let mut config = default_config();
+        config.world.width = 10;
+        config.world.height = 10;
+        config.organism.initial_organisms = 20;
+        config.predator.initial_predators = 2;
+        let options = DoctorOptions {
+            generations: 5,
+            seconds: 30.0,
+        };
+        let report = run(&config, &options).unwrap();
+        assert!(report.generations <= 5);
+        assert!(report.to_string().starts_with("ran "));
+    }
+}
@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{EntityKind, TraitSnapshot};
+use crate::resources::Biome;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BiomeChangeCause {
+    Desertification,
+    Flood,
+}
+
+/// Emitted by every system that mutates `Tile.biome`.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct BiomeChanged {
+    pub x: usize,
+    pub y: usize,
+    pub from: Biome,
+    pub to: Biome,
+    pub cause: BiomeChangeCause,
+    pub generation: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CatastropheKind {
+    Meteor,
+    Flood,
+}
+
+/// A rare large shock centered on `(x, y)` affecting every tile within `radius`.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct CatastropheStruck {
+    pub kind: CatastropheKind,
+    pub x: usize,
+    pub y: usize,
+    pub radius: usize,
+    pub killed: usize,
+    pub generation: usize,
+}
+
+/// A food patch appeared centered on `(x, y)`, adding `bonus_food` to the `tiles`
+/// land tiles within `radius` for `duration` generations.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct FoodPatchAppeared {
+    pub x: usize,
+    pub y: usize,
+    pub radius: usize,
+    pub tiles: usize,
+    pub bonus_food: f32,
+    pub duration: usize,
+    pub generation: usize,
+}
+
+/// An organism or predator entered the world, either as a founder or as offspring.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct Born {
+    pub id: u64,
+    pub parent: Option<u64>,
+    pub lineage: u64,
+    pub kind: EntityKind,
+    pub generation: usize,
+    #[serde(default)]
+    pub traits: TraitSnapshot,
+    /// `None` for founders.
+    #[serde(default)]
+    pub parent_traits: Option<TraitSnapshot>,
+    /// `traits` minus `parent_traits`, what mutation did to every trait. `None` for
+    /// founders.
+    #[serde(default)]
+    pub mutations: Option<TraitSnapshot>,
+    /// The parent's age in generations when it gave birth. `None` for founders.
+    #[serde(default)]
+    pub parent_age: Option<usize>,
+}
+
+/// An organism or predator left the world, for whatever reason.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct Died {
+    pub id: u64,
+    pub kind: EntityKind,
+    pub generation: usize,
+    pub age: usize,
+    /// Offspring it had, its lifetime reproductive success.
+    #[serde(default)]
+    pub offspring: u32,
+    /// Its traits when it died.
+    #[serde(default)]
+    pub traits: TraitSnapshot,
+}
+
+/// An organism or predator still alive when the run ended, logged like a death so
+/// that its offspring count is not lost. Written once at the end, never sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Survived {
+    pub id: u64,
+    pub kind: EntityKind,
+    pub generation: usize,
+    pub age: usize,
+    pub offspring: u32,
+    pub traits: TraitSnapshot,
+}
+
+/// A predator ate `prey` at `(x, y)`. Only read within the tick it is sent in, so
+/// it refers to entities rather than identities.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Killed {
+    pub predator: Entity,
+    pub prey: Entity,
+    pub x: usize,
+    pub y: usize,
+}
+
+/// A kill at `(x, y)` panicked `panicked` organisms, counting the ones panicked by
+/// other panicked organisms.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct PanicSpread {
+    pub x: usize,
+    pub y: usize,
+    pub panicked: usize,
+    pub generation: usize,
+}
+
+/// A juvenile predator attacked the organism at `(x, y)` and missed, leaving it
+/// injured with `severity`.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct PreyInjured {
+    pub id: Option<u64>,
+    pub x: usize,
+    pub y: usize,
+    pub severity: f32,
+    pub generation: usize,
+}
+
+/// A single line of `events.jsonl`, tagged with the event kind.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "event")]
+pub enum EventRecord {
+    BiomeChanged(BiomeChanged),
+    CatastropheStruck(CatastropheStruck),
+    FoodPatchAppeared(FoodPatchAppeared),
+    Born(Born),
+    Died(Died),
+    Survived(Survived),
+    PanicSpread(PanicSpread),
+    PreyInjured(PreyInjured),
+}
@@ -0,0 +1,601 @@
+//! The last few states of a run, kept in memory so that the GUI can step back
+//! through them while paused, with `Left` and `Right`, and either return to the live
+//! state or resume from an earlier one. A state is the individuals and the grids the
+//! next tick depends on, not what is drawn, so the meshes and the heatmap simply
+//! follow the state on show.
+//!
+//! Resuming from an earlier state branches the run: the states after it are dropped
+//! and [`archive_branch`] moves the logs of the abandoned future into a `branch_N`
+//! directory next to them.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::ecs::world::World as Ecs;
+use bevy::prelude::*;
+use bevy::utils::hashbrown::HashMap;
+
+use crate::components::{
+    Active, Dispersing, EntityRng, Identity, Injured, IntakeHistory, Organism, Panicked, Position,
+    Predator, PredatorSight, Residence, SpeciesId,
+};
+use crate::events::{Born, Died};
+use crate::provenance::meta_path;
+use crate::resources::{
+    ActiveTiles, CarrionGrid, DeathTally, DisplayConfig, FoodGrid, FoodPatches, Generation,
+    KillScentGrid, NewbornSurvival, NextId, PredatorSpatialIndex, SpatialIndex, StatsHistory,
+    Territory, TerritoryMap, World,
+};
+use crate::utils::safe_write;
+
+/// One living organism or predator with every component a later tick reads.
+#[derive(Clone)]
+struct Individual {
+    position: Position,
+    identity: Option<Identity>,
+    species: Option<SpeciesId>,
+    organism: Option<Organism>,
+    predator: Option<Predator>,
+    rng: Option<EntityRng>,
+    intake: Option<IntakeHistory>,
+    residence: Option<Residence>,
+    panicked: Option<Panicked>,
+    dispersing: Option<Dispersing>,
+    injured: Option<Injured>,
+    sight: Option<PredatorSight>,
+    territory: Option<Territory>,
+}
+
+/// What an [`Individual`] is captured from.
+type Carried = (
+    Entity,
+    &'static Position,
+    Option<&'static Identity>,
+    Option<&'static SpeciesId>,
+    Option<&'static Organism>,
+    Option<&'static Predator>,
+    Option<&'static EntityRng>,
+    Option<&'static IntakeHistory>,
+    Option<&'static Residence>,
+    Option<&'static Panicked>,
+    Option<&'static Dispersing>,
+    Option<&'static Injured>,
+    Option<&'static PredatorSight>,
+);
+
+/// The state of a run after one generation.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub generation: usize,
+    /// In the order queries visit them. Respawned in it, they are visited in it
+    /// again, so that a run resumed from the snapshot draws its random numbers for
+    /// them in the same order as the first time.
+    individuals: Vec<Individual>,
+    /// The spatial indices as the last move left them, as `(tile, individual)` in
+    /// the order they list the individuals. The ones born since are not in them, and
+    /// the ones that died since, `None` here, still are.
+    organism_index: Vec<(usize, Option<usize>)>,
+    predator_index: Vec<(usize, Option<usize>)>,
+    world: World,
+    food: Vec<f32>,
+    carrion: CarrionGrid,
+    kill_scent: KillScentGrid,
+    active_tiles: ActiveTiles,
+    patches: FoodPatches,
+    newborns: NewbornSurvival,
+    next_id: u64,
+}
+
+impl Snapshot {
+    pub fn capture(ecs: &mut Ecs) -> Self {
+        let territories = ecs.get_resource::<TerritoryMap>().cloned();
+        let territory_of = |entity| {
+            territories
+                .as_ref()
+                .and_then(|territories| territories.territories.get(&entity).copied())
+        };
+        let mut entities = HashMap::new();
+        let individuals: Vec<Individual> = ecs
+            .query_filtered::<Carried, (Or<(With<Organism>, With<Predator>)>, Active)>()
+            .iter(ecs)
+            .map(
+                |(
+                    entity,
+                    position,
+                    identity,
+                    species,
+                    organism,
+                    predator,
+                    rng,
+                    intake,
+                    residence,
+                    panicked,
+                    dispersing,
+                    injured,
+                    sight,
+                )| {
+                    entities.insert(entity, entities.len());
+                    Individual {
+                        position: *position,
+                        identity: identity.copied(),
+                        species: species.copied(),
+                        organism: organism.cloned(),
+                        predator: predator.copied(),
+                        rng: rng.cloned(),
+                        intake: intake.cloned(),
+                        residence: residence.copied(),
+                        panicked: panicked.copied(),
+                        dispersing: dispersing.copied(),
+                        injured: injured.copied(),
+                        sight: sight.copied(),
+                        territory: territory_of(entity),
+                    }
+                },
+            )
+            .collect();
+
+        let listed = |index: &SpatialIndex| -> Vec<(usize, Option<usize>)> {
+            index
+                .cells
+                .iter()
+                .enumerate()
+                .flat_map(|(tile, cell)| cell.iter().map(move |entity| (tile, entity)))
+                .map(|(tile, entity)| (tile, entities.get(entity).copied()))
+                .collect()
+        };
+
+        Self {
+            generation: ecs.resource::<Generation>().0,
+            individuals,
+            organism_index: listed(ecs.resource::<SpatialIndex>()),
+            predator_index: listed(&ecs.resource::<PredatorSpatialIndex>().0),
+            world: ecs.resource::<World>().clone(),
+            food: ecs.resource::<FoodGrid>().values().to_vec(),
+            carrion: ecs.resource::<CarrionGrid>().clone(),
+            kill_scent: ecs.resource::<KillScentGrid>().clone(),
+            active_tiles: ecs.resource::<ActiveTiles>().clone(),
+            patches: ecs.resource::<FoodPatches>().clone(),
+            newborns: ecs.resource::<NewbornSurvival>().clone(),
+            next_id: ecs.resource::<NextId>().0,
+        }
+    }
+
+    /// Puts the state back in place of the one in `ecs`, pooled entities included.
+    /// The individuals are respawned, which records no births or deaths.
+    pub fn apply(&self, ecs: &mut Ecs) {
+        let tally = std::mem::take(&mut *ecs.resource_mut::<DeathTally>());
+        let doomed: Vec<Entity> = ecs
+            .query_filtered::<Entity, Or<(With<Organism>, With<Predator>)>>()
+            .iter(ecs)
+            .collect();
+        for entity in doomed {
+            ecs.despawn(entity);
+        }
+
+        let (width, height) = (self.world.width, self.world.height);
+        let mut territories = TerritoryMap::new(width, height);
+        let mut spawned = Vec::with_capacity(self.individuals.len());
+        for individual in &self.individuals {
+            let mut entity = ecs.spawn(individual.position);
+            if let Some(identity) = individual.identity {
+                entity.insert(identity);
+            }
+            if let Some(species) = individual.species {
+                entity.insert(species);
+            }
+            if let Some(organism) = &individual.organism {
+                entity.insert(organism.clone());
+            }
+            if let Some(predator) = individual.predator {
+                entity.insert(predator);
+            }
+            if let Some(rng) = &individual.rng {
+                entity.insert(rng.clone());
+            }
+            if let Some(intake) = &individual.intake {
+                entity.insert(intake.clone());
+            }
+            if let Some(residence) = individual.residence {
+                entity.insert(residence);
+            }
+            if let Some(panicked) = individual.panicked {
+                entity.insert(panicked);
+            }
+            if let Some(dispersing) = individual.dispersing {
+                entity.insert(dispersing);
+            }
+            if let Some(injured) = individual.injured {
+                entity.insert(injured);
+            }
+            if let Some(sight) = individual.sight {
+                entity.insert(sight);
+            }
+            let entity = entity.id();
+            spawned.push(entity);
+            if let Some(territory) = individual.territory {
+                territories.claim(entity, territory.x, territory.y, territory.radius);
+            }
+        }
+        // the dead still take up their tiles, as they do until the next move
+        let index = |listed: &[(usize, Option<usize>)]| {
+            let mut index = SpatialIndex::new(width, height);
+            for &(tile, individual) in listed {
+                let entity = individual.map_or(Entity::PLACEHOLDER, |i| spawned[i]);
+                index.cells[tile].push(entity);
+            }
+            index
+        };
+        let organisms = index(&self.organism_index);
+        let predators = PredatorSpatialIndex(index(&self.predator_index));
+
+        // the respawned were announced as born, which they were not
+        ecs.resource_mut::<Events<Born>>().clear();
+        ecs.resource_mut::<Events<Died>>().clear();
+        *ecs.resource_mut::<DeathTally>() = tally;
+
+        ecs.insert_resource(Generation(self.generation));
+        ecs.insert_resource(self.world.clone());
+        // a new grid has every chunk dirty, so the whole heatmap is repainted
+        ecs.insert_resource(FoodGrid::new(width, self.food.clone()));
+        ecs.insert_resource(self.carrion.clone());
+        ecs.insert_resource(self.kill_scent.clone());
+        ecs.insert_resource(self.active_tiles.clone());
+        ecs.insert_resource(self.patches.clone());
+        ecs.insert_resource(self.newborns.clone());
+        ecs.insert_resource(NextId(self.next_id));
+        ecs.insert_resource(organisms);
+        ecs.insert_resource(predators);
+        if ecs.contains_resource::<TerritoryMap>() {
+            ecs.insert_resource(territories);
+        }
+    }
+}
+
+/// A ring of the last `capacity` snapshots, one taken every `interval` generations,
+/// and which of them is on show.
+#[derive(Resource)]
+pub struct History {
+    snapshots: VecDeque<Snapshot>,
+    capacity: usize,
+    interval: usize,
+    /// Index in `snapshots` of the one on show, `None` while the live state is.
+    viewing: Option<usize>,
+    /// The live state, put aside while a snapshot is on show.
+    live: Option<Snapshot>,
+}
+
+impl History {
+    pub fn new(capacity: usize, interval: usize) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+            interval: interval.max(1),
+            viewing: None,
+            live: None,
+        }
+    }
+
+    pub fn for_display(display: &DisplayConfig) -> Self {
+        Self::new(display.history_snapshots, display.history_interval)
+    }
+
+    /// Whether a snapshot is due after `generation`.
+    pub fn due(&self, generation: usize) -> bool {
+        self.capacity > 0 && generation.is_multiple_of(self.interval)
+    }
+
+    /// Keeps `snapshot`, dropping the oldest one when the ring is full.
+    pub fn record(&mut self, snapshot: Snapshot) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// The generation on show and its place in the ring, `None` while live.
+    pub fn viewing(&self) -> Option<(usize, usize)> {
+        self.viewing.map(|i| (self.snapshots[i].generation, i))
+    }
+
+    /// Shows the snapshot before the one on show, or from the live state the last
+    /// one older than it. Whether there was one.
+    pub fn back(&mut self, ecs: &mut Ecs) -> bool {
+        let previous = match self.viewing {
+            Some(i) => i.checked_sub(1),
+            None => {
+                let generation = ecs.resource::<Generation>().0;
+                self.snapshots
+                    .iter()
+                    .rposition(|snapshot| snapshot.generation < generation)
+            }
+        };
+        let Some(i) = previous else {
+            return false;
+        };
+        if self.live.is_none() {
+            self.live = Some(Snapshot::capture(ecs));
+        }
+        self.snapshots[i].apply(ecs);
+        self.viewing = Some(i);
+        true
+    }
+
+    /// Shows the snapshot after the one on show, or the live state after the newest
+    /// one older than it. Whether anything changed.
+    pub fn forward(&mut self, ecs: &mut Ecs) -> bool {
+        let Some(i) = self.viewing else {
+            return false;
+        };
+        let live_generation = self
+            .live
+            .as_ref()
+            .map_or(usize::MAX, |live| live.generation);
+        match self.snapshots.get(i + 1) {
+            Some(next) if next.generation < live_generation => {
+                next.apply(ecs);
+                self.viewing = Some(i + 1);
+                true
+            }
+            _ => self.return_to_live(ecs),
+        }
+    }
+
+    /// Puts the live state back. Whether a snapshot was on show.
+    pub fn return_to_live(&mut self, ecs: &mut Ecs) -> bool {
+        self.viewing = None;
+        let Some(live) = self.live.take() else {
+            return false;
+        };
+        live.apply(ecs);
+        true
+    }
+
+    /// Makes the snapshot on show the live state, forgetting the ones after it and
+    /// the stats of the generations after it. The generation the run branches at,
+    /// `None` while live.
+    pub fn branch(&mut self, ecs: &mut Ecs) -> Option<usize> {
+        let i = self.viewing.take()?;
+        self.live = None;
+        self.snapshots.truncate(i + 1);
+        let generation = self.snapshots[i].generation;
+        ecs.resource_mut::<StatsHistory>().truncate(generation);
+        Some(generation)
+    }
+}
+
+/// Takes a snapshot into the [`History`] every `history_interval` generations.
+pub fn record_history(ecs: &mut Ecs) {
+    let generation = ecs.resource::<Generation>().0;
+    if !ecs.resource::<History>().due(generation) {
+        return;
+    }
+    let snapshot = Snapshot::capture(ecs);
+    ecs.resource_mut::<History>().record(snapshot);
+}
+
+/// Logs cut back when a run branches, each line stamped with a `generation`, and
+/// whether that is the generation its tick started in rather than the one it ended
+/// in, as for events.
+const BRANCHED_LOGS: [(&str, bool); 6] = [
+    ("world_data.jsonl", false),
+    ("summary_data.jsonl", false),
+    ("heritability.jsonl", false),
+    ("samples.jsonl", false),
+    ("events.jsonl", true),
+    ("trace.jsonl", true),
+];
+
+/// Whether a log line belongs to the run up to `generation`. Lines that do not say
+/// are kept.
+fn before_branch(line: &str, started: bool, generation: usize) -> bool {
+    let stamp = serde_json::from_str::<serde_json::Value>(line)
+        .ok()
+        .and_then(|value| value["generation"].as_u64());
+    stamp.is_none_or(|stamp| stamp as usize + usize::from(started) <= generation)
+}
+
+/// The first `branch_N` directory in `dir` that does not exist yet, from `branch_1`.
+fn next_branch_dir(dir: &Path) -> PathBuf {
+    (1..)
+        .map(|n| dir.join(format!("branch_{}", n)))
+        .find(|path| !path.exists())
+        .expect("a free branch directory")
+}
+
+/// Copies the sidecar of the log at `path` into `branch`, if it has one.
+fn copy_sidecar(path: &Path, branch: &Path) -> io::Result<()> {
+    let sidecar = meta_path(path);
+    if let Some(name) = sidecar.file_name().filter(|_| sidecar.exists()) {
+        fs::copy(&sidecar, branch.join(name))?;
+    }
+    Ok(())
+}
+
+/// Copies the closed logs in `dir` and their sidecars into a new `branch_N`
+/// directory, where they keep the abandoned run whole, and cuts those in `dir` back
+/// to `generation`, for the run resumed there to append to. `index.json` is left open, without its closing
+/// bracket, like an index being written. Returns the branch directory.
+pub fn archive_branch(dir: &Path, generation: usize) -> io::Result<PathBuf> {
+    let branch = next_branch_dir(dir);
+    fs::create_dir_all(&branch)?;
+    for (name, started) in BRANCHED_LOGS {
+        let path = dir.join(name);
+        if !path.exists() {
+            continue;
+        }
+        let log = fs::read_to_string(&path)?;
+        fs::write(branch.join(name), &log)?;
+        copy_sidecar(&path, &branch)?;
+        let kept: String = log
+            .lines()
+            .filter(|line| before_branch(line, started, generation))
+            .flat_map(|line| [line, "\n"])
+            .collect();
+        safe_write(&path, kept)?;
+    }
+
+    let index = dir.join("index.json");
+    if index.exists() {
+        let json = fs::read_to_string(&index)?;
+        fs::write(branch.join("index.json"), &json)?;
+        copy_sidecar(&index, &branch)?;
+        let entries: Vec<serde_json::Value> = serde_json::from_str(&json)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let kept: String = entries
+            .iter()
+            .filter(|entry| {
+                entry["generation"]
+                    .as_u64()
+                    .is_none_or(|stamp| stamp as usize <= generation)
+            })
+            .enumerate()
+            .map(|(i, entry)| format!("{}{}\n", if i == 0 { '[' } else { ',' }, entry))
+            .collect();
+        safe_write(&index, kept)?;
+    }
+    Ok(branch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::build_app;
+    use crate::self_check::StateSnapshot;
+    use crate::utils::default_config;
+
+    fn app() -> App {
+        let mut config = default_config();
+        config.world.headless = true;
+        config.world.printing = false;
+        config.world.seed = 5;
+        config.organism.initial_organisms = 60;
+        config.predator.initial_predators = 6;
+        config.logging.log_data = false;
+        build_app(config)
+    }
+
+    fn run(app: &mut App, generations: usize) {
+        for _ in 0..generations {
+            app.update();
+        }
+    }
+
+    #[test]
+    fn an_applied_snapshot_is_the_state_it_was_taken_from() {
+        let mut app = app();
+        run(&mut app, 4);
+        let early = Snapshot::capture(app.world_mut());
+        let early_state = StateSnapshot::capture(&mut app);
+        run(&mut app, 5);
+        let late = Snapshot::capture(app.world_mut());
+        let late_state = StateSnapshot::capture(&mut app);
+
+        early.apply(app.world_mut());
+        assert_eq!(StateSnapshot::capture(&mut app), early_state);
+        late.apply(app.world_mut());
+        assert_eq!(StateSnapshot::capture(&mut app), late_state);
+    }
+
+    #[test]
+    fn a_run_resumed_from_a_snapshot_repeats_itself() {
+        let mut app = app();
+        run(&mut app, 4);
+        let snapshot = Snapshot::capture(app.world_mut());
+        run(&mut app, 6);
+        let ahead = StateSnapshot::capture(&mut app);
+
+        snapshot.apply(app.world_mut());
+        run(&mut app, 6);
+        let again = StateSnapshot::capture(&mut app);
+        assert_eq!(again.first_difference(&ahead), None);
+        assert_eq!(again.hash(), ahead.hash());
+    }
+
+    #[test]
+    fn stepping_back_and_forward_returns_to_the_live_state() {
+        let mut app = app();
+        let mut history = History::new(3, 2);
+        for _ in 0..9 {
+            app.update();
+            if history.due(app.world().resource::<Generation>().0) {
+                history.record(Snapshot::capture(app.world_mut()));
+            }
+        }
+        // generations 4, 6 and 8 are kept, the live state is at 9
+        assert_eq!(history.len(), 3);
+        let live = StateSnapshot::capture(&mut app);
+
+        let ecs = app.world_mut();
+        assert!(history.back(ecs));
+        assert_eq!(history.viewing(), Some((8, 2)));
+        assert!(history.back(ecs) && history.back(ecs));
+        assert_eq!(history.viewing(), Some((4, 0)));
+        assert!(!history.back(ecs));
+        assert_eq!(ecs.resource::<Generation>().0, 4);
+        assert!(history.forward(ecs));
+        assert_eq!(history.viewing(), Some((6, 1)));
+        assert!(history.return_to_live(ecs));
+        assert_eq!(history.viewing(), None);
+        assert_eq!(StateSnapshot::capture(&mut app), live);
+
+        let ecs = app.world_mut();
+        history.back(ecs);
+        history.back(ecs);
+        assert_eq!(history.branch(ecs), Some(6));
+        assert_eq!(history.len(), 2);
+        assert_eq!(ecs.resource::<Generation>().0, 6);
+        assert!(!history.forward(ecs));
+    }
+
+    #[test]
+    fn branching_archives_the_abandoned_logs() {
+        let dir = std::env::temp_dir().join("evolution_history_branch");
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(dir.join("branch_1")).unwrap();
+        let lines = |stamps: &[usize]| -> String {
+            stamps
+                .iter()
+                .map(|generation| format!("{{\"generation\":{}}}\n", generation))
+                .collect()
+        };
+        fs::write(dir.join("world_data.jsonl"), lines(&[1, 2, 3, 4, 5])).unwrap();
+        fs::write(dir.join("events.jsonl"), lines(&[0, 1, 2, 3, 4])).unwrap();
+        fs::write(
+            dir.join("index.json"),
+            "[{\"generation\":2,\"offset\":0}\n,{\"generation\":4,\"offset\":17}\n]\n",
+        )
+        .unwrap();
+
+        let branch = archive_branch(&dir, 3).unwrap();
+        assert_eq!(branch, dir.join("branch_2"));
+        let read = |path: PathBuf| fs::read_to_string(path).unwrap();
+        assert_eq!(
+            read(branch.join("world_data.jsonl")),
+            lines(&[1, 2, 3, 4, 5])
+        );
+        assert_eq!(read(dir.join("world_data.jsonl")), lines(&[1, 2, 3]));
+        // events of a tick carry the generation it started in
+        assert_eq!(read(dir.join("events.jsonl")), lines(&[0, 1, 2]));
+        assert!(!branch.join("samples.jsonl").exists());
+
+        let index = read(dir.join("index.json"));
+        assert_eq!(index, "[{\"generation\":2,\"offset\":0}\n");
+        let archived: Vec<serde_json::Value> =
+            serde_json::from_str(&read(branch.join("index.json"))).unwrap();
+        assert_eq!(archived.len(), 2);
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,9 +1,34 @@
+pub mod analyze;
+pub mod app;
+pub mod batch;
+pub mod bench;
 pub mod components;
+pub mod config;
+pub mod doctor;
+pub mod events;
+pub mod history;
 pub mod plugins;
+pub mod prelude;
+pub mod presets;
+pub mod provenance;
+pub mod region;
+pub mod repl;
+pub mod replay;
 pub mod resources;
+pub mod schema;
+pub mod seed_bank;
+pub mod self_check;
+pub mod spatial;
+pub mod stats_sink;
+pub mod universes;
 pub mod utils;
+pub mod variance;
+pub mod vega;
 
+pub use app::*;
 pub use components::*;
+pub use config::*;
+pub use events::*;
 pub use plugins::*;
 pub use resources::*;
 pub use utils::*;
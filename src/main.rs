@@ -1,46 +1,226 @@
-use bevy::prelude::*;
-use bevy::state::app::StatesPlugin;
-use evolution::plugins::{LoggingPlugin, RenderingPlugin, SimulationPlugin};
-use evolution::resources::{ReproductionRng, SpawnRng, World};
 use evolution::*;
-use rand::prelude::*;
 
 fn main() {
-    let config = get_config();
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("analyze") {
+        let options = analyze::AnalyzeOptions::from_args(&args[2..]).unwrap_or_else(|err| {
+            eprintln!("analyze: {}", err);
+            std::process::exit(1);
+        });
+        if let Err(err) = analyze::run(&options) {
+            eprintln!("analyze failed: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.iter().any(|arg| arg == "--list-presets") {
+        print!("{}", presets::list());
+        return;
+    }
+    let preset_names = presets::PRESETS.map(|preset| preset.name).join(", ");
+    if let Some(value) = args
+        .iter()
+        .position(|arg| arg == "--dump-preset")
+        .map(|i| args.get(i + 1))
+    {
+        let Some(preset) = value.and_then(|name| presets::find(name)) else {
+            eprintln!("--dump-preset needs one of: {}", preset_names);
+            std::process::exit(1);
+        };
+        let (config, _) = layer_config(Some(preset), None, &[]).expect("Presets are valid");
+        print!(
+            "{}",
+            config.to_toml_string().expect("Failed to serialize config")
+        );
+        return;
+    }
+
+    let preset = args.iter().position(|arg| arg == "--preset").map(|i| {
+        args.get(i + 1)
+            .and_then(|name| presets::find(name))
+            .unwrap_or_else(|| {
+                eprintln!("--preset needs one of: {}", preset_names);
+                std::process::exit(1);
+            })
+    });
+    let overrides: Vec<String> = args
+        .windows(2)
+        .filter(|pair| pair[0] == "--set")
+        .map(|pair| pair[1].clone())
+        .collect();
+    let flags = ConfigFlags::from_args(&args).unwrap_or_else(|err| {
+        eprintln!("Failed to load config: {}", err);
+        std::process::exit(1);
+    });
+    let config_path = args.iter().position(|arg| arg == "--config").map(|i| {
+        args.get(i + 1)
+            .map(std::path::Path::new)
+            .unwrap_or_else(|| {
+                eprintln!("--config needs the path of a config file");
+                std::process::exit(1);
+            })
+    });
+    let env = env_overrides(std::env::vars()).unwrap_or_else(|err| {
+        eprintln!("Failed to load config: {}", err);
+        std::process::exit(1);
+    });
+    // only the config next to the executable falls back to the defaults, a file
+    // asked for by name or with values set from outside has to load
+    let (mut config, defaulted) =
+        if preset.is_none() && overrides.is_empty() && env.is_empty() && config_path.is_none() {
+            get_config(None, &flags)
+        } else {
+            load_layered_config(preset, config_path, &overrides, &flags).unwrap_or_else(|err| {
+                eprintln!("Failed to load config: {}", err);
+                std::process::exit(1);
+            })
+        };
+
+    if args.get(1).map(String::as_str) == Some("bench") {
+        let options = bench::BenchOptions::from_args(&args[2..]).unwrap_or_else(|err| {
+            eprintln!("bench: {}", err);
+            std::process::exit(1);
+        });
+        match bench::run(&config, &options) {
+            Ok(report) if options.json => println!(
+                "{}",
+                serde_json::to_string(&report).expect("Failed to serialize bench report")
+            ),
+            Ok(report) => println!("{}", report),
+            Err(err) => {
+                eprintln!("invalid config: {}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if args.get(1).map(String::as_str) == Some("doctor") {
+        let options = doctor::DoctorOptions::from_args(&args[2..]).unwrap_or_else(|err| {
+            eprintln!("doctor: {}", err);
+            std::process::exit(1);
+        });
+        match doctor::run(&config, &options) {
+            Ok(report) => println!("{}", report),
+            Err(err) => {
+                eprintln!("invalid config: {}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
     println!("{:?}", config);
+    if !env.is_empty() {
+        let set: Vec<_> = env.iter().map(ToString::to_string).collect();
+        println!("Config values set by the environment: {}", set.join(", "));
+    }
+    if !defaulted.0.is_empty() {
+        println!(
+            "Config fields filled by defaults: {}",
+            defaulted.0.join(", ")
+        );
+    }
+    if let Some(warning) = defaulted.warning() {
+        eprintln!("Warning: {}", warning);
+    }
+
+    if let Some(value) = args
+        .iter()
+        .position(|arg| arg == "--self-check")
+        .map(|i| args.get(i + 1))
+    {
+        let Some(generations) = value.and_then(|value| value.parse().ok()) else {
+            eprintln!("--self-check needs a number of generations");
+            std::process::exit(1);
+        };
+        if let Err(err) = self_check::run(&config, generations) {
+            eprintln!("self-check failed: {}", err);
+            std::process::exit(1);
+        }
+        println!(
+            "Self-check passed: the first {} generations replay identically",
+            generations
+        );
+    }
+
+    if let Some(value) = args
+        .iter()
+        .position(|arg| arg == "--universes")
+        .map(|i| args.get(i + 1))
+    {
+        let Some(count) = value.and_then(|value| value.parse().ok()) else {
+            eprintln!("--universes needs a number of universes");
+            std::process::exit(1);
+        };
+        let Some(generations) = config.world.generation_limit else {
+            eprintln!("--universes needs a generation_limit in the config");
+            std::process::exit(1);
+        };
+        let dir = config
+            .logging
+            .output_dir
+            .clone()
+            .unwrap_or_else(|| ".".into());
+        init_logging(&universes::universe_config(&config, 0, generations, &dir));
+        if let Err(err) = universes::run(&config, count, generations, &dir) {
+            eprintln!("universes failed: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if batch::is_batch(&config) {
+        let dir = config
+            .logging
+            .output_dir
+            .clone()
+            .unwrap_or_else(|| ".".into());
+        let seeds = batch::seeds(&config);
+        init_logging(&batch::replicate_config(&config, seeds[0], &dir));
+        if let Err(err) = batch::run(&config, &dir) {
+            eprintln!("batch failed: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let repl = args.iter().any(|arg| arg == "--repl");
+    if repl {
+        config.world.headless = true;
+    }
+
+    init_logging(&config);
+    let mut app = match try_build_app(config) {
+        Ok(mut app) => {
+            app.insert_resource(defaulted);
+            app
+        }
+        Err(err) => {
+            eprintln!("invalid config: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let config = app.world().resource::<Config>();
+    // with `log_data` the logging plugin writes it next to the logs
+    if !config.logging.log_data {
+        let run_id = app.world().resource::<provenance::RunId>();
+        let provenance = provenance::Provenance::new(run_id, config.world.seed, 0, None);
+        let path = config.logging.output_path(EFFECTIVE_CONFIG_FILE);
+        if let Err(err) = config
+            .logging
+            .create_output_dir()
+            .map_err(Into::into)
+            .and_then(|()| config.save(&path, &provenance))
+        {
+            eprintln!("Failed to write {}: {}", path.display(), err);
+        }
+    }
+    if repl {
+        repl::run(&mut app);
+        return;
+    }
 
-    let headless = config.world.headless;
-    let mut app = App::new();
-
-    match headless {
-        true => {
-            app.add_plugins((MinimalPlugins, StatesPlugin));
-        }
-        false => {
-            app.add_plugins(DefaultPlugins);
-        }
-    }
-
-    let seed = config.world.seed;
-    let mut base_rng = StdRng::seed_from_u64(seed);
-    let reproduction_seed: u64 = base_rng.gen();
-    let spawn_seed: u64 = base_rng.gen();
-
-    let (world, food_grid) = World::new(config.world.width, config.world.height, seed);
-    app.insert_resource(world)
-        .insert_resource(food_grid)
-        .insert_resource(ReproductionRng(SmallRng::seed_from_u64(reproduction_seed)))
-        .insert_resource(SpawnRng(SmallRng::seed_from_u64(spawn_seed)))
-        .insert_resource(SpatialIndex::new(config.world.width, config.world.height))
-        .insert_resource(PredatorSpatialIndex::new(
-            config.world.width,
-            config.world.height,
-        ))
-        .insert_resource(PopulationCount::default())
-        .insert_resource(config)
-        .insert_resource(Generation(0))
-        .init_state::<AppState>()
-        .add_plugins((SimulationPlugin, RenderingPlugin, LoggingPlugin))
-        .run();
+    app.run();
 }
@@ -1,166 +1,362 @@
-use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
 use std::sync::mpsc::{self, Sender};
 use std::sync::Mutex;
 use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
+use bevy::ecs::world::World as Ecs;
 use bevy::prelude::*;
 use bevy::utils::hashbrown::HashMap;
-use serde::Serialize;
+use rand::prelude::*;
 
-use crate::components::{Organism, Position, Predator};
-use crate::plugins::simulation::SimulationSet;
-use crate::resources::{AppState, Biome, Config, FoodGrid, Generation, World};
+use crate::components::{
+    Active, Dispersing, EntityKind, Identity, Injured, IntakeHistory, Organism, Position, Predator,
+    SpeciesId, Stance, TraitSnapshot,
+};
+use crate::config::{DefaultedFields, DensityAdjustment, EFFECTIVE_CONFIG_FILE};
+use crate::events::{
+    BiomeChanged, Born, CatastropheStruck, Died, EventRecord, FoodPatchAppeared, Killed,
+    PanicSpread, PreyInjured, Survived,
+};
+use crate::history::archive_branch;
+use crate::plugins::simulation::{predator_maturity, tick_due, SimulationSet};
+use crate::provenance::{write_artifact, write_sidecar, Provenance, RunId};
+use crate::resources::{
+    AppState, Biome, CarrionGrid, ClampTally, ClampWarnings, Config, DeathCause, DeathTally,
+    EnergyFlux, EnergyIntake, FoodGrid, Generation, LoggingConfig, MovementTrace, NewbornSurvival,
+    PercentileRank, ResidenceTally, RngStreams, StatsHistory, TerritoryMap, World, FRAMES_DIR,
+};
+use crate::schema::{
+    Distribution, EnergyStats, ExportData, GenerationStats, HeritabilityRecord,
+    OrganismWithPosition, PerBiome, PercentileRecord, PredatorStats, PredatorWithPosition, Report,
+    SamplePick, SampleRecord, SpatialSummary, SpeciesStats, VarianceSummary, WorldIndexEntry,
+    PERCENTILES, SCHEMA_VERSION,
+};
+use crate::seed_bank::SeedBank;
+use crate::self_check::state_hash;
+use crate::spatial::spatial_stats;
+use crate::stats_sink::StatsStore;
+use crate::variance::trait_variance;
 
 pub struct LoggingPlugin;
 
 impl Plugin for LoggingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, initialize_log_file)
+        app.add_event::<GenerationStats>()
+            .add_systems(Startup, initialize_log_file)
             .add_systems(
                 Update,
-                (log_world_data, log_preprocessed_world_data)
-                    .run_if(in_state(AppState::Simulate))
-                    .after(SimulationSet),
+                (
+                    log_world_data,
+                    (log_preprocessed_world_data, store_stats).chain(),
+                    log_events,
+                    log_heritability,
+                    log_samples,
+                    log_percentiles,
+                    log_trace,
+                    warn_on_clamping,
+                )
+                    .run_if(in_state(AppState::Simulate).and(tick_due))
+                    .after(SimulationSet::End),
             )
-            .add_systems(OnEnter(AppState::Finished), flush_log);
+            .add_systems(
+                OnEnter(AppState::Finished),
+                (
+                    record_final_state,
+                    (
+                        (write_report, export_population, write_summary).chain(),
+                        (log_survivors, flush_log).chain(),
+                    ),
+                )
+                    .chain(),
+            );
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
 enum LogTarget {
     World,
-    Summary,
+    /// `index.json`, filled with the `index` of messages to the other targets.
+    Index,
+    Events,
+    Heritability,
+    Samples,
+    Percentiles,
+    Trace,
 }
 
 struct LogMessage {
     target: LogTarget,
     line: String,
+    /// Entry added to the index once the line's offset is known.
+    index: Option<WorldIndexEntry>,
 }
 
 #[derive(Resource)]
 struct LogWriter {
     sender: Mutex<Option<Sender<LogMessage>>>,
     handle: Mutex<Option<JoinHandle<()>>>,
+    paths: Vec<PathBuf>,
 }
 
 impl LogWriter {
+    /// Starts a thread appending each message to the file of its target, which with
+    /// `append` keeps what the file holds already. The index, if it is one of the
+    /// files, is a JSON array written an entry at a time, and one being appended to is
+    /// still open, an entry a line.
+    fn spawn(files: Vec<(LogTarget, String)>, append: bool) -> Self {
+        let (tx, rx) = mpsc::channel::<LogMessage>();
+        let paths = files.iter().map(|(_, path)| PathBuf::from(path)).collect();
+
+        let handle = thread::spawn(move || {
+            let mut indexed = 0;
+            let mut writers: Vec<_> = files
+                .into_iter()
+                .map(|(target, path)| {
+                    let file = OpenOptions::new()
+                        .create(true)
+                        .write(true)
+                        .append(append)
+                        .truncate(!append)
+                        .open(&path)
+                        .unwrap_or_else(|err| panic!("Failed to create {}: {}", path, err));
+                    let written = match append {
+                        true => file.metadata().map_or(0, |metadata| metadata.len()),
+                        false => 0,
+                    };
+                    if append && target == LogTarget::Index {
+                        indexed =
+                            fs::read_to_string(&path).map_or(0, |index| index.lines().count());
+                    }
+                    (target, path, BufWriter::new(file), written)
+                })
+                .collect();
+
+            while let Ok(msg) = rx.recv() {
+                let Some((_, path, file, written)) = writers
+                    .iter_mut()
+                    .find(|(target, _, _, _)| *target == msg.target)
+                else {
+                    continue;
+                };
+                let offset = *written;
+                writeln!(file, "{}", msg.line)
+                    .unwrap_or_else(|err| panic!("Failed to write to {}: {}", path, err));
+                *written += msg.line.len() as u64 + 1;
+
+                let Some(mut entry) = msg.index else {
+                    continue;
+                };
+                entry.offset = offset;
+                if let Some((_, path, file, _)) = writers
+                    .iter_mut()
+                    .find(|(target, _, _, _)| *target == LogTarget::Index)
+                {
+                    let line = serde_json::to_string(&entry).expect("Failed to serialize index");
+                    let separator = if indexed == 0 { '[' } else { ',' };
+                    writeln!(file, "{}{}", separator, line)
+                        .unwrap_or_else(|err| panic!("Failed to write to {}: {}", path, err));
+                    indexed += 1;
+                }
+            }
+
+            for (target, path, file, _) in writers.iter_mut() {
+                if *target == LogTarget::Index {
+                    let close = if indexed == 0 { "[]" } else { "]" };
+                    writeln!(file, "{}", close)
+                        .unwrap_or_else(|err| panic!("Failed to write to {}: {}", path, err));
+                }
+                file.flush()
+                    .unwrap_or_else(|err| panic!("Failed to flush {}: {}", path, err));
+            }
+        });
+
+        Self {
+            sender: Mutex::new(Some(tx)),
+            handle: Mutex::new(Some(handle)),
+            paths,
+        }
+    }
+
+    /// Writes the sidecar of every file with `provenance`.
+    fn describe(&self, provenance: &Provenance) {
+        for path in &self.paths {
+            if let Err(err) = write_sidecar(path, provenance) {
+                warn!(target: "sim::logging", "Failed to describe {}: {}", path.display(), err);
+            }
+        }
+    }
+
     fn send(&self, msg: LogMessage) {
         if let Some(ref tx) = *self.sender.lock().unwrap() {
             tx.send(msg).ok();
         }
     }
-}
 
-#[derive(Serialize)]
-struct OrganismWithPosition {
-    organism: Organism,
-    position: Position,
+    /// Stops taking messages and waits until everything sent is on disk.
+    fn close(&self) {
+        drop(self.sender.lock().unwrap().take());
+        let handle = self.handle.lock().unwrap().take();
+        if let Some(h) = handle {
+            let _ = h.join();
+        }
+    }
 }
 
-#[derive(Serialize)]
-struct PredatorWithPosition {
-    predator: Predator,
-    position: Position,
-}
+/// Writer of `samples.jsonl`, `percentile_individuals.jsonl` and `trace.jsonl`,
+/// kept apart from [`LogWriter`] since none of them depend on `log_data`.
+#[derive(Resource)]
+struct SampleLog(LogWriter);
 
-#[derive(Serialize)]
-struct ExportData<'a> {
-    config: &'a Config,
-    organisms: Vec<OrganismWithPosition>,
-    predators: Vec<PredatorWithPosition>,
-    world: &'a World,
-    food: &'a [f32],
-    generation: usize,
+/// Writers of the logs `logging` asks for, adding to the files already there with
+/// `append`, their sidecars saying they hold `provenance`'s run so far.
+fn log_writers(
+    logging: &LoggingConfig,
+    append: bool,
+    provenance: &Provenance,
+) -> (Option<SampleLog>, Option<LogWriter>) {
+    let path = |name| logging.output_path(name).display().to_string();
+    let mut sampled = Vec::new();
+    if logging.sample_individuals_per_generation > 0 {
+        sampled.push((LogTarget::Samples, path("samples.jsonl")));
+    }
+    if !logging.percentile_individuals.is_empty() {
+        sampled.push((LogTarget::Percentiles, path("percentile_individuals.jsonl")));
+    }
+    if logging.trace_entity.is_some() {
+        sampled.push((LogTarget::Trace, path("trace.jsonl")));
+    }
+    let samples = (!sampled.is_empty()).then(|| SampleLog(LogWriter::spawn(sampled, append)));
+    let logs = logging.log_data.then(|| {
+        LogWriter::spawn(
+            vec![
+                (LogTarget::World, path("world_data.jsonl")),
+                (LogTarget::Index, path("index.json")),
+                (LogTarget::Events, path("events.jsonl")),
+                (LogTarget::Heritability, path("heritability.jsonl")),
+            ],
+            append,
+        )
+    });
+    for writer in samples.iter().map(|samples| &samples.0).chain(&logs) {
+        writer.describe(provenance);
+    }
+    (samples, logs)
 }
 
-#[derive(Serialize)]
-struct GenerationStats {
-    generation: u32,
-    organism_count: usize,
-    predator_count: usize,
-    organism_avg_size: f32,
-    organism_avg_speed: f32,
-    organism_avg_energy: f32,
-    organism_avg_reproduction_threshold: f32,
-    predator_avg_size: f32,
-    predator_avg_speed: f32,
-    predator_avg_energy: f32,
-    predator_avg_reproduction_threshold: f32,
-    predator_avg_hunting_efficiency: f32,
-    predator_avg_satiation_threshold: f32,
-    biome_tally: HashMap<Biome, f32>,
-    average_food: f32,
-}
-
-fn initialize_log_file(mut commands: Commands, config: Res<Config>) {
-    if !config.logging.log_data {
-        return;
+fn initialize_log_file(
+    mut commands: Commands,
+    config: Res<Config>,
+    run_id: Res<RunId>,
+    generation: Res<Generation>,
+) {
+    let logging = &config.logging;
+    if let Err(err) = logging.create_output_dir() {
+        warn!(target: "sim::logging", "Failed to create the output directory: {}", err);
     }
+    commands.insert_resource(StatsStore::for_config(&config, &run_id));
+    commands.insert_resource(BirthWindows::default());
 
-    let (tx, rx) = mpsc::channel::<LogMessage>();
-
-    let handle = thread::spawn(move || {
-        let mut world_file =
-            BufWriter::new(File::create("world_data.jsonl").expect("Failed to create world log"));
-        let mut summary_file = BufWriter::new(
-            File::create("summary_data.jsonl").expect("Failed to create summary log"),
-        );
-
-        while let Ok(msg) = rx.recv() {
-            let file = match msg.target {
-                LogTarget::World => &mut world_file,
-                LogTarget::Summary => &mut summary_file,
-            };
-            writeln!(file, "{}", msg.line).expect("Failed to write log line");
+    let provenance = Provenance::new(&run_id, config.world.seed, generation.0, None);
+    // world records name the run instead of carrying its config
+    if logging.log_data {
+        let path = logging.output_path(EFFECTIVE_CONFIG_FILE);
+        if let Err(err) = config.save(&path, &provenance) {
+            warn!(target: "sim::logging", "Failed to write {}: {}", path.display(), err);
         }
+    }
+    let (samples, logs) = log_writers(logging, false, &provenance);
+    if let Some(samples) = samples {
+        commands.insert_resource(samples);
+    }
+    if let Some(logs) = logs {
+        commands.insert_resource(logs);
+    }
+}
 
-        world_file.flush().expect("Failed to flush world log");
-        summary_file.flush().expect("Failed to flush summary log");
-    });
+/// Closes and takes out every log of the run, for a run to follow in the same app.
+pub fn close_logs(ecs: &mut Ecs) {
+    if let Some(samples) = ecs.remove_resource::<SampleLog>() {
+        samples.0.close();
+    }
+    if let Some(logs) = ecs.remove_resource::<LogWriter>() {
+        logs.close();
+    }
+    // dropping the store flushes `summary_data.jsonl`
+    drop(ecs.remove_resource::<StatsStore>());
+}
 
-    commands.insert_resource(LogWriter {
-        sender: Mutex::new(Some(tx)),
-        handle: Mutex::new(Some(handle)),
-    });
+/// Cuts the logs back to `generation` for a run resumed there, closing them, keeping
+/// the abandoned ones in a `branch_N` directory with [`archive_branch`] and reopening
+/// them to append to. Returns that directory, `None` when nothing is logged.
+pub fn branch_logs(ecs: &mut Ecs, generation: usize) -> io::Result<Option<PathBuf>> {
+    let logging = ecs.resource::<Config>().logging.clone();
+    if !logging.log_data
+        && logging.sample_individuals_per_generation == 0
+        && logging.percentile_individuals.is_empty()
+        && logging.trace_entity.is_none()
+    {
+        return Ok(None);
+    }
+    close_logs(ecs);
+
+    let branch = archive_branch(&logging.output_path(""), generation);
+    let run_id = ecs.resource::<RunId>().clone();
+    let seed = ecs.resource::<Config>().world.seed;
+    let provenance = Provenance::new(&run_id, seed, generation, None);
+    let (samples, logs) = log_writers(&logging, true, &provenance);
+    if let Some(samples) = samples {
+        ecs.insert_resource(samples);
+    }
+    if let Some(logs) = logs {
+        ecs.insert_resource(logs);
+    }
+    let store = StatsStore::resumed(ecs.resource::<Config>(), &run_id, generation);
+    ecs.insert_resource(store);
+    branch.map(Some)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn log_world_data(
     config: Res<Config>,
+    run_id: Res<RunId>,
     world: Res<World>,
     food_grid: Res<FoodGrid>,
     generation: Res<Generation>,
-    organisms_query: Query<(&Organism, &Position)>,
-    predators_query: Query<(&Predator, &Position)>,
+    organisms_query: Query<(&Organism, &Position, Option<&SpeciesId>), Active>,
+    predators_query: Query<(&Predator, &Position), Active>,
     log_writer: Option<Res<LogWriter>>,
 ) {
     let Some(log_writer) = log_writer else { return };
     let interval = config.logging.log_interval.max(1);
-    if generation.0 % interval != 0 {
+    if !generation.0.is_multiple_of(interval) {
         return;
     }
 
     let organisms = organisms_query
         .iter()
-        .map(|(organism, position)| OrganismWithPosition {
-            organism: organism.clone(),
-            position: *position,
+        .map(|(organism, position, species)| {
+            OrganismWithPosition::new(organism, SpeciesId::of(species), *position, &config)
         })
         .collect::<Vec<_>>();
 
     let predators = predators_query
         .iter()
-        .map(|(predator, position)| PredatorWithPosition {
-            predator: *predator,
-            position: *position,
-        })
+        .map(|(predator, position)| PredatorWithPosition::new(predator, *position, &config))
         .collect::<Vec<_>>();
 
     let export = ExportData {
-        config: &config,
+        schema_version: SCHEMA_VERSION,
+        run_id: run_id.0.clone(),
         organisms,
         predators,
-        world: &world,
-        food: &food_grid.0,
+        world: Cow::Borrowed(&world),
+        food: Cow::Borrowed(food_grid.values()),
         generation: generation.0,
     };
 
@@ -168,39 +364,119 @@ fn log_world_data(
     log_writer.send(LogMessage {
         target: LogTarget::World,
         line,
+        index: Some(WorldIndexEntry::new(&export, 0)),
     });
 }
 
+/// An organism as the generation stats see it, with whether it is dispersing and
+/// whether it is injured.
+type OrganismRecord = (
+    &'static Organism,
+    &'static Position,
+    Option<&'static SpeciesId>,
+    Has<Dispersing>,
+    Has<Injured>,
+    Option<&'static IntakeHistory>,
+    Option<&'static Identity>,
+);
+
+/// [`trait_variance`] of the organisms and predators with a lineage.
+fn lineage_variance<'a>(
+    organisms: impl Iterator<Item = (&'a Organism, Option<&'a Identity>)>,
+    predators: impl Iterator<Item = (&'a Predator, Option<&'a Identity>)>,
+) -> VarianceSummary {
+    VarianceSummary {
+        organisms: trait_variance(organisms.filter_map(|(organism, identity)| {
+            identity.map(|identity| (identity.lineage, organism.traits()))
+        })),
+        predators: trait_variance(predators.filter_map(|(predator, identity)| {
+            identity.map(|identity| (identity.lineage, predator.traits()))
+        })),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn log_preprocessed_world_data(
     config: Res<Config>,
+    world: Res<World>,
     food_grid: Res<FoodGrid>,
     generation: Res<Generation>,
-    organisms_query: Query<(&Organism, &Position)>,
-    predators_query: Query<(&Predator, &Position)>,
-    log_writer: Option<Res<LogWriter>>,
+    organisms_query: Query<OrganismRecord, Active>,
+    predators_query: Query<(&Predator, &Position, Option<&Identity>), Active>,
+    death_tally: Res<DeathTally>,
+    clamps: Res<ClampTally>,
+    intake: Res<EnergyIntake>,
+    flux: Res<EnergyFlux>,
+    (carrion, territories, residences): (Res<CarrionGrid>, Res<TerritoryMap>, Res<ResidenceTally>),
+    mut newborns: ResMut<NewbornSurvival>,
+    (mut biome_changed, mut panic_spread, mut killed): (
+        EventReader<BiomeChanged>,
+        EventReader<PanicSpread>,
+        EventReader<Killed>,
+    ),
+    mut history: ResMut<StatsHistory>,
+    mut stats: EventWriter<GenerationStats>,
 ) {
-    let Some(log_writer) = log_writer else { return };
+    let biome_changes = biome_changed.read().count();
+    let organisms_panicked = panic_spread.read().map(|event| event.panicked).sum();
+    let mut kills_per_biome = PerBiome::default();
+    for kill in killed.read() {
+        kills_per_biome[world.tile(kill.x, kill.y).biome] += 1;
+    }
     let interval = config.logging.log_interval.max(1);
-    if generation.0 % interval != 0 {
+    if !generation.0.is_multiple_of(interval) {
         return;
     }
 
-    let mut biome_tally = HashMap::new();
+    let mut biome_tally = PerBiome::default();
+    let mut organisms_per_biome = PerBiome::default();
     let mut organism_count = 0;
     let mut organism_size_sum = 0.0;
     let mut organism_speed_sum = 0.0;
     let mut organism_energy_sum = 0.0;
     let mut organism_repro_sum = 0.0;
+    let mut organism_boldness_sum = 0.0;
+    let mut organism_exploration_sum = 0.0;
+    let mut organism_investment_sum = 0.0;
+    let mut organism_altruism_sum = 0.0;
+    let mut reserve_capacities = Vec::new();
+    let mut meals = Vec::new();
+    let mut organism_giving_up_sum = 0.0;
+    let mut organism_y_sum = 0.0;
+    let mut organisms_in_reserves = 0;
+    let mut organisms_dispersing = 0;
+    let mut organisms_injured = 0;
+    let mut morph_counts = vec![0; config.organism.morphs as usize];
 
-    for (organism, _) in organisms_query.iter() {
+    for (organism, position, _, dispersing, injured, intake_history, _) in organisms_query.iter() {
         organism_count += 1;
+        organisms_dispersing += usize::from(dispersing);
+        organisms_injured += usize::from(injured);
+        let tile = world.tile(position.x, position.y);
+        if tile.reserve {
+            organisms_in_reserves += 1;
+        }
+        organisms_per_biome[tile.biome] += 1;
         organism_size_sum += organism.size;
         organism_speed_sum += organism.speed;
         organism_energy_sum += organism.energy;
         organism_repro_sum += organism.reproduction_threshold;
+        organism_boldness_sum += organism.boldness;
+        organism_exploration_sum += organism.exploration;
+        organism_investment_sum += organism.parental_investment;
+        organism_altruism_sum += organism.altruism;
+        reserve_capacities.push(organism.reserve_capacity);
+        meals.push(intake_history.map_or(0.0, |history| history.last_meal));
+        organism_giving_up_sum += organism.giving_up_density;
+        organism_y_sum += position.y as f32;
+        let morph = organism.morph as usize;
+        if morph >= morph_counts.len() {
+            morph_counts.resize(morph + 1, 0);
+        }
+        morph_counts[morph] += 1;
 
-        for biome in [Biome::Forest, Biome::Desert, Biome::Water, Biome::Grassland] {
-            *biome_tally.entry(biome).or_insert(0.0) += organism.biome_tolerance[biome.idx()];
+        for biome in Biome::ALL {
+            biome_tally[biome] += organism.biome_tolerance[biome.idx()];
         }
     }
 
@@ -211,50 +487,1307 @@ fn log_preprocessed_world_data(
     let mut predator_repro_sum = 0.0;
     let mut predator_hunting_sum = 0.0;
     let mut predator_satiation_sum = 0.0;
+    let mut predator_temperature_sum = 0.0;
+    let mut predator_cooldown_sum = 0.0;
+    let mut predators_in_reserves = 0;
+    let mut juvenile_predators = 0;
+    let mut predators_ambushing = 0;
 
-    for (predator, _) in predators_query.iter() {
+    for (predator, position, identity) in predators_query.iter() {
         predator_count += 1;
+        if world.tile(position.x, position.y).reserve {
+            predators_in_reserves += 1;
+        }
+        if predator_maturity(identity, generation.0, &config.predator) < 1.0 {
+            juvenile_predators += 1;
+        }
         predator_size_sum += predator.size;
         predator_speed_sum += predator.speed;
         predator_energy_sum += predator.energy;
         predator_repro_sum += predator.reproduction_threshold;
         predator_hunting_sum += predator.hunting_efficiency;
         predator_satiation_sum += predator.satiation_threshold;
+        predator_temperature_sum += predator.preferred_temperature;
+        predator_cooldown_sum += predator.hunt_cooldown_ticks;
+        predators_ambushing += usize::from(predator.stance == Stance::Ambush);
     }
 
+    let kills = death_tally
+        .causes
+        .get(&DeathCause::Predation)
+        .copied()
+        .unwrap_or(0);
     let total_tiles = (config.world.width * config.world.height) as f32;
-    let total_food: f32 = food_grid.0.iter().sum();
+    let total_food: f32 = food_grid.values().iter().sum();
+    let (water_tiles, water_food) = world
+        .grid
+        .iter()
+        .zip(food_grid.values())
+        .filter(|(tile, _)| tile.biome == Biome::Water)
+        .fold((0, 0.0), |(tiles, food), (_, &tile_food)| {
+            (tiles + 1, food + tile_food)
+        });
 
-    let summary = GenerationStats {
-        generation: generation.0 as u32,
-        organism_count,
+    let spatial_interval = config.logging.spatial_interval;
+    let spatial = if spatial_interval > 0 && generation.0.is_multiple_of(spatial_interval) {
+        let (width, height) = (config.world.width, config.world.height);
+        let sample = config.logging.spatial_sample;
+        let organisms: Vec<_> = organisms_query.iter().map(|(_, p, ..)| *p).collect();
+        let predators: Vec<_> = predators_query.iter().map(|(_, p, _)| *p).collect();
+        Some(SpatialSummary {
+            organisms: spatial_stats(&organisms, width, height, sample),
+            predators: spatial_stats(&predators, width, height, sample),
+        })
+    } else {
+        None
+    };
+
+    let variance_interval = config.logging.variance_interval;
+    let trait_variance = (variance_interval > 0 && generation.0.is_multiple_of(variance_interval))
+        .then(|| {
+            lineage_variance(
+                organisms_query
+                    .iter()
+                    .map(|(organism, .., identity)| (organism, identity)),
+                predators_query
+                    .iter()
+                    .map(|(predator, _, identity)| (predator, identity)),
+            )
+        });
+
+    let species = match config.species_b {
+        Some(_) => config
+            .species()
+            .into_iter()
+            .map(|species| {
+                let members = organisms_query
+                    .iter()
+                    .map(|(organism, _, of, ..)| (organism, SpeciesId::of(of)));
+                SpeciesStats::new(species, members)
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let predators = config.predator.predators_enabled.then(|| PredatorStats {
         predator_count,
-        organism_avg_size: organism_size_sum / organism_count.max(1) as f32,
-        organism_avg_speed: organism_speed_sum / organism_count.max(1) as f32,
-        organism_avg_energy: organism_energy_sum / organism_count.max(1) as f32,
-        organism_avg_reproduction_threshold: organism_repro_sum / organism_count.max(1) as f32,
+        predators_in_reserves,
+        juvenile_predators,
+        adult_predators: predator_count - juvenile_predators,
         predator_avg_size: predator_size_sum / predator_count.max(1) as f32,
         predator_avg_speed: predator_speed_sum / predator_count.max(1) as f32,
         predator_avg_energy: predator_energy_sum / predator_count.max(1) as f32,
         predator_avg_reproduction_threshold: predator_repro_sum / predator_count.max(1) as f32,
         predator_avg_hunting_efficiency: predator_hunting_sum / predator_count.max(1) as f32,
         predator_avg_satiation_threshold: predator_satiation_sum / predator_count.max(1) as f32,
+        predator_avg_preferred_temperature: predator_temperature_sum / predator_count.max(1) as f32,
+        predator_avg_hunt_cooldown: predator_cooldown_sum / predator_count.max(1) as f32,
+        predator_avg_kills: kills as f32 / predator_count.max(1) as f32,
+        predators_ambushing_fraction: predators_ambushing as f32 / predator_count.max(1) as f32,
+        territories: territories.territories.len(),
+        mean_territory_overlap: territories.mean_overlap(),
+        kills_per_biome,
+        total_carrion: carrion.total(),
+        predator_births: clamps.births(EntityKind::Predator),
+        predator_births_blocked: clamps.predator_births_blocked,
+        predator_mean_lifetime_offspring: death_tally.predator_offspring.mean(),
+        predator_max_lifetime_offspring: death_tally.predator_offspring.max,
+    });
+    let summary = GenerationStats {
+        schema_version: SCHEMA_VERSION,
+        generation: generation.0 as u32,
+        season: config.world.seasons.season(generation.0),
+        organism_count,
+        predators,
+        organisms_in_reserves,
+        organism_avg_size: organism_size_sum / organism_count.max(1) as f32,
+        organism_avg_speed: organism_speed_sum / organism_count.max(1) as f32,
+        organism_avg_energy: organism_energy_sum / organism_count.max(1) as f32,
+        organism_avg_reproduction_threshold: organism_repro_sum / organism_count.max(1) as f32,
+        organism_avg_boldness: organism_boldness_sum / organism_count.max(1) as f32,
+        organism_avg_exploration: organism_exploration_sum / organism_count.max(1) as f32,
+        organism_avg_parental_investment: organism_investment_sum / organism_count.max(1) as f32,
+        organism_avg_altruism: organism_altruism_sum / organism_count.max(1) as f32,
+        organism_reserve_capacity: Distribution::of(&mut reserve_capacities),
+        organism_avg_giving_up_density: organism_giving_up_sum / organism_count.max(1) as f32,
+        organism_residence_ticks: Distribution::of(
+            &mut residences
+                .0
+                .iter()
+                .map(|&ticks| ticks as f32)
+                .collect::<Vec<_>>(),
+        ),
+        organism_mean_y: organism_y_sum / organism_count.max(1) as f32,
+        morph_counts,
+        organism_energy_intake_per_capita: intake.gained / organism_count.max(1) as f32,
+        organism_intake_gini: gini(&mut meals),
+        digestion_loss: intake.digestion_loss,
+        energy_shared: intake.shared,
         biome_tally,
+        organisms_per_biome,
         average_food: total_food / total_tiles,
+        average_water_food: water_food / water_tiles.max(1) as f32,
+        biome_changes,
+        organisms_panicked,
+        organisms_dispersing_fraction: organisms_dispersing as f32 / organism_count.max(1) as f32,
+        organisms_injured,
+        death_causes: death_tally.causes.clone(),
+        organism_births: clamps.births(EntityKind::Organism),
+        organism_births_by_parent_age: clamps.births_by_parent_age(EntityKind::Organism),
+        organism_mean_lifetime_offspring: death_tally.organism_offspring.mean(),
+        organism_max_lifetime_offspring: death_tally.organism_offspring.max,
+        newborn_death_rate: newborns.close(generation.0),
+        clamp_hits: clamps.hits(),
+        species,
+        spatial,
+        trait_variance,
+        energy: EnergyStats {
+            organisms: organism_energy_sum,
+            predators: predator_energy_sum,
+            food: total_food * config.organism.food_energy_conversion,
+            food_regenerated: flux.food_regenerated,
+            food_consumed: intake.gained + intake.digestion_loss,
+            upkeep: flux.upkeep,
+            removed_by_deaths: death_tally.energy,
+            passive: flux.passive,
+        },
     };
 
-    let line = serde_json::to_string(&summary).expect("Failed to serialize summary data");
-    log_writer.send(LogMessage {
-        target: LogTarget::Summary,
-        line,
+    stats.send(summary.clone());
+    history.push(summary);
+}
+
+/// Hands the stats of every logged generation to the [`StatsStore`].
+fn store_stats(mut stats: EventReader<GenerationStats>, store: Option<ResMut<StatsStore>>) {
+    let Some(mut store) = store else {
+        stats.clear();
+        return;
+    };
+    for stats in stats.read() {
+        store.record(stats);
+    }
+}
+
+/// The living organisms and predators, with their positions.
+pub(crate) type Living<'w, 's, T> = Query<'w, 's, (&'static T, &'static Position), Active>;
+
+/// [`state_hash`] of the world and the living.
+pub(crate) fn living_state_hash(
+    world: &World,
+    organisms: &Living<Organism>,
+    predators: &Living<Predator>,
+    generation: usize,
+) -> u64 {
+    let organisms: Vec<_> = organisms
+        .iter()
+        .map(|(organism, position)| (organism.clone(), *position))
+        .collect();
+    let predators: Vec<_> = predators
+        .iter()
+        .map(|(predator, position)| (*predator, *position))
+        .collect();
+    state_hash(world, &organisms, &predators, generation)
+}
+
+/// The [`state_hash`] of the final state, taken once for everything written when
+/// the run finishes, and their provenance.
+#[derive(Resource)]
+struct FinalState {
+    hash: u64,
+    provenance: Provenance,
+}
+
+fn record_final_state(
+    mut commands: Commands,
+    config: Res<Config>,
+    run_id: Res<RunId>,
+    generation: Res<Generation>,
+    world: Res<World>,
+    organisms: Living<Organism>,
+    predators: Living<Predator>,
+) {
+    let hash = living_state_hash(&world, &organisms, &predators, generation.0);
+    commands.insert_resource(FinalState {
+        hash,
+        provenance: Provenance::new(&run_id, config.world.seed, generation.0, Some(hash)),
     });
 }
 
-fn flush_log(log_writer: Option<Res<LogWriter>>) {
-    let Some(log_writer) = log_writer else { return };
-    drop(log_writer.sender.lock().unwrap().take());
-    let handle = log_writer.handle.lock().unwrap().take();
-    if let Some(h) = handle {
-        let _ = h.join();
+#[allow(clippy::too_many_arguments)]
+fn write_report(
+    mut store: ResMut<StatsStore>,
+    history: Res<StatsHistory>,
+    generation: Res<Generation>,
+    density_adjustment: Option<Res<DensityAdjustment>>,
+    defaulted: Option<Res<DefaultedFields>>,
+    final_state: Res<FinalState>,
+    organisms: Query<(&Organism, Option<&Identity>), Active>,
+    predators: Query<(&Predator, Option<&Identity>), Active>,
+) {
+    let hash = final_state.hash;
+    info!(
+        target: "sim::logging",
+        "State hash at generation {}: {:016x}",
+        generation.0,
+        hash
+    );
+    let report = Report {
+        schema_version: SCHEMA_VERSION,
+        generation: generation.0,
+        stride: history.stride(),
+        history: history.records().cloned().collect(),
+        density_adjustment: density_adjustment.map(|adjustment| adjustment.clone()),
+        defaulted_fields: defaulted.map_or_else(Vec::new, |defaulted| defaulted.0.clone()),
+        state_hash: Some(format!("{:016x}", hash)),
+        trait_variance: Some(lineage_variance(organisms.iter(), predators.iter())),
+    };
+    store.finish(&report);
+}
+
+/// What `summary.md` is written from when a run finishes.
+struct RunSummary<'a> {
+    config: &'a Config,
+    history: &'a [GenerationStats],
+    generation: usize,
+    duration: Duration,
+    /// [`state_hash`] of the final state.
+    state_hash: u64,
+    /// The lineage with the most living members and how many there are.
+    winning_lineage: Option<(u64, usize)>,
+    /// Files and directories the run wrote.
+    outputs: Vec<String>,
+}
+
+/// Change from `start` to `end` in percent, `None` from zero.
+fn percent_change(start: f32, end: f32) -> Option<f32> {
+    (start != 0.0).then(|| (end - start) / start.abs() * 100.0)
+}
+
+/// Generations of the history where a population is first seen gone after having
+/// been alive. With a thinned-out history they are the first recorded ones.
+fn extinctions(history: &[GenerationStats], count: impl Fn(&GenerationStats) -> usize) -> Vec<u32> {
+    history
+        .windows(2)
+        .filter(|pair| count(&pair[0]) > 0 && count(&pair[1]) == 0)
+        .map(|pair| pair[1].generation)
+        .collect()
+}
+
+/// A predator average of `stats`, 0 in a prey-only run.
+fn predator_average(stats: &GenerationStats, average: fn(&PredatorStats) -> f32) -> f32 {
+    stats.predators.as_ref().map_or(0.0, average)
+}
+
+/// A row of a summary table: its name and the stat it shows.
+type Column<T> = (&'static str, fn(&GenerationStats) -> T);
+
+impl RunSummary<'_> {
+    fn to_markdown(&self) -> String {
+        use std::fmt::Write as _;
+
+        let config = self.config;
+        let mut out = String::from("# Run summary\n\n## Configuration\n\n");
+        let _ = writeln!(
+            out,
+            "- World: {} x {} tiles",
+            config.world.width, config.world.height
+        );
+        let _ = writeln!(out, "- Seed: {}", config.world.seed);
+        let limit = config
+            .world
+            .generation_limit
+            .map_or_else(|| "none".to_string(), |limit| limit.to_string());
+        let _ = writeln!(out, "- Generation limit: {}", limit);
+        let predators_enabled = config.predator.predators_enabled;
+        if predators_enabled {
+            let _ = writeln!(
+                out,
+                "- Initial population: {} organisms, {} predators",
+                config.organism.initial_organisms, config.predator.initial_predators
+            );
+        } else {
+            let _ = writeln!(
+                out,
+                "- Initial population: {} organisms, predators disabled",
+                config.organism.initial_organisms
+            );
+        }
+        let _ = writeln!(out, "- Entity cap: {}", config.world.max_total_entities);
+
+        out.push_str("\n## Run\n\n");
+        let _ = writeln!(out, "- Generations: {}", self.generation);
+        let _ = writeln!(out, "- Duration: {:.1} s", self.duration.as_secs_f64());
+        let _ = writeln!(out, "- State hash: `{:016x}`", self.state_hash);
+
+        out.push_str("\n## Populations\n\n");
+        if let (Some(first), Some(last)) = (self.history.first(), self.history.last()) {
+            out.push_str("| | Start | End | Peak |\n|---|---|---|---|\n");
+            let populations: [Column<usize>; 2] = [
+                ("Organisms", |stats| stats.organism_count),
+                ("Predators", GenerationStats::predator_count),
+            ];
+            for (name, count) in populations
+                .into_iter()
+                .take(1 + usize::from(predators_enabled))
+            {
+                let peak = self
+                    .history
+                    .iter()
+                    .max_by_key(|stats| count(stats))
+                    .unwrap_or(last);
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} | {} (generation {}) |",
+                    name,
+                    count(first),
+                    count(last),
+                    count(peak),
+                    peak.generation
+                );
+            }
+        } else {
+            out.push_str("No generation stats were recorded.\n");
+        }
+
+        out.push_str("\n## Extinctions\n\n");
+        let organisms = extinctions(self.history, |stats| stats.organism_count);
+        let predators = extinctions(self.history, GenerationStats::predator_count);
+        if organisms.is_empty() && predators.is_empty() {
+            out.push_str("None.\n");
+        }
+        for (name, generations) in [("Organisms", organisms), ("Predators", predators)] {
+            for generation in generations {
+                let _ = writeln!(out, "- {} died out by generation {}", name, generation);
+            }
+        }
+
+        out.push_str("\n## Winning lineage\n\n");
+        match self.winning_lineage {
+            Some((lineage, members)) => {
+                let _ = writeln!(out, "Lineage {} with {} living members.", lineage, members);
+            }
+            None => out.push_str("Nobody survived.\n"),
+        }
+
+        if let (Some(first), Some(last)) = (self.history.first(), self.history.last()) {
+            out.push_str(
+                "\n## Trait trends\n\n| Trait | Start | End | Change |\n|---|---|---|---|\n",
+            );
+            let traits: [Column<f32>; 8] = [
+                ("Organism size", |stats| stats.organism_avg_size),
+                ("Organism speed", |stats| stats.organism_avg_speed),
+                ("Organism reproduction threshold", |stats| {
+                    stats.organism_avg_reproduction_threshold
+                }),
+                ("Organism boldness", |stats| stats.organism_avg_boldness),
+                ("Organism exploration", |stats| {
+                    stats.organism_avg_exploration
+                }),
+                ("Predator size", |stats| {
+                    predator_average(stats, |predators| predators.predator_avg_size)
+                }),
+                ("Predator speed", |stats| {
+                    predator_average(stats, |predators| predators.predator_avg_speed)
+                }),
+                ("Predator hunting efficiency", |stats| {
+                    predator_average(stats, |predators| predators.predator_avg_hunting_efficiency)
+                }),
+            ];
+            let shown = if predators_enabled { 8 } else { 5 };
+            for (name, average) in traits.into_iter().take(shown) {
+                let (start, end) = (average(first), average(last));
+                let change = percent_change(start, end)
+                    .map_or_else(|| "n/a".to_string(), |change| format!("{:+.1}%", change));
+                let _ = writeln!(out, "| {} | {:.3} | {:.3} | {} |", name, start, end, change);
+            }
+        }
+
+        if !self.outputs.is_empty() {
+            out.push_str("\n## Output files\n\n");
+            for output in &self.outputs {
+                let _ = writeln!(out, "- [{}]({})", output, output);
+            }
+        }
+        out
+    }
+}
+
+/// Writes `summary.md`, a readable overview of the run for people who will not open
+/// the logs.
+fn write_summary(
+    config: Res<Config>,
+    history: Res<StatsHistory>,
+    generation: Res<Generation>,
+    time: Option<Res<Time<Real>>>,
+    identities: Query<&Identity, Active>,
+    final_state: Res<FinalState>,
+) {
+    let mut lineages: HashMap<u64, usize> = HashMap::new();
+    for identity in identities.iter() {
+        *lineages.entry(identity.lineage).or_insert(0) += 1;
+    }
+    let winning_lineage = lineages
+        .into_iter()
+        .max_by_key(|&(lineage, members)| (members, std::cmp::Reverse(lineage)));
+
+    let mut outputs = vec!["report.json".to_string(), EFFECTIVE_CONFIG_FILE.to_string()];
+    if config.logging.log_data {
+        outputs.extend(
+            [
+                "world_data.jsonl",
+                "index.json",
+                "summary_data.jsonl",
+                "events.jsonl",
+                "heritability.jsonl",
+            ]
+            .map(String::from),
+        );
+    }
+    if config.logging.sample_individuals_per_generation > 0 {
+        outputs.push("samples.jsonl".into());
+    }
+    if !config.logging.percentile_individuals.is_empty() {
+        outputs.push("percentile_individuals.jsonl".into());
+    }
+    if config.logging.trace_entity.is_some() {
+        outputs.push("trace.jsonl".into());
+    }
+    if config.world.screenshot_interval.is_some() {
+        outputs.push(format!("{}/", FRAMES_DIR));
+    }
+    outputs.retain(|path| config.logging.output_path(path).exists());
+    if let Some(path) = config
+        .logging
+        .export_population
+        .as_ref()
+        .filter(|path| path.exists())
+    {
+        outputs.push(path.display().to_string());
+    }
+
+    let history: Vec<_> = history.records().cloned().collect();
+    let summary = RunSummary {
+        config: &config,
+        history: &history,
+        generation: generation.0,
+        duration: time.map_or(Duration::ZERO, |time| time.elapsed()),
+        state_hash: final_state.hash,
+        winning_lineage,
+        outputs,
+    };
+    let path = config.logging.output_path("summary.md");
+    if let Err(err) = write_artifact(&path, summary.to_markdown(), &final_state.provenance) {
+        warn!(target: "sim::logging", "Failed to write summary.md: {}", err);
+    }
+}
+
+fn export_population(
+    config: Res<Config>,
+    generation: Res<Generation>,
+    organisms: Query<&Organism, Active>,
+    predators: Query<&Predator, Active>,
+    final_state: Res<FinalState>,
+) {
+    let Some(path) = &config.logging.export_population else {
+        return;
+    };
+    let bank = SeedBank::new(&organisms, &predators, generation.0);
+    if let Err(err) = bank.save(path, &final_state.provenance) {
+        warn!(
+            target: "sim::logging",
+            "Failed to export population to {}: {}",
+            path.display(),
+            err
+        );
+    }
+}
+
+/// Warns the first time each trait piles up at one of its bounds in a generation,
+/// which usually means the config leaves it too little room to evolve.
+fn warn_on_clamping(
+    config: Res<Config>,
+    generation: Res<Generation>,
+    clamps: Res<ClampTally>,
+    mut warned: ResMut<ClampWarnings>,
+) {
+    for (trait_key, bound, hits, births) in clamps.excessive(config.logging.clamp_warning_fraction)
+    {
+        if warned.0.insert((trait_key.clone(), bound)) {
+            warn!(
+                target: "sim::clamping",
+                "Generation {}: {} of {} births had {} clamped at its {} bound. Check the config; further warnings for it are suppressed.",
+                generation.0, hits, births, trait_key, bound
+            );
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn log_events(
+    mut biome_changed: EventReader<BiomeChanged>,
+    mut catastrophe_struck: EventReader<CatastropheStruck>,
+    mut food_patch_appeared: EventReader<FoodPatchAppeared>,
+    mut born: EventReader<Born>,
+    mut died: EventReader<Died>,
+    mut panic_spread: EventReader<PanicSpread>,
+    mut prey_injured: EventReader<PreyInjured>,
+    log_writer: Option<Res<LogWriter>>,
+) {
+    let Some(log_writer) = log_writer else {
+        biome_changed.clear();
+        catastrophe_struck.clear();
+        food_patch_appeared.clear();
+        born.clear();
+        died.clear();
+        panic_spread.clear();
+        prey_injured.clear();
+        return;
+    };
+
+    let records = biome_changed
+        .read()
+        .map(|event| EventRecord::BiomeChanged(event.clone()))
+        .chain(
+            catastrophe_struck
+                .read()
+                .map(|event| EventRecord::CatastropheStruck(event.clone())),
+        )
+        .chain(
+            food_patch_appeared
+                .read()
+                .map(|event| EventRecord::FoodPatchAppeared(event.clone())),
+        )
+        .chain(born.read().map(|event| EventRecord::Born(event.clone())))
+        .chain(died.read().map(|event| EventRecord::Died(event.clone())))
+        .chain(
+            panic_spread
+                .read()
+                .map(|event| EventRecord::PanicSpread(event.clone())),
+        )
+        .chain(
+            prey_injured
+                .read()
+                .map(|event| EventRecord::PreyInjured(event.clone())),
+        );
+    for record in records {
+        let line = serde_json::to_string(&record).expect("Failed to serialize event");
+        log_writer.send(LogMessage {
+            target: LogTarget::Events,
+            line,
+            index: None,
+        });
+    }
+}
+
+/// Fewest parent-offspring pairs a heritability estimate is computed from.
+const MIN_HERITABILITY_PAIRS: usize = 3;
+
+/// Gini coefficient of `values`, which it sorts: 0 when all are equal, nearing 1 as
+/// one holds all of it. 0 for no values or a zero total.
+fn gini(values: &mut [f32]) -> f32 {
+    let total: f32 = values.iter().sum();
+    if values.is_empty() || total <= 0.0 {
+        return 0.0;
+    }
+    values.sort_by(f32::total_cmp);
+    let n = values.len() as f32;
+    let ranked: f32 = values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| (i + 1) as f32 * value)
+        .sum();
+    2.0 * ranked / (n * total) - (n + 1.0) / n
+}
+
+/// Least-squares slope of offspring value on parent value. `None` with too few pairs
+/// or when every parent had the same value.
+fn regression_slope(pairs: &[(f32, f32)]) -> Option<f32> {
+    if pairs.len() < MIN_HERITABILITY_PAIRS {
+        return None;
+    }
+    let n = pairs.len() as f32;
+    let mean_parent = pairs.iter().map(|&(parent, _)| parent).sum::<f32>() / n;
+    let mean_child = pairs.iter().map(|&(_, child)| child).sum::<f32>() / n;
+
+    let mut covariance = 0.0;
+    let mut parent_variance = 0.0;
+    for &(parent, child) in pairs {
+        covariance += (parent - mean_parent) * (child - mean_child);
+        parent_variance += (parent - mean_parent) * (parent - mean_parent);
+    }
+    if parent_variance <= f32::EPSILON {
+        return None;
+    }
+    Some(covariance / parent_variance)
+}
+
+/// Parent and child snapshots of the latest births of one kind.
+type BirthWindow = VecDeque<(TraitSnapshot, TraitSnapshot)>;
+
+/// Births of each kind waiting for the next heritability record of the run.
+#[derive(Resource, Default)]
+struct BirthWindows(HashMap<EntityKind, BirthWindow>);
+
+fn heritability_record(
+    kind: EntityKind,
+    window: &BirthWindow,
+    trait_names: &[&str],
+    generation: usize,
+) -> HeritabilityRecord {
+    let slopes = trait_names
+        .iter()
+        .map(|name| {
+            let pairs: Vec<_> = window
+                .iter()
+                .filter_map(|(parent, child)| Some((*parent.get(*name)?, *child.get(*name)?)))
+                .collect();
+            (name.to_string(), regression_slope(&pairs))
+        })
+        .collect();
+
+    HeritabilityRecord {
+        schema_version: SCHEMA_VERSION,
+        generation,
+        kind,
+        births: window.len(),
+        slopes,
+    }
+}
+
+fn log_heritability(
+    config: Res<Config>,
+    generation: Res<Generation>,
+    mut born: EventReader<Born>,
+    log_writer: Option<Res<LogWriter>>,
+    mut windows: ResMut<BirthWindows>,
+) {
+    let interval = config.logging.heritability_interval;
+    let Some(log_writer) = log_writer.filter(|_| interval > 0) else {
+        born.clear();
+        return;
+    };
+
+    let capacity = config.logging.heritability_window.max(1);
+    for event in born.read() {
+        let Some(parent_traits) = &event.parent_traits else {
+            continue;
+        };
+        let window = windows.0.entry(event.kind).or_default();
+        if window.len() == capacity {
+            window.pop_front();
+        }
+        window.push_back((parent_traits.clone(), event.traits.clone()));
+    }
+
+    if !generation.0.is_multiple_of(interval) {
+        return;
+    }
+
+    for (kind, trait_names) in [
+        (EntityKind::Organism, &Organism::TRAITS[..]),
+        (EntityKind::Predator, &Predator::TRAITS[..]),
+    ] {
+        let window = windows.0.entry(kind).or_default();
+        let record = heritability_record(kind, window, trait_names, generation.0);
+        window.clear();
+
+        let line = serde_json::to_string(&record).expect("Failed to serialize heritability");
+        log_writer.send(LogMessage {
+            target: LogTarget::Heritability,
+            line,
+            index: None,
+        });
+    }
+}
+
+/// Indices into `energies` of the individuals to sample: the most and the least
+/// energetic one, then random picks among the rest up to `count`. Everyone when there
+/// are no more than `count`.
+fn pick_samples(energies: &[f32], count: usize, rng: &mut impl Rng) -> Vec<(usize, SamplePick)> {
+    let mut picks = Vec::new();
+    let by_energy = |&a: &usize, &b: &usize| energies[a].total_cmp(&energies[b]);
+    let best = (0..energies.len()).max_by(by_energy);
+    let worst = (0..energies.len()).min_by(by_energy);
+    if let Some(best) = best.filter(|_| count > 0) {
+        picks.push((best, SamplePick::Best));
+    }
+    if let Some(worst) = worst.filter(|&worst| count > 1 && Some(worst) != best) {
+        picks.push((worst, SamplePick::Worst));
+    }
+
+    let rest: Vec<_> = (0..energies.len())
+        .filter(|&i| Some(i) != best && Some(i) != worst)
+        .collect();
+    let wanted = count.saturating_sub(picks.len()).min(rest.len());
+    let mut random: Vec<_> = rand::seq::index::sample(rng, rest.len(), wanted)
+        .into_iter()
+        .map(|k| rest[k])
+        .collect();
+    random.sort_unstable();
+    picks.extend(random.into_iter().map(|i| (i, SamplePick::Random)));
+    picks
+}
+
+/// Writes `sample_individuals_per_generation` organisms and predators to
+/// `samples.jsonl` every generation, stratified by [`pick_samples`].
+fn log_samples(
+    config: Res<Config>,
+    generation: Res<Generation>,
+    streams: Res<RngStreams>,
+    organisms: Query<(&Organism, &Position, Option<&Identity>), Active>,
+    predators: Query<(&Predator, &Position, Option<&Identity>), Active>,
+    sample_log: Option<Res<SampleLog>>,
+) {
+    let Some(sample_log) = sample_log else { return };
+    let count = config.logging.sample_individuals_per_generation;
+    let mut rng = streams.for_system("log_samples", generation.0);
+    let send = |kind, pick, identity: Option<&Identity>, position: &Position, energy, traits| {
+        let record = SampleRecord {
+            schema_version: SCHEMA_VERSION,
+            generation: generation.0,
+            kind,
+            pick,
+            id: identity.map(|identity| identity.id),
+            position: *position,
+            energy,
+            traits,
+        };
+        let line = serde_json::to_string(&record).expect("Failed to serialize sample");
+        sample_log.0.send(LogMessage {
+            target: LogTarget::Samples,
+            line,
+            index: None,
+        });
+    };
+
+    let organisms: Vec<_> = organisms.iter().collect();
+    let energies: Vec<_> = organisms
+        .iter()
+        .map(|(organism, ..)| organism.energy)
+        .collect();
+    for (i, pick) in pick_samples(&energies, count, &mut rng) {
+        let (organism, position, identity) = organisms[i];
+        let traits = organism.traits();
+        send(
+            EntityKind::Organism,
+            pick,
+            identity,
+            position,
+            organism.energy,
+            traits,
+        );
+    }
+
+    let predators: Vec<_> = predators.iter().collect();
+    let energies: Vec<_> = predators
+        .iter()
+        .map(|(predator, ..)| predator.energy)
+        .collect();
+    for (i, pick) in pick_samples(&energies, count, &mut rng) {
+        let (predator, position, identity) = predators[i];
+        let traits = predator.traits();
+        send(
+            EntityKind::Predator,
+            pick,
+            identity,
+            position,
+            predator.energy,
+            traits,
+        );
+    }
+}
+
+/// Indices into `individuals`, given as the value they are ranked by and their id, of
+/// the one at each of [`PERCENTILES`] by nearest rank. The sort is stable with ties
+/// going to the lower id, so the same population always gives the same picks.
+/// Populations of fewer than five repeat individuals across percentiles.
+fn percentile_picks(individuals: &[(f32, u64)]) -> Vec<(u8, usize)> {
+    let mut order: Vec<usize> = (0..individuals.len()).collect();
+    order.sort_by(|&a, &b| {
+        let ((a_value, a_id), (b_value, b_id)) = (individuals[a], individuals[b]);
+        a_value.total_cmp(&b_value).then(a_id.cmp(&b_id))
+    });
+    let Some(last) = order.len().checked_sub(1) else {
+        return Vec::new();
+    };
+    PERCENTILES
+        .iter()
+        .map(|&percentile| (percentile, order[(percentile as usize * last + 50) / 100]))
+        .collect()
+}
+
+/// An individual as [`log_percentiles`] ranks it.
+struct Ranked<'a> {
+    energy: f32,
+    size: f32,
+    position: &'a Position,
+    identity: Option<&'a Identity>,
+    traits: TraitSnapshot,
+}
+
+/// Writes the organisms and predators at [`PERCENTILES`] of each ranking in
+/// `percentile_individuals` to `percentile_individuals.jsonl` every generation.
+fn log_percentiles(
+    config: Res<Config>,
+    generation: Res<Generation>,
+    organisms: Query<(&Organism, &Position, Option<&Identity>), Active>,
+    predators: Query<(&Predator, &Position, Option<&Identity>), Active>,
+    sample_log: Option<Res<SampleLog>>,
+) {
+    let ranks = &config.logging.percentile_individuals;
+    let Some(sample_log) = sample_log.filter(|_| !ranks.is_empty()) else {
+        return;
+    };
+    let organisms: Vec<_> = organisms
+        .iter()
+        .map(|(organism, position, identity)| Ranked {
+            energy: organism.energy,
+            size: organism.size,
+            position,
+            identity,
+            traits: organism.traits(),
+        })
+        .collect();
+    let predators: Vec<_> = predators
+        .iter()
+        .map(|(predator, position, identity)| Ranked {
+            energy: predator.energy,
+            size: predator.size,
+            position,
+            identity,
+            traits: predator.traits(),
+        })
+        .collect();
+
+    for (kind, individuals) in [
+        (EntityKind::Organism, organisms),
+        (EntityKind::Predator, predators),
+    ] {
+        for &rank in ranks {
+            let values: Vec<_> = individuals
+                .iter()
+                .map(|individual| {
+                    let value = match rank {
+                        PercentileRank::Energy => individual.energy,
+                        PercentileRank::Size => individual.size,
+                    };
+                    (
+                        value,
+                        individual.identity.map_or(u64::MAX, |identity| identity.id),
+                    )
+                })
+                .collect();
+            for (percentile, i) in percentile_picks(&values) {
+                let individual = &individuals[i];
+                let record = PercentileRecord {
+                    schema_version: SCHEMA_VERSION,
+                    generation: generation.0,
+                    kind,
+                    rank,
+                    percentile,
+                    id: individual.identity.map(|identity| identity.id),
+                    position: *individual.position,
+                    energy: individual.energy,
+                    size: individual.size,
+                    traits: individual.traits.clone(),
+                };
+                let line = serde_json::to_string(&record).expect("Failed to serialize percentile");
+                sample_log.0.send(LogMessage {
+                    target: LogTarget::Percentiles,
+                    line,
+                    index: None,
+                });
+            }
+        }
+    }
+}
+
+/// Writes the movement decisions of the `trace_entity` to `trace.jsonl`.
+fn log_trace(mut trace: ResMut<MovementTrace>, sample_log: Option<Res<SampleLog>>) {
+    let records = std::mem::take(&mut trace.0);
+    let Some(sample_log) = sample_log else { return };
+    for record in records {
+        let line = serde_json::to_string(&record).expect("Failed to serialize trace");
+        sample_log.0.send(LogMessage {
+            target: LogTarget::Trace,
+            line,
+            index: None,
+        });
+    }
+}
+
+/// Logs every individual still alive when the run ends, so that the offspring of
+/// those that outlived it are in `events.jsonl` as well as those of the dead.
+fn log_survivors(
+    generation: Res<Generation>,
+    individuals: Query<(&Identity, Option<&Organism>, Option<&Predator>), Active>,
+    log_writer: Option<Res<LogWriter>>,
+) {
+    let Some(log_writer) = log_writer else {
+        return;
+    };
+    let mut survivors: Vec<_> = individuals
+        .iter()
+        .map(|(identity, organism, predator)| {
+            let (kind, traits) = match (organism, predator) {
+                (Some(organism), _) => (EntityKind::Organism, organism.traits()),
+                (_, Some(predator)) => (EntityKind::Predator, predator.traits()),
+                _ => (EntityKind::Organism, TraitSnapshot::new()),
+            };
+            Survived {
+                id: identity.id,
+                kind,
+                generation: generation.0,
+                age: generation.0.saturating_sub(identity.birth_generation),
+                offspring: identity.offspring_count,
+                traits,
+            }
+        })
+        .collect();
+    survivors.sort_unstable_by_key(|survivor| survivor.id);
+    for survivor in survivors {
+        let line = serde_json::to_string(&EventRecord::Survived(survivor))
+            .expect("Failed to serialize event");
+        log_writer.send(LogMessage {
+            target: LogTarget::Events,
+            line,
+            index: None,
+        });
+    }
+}
+
+fn flush_log(
+    log_writer: Option<Res<LogWriter>>,
+    sample_log: Option<Res<SampleLog>>,
+    final_state: Res<FinalState>,
+) {
+    if let Some(log_writer) = log_writer {
+        log_writer.close();
+        log_writer.describe(&final_state.provenance);
+    }
+    if let Some(sample_log) = sample_log {
+        sample_log.0.close();
+        sample_log.0.describe(&final_state.provenance);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regression_slope_recovers_known_slope() {
+        let pairs: Vec<_> = (0..10)
+            .map(|i| {
+                let parent = i as f32;
+                (parent, 0.5 * parent + 1.0)
+            })
+            .collect();
+        let slope = regression_slope(&pairs).unwrap();
+        assert!((slope - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn gini_runs_from_equal_shares_to_one_holding_all() {
+        assert_eq!(gini(&mut [0.5, 0.5, 0.5]), 0.0);
+        assert_eq!(gini(&mut []), 0.0);
+        assert_eq!(gini(&mut [0.0, 0.0]), 0.0);
+        // one of four holding all of it: (n - 1) / n
+        assert!((gini(&mut [0.0, 2.0, 0.0, 0.0]) - 0.75).abs() < 1e-6);
+        // the meals of 0.5, 0 and 0 against 0.3, 0.15 and 0.05
+        let priority = gini(&mut [0.5, 0.0, 0.0]);
+        let proportional = gini(&mut [0.3, 0.15, 0.05]);
+        assert!((priority - 2.0 / 3.0).abs() < 1e-6);
+        assert!((proportional - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn regression_slope_needs_enough_varied_parents() {
+        assert_eq!(regression_slope(&[(1.0, 2.0), (2.0, 3.0)]), None);
+        assert_eq!(
+            regression_slope(&[(1.0, 2.0), (1.0, 3.0), (1.0, 4.0)]),
+            None
+        );
+    }
+
+    #[test]
+    fn sparse_windows_emit_null_slopes() {
+        let organism = Organism {
+            energy: 10.0,
+            speed: 1.0,
+            size: 1.0,
+            reproduction_threshold: 100.0,
+            reproduction_cooldown: 0.0,
+            biome_tolerance: [1.0; 4],
+            boldness: 0.5,
+            exploration: 2.0,
+            morph: 0,
+            parental_investment: 0.5,
+            dispersal: 1.0,
+            preferred_temperature: 20.0,
+            migratory_tendency: 0.0,
+            altruism: 0.0,
+            starvation_trigger: 0.0,
+            burst_duration: 1.0,
+            reserve_capacity: 200.0,
+            breeding_flexibility: 0.0,
+            giving_up_density: 1.0,
+        };
+        let window = BirthWindow::from([(organism.traits(), organism.traits())]);
+        let record = heritability_record(EntityKind::Organism, &window, &Organism::TRAITS, 100);
+
+        assert_eq!(record.births, 1);
+        assert_eq!(record.slopes.len(), Organism::TRAITS.len());
+        assert!(record.slopes.values().all(Option::is_none));
+        let line = serde_json::to_string(&record).unwrap();
+        assert!(line.contains("\"size\":null"));
+    }
+
+    /// The energy numbers of one generation with `energies` as the organisms' and
+    /// 3 and 5 food on the two tiles.
+    fn energy_stats(energies: &[f32]) -> EnergyStats {
+        use crate::plugins::simulation::founder_organism;
+        use crate::utils::default_config;
+
+        let mut config = default_config();
+        config.world.width = 2;
+        config.world.height = 1;
+        config.organism.food_energy_conversion = 2.0;
+
+        let mut app = App::new();
+        app.insert_resource(World::new(2, 1, 0).0)
+            .insert_resource(FoodGrid::new(2, vec![3.0, 5.0]))
+            .insert_resource(Generation(1))
+            .insert_resource(DeathTally::default())
+            .insert_resource(ClampTally::default())
+            .insert_resource(EnergyIntake {
+                gained: 3.0,
+                digestion_loss: 1.0,
+                shared: 0.0,
+            })
+            .insert_resource(EnergyFlux {
+                food_regenerated: 6.0,
+                upkeep: 2.5,
+                passive: -0.5,
+            })
+            .insert_resource(CarrionGrid::new(2))
+            .init_resource::<TerritoryMap>()
+            .init_resource::<ResidenceTally>()
+            .insert_resource(NewbornSurvival::default())
+            .insert_resource(StatsHistory::new(10))
+            .add_event::<BiomeChanged>()
+            .add_event::<PanicSpread>()
+            .add_event::<Killed>()
+            .add_event::<GenerationStats>()
+            .add_systems(Update, log_preprocessed_world_data);
+        app.world_mut()
+            .resource_mut::<DeathTally>()
+            .record(DeathCause::Overcrowding, 4.0);
+        for (x, &energy) in energies.iter().enumerate() {
+            let mut organism = founder_organism(&config, [1.0; 4]);
+            organism.energy = energy;
+            app.world_mut().spawn((organism, Position { x, y: 0 }));
+        }
+        app.insert_resource(config);
+
+        app.update();
+        app.world()
+            .resource::<StatsHistory>()
+            .records()
+            .last()
+            .unwrap()
+            .energy
+    }
+
+    #[test]
+    fn energy_totals_and_fluxes_add_up() {
+        assert_eq!(
+            energy_stats(&[10.0, 20.0]),
+            EnergyStats {
+                organisms: 30.0,
+                predators: 0.0,
+                food: 16.0,
+                food_regenerated: 6.0,
+                food_consumed: 4.0,
+                upkeep: 2.5,
+                removed_by_deaths: 4.0,
+                passive: -0.5,
+            }
+        );
+
+        let empty = energy_stats(&[]);
+        assert_eq!((empty.organisms, empty.predators), (0.0, 0.0));
+    }
+
+    #[test]
+    fn samples_are_the_best_the_worst_and_random_picks() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(3);
+        let energies = [3.0, 9.0, 1.0, 5.0, 7.0];
+
+        let picks = pick_samples(&energies, 3, &mut rng);
+        assert_eq!(picks[..2], [(1, SamplePick::Best), (2, SamplePick::Worst)]);
+        assert_eq!(picks[2].1, SamplePick::Random);
+        assert!([0, 3, 4].contains(&picks[2].0));
+
+        assert_eq!(pick_samples(&energies, 10, &mut rng).len(), 5);
+        assert_eq!(
+            pick_samples(&energies, 1, &mut rng),
+            [(1, SamplePick::Best)]
+        );
+        assert!(pick_samples(&energies, 0, &mut rng).is_empty());
+        assert_eq!(pick_samples(&[2.0], 4, &mut rng), [(0, SamplePick::Best)]);
+    }
+
+    #[test]
+    fn percentiles_pick_by_nearest_rank_with_ids_breaking_ties() {
+        let picks = |individuals: &[(f32, u64)]| -> Vec<usize> {
+            percentile_picks(individuals)
+                .into_iter()
+                .map(|(_, i)| i)
+                .collect()
+        };
+        assert!(percentile_picks(&[]).is_empty());
+        assert_eq!(picks(&[(4.0, 1)]), [0; 5]);
+        // 5% and 25% round down to the poorer, the rest up to the richer
+        assert_eq!(picks(&[(9.0, 1), (2.0, 2)]), [1, 1, 0, 0, 0]);
+        assert_eq!(picks(&[(3.0, 1), (1.0, 2), (2.0, 3)]), [1, 2, 2, 0, 0]);
+        assert_eq!(
+            picks(&[(5.0, 1), (1.0, 2), (4.0, 3), (2.0, 4)]),
+            [1, 3, 2, 2, 0]
+        );
+        let five = [(50.0, 1), (10.0, 2), (40.0, 3), (20.0, 4), (30.0, 5)];
+        assert_eq!(
+            percentile_picks(&five),
+            [(5, 1), (25, 3), (50, 4), (75, 2), (95, 0)]
+        );
+
+        // equal values are ranked by id, wherever they sit in the population
+        let tied = [(1.0, 9), (1.0, 3), (1.0, 5)];
+        assert_eq!(picks(&tied), [1, 2, 2, 0, 0]);
+        let reordered = [(1.0, 5), (1.0, 9), (1.0, 3)];
+        assert_eq!(picks(&reordered), [2, 0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn every_generation_writes_the_requested_number_of_samples() {
+        use crate::plugins::simulation::{founder_organism, founder_predator};
+        use crate::schema::read_samples;
+        use crate::utils::default_config;
+
+        let path = std::env::temp_dir().join("evolution_samples.jsonl");
+        let mut config = default_config();
+        config.logging.sample_individuals_per_generation = 4;
+
+        let mut app = App::new();
+        app.insert_resource(SampleLog(LogWriter::spawn(
+            vec![(LogTarget::Samples, path.display().to_string())],
+            false,
+        )))
+        .insert_resource(RngStreams::new(0))
+        .insert_resource(Generation(0))
+        .add_systems(Update, log_samples);
+        for i in 0..10 {
+            let mut organism = founder_organism(&config, [1.0; 4]);
+            organism.energy = i as f32;
+            app.world_mut().spawn((organism, Position { x: i, y: 0 }));
+        }
+        for i in 0..2 {
+            app.world_mut()
+                .spawn((founder_predator(&config), Position { x: i, y: 1 }));
+        }
+        app.insert_resource(config);
+
+        for generation in 0..3 {
+            app.world_mut().resource_mut::<Generation>().0 = generation;
+            app.update();
+        }
+        app.world().resource::<SampleLog>().0.close();
+        let samples = read_samples(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        for generation in 0..3 {
+            let count = |kind| {
+                samples
+                    .iter()
+                    .filter(|sample| sample.generation == generation && sample.kind == kind)
+                    .count()
+            };
+            // only two predators to pick from
+            assert_eq!(count(EntityKind::Organism), 4);
+            assert_eq!(count(EntityKind::Predator), 2);
+        }
+        let best = samples
+            .iter()
+            .find(|sample| sample.kind == EntityKind::Organism && sample.pick == SamplePick::Best)
+            .unwrap();
+        assert_eq!(best.energy, 9.0);
+        assert_eq!(best.traits.len(), Organism::TRAITS.len());
+    }
+
+    #[test]
+    fn run_summary_reports_peaks_extinctions_and_trends() {
+        let config = crate::utils::default_config();
+        let stats = |generation, organisms, predators, size| GenerationStats {
+            generation,
+            organism_count: organisms,
+            predators: Some(PredatorStats {
+                predator_count: predators,
+                ..default()
+            }),
+            organism_avg_size: size,
+            ..default()
+        };
+        let history = vec![
+            stats(0, 100, 10, 1.0),
+            stats(10, 250, 12, 1.1),
+            stats(20, 180, 0, 1.2),
+            stats(30, 150, 0, 1.5),
+        ];
+        let summary = RunSummary {
+            config: &config,
+            history: &history,
+            generation: 31,
+            duration: Duration::from_millis(2500),
+            state_hash: 0xabc,
+            winning_lineage: Some((7, 42)),
+            outputs: vec!["report.json".into()],
+        };
+        let markdown = summary.to_markdown();
+
+        assert!(markdown.starts_with("# Run summary\n"));
+        assert!(markdown.contains(&format!(
+            "- World: {} x {} tiles",
+            config.world.width, config.world.height
+        )));
+        assert!(markdown.contains("- Duration: 2.5 s"));
+        assert!(markdown.contains("- State hash: `0000000000000abc`"));
+        assert!(markdown.contains("| Organisms | 100 | 150 | 250 (generation 10) |"));
+        assert!(markdown.contains("| Predators | 10 | 0 | 12 (generation 10) |"));
+        assert!(markdown.contains("- Predators died out by generation 20"));
+        assert!(!markdown.contains("Organisms died out"));
+        assert!(markdown.contains("Lineage 7 with 42 living members."));
+        assert!(markdown.contains("| Organism size | 1.000 | 1.500 | +50.0% |"));
+        // no predators left, so their averages fall to zero
+        assert!(markdown.contains("| Predator size | 0.000 | 0.000 | n/a |"));
+        assert!(markdown.contains("- [report.json](report.json)"));
+    }
+
+    #[test]
+    fn empty_history_still_summarizes() {
+        let config = crate::utils::default_config();
+        let summary = RunSummary {
+            config: &config,
+            history: &[],
+            generation: 0,
+            duration: Duration::ZERO,
+            state_hash: 0,
+            winning_lineage: None,
+            outputs: Vec::new(),
+        };
+        let markdown = summary.to_markdown();
+        assert!(markdown.contains("No generation stats were recorded."));
+        assert!(markdown.contains("Nobody survived."));
+        assert!(!markdown.contains("## Trait trends"));
+        assert!(!markdown.contains("## Output files"));
     }
 }
@@ -1,7 +1,11 @@
 pub mod logging;
+#[cfg(feature = "gui")]
 pub mod rendering;
 pub mod simulation;
+pub mod watchdog;
 
 pub use logging::LoggingPlugin;
+#[cfg(feature = "gui")]
 pub use rendering::RenderingPlugin;
 pub use simulation::SimulationPlugin;
+pub use watchdog::WatchdogPlugin;
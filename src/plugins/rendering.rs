@@ -1,33 +1,233 @@
+use std::path::PathBuf;
+
 use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
 use bevy::render::render_asset::RenderAssetUsages;
 use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
+#[cfg(target_arch = "wasm32")]
+use bevy::render::view::screenshot::save_to_disk;
+use bevy::render::view::screenshot::{Screenshot, ScreenshotCaptured};
+use bevy::utils::Instant;
+use bevy::window::PrimaryWindow;
 use bevy_image::{Image, ImageSampler};
 
-use crate::components::{Organism, Position, Predator, TileComponent};
-use crate::plugins::simulation::SimulationSet;
-use crate::resources::{AppState, Biome, FoodGrid, World, TILE_SIZE_IN_PIXELS};
+use crate::components::{Active, Identity, Organism, Position, Predator, SpeciesId, TileComponent};
+#[cfg(feature = "debug_overlay")]
+use crate::components::{HuntState, PredatorSight};
+use crate::events::{BiomeChanged, CatastropheKind, CatastropheStruck};
+use crate::history::{record_history, History};
+use crate::plugins::logging::{branch_logs, living_state_hash, Living};
+use crate::plugins::simulation::{predator_maturity, tick_due, SimulationSet};
+use crate::provenance::{Provenance, RunId};
+use crate::region::{region_stats, Region};
+use crate::resources::{
+    AppState, Biome, Config, FoodGrid, Generation, PredatorSpatialIndex, SpatialIndex,
+    StatsHistory, TickClock, World, CHUNK_SIZE, ENTITY_SCALE_BOUNDS, FRAMES_DIR,
+    TILE_SIZE_IN_PIXELS,
+};
+#[cfg(feature = "debug_overlay")]
+use crate::resources::{KillScentGrid, TerritoryMap};
+use crate::stats_sink::StatsStore;
 
 pub struct RenderingPlugin;
 
 #[derive(Resource)]
 struct HeatmapHandle(Handle<Image>);
 
+/// What the heatmap colors organism tiles by, cycled with `C`.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum HeatmapMode {
+    /// Green for organisms and red for predators, brighter the more there are.
+    #[default]
+    Population,
+    /// Organisms tinted by the biome they are most tolerant of, more saturated the
+    /// more specialized they are.
+    DominantBiome,
+    /// Organisms tinted by their color morph.
+    Morph,
+    /// Organisms tinted by their species.
+    Species,
+}
+
+impl HeatmapMode {
+    fn next(self) -> Self {
+        match self {
+            HeatmapMode::Population => HeatmapMode::DominantBiome,
+            HeatmapMode::DominantBiome => HeatmapMode::Morph,
+            HeatmapMode::Morph => HeatmapMode::Species,
+            HeatmapMode::Species => HeatmapMode::Population,
+        }
+    }
+
+    /// The tint of an organism in this mode, `None` when organisms are only counted.
+    fn organism_tint(self) -> Option<fn(&Organism, SpeciesId) -> Color> {
+        match self {
+            HeatmapMode::Population => None,
+            HeatmapMode::DominantBiome => Some(|organism, _| {
+                let (biome, dominance) = Biome::dominant(&organism.biome_tolerance);
+                specialization_color(biome, dominance)
+            }),
+            HeatmapMode::Morph => Some(|organism, _| morph_color(organism.morph)),
+            HeatmapMode::Species => Some(|_, species| species_color(species)),
+        }
+    }
+}
+
+/// Which layers are drawn, toggled with `1` (tiles), `2` (organisms) and
+/// `3` (predators).
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+struct LayerVisibility {
+    tiles: bool,
+    organisms: bool,
+    predators: bool,
+}
+
+/// Side in tiles of the square the organisms and predators of a tile are drawn over,
+/// changed with `[` and `]`.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+struct EntityRenderScale(u32);
+
+/// Whether each predator's vision square and target, the territories and the kill
+/// scent are drawn, toggled with `V`.
+#[cfg(feature = "debug_overlay")]
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct SightOverlay(bool);
+
+/// A swatch of the legend of a heatmap mode, shown in that mode only.
+#[derive(Component)]
+struct HeatmapLegend(HeatmapMode);
+
+/// Tile entities in row-major order, matching `World.grid`.
+#[derive(Resource)]
+struct TileEntities(Vec<Entity>);
+
+/// Reserve tiles get a light tint plus one diagonal stripe each, which joins into
+/// hatching across a protected area.
+#[derive(Resource)]
+struct ReserveHatching {
+    tile: Handle<Mesh>,
+    tint: Handle<ColorMaterial>,
+    stripe: Handle<Mesh>,
+    stripe_color: Handle<ColorMaterial>,
+}
+
+impl ReserveHatching {
+    fn hatch(&self, commands: &mut Commands, tile: Entity) {
+        commands.entity(tile).with_children(|parent| {
+            parent.spawn((
+                Mesh2d(self.tile.clone()),
+                MeshMaterial2d(self.tint.clone()),
+                Transform::from_xyz(0.0, 0.0, 0.25),
+            ));
+            parent.spawn((
+                Mesh2d(self.stripe.clone()),
+                MeshMaterial2d(self.stripe_color.clone()),
+                Transform::from_xyz(0.0, 0.0, 0.3)
+                    .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+            ));
+        });
+    }
+}
+
+/// The box dragged out with the left mouse button: where the drag started, in world
+/// coordinates, while it lasts, and the tiles of the last box released.
+#[derive(Resource, Debug, Default)]
+struct BoxSelection {
+    anchor: Option<Vec2>,
+    region: Option<Region>,
+}
+
+/// The rectangle drawn over the world while dragging out a selection.
+#[derive(Component)]
+struct SelectionBox;
+
+/// Frames a converted tile keeps flashing before settling on its new biome color.
+const TILE_FLASH_FRAMES: u32 = 30;
+
+#[derive(Component)]
+struct TileFlash {
+    frames_left: u32,
+}
+
+const CATASTROPHE_FLASH_FRAMES: u32 = 40;
+
+#[derive(Component)]
+struct CatastropheFlash {
+    frames_left: u32,
+}
+
 impl Plugin for RenderingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, (spawn_world, setup_heatmap).chain())
+        app.init_resource::<HeatmapMode>()
+            .init_resource::<BoxSelection>()
+            .add_systems(
+                Startup,
+                (
+                    init_display,
+                    spawn_world,
+                    setup_heatmap,
+                    spawn_legend,
+                    spawn_food_dots,
+                    spawn_selection_box,
+                )
+                    .chain(),
+            )
             .add_systems(
                 Update,
                 (
-                    update_heatmap.after(SimulationSet),
+                    ((update_heatmap, update_food_dots), clear_food_chunks)
+                        .chain()
+                        .after(SimulationSet::End),
+                    (flash_converted_tiles, fade_tile_flash)
+                        .chain()
+                        .after(SimulationSet::End),
+                    (flash_catastrophes, fade_catastrophe_flash)
+                        .chain()
+                        .after(SimulationSet::End),
                     handle_camera_movement,
                     handle_zoom,
+                    cycle_heatmap_mode,
+                    (toggle_layers, apply_tile_visibility).chain(),
+                    change_entity_scale,
+                    (adjust_tick_rate, show_tick_rate).chain(),
+                    capture_timelapse
+                        .after(SimulationSet::End)
+                        .run_if(timelapse_enabled),
+                    manual_screenshot,
+                    export_stats,
+                    (box_select, reserve_selection).chain(),
+                    record_history.after(SimulationSet::End).run_if(tick_due),
+                    scrub_history.before(show_tick_rate),
                 )
                     .run_if(in_state(AppState::Simulate)),
             );
+
+        #[cfg(feature = "debug_overlay")]
+        app.init_resource::<SightOverlay>().add_systems(
+            Update,
+            (
+                toggle_sight_overlay,
+                (draw_predator_sight, draw_territories, draw_kill_scent)
+                    .after(SimulationSet::End)
+                    .run_if(|overlay: Res<SightOverlay>| overlay.0),
+            )
+                .chain()
+                .run_if(in_state(AppState::Simulate)),
+        );
     }
 }
 
+fn init_display(mut commands: Commands, config: Res<Config>) {
+    let display = &config.world.display;
+    commands.insert_resource(LayerVisibility {
+        tiles: display.show_tiles,
+        organisms: display.show_organisms,
+        predators: display.show_predators,
+    });
+    commands.insert_resource(EntityRenderScale(display.entity_scale));
+    commands.insert_resource(History::for_display(display));
+}
+
 fn spawn_world(
     mut commands: Commands,
     world: Res<World>,
@@ -37,36 +237,351 @@ fn spawn_world(
     let tile_size = Vec2::new(TILE_SIZE_IN_PIXELS, TILE_SIZE_IN_PIXELS);
 
     let shape = meshes.add(Rectangle::new(tile_size.x, tile_size.y));
+    let mut tile_entities = Vec::with_capacity(world.grid.len());
+
+    let hatching = ReserveHatching {
+        tile: shape.clone(),
+        tint: materials.add(Color::srgba(1.0, 1.0, 1.0, 0.15)),
+        stripe: meshes.add(Rectangle::new(tile_size.x * std::f32::consts::SQRT_2, 1.0)),
+        stripe_color: materials.add(Color::srgba(1.0, 1.0, 1.0, 0.4)),
+    };
 
     for (i, tile) in world.grid.iter().enumerate() {
         let x = i % world.width;
         let y = i / world.width;
 
-        let color = match tile.biome {
-            Biome::Forest => Color::hsl(120.0, 1.0, 0.1),
-            Biome::Desert => Color::hsl(60.0, 1.0, 0.5),
-            Biome::Water => Color::hsl(240.0, 1.0, 0.5),
-            Biome::Grassland => Color::hsl(100.0, 1.0, 0.7),
-        };
+        let color = biome_color(tile.biome);
 
-        commands
+        let entity = commands
             .spawn((Mesh2d(shape.clone()), MeshMaterial2d(materials.add(color))))
             .insert(TileComponent {
-                biome: tile.biome.clone(),
+                biome: tile.biome,
+                x,
+                y,
             })
             .insert(Transform {
                 translation: Vec3::new(x as f32 * tile_size.x, y as f32 * tile_size.y, 0.0),
                 ..Default::default()
-            });
+            })
+            .id();
+        if tile.reserve {
+            hatching.hatch(&mut commands, entity);
+        }
+        tile_entities.push(entity);
     }
 
+    commands.insert_resource(TileEntities(tile_entities));
+    commands.insert_resource(hatching);
+
     let center_x = world.width as f32 * TILE_SIZE_IN_PIXELS / 2.0;
     let center_y = world.height as f32 * TILE_SIZE_IN_PIXELS / 2.0;
 
-    commands.spawn((
-        Camera2d::default(),
-        Transform::from_xyz(center_x, center_y, 10.0),
+    commands.spawn((Camera2d, Transform::from_xyz(center_x, center_y, 10.0)));
+}
+
+fn biome_color(biome: Biome) -> Color {
+    match biome {
+        Biome::Forest => Color::hsl(120.0, 1.0, 0.1),
+        Biome::Desert => Color::hsl(60.0, 1.0, 0.5),
+        Biome::Water => Color::hsl(240.0, 1.0, 0.5),
+        Biome::Grassland => Color::hsl(100.0, 1.0, 0.7),
+    }
+}
+
+/// Tint of an organism specialized in `biome`; grey when `dominance` is 0.
+fn specialization_color(biome: Biome, dominance: f32) -> Color {
+    let (hue, lightness) = match biome {
+        Biome::Forest => (120.0, 0.35),
+        Biome::Desert => (55.0, 0.55),
+        Biome::Water => (220.0, 0.55),
+        Biome::Grassland => (95.0, 0.75),
+    };
+    Color::hsl(hue, dominance.clamp(0.0, 1.0), lightness)
+}
+
+/// Green for species A and magenta for species B.
+fn species_color(species: SpeciesId) -> Color {
+    match species {
+        SpeciesId::A => Color::hsl(120.0, 0.85, 0.5),
+        _ => Color::hsl(300.0, 0.85, 0.6),
+    }
+}
+
+/// Distinct hues for consecutive morphs, spaced by the golden angle.
+fn morph_color(morph: u8) -> Color {
+    Color::hsl((morph as f32 * 137.5) % 360.0, 0.85, 0.55)
+}
+
+fn spawn_legend(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<Config>,
+) {
+    let swatch = meshes.add(Rectangle::new(
+        2.0 * TILE_SIZE_IN_PIXELS,
+        2.0 * TILE_SIZE_IN_PIXELS,
     ));
+    let biome_colors: Vec<_> = Biome::ALL
+        .map(|biome| specialization_color(biome, 1.0))
+        .into_iter()
+        .chain([specialization_color(Biome::Forest, 0.0)])
+        .collect();
+    let morph_colors: Vec<_> = (0..config.organism.morphs).map(morph_color).collect();
+    let species_colors: Vec<_> = config.species().into_iter().map(species_color).collect();
+
+    for (mode, colors) in [
+        (HeatmapMode::DominantBiome, biome_colors),
+        (HeatmapMode::Morph, morph_colors),
+        (HeatmapMode::Species, species_colors),
+    ] {
+        for (i, color) in colors.into_iter().enumerate() {
+            commands.spawn((
+                Mesh2d(swatch.clone()),
+                MeshMaterial2d(materials.add(color)),
+                Transform::from_xyz(
+                    i as f32 * 3.0 * TILE_SIZE_IN_PIXELS,
+                    -3.0 * TILE_SIZE_IN_PIXELS,
+                    0.6,
+                ),
+                Visibility::Hidden,
+                HeatmapLegend(mode),
+            ));
+        }
+    }
+}
+
+fn cycle_heatmap_mode(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut mode: ResMut<HeatmapMode>,
+    mut legend: Query<(&mut Visibility, &HeatmapLegend)>,
+) {
+    if !keys.just_pressed(KeyCode::KeyC) {
+        return;
+    }
+    *mode = mode.next();
+    for (mut visibility, swatch) in legend.iter_mut() {
+        *visibility = if swatch.0 == *mode {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn toggle_layers(keys: Res<ButtonInput<KeyCode>>, mut layers: ResMut<LayerVisibility>) {
+    if keys.just_pressed(KeyCode::Digit1) {
+        layers.tiles = !layers.tiles;
+    }
+    if keys.just_pressed(KeyCode::Digit2) {
+        layers.organisms = !layers.organisms;
+    }
+    if keys.just_pressed(KeyCode::Digit3) {
+        layers.predators = !layers.predators;
+    }
+}
+
+#[cfg(feature = "debug_overlay")]
+fn toggle_sight_overlay(keys: Res<ButtonInput<KeyCode>>, mut overlay: ResMut<SightOverlay>) {
+    if keys.just_pressed(KeyCode::KeyV) {
+        overlay.0 = !overlay.0;
+    }
+}
+
+#[cfg(feature = "debug_overlay")]
+fn hunt_state_color(state: HuntState) -> Color {
+    match state {
+        HuntState::Searching => Color::srgb(0.9, 0.9, 0.9),
+        HuntState::Pursuing => Color::srgb(1.0, 0.2, 0.1),
+        HuntState::Sated => Color::srgb(0.3, 0.6, 1.0),
+    }
+}
+
+/// Outlines the square each predator scans for prey and draws a line to the prey it
+/// moved towards, colored by its hunting state.
+#[cfg(feature = "debug_overlay")]
+fn draw_predator_sight(predators: Query<(&Position, &PredatorSight), Active>, mut gizmos: Gizmos) {
+    let tile_center = |x: usize, y: usize| Vec2::new(x as f32, y as f32) * TILE_SIZE_IN_PIXELS;
+    for (position, sight) in predators.iter() {
+        let color = hunt_state_color(sight.state);
+        let center = tile_center(position.x, position.y);
+        let side = (2 * sight.radius + 1) as f32 * TILE_SIZE_IN_PIXELS;
+        gizmos.rect_2d(
+            Isometry2d::from_translation(center),
+            Vec2::splat(side),
+            color,
+        );
+        if let Some((x, y)) = sight.target {
+            gizmos.line_2d(center, tile_center(x, y), color);
+        }
+    }
+}
+
+/// Outlines every predator territory faintly, so overlaps show where the circles
+/// cross.
+#[cfg(feature = "debug_overlay")]
+fn draw_territories(territories: Res<TerritoryMap>, mut gizmos: Gizmos) {
+    for territory in territories.territories.values() {
+        let center = Vec2::new(territory.x as f32, territory.y as f32) * TILE_SIZE_IN_PIXELS;
+        gizmos.circle_2d(
+            Isometry2d::from_translation(center),
+            (territory.radius + 0.5) * TILE_SIZE_IN_PIXELS,
+            Color::srgba(1.0, 0.8, 0.2, 0.25),
+        );
+    }
+}
+
+/// Marks every scented kill site with a violet square, more opaque the stronger the
+/// scent, a fresh kill's being fully opaque.
+#[cfg(feature = "debug_overlay")]
+fn draw_kill_scent(
+    scent: Res<KillScentGrid>,
+    world: Res<World>,
+    config: Res<Config>,
+    mut gizmos: Gizmos,
+) {
+    let deposit = config.predator.kill_scent.deposit;
+    if deposit <= 0.0 {
+        return;
+    }
+    for (i, &strength) in scent.0.iter().enumerate() {
+        if strength <= 0.0 {
+            continue;
+        }
+        let center =
+            Vec2::new((i % world.width) as f32, (i / world.width) as f32) * TILE_SIZE_IN_PIXELS;
+        gizmos.rect_2d(
+            Isometry2d::from_translation(center),
+            Vec2::splat(0.8 * TILE_SIZE_IN_PIXELS),
+            Color::hsla(275.0, 0.8, 0.6, (strength / deposit).min(1.0)),
+        );
+    }
+}
+
+/// Shows or hides the tile entities, reserve hatching included, whenever the tile
+/// layer is toggled; also on the first frame, to apply the configured default.
+fn apply_tile_visibility(
+    layers: Res<LayerVisibility>,
+    mut tiles: Query<&mut Visibility, With<TileComponent>>,
+) {
+    if !layers.is_changed() {
+        return;
+    }
+    let visibility = if layers.tiles {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    for mut tile in tiles.iter_mut() {
+        tile.set_if_neq(visibility);
+    }
+}
+
+fn change_entity_scale(keys: Res<ButtonInput<KeyCode>>, mut scale: ResMut<EntityRenderScale>) {
+    let (min_scale, max_scale) = ENTITY_SCALE_BOUNDS;
+    if keys.just_pressed(KeyCode::BracketLeft) {
+        scale.0 = scale.0.saturating_sub(1).max(min_scale);
+    }
+    if keys.just_pressed(KeyCode::BracketRight) {
+        scale.0 = (scale.0 + 1).min(max_scale);
+    }
+}
+
+/// Factor `+` and `-` change `target_ticks_per_second` by.
+const TICK_RATE_STEP: f32 = 1.5;
+
+/// `-` slows the simulation down, starting from the rate it reaches unthrottled, and
+/// `+` speeds it up again.
+fn adjust_tick_rate(
+    keys: Res<ButtonInput<KeyCode>>,
+    clock: Res<TickClock>,
+    mut config: ResMut<Config>,
+) {
+    let faster = keys.any_just_pressed([KeyCode::Equal, KeyCode::NumpadAdd]);
+    let slower = keys.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]);
+    if faster == slower {
+        return;
+    }
+    let target = &mut config.world.target_ticks_per_second;
+    let current = target.unwrap_or_else(|| clock.achieved(Instant::now()).max(1) as f32);
+    *target = Some(if faster {
+        current * TICK_RATE_STEP
+    } else {
+        current / TICK_RATE_STEP
+    });
+}
+
+/// Puts the generation and the tick rate reached over the last second in the title
+/// of the window, whether the run is paused or showing an earlier generation, and a
+/// warning once the stats could not be saved.
+fn show_tick_rate(
+    clock: Res<TickClock>,
+    config: Res<Config>,
+    generation: Res<Generation>,
+    history: Option<Res<History>>,
+    store: Option<Res<StatsStore>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    let achieved = clock.achieved(Instant::now());
+    let mut title = match config.world.target_ticks_per_second {
+        Some(target) => format!(
+            "evolution - generation {} - {} of {:.1} ticks/s",
+            generation.0, achieved, target
+        ),
+        None => format!(
+            "evolution - generation {} - {} ticks/s",
+            generation.0, achieved
+        ),
+    };
+    match history.as_ref().and_then(|history| history.viewing()) {
+        Some((shown, i)) => title.push_str(&format!(
+            " - history: generation {} ({} of {})",
+            shown,
+            i + 1,
+            history.as_ref().map_or(0, |history| history.len())
+        )),
+        None if clock.paused => title.push_str(" - paused"),
+        None => {}
+    }
+    if let Some(warning) = store.as_ref().and_then(|store| store.warning()) {
+        title.push_str(" - ");
+        title.push_str(warning);
+    }
+    if window.title != title {
+        window.title = title;
+    }
+}
+
+/// Tiles along an axis of `len` tiles covered by the entities on tile `i` when they
+/// are drawn `scale` tiles wide; an even width reaches one tile further up than down.
+fn footprint(i: usize, len: usize, scale: u32) -> std::ops::Range<usize> {
+    let scale = scale.max(1) as usize;
+    i.saturating_sub((scale - 1) / 2)..(i + scale / 2 + 1).min(len)
+}
+
+/// For every tile, the tile whose entities are drawn on it at `scale`: the most
+/// crowded one whose footprint covers it, or the tile itself when none does.
+fn scaled_sources(counts: &[u16], width: usize, scale: u32) -> Vec<usize> {
+    let height = counts.len() / width.max(1);
+    let mut sources: Vec<usize> = (0..counts.len()).collect();
+    for (source, &count) in counts.iter().enumerate() {
+        if count == 0 {
+            continue;
+        }
+        let (x, y) = (source % width, source / width);
+        for ty in footprint(y, height, scale) {
+            for tx in footprint(x, width, scale) {
+                let target = ty * width + tx;
+                if count > counts[sources[target]] {
+                    sources[target] = source;
+                }
+            }
+        }
+    }
+    sources
 }
 
 fn setup_heatmap(
@@ -118,81 +633,556 @@ fn setup_heatmap(
     commands.insert_resource(HeatmapHandle(image_handle));
 }
 
+/// Organisms and predators counted on every tile for the heatmap, with the summed
+/// tints of the organisms when the mode tints them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Occupancy {
+    pub organisms: Vec<u16>,
+    pub predators: Vec<u16>,
+    pub tints: Vec<Vec3>,
+}
+
+impl Occupancy {
+    fn reset(&mut self, total: usize) {
+        self.organisms.clear();
+        self.organisms.resize(total, 0);
+        self.predators.clear();
+        self.predators.resize(total, 0);
+        self.tints.clear();
+        self.tints.resize(total, Vec3::ZERO);
+    }
+
+    fn differs(&self, other: &Occupancy, i: usize) -> bool {
+        self.organisms.get(i) != other.organisms.get(i)
+            || self.predators.get(i) != other.predators.get(i)
+            || self.tints.get(i) != other.tints.get(i)
+    }
+}
+
+/// A living organism as the heatmap paints it, tinted by its species.
+type PaintedOrganism = (
+    &'static Position,
+    &'static Organism,
+    Option<&'static SpeciesId>,
+);
+
+/// A living predator as the heatmap paints it, dimmer while juvenile.
+type PaintedPredator = (&'static Position, Option<&'static Identity>);
+
+#[allow(clippy::too_many_arguments)]
 fn update_heatmap(
     heatmap: Res<HeatmapHandle>,
     mut images: ResMut<Assets<Image>>,
-    organism_query: Query<&Position, (With<Organism>, Without<Predator>)>,
-    predator_query: Query<&Position, With<Predator>>,
+    organism_query: Query<PaintedOrganism, (Without<Predator>, Active)>,
+    predator_query: Query<PaintedPredator, (With<Predator>, Active)>,
+    mut biome_changed: EventReader<BiomeChanged>,
     food_grid: Res<FoodGrid>,
     world: Res<World>,
-    mut org_counts: Local<Vec<u16>>,
-    mut pred_counts: Local<Vec<u16>>,
+    config: Res<Config>,
+    generation: Res<Generation>,
+    mode: Res<HeatmapMode>,
+    layers: Res<LayerVisibility>,
+    scale: Res<EntityRenderScale>,
+    mut occupancy: Local<Occupancy>,
+    mut painted: Local<Occupancy>,
+) {
+    let w = world.width;
+    let total = w * world.height;
+
+    occupancy.reset(total);
+    let organism_tint = mode.organism_tint();
+
+    if layers.organisms {
+        for (pos, organism, species) in organism_query.iter() {
+            let idx = pos.y * w + pos.x;
+            occupancy.organisms[idx] = occupancy.organisms[idx].saturating_add(1);
+            if let Some(organism_tint) = organism_tint {
+                occupancy.tints[idx] += organism_tint(organism, SpeciesId::of(species))
+                    .to_srgba()
+                    .to_vec3();
+            }
+        }
+    }
+    if layers.predators {
+        for (pos, identity) in predator_query.iter() {
+            let idx = pos.y * w + pos.x;
+            // Juveniles count half as much as adults, so they are drawn dimmer.
+            let weight = if predator_maturity(identity, generation.0, &config.predator) < 1.0 {
+                1
+            } else {
+                2
+            };
+            occupancy.predators[idx] = occupancy.predators[idx].saturating_add(weight);
+        }
+    }
+
+    if scale.0 > 1 {
+        let sources = scaled_sources(&occupancy.organisms, w, scale.0);
+        occupancy.organisms = sources
+            .iter()
+            .map(|&source| occupancy.organisms[source])
+            .collect();
+        if organism_tint.is_some() {
+            occupancy.tints = sources
+                .iter()
+                .map(|&source| occupancy.tints[source])
+                .collect();
+        }
+        let sources = scaled_sources(&occupancy.predators, w, scale.0);
+        occupancy.predators = sources
+            .iter()
+            .map(|&source| occupancy.predators[source])
+            .collect();
+    }
+
+    // A chunk is repainted when its food or entities changed; switching what is drawn
+    // repaints everything.
+    let everything = mode.is_changed() || layers.is_changed() || scale.is_changed();
+    let mut repaint: Vec<bool> = (0..food_grid.chunk_count())
+        .map(|chunk| everything || food_grid.is_dirty(chunk))
+        .collect();
+    for i in 0..total {
+        if occupancy.differs(&painted, i) {
+            repaint[food_grid.chunk_of(i)] = true;
+        }
+    }
+    // A converted tile is colored against its new biome's food maximum.
+    for event in biome_changed.read() {
+        repaint[food_grid.chunk_of(event.y * w + event.x)] = true;
+    }
+
+    let Some(image) = images.get_mut(&heatmap.0) else {
+        return;
+    };
+    paint_heatmap(
+        &mut image.data,
+        &repaint,
+        &occupancy,
+        organism_tint.is_some(),
+        &food_grid,
+        &world,
+        &config,
+    );
+    std::mem::swap(&mut *occupancy, &mut *painted);
+}
+
+/// Forgets which food chunks changed once every view of the food has caught up.
+fn clear_food_chunks(mut food_grid: ResMut<FoodGrid>) {
+    food_grid.clear_dirty();
+}
+
+/// Writes the heatmap texels of the chunks flagged in `repaint`, chunks numbered as
+/// in [`FoodGrid::chunk_of`]; texels of the other chunks are left untouched.
+pub fn paint_heatmap(
+    data: &mut [u8],
+    repaint: &[bool],
+    occupancy: &Occupancy,
+    tinted: bool,
+    food_grid: &FoodGrid,
+    world: &World,
+    config: &Config,
 ) {
     let w = world.width;
     let h = world.height;
-    let total = w * h;
+    let chunks_x = food_grid.chunks_x().max(1);
 
-    if org_counts.len() != total {
-        *org_counts = vec![0u16; total];
-        *pred_counts = vec![0u16; total];
+    for chunk in (0..repaint.len()).filter(|&chunk| repaint[chunk]) {
+        let (x0, y0) = (chunk % chunks_x * CHUNK_SIZE, chunk / chunks_x * CHUNK_SIZE);
+        for y in y0..(y0 + CHUNK_SIZE).min(h) {
+            for x in x0..(x0 + CHUNK_SIZE).min(w) {
+                let sim_idx = y * w + x;
+                // Flip Y: sim y=0 is world bottom, but texture row 0 is screen top
+                let tex_y = h - 1 - y;
+                let tex_idx = (tex_y * w + x) * 4;
+                paint_tile(
+                    &mut data[tex_idx..tex_idx + 4],
+                    occupancy,
+                    tinted,
+                    sim_idx,
+                    food_grid.get(sim_idx),
+                    config
+                        .world
+                        .biome(world.grid[sim_idx].biome)
+                        .max_food_availability,
+                );
+            }
+        }
     }
+}
+
+fn paint_tile(
+    texel: &mut [u8],
+    occupancy: &Occupancy,
+    tinted: bool,
+    sim_idx: usize,
+    food: f32,
+    max_food: f32,
+) {
+    let org = occupancy.organisms[sim_idx];
+    let pred = occupancy.predators[sim_idx];
 
-    for v in org_counts.iter_mut() {
-        *v = 0;
+    if tinted && org > 0 {
+        // Average tint of the organisms on the tile.
+        let tint = occupancy.tints[sim_idx] / org as f32 * 255.0;
+        texel.copy_from_slice(&[tint.x as u8, tint.y as u8, tint.z as u8, 255]);
+    } else if pred > 0 || org > 0 {
+        // Entities present: fully opaque. Each entity contributes 50 brightness,
+        // saturating at 5 organisms (green) or 5 adult predators (red); a
+        // juvenile predator contributes 25.
+        texel.copy_from_slice(&[
+            (pred as u32 * 25).min(255) as u8, // R: predators
+            (org as u32 * 50).min(255) as u8,  // G: organisms
+            0,
+            255,
+        ]);
+    } else if food > max_food {
+        // Food patch: food above the biome's maximum shows gold.
+        texel.copy_from_slice(&[255, 200, 0, 140]);
+    } else if food > 0.5 {
+        // Food only: subtle blue tint, semi-transparent so biome shows through.
+        texel.copy_from_slice(&[0, 0, ((food / 100.0) * 200.0).min(200.0) as u8, 80]);
+    } else {
+        // Empty: fully transparent, biome tile visible.
+        texel.copy_from_slice(&[0, 0, 0, 0]);
     }
-    for v in pred_counts.iter_mut() {
-        *v = 0;
+}
+
+/// Dots drawn for the food of a tile holding `food` out of `max_food`, out of
+/// `per_tile`: the food quantized to the nearest level, a patch above the maximum
+/// showing every dot.
+fn food_dot_level(food: f32, max_food: f32, per_tile: u8) -> u8 {
+    if max_food <= 0.0 {
+        return if food > 0.0 { per_tile } else { 0 };
     }
+    let ratio = (food / max_food).clamp(0.0, 1.0);
+    (ratio * per_tile as f32).round() as u8
+}
 
-    for pos in organism_query.iter() {
-        let idx = pos.y * w + pos.x;
-        org_counts[idx] = org_counts[idx].saturating_add(1);
+/// A pool of `per_tile` dot entities for every tile, spawned once and shown or hidden
+/// as the food of the tile changes.
+#[derive(Resource, Debug)]
+struct FoodDots {
+    dots: Vec<Entity>,
+    per_tile: u8,
+    /// Dots currently visible on every tile; always the first ones of its pool.
+    shown: Vec<u8>,
+}
+
+impl FoodDots {
+    fn new(dots: Vec<Entity>, per_tile: u8) -> Self {
+        let tiles = dots.len() / per_tile.max(1) as usize;
+        Self {
+            dots,
+            per_tile,
+            shown: vec![0; tiles],
+        }
     }
-    for pos in predator_query.iter() {
-        let idx = pos.y * w + pos.x;
-        pred_counts[idx] = pred_counts[idx].saturating_add(1);
+
+    /// Records that `level` dots of `tile` are visible, returning the dots whose
+    /// visibility has to change and what it changes to.
+    fn show(&mut self, tile: usize, level: u8) -> (&[Entity], Visibility) {
+        let level = level.min(self.per_tile);
+        let shown = std::mem::replace(&mut self.shown[tile], level);
+        let first = tile * self.per_tile as usize;
+        if level >= shown {
+            let range = first + shown as usize..first + level as usize;
+            (&self.dots[range], Visibility::Inherited)
+        } else {
+            let range = first + level as usize..first + shown as usize;
+            (&self.dots[range], Visibility::Hidden)
+        }
     }
+}
 
-    let Some(image) = images.get_mut(&heatmap.0) else {
+/// Spawns the hidden dot pools when food dots are enabled and the world is small
+/// enough for one entity per dot.
+fn spawn_food_dots(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    world: Res<World>,
+    config: Res<Config>,
+) {
+    let display = &config.world.display;
+    if !display.food_dots {
+        return;
+    }
+    let tiles = world.width * world.height;
+    // bound first, since `display` means something else inside `warn!`
+    let max_tiles = display.food_dots_max_tiles;
+    if tiles > max_tiles {
+        warn!(
+            "Not drawing food dots: the world has {} tiles, more than food_dots_max_tiles ({})",
+            tiles, max_tiles
+        );
+        return;
+    }
+
+    // The dots of a tile sit on a square grid just large enough to hold them all.
+    let per_tile = display.food_dots_per_tile;
+    let columns = (per_tile as f32).sqrt().ceil() as usize;
+    let spacing = TILE_SIZE_IN_PIXELS / columns as f32;
+    let mesh = meshes.add(Circle::new(spacing * 0.3));
+    let material = materials.add(Color::srgb(0.95, 0.8, 0.2));
+
+    let mut dots = Vec::with_capacity(tiles * per_tile as usize);
+    for tile in 0..tiles {
+        let (x, y) = (tile % world.width, tile / world.width);
+        let corner = Vec2::new(x as f32, y as f32) * TILE_SIZE_IN_PIXELS
+            - Vec2::splat((TILE_SIZE_IN_PIXELS - spacing) / 2.0);
+        for dot in 0..per_tile as usize {
+            let offset = Vec2::new((dot % columns) as f32, (dot / columns) as f32) * spacing;
+            dots.push(
+                commands
+                    .spawn((
+                        Mesh2d(mesh.clone()),
+                        MeshMaterial2d(material.clone()),
+                        Transform::from_translation((corner + offset).extend(0.4)),
+                        Visibility::Hidden,
+                    ))
+                    .id(),
+            );
+        }
+    }
+    commands.insert_resource(FoodDots::new(dots, per_tile));
+}
+
+/// Shows as many dots on every tile of a changed chunk as its food level calls for.
+fn update_food_dots(
+    dots: Option<ResMut<FoodDots>>,
+    food_grid: Res<FoodGrid>,
+    world: Res<World>,
+    config: Res<Config>,
+    mut biome_changed: EventReader<BiomeChanged>,
+    mut visibility: Query<&mut Visibility>,
+) {
+    let Some(mut dots) = dots else {
         return;
     };
-    let data = &mut image.data;
-
-    for y in 0..h {
-        for x in 0..w {
-            let sim_idx = y * w + x;
-            // Flip Y: sim y=0 is world bottom, but texture row 0 is screen top
-            let tex_y = h - 1 - y;
-            let tex_idx = (tex_y * w + x) * 4;
-
-            let org = org_counts[sim_idx];
-            let pred = pred_counts[sim_idx];
-            let food = food_grid.0[sim_idx];
-
-            if pred > 0 || org > 0 {
-                // Entities present: fully opaque. Each entity contributes 50 brightness,
-                // saturating at 5 organisms (green) or 5 predators (red).
-                data[tex_idx] = (pred as u32 * 50).min(255) as u8; // R: predators
-                data[tex_idx + 1] = (org as u32 * 50).min(255) as u8; // G: organisms
-                data[tex_idx + 2] = 0;
-                data[tex_idx + 3] = 255;
-            } else if food > 0.5 {
-                // Food only: subtle blue tint, semi-transparent so biome shows through.
-                data[tex_idx] = 0;
-                data[tex_idx + 1] = 0;
-                data[tex_idx + 2] = ((food / 100.0) * 200.0).min(200.0) as u8;
-                data[tex_idx + 3] = 80;
-            } else {
-                // Empty: fully transparent, biome tile visible.
-                data[tex_idx] = 0;
-                data[tex_idx + 1] = 0;
-                data[tex_idx + 2] = 0;
-                data[tex_idx + 3] = 0;
+    // A converted tile is measured against its new biome's food maximum.
+    let mut changed: Vec<bool> = (0..food_grid.chunk_count())
+        .map(|chunk| food_grid.is_dirty(chunk))
+        .collect();
+    for event in biome_changed.read() {
+        changed[food_grid.chunk_of(event.y * world.width + event.x)] = true;
+    }
+
+    for chunk in (0..changed.len()).filter(|&chunk| changed[chunk]) {
+        for tile in food_grid.chunk_tiles(chunk) {
+            let max_food = config
+                .world
+                .biome(world.grid[tile].biome)
+                .max_food_availability;
+            let level = food_dot_level(food_grid.get(tile), max_food, dots.per_tile);
+            let (toggled, shown) = dots.show(tile, level);
+            for &dot in toggled {
+                if let Ok(mut dot) = visibility.get_mut(dot) {
+                    *dot = shown;
+                }
             }
         }
     }
 }
 
+fn flash_converted_tiles(
+    mut commands: Commands,
+    mut biome_changed: EventReader<BiomeChanged>,
+    tile_entities: Res<TileEntities>,
+    world: Res<World>,
+    mut tiles: Query<(&mut TileComponent, &MeshMaterial2d<ColorMaterial>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for event in biome_changed.read() {
+        let entity = tile_entities.0[event.y * world.width + event.x];
+        let Ok((mut tile, material)) = tiles.get_mut(entity) else {
+            continue;
+        };
+        tile.biome = event.to;
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.color = Color::WHITE;
+        }
+        commands.entity(entity).insert(TileFlash {
+            frames_left: TILE_FLASH_FRAMES,
+        });
+    }
+}
+
+fn fade_tile_flash(
+    mut commands: Commands,
+    mut tiles: Query<(
+        Entity,
+        &TileComponent,
+        &MeshMaterial2d<ColorMaterial>,
+        &mut TileFlash,
+    )>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (entity, tile, material, mut flash) in tiles.iter_mut() {
+        flash.frames_left = flash.frames_left.saturating_sub(1);
+        let Some(material) = materials.get_mut(&material.0) else {
+            continue;
+        };
+        if flash.frames_left == 0 {
+            material.color = biome_color(tile.biome);
+            commands.entity(entity).remove::<TileFlash>();
+        } else if flash.frames_left % 10 < 5 {
+            material.color = Color::WHITE;
+        } else {
+            material.color = biome_color(tile.biome);
+        }
+    }
+}
+
+fn flash_catastrophes(
+    mut commands: Commands,
+    mut catastrophe_struck: EventReader<CatastropheStruck>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for event in catastrophe_struck.read() {
+        let color = match event.kind {
+            CatastropheKind::Meteor => Color::srgba(1.0, 0.4, 0.0, 0.5),
+            CatastropheKind::Flood => Color::srgba(0.1, 0.3, 1.0, 0.5),
+        };
+        let radius = (event.radius as f32 + 0.5) * TILE_SIZE_IN_PIXELS;
+
+        commands.spawn((
+            Mesh2d(meshes.add(Circle::new(radius))),
+            MeshMaterial2d(materials.add(color)),
+            Transform::from_xyz(
+                event.x as f32 * TILE_SIZE_IN_PIXELS,
+                event.y as f32 * TILE_SIZE_IN_PIXELS,
+                1.0,
+            ),
+            CatastropheFlash {
+                frames_left: CATASTROPHE_FLASH_FRAMES,
+            },
+        ));
+    }
+}
+
+fn fade_catastrophe_flash(
+    mut commands: Commands,
+    mut flashes: Query<(Entity, &mut CatastropheFlash, &mut Visibility)>,
+) {
+    for (entity, mut flash, mut visibility) in flashes.iter_mut() {
+        flash.frames_left = flash.frames_left.saturating_sub(1);
+        if flash.frames_left == 0 {
+            commands.entity(entity).despawn_recursive();
+        } else if flash.frames_left % 10 < 5 {
+            *visibility = Visibility::Hidden;
+        } else {
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+fn spawn_selection_box(mut commands: Commands) {
+    commands.spawn((
+        Sprite::from_color(Color::srgba(1.0, 1.0, 0.4, 0.25), Vec2::ONE),
+        Transform::from_xyz(0.0, 0.0, 2.0),
+        Visibility::Hidden,
+        SelectionBox,
+    ));
+}
+
+/// The tile under a point in world coordinates, which may lie outside the world.
+fn tile_at(point: Vec2) -> (isize, isize) {
+    let tile = (point / TILE_SIZE_IN_PIXELS).round();
+    (tile.x as isize, tile.y as isize)
+}
+
+/// Drags out a box with the left mouse button and, on release, prints the
+/// statistics of the tiles it covers.
+#[allow(clippy::too_many_arguments)]
+fn box_select(
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut selection: ResMut<BoxSelection>,
+    mut boxes: Query<(&mut Transform, &mut Visibility), With<SelectionBox>>,
+    world: Res<World>,
+    index: Res<SpatialIndex>,
+    predator_index: Res<PredatorSpatialIndex>,
+    food_grid: Res<FoodGrid>,
+    organisms: Query<&Organism, Active>,
+    predators: Query<&Predator, Active>,
+) {
+    let cursor = windows
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position)
+        .zip(cameras.get_single().ok())
+        .and_then(|(cursor, (camera, transform))| {
+            camera.viewport_to_world_2d(transform, cursor).ok()
+        });
+    let Ok((mut rectangle, mut visibility)) = boxes.get_single_mut() else {
+        return;
+    };
+
+    if mouse.just_pressed(MouseButton::Left) {
+        selection.anchor = cursor;
+    }
+    let Some(anchor) = selection.anchor else {
+        return;
+    };
+    let corner = cursor.unwrap_or(anchor);
+    if mouse.pressed(MouseButton::Left) {
+        rectangle.translation = ((anchor + corner) / 2.0).extend(rectangle.translation.z);
+        rectangle.scale = (corner - anchor).abs().max(Vec2::ONE).extend(1.0);
+        *visibility = Visibility::Inherited;
+        return;
+    }
+
+    selection.anchor = None;
+    *visibility = Visibility::Hidden;
+    selection.region = Region::between(tile_at(anchor), tile_at(corner), world.width, world.height);
+    let Some(region) = selection.region else {
+        println!("selection lies outside the world");
+        return;
+    };
+    let stats = region_stats(
+        region,
+        &index,
+        &predator_index.0,
+        &food_grid,
+        |entity| organisms.get(entity).ok().map(Organism::traits),
+        |entity| predators.get(entity).ok().map(Predator::traits),
+    );
+    println!("{}", stats);
+    println!("press R to make the selection a reserve");
+}
+
+/// Turns the last box selected into a reserve with `R`, printing the entry of
+/// `reserves.rectangles` that makes it one from the start of a run.
+fn reserve_selection(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    selection: Res<BoxSelection>,
+    mut world: ResMut<World>,
+    tile_entities: Res<TileEntities>,
+    hatching: Res<ReserveHatching>,
+) {
+    if !keys.just_pressed(KeyCode::KeyR) {
+        return;
+    }
+    let Some(region) = selection.region else {
+        return;
+    };
+    for (x, y) in region.tiles() {
+        let i = y * world.width + x;
+        if !world.grid[i].reserve {
+            world.grid[i].reserve = true;
+            hatching.hatch(&mut commands, tile_entities.0[i]);
+        }
+    }
+    let reserve = region.as_reserve();
+    println!(
+        "reserve added, in a config: rectangles = [{{ x = {}, y = {}, width = {}, height = {} }}]",
+        reserve.x, reserve.y, reserve.width, reserve.height
+    );
+}
+
 fn handle_camera_movement(
     mut query: Query<(&mut Transform, &Camera)>,
     keys: Res<ButtonInput<KeyCode>>,
@@ -281,10 +1271,279 @@ fn zoom_centered(
     (camera_pos + delta, new_scale)
 }
 
+/// Zero-padded so that frames sort in generation order, as ffmpeg expects.
+fn frame_path(prefix: &str, generation: usize) -> PathBuf {
+    PathBuf::from(FRAMES_DIR).join(format!("{}_{:08}.png", prefix, generation))
+}
+
+fn frame_due(interval: Option<usize>, generation: usize) -> bool {
+    match interval {
+        Some(interval) if interval > 0 => generation.is_multiple_of(interval),
+        _ => false,
+    }
+}
+
+fn timelapse_enabled(config: Res<Config>) -> bool {
+    !config.world.headless && config.world.screenshot_interval.is_some()
+}
+
+/// The provenance of a screenshot taken now.
+fn screenshot_provenance(
+    config: &Config,
+    run_id: &RunId,
+    generation: usize,
+    world: &World,
+    organisms: &Living<Organism>,
+    predators: &Living<Predator>,
+) -> Provenance {
+    let hash = living_state_hash(world, organisms, predators, generation);
+    Provenance::new(run_id, config.world.seed, generation, Some(hash))
+}
+
+fn capture_screenshot(commands: &mut Commands, path: PathBuf, provenance: Provenance) {
+    if let Err(err) = std::fs::create_dir_all(FRAMES_DIR) {
+        warn!("Cannot create {}: {}", FRAMES_DIR, err);
+        return;
+    }
+    #[cfg(target_arch = "wasm32")]
+    let save = {
+        let _ = provenance;
+        save_to_disk(path)
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    let save = save_with_provenance(path, provenance);
+    commands.spawn(Screenshot::primary_window()).observe(save);
+}
+
+/// [`save_to_disk`] for PNGs, with `provenance` in their text chunks. Like it, drops
+/// the alpha channel, which holds brightness with HDR on.
+#[cfg(not(target_arch = "wasm32"))]
+fn save_with_provenance(
+    path: PathBuf,
+    provenance: Provenance,
+) -> impl FnMut(Trigger<ScreenshotCaptured>) {
+    use std::io::Cursor;
+
+    use image::ImageFormat;
+
+    use crate::provenance::write_artifact;
+
+    move |trigger| {
+        let image = match trigger.event().0.clone().try_into_dynamic() {
+            Ok(image) => image,
+            Err(err) => {
+                warn!("Cannot save screenshot, unsupported image: {}", err);
+                return;
+            }
+        };
+        let mut png = Cursor::new(Vec::new());
+        if let Err(err) = image.to_rgb8().write_to(&mut png, ImageFormat::Png) {
+            warn!("Cannot encode screenshot: {}", err);
+            return;
+        }
+        match write_artifact(&path, png.into_inner(), &provenance) {
+            Ok(()) => info!("Screenshot saved to {}", path.display()),
+            Err(err) => warn!("Cannot save screenshot, IO error: {}", err),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn capture_timelapse(
+    mut commands: Commands,
+    config: Res<Config>,
+    run_id: Res<RunId>,
+    generation: Res<Generation>,
+    world: Res<World>,
+    organisms: Living<Organism>,
+    predators: Living<Predator>,
+    pending: Query<(), With<Screenshot>>,
+    mut last_frame: Local<Option<usize>>,
+) {
+    if *last_frame == Some(generation.0)
+        || !frame_due(config.world.screenshot_interval, generation.0)
+    {
+        return;
+    }
+    *last_frame = Some(generation.0);
+
+    if !pending.is_empty() {
+        warn!(
+            "Skipping time-lapse frame for generation {}: the previous one is still being saved",
+            generation.0
+        );
+        return;
+    }
+    let provenance = screenshot_provenance(
+        &config,
+        &run_id,
+        generation.0,
+        &world,
+        &organisms,
+        &predators,
+    );
+    capture_screenshot(&mut commands, frame_path("frame", generation.0), provenance);
+}
+
+/// Pauses and resumes the run on `Space`. While paused, `Left` and `Right` step
+/// through the snapshots in the [`History`] and `Enter` returns to the live state;
+/// resuming from an earlier generation branches the run there, archiving the logs
+/// of the abandoned one.
+fn scrub_history(ecs: &mut bevy::ecs::world::World) {
+    // a headless run has no keyboard to scrub with
+    let Some(keys) = ecs.get_resource::<ButtonInput<KeyCode>>() else {
+        return;
+    };
+    let toggle = keys.just_pressed(KeyCode::Space);
+    let back = keys.just_pressed(KeyCode::ArrowLeft);
+    let forward = keys.just_pressed(KeyCode::ArrowRight);
+    let live = keys.just_pressed(KeyCode::Enter);
+    let paused = ecs.resource::<TickClock>().paused;
+
+    let changed = ecs.resource_scope(|ecs, mut history: Mut<History>| {
+        if toggle && paused {
+            if let Some(generation) = history.branch(ecs) {
+                println!("Branched the run at generation {}", generation);
+                match branch_logs(ecs, generation) {
+                    Ok(Some(branch)) => {
+                        println!("The abandoned run's logs are in {}", branch.display())
+                    }
+                    Ok(None) => {}
+                    Err(err) => eprintln!("Failed to archive the abandoned logs: {}", err),
+                }
+            }
+            return false;
+        }
+        if !paused {
+            return false;
+        }
+        if back {
+            history.back(ecs)
+        } else if forward {
+            history.forward(ecs)
+        } else if live {
+            history.return_to_live(ecs)
+        } else {
+            false
+        }
+    });
+    if toggle {
+        ecs.resource_mut::<TickClock>().paused = !paused;
+    }
+    if changed {
+        recolor_tiles(ecs);
+    }
+}
+
+/// Gives every tile whose biome differs from the one in `World` the color of the
+/// latter, after a snapshot put back an earlier world.
+fn recolor_tiles(ecs: &mut bevy::ecs::world::World) {
+    let biomes: Vec<Biome> = ecs
+        .resource::<World>()
+        .grid
+        .iter()
+        .map(|tile| tile.biome)
+        .collect();
+    let tile_entities = ecs.resource::<TileEntities>().0.clone();
+    let mut recolored = Vec::new();
+    for (entity, biome) in tile_entities.into_iter().zip(biomes) {
+        let Some(mut tile) = ecs.get_mut::<TileComponent>(entity) else {
+            continue;
+        };
+        if tile.biome == biome {
+            continue;
+        }
+        tile.biome = biome;
+        if let Some(material) = ecs.get::<MeshMaterial2d<ColorMaterial>>(entity) {
+            recolored.push((material.0.clone(), biome));
+        }
+    }
+    let mut materials = ecs.resource_mut::<Assets<ColorMaterial>>();
+    for (handle, biome) in recolored {
+        if let Some(material) = materials.get_mut(&handle) {
+            material.color = biome_color(biome);
+        }
+    }
+}
+
+/// Exports the stats recorded so far on `E`, as a download in the browser.
+fn export_stats(
+    keys: Res<ButtonInput<KeyCode>>,
+    history: Res<StatsHistory>,
+    store: Option<ResMut<StatsStore>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyE) {
+        return;
+    }
+    let Some(mut store) = store else { return };
+    let records: Vec<_> = history.records().cloned().collect();
+    if let Some(place) = store.export(&records) {
+        println!(
+            "Exported the stats of {} generations to {}",
+            records.len(),
+            place
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn manual_screenshot(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    config: Res<Config>,
+    run_id: Res<RunId>,
+    generation: Res<Generation>,
+    world: Res<World>,
+    organisms: Living<Organism>,
+    predators: Living<Predator>,
+) {
+    if keys.just_pressed(KeyCode::F12) {
+        let provenance = screenshot_provenance(
+            &config,
+            &run_id,
+            generation.0,
+            &world,
+            &organisms,
+            &predators,
+        );
+        capture_screenshot(
+            &mut commands,
+            frame_path("screenshot", generation.0),
+            provenance,
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn frame_paths_are_zero_padded() {
+        assert_eq!(
+            frame_path("frame", 42),
+            PathBuf::from("frames").join("frame_00000042.png")
+        );
+        let mut paths: Vec<_> = [1000, 5, 20]
+            .map(|generation| frame_path("frame", generation))
+            .into();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [5, 20, 1000].map(|generation| frame_path("frame", generation))
+        );
+    }
+
+    #[test]
+    fn frames_are_due_every_interval_only_when_set() {
+        let due: Vec<_> = (0..10)
+            .filter(|&generation| frame_due(Some(3), generation))
+            .collect();
+        assert_eq!(due, vec![0, 3, 6, 9]);
+        assert!((0..10).all(|generation| !frame_due(None, generation)));
+        assert!((0..10).all(|generation| !frame_due(Some(0), generation)));
+    }
+
     #[test]
     fn zoom_in_shifts_camera_toward_cursor() {
         // cursor 50px right of center, zoom in (factor < 1)
@@ -314,9 +1573,127 @@ mod tests {
         assert_eq!(scale, 0.1);
     }
 
+    #[test]
+    fn points_fall_on_the_tile_centered_nearest_them() {
+        let half = TILE_SIZE_IN_PIXELS / 2.0;
+        assert_eq!(tile_at(Vec2::ZERO), (0, 0));
+        assert_eq!(tile_at(Vec2::new(half - 0.1, -half + 0.1)), (0, 0));
+        assert_eq!(
+            tile_at(Vec2::new(3.0 * TILE_SIZE_IN_PIXELS + half + 0.1, 0.0)),
+            (4, 0)
+        );
+        assert_eq!(tile_at(Vec2::new(-2.0 * TILE_SIZE_IN_PIXELS, 0.0)), (-2, 0));
+    }
+
     #[test]
     fn zoom_no_cursor_offset_does_not_translate() {
         let (pos, _) = zoom_centered(Vec2::new(50.0, 50.0), Vec2::ZERO, 1.0, 0.9, 0.1, 10.0);
         assert_eq!(pos, Vec2::new(50.0, 50.0));
     }
+
+    #[test]
+    fn footprints_are_centered_and_clipped() {
+        assert_eq!(footprint(5, 10, 1), 5..6);
+        assert_eq!(footprint(5, 10, 3), 4..7);
+        assert_eq!(footprint(5, 10, 4), 4..8);
+        assert_eq!(footprint(0, 10, 3), 0..2);
+        assert_eq!(footprint(9, 10, 5), 7..10);
+        assert_eq!(footprint(5, 10, 0), 5..6);
+    }
+
+    #[test]
+    fn scaled_entities_cover_their_footprint_and_crowds_win() {
+        // a 5x1 strip: one organism on tile 1, three on tile 3
+        let counts = [0, 1, 0, 3, 0];
+        let sources = scaled_sources(&counts, 5, 3);
+        let drawn: Vec<_> = sources.iter().map(|&source| counts[source]).collect();
+        // tile 2 is covered by both and shows the crowd
+        assert_eq!(drawn, vec![1, 1, 3, 3, 3]);
+
+        let unscaled = scaled_sources(&counts, 5, 1);
+        assert_eq!(unscaled, vec![0, 1, 2, 3, 4]);
+
+        // in two dimensions a scale of 3 covers the 3x3 block around the tile
+        let mut grid = [0u16; 25];
+        grid[2 * 5 + 2] = 1;
+        let covered = scaled_sources(&grid, 5, 3)
+            .iter()
+            .filter(|&&source| grid[source] > 0)
+            .count();
+        assert_eq!(covered, 9);
+    }
+
+    #[test]
+    fn food_dot_levels_track_food() {
+        assert_eq!(food_dot_level(0.0, 100.0, 4), 0);
+        assert_eq!(food_dot_level(10.0, 100.0, 4), 0);
+        assert_eq!(food_dot_level(13.0, 100.0, 4), 1);
+        assert_eq!(food_dot_level(50.0, 100.0, 4), 2);
+        assert_eq!(food_dot_level(100.0, 100.0, 4), 4);
+        // patches above the maximum still show every dot, never more
+        assert_eq!(food_dot_level(250.0, 100.0, 4), 4);
+        assert_eq!(food_dot_level(-1.0, 100.0, 4), 0);
+        assert_eq!(food_dot_level(1.0, 0.0, 4), 4);
+        assert_eq!(food_dot_level(0.0, 0.0, 4), 0);
+    }
+
+    #[test]
+    fn food_dots_toggle_only_the_dots_that_change() {
+        let entities: Vec<Entity> = (0..6).map(Entity::from_raw).collect();
+        let mut dots = FoodDots::new(entities.clone(), 3);
+        assert_eq!(dots.shown, vec![0, 0]);
+
+        let (toggled, visibility) = dots.show(1, 2);
+        assert_eq!(toggled, &entities[3..5]);
+        assert_eq!(visibility, Visibility::Inherited);
+
+        let (toggled, visibility) = dots.show(1, 3);
+        assert_eq!(toggled, &entities[5..6]);
+        assert_eq!(visibility, Visibility::Inherited);
+
+        let (toggled, visibility) = dots.show(1, 1);
+        assert_eq!(toggled, &entities[4..6]);
+        assert_eq!(visibility, Visibility::Hidden);
+
+        // an unchanged level toggles nothing, and levels past the pool are capped
+        assert!(dots.show(1, 1).0.is_empty());
+        assert_eq!(dots.show(0, 9).0, &entities[0..3]);
+        assert_eq!(dots.shown, vec![3, 1]);
+    }
+
+    #[test]
+    fn only_changed_chunks_are_repainted() {
+        let config = crate::utils::default_config();
+        let (width, height) = (CHUNK_SIZE * 2, CHUNK_SIZE * 2);
+        let (world, mut food_grid) = World::new(width, height, 3);
+        let mut occupancy = Occupancy::default();
+        occupancy.reset(width * height);
+        food_grid.clear_dirty();
+        assert!((0..food_grid.chunk_count()).all(|chunk| !food_grid.is_dirty(chunk)));
+
+        // emptying the bottom-left tile flags only the bottom-left chunk
+        food_grid.set(0, 0.0);
+        let repaint: Vec<bool> = (0..food_grid.chunk_count())
+            .map(|chunk| food_grid.is_dirty(chunk))
+            .collect();
+        assert_eq!(repaint, vec![true, false, false, false]);
+
+        let mut data = vec![7u8; width * height * 4];
+        paint_heatmap(
+            &mut data, &repaint, &occupancy, false, &food_grid, &world, &config,
+        );
+        for y in 0..height {
+            for x in 0..width {
+                let tex_idx = ((height - 1 - y) * width + x) * 4;
+                let untouched = data[tex_idx..tex_idx + 4] == [7; 4];
+                assert_eq!(
+                    untouched,
+                    x >= CHUNK_SIZE || y >= CHUNK_SIZE,
+                    "tile ({}, {})",
+                    x,
+                    y
+                );
+            }
+        }
+    }
 }
@@ -1,76 +1,623 @@
+use std::collections::VecDeque;
+use std::f32::consts::{FRAC_PI_4, PI, TAU};
+use std::sync::Mutex;
+
 use bevy::ecs::schedule::SystemSet;
 use bevy::prelude::*;
-use bevy::utils::hashbrown::HashSet;
+use bevy::utils::hashbrown::{HashMap, HashSet};
+use bevy::utils::{Duration, Instant};
 use rand::prelude::*;
-use rand::rngs::SmallRng;
 
-use crate::components::{EntityRng, Organism, Position, Predator};
+use crate::components::{
+    trait_deltas, Active, Dispersing, EntityKind, EntityRng, HuntState, Identity, Inactive,
+    Injured, IntakeHistory, KillMemory, Organism, Panicked, ParentAge, ParentTraits, Position,
+    Predator, PredatorSight, Residence, SpeciesId, Stance, TraitSnapshot,
+};
+use crate::events::{
+    BiomeChangeCause, BiomeChanged, Born, CatastropheKind, CatastropheStruck, Died,
+    FoodPatchAppeared, Killed, PanicSpread, PreyInjured,
+};
 use crate::resources::{
-    AppState, Biome, Config, FoodGrid, Generation, PopulationCount, PredatorSpatialIndex,
-    ReproductionRng, SpatialIndex, SpawnRng, World,
+    clamp_trait, ActiveTiles, AppState, Biome, Bound, CarrionGrid, ClampTally, Config, DeathCause,
+    DeathTally, DesertificationPressure, EnergyFlux, EnergyIntake, FoodAllocation, FoodGrid,
+    FoodPatch, FoodPatches, Generation, KillScentGrid, MovementTrace, NewbornSurvival, NextId,
+    OffspringPlacement, OrganismConfig, PopulationCount, PredatorConfig, PredatorSpatialIndex,
+    ReproductionModel, ResidenceTally, RngStreams, SpatialIndex, TerritoryMap, TickClock, Tile,
+    WanderConfig, World, WorldConfig, PARENTAL_INVESTMENT_BOUNDS, RELATIVE_THRESHOLD_BOUNDS,
 };
-use crate::utils::{get_biome_tolerance, DIRECTIONS};
+use crate::schema::{TraceCandidate, TraceRecord, TraceState, TraceStep, SCHEMA_VERSION};
+use crate::seed_bank::{sample, SeedBank};
+use crate::utils::{get_biome_tolerance, COMPASS, DIRECTIONS, ORTHOGONAL_DIRECTIONS};
 
-#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
-pub struct SimulationSet;
+/// The phases of a tick, in the order they run. Every phase runs only while the
+/// simulation is in [`AppState::Simulate`] and a tick is due, and all of one phase
+/// finishes before the next starts, so a system from another crate can join the tick
+/// with `.in_set(SimulationSet::Metabolism)` and see the crate's components as the
+/// phases around it leave them. Systems that read the state a tick ends in go
+/// `.after(SimulationSet::End)`.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SimulationSet {
+    /// Per-tick counters and tallies are reset.
+    Start,
+    /// Organisms and predators move, paying for it, and the spatial indices are
+    /// rebuilt from their new positions.
+    Movement,
+    /// Predators hunt and the killed are taken out, then panic spreads.
+    Interaction,
+    /// Organisms eat, adapt and share energy, and food grows back.
+    Metabolism,
+    /// Carrion rots, biomes change, catastrophes strike and food patches appear.
+    Environment,
+    /// The dead are taken out, overcrowding culls, survivors reproduce and the entity
+    /// pool is trimmed.
+    Population,
+    /// The generation counter moves on and the run ends at its limit.
+    End,
+}
 
 pub struct SimulationPlugin;
 
 impl Plugin for SimulationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, (spawn_organisms, spawn_predators))
+        app.add_event::<BiomeChanged>()
+            .add_event::<CatastropheStruck>()
+            .add_event::<FoodPatchAppeared>()
+            .add_event::<Born>()
+            .add_event::<Died>()
+            .add_event::<Killed>()
+            .add_event::<PanicSpread>()
+            .add_event::<PreyInjured>()
+            .add_observer(record_birth)
+            .add_observer(record_death)
+            .add_observer(record_retirement)
+            .add_systems(Startup, spawn_organisms)
+            .add_systems(First, advance_tick_clock)
+            .configure_sets(
+                Update,
+                (
+                    SimulationSet::Start,
+                    SimulationSet::Movement,
+                    SimulationSet::Interaction,
+                    SimulationSet::Metabolism,
+                    SimulationSet::Environment,
+                    SimulationSet::Population,
+                    SimulationSet::End,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Simulate).and(tick_due)),
+            )
             .add_systems(
                 Update,
                 (
                     update_population_count,
-                    (organism_movement, predator_movement),
-                    (rebuild_spatial_index, rebuild_predator_spatial_index),
-                    hunting,
-                    (consume_food, biome_adaptation, regenerate_food),
-                    (despawn_dead_organisms, despawn_dead_predators),
-                    (organism_overcrowding, predator_overcrowding),
-                    (reproduction, predator_reproduction),
+                    reset_death_tally,
+                    reset_clamp_tally,
+                    reset_energy_intake,
+                    reset_residence_tally,
+                    open_energy_flux,
+                )
+                    .in_set(SimulationSet::Start),
+            )
+            .add_systems(
+                Update,
+                (
+                    (organism_movement, track_residence).chain(),
+                    (rebuild_spatial_index, close_upkeep),
+                )
+                    .chain()
+                    .in_set(SimulationSet::Movement),
+            )
+            .add_systems(Update, spread_panic.in_set(SimulationSet::Interaction))
+            .add_systems(
+                Update,
+                (
+                    (
+                        consume_food,
+                        biome_adaptation,
+                        morph_selection,
+                        regenerate_food,
+                    ),
+                    share_energy,
+                    start_dispersal,
+                )
+                    .chain()
+                    .in_set(SimulationSet::Metabolism),
+            )
+            .add_systems(
+                Update,
+                (desertification, catastrophes, food_patches, apply_deferred)
+                    .chain()
+                    .in_set(SimulationSet::Environment),
+            )
+            .add_systems(
+                Update,
+                (
+                    despawn_dead_organisms,
+                    organism_overcrowding,
+                    reproduction,
+                    (calm_panicked, end_dispersal, heal_injuries),
                     kill_over_limit_organisms,
+                    trim_entity_pool,
+                )
+                    .chain()
+                    .in_set(SimulationSet::Population),
+            )
+            .add_systems(
+                Update,
+                (
                     increment_generation,
                     run_for_x_generations,
                     print_simulation_progress,
                 )
                     .chain()
-                    .run_if(in_state(AppState::Simulate))
-                    .in_set(SimulationSet),
+                    .in_set(SimulationSet::End),
             )
             .add_systems(Update, exit_app.run_if(in_state(AppState::Finished)));
+
+        let predators_enabled = app
+            .world()
+            .get_resource::<Config>()
+            .is_none_or(|config| config.predator.predators_enabled);
+        if predators_enabled {
+            add_predator_systems(app);
+        }
+    }
+}
+
+/// Schedules the systems that only concern predators, each ordered against the
+/// organism systems of its phase the way it would be in one chain with them. A
+/// prey-only run never adds them.
+fn add_predator_systems(app: &mut App) {
+    app.add_systems(Startup, spawn_predators.after(spawn_organisms))
+        .add_systems(
+            Update,
+            (
+                (predator_movement, territorial_contests)
+                    .chain()
+                    .before(rebuild_spatial_index)
+                    .before(close_upkeep),
+                rebuild_predator_spatial_index
+                    .after(track_residence)
+                    .after(territorial_contests),
+            )
+                .in_set(SimulationSet::Movement),
+        )
+        .add_systems(
+            Update,
+            (
+                (
+                    hunting,
+                    // Killed entities are taken out here, and every system below also
+                    // skips anything with no energy left in case it runs without one.
+                    apply_deferred,
+                )
+                    .chain()
+                    .before(spread_panic),
+                mark_kill_sites.after(spread_panic),
+            )
+                .in_set(SimulationSet::Interaction),
+        )
+        .add_systems(
+            Update,
+            (rot_carrion, fade_kill_scent)
+                .chain()
+                .before(desertification)
+                .in_set(SimulationSet::Environment),
+        )
+        .add_systems(
+            Update,
+            (
+                despawn_dead_predators.before(organism_overcrowding),
+                predator_overcrowding
+                    .after(despawn_dead_organisms)
+                    .before(reproduction),
+                predator_reproduction
+                    .after(organism_overcrowding)
+                    .before(calm_panicked)
+                    .before(end_dispersal)
+                    .before(heal_injuries),
+                claim_territories
+                    .after(reproduction)
+                    .before(kill_over_limit_organisms),
+            )
+                .chain()
+                .in_set(SimulationSet::Population),
+        );
+}
+
+/// Sends [`Born`] for every new individual, whether spawned or recycled from the
+/// entity pool.
+fn record_birth(
+    trigger: Trigger<OnInsert, Identity>,
+    mut commands: Commands,
+    identities: Query<(&Identity, Option<&ParentTraits>, Option<&ParentAge>)>,
+    organisms: Query<&Organism>,
+    predators: Query<&Predator>,
+    mut born: EventWriter<Born>,
+    mut newborns: ResMut<NewbornSurvival>,
+) {
+    let entity = trigger.entity();
+    let Ok((identity, parent_traits, parent_age)) = identities.get(entity) else {
+        return;
+    };
+    if identity.parent.is_some() {
+        newborns.born(identity.birth_generation);
+    }
+    let predator = predators.get(entity).ok();
+    let traits = match (organisms.get(entity), predator) {
+        (Ok(organism), _) => organism.traits(),
+        (_, Some(predator)) => predator.traits(),
+        _ => TraitSnapshot::new(),
+    };
+    born.send(Born {
+        id: identity.id,
+        parent: identity.parent,
+        lineage: identity.lineage,
+        kind: entity_kind(predator.is_some()),
+        generation: identity.birth_generation,
+        mutations: parent_traits.map(|parent_traits| trait_deltas(&traits, &parent_traits.0)),
+        traits,
+        parent_traits: parent_traits.map(|parent_traits| parent_traits.0.clone()),
+        parent_age: parent_age.map(|parent_age| parent_age.0),
+    });
+    if parent_traits.is_some() || parent_age.is_some() {
+        commands
+            .entity(entity)
+            .remove::<(ParentTraits, ParentAge)>();
+    }
+}
+
+/// An individual as it is remembered when it dies.
+type Deceased = (
+    &'static Identity,
+    Option<&'static Organism>,
+    Option<&'static Predator>,
+);
+
+/// Sends [`Died`] for a despawned individual. Pooled ones were recorded when they
+/// entered the pool, so trimming it records nothing.
+fn record_death(
+    trigger: Trigger<OnRemove, Identity>,
+    query: Query<Deceased, Active>,
+    generation: Res<Generation>,
+    died: EventWriter<Died>,
+    newborns: ResMut<NewbornSurvival>,
+    death_tally: ResMut<DeathTally>,
+) {
+    if let Ok(deceased) = query.get(trigger.entity()) {
+        send_death(deceased, generation.0, died, newborns, death_tally);
     }
 }
 
+/// Sends [`Died`] for an individual entering the entity pool, and ends its
+/// territory so that whoever is recycled into the entity does not inherit it.
+fn record_retirement(
+    trigger: Trigger<OnAdd, Inactive>,
+    query: Query<Deceased>,
+    generation: Res<Generation>,
+    died: EventWriter<Died>,
+    newborns: ResMut<NewbornSurvival>,
+    death_tally: ResMut<DeathTally>,
+    territories: Option<ResMut<TerritoryMap>>,
+) {
+    if let Some(mut territories) = territories {
+        territories.dissolve(trigger.entity());
+    }
+    if let Ok(deceased) = query.get(trigger.entity()) {
+        send_death(deceased, generation.0, died, newborns, death_tally);
+    }
+}
+
+fn send_death(
+    (identity, organism, predator): (&Identity, Option<&Organism>, Option<&Predator>),
+    generation: usize,
+    mut died: EventWriter<Died>,
+    mut newborns: ResMut<NewbornSurvival>,
+    mut death_tally: ResMut<DeathTally>,
+) {
+    let age = generation.saturating_sub(identity.birth_generation);
+    if identity.parent.is_some() {
+        newborns.died(identity.birth_generation, age);
+    }
+    let (kind, traits) = match (organism, predator) {
+        (Some(organism), _) => {
+            death_tally
+                .organism_offspring
+                .record(identity.offspring_count);
+            (EntityKind::Organism, organism.traits())
+        }
+        (_, Some(predator)) => {
+            death_tally
+                .predator_offspring
+                .record(identity.offspring_count);
+            (EntityKind::Predator, predator.traits())
+        }
+        _ => (EntityKind::Organism, TraitSnapshot::new()),
+    };
+    died.send(Died {
+        id: identity.id,
+        kind,
+        generation,
+        age,
+        offspring: identity.offspring_count,
+        traits,
+    });
+}
+
+fn entity_kind(is_predator: bool) -> EntityKind {
+    if is_predator {
+        EntityKind::Predator
+    } else {
+        EntityKind::Organism
+    }
+}
+
+/// `threshold` as a reproduction trait: unchanged with absolute thresholds, otherwise
+/// moved into `RELATIVE_THRESHOLD_BOUNDS` of `max_energy`. Absolute thresholds from
+/// older configs are converted this way at founding.
+pub(crate) fn coupled_reproduction_threshold(
+    threshold: f32,
+    max_energy: f32,
+    config: &Config,
+) -> f32 {
+    clamped_reproduction_threshold(threshold, max_energy, config).0
+}
+
+/// [`coupled_reproduction_threshold`] along with the relative bound it was clamped to.
+fn clamped_reproduction_threshold(
+    threshold: f32,
+    max_energy: f32,
+    config: &Config,
+) -> (f32, Option<Bound>) {
+    if !config.organism.relative_reproduction_threshold {
+        return (threshold, None);
+    }
+    let (low, high) = RELATIVE_THRESHOLD_BOUNDS;
+    let (fraction, bound) = clamp_trait(threshold / max_energy, low, high);
+    (fraction * max_energy, bound)
+}
+
+/// Chance of reproducing in a tick with `energy` under the `probabilistic` model:
+/// logistic in `(energy - threshold) / scale`, so the threshold is the midpoint.
+fn reproduction_chance(energy: f32, threshold: f32, scale: f32) -> f32 {
+    1.0 / (1.0 + (-(energy - threshold) / scale).exp())
+}
+
+/// A random change of up to `factor` either way for a child's trait. A factor of 0
+/// draws nothing, as the range would be empty, and leaves the trait as it was.
+fn mutation(rng: &mut impl Rng, factor: f32) -> f32 {
+    if factor == 0.0 {
+        return 0.0;
+    }
+    rng.gen_range(-factor..factor)
+}
+
+/// Whether an individual with `energy` and `threshold` reproduces this tick. Only
+/// the `probabilistic` model draws from `rng`, so threshold runs replay unchanged.
+fn decides_to_reproduce(
+    energy: f32,
+    threshold: f32,
+    world: &WorldConfig,
+    rng: &mut impl Rng,
+) -> bool {
+    match world.reproduction_model {
+        ReproductionModel::Threshold => energy > threshold,
+        ReproductionModel::Probabilistic => {
+            rng.gen::<f32>() < reproduction_chance(energy, threshold, world.reproduction_scale)
+        }
+    }
+}
+
+/// An organism with the initial traits from `config`.
+pub(crate) fn founder_organism(config: &Config, biome_tolerance: [f32; 4]) -> Organism {
+    Organism {
+        energy: config.organism.initial_organism_energy,
+        speed: config.organism.initial_organism_speed,
+        size: config.organism.initial_organism_size,
+        reproduction_threshold: coupled_reproduction_threshold(
+            config.organism.initial_organism_reproduction_threshold,
+            config.organism.max_organism_energy,
+            config,
+        ),
+        reproduction_cooldown: config.organism.organism_reproduction_cooldown,
+        biome_tolerance,
+        boldness: config.organism.initial_organism_boldness,
+        exploration: config.organism.initial_organism_exploration,
+        morph: 0,
+        parental_investment: config.organism.initial_parental_investment,
+        dispersal: config.organism.initial_organism_dispersal,
+        preferred_temperature: config.organism.initial_organism_preferred_temperature,
+        migratory_tendency: config.organism.initial_organism_migratory_tendency,
+        altruism: config.organism.kin_sharing.initial_altruism,
+        starvation_trigger: config.organism.starvation_dispersal.initial_trigger,
+        burst_duration: config.organism.starvation_dispersal.initial_duration,
+        reserve_capacity: config.organism.fat_storage.initial_capacity,
+        breeding_flexibility: config.organism.breeding.initial_flexibility,
+        giving_up_density: config.organism.giving_up.initial_density,
+    }
+}
+
+/// Most energy `organism` can hold: its `reserve_capacity` under `fat_storage`,
+/// otherwise unlimited.
+pub(crate) fn energy_cap(organism: &Organism, config: &Config) -> f32 {
+    if config.organism.fat_storage.enabled {
+        organism.reserve_capacity
+    } else {
+        f32::INFINITY
+    }
+}
+
+/// How much of `gain` an organism with `energy` can take in below `cap`. Energy it
+/// already holds above the cap is kept.
+fn capped_gain(energy: f32, gain: f32, cap: f32) -> f32 {
+    gain.min((cap - energy).max(0.0))
+}
+
+/// The size `organism` moves and is spotted as, made heavier under `fat_storage` by
+/// the reserve it can carry.
+pub(crate) fn effective_size(organism: &Organism, config: &Config) -> f32 {
+    let settings = &config.organism.fat_storage;
+    if !settings.enabled {
+        return organism.size;
+    }
+    let reserve = organism.reserve_capacity / config.organism.max_organism_energy;
+    organism.size * (1.0 + settings.weight_coupling * reserve)
+}
+
+/// A founder's morph, drawn uniformly.
+pub(crate) fn random_morph(config: &Config, rng: &mut impl Rng) -> u8 {
+    rng.gen_range(0..config.organism.morphs.max(1))
+}
+
+/// An offspring's morph: the parent's, except with `probability` one of the other
+/// `morphs` picked uniformly.
+fn inherit_morph(parent: u8, morphs: u8, probability: f32, rng: &mut impl Rng) -> u8 {
+    if morphs < 2 || probability <= 0.0 || rng.gen::<f32>() >= probability {
+        return parent;
+    }
+    let other = rng.gen_range(0..morphs - 1);
+    if other >= parent {
+        other + 1
+    } else {
+        other
+    }
+}
+
+/// A predator with the initial traits from `config`.
+pub(crate) fn founder_predator(config: &Config) -> Predator {
+    Predator {
+        energy: config.predator.initial_predator_energy,
+        speed: config.predator.initial_predator_speed,
+        size: config.predator.initial_predator_size,
+        reproduction_threshold: coupled_reproduction_threshold(
+            config.predator.initial_predator_reproduction_threshold,
+            config.predator.max_predator_energy,
+            config,
+        ),
+        hunting_efficiency: config.predator.initial_predator_hunting_efficiency,
+        satiation_threshold: config.predator.initial_predator_satiation_threshold,
+        reproduction_cooldown: config.predator.predator_reproduction_cooldown,
+        preferred_temperature: config.predator.initial_predator_preferred_temperature,
+        hunt_cooldown_ticks: config.predator.hunt_cooldown_ticks as f32,
+        hunt_cooldown_remaining: 0,
+        patience: config.predator.ambush.initial_patience,
+        stance: Stance::Roaming,
+        heading: 0.0,
+        aggression: config.predator.territory.initial_aggression,
+        breeding_flexibility: config.predator.predator_breeding.initial_flexibility,
+        recent_kills: KillMemory::default(),
+    }
+}
+
+/// A random wandering direction for a founder predator, drawn only with `wander` on
+/// so that runs without it keep their random streams.
+fn founder_heading(config: &Config, rng: &mut impl Rng) -> f32 {
+    if config.predator.wander.enabled {
+        rng.gen_range(0.0..TAU)
+    } else {
+        0.0
+    }
+}
+
+/// A random tile that is not water, or any tile in a world of water only.
+fn random_habitable_tile(world: &World, rng: &mut impl Rng) -> (usize, usize) {
+    let habitable: Vec<_> = (0..world.grid.len())
+        .filter(|&i| world.grid[i].biome != Biome::Water)
+        .collect();
+    let i = habitable
+        .choose(rng)
+        .copied()
+        .unwrap_or_else(|| rng.gen_range(0..world.grid.len()));
+    (i % world.width, i / world.width)
+}
+
 fn spawn_organisms(
     mut commands: Commands,
     world: Res<World>,
     config: Res<Config>,
-    mut rng: ResMut<SpawnRng>,
+    seed_bank: Option<Res<SeedBank>>,
+    streams: Res<RngStreams>,
+    mut next_id: ResMut<NextId>,
 ) {
+    let mut rng = streams.for_system("spawn_organisms", 0);
     let organism_count = config.organism.initial_organisms;
 
-    for _ in 0..organism_count {
-        let x = rng.0.gen_range(0..world.width);
-        let y = rng.0.gen_range(0..world.height);
+    // Seeded founders keep their traits but start with fresh energy. They all
+    // belong to species A.
+    if let Some(bank) = seed_bank.filter(|bank| !bank.organisms.is_empty()) {
+        for seed in sample(&bank.organisms, organism_count, &mut rng) {
+            let (x, y) = random_habitable_tile(&world, &mut rng);
+            let id = next_id.allocate();
+            commands.spawn((
+                Organism {
+                    energy: config.organism.initial_organism_energy,
+                    reproduction_cooldown: 0.0,
+                    ..seed.clone()
+                },
+                Position { x, y },
+                EntityRng(streams.for_entity(id, 0)),
+                Identity::founder(id, 0),
+                SpeciesId::A,
+            ));
+        }
+    } else {
+        spawn_founders(
+            &mut commands,
+            &world,
+            &config,
+            SpeciesId::A,
+            &mut rng,
+            &streams,
+            &mut next_id,
+        );
+    }
+
+    // Species B always starts from its config, after species A so a second species
+    // leaves the founders of the first one unchanged.
+    if config.species_b.is_some() {
+        let species_config = Config {
+            organism: config.species_organism(SpeciesId::B).into_owned(),
+            ..config.clone()
+        };
+        spawn_founders(
+            &mut commands,
+            &world,
+            &species_config,
+            SpeciesId::B,
+            &mut rng,
+            &streams,
+            &mut next_id,
+        );
+    }
+}
+
+/// `initial_organisms` founders of `species` with the traits from `config`.
+fn spawn_founders(
+    commands: &mut Commands,
+    world: &World,
+    config: &Config,
+    species: SpeciesId,
+    rng: &mut impl Rng,
+    streams: &RngStreams,
+    next_id: &mut NextId,
+) {
+    for _ in 0..config.organism.initial_organisms {
+        let x = rng.gen_range(0..world.width);
+        let y = rng.gen_range(0..world.height);
 
         let tile_biome = world.tile(x, y).biome;
 
-        let biome_tolerance = get_biome_tolerance(tile_biome, &mut rng.0);
-        let entity_seed: u64 = rng.0.gen();
+        let biome_tolerance = get_biome_tolerance(tile_biome, rng);
+        let id = next_id.allocate();
+        let mut organism = founder_organism(config, biome_tolerance);
+        organism.morph = random_morph(config, rng);
 
         commands.spawn((
-            Organism {
-                energy: config.organism.initial_organism_energy,
-                speed: config.organism.initial_organism_speed,
-                size: config.organism.initial_organism_size,
-                reproduction_threshold: config.organism.initial_organism_reproduction_threshold,
-                reproduction_cooldown: config.organism.organism_reproduction_cooldown,
-                biome_tolerance,
-            },
+            organism,
             Position { x, y },
-            EntityRng(SmallRng::seed_from_u64(entity_seed)),
+            EntityRng(streams.for_entity(id, 0)),
+            Identity::founder(id, 0),
+            species,
         ));
     }
 }
@@ -79,81 +626,270 @@ fn spawn_predators(
     mut commands: Commands,
     world: Res<World>,
     config: Res<Config>,
-    mut rng: ResMut<SpawnRng>,
+    seed_bank: Option<Res<SeedBank>>,
+    streams: Res<RngStreams>,
+    mut next_id: ResMut<NextId>,
 ) {
+    let mut rng = streams.for_system("spawn_predators", 0);
     let predator_count = config.predator.initial_predators;
 
+    if let Some(bank) = seed_bank.filter(|bank| !bank.predators.is_empty()) {
+        for seed in sample(&bank.predators, predator_count, &mut rng) {
+            let (x, y) = random_habitable_tile(&world, &mut rng);
+            let id = next_id.allocate();
+            commands.spawn((
+                Predator {
+                    energy: config.predator.initial_predator_energy,
+                    reproduction_cooldown: 0.0,
+                    hunt_cooldown_remaining: 0,
+                    stance: Stance::Roaming,
+                    heading: founder_heading(&config, &mut rng),
+                    ..*seed
+                },
+                Position { x, y },
+                EntityRng(streams.for_entity(id, 0)),
+                Identity::founder(id, 0),
+            ));
+        }
+        return;
+    }
+
     for _ in 0..predator_count {
-        let x = rng.0.gen_range(0..world.width);
-        let y = rng.0.gen_range(0..world.height);
+        let x = rng.gen_range(0..world.width);
+        let y = rng.gen_range(0..world.height);
 
-        let entity_seed: u64 = rng.0.gen();
+        let id = next_id.allocate();
 
         commands.spawn((
             Predator {
-                energy: config.predator.initial_predator_energy,
-                speed: config.predator.initial_predator_speed,
-                size: config.predator.initial_predator_size,
-                reproduction_threshold: config.predator.initial_predator_reproduction_threshold,
-                hunting_efficiency: config.predator.initial_predator_hunting_efficiency,
-                satiation_threshold: config.predator.initial_predator_satiation_threshold,
-                reproduction_cooldown: config.predator.predator_reproduction_cooldown,
+                heading: founder_heading(&config, &mut rng),
+                ..founder_predator(&config)
             },
             Position { x, y },
-            EntityRng(SmallRng::seed_from_u64(entity_seed)),
+            EntityRng(streams.for_entity(id, 0)),
+            Identity::founder(id, 0),
         ));
     }
 }
 
+/// The fear and food terms of an organism's cost for stepping onto a tile with
+/// `food` and `predators`, the second negative. Bold organisms discount the fear of
+/// predators and are drawn more strongly towards food.
+fn fear_and_food_terms(
+    food: f32,
+    predators: usize,
+    boldness: f32,
+    config: &OrganismConfig,
+) -> (f32, f32) {
+    let fear = config.predator_fear_weight * predators as f32 * (1.0 - boldness);
+    let attraction = config.food_attraction_weight * food * (1.0 + boldness);
+    (fear, -attraction)
+}
+
+/// Movement cost a panicked organism saves per tile of distance from the kill it flees.
+const PANIC_FLEE_WEIGHT: f32 = 100.0;
+
+/// An organism that moves, with the states that change how it moves.
+type Mover = (
+    &'static mut Position,
+    &'static mut Organism,
+    &'static mut EntityRng,
+    Option<&'static Panicked>,
+    Option<&'static mut Dispersing>,
+    Option<&'static Injured>,
+    Option<&'static Identity>,
+);
+
+#[allow(clippy::too_many_arguments)]
 fn organism_movement(
-    mut query: Query<(&mut Position, &mut Organism, &mut EntityRng)>,
+    mut query: Query<Mover, Active>,
     world: Res<World>,
+    food_grid: Res<FoodGrid>,
+    predator_index: Res<PredatorSpatialIndex>,
+    kill_scent: Res<KillScentGrid>,
+    config: Res<Config>,
+    generation: Res<Generation>,
+    streams: Res<RngStreams>,
+    mut trace: ResMut<MovementTrace>,
 ) {
-    query
-        .par_iter_mut()
-        .for_each(|(mut position, mut organism, mut entity_rng)| {
+    let trace_entity = config.logging.trace_entity;
+    let traced_record = Mutex::new(None);
+    query.par_iter_mut().for_each(
+        |(
+            mut position,
+            mut organism,
+            mut entity_rng,
+            panicked,
+            mut dispersing,
+            injured,
+            identity,
+        )| {
             if organism.energy <= 0.0 {
                 return;
             }
+            let id = identity.map_or(u64::MAX, |identity| identity.id);
+            let traced = trace_entity == Some(id);
+            let energy = organism.energy;
+            let record = |state, steps| {
+                *traced_record.lock().unwrap() = Some(TraceRecord {
+                    schema_version: SCHEMA_VERSION,
+                    generation: generation.0,
+                    id,
+                    state,
+                    energy,
+                    steps,
+                });
+            };
+            let metabolism = identity.map_or(1.0, |identity| {
+                let age = generation.0.saturating_sub(identity.birth_generation);
+                config.organism.aging.metabolic_factor(age)
+            });
 
             let rng = &mut entity_rng.0;
             let base_moves = organism.speed.floor() as u32;
             let extra = u32::from(rng.gen::<f32>() < organism.speed.fract());
-            let total_moves = (base_moves + extra).max(1);
+            let mut total_moves = (base_moves + extra).max(1);
+            if panicked.is_some() {
+                total_moves = (total_moves as f32 * config.organism.panic.speed_multiplier)
+                    .ceil()
+                    .max(1.0) as u32;
+            }
+            if let Some(injured) = injured {
+                total_moves = injured_moves(
+                    total_moves,
+                    injured.severity,
+                    config.organism.injury.movement_penalty,
+                );
+            }
+            if let (Some(dispersing), None) = (dispersing.as_deref_mut(), panicked) {
+                disperse(
+                    &mut position,
+                    &mut organism,
+                    dispersing,
+                    total_moves,
+                    metabolism,
+                    &world,
+                    &config,
+                    rng,
+                );
+                if traced {
+                    record(TraceState::Dispersing, Vec::new());
+                }
+                return;
+            }
+            if config.organism.giving_up.enabled
+                && panicked.is_none()
+                && food_grid.get(position.y * world.width + position.x) > organism.giving_up_density
+            {
+                // still worth grazing here
+                if traced {
+                    record(TraceState::Grazing, Vec::new());
+                }
+                return;
+            }
 
-            for _ in 0..total_moves {
-                let mut best_direction = (0isize, 0isize);
-                let mut best_cost = f32::MAX;
+            let mut steps = Vec::new();
+            for move_index in 0..total_moves as usize {
+                let mut costs = [0.0; DIRECTIONS.len()];
+                let mut candidates = Vec::new();
+                let comfort = match panicked {
+                    None if organism.migratory_tendency > 0.0 => comfort_direction(
+                        &world,
+                        position.x,
+                        position.y,
+                        organism.preferred_temperature,
+                        config.organism.temperature_tolerance,
+                    ),
+                    _ => None,
+                };
 
-                for &(dx, dy) in DIRECTIONS.iter() {
+                for (cost, &(dx, dy)) in costs.iter_mut().zip(DIRECTIONS.iter()) {
                     let new_x =
                         (position.x as isize + dx).clamp(0, (world.width - 1) as isize) as usize;
                     let new_y =
                         (position.y as isize + dy).clamp(0, (world.height - 1) as isize) as usize;
                     let tile = world.tile(new_x, new_y);
 
-                    let base_cost = match tile.biome {
-                        Biome::Water => 100.0,
-                        Biome::Desert => 50.0,
-                        Biome::Grassland => 10.0,
-                        Biome::Forest => 20.0,
-                    };
+                    let base_cost = config.world.organism_movement_cost(tile.biome);
 
                     let tolerance = organism.biome_tolerance[tile.biome.idx()];
-                    let cost = base_cost / tolerance + rng.gen_range(0.0..5.0_f32);
-
-                    if cost < best_cost {
-                        best_cost = cost;
-                        best_direction = (dx, dy);
+                    let mut candidate = TraceCandidate {
+                        dx,
+                        dy,
+                        biome: tile.biome,
+                        base_cost,
+                        tolerance,
+                        terrain: base_cost / tolerance,
+                        fear: 0.0,
+                        food: 0.0,
+                        kill_scent: 0.0,
+                        migration: 0.0,
+                        flee: 0.0,
+                        noise: 0.0,
+                        total: 0.0,
+                    };
+                    if let Some(panicked) = panicked {
+                        let dx = new_x as f32 - panicked.x as f32;
+                        let dy = new_y as f32 - panicked.y as f32;
+                        candidate.flee = -PANIC_FLEE_WEIGHT * (dx * dx + dy * dy).sqrt();
+                    } else {
+                        let tile_index = new_y * world.width + new_x;
+                        (candidate.fear, candidate.food) = fear_and_food_terms(
+                            food_grid.get(tile_index),
+                            predator_index.0.get(new_x, new_y).len(),
+                            organism.boldness,
+                            &config.organism,
+                        );
+                        candidate.kill_scent = config
+                            .predator
+                            .kill_scent
+                            .organism_bias(kill_scent.0[tile_index]);
+                        if let Some(comfort) = comfort {
+                            let step = Vec2::new(dx as f32, dy as f32).normalize();
+                            candidate.migration =
+                                -(organism.migratory_tendency * step.dot(comfort));
+                        }
+                        if config.organism.legacy_movement_jitter {
+                            candidate.noise = rng.gen_range(0.0..5.0_f32);
+                        }
+                    }
+                    candidate.total = candidate.sum();
+                    *cost = candidate.total;
+                    if traced {
+                        candidates.push(candidate);
                     }
                 }
 
+                let temperature = if config.organism.legacy_movement_jitter || panicked.is_some() {
+                    0.0
+                } else {
+                    organism.exploration
+                };
+                let tie = |direction: usize| {
+                    streams.tie_break(
+                        id,
+                        generation.0,
+                        (move_index * DIRECTIONS.len() + direction) as u64,
+                    )
+                };
+                let chosen = softmax_pick(&costs, temperature, tie, rng);
+                if traced {
+                    steps.push(TraceStep {
+                        from: *position,
+                        temperature,
+                        candidates,
+                        chosen,
+                    });
+                }
+                let best_direction = DIRECTIONS[chosen];
+
                 position.x = (position.x as isize + best_direction.0)
                     .clamp(0, (world.width - 1) as isize) as usize;
                 position.y = (position.y as isize + best_direction.1)
                     .clamp(0, (world.height - 1) as isize) as usize;
 
-                organism.energy -= 0.1 * organism.speed * organism.size;
+                organism.energy -=
+                    0.1 * organism.speed * effective_size(&organism, &config) * metabolism;
 
                 let tile = world.tile(position.x, position.y);
                 if tile.biome == Biome::Water {
@@ -161,31 +897,255 @@ fn organism_movement(
                     break;
                 }
             }
-        });
+            if traced {
+                let state = match panicked {
+                    Some(_) => TraceState::Panicked,
+                    None => TraceState::Foraging,
+                };
+                record(state, steps);
+            }
+        },
+    );
+    if let Some(record) = traced_record.into_inner().unwrap() {
+        trace.0.push(record);
+    }
+}
+
+/// Counts another tick on its tile for every organism, tallying the visits that its
+/// moves this tick ended.
+fn track_residence(
+    mut query: Query<(&Position, &mut Residence), (With<Organism>, Active)>,
+    mut tally: ResMut<ResidenceTally>,
+) {
+    for (position, mut residence) in query.iter_mut() {
+        if let Some(ticks) = residence.stay((position.x, position.y)) {
+            tally.0.push(ticks);
+        }
+    }
+}
+
+/// Moves a dispersing organism `moves` steps along its wandering heading, ignoring
+/// food and paying `energy_cost_multiplier` times the usual cost per step, scaled by
+/// its `metabolism`. Water and the edge of the world turn it around instead.
+#[allow(clippy::too_many_arguments)]
+fn disperse(
+    position: &mut Position,
+    organism: &mut Organism,
+    dispersing: &mut Dispersing,
+    moves: u32,
+    metabolism: f32,
+    world: &World,
+    config: &Config,
+    rng: &mut impl Rng,
+) {
+    let settings = &config.organism.starvation_dispersal;
+    for _ in 0..moves {
+        dispersing.heading = next_heading(dispersing.heading, settings.heading_persistence, rng);
+        let (dx, dy) = COMPASS[dispersing.heading];
+        let (x, y) = (position.x as isize + dx, position.y as isize + dy);
+        let inside = x >= 0 && y >= 0 && (x as usize) < world.width && (y as usize) < world.height;
+        if !inside || world.tile(x as usize, y as usize).biome == Biome::Water {
+            dispersing.heading = (dispersing.heading + COMPASS.len() / 2) % COMPASS.len();
+            continue;
+        }
+        position.x = x as usize;
+        position.y = y as usize;
+        organism.energy -= 0.1
+            * organism.speed
+            * effective_size(organism, config)
+            * settings.energy_cost_multiplier
+            * metabolism;
+    }
+}
+
+/// Unit vector along which the temperature around `(x, y)` moves towards
+/// `preferred`, from the differences between the neighbouring tiles on either side.
+/// `None` while the tile is within `tolerance` degrees of `preferred` or the ground
+/// around it is flat.
+pub(crate) fn comfort_direction(
+    world: &World,
+    x: usize,
+    y: usize,
+    preferred: f32,
+    tolerance: f32,
+) -> Option<Vec2> {
+    let deviation = world.tile(x, y).temperature - preferred;
+    if deviation.abs() <= tolerance {
+        return None;
+    }
+    let temperature = |x: usize, y: usize| world.tile(x, y).temperature;
+    let (left, right) = (x.saturating_sub(1), (x + 1).min(world.width - 1));
+    let (down, up) = (y.saturating_sub(1), (y + 1).min(world.height - 1));
+    let gradient = Vec2::new(
+        temperature(right, y) - temperature(left, y),
+        temperature(x, up) - temperature(x, down),
+    );
+    // too hot means heading down the gradient, too cold up it
+    (-deviation.signum() * gradient).try_normalize()
+}
+
+/// Temperatures below this are treated as fully greedy.
+const MIN_EXPLORATION: f32 = 1e-3;
+
+/// Samples an index with probability proportional to `exp(-cost / temperature)`,
+/// falling back to the cheapest index as the temperature approaches zero. Among
+/// equally cheap indices that is the one `tie` ranks lowest, not the first.
+fn softmax_pick(
+    costs: &[f32],
+    temperature: f32,
+    tie: impl Fn(usize) -> u64,
+    rng: &mut impl Rng,
+) -> usize {
+    let (argmin, &min_cost) = costs
+        .iter()
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(b.1).then_with(|| tie(a.0).cmp(&tie(b.0))))
+        .expect("at least one candidate");
+    if temperature < MIN_EXPLORATION {
+        return argmin;
+    }
+
+    let weight = |cost: f32| (-(cost - min_cost) / temperature).exp();
+    let total: f32 = costs.iter().map(|&cost| weight(cost)).sum();
+    let mut roll = rng.gen_range(0.0..total);
+    for (i, &cost) in costs.iter().enumerate() {
+        roll -= weight(cost);
+        if roll < 0.0 {
+            return i;
+        }
+    }
+    argmin
+}
+
+/// Degrees of preferred-temperature drift per unit of mutation.
+const TEMPERATURE_MUTATION_SCALE: f32 = 10.0;
+
+/// A predator's hunting ability relative to an adult's: `juvenile_factor` at birth,
+/// rising linearly to 1 at `predator_maturity_age`. Founders, and predators without
+/// an identity, are adults.
+pub(crate) fn predator_maturity(
+    identity: Option<&Identity>,
+    generation: usize,
+    config: &PredatorConfig,
+) -> f32 {
+    let maturity_age = config.predator_maturity_age;
+    let Some(identity) = identity.filter(|identity| identity.parent.is_some()) else {
+        return 1.0;
+    };
+    let age = generation.saturating_sub(identity.birth_generation);
+    if age >= maturity_age {
+        return 1.0;
+    }
+    let factor = config.juvenile_factor;
+    factor + (1.0 - factor) * age as f32 / maturity_age as f32
+}
+
+/// Tiles a predator with `maturity` sees prey within from a biome with `vision`.
+pub fn sight_radius(seek_radius: usize, maturity: f32, vision: f32) -> isize {
+    (seek_radius as f32 * maturity * vision).round() as isize
+}
+
+/// Whether a predator stays put this tick instead of moving: it keeps up an ambush
+/// while the closest prey in sight is within striking reach, and with none in sight
+/// lies in wait with a chance of its patience.
+fn holds_ambush(predator: &Predator, closest: Option<(isize, isize)>, rng: &mut impl Rng) -> bool {
+    match closest {
+        Some((dx, dy)) => predator.stance == Stance::Ambush && dx.abs() <= 1 && dy.abs() <= 1,
+        None => rng.gen::<f32>() < predator.patience,
+    }
+}
+
+/// How much more a predator pays to live on `tile` than on a neutral tile, from the
+/// biome's decay factor and the mismatch with its preferred temperature.
+fn predator_upkeep_factor(tile: &Tile, predator: &Predator, config: &Config) -> f32 {
+    let biome_factor = config.world.biome(tile.biome).predator_decay_factor;
+    let thermal_stress = (tile.temperature - predator.preferred_temperature).abs();
+    biome_factor * (1.0 + config.predator.predator_thermoregulation_cost * thermal_stress)
+}
+
+/// Turns a wandering predator's `heading` by a correlated random walk, see
+/// [`WanderConfig`](crate::resources::WanderConfig), with `costs` of the tiles
+/// around it in `DIRECTIONS` order pushing it away from the dearer ones.
+fn wander_heading(heading: f32, costs: &[f32], settings: &WanderConfig, rng: &mut impl Rng) -> f32 {
+    let mut heading = heading + rng.gen_range(-settings.turn_noise..=settings.turn_noise);
+    if rng.gen::<f32>() < settings.turn_chance {
+        heading = rng.gen_range(0.0..TAU);
+    }
+    let mean = costs.iter().sum::<f32>() / costs.len() as f32;
+    let push: Vec2 = DIRECTIONS
+        .iter()
+        .zip(costs)
+        .map(|(&(dx, dy), &cost)| Vec2::new(dx as f32, dy as f32).normalize() * (mean - cost))
+        .sum();
+    let facing = Vec2::from_angle(heading) + settings.cost_repulsion * push;
+    facing.to_angle().rem_euclid(TAU)
+}
+
+/// The neighbouring step closest to `heading`, measured anticlockwise from +x.
+fn heading_step(heading: f32) -> (isize, isize) {
+    let eighth = (heading / FRAC_PI_4).round();
+    let direction = Vec2::from_angle(eighth * FRAC_PI_4).round();
+    (direction.x as isize, direction.y as isize)
+}
+
+/// Whether a step of `(dx, dy)` from `position` stays in the world.
+fn inside(world: &World, position: &Position, (dx, dy): (isize, isize)) -> bool {
+    let (x, y) = (position.x as isize + dx, position.y as isize + dy);
+    x >= 0 && y >= 0 && (x as usize) < world.width && (y as usize) < world.height
 }
 
+/// A predator that moves, with what it sees and knows of its own age.
+type Stalker = (
+    Entity,
+    &'static mut Position,
+    &'static mut Predator,
+    &'static mut EntityRng,
+    &'static mut PredatorSight,
+    Option<&'static Identity>,
+);
+
+#[allow(clippy::too_many_arguments)]
 fn predator_movement(
-    mut predator_query: Query<(&mut Position, &mut Predator, &mut EntityRng)>,
+    mut predator_query: Query<Stalker, Active>,
     world: Res<World>,
     config: Res<Config>,
     index: Res<SpatialIndex>,
+    generation: Res<Generation>,
+    territories: Res<TerritoryMap>,
+    kill_scent: Res<KillScentGrid>,
+    streams: Res<RngStreams>,
 ) {
-    let radius = config.predator.predator_seek_radius as isize;
-
+    let territory = &config.predator.territory;
+    let wander = &config.predator.wander;
     predator_query.par_iter_mut().for_each(
-        |(mut predator_position, mut predator, mut entity_rng)| {
+        |(entity, mut predator_position, mut predator, mut entity_rng, mut sight, identity)| {
             if predator.energy <= 0.0 {
                 return;
             }
+            let maturity = predator_maturity(identity, generation.0, &config.predator);
+            let id = identity.map_or(u64::MAX, |identity| identity.id);
 
             let rng = &mut entity_rng.0;
             let base_moves = predator.speed.floor() as u32;
             let extra = u32::from(rng.gen::<f32>() < predator.speed.fract());
             let total_moves = (base_moves + extra).max(1);
 
-            for _ in 0..total_moves {
+            for step in 0..total_moves {
+                // equally good choices go to the lowest rank, whatever order they
+                // were looked at in
+                let tie = |choice: u64| {
+                    streams.tie_break(id, generation.0, choice ^ ((step as u64) << 32))
+                };
+                let biome = world.tile(predator_position.x, predator_position.y).biome;
+                let radius = sight_radius(
+                    config.predator.predator_seek_radius,
+                    maturity,
+                    config.world.biome(biome).predator_vision,
+                );
+                sight.radius = radius as usize;
                 let mut closest_offset: Option<(isize, isize)> = None;
-                let mut min_dist_sq = i32::MAX;
+                let mut min_dist_sq = (i32::MAX, u64::MAX);
+                sight.target = None;
 
                 for ddx in -radius..=radius {
                     for ddy in -radius..=radius {
@@ -196,15 +1156,32 @@ fn predator_movement(
                             .clamp(0, world.height as isize - 1)
                             as usize;
                         if !index.get(nx, ny).is_empty() {
-                            let d = (ddx * ddx + ddy * ddy) as i32;
+                            let d = (
+                                (ddx * ddx + ddy * ddy) as i32,
+                                tie((ny * world.width + nx) as u64),
+                            );
                             if d < min_dist_sq {
                                 min_dist_sq = d;
                                 closest_offset = Some((ddx, ddy));
+                                sight.target = Some((nx, ny));
                             }
                         }
                     }
                 }
 
+                let ambush = &config.predator.ambush;
+                if ambush.enabled && step == 0 && holds_ambush(&predator, closest_offset, rng) {
+                    predator.stance = Stance::Ambush;
+                    let tile = world.tile(predator_position.x, predator_position.y);
+                    predator.energy -= config.predator.predator_energy_decay_rate
+                        * predator.speed
+                        * predator.size
+                        * predator_upkeep_factor(tile, &predator, &config)
+                        * ambush.decay_factor;
+                    break;
+                }
+                predator.stance = Stance::Roaming;
+
                 if let Some((dx, dy)) = closest_offset {
                     predator_position.x = (predator_position.x as isize + dx.signum())
                         .clamp(0, world.width as isize - 1)
@@ -213,10 +1190,8 @@ fn predator_movement(
                         .clamp(0, world.height as isize - 1)
                         as usize;
                 } else {
-                    let mut best_direction = (0isize, 0isize);
-                    let mut best_cost = f32::MAX;
-
-                    for &(dx, dy) in DIRECTIONS.iter() {
+                    let mut costs = [0.0; DIRECTIONS.len()];
+                    for (cost, &(dx, dy)) in costs.iter_mut().zip(DIRECTIONS.iter()) {
                         let new_x = (predator_position.x as isize + dx)
                             .clamp(0, world.width as isize - 1)
                             as usize;
@@ -224,18 +1199,50 @@ fn predator_movement(
                             .clamp(0, world.height as isize - 1)
                             as usize;
                         let tile = world.tile(new_x, new_y);
-                        let cost = match tile.biome {
-                            Biome::Water => 100.0,
-                            Biome::Desert => 10.0,
-                            Biome::Grassland => 5.0,
-                            Biome::Forest => 6.0,
-                        } + rng.gen_range(0.0..5.0_f32);
-                        if cost < best_cost {
-                            best_cost = cost;
-                            best_direction = (dx, dy);
-                        }
+                        let base_cost = config.world.predator_movement_cost(tile.biome);
+                        let reserve_cost = if tile.reserve {
+                            config.world.reserves.predator_movement_cost
+                        } else {
+                            0.0
+                        };
+                        let intrusion_cost =
+                            if territory.enabled && territories.is_foreign(new_x, new_y, entity) {
+                                territory.intrusion_cost
+                            } else {
+                                0.0
+                            };
+                        *cost = base_cost * predator_upkeep_factor(tile, &predator, &config)
+                            + reserve_cost
+                            + intrusion_cost
+                            + config
+                                .predator
+                                .kill_scent
+                                .predator_bias(kill_scent.0[new_y * world.width + new_x]);
                     }
 
+                    let best_direction = if wander.enabled {
+                        predator.heading = wander_heading(predator.heading, &costs, wander, rng);
+                        let mut step = heading_step(predator.heading);
+                        if !inside(&world, &predator_position, step) {
+                            predator.heading = (predator.heading + PI).rem_euclid(TAU);
+                            step = heading_step(predator.heading);
+                        }
+                        step
+                    } else {
+                        let mut best_direction = (0isize, 0isize);
+                        let mut best_cost = (f32::MAX, u64::MAX);
+                        for (i, (&cost, &direction)) in
+                            costs.iter().zip(DIRECTIONS.iter()).enumerate()
+                        {
+                            let cost = (cost + rng.gen_range(0.0..5.0_f32), tie(i as u64));
+                            if cost < best_cost {
+                                best_cost = cost;
+                                best_direction = direction;
+                            }
+                        }
+                        best_direction
+                    };
+
                     predator_position.x = (predator_position.x as isize + best_direction.0)
                         .clamp(0, world.width as isize - 1)
                         as usize;
@@ -244,480 +1251,5349 @@ fn predator_movement(
                         as usize;
                 }
 
-                predator.energy -=
-                    config.predator.predator_energy_decay_rate * predator.speed * predator.size;
+                let tile = world.tile(predator_position.x, predator_position.y);
+                predator.energy -= config.predator.predator_energy_decay_rate
+                    * predator.speed
+                    * predator.size
+                    * predator_upkeep_factor(tile, &predator, &config);
             }
+
+            sight.state = if predator.energy >= predator.satiation_threshold {
+                HuntState::Sated
+            } else if sight.target.is_some() {
+                HuntState::Pursuing
+            } else {
+                HuntState::Searching
+            };
         },
     );
 }
 
-fn despawn_dead_organisms(mut commands: Commands, query: Query<(Entity, &Organism)>) {
-    for (entity, organism) in query.iter() {
-        if organism.energy <= 0.0 {
-            commands.entity(entity).despawn_recursive();
+/// Owners fight the rival predators that come next to them inside their territory,
+/// see [`TerritoryConfig`](crate::resources::TerritoryConfig).
+fn territorial_contests(
+    mut predators: Query<(Entity, &mut Predator, &Position), Active>,
+    territories: Res<TerritoryMap>,
+    config: Res<Config>,
+    streams: Res<RngStreams>,
+    generation: Res<Generation>,
+    mut rivals: Local<Vec<Entity>>,
+) {
+    let settings = &config.predator.territory;
+    if !settings.enabled {
+        return;
+    }
+    let mut rng = streams.for_system("territorial_contests", generation.0);
+    for (&owner, territory) in &territories.territories {
+        let Ok((_, _, &home)) = predators.get(owner) else {
+            continue;
+        };
+        rivals.clear();
+        rivals.extend(
+            predators
+                .iter()
+                .filter(|(entity, predator, position)| {
+                    *entity != owner
+                        && predator.energy > 0.0
+                        && position.x.abs_diff(home.x) <= 1
+                        && position.y.abs_diff(home.y) <= 1
+                        && territory.covers(position.x, position.y)
+                })
+                .map(|(entity, _, _)| entity),
+        );
+        for &rival in rivals.iter() {
+            let Ok([(_, mut defender, _), (_, mut intruder, _)]) =
+                predators.get_many_mut([owner, rival])
+            else {
+                continue;
+            };
+            if defender.energy <= 0.0 || intruder.energy <= 0.0 {
+                continue;
+            }
+            let odds = defender.aggression + intruder.aggression;
+            let defender_wins = if odds > 0.0 {
+                rng.gen::<f32>() * odds < defender.aggression
+            } else {
+                rng.gen::<bool>()
+            };
+            defender.energy -= settings.aggression_cost * defender.aggression;
+            intruder.energy -= settings.aggression_cost * intruder.aggression;
+            let loser = if defender_wins {
+                &mut intruder
+            } else {
+                &mut defender
+            };
+            loser.energy -= settings.contest_cost;
         }
     }
 }
 
-fn despawn_dead_predators(mut commands: Commands, query: Query<(Entity, &Predator)>) {
-    for (entity, predator) in query.iter() {
-        if predator.energy <= 0.0 {
-            commands.entity(entity).despawn_recursive();
+/// Dissolves the territories of predators that died, then lets every predator
+/// without one claim one around its tile, unless another already claims the tile.
+fn claim_territories(
+    predators: Query<(Entity, &Predator, &Position), Active>,
+    mut territories: ResMut<TerritoryMap>,
+    config: Res<Config>,
+) {
+    let settings = &config.predator.territory;
+    if !settings.enabled {
+        return;
+    }
+    let dead: Vec<_> = territories
+        .territories
+        .keys()
+        .copied()
+        .filter(|&owner| {
+            predators
+                .get(owner)
+                .map_or(true, |(_, predator, _)| predator.energy <= 0.0)
+        })
+        .collect();
+    for owner in dead {
+        territories.dissolve(owner);
+    }
+    for (entity, predator, position) in predators.iter() {
+        if predator.energy <= 0.0
+            || territories.territories.contains_key(&entity)
+            || !territories.owners(position.x, position.y).is_empty()
+        {
+            continue;
         }
+        let radius = settings.radius + settings.radius_per_size * predator.size;
+        territories.claim(entity, position.x, position.y, radius);
     }
 }
 
-fn regenerate_food(world: Res<World>, mut food_grid: ResMut<FoodGrid>, config: Res<Config>) {
-    for (tile, food) in world.grid.iter().zip(food_grid.0.iter_mut()) {
-        match tile.biome {
-            Biome::Forest => {
-                if *food <= config.world.forest.max_food_availability {
-                    *food += config.world.forest.food_availability;
-                }
-            }
-            Biome::Desert => {
-                if *food <= config.world.desert.max_food_availability {
-                    *food += config.world.desert.food_availability;
-                }
-            }
-            Biome::Grassland => {
-                if *food <= config.world.grassland.max_food_availability {
-                    *food += config.world.grassland.food_availability;
-                }
-            }
-            _ => {}
+/// Components only some individuals carry for a while, stripped from the pooled so
+/// that a recycled entity starts without them.
+type Transient = (Panicked, Dispersing, Injured, ParentTraits, ParentAge);
+
+/// Takes a dead individual out of the world: into the entity pool as [`Inactive`]
+/// under `entity_pool_size`, otherwise despawned.
+fn retire(commands: &mut Commands, entity: Entity, config: &Config) {
+    if config.world.entity_pool_size > 0 {
+        commands
+            .entity(entity)
+            .try_insert(Inactive)
+            .remove::<Transient>();
+    } else {
+        commands.entity(entity).try_despawn_recursive();
+    }
+}
+
+/// Spawns a newborn, reusing a pooled entity of its kind from `pool` when there is
+/// one. `bundle` has to hold every component the newborn's kind always carries, so
+/// that nothing of the entity's previous life is left.
+fn spawn_recycled(
+    commands: &mut Commands,
+    pool: &mut impl Iterator<Item = Entity>,
+    bundle: impl Bundle,
+) -> Entity {
+    match pool.next() {
+        Some(entity) => {
+            commands
+                .entity(entity)
+                .remove::<Transient>()
+                .insert(bundle)
+                .remove::<Inactive>();
+            entity
         }
+        None => commands.spawn(bundle).id(),
     }
 }
 
-fn consume_food(
-    mut food_grid: ResMut<FoodGrid>,
-    index: Res<SpatialIndex>,
-    mut query: Query<&mut Organism>,
-    mut scratch: Local<Vec<(Entity, f32)>>,
+fn despawn_dead_organisms(
+    mut commands: Commands,
+    query: Query<(Entity, &Organism), Active>,
+    config: Res<Config>,
 ) {
-    for (i, cell) in index.cells.iter().enumerate() {
-        if cell.is_empty() {
-            continue;
-        }
-        let food = food_grid.0[i];
-        if food <= 0.0 {
-            continue;
+    for (entity, organism) in query.iter() {
+        if organism.energy <= 0.0 {
+            retire(&mut commands, entity, &config);
         }
+    }
+}
 
-        scratch.clear();
-        for &entity in cell.iter() {
-            if let Ok(organism) = query.get(entity) {
-                scratch.push((entity, organism.size));
+/// Dead predators leave `carrion.food_per_size` per unit of size on their tile.
+fn despawn_dead_predators(
+    mut commands: Commands,
+    query: Query<(Entity, &Predator, &Position), Active>,
+    world: Res<World>,
+    config: Res<Config>,
+    mut carrion: ResMut<CarrionGrid>,
+) {
+    let food_per_size = config.predator.carrion.food_per_size;
+    for (entity, predator, position) in query.iter() {
+        if predator.energy <= 0.0 {
+            if food_per_size > 0.0 {
+                carrion.0[position.y * world.width + position.x] += predator.size * food_per_size;
             }
+            retire(&mut commands, entity, &config);
         }
-        if scratch.is_empty() {
-            continue;
-        }
+    }
+}
 
-        scratch.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+/// Carrion below this much has rotted away entirely.
+const CARRION_EPSILON: f32 = 1e-3;
 
-        let mut remaining = food;
-        for &(entity, _) in scratch.iter() {
-            if remaining <= 0.0 {
-                break;
-            }
-            if let Ok(mut organism) = query.get_mut(entity) {
-                let needed = organism.size * 0.2 * organism.speed;
-                let consumed = needed.min(remaining);
-                remaining -= consumed;
-                organism.energy += consumed * 2.0;
-            }
+/// Carrion loses `decay_rate` of itself every tick.
+fn rot_carrion(mut carrion: ResMut<CarrionGrid>, config: Res<Config>) {
+    let kept = 1.0 - config.predator.carrion.decay_rate;
+    for food in carrion.0.iter_mut().filter(|food| **food > 0.0) {
+        *food *= kept;
+        if *food < CARRION_EPSILON {
+            *food = 0.0;
         }
-        food_grid.0[i] = remaining;
     }
 }
 
-fn biome_adaptation(mut query: Query<(&mut Organism, &Position)>, world: Res<World>) {
-    query.par_iter_mut().for_each(|(mut organism, position)| {
-        let tile = world.tile(position.x, position.y);
-        let tolerance = organism.biome_tolerance[tile.biome.idx()];
+/// Every kill leaves `kill_scent.deposit` on its tile.
+fn mark_kill_sites(
+    mut kills: EventReader<Killed>,
+    mut scent: ResMut<KillScentGrid>,
+    world: Res<World>,
+    config: Res<Config>,
+) {
+    let settings = &config.predator.kill_scent;
+    if !settings.enabled {
+        kills.clear();
+        return;
+    }
+    for kill in kills.read() {
+        scent.0[kill.y * world.width + kill.x] += settings.deposit;
+    }
+}
 
-        match tile.biome {
-            Biome::Forest => {
-                organism.energy += 0.1 * tolerance;
-            }
-            Biome::Desert => {
-                organism.energy -= 0.1 / tolerance;
-            }
-            Biome::Water => {
-                organism.energy = -1.0;
-            }
-            Biome::Grassland => {
-                organism.energy += 0.05 * tolerance;
-            }
+/// Kill scent loses `deposit / decay_ticks` every tick.
+fn fade_kill_scent(mut scent: ResMut<KillScentGrid>, config: Res<Config>) {
+    let settings = &config.predator.kill_scent;
+    let fade = settings.deposit / settings.decay_ticks as f32;
+    for strength in scent.0.iter_mut().filter(|strength| **strength > 0.0) {
+        // a tiny remainder left by rounding goes with the last step
+        *strength -= fade;
+        if *strength < fade * 1e-3 {
+            *strength = 0.0;
         }
-    });
+    }
 }
 
-fn update_population_count(
-    organisms_query: Query<&Organism>,
-    predators_query: Query<&Predator>,
-    mut pop: ResMut<PopulationCount>,
-) {
-    pop.organisms = organisms_query.iter().count();
-    pop.predators = predators_query.iter().count();
+/// Land tiles covered by `patch`.
+fn patch_tiles<'a>(patch: &'a FoodPatch, world: &'a World) -> impl Iterator<Item = usize> + 'a {
+    let rows = patch.y.saturating_sub(patch.radius)..(patch.y + patch.radius + 1).min(world.height);
+    rows.flat_map(move |y| {
+        let columns =
+            patch.x.saturating_sub(patch.radius)..(patch.x + patch.radius + 1).min(world.width);
+        columns.map(move |x| (x, y))
+    })
+    .filter(|&(x, y)| patch.covers(x, y) && world.tile(x, y).biome != Biome::Water)
+    .map(|(x, y)| y * world.width + x)
 }
 
-fn reproduction(
-    mut commands: Commands,
-    mut query: Query<(&mut Organism, &Position)>,
+/// Excess food below this much has decayed away entirely.
+const PATCH_EPSILON: f32 = 1e-3;
+
+/// Ages the food patches, lets the excess food of expired ones decay and now and
+/// then seeds a new one, see [`FoodPatchConfig`](crate::resources::FoodPatchConfig).
+#[allow(clippy::too_many_arguments)]
+fn food_patches(
     world: Res<World>,
+    mut food_grid: ResMut<FoodGrid>,
+    mut active: ResMut<ActiveTiles>,
+    mut patches: ResMut<FoodPatches>,
     config: Res<Config>,
-    mut rng: ResMut<ReproductionRng>,
-    pop: Res<PopulationCount>,
+    generation: Res<Generation>,
+    streams: Res<RngStreams>,
+    mut patch_appeared: EventWriter<FoodPatchAppeared>,
 ) {
-    let total_entities = pop.organisms + pop.predators;
+    let settings = &config.world.food_patches;
+    let max_food = |i: usize| {
+        config
+            .world
+            .biome(world.grid[i].biome)
+            .max_food_availability
+    };
+    let FoodPatches {
+        active: in_place,
+        decaying,
+    } = &mut *patches;
 
-    if total_entities >= config.world.max_total_entities {
-        if config.world.printing {
-            println!("Max entities reached, not spawning organism");
-        }
-        return;
+    for patch in in_place.iter_mut() {
+        patch.remaining = patch.remaining.saturating_sub(1);
+    }
+    for patch in in_place.iter().filter(|patch| patch.remaining == 0) {
+        decaying.extend(patch_tiles(patch, &world));
     }
+    in_place.retain(|patch| patch.remaining > 0);
+    decaying.sort_unstable();
+    decaying.dedup();
 
-    for (mut organism, position) in query.iter_mut() {
-        if organism.energy <= 0.0 {
-            continue;
-        }
-        if organism.reproduction_cooldown > 0.0 {
-            organism.reproduction_cooldown -= 1.0;
-            continue;
+    // tiles under a newer patch wait for it to expire too
+    let kept = 1.0 - settings.decay_rate;
+    decaying.retain(|&i| {
+        let (x, y) = (i % world.width, i / world.width);
+        if in_place.iter().any(|patch| patch.covers(x, y)) {
+            return true;
         }
-
-        if organism.energy > organism.reproduction_threshold {
-            let mutation_factor = config.organism.organism_mutability;
-
-            let mut biome_tolerance = organism.biome_tolerance;
-            for tolerance in biome_tolerance.iter_mut() {
-                *tolerance *= 1.0 + rng.0.gen_range(-mutation_factor..mutation_factor);
-                *tolerance = tolerance.max(0.01);
+        let max = max_food(i);
+        let mut food = food_grid.get(i);
+        if food > max {
+            food = max + (food - max) * kept;
+            if food - max < PATCH_EPSILON {
+                food = max;
             }
+            food_grid.set(i, food);
+        }
+        if food <= max {
+            active.mark(i);
+            return false;
+        }
+        true
+    });
 
-            let reproduction_threshold = organism.reproduction_threshold
-                * (1.0 + rng.0.gen_range(-mutation_factor..mutation_factor));
-
-            let mutated_size =
-                organism.size * (1.0 + rng.0.gen_range(-mutation_factor..mutation_factor));
-            let size = mutated_size.max(0.1);
-            let mutated_speed =
-                organism.speed * (1.1 + rng.0.gen_range(-mutation_factor..mutation_factor));
-            let penalty = size * 0.1;
-            let speed = (mutated_speed - penalty).max(0.1);
-
-            let mutated_cooldown = (config.organism.organism_reproduction_cooldown
-                * (1.0 + rng.0.gen_range(-mutation_factor..mutation_factor)))
-            .max(1.0);
-
-            let child = Organism {
-                energy: organism.energy / 2.0,
-                speed,
-                size,
-                reproduction_threshold,
-                biome_tolerance,
-                reproduction_cooldown: mutated_cooldown,
-            };
-
-            let x_offset = rng.0.gen_range(-1..=1);
-            let y_offset = rng.0.gen_range(-1..=1);
-
-            let child_position = Position {
-                x: (position.x as isize + x_offset).clamp(0, world.width as isize - 1) as usize,
-                y: (position.y as isize + y_offset).clamp(0, world.height as isize - 1) as usize,
-            };
-
-            let child_seed: u64 = rng.0.gen();
-            commands.spawn((
-                child,
-                child_position,
-                EntityRng(SmallRng::seed_from_u64(child_seed)),
-            ));
+    let mut rng = streams.for_system("food_patches", generation.0);
+    if settings.probability <= 0.0 || rng.gen::<f32>() >= settings.probability {
+        return;
+    }
+    let (x, y) = random_habitable_tile(&world, &mut rng);
+    if world.tile(x, y).biome == Biome::Water {
+        return;
+    }
 
-            organism.energy /= 2.0;
-            organism.reproduction_cooldown = config.organism.organism_reproduction_cooldown;
-        }
+    let patch = FoodPatch {
+        x,
+        y,
+        radius: settings.radius,
+        remaining: settings.duration,
+    };
+    let mut tiles = 0;
+    for i in patch_tiles(&patch, &world) {
+        let max = max_food(i);
+        let food = food_grid.get(i).max(max) + settings.bonus_food;
+        food_grid.set(i, food);
+        tiles += 1;
     }
+    in_place.push(patch);
+
+    info!(
+        target: "sim::food_patches",
+        "Food patch appeared at ({}, {}) with radius {} over {} tiles",
+        x,
+        y,
+        settings.radius,
+        tiles
+    );
+    patch_appeared.send(FoodPatchAppeared {
+        x,
+        y,
+        radius: settings.radius,
+        tiles,
+        bonus_food: settings.bonus_food,
+        duration: settings.duration,
+        generation: generation.0,
+    });
 }
 
-fn rebuild_spatial_index(
-    mut index: ResMut<SpatialIndex>,
-    query: Query<(Entity, &Position), With<Organism>>,
-) {
-    index.clear();
-    for (entity, position) in query.iter() {
-        index.insert(position.x, position.y, entity);
+/// Regrows one tile's food and returns whether it can still change on later ticks.
+fn regenerate_tile(tile: &Tile, food: &mut f32, config: &Config) -> bool {
+    let biome_config = config.world.biome(tile.biome);
+    if biome_config.food_availability <= 0.0 {
+        return false;
     }
+    if *food <= biome_config.max_food_availability {
+        *food += biome_config.food_availability;
+    }
+    *food <= biome_config.max_food_availability
 }
 
-fn rebuild_predator_spatial_index(
-    mut index: ResMut<PredatorSpatialIndex>,
-    query: Query<(Entity, &Position), With<Predator>>,
+/// Only visits tiles in `ActiveTiles`; the others are at their steady state.
+fn regenerate_food(
+    world: Res<World>,
+    mut food_grid: ResMut<FoodGrid>,
+    mut active: ResMut<ActiveTiles>,
+    mut flux: ResMut<EnergyFlux>,
+    config: Res<Config>,
 ) {
-    index.0.clear();
-    for (entity, position) in query.iter() {
-        index.0.insert(position.x, position.y, entity);
-    }
+    let conversion = config.organism.food_energy_conversion;
+    active.retain(|i| {
+        let before = food_grid.get(i);
+        let mut food = before;
+        let growing = regenerate_tile(&world.grid[i], &mut food, &config);
+        food_grid.set(i, food);
+        flux.food_regenerated += (food - before) * conversion;
+        growing
+    });
 }
 
-fn hunting(
-    mut commands: Commands,
-    mut predator_query: Query<(&mut Predator, &Position)>,
-    mut organism_query: Query<&mut Organism>,
-    index: Res<SpatialIndex>,
+fn desertification(
+    mut world: ResMut<World>,
+    mut active: ResMut<ActiveTiles>,
+    food_grid: Res<FoodGrid>,
     config: Res<Config>,
-    mut eaten: Local<HashSet<Entity>>,
+    generation: Res<Generation>,
+    mut pressure: ResMut<DesertificationPressure>,
+    mut biome_changed: EventWriter<BiomeChanged>,
 ) {
-    eaten.clear();
-    for (mut predator, predator_position) in predator_query.iter_mut() {
-        if predator.energy >= predator.satiation_threshold {
+    let settings = &config.world.desertification;
+    if !settings.enabled {
+        return;
+    }
+
+    let width = world.width;
+    let pressure = &mut pressure.0;
+    if pressure.len() != world.grid.len() {
+        *pressure = vec![0; world.grid.len()];
+    }
+
+    for (i, (tile, &food)) in world.grid.iter_mut().zip(food_grid.values()).enumerate() {
+        if tile.biome != Biome::Grassland || food >= settings.food_threshold {
+            pressure[i] = 0;
             continue;
         }
 
-        let prey_entities = index.get(predator_position.x, predator_position.y);
-        if !prey_entities.is_empty() {
-            for &prey_entity in prey_entities {
-                if eaten.contains(&prey_entity) {
-                    continue;
-                }
-                if let Ok(mut prey) = organism_query.get_mut(prey_entity) {
-                    let energy_gained = prey.size * predator.hunting_efficiency;
-                    predator.energy =
-                        (predator.energy + energy_gained).min(config.predator.max_predator_energy);
-                    eaten.insert(prey_entity);
-                    prey.energy = -1.0;
-                    commands.entity(prey_entity).try_despawn_recursive();
-                    break;
-                }
-            }
+        pressure[i] += 1;
+        if pressure[i] >= settings.generations {
+            pressure[i] = 0;
+            tile.biome = Biome::Desert;
+            active.mark(i);
+            biome_changed.send(BiomeChanged {
+                x: i % width,
+                y: i / width,
+                from: Biome::Grassland,
+                to: Biome::Desert,
+                cause: BiomeChangeCause::Desertification,
+                generation: generation.0,
+            });
         }
     }
 }
 
-fn predator_reproduction(
+#[allow(clippy::too_many_arguments)]
+fn catastrophes(
     mut commands: Commands,
-    mut query: Query<(&mut Predator, &Position)>,
-    world: Res<World>,
+    mut world: ResMut<World>,
+    mut food_grid: ResMut<FoodGrid>,
+    mut active: ResMut<ActiveTiles>,
+    mut organisms: Query<(Entity, &Position, &mut Organism), Active>,
+    mut predators: Query<(Entity, &Position, &mut Predator), Active>,
     config: Res<Config>,
-    mut rng: ResMut<ReproductionRng>,
-    pop: Res<PopulationCount>,
+    generation: Res<Generation>,
+    streams: Res<RngStreams>,
+    mut death_tally: ResMut<DeathTally>,
+    mut catastrophe_struck: EventWriter<CatastropheStruck>,
+    mut biome_changed: EventWriter<BiomeChanged>,
 ) {
-    let total_entities = pop.organisms + pop.predators;
-
-    if total_entities >= config.world.max_total_entities {
-        if config.world.printing {
-            println!("Max entities reached, not spawning predator");
-        }
+    let settings = &config.world.catastrophes;
+    let rng = &mut streams.for_system("catastrophes", generation.0);
+    if settings.probability <= 0.0 || rng.gen::<f32>() >= settings.probability {
         return;
     }
 
-    for (mut predator, position) in query.iter_mut() {
-        if predator.energy <= 0.0 {
-            continue;
+    let cx = rng.gen_range(0..world.width);
+    let cy = rng.gen_range(0..world.height);
+    let radius = rng.gen_range(settings.min_radius..=settings.max_radius.max(settings.min_radius));
+    let kind = if rng.gen::<f32>() < settings.flood_share {
+        CatastropheKind::Flood
+    } else {
+        CatastropheKind::Meteor
+    };
+    let within = |x: usize, y: usize| {
+        let dx = x as isize - cx as isize;
+        let dy = y as isize - cy as isize;
+        dx * dx + dy * dy <= (radius * radius) as isize
+    };
+
+    let mut killed = 0;
+    for (entity, position, mut organism) in organisms.iter_mut() {
+        if organism.energy > 0.0
+            && within(position.x, position.y)
+            && rng.gen::<f32>() < settings.mortality
+        {
+            death_tally.record(DeathCause::Catastrophe, organism.energy);
+            organism.energy = -1.0;
+            retire(&mut commands, entity, &config);
+            killed += 1;
         }
-        if predator.reproduction_cooldown > 0.0 {
-            predator.reproduction_cooldown -= 1.0;
-            continue;
+    }
+    for (entity, position, mut predator) in predators.iter_mut() {
+        if predator.energy > 0.0
+            && within(position.x, position.y)
+            && rng.gen::<f32>() < settings.mortality
+        {
+            death_tally.record(DeathCause::Catastrophe, predator.energy);
+            predator.energy = -1.0;
+            retire(&mut commands, entity, &config);
+            killed += 1;
         }
+    }
 
-        if predator.energy > predator.reproduction_threshold {
-            let mutation_factor = config.predator.predator_mutability;
-
-            let mutated_size =
-                predator.size * (1.0 + rng.0.gen_range(-mutation_factor..mutation_factor));
-            let size = mutated_size.max(0.1);
+    let width = world.width;
+    let height = world.height;
+    let mut flooded = Vec::new();
+    for y in cy.saturating_sub(radius)..(cy + radius + 1).min(height) {
+        for x in cx.saturating_sub(radius)..(cx + radius + 1).min(width) {
+            if !within(x, y) {
+                continue;
+            }
+            let i = y * width + x;
+            let food = food_grid.get(i);
+            food_grid.set(i, food * (1.0 - settings.food_destruction));
+            active.mark(i);
 
-            let mutated_speed =
-                predator.speed * (1.1 + rng.0.gen_range(-mutation_factor..mutation_factor));
-            let penalty = size * 0.1;
-            let speed = (mutated_speed - penalty).max(0.1);
+            let shoreline = DIRECTIONS.iter().any(|&(dx, dy)| {
+                let nx = x as isize + dx;
+                let ny = y as isize + dy;
+                nx >= 0
+                    && ny >= 0
+                    && (nx as usize) < width
+                    && (ny as usize) < height
+                    && world.tile(nx as usize, ny as usize).biome == Biome::Water
+            });
+            if kind == CatastropheKind::Flood
+                && settings.flood_converts_biomes
+                && world.tile(x, y).biome != Biome::Water
+                && shoreline
+            {
+                flooded.push((x, y));
+            }
+        }
+    }
 
-            let reproduction_cooldown = (config.predator.predator_reproduction_cooldown
-                * (1.0 + rng.0.gen_range(-mutation_factor..mutation_factor)))
-            .max(1.0);
+    for (x, y) in flooded {
+        let tile = &mut world.grid[y * width + x];
+        biome_changed.send(BiomeChanged {
+            x,
+            y,
+            from: tile.biome,
+            to: Biome::Water,
+            cause: BiomeChangeCause::Flood,
+            generation: generation.0,
+        });
+        tile.biome = Biome::Water;
+        food_grid.set(y * width + x, 0.0);
+    }
 
-            let child = Predator {
-                energy: predator.energy / 2.0,
-                speed,
-                size,
-                hunting_efficiency: predator.hunting_efficiency
-                    * (1.0 + rng.0.gen_range(-mutation_factor..mutation_factor)),
-                satiation_threshold: predator.satiation_threshold
-                    * (1.0 + rng.0.gen_range(-mutation_factor..mutation_factor)),
-                reproduction_threshold: predator.reproduction_threshold
-                    * (1.0 + rng.0.gen_range(-mutation_factor..mutation_factor)),
-                reproduction_cooldown,
-            };
+    info!(
+        target: "sim::catastrophes",
+        "{:?} struck at ({}, {}) with radius {}, killing {}",
+        kind,
+        cx,
+        cy,
+        radius,
+        killed
+    );
+    catastrophe_struck.send(CatastropheStruck {
+        kind,
+        x: cx,
+        y: cy,
+        radius,
+        killed,
+        generation: generation.0,
+    });
+}
 
-            let x_offset = rng.0.gen_range(-1..=1);
-            let y_offset = rng.0.gen_range(-1..=1);
+/// Energy gained from eating `food` units on a tile of `biome`.
+fn food_energy(food: f32, biome: Biome, config: &Config) -> f32 {
+    food * config.organism.food_energy_conversion * config.world.biome(biome).food_nutrition
+}
 
-            let child_position = Position {
-                x: (position.x as isize + x_offset).clamp(0, world.width as isize - 1) as usize,
-                y: (position.y as isize + y_offset).clamp(0, world.height as isize - 1) as usize,
-            };
+/// Credits `organism` and the tick's tally with eating `food` units from a tile of
+/// `biome`. Whatever would take it past its energy cap is lost to digestion.
+fn eat(
+    organism: &mut Organism,
+    history: &mut IntakeHistory,
+    food: f32,
+    biome: Biome,
+    config: &Config,
+    intake: &mut EnergyIntake,
+) {
+    let gained = capped_gain(
+        organism.energy,
+        food_energy(food, biome, config),
+        energy_cap(organism, config),
+    );
+    organism.energy += gained;
+    history.meal += gained;
+    intake.gained += gained;
+    intake.digestion_loss += food * config.organism.food_energy_conversion - gained;
+}
 
-            let child_seed: u64 = rng.0.gen();
-            commands.spawn((
-                child,
-                child_position,
-                EntityRng(SmallRng::seed_from_u64(child_seed)),
-            ));
+/// An organism at a meal, with its id to settle who eats first among equals.
+type Eater = (
+    &'static mut Organism,
+    &'static mut IntakeHistory,
+    Option<&'static Identity>,
+);
 
-            predator.energy /= 2.0;
-            predator.reproduction_cooldown = config.predator.predator_reproduction_cooldown;
+/// Shares of `food` for organisms needing `needs`, listed in the order they eat,
+/// split by `policy` into `shares`. Returns the food left over.
+fn allocate_food(policy: FoodAllocation, needs: &[f32], food: f32, shares: &mut Vec<f32>) -> f32 {
+    shares.clear();
+    shares.resize(needs.len(), 0.0);
+    let mut remaining = food;
+    match policy {
+        FoodAllocation::PriorityBySize => {
+            for (share, &need) in shares.iter_mut().zip(needs) {
+                if remaining <= 0.0 {
+                    break;
+                }
+                *share = need.min(remaining);
+                remaining -= *share;
+            }
+        }
+        FoodAllocation::Proportional => {
+            let total: f32 = needs.iter().sum();
+            if total <= 0.0 || remaining <= 0.0 {
+                return remaining;
+            }
+            let fraction = (remaining / total).min(1.0);
+            for (share, &need) in shares.iter_mut().zip(needs) {
+                *share = need * fraction;
+            }
+            remaining = (remaining - total * fraction).max(0.0);
+        }
+        FoodAllocation::EqualSplit => {
+            // the least hungry are filled first, and what they leave of an equal
+            // share goes to those still hungry
+            let mut order: Vec<usize> = (0..needs.len()).collect();
+            order.sort_by(|&a, &b| needs[a].total_cmp(&needs[b]).then(a.cmp(&b)));
+            for (k, &i) in order.iter().enumerate() {
+                if remaining <= 0.0 {
+                    break;
+                }
+                shares[i] = needs[i].min(remaining / (order.len() - k) as f32);
+                remaining -= shares[i];
+            }
         }
     }
+    remaining
 }
 
-fn organism_overcrowding(
-    mut query: Query<&mut Organism>,
+/// Organisms eat from their own tile, split between them by `food_allocation`, and
+/// turn to its carrion for whatever appetite the plant food left. Water food is out
+/// of reach from the water itself: only organisms on an orthogonally adjacent land
+/// tile graze it, up to `shoreline_grazing_rate` each per tick.
+#[allow(clippy::too_many_arguments)]
+fn consume_food(
+    mut food_grid: ResMut<FoodGrid>,
+    mut carrion: ResMut<CarrionGrid>,
+    mut active: ResMut<ActiveTiles>,
     index: Res<SpatialIndex>,
+    world: Res<World>,
     config: Res<Config>,
-    mut scratch: Local<Vec<(Entity, f32)>>,
+    mut intake: ResMut<EnergyIntake>,
+    mut query: Query<Eater, (Without<Panicked>, Active)>,
+    mut scratch: Local<Vec<(Entity, f32, u64)>>,
+    mut needs: Local<Vec<f32>>,
+    mut eaten: Local<Vec<f32>>,
+    mut shore: Local<Vec<usize>>,
 ) {
-    let threshold = config.organism.overcrowding_threshold_for_organisms;
-    for cell in index.cells.iter() {
-        if cell.len() <= threshold {
-            continue;
-        }
+    let grazing_rate = config.organism.shoreline_grazing_rate;
+    let width = world.width;
 
-        scratch.clear();
-        for &entity in cell.iter() {
-            if let Ok(organism) = query.get(entity) {
-                scratch.push((entity, organism.energy));
-            }
+    for (i, cell) in index.cells.iter().enumerate() {
+        if cell.is_empty() || world.grid[i].biome == Biome::Water {
+            continue;
         }
 
-        scratch.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
-        let to_remove = scratch.len().saturating_sub(threshold);
-        for &(entity, _) in scratch.iter().take(to_remove) {
-            if let Ok(mut organism) = query.get_mut(entity) {
-                organism.energy = -1.0;
-                if config.world.printing {
-                    println!("Organism died due to overcrowding");
+        shore.clear();
+        if grazing_rate > 0.0 {
+            let (x, y) = (i % width, i / width);
+            for &(dx, dy) in ORTHOGONAL_DIRECTIONS.iter() {
+                let (nx, ny) = (x as isize + dx, y as isize + dy);
+                if nx < 0 || ny < 0 || nx as usize >= width || ny as usize >= world.height {
+                    continue;
+                }
+                let j = ny as usize * width + nx as usize;
+                if world.grid[j].biome == Biome::Water {
+                    shore.push(j);
                 }
             }
         }
-    }
-}
 
-fn predator_overcrowding(
-    mut query: Query<&mut Predator>,
-    index: Res<PredatorSpatialIndex>,
-    config: Res<Config>,
-    mut scratch: Local<Vec<(Entity, f32)>>,
-) {
-    let threshold = config.predator.overcrowding_threshold_for_predators;
-    for cell in index.0.cells.iter() {
-        if cell.len() <= threshold {
+        let food = food_grid.get(i);
+        if food <= 0.0 && carrion.0[i] <= 0.0 && shore.is_empty() {
             continue;
         }
 
         scratch.clear();
         for &entity in cell.iter() {
-            if let Ok(predator) = query.get(entity) {
-                scratch.push((entity, predator.energy));
+            if let Ok((organism, _, identity)) = query.get(entity) {
+                if organism.energy > 0.0 {
+                    let id = identity.map_or(u64::MAX, |identity| identity.id);
+                    scratch.push((entity, organism.size, id));
+                }
             }
         }
+        if scratch.is_empty() {
+            continue;
+        }
 
-        scratch.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
-        let to_remove = scratch.len().saturating_sub(threshold);
-        for &(entity, _) in scratch.iter().take(to_remove) {
-            if let Ok(mut predator) = query.get_mut(entity) {
-                predator.energy = -1.0;
-                if config.world.printing {
-                    println!("Predator died due to overcrowding");
+        // equal sizes eat in order of id, not of spawning
+        scratch.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.2.cmp(&b.2)));
+
+        needs.clear();
+        for &(entity, ..) in scratch.iter() {
+            let need = query
+                .get(entity)
+                .map_or(0.0, |(organism, ..)| organism.size * 0.2 * organism.speed);
+            needs.push(need);
+        }
+        let remaining = allocate_food(config.organism.food_allocation, &needs, food, &mut eaten);
+        for (k, &(entity, ..)) in scratch.iter().enumerate() {
+            if eaten[k] <= 0.0 {
+                continue;
+            }
+            if let Ok((mut organism, mut history, _)) = query.get_mut(entity) {
+                eat(
+                    &mut organism,
+                    &mut history,
+                    eaten[k],
+                    world.grid[i].biome,
+                    &config,
+                    &mut intake,
+                );
+            }
+        }
+        if remaining < food {
+            active.mark(i);
+        }
+        food_grid.set(i, remaining);
+
+        for (k, &(entity, ..)) in scratch.iter().enumerate() {
+            if carrion.0[i] <= 0.0 {
+                break;
+            }
+            let Ok((mut organism, mut history, _)) = query.get_mut(entity) else {
+                continue;
+            };
+            let appetite = organism.size * 0.2 * organism.speed - eaten[k];
+            if appetite <= 0.0 {
+                continue;
+            }
+            let consumed = appetite.min(carrion.0[i]);
+            carrion.0[i] -= consumed;
+            eat(
+                &mut organism,
+                &mut history,
+                consumed,
+                world.grid[i].biome,
+                &config,
+                &mut intake,
+            );
+        }
+
+        for &(entity, ..) in scratch.iter() {
+            let Ok((mut organism, mut history, _)) = query.get_mut(entity) else {
+                continue;
+            };
+            let mut appetite = (organism.size * 0.2 * organism.speed).min(grazing_rate);
+            for &j in shore.iter() {
+                if appetite <= 0.0 {
+                    break;
+                }
+                let food = food_grid.get(j);
+                let consumed = appetite.min(food.max(0.0));
+                if consumed <= 0.0 {
+                    continue;
                 }
+                appetite -= consumed;
+                food_grid.set(j, food - consumed);
+                active.mark(j);
+                eat(
+                    &mut organism,
+                    &mut history,
+                    consumed,
+                    Biome::Water,
+                    &config,
+                    &mut intake,
+                );
             }
         }
     }
 }
 
-fn increment_generation(mut generation: ResMut<Generation>) {
-    generation.0 += 1;
-}
-
-fn kill_over_limit_organisms(
-    mut commands: Commands,
-    organisms_query: Query<(Entity, &Organism)>,
-    predators_query: Query<(Entity, &Predator)>,
-    config: Res<Config>,
-    mut rng: ResMut<SpawnRng>,
-    pop: Res<PopulationCount>,
-) {
-    let limit = config.world.max_total_entities;
-    let total_entities = pop.organisms + pop.predators;
-    let over_limit = total_entities as i32 - limit as i32;
-    if over_limit <= 0 {
+/// Applies `morph_fitness`; does nothing while the list is empty.
+fn morph_selection(mut query: Query<&mut Organism, Active>, config: Res<Config>) {
+    let fitness = &config.organism.morph_fitness;
+    if fitness.is_empty() {
         return;
     }
-    let to_kill = over_limit as usize;
-    let kill_prob = to_kill as f32 / total_entities as f32;
-    let mut killed = 0usize;
-
-    for (entity, _) in organisms_query.iter() {
-        if killed >= to_kill {
-            break;
+    for mut organism in query.iter_mut() {
+        if organism.energy <= 0.0 {
+            continue;
         }
-        if rng.0.gen::<f32>() < kill_prob {
-            commands.entity(entity).despawn_recursive();
-            killed += 1;
+        if let Some(bonus) = fitness.get(organism.morph as usize) {
+            organism.energy += bonus;
         }
     }
-    for (entity, _) in predators_query.iter() {
-        if killed >= to_kill {
-            break;
+}
+
+/// Energy a donor hands a poorer relative in one tick: `rate` scaled by the donor's
+/// altruism, never more than would leave the relative richer, and nothing to one
+/// that already has enough to reproduce.
+fn kin_transfer(donor: &Organism, recipient: &Organism, rate: f32) -> f32 {
+    if donor.energy <= recipient.energy || recipient.energy > recipient.reproduction_threshold {
+        return 0.0;
+    }
+    (rate * donor.altruism).min((donor.energy - recipient.energy) / 2.0)
+}
+
+/// Moves energy between kin sharing a tile under `kin_sharing`, from the richer to
+/// the poorer of every related pair. Whatever one loses the other gains.
+fn share_energy(
+    mut query: Query<(&mut Organism, &Identity), Active>,
+    index: Res<SpatialIndex>,
+    config: Res<Config>,
+    mut intake: ResMut<EnergyIntake>,
+) {
+    let settings = &config.organism.kin_sharing;
+    if !settings.enabled {
+        return;
+    }
+    for cell in index.cells.iter().filter(|cell| cell.len() > 1) {
+        for (k, &first) in cell.iter().enumerate() {
+            for &second in &cell[k + 1..] {
+                let Ok([(mut a, a_identity), (mut b, b_identity)]) =
+                    query.get_many_mut([first, second])
+                else {
+                    continue;
+                };
+                if a.energy <= 0.0
+                    || b.energy <= 0.0
+                    || !a_identity.is_kin(b_identity, settings.kin_generations)
+                {
+                    continue;
+                }
+                let (donor, recipient) = if a.energy >= b.energy {
+                    (&mut *a, &mut *b)
+                } else {
+                    (&mut *b, &mut *a)
+                };
+                let amount = kin_transfer(donor, recipient, settings.rate);
+                if amount > 0.0 {
+                    donor.energy -= amount;
+                    recipient.energy += amount;
+                    intake.shared += amount;
+                }
+            }
         }
-        if rng.0.gen::<f32>() < kill_prob {
-            commands.entity(entity).despawn_recursive();
-            killed += 1;
+    }
+}
+
+/// Energy an organism with `tolerance` for its biome gains from the biome's
+/// passive `delta`, or loses when negative.
+fn passive_energy_change(energy: f32, delta: f32, tolerance: f32, cap: f32) -> f32 {
+    if delta >= 0.0 {
+        capped_gain(energy, delta * tolerance, cap)
+    } else {
+        delta / tolerance
+    }
+}
+
+/// Drowns the organisms on water and, with `passive_energy`, adds the
+/// `passive_energy_delta` of their biome to the others.
+fn biome_adaptation(
+    mut query: Query<(&mut Organism, &Position), Active>,
+    world: Res<World>,
+    config: Res<Config>,
+    mut flux: ResMut<EnergyFlux>,
+) {
+    let deltas = Biome::ALL.map(|biome| config.world.passive_energy_delta(biome));
+    for (mut organism, position) in query.iter_mut() {
+        if organism.energy <= 0.0 {
+            continue;
+        }
+        let tile = world.tile(position.x, position.y);
+        if tile.biome == Biome::Water {
+            organism.energy = -1.0;
+            continue;
+        }
+        let upkeep = config
+            .organism
+            .tolerance_cost
+            .cost(&organism.biome_tolerance);
+        organism.energy -= upkeep;
+        flux.upkeep += upkeep;
+        if !config.world.passive_energy {
+            continue;
         }
+        let change = passive_energy_change(
+            organism.energy,
+            deltas[tile.biome.idx()],
+            organism.biome_tolerance[tile.biome.idx()],
+            energy_cap(&organism, &config),
+        );
+        organism.energy += change;
+        flux.passive += change;
     }
 }
 
-fn run_for_x_generations(
-    generation: Res<Generation>,
+fn reset_death_tally(mut death_tally: ResMut<DeathTally>) {
+    *death_tally = DeathTally::default();
+}
+
+fn reset_clamp_tally(mut clamps: ResMut<ClampTally>) {
+    clamps.clear();
+}
+
+fn reset_energy_intake(mut intake: ResMut<EnergyIntake>) {
+    *intake = EnergyIntake::default();
+}
+
+fn reset_residence_tally(mut tally: ResMut<ResidenceTally>) {
+    tally.0.clear();
+}
+
+/// Energy held by every organism and predator still alive.
+fn living_energy(
+    organisms: &Query<&Organism, Active>,
+    predators: &Query<&Predator, Active>,
+) -> f32 {
+    let organism_energy: f32 = organisms.iter().map(|o| o.energy.max(0.0)).sum();
+    let predator_energy: f32 = predators.iter().map(|p| p.energy.max(0.0)).sum();
+    organism_energy + predator_energy
+}
+
+/// Starts the generation's flux with the energy of the living before they move, for
+/// `close_upkeep` to subtract what they have left after.
+fn open_energy_flux(
+    mut flux: ResMut<EnergyFlux>,
+    organisms: Query<&Organism, Active>,
+    predators: Query<&Predator, Active>,
+) {
+    *flux = EnergyFlux {
+        upkeep: living_energy(&organisms, &predators),
+        ..default()
+    };
+}
+
+fn close_upkeep(
+    mut flux: ResMut<EnergyFlux>,
+    organisms: Query<&Organism, Active>,
+    predators: Query<&Predator, Active>,
+) {
+    flux.upkeep -= living_energy(&organisms, &predators);
+}
+
+fn update_population_count(
+    organisms_query: Query<&Organism, Active>,
+    predators_query: Query<&Predator, Active>,
+    mut pop: ResMut<PopulationCount>,
+) {
+    pop.organisms = organisms_query.iter().count();
+    pop.predators = predators_query.iter().count();
+}
+
+/// An organism that may reproduce, with its species and whether it is panicked.
+type Parent = (
+    &'static mut Organism,
+    &'static Position,
+    &'static mut Identity,
+    Option<&'static SpeciesId>,
+    Has<Panicked>,
+);
+
+/// Warns that `max_total_entities` turned births away in `generation`, once however
+/// many kinds of individual try to reproduce past it.
+fn warn_cap_reached(clamps: &mut ClampTally, total_entities: usize, generation: usize) {
+    if !std::mem::replace(&mut clamps.cap_reached, true) {
+        warn!(
+            target: "sim::reproduction",
+            "Generation {}: {} individuals reached max_total_entities, no births",
+            generation,
+            total_entities
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn reproduction(
+    mut commands: Commands,
+    mut query: Query<Parent, Active>,
+    pool: Query<Entity, (With<Organism>, With<Inactive>)>,
+    world: Res<World>,
     config: Res<Config>,
-    mut next_state: ResMut<NextState<AppState>>,
+    streams: Res<RngStreams>,
+    pop: Res<PopulationCount>,
+    generation: Res<Generation>,
+    index: Res<SpatialIndex>,
+    mut next_id: ResMut<NextId>,
+    mut clamps: ResMut<ClampTally>,
+    mut placed: Local<HashMap<(usize, usize), usize>>,
 ) {
-    if let Some(limit) = config.world.generation_limit {
-        if generation.0 >= limit {
-            next_state.set(AppState::Finished);
+    let total_entities = pop.organisms + pop.predators;
+
+    if total_entities >= config.world.max_total_entities {
+        warn_cap_reached(&mut clamps, total_entities, generation.0);
+        return;
+    }
+    let mut rng = streams.for_system("reproduction", generation.0);
+    let mut pool = pool.iter();
+    placed.clear();
+    let threshold = config.organism.overcrowding_threshold_for_organisms;
+
+    let species_b = config.species_organism(SpeciesId::B);
+    let species_b_mutability = species_b.organism_mutability;
+    let season = config.world.seasons.season(generation.0);
+    let off_season_a = config.organism.breeding.off_season(season);
+    let off_season_b = species_b.breeding.off_season(season);
+    let windows_a = config.organism.breeding.windowed(season);
+    let windows_b = species_b.breeding.windowed(season);
+
+    for (mut organism, position, mut identity, species, panicked) in query.iter_mut() {
+        if organism.energy <= 0.0 {
+            continue;
+        }
+        if organism.reproduction_cooldown > 0.0 {
+            organism.reproduction_cooldown -= 1.0;
+            continue;
+        }
+        if panicked {
+            continue;
+        }
+        // Reproduction is asexual, so offspring simply stay in the parent's species.
+        let species = SpeciesId::of(species);
+        let (off_season, windows) = match species {
+            SpeciesId::B => (off_season_b, windows_b),
+            _ => (off_season_a, windows_a),
+        };
+
+        let age = generation.0.saturating_sub(identity.birth_generation);
+        let fertility = config
+            .organism
+            .aging
+            .fertility(age, identity.parent.is_none());
+
+        // only drawn for the senescent with aging on, so runs without it keep their
+        // random streams
+        if decides_to_reproduce(
+            organism.energy,
+            organism.reproduction_threshold,
+            &config.world,
+            &mut rng,
+        ) && fertility > 0.0
+            && (fertility >= 1.0 || rng.gen::<f32>() < fertility)
+            && (!off_season || rng.gen::<f32>() < organism.breeding_flexibility)
+        {
+            let mutation_factor = match species {
+                SpeciesId::B => species_b_mutability,
+                _ => config.organism.organism_mutability,
+            };
+            let kind = EntityKind::Organism;
+
+            let mut biome_tolerance = organism.biome_tolerance;
+            let mut tolerance_bound = None;
+            for tolerance in biome_tolerance.iter_mut() {
+                let mutated = *tolerance * (1.0 + mutation(&mut rng, mutation_factor));
+                let (clamped, bound) = clamp_trait(mutated, 0.01, f32::INFINITY);
+                *tolerance = clamped;
+                tolerance_bound = tolerance_bound.or(bound);
+            }
+            clamps.record(kind, "biome_tolerance", tolerance_bound);
+
+            let (reproduction_threshold, bound) = clamped_reproduction_threshold(
+                organism.reproduction_threshold * (1.0 + mutation(&mut rng, mutation_factor)),
+                config.organism.max_organism_energy,
+                &config,
+            );
+            clamps.record(kind, "reproduction_threshold", bound);
+
+            let mutated_size = organism.size * (1.0 + mutation(&mut rng, mutation_factor));
+            let size = clamps.clamp(kind, "size", mutated_size, 0.1, f32::INFINITY);
+            let mutated_speed = organism.speed * (1.1 + mutation(&mut rng, mutation_factor));
+            let penalty = size * 0.1;
+            let speed = clamps.clamp(kind, "speed", mutated_speed - penalty, 0.1, f32::INFINITY);
+
+            // drawn from the config rather than inherited, so not a trait to report on
+            let mutated_cooldown = (config.organism.organism_reproduction_cooldown
+                * (1.0 + mutation(&mut rng, mutation_factor)))
+            .max(1.0);
+
+            let boldness = clamps.clamp(
+                kind,
+                "boldness",
+                organism.boldness + mutation(&mut rng, mutation_factor),
+                0.0,
+                1.0,
+            );
+
+            let migratory_tendency = clamps.clamp(
+                kind,
+                "migratory_tendency",
+                organism.migratory_tendency * (1.0 + mutation(&mut rng, mutation_factor)),
+                0.0,
+                f32::INFINITY,
+            );
+            let preferred_temperature = organism.preferred_temperature
+                + mutation(&mut rng, mutation_factor) * TEMPERATURE_MUTATION_SCALE;
+
+            let exploration = clamps.clamp(
+                kind,
+                "exploration",
+                organism.exploration * (1.0 + mutation(&mut rng, mutation_factor)),
+                0.0,
+                f32::INFINITY,
+            );
+
+            let dispersal = clamps.clamp(
+                kind,
+                "dispersal",
+                organism.dispersal * (1.0 + mutation(&mut rng, mutation_factor)),
+                0.0,
+                f32::INFINITY,
+            );
+
+            let morph = inherit_morph(
+                organism.morph,
+                config.organism.morphs,
+                config.organism.morph_mutation_probability,
+                &mut rng,
+            );
+
+            let (investment, parental_investment) = if config.organism.evolve_parental_investment {
+                let (low, high) = PARENTAL_INVESTMENT_BOUNDS;
+                let mutated = organism.parental_investment + mutation(&mut rng, mutation_factor);
+                let mutated = clamps.clamp(kind, "parental_investment", mutated, low, high);
+                (organism.parental_investment, mutated)
+            } else {
+                (
+                    config.organism.initial_parental_investment,
+                    organism.parental_investment,
+                )
+            };
+            let child_energy = organism.energy * investment;
+
+            // only drawn with sharing on, so runs without it keep their random streams
+            let altruism = if config.organism.kin_sharing.enabled {
+                clamps.clamp(
+                    kind,
+                    "altruism",
+                    organism.altruism + mutation(&mut rng, mutation_factor),
+                    0.0,
+                    1.0,
+                )
+            } else {
+                organism.altruism
+            };
+            let dispersal_config = &config.organism.starvation_dispersal;
+            let (starvation_trigger, burst_duration) =
+                if dispersal_config.enabled && dispersal_config.evolvable {
+                    let trigger =
+                        organism.starvation_trigger * (1.0 + mutation(&mut rng, mutation_factor));
+                    let duration =
+                        organism.burst_duration * (1.0 + mutation(&mut rng, mutation_factor));
+                    (
+                        clamps.clamp(kind, "starvation_trigger", trigger, 0.0, f32::INFINITY),
+                        clamps.clamp(kind, "burst_duration", duration, 1.0, f32::INFINITY),
+                    )
+                } else {
+                    (organism.starvation_trigger, organism.burst_duration)
+                };
+            // only drawn with fat storage on, so runs without it keep their random streams
+            let reserve_capacity = if config.organism.fat_storage.enabled {
+                clamps.clamp(
+                    kind,
+                    "reserve_capacity",
+                    organism.reserve_capacity * (1.0 + mutation(&mut rng, mutation_factor)),
+                    1.0,
+                    f32::INFINITY,
+                )
+            } else {
+                organism.reserve_capacity
+            };
+            // only drawn with breeding windows, so runs without them keep their random
+            // streams; strict breeders sit at 0, so hitting it is not worth a warning
+            let breeding_flexibility = if windows {
+                (organism.breeding_flexibility + mutation(&mut rng, mutation_factor))
+                    .clamp(0.0, 1.0)
+            } else {
+                organism.breeding_flexibility
+            };
+            // only drawn with giving up on, so runs without it keep their random streams
+            let giving_up_density = if config.organism.giving_up.enabled {
+                clamps.clamp(
+                    kind,
+                    "giving_up_density",
+                    organism.giving_up_density + mutation(&mut rng, mutation_factor),
+                    0.0,
+                    f32::INFINITY,
+                )
+            } else {
+                organism.giving_up_density
+            };
+
+            let child = Organism {
+                energy: child_energy,
+                speed,
+                size,
+                reproduction_threshold,
+                biome_tolerance,
+                reproduction_cooldown: mutated_cooldown,
+                boldness,
+                exploration,
+                morph,
+                parental_investment,
+                dispersal,
+                preferred_temperature,
+                migratory_tendency,
+                altruism,
+                starvation_trigger,
+                burst_duration,
+                reserve_capacity,
+                breeding_flexibility,
+                giving_up_density,
+            };
+
+            let child_position = place_offspring(
+                position,
+                organism.dispersal,
+                &world,
+                &config,
+                |x, y| index.get(x, y).len() + placed.get(&(x, y)).unwrap_or(&0) < threshold,
+                &mut rng,
+            );
+            let tile = (child_position.x, child_position.y);
+            *placed.entry(tile).or_default() += 1;
+
+            let id = next_id.allocate();
+            spawn_recycled(
+                &mut commands,
+                &mut pool,
+                (
+                    child,
+                    child_position,
+                    EntityRng(streams.for_entity(id, generation.0)),
+                    identity.child(id, generation.0),
+                    ParentTraits(organism.traits()),
+                    ParentAge(age),
+                    species,
+                    IntakeHistory::default(),
+                    Residence::default(),
+                ),
+            );
+            identity.offspring_count += 1;
+            clamps.birth(kind, age);
+
+            organism.energy -= child_energy;
+            if off_season {
+                organism.energy *= 1.0 - config.organism.breeding.off_season_cost;
+            }
+            organism.reproduction_cooldown = config.organism.organism_reproduction_cooldown;
         }
     }
 }
 
-fn print_simulation_progress(
+/// Where a parent at `position` places its offspring under `offspring_placement`.
+/// `free` tells whether a tile takes one more individual of the parent's kind
+/// without overcrowding.
+fn place_offspring(
+    position: &Position,
+    dispersal: f32,
+    world: &World,
+    config: &Config,
+    free: impl Fn(usize, usize) -> bool,
+    rng: &mut impl Rng,
+) -> Position {
+    let reach = match config.world.offspring_placement {
+        OffspringPlacement::AdjacentRandom => 1,
+        OffspringPlacement::DispersalTrait => dispersal.round() as isize,
+        OffspringPlacement::NearestFree => {
+            let radius = config.world.offspring_search_radius;
+            if let Some((x, y)) = nearest_free_tile(position.x, position.y, radius, world, free) {
+                return Position { x, y };
+            }
+            1
+        }
+    };
+
+    let x_offset = rng.gen_range(-reach..=reach);
+    let y_offset = rng.gen_range(-reach..=reach);
+    Position {
+        x: (position.x as isize + x_offset).clamp(0, world.width as isize - 1) as usize,
+        y: (position.y as isize + y_offset).clamp(0, world.height as isize - 1) as usize,
+    }
+}
+
+/// The tile fewest steps from `(x, y)`, at most `radius` of them, for which `free`
+/// holds. The search is breadth-first from `(x, y)` itself, taking neighbours in
+/// `DIRECTIONS` order, so ties always go to the same tile.
+pub(crate) fn nearest_free_tile(
+    x: usize,
+    y: usize,
+    radius: usize,
+    world: &World,
+    free: impl Fn(usize, usize) -> bool,
+) -> Option<(usize, usize)> {
+    let side = 2 * radius + 1;
+    let mut visited = vec![false; side * side];
+    visited[radius * side + radius] = true;
+    let mut queue = VecDeque::from([(x, y, 0)]);
+
+    while let Some((cx, cy, steps)) = queue.pop_front() {
+        if free(cx, cy) {
+            return Some((cx, cy));
+        }
+        if steps == radius {
+            continue;
+        }
+        for &(dx, dy) in DIRECTIONS.iter() {
+            let nx = cx as isize + dx;
+            let ny = cy as isize + dy;
+            if nx < 0 || ny < 0 || nx >= world.width as isize || ny >= world.height as isize {
+                continue;
+            }
+            // within the window, as no tile in the queue is `radius` steps out yet
+            let slot = (ny - y as isize + radius as isize) as usize * side
+                + (nx - x as isize + radius as isize) as usize;
+            if !visited[slot] {
+                visited[slot] = true;
+                queue.push_back((nx as usize, ny as usize, steps + 1));
+            }
+        }
+    }
+    None
+}
+
+fn rebuild_spatial_index(
+    mut index: ResMut<SpatialIndex>,
+    query: Query<(Entity, &Position), (With<Organism>, Active)>,
+) {
+    index.clear();
+    for (entity, position) in query.iter() {
+        index.insert(position.x, position.y, entity);
+    }
+}
+
+fn rebuild_predator_spatial_index(
+    mut index: ResMut<PredatorSpatialIndex>,
+    query: Query<(Entity, &Position), (With<Predator>, Active)>,
+) {
+    index.0.clear();
+    for (entity, position) in query.iter() {
+        index.0.insert(position.x, position.y, entity);
+    }
+}
+
+/// A predator on the hunt, with what it knows of its own age.
+type Hunter = (
+    Entity,
+    &'static mut Predator,
+    &'static Position,
+    &'static mut EntityRng,
+    Option<&'static Identity>,
+);
+
+/// An organism as predators see it, with any injury it already carries.
+type Prey = (
+    &'static mut Organism,
+    &'static Position,
+    Option<&'static Identity>,
+    Option<&'static Injured>,
+);
+
+#[allow(clippy::too_many_arguments)]
+fn hunting(
+    mut commands: Commands,
+    mut predator_query: Query<Hunter, Active>,
+    mut organism_query: Query<Prey, Active>,
+    index: Res<SpatialIndex>,
+    world: Res<World>,
+    config: Res<Config>,
     generation: Res<Generation>,
+    mut death_tally: ResMut<DeathTally>,
+    mut killed: EventWriter<Killed>,
+    mut prey_injured: EventWriter<PreyInjured>,
+    territories: Res<TerritoryMap>,
+    mut eaten: Local<HashSet<Entity>>,
+    mut candidates: Local<Vec<(Entity, f32)>>,
+) {
+    eaten.clear();
+    let exposure = config.organism.boldness_exposure;
+    let weighted = exposure > 0.0 || config.organism.fat_storage.enabled;
+    for (predator_entity, mut predator, predator_position, mut entity_rng, identity) in
+        predator_query.iter_mut()
+    {
+        if predator.hunt_cooldown_remaining > 0 {
+            predator.hunt_cooldown_remaining -= 1;
+        }
+        if predator.hunt_cooldown_remaining > 0 {
+            continue;
+        }
+        if predator.energy >= predator.satiation_threshold {
+            continue;
+        }
+        if world.tile(predator_position.x, predator_position.y).reserve {
+            continue;
+        }
+
+        // a predator in ambush also strikes at prey next to it
+        let ambushing = predator.stance == Stance::Ambush;
+        let reach = if ambushing { 1 } else { 0 };
+        candidates.clear();
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let x = predator_position.x as isize + dx;
+                let y = predator_position.y as isize + dy;
+                if x < 0
+                    || y < 0
+                    || x >= world.width as isize
+                    || y >= world.height as isize
+                    || world.tile(x as usize, y as usize).reserve
+                {
+                    continue;
+                }
+                for &prey_entity in index.get(x as usize, y as usize) {
+                    if eaten.contains(&prey_entity) {
+                        continue;
+                    }
+                    match organism_query.get(prey_entity) {
+                        Ok((prey, _, _, _)) if prey.energy > 0.0 => {
+                            // a heavier reserve makes prey easier to spot
+                            let visibility = effective_size(prey, &config) / prey.size;
+                            candidates
+                                .push((prey_entity, (1.0 + exposure * prey.boldness) * visibility));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let Some(prey_entity) = pick_prey(&candidates, weighted, &mut entity_rng.0) else {
+            continue;
+        };
+        // Adults always catch the prey they pick, unless the biome makes it harder;
+        // juveniles only with their maturity, helped by any injury an earlier miss
+        // left the prey with. An ambush makes up for some of either.
+        let maturity = predator_maturity(identity, generation.0, &config.predator);
+        let Ok((_, &prey_position, prey_identity, injury)) = organism_query.get(prey_entity) else {
+            continue;
+        };
+        let injury_settings = &config.organism.injury;
+        let severity = injury.map_or(0.0, |injury| injury.severity);
+        let biome = world.tile(predator_position.x, predator_position.y).biome;
+        let ambush_bonus = if ambushing {
+            config.predator.ambush.catch_bonus
+        } else {
+            0.0
+        };
+        let (x, y) = (predator_position.x, predator_position.y);
+        let home_bonus = match territories.territories.get(&predator_entity) {
+            Some(home) if config.predator.territory.enabled && home.covers(x, y) => {
+                config.predator.territory.home_catch_bonus
+            }
+            _ => 0.0,
+        };
+        let chance = (catch_chance(maturity, severity, injury_settings.catch_bonus)
+            * config.world.biome(biome).predator_catch
+            + ambush_bonus
+            + home_bonus)
+            .min(1.0);
+        if chance < 1.0 && entity_rng.0.gen::<f32>() >= chance {
+            if injury_settings.enabled {
+                let wound = entity_rng
+                    .0
+                    .gen_range(injury_settings.min_severity..=injury_settings.max_severity);
+                let severity = wound.max(severity);
+                let ticks = (severity * injury_settings.heal_ticks_per_severity).ceil() as u32;
+                commands.entity(prey_entity).try_insert(Injured {
+                    severity,
+                    ticks_remaining: ticks.max(1),
+                });
+                prey_injured.send(PreyInjured {
+                    id: prey_identity.map(|identity| identity.id),
+                    x: prey_position.x,
+                    y: prey_position.y,
+                    severity,
+                    generation: generation.0,
+                });
+            }
+            continue;
+        }
+        if let Ok((mut prey, _, _, _)) = organism_query.get_mut(prey_entity) {
+            let energy_gained = prey.size * predator.hunting_efficiency;
+            predator.energy =
+                (predator.energy + energy_gained).min(config.predator.max_predator_energy);
+            predator.hunt_cooldown_remaining = predator.hunt_cooldown_ticks.round() as u32;
+            predator.recent_kills.record(generation.0);
+            eaten.insert(prey_entity);
+            death_tally.record(DeathCause::Predation, prey.energy);
+            prey.energy = -1.0;
+            retire(&mut commands, prey_entity, &config);
+            killed.send(Killed {
+                predator: predator_entity,
+                prey: prey_entity,
+                x: prey_position.x,
+                y: prey_position.y,
+            });
+        }
+    }
+}
+
+/// Chance of a predator with `maturity` catching the prey it picked, raised by
+/// `catch_bonus` per unit of the prey's injury `severity`.
+pub fn catch_chance(maturity: f32, severity: f32, catch_bonus: f32) -> f32 {
+    (maturity + catch_bonus * severity).min(1.0)
+}
+
+/// Panics the organisms around each kill, then lets the panic hop from every newly
+/// panicked organism to its own neighbours with a chance that shrinks at each hop.
+#[allow(clippy::too_many_arguments)]
+fn spread_panic(
+    mut commands: Commands,
+    mut kills: EventReader<Killed>,
+    mut organisms: Query<(&Organism, &Position, &mut EntityRng), (Without<Panicked>, Active)>,
+    index: Res<SpatialIndex>,
+    world: Res<World>,
     config: Res<Config>,
-    app_state: Res<State<AppState>>,
-    pop: Res<PopulationCount>,
+    generation: Res<Generation>,
+    mut panic_spread: EventWriter<PanicSpread>,
+    mut panicked: Local<HashSet<Entity>>,
 ) {
-    if !config.world.printing {
+    let settings = &config.organism.panic;
+    if !settings.enabled {
+        kills.clear();
         return;
     }
-    if app_state.get() == &AppState::Simulate {
-        let organisms_count = pop.organisms;
-        let predators_count = pop.predators;
-        let total_entities = organisms_count + predators_count;
-        println!(
-            "Generation: {} / {}, Total entities: {}, Organisms: {}, Predators: {}",
-            generation.0,
-            config.world.generation_limit.unwrap_or(0),
-            total_entities,
-            organisms_count,
-            predators_count
-        );
+
+    panicked.clear();
+    let radius = settings.radius as isize;
+    for kill in kills.read() {
+        let mut frontier = vec![(kill.x, kill.y)];
+        let mut probability = 1.0;
+        let mut count = 0;
+
+        while !frontier.is_empty() && probability > 0.0 {
+            let mut next = Vec::new();
+            for &(fx, fy) in &frontier {
+                for dy in -radius..=radius {
+                    for dx in -radius..=radius {
+                        let x = fx as isize + dx;
+                        let y = fy as isize + dy;
+                        if dx * dx + dy * dy > radius * radius
+                            || x < 0
+                            || y < 0
+                            || x >= world.width as isize
+                            || y >= world.height as isize
+                        {
+                            continue;
+                        }
+                        for &entity in index.get(x as usize, y as usize) {
+                            if panicked.contains(&entity) {
+                                continue;
+                            }
+                            let Ok((organism, position, mut entity_rng)) =
+                                organisms.get_mut(entity)
+                            else {
+                                continue;
+                            };
+                            if organism.energy <= 0.0
+                                || (probability < 1.0 && entity_rng.0.gen::<f32>() >= probability)
+                            {
+                                continue;
+                            }
+
+                            panicked.insert(entity);
+                            commands.entity(entity).insert(Panicked {
+                                ticks: settings.duration,
+                                x: kill.x,
+                                y: kill.y,
+                            });
+                            next.push((position.x, position.y));
+                            count += 1;
+                        }
+                    }
+                }
+            }
+            frontier = next;
+            probability *= settings.chain_probability;
+        }
+
+        panic_spread.send(PanicSpread {
+            x: kill.x,
+            y: kill.y,
+            panicked: count,
+            generation: generation.0,
+        });
     }
 }
 
-fn exit_app(mut exit: EventWriter<AppExit>, app_state: Res<State<AppState>>) {
-    if app_state.get() == &AppState::Finished {
-        exit.send(AppExit::Success);
+/// Records every organism's meal of this tick and sends off those whose recent
+/// intake fell below their `starvation_trigger`, on a random heading.
+fn start_dispersal(
+    mut commands: Commands,
+    mut query: Query<
+        (
+            Entity,
+            &Organism,
+            &mut IntakeHistory,
+            &mut EntityRng,
+            Has<Dispersing>,
+        ),
+        Active,
+    >,
+    config: Res<Config>,
+) {
+    let settings = &config.organism.starvation_dispersal;
+    for (entity, organism, mut history, mut entity_rng, dispersing) in query.iter_mut() {
+        if !settings.enabled {
+            history.end_meal();
+            continue;
+        }
+        history.record(settings.window);
+        if dispersing || organism.energy <= 0.0 {
+            continue;
+        }
+        let starving = history
+            .average(settings.window)
+            .is_some_and(|average| average < organism.starvation_trigger);
+        if starving {
+            // a fresh window once the burst is over, so it does not set off again at once
+            history.clear();
+            commands.entity(entity).insert(Dispersing {
+                ticks: organism.burst_duration.round().max(1.0) as u32,
+                heading: entity_rng.0.gen_range(0..COMPASS.len()),
+            });
+        }
+    }
+}
+
+/// The heading of a dispersing organism after one more step: the same with chance
+/// `persistence`, otherwise turned one eighth of a circle either way.
+fn next_heading(heading: usize, persistence: f32, rng: &mut impl Rng) -> usize {
+    if rng.gen::<f32>() < persistence {
+        return heading;
+    }
+    let turn = if rng.gen() { 1 } else { COMPASS.len() - 1 };
+    (heading + turn) % COMPASS.len()
+}
+
+fn end_dispersal(mut commands: Commands, mut query: Query<(Entity, &mut Dispersing)>) {
+    for (entity, mut dispersing) in query.iter_mut() {
+        dispersing.ticks = dispersing.ticks.saturating_sub(1);
+        if dispersing.ticks == 0 {
+            commands.entity(entity).remove::<Dispersing>();
+        }
+    }
+}
+
+/// Drains the energy injuries cost and removes the ones that have healed.
+fn heal_injuries(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Organism, &mut Injured), Active>,
+    config: Res<Config>,
+) {
+    for (entity, mut organism, mut injured) in query.iter_mut() {
+        organism.energy -= config.organism.injury.energy_drain * injured.severity;
+        injured.ticks_remaining = injured.ticks_remaining.saturating_sub(1);
+        if injured.ticks_remaining == 0 {
+            commands.entity(entity).remove::<Injured>();
+        }
+    }
+}
+
+/// Moves left to an organism with `moves` after an injury of `severity`, which may
+/// leave a badly injured one unable to move at all.
+pub fn injured_moves(moves: u32, severity: f32, movement_penalty: f32) -> u32 {
+    (moves as f32 * (1.0 - movement_penalty * severity).max(0.0)).round() as u32
+}
+
+fn calm_panicked(mut commands: Commands, mut query: Query<(Entity, &mut Panicked)>) {
+    for (entity, mut panicked) in query.iter_mut() {
+        panicked.ticks = panicked.ticks.saturating_sub(1);
+        if panicked.ticks == 0 {
+            commands.entity(entity).remove::<Panicked>();
+        }
+    }
+}
+
+/// Chooses which co-located prey a predator attacks. Unless `weighted` the first prey
+/// is taken; otherwise prey are picked in proportion to their weight, which grows
+/// with boldness and reserve.
+fn pick_prey(candidates: &[(Entity, f32)], weighted: bool, rng: &mut impl Rng) -> Option<Entity> {
+    if candidates.is_empty() {
+        return None;
+    }
+    if !weighted {
+        return Some(candidates[0].0);
+    }
+
+    let total: f32 = candidates.iter().map(|&(_, weight)| weight).sum();
+    let mut roll = rng.gen_range(0.0..total);
+    for &(entity, weight) in candidates {
+        if roll < weight {
+            return Some(entity);
+        }
+        roll -= weight;
+    }
+    candidates.last().map(|&(entity, _)| entity)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn predator_reproduction(
+    mut commands: Commands,
+    mut query: Query<(&mut Predator, &Position, &mut Identity), Active>,
+    pool: Query<Entity, (With<Predator>, With<Inactive>)>,
+    world: Res<World>,
+    config: Res<Config>,
+    streams: Res<RngStreams>,
+    pop: Res<PopulationCount>,
+    generation: Res<Generation>,
+    index: Res<PredatorSpatialIndex>,
+    mut next_id: ResMut<NextId>,
+    mut clamps: ResMut<ClampTally>,
+    mut placed: Local<HashMap<(usize, usize), usize>>,
+) {
+    let total_entities = pop.organisms + pop.predators;
+
+    if total_entities >= config.world.max_total_entities {
+        warn_cap_reached(&mut clamps, total_entities, generation.0);
+        return;
+    }
+    let mut rng = streams.for_system("predator_reproduction", generation.0);
+    let mut pool = pool.iter();
+    placed.clear();
+    let threshold = config.predator.overcrowding_threshold_for_predators;
+    let breeding = &config.predator.predator_breeding;
+    let season = config.world.seasons.season(generation.0);
+    let off_season = breeding.off_season(season);
+    let windows = breeding.windowed(season);
+
+    for (mut predator, position, mut identity) in query.iter_mut() {
+        if predator.energy <= 0.0 {
+            continue;
+        }
+        if predator.reproduction_cooldown > 0.0 {
+            predator.reproduction_cooldown -= 1.0;
+            continue;
+        }
+        if predator_maturity(Some(&identity), generation.0, &config.predator) < 1.0 {
+            continue;
+        }
+
+        if decides_to_reproduce(
+            predator.energy,
+            predator.reproduction_threshold,
+            &config.world,
+            &mut rng,
+        ) && (!off_season || rng.gen::<f32>() < predator.breeding_flexibility)
+        {
+            let min_kills = config.predator.min_kills_for_reproduction;
+            if min_kills > 0
+                && predator
+                    .recent_kills
+                    .within(generation.0, config.predator.kill_memory_ticks)
+                    < min_kills
+            {
+                clamps.predator_births_blocked += 1;
+                continue;
+            }
+            let mutation_factor = config.predator.predator_mutability;
+            let kind = EntityKind::Predator;
+
+            let mutated_size = predator.size * (1.0 + mutation(&mut rng, mutation_factor));
+            let size = clamps.clamp(kind, "size", mutated_size, 0.1, f32::INFINITY);
+
+            let mutated_speed = predator.speed * (1.1 + mutation(&mut rng, mutation_factor));
+            let penalty = size * 0.1;
+            let speed = clamps.clamp(kind, "speed", mutated_speed - penalty, 0.1, f32::INFINITY);
+
+            let reproduction_cooldown = (config.predator.predator_reproduction_cooldown
+                * (1.0 + mutation(&mut rng, mutation_factor)))
+            .max(1.0);
+
+            let hunt_cooldown_ticks = if config.predator.hunt_cooldown_evolvable {
+                clamps.clamp(
+                    kind,
+                    "hunt_cooldown_ticks",
+                    predator.hunt_cooldown_ticks * (1.0 + mutation(&mut rng, mutation_factor)),
+                    0.0,
+                    f32::INFINITY,
+                )
+            } else {
+                config.predator.hunt_cooldown_ticks as f32
+            };
+
+            // only drawn with ambushes on, so runs without them keep their random streams
+            let patience = if config.predator.ambush.enabled {
+                clamps.clamp(
+                    kind,
+                    "patience",
+                    predator.patience + mutation(&mut rng, mutation_factor),
+                    0.0,
+                    1.0,
+                )
+            } else {
+                predator.patience
+            };
+            let aggression = if config.predator.territory.enabled {
+                clamps.clamp(
+                    kind,
+                    "aggression",
+                    predator.aggression + mutation(&mut rng, mutation_factor),
+                    0.0,
+                    1.0,
+                )
+            } else {
+                predator.aggression
+            };
+            let breeding_flexibility = if windows {
+                (predator.breeding_flexibility + mutation(&mut rng, mutation_factor))
+                    .clamp(0.0, 1.0)
+            } else {
+                predator.breeding_flexibility
+            };
+
+            // only drawn with wandering on, so runs without it keep their random streams
+            let heading = if config.predator.wander.enabled {
+                rng.gen_range(0.0..TAU)
+            } else {
+                0.0
+            };
+
+            let hunting_efficiency =
+                predator.hunting_efficiency * (1.0 + mutation(&mut rng, mutation_factor));
+            let satiation_threshold =
+                predator.satiation_threshold * (1.0 + mutation(&mut rng, mutation_factor));
+            let (reproduction_threshold, bound) = clamped_reproduction_threshold(
+                predator.reproduction_threshold * (1.0 + mutation(&mut rng, mutation_factor)),
+                config.predator.max_predator_energy,
+                &config,
+            );
+            clamps.record(kind, "reproduction_threshold", bound);
+
+            let child = Predator {
+                energy: predator.energy / 2.0,
+                speed,
+                size,
+                hunting_efficiency,
+                satiation_threshold,
+                reproduction_threshold,
+                reproduction_cooldown,
+                preferred_temperature: predator.preferred_temperature
+                    + mutation(&mut rng, mutation_factor) * TEMPERATURE_MUTATION_SCALE,
+                hunt_cooldown_ticks,
+                hunt_cooldown_remaining: 0,
+                patience,
+                stance: Stance::Roaming,
+                heading,
+                aggression,
+                breeding_flexibility,
+                recent_kills: KillMemory::default(),
+            };
+
+            let child_position = place_offspring(
+                position,
+                1.0,
+                &world,
+                &config,
+                |x, y| index.0.get(x, y).len() + placed.get(&(x, y)).unwrap_or(&0) < threshold,
+                &mut rng,
+            );
+            let tile = (child_position.x, child_position.y);
+            *placed.entry(tile).or_default() += 1;
+
+            let age = generation.0.saturating_sub(identity.birth_generation);
+            let id = next_id.allocate();
+            spawn_recycled(
+                &mut commands,
+                &mut pool,
+                (
+                    child,
+                    child_position,
+                    EntityRng(streams.for_entity(id, generation.0)),
+                    identity.child(id, generation.0),
+                    ParentTraits(predator.traits()),
+                    ParentAge(age),
+                    PredatorSight::default(),
+                ),
+            );
+            identity.offspring_count += 1;
+            clamps.birth(kind, age);
+
+            predator.energy /= 2.0;
+            if off_season {
+                predator.energy *= 1.0 - breeding.off_season_cost;
+            }
+            predator.reproduction_cooldown = config.predator.predator_reproduction_cooldown;
+        }
+    }
+}
+
+fn organism_overcrowding(
+    mut query: Query<(&mut Organism, Option<&Identity>), Active>,
+    index: Res<SpatialIndex>,
+    config: Res<Config>,
+    mut death_tally: ResMut<DeathTally>,
+    mut scratch: Local<Vec<(Entity, f32, u64)>>,
+) {
+    let threshold = config.organism.overcrowding_threshold_for_organisms;
+    for cell in index.cells.iter() {
+        if cell.len() <= threshold {
+            continue;
+        }
+
+        scratch.clear();
+        for &entity in cell.iter() {
+            if let Ok((organism, identity)) = query.get(entity) {
+                let id = identity.map_or(u64::MAX, |identity| identity.id);
+                scratch.push((entity, organism.energy, id));
+            }
+        }
+
+        scratch.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.2.cmp(&b.2)));
+        let to_remove = scratch.len().saturating_sub(threshold);
+        for &(entity, ..) in scratch.iter().take(to_remove) {
+            if let Ok((mut organism, _)) = query.get_mut(entity) {
+                death_tally.record(DeathCause::Overcrowding, organism.energy);
+                organism.energy = -1.0;
+                debug!(target: "sim::overcrowding", "Organism died due to overcrowding");
+            }
+        }
+    }
+}
+
+fn predator_overcrowding(
+    mut query: Query<(&mut Predator, Option<&Identity>), Active>,
+    index: Res<PredatorSpatialIndex>,
+    config: Res<Config>,
+    mut death_tally: ResMut<DeathTally>,
+    mut scratch: Local<Vec<(Entity, f32, u64)>>,
+) {
+    let threshold = config.predator.overcrowding_threshold_for_predators;
+    for cell in index.0.cells.iter() {
+        if cell.len() <= threshold {
+            continue;
+        }
+
+        scratch.clear();
+        for &entity in cell.iter() {
+            if let Ok((predator, identity)) = query.get(entity) {
+                let id = identity.map_or(u64::MAX, |identity| identity.id);
+                scratch.push((entity, predator.energy, id));
+            }
+        }
+
+        scratch.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.2.cmp(&b.2)));
+        let to_remove = scratch.len().saturating_sub(threshold);
+        for &(entity, ..) in scratch.iter().take(to_remove) {
+            if let Ok((mut predator, _)) = query.get_mut(entity) {
+                death_tally.record(DeathCause::Overcrowding, predator.energy);
+                predator.energy = -1.0;
+                debug!(target: "sim::overcrowding", "Predator died due to overcrowding");
+            }
+        }
+    }
+}
+
+fn increment_generation(mut generation: ResMut<Generation>) {
+    generation.0 += 1;
+}
+
+/// Culls organisms and predators picked uniformly at random until no more are alive
+/// than `max_total_entities`. Counts the living after this tick's births rather
+/// than the [`PopulationCount`] of its start, so the tick never ends over the cap.
+fn kill_over_limit_organisms(
+    mut commands: Commands,
+    organisms_query: Query<(Entity, &Organism), Active>,
+    predators_query: Query<(Entity, &Predator), Active>,
+    config: Res<Config>,
+    streams: Res<RngStreams>,
+    generation: Res<Generation>,
+    mut death_tally: ResMut<DeathTally>,
+) {
+    let limit = config.world.max_total_entities;
+    let mut remaining = organisms_query.iter().len() + predators_query.iter().len();
+    if remaining <= limit {
+        return;
+    }
+    let mut to_kill = remaining - limit;
+    let mut rng = streams.for_system("kill_over_limit_organisms", generation.0);
+    let individuals = organisms_query
+        .iter()
+        .map(|(entity, organism)| (entity, organism.energy))
+        .chain(
+            predators_query
+                .iter()
+                .map(|(entity, predator)| (entity, predator.energy)),
+        );
+
+    // selection sampling: each is culled with the chance that leaves exactly
+    // `to_kill` culled once every individual has been considered
+    for (entity, energy) in individuals {
+        if to_kill == 0 {
+            break;
+        }
+        if rng.gen::<f32>() * (remaining as f32) < to_kill as f32 {
+            retire(&mut commands, entity, &config);
+            death_tally.record(DeathCause::Culled, energy);
+            to_kill -= 1;
+        }
+        remaining -= 1;
+    }
+}
+
+/// Despawns the pooled entities of each kind beyond `entity_pool_size`.
+fn trim_entity_pool(
+    mut commands: Commands,
+    organisms: Query<Entity, (With<Organism>, With<Inactive>)>,
+    predators: Query<Entity, (With<Predator>, With<Inactive>)>,
+    config: Res<Config>,
+) {
+    let size = config.world.entity_pool_size;
+    for entity in organisms
+        .iter()
+        .skip(size)
+        .chain(predators.iter().skip(size))
+    {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn run_for_x_generations(
+    generation: Res<Generation>,
+    config: Res<Config>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if let Some(limit) = config.world.generation_limit {
+        if generation.0 >= limit {
+            next_state.set(AppState::Finished);
+        }
+    }
+}
+
+fn print_simulation_progress(
+    generation: Res<Generation>,
+    config: Res<Config>,
+    app_state: Res<State<AppState>>,
+    pop: Res<PopulationCount>,
+) {
+    if app_state.get() == &AppState::Simulate {
+        let organisms_count = pop.organisms;
+        let predators_count = pop.predators;
+        let total_entities = organisms_count + predators_count;
+        info!(
+            target: "sim::progress",
+            "Generation: {} / {}, Total entities: {}, Organisms: {}, Predators: {}",
+            generation.0,
+            config.world.generation_limit.unwrap_or(0),
+            total_entities,
+            organisms_count,
+            predators_count
+        );
+    }
+}
+
+/// Decides whether the simulation ticks this frame. Headless runs have no frames to
+/// draw in between, so they sleep until the next tick is due instead of spinning.
+fn advance_tick_clock(config: Res<Config>, mut clock: ResMut<TickClock>) {
+    let interval = config
+        .world
+        .target_ticks_per_second
+        .map(|target| Duration::from_secs_f32(1.0 / target));
+    if config.world.headless && interval.is_some() {
+        std::thread::sleep(clock.until_next(Instant::now()));
+    }
+    clock.due = !clock.paused && clock.tick(Instant::now(), interval);
+}
+
+/// Whether the simulation, and whatever follows it, runs this frame.
+pub fn tick_due(clock: Res<TickClock>) -> bool {
+    clock.due
+}
+
+fn exit_app(mut exit: EventWriter<AppExit>, app_state: Res<State<AppState>>) {
+    if app_state.get() == &AppState::Finished {
+        exit.send(AppExit::Success);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+
+    use super::*;
+    use crate::components::KILL_MEMORY;
+    use crate::resources::{AgingConfig, KinSharingConfig, Season, Territory};
+    use crate::utils::default_config;
+
+    fn grassland_world(width: usize, height: usize) -> World {
+        let mut world = World::new(width, height, 0).0;
+        for tile in world.grid.iter_mut() {
+            tile.biome = Biome::Grassland;
+        }
+        world
+    }
+
+    #[test]
+    fn bold_organisms_discount_predators_and_favor_food() {
+        let mut config = default_config().organism;
+        config.predator_fear_weight = 10.0;
+        config.food_attraction_weight = 0.1;
+        let cost = |food, predators, boldness| {
+            let (fear, food) = fear_and_food_terms(food, predators, boldness, &config);
+            10.0 + fear + food
+        };
+
+        let timid = cost(50.0, 3, 0.0);
+        let bold = cost(50.0, 3, 1.0);
+        assert!(bold < timid);
+
+        let timid_fear = cost(0.0, 3, 0.0) - cost(0.0, 0, 0.0);
+        let bold_fear = cost(0.0, 3, 1.0) - cost(0.0, 0, 1.0);
+        assert!((timid_fear - 30.0).abs() < 1e-4);
+        assert!(bold_fear.abs() < 1e-4);
+
+        let timid_food = cost(50.0, 0, 0.0);
+        let bold_food = cost(50.0, 0, 1.0);
+        assert!((timid_food - 5.0).abs() < 1e-4);
+        assert!((bold_food - 0.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn bold_prey_are_targeted_more_often() {
+        let timid = Entity::from_raw(1);
+        let bold = Entity::from_raw(2);
+        let candidates = [(timid, 1.0), (bold, 1.0 + 2.0)];
+        let mut rng = SmallRng::seed_from_u64(7);
+
+        let bold_picks = (0..10_000)
+            .filter(|_| pick_prey(&candidates, true, &mut rng) == Some(bold))
+            .count();
+        assert!((7_000..8_000).contains(&bold_picks));
+        assert_eq!(pick_prey(&candidates, false, &mut rng), Some(timid));
+        assert_eq!(pick_prey(&[], true, &mut rng), None);
+    }
+
+    /// A grassland world warming by 10 degrees per row.
+    fn temperature_ramp(width: usize, height: usize) -> World {
+        let mut world = grassland_world(width, height);
+        for (i, tile) in world.grid.iter_mut().enumerate() {
+            tile.temperature = (i / width) as f32 * 10.0;
+        }
+        world
+    }
+
+    #[test]
+    fn comfort_lies_along_the_temperature_gradient() {
+        let world = temperature_ramp(5, 9);
+        // row 4 is at 40 degrees
+        assert_eq!(comfort_direction(&world, 2, 4, 0.0, 5.0), Some(Vec2::NEG_Y));
+        assert_eq!(comfort_direction(&world, 2, 4, 80.0, 5.0), Some(Vec2::Y));
+        assert_eq!(comfort_direction(&world, 2, 4, 44.0, 5.0), None);
+        assert_eq!(
+            comfort_direction(&grassland_world(5, 9), 2, 4, 80.0, 5.0),
+            None
+        );
+    }
+
+    #[test]
+    fn migratory_organisms_step_towards_comfort() {
+        let step_from_row_4 = |preferred_temperature: f32| {
+            let mut config = default_config();
+            config.organism.temperature_tolerance = 5.0;
+            let (width, height) = (5, 9);
+            let mut app = App::new();
+            app.insert_resource(temperature_ramp(width, height))
+                .insert_resource(FoodGrid::new(width, vec![0.0; width * height]))
+                .insert_resource(PredatorSpatialIndex::new(width, height))
+                .insert_resource(KillScentGrid::new(width * height))
+                .init_resource::<MovementTrace>()
+                .insert_resource(Generation(0))
+                .insert_resource(RngStreams::new(0))
+                .insert_resource(config.clone())
+                .add_systems(Update, organism_movement);
+            let organism = app
+                .world_mut()
+                .spawn((
+                    Organism {
+                        speed: 1.0,
+                        exploration: 0.0,
+                        preferred_temperature,
+                        migratory_tendency: 5.0,
+                        ..founder_organism(&config, [1.0; 4])
+                    },
+                    Position { x: 2, y: 4 },
+                    EntityRng(SmallRng::seed_from_u64(3)),
+                ))
+                .id();
+            app.update();
+            *app.world().get::<Position>(organism).unwrap()
+        };
+
+        let too_hot = step_from_row_4(0.0);
+        assert_eq!((too_hot.x, too_hot.y), (2, 3));
+        let too_cold = step_from_row_4(80.0);
+        assert_eq!((too_cold.x, too_cold.y), (2, 5));
+    }
+
+    #[test]
+    fn only_the_traced_organism_records_its_decisions() {
+        let mut config = default_config();
+        config.organism.food_attraction_weight = 0.1;
+        config.organism.legacy_movement_jitter = true;
+        config.predator.kill_scent.enabled = true;
+        config.logging.trace_entity = Some(3);
+        let (width, height) = (9, 9);
+        let food = (0..width * height).map(|i| (i % 7) as f32).collect();
+        let scent = (0..width * height).map(|i| (i % 3) as f32 * 0.5).collect();
+        let mut app = App::new();
+        app.insert_resource(temperature_ramp(width, height))
+            .insert_resource(FoodGrid::new(width, food))
+            .insert_resource(PredatorSpatialIndex::new(width, height))
+            .insert_resource(KillScentGrid(scent))
+            .init_resource::<MovementTrace>()
+            .insert_resource(Generation(0))
+            .insert_resource(RngStreams::new(0))
+            .insert_resource(config.clone())
+            .add_systems(Update, organism_movement);
+        for id in 1..=4 {
+            app.world_mut().spawn((
+                Organism {
+                    speed: 2.0,
+                    energy: 1_000.0,
+                    migratory_tendency: 1.0,
+                    ..founder_organism(&config, [1.0; 4])
+                },
+                Position { x: 4, y: 4 },
+                EntityRng(SmallRng::seed_from_u64(id)),
+                Identity::founder(id, 0),
+            ));
+        }
+        for _ in 0..5 {
+            app.update();
+            app.world_mut().resource_mut::<Generation>().0 += 1;
+        }
+
+        let trace = &app.world().resource::<MovementTrace>().0;
+        let generations: Vec<_> = trace.iter().map(|record| record.generation).collect();
+        assert_eq!(generations, [0, 1, 2, 3, 4]);
+        let mut weighed = TraceCandidate {
+            dx: 0,
+            dy: 0,
+            biome: Biome::Grassland,
+            base_cost: 0.0,
+            tolerance: 0.0,
+            terrain: 0.0,
+            fear: 0.0,
+            food: 0.0,
+            kill_scent: 0.0,
+            migration: 0.0,
+            flee: 0.0,
+            noise: 0.0,
+            total: 0.0,
+        };
+        for record in trace {
+            assert_eq!(record.id, 3);
+            assert_eq!(record.state, TraceState::Foraging);
+            assert_eq!(record.steps.len(), 2);
+            for step in &record.steps {
+                assert_eq!(step.temperature, 0.0);
+                assert_eq!(step.candidates.len(), DIRECTIONS.len());
+                for candidate in &step.candidates {
+                    assert_eq!(
+                        candidate.terrain
+                            + candidate.fear
+                            + candidate.food
+                            + candidate.kill_scent
+                            + candidate.migration
+                            + candidate.flee
+                            + candidate.noise,
+                        candidate.total
+                    );
+                    weighed.food = weighed.food.min(candidate.food);
+                    weighed.kill_scent = weighed.kill_scent.max(candidate.kill_scent);
+                    weighed.migration = weighed.migration.min(candidate.migration);
+                    weighed.noise = weighed.noise.max(candidate.noise);
+                }
+                // without exploration the cheapest tile is taken
+                let cheapest = step.candidates[step.chosen].total;
+                assert!(step.candidates.iter().all(|c| cheapest <= c.total));
+            }
+            let (first, second) = (&record.steps[0], &record.steps[1]);
+            let (dx, dy) = DIRECTIONS[first.chosen];
+            assert_eq!(
+                (second.from.x as isize, second.from.y as isize),
+                (first.from.x as isize + dx, first.from.y as isize + dy)
+            );
+        }
+        assert!(weighed.food < 0.0 && weighed.migration < 0.0);
+        assert!(weighed.kill_scent > 0.0 && weighed.noise > 0.0);
+    }
+
+    #[test]
+    fn zero_exploration_always_picks_cheapest_direction() {
+        let costs = [12.0, 3.0, 7.5, 3.2, 40.0, 9.0, 100.0, 5.0];
+        let mut rng = SmallRng::seed_from_u64(11);
+        for temperature in [0.0, 1e-6, MIN_EXPLORATION / 2.0] {
+            assert!(
+                (0..1_000).all(|_| softmax_pick(&costs, temperature, |i| i as u64, &mut rng) == 1)
+            );
+        }
+
+        let wandering = (0..1_000)
+            .filter(|_| softmax_pick(&costs, 1_000.0, |i| i as u64, &mut rng) != 1)
+            .count();
+        assert!(wandering > 500);
+    }
+
+    #[test]
+    fn equally_cheap_directions_go_to_the_lowest_tie_rank() {
+        let costs = [5.0, 3.0, 7.0, 3.0, 3.0, 9.0, 3.0, 5.0];
+        let mut rng = SmallRng::seed_from_u64(11);
+        assert_eq!(softmax_pick(&costs, 0.0, |i| i as u64, &mut rng), 1);
+        assert_eq!(softmax_pick(&costs, 0.0, |i| 10 - i as u64, &mut rng), 6);
+
+        // the rank depends on the individual and the tick, not on the order looked at
+        let streams = RngStreams::new(3);
+        let picks: std::collections::HashSet<usize> = (0..64)
+            .map(|id| {
+                softmax_pick(
+                    &[0.0; 8],
+                    0.0,
+                    |i| streams.tie_break(id, 5, i as u64),
+                    &mut rng,
+                )
+            })
+            .collect();
+        assert!(picks.len() > 4, "{:?}", picks);
+        let mut pick = |tick| {
+            softmax_pick(
+                &[0.0; 8],
+                0.0,
+                |i| streams.tie_break(9, tick, i as u64),
+                &mut rng,
+            )
+        };
+        assert_eq!(pick(5), pick(5));
+    }
+
+    #[test]
+    fn food_patches_exceed_the_biome_max_only_while_they_last() {
+        let mut config = default_config();
+        config.world.food_patches = crate::resources::FoodPatchConfig {
+            probability: 1.0,
+            radius: 1,
+            bonus_food: 50.0,
+            duration: 3,
+            decay_rate: 0.5,
+        };
+        let max = config.world.grassland.max_food_availability;
+
+        let (width, height) = (7, 7);
+        let mut app = App::new();
+        app.add_event::<FoodPatchAppeared>()
+            .insert_resource(grassland_world(width, height))
+            .insert_resource(FoodGrid::new(width, vec![max; width * height]))
+            .insert_resource(ActiveTiles::all(width * height))
+            .insert_resource(FoodPatches::default())
+            .insert_resource(config)
+            .insert_resource(Generation(0))
+            .insert_resource(RngStreams::new(5))
+            .add_systems(Update, food_patches);
+
+        app.update();
+        app.world_mut()
+            .resource_mut::<Config>()
+            .world
+            .food_patches
+            .probability = 0.0;
+        let appeared: Vec<_> = app
+            .world_mut()
+            .resource_mut::<Events<FoodPatchAppeared>>()
+            .drain()
+            .collect();
+        assert_eq!(appeared.len(), 1);
+        let patch = app.world().resource::<FoodPatches>().active[0];
+        let center = patch.y * width + patch.x;
+        let food = |app: &App, i: usize| app.world().resource::<FoodGrid>().get(i);
+        let outside = (0..width * height)
+            .find(|&i| !patch.covers(i % width, i / width))
+            .unwrap();
+
+        // in place for the generation it appeared in and the next two
+        for _ in 0..3 {
+            assert_eq!(food(&app, center), max + 50.0);
+            assert_eq!(food(&app, outside), max);
+            app.update();
+        }
+        assert_eq!(food(&app, center), max + 25.0);
+        app.update();
+        assert_eq!(food(&app, center), max + 12.5);
+
+        for _ in 0..20 {
+            app.update();
+        }
+        assert_eq!(food(&app, center), max);
+        let patches = app.world().resource::<FoodPatches>();
+        assert!(patches.active.is_empty() && patches.decaying.is_empty());
+    }
+
+    #[test]
+    fn catastrophe_respects_radius() {
+        let mut config = default_config();
+        config.world.catastrophes.probability = 1.0;
+        config.world.catastrophes.min_radius = 2;
+        config.world.catastrophes.max_radius = 2;
+        config.world.catastrophes.mortality = 1.0;
+        config.world.catastrophes.food_destruction = 1.0;
+        config.world.catastrophes.flood_share = 0.0;
+
+        let (width, height) = (9, 9);
+        let mut app = App::new();
+        app.add_event::<BiomeChanged>()
+            .add_event::<CatastropheStruck>()
+            .insert_resource(grassland_world(width, height))
+            .insert_resource(FoodGrid::new(width, vec![10.0; width * height]))
+            .insert_resource(ActiveTiles::all(width * height))
+            .insert_resource(config)
+            .insert_resource(Generation(0))
+            .insert_resource(DeathTally::default())
+            .insert_resource(RngStreams::new(3))
+            .add_systems(Update, catastrophes);
+
+        for y in 0..height {
+            for x in 0..width {
+                let organism = Organism {
+                    energy: 10.0,
+                    speed: 1.0,
+                    size: 1.0,
+                    reproduction_threshold: 100.0,
+                    reproduction_cooldown: 0.0,
+                    biome_tolerance: [1.0; 4],
+                    boldness: 0.5,
+                    exploration: 2.0,
+                    morph: 0,
+                    parental_investment: 0.5,
+                    dispersal: 1.0,
+                    preferred_temperature: 20.0,
+                    migratory_tendency: 0.0,
+                    altruism: 0.0,
+                    starvation_trigger: 0.0,
+                    burst_duration: 1.0,
+                    reserve_capacity: 200.0,
+                    breeding_flexibility: 0.0,
+                    giving_up_density: 1.0,
+                };
+                app.world_mut().spawn((organism, Position { x, y }));
+            }
+        }
+
+        app.update();
+
+        let struck: Vec<_> = app
+            .world_mut()
+            .resource_mut::<Events<CatastropheStruck>>()
+            .drain()
+            .collect();
+        assert_eq!(struck.len(), 1);
+        let (cx, cy, radius) = (struck[0].x as isize, struck[0].y as isize, 2isize);
+        let within = |x: usize, y: usize| {
+            let (dx, dy) = (x as isize - cx, y as isize - cy);
+            dx * dx + dy * dy <= radius * radius
+        };
+
+        let mut survivors = app.world_mut().query::<&Position>();
+        let survivors: Vec<_> = survivors.iter(app.world()).copied().collect();
+        let inside = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| within(x, y))
+            .count();
+        assert_eq!(survivors.len(), width * height - inside);
+        assert!(survivors.iter().all(|p| !within(p.x, p.y)));
+        assert_eq!(struck[0].killed, inside);
+        assert_eq!(
+            app.world().resource::<DeathTally>().causes[&DeathCause::Catastrophe],
+            inside
+        );
+
+        let food = app.world().resource::<FoodGrid>().values();
+        for y in 0..height {
+            for x in 0..width {
+                let expected = if within(x, y) { 0.0 } else { 10.0 };
+                assert_eq!(food[y * width + x], expected);
+            }
+        }
+    }
+
+    #[test]
+    fn prey_on_reserve_tiles_cannot_be_hunted() {
+        let mut world = grassland_world(2, 1);
+        world.grid[0].reserve = true;
+
+        let mut app = App::new();
+        app.insert_resource(world)
+            .insert_resource(default_config())
+            .insert_resource(SpatialIndex::new(2, 1))
+            .insert_resource(DeathTally::default())
+            .insert_resource(Generation(0))
+            .add_event::<Killed>()
+            .add_event::<PreyInjured>()
+            .init_resource::<TerritoryMap>()
+            .add_systems(Update, hunting);
+
+        let mut prey = Vec::new();
+        for x in 0..2 {
+            let organism = Organism {
+                energy: 10.0,
+                speed: 1.0,
+                size: 1.0,
+                reproduction_threshold: 100.0,
+                reproduction_cooldown: 0.0,
+                biome_tolerance: [1.0; 4],
+                boldness: 0.5,
+                exploration: 2.0,
+                morph: 0,
+                parental_investment: 0.5,
+                dispersal: 1.0,
+                preferred_temperature: 20.0,
+                migratory_tendency: 0.0,
+                altruism: 0.0,
+                starvation_trigger: 0.0,
+                burst_duration: 1.0,
+                reserve_capacity: 200.0,
+                breeding_flexibility: 0.0,
+                giving_up_density: 1.0,
+            };
+            let entity = app.world_mut().spawn((organism, Position { x, y: 0 })).id();
+            app.world_mut()
+                .resource_mut::<SpatialIndex>()
+                .insert(x, 0, entity);
+            prey.push(entity);
+
+            app.world_mut().spawn((
+                Predator {
+                    energy: 10.0,
+                    speed: 1.0,
+                    size: 1.0,
+                    reproduction_threshold: 100.0,
+                    hunting_efficiency: 1.0,
+                    satiation_threshold: 100.0,
+                    reproduction_cooldown: 0.0,
+                    preferred_temperature: 20.0,
+                    hunt_cooldown_ticks: 0.0,
+                    hunt_cooldown_remaining: 0,
+                    patience: 0.0,
+                    stance: Stance::Roaming,
+                    heading: 0.0,
+                    aggression: 0.0,
+                    breeding_flexibility: 0.0,
+                    recent_kills: KillMemory::default(),
+                },
+                Position { x, y: 0 },
+                EntityRng(SmallRng::seed_from_u64(1)),
+            ));
+        }
+
+        app.update();
+
+        assert!(app.world().get_entity(prey[0]).is_ok());
+        assert!(app.world().get_entity(prey[1]).is_err());
+        assert_eq!(
+            app.world().resource::<DeathTally>().causes[&DeathCause::Predation],
+            1
+        );
+    }
+
+    #[test]
+    fn hunt_cooldown_limits_kills_on_consecutive_ticks() {
+        let mut config = default_config();
+        config.predator.hunt_cooldown_ticks = 2;
+
+        let mut app = App::new();
+        app.insert_resource(grassland_world(1, 1))
+            .insert_resource(SpatialIndex::new(1, 1))
+            .insert_resource(DeathTally::default())
+            .insert_resource(Generation(0))
+            .add_event::<Killed>()
+            .add_event::<PreyInjured>()
+            .init_resource::<TerritoryMap>()
+            .add_systems(Update, hunting);
+
+        let mut predator = founder_predator(&config);
+        predator.satiation_threshold = f32::MAX;
+        app.insert_resource(config);
+        app.world_mut().spawn((
+            predator,
+            Position { x: 0, y: 0 },
+            EntityRng(SmallRng::seed_from_u64(1)),
+        ));
+
+        let prey: Vec<_> = (0..2)
+            .map(|_| {
+                let entity = app
+                    .world_mut()
+                    .spawn((
+                        founder_organism(&default_config(), [1.0; 4]),
+                        Position { x: 0, y: 0 },
+                    ))
+                    .id();
+                app.world_mut()
+                    .resource_mut::<SpatialIndex>()
+                    .insert(0, 0, entity);
+                entity
+            })
+            .collect();
+
+        app.update();
+        app.update();
+
+        let survivors = prey
+            .iter()
+            .filter(|&&entity| app.world().get_entity(entity).is_ok())
+            .count();
+        assert_eq!(survivors, 1);
+
+        // the cooldown has run out by the third tick
+        app.update();
+        assert!(prey
+            .iter()
+            .all(|&entity| app.world().get_entity(entity).is_err()));
+    }
+
+    /// Whether a predator born to a parent in `birth_generation` kills the prey on its
+    /// tile at generation 10.
+    fn offspring_predator_kills(birth_generation: usize, config: Config) -> bool {
+        let mut app = App::new();
+        app.insert_resource(grassland_world(1, 1))
+            .insert_resource(SpatialIndex::new(1, 1))
+            .insert_resource(DeathTally::default())
+            .insert_resource(Generation(10))
+            .add_event::<Killed>()
+            .add_event::<PreyInjured>()
+            .init_resource::<TerritoryMap>()
+            .add_systems(Update, hunting);
+
+        let identity = Identity::founder(0, 0).child(1, birth_generation);
+        let mut predator = founder_predator(&config);
+        predator.satiation_threshold = f32::MAX;
+        app.world_mut().spawn((
+            predator,
+            Position { x: 0, y: 0 },
+            EntityRng(SmallRng::seed_from_u64(1)),
+            identity,
+        ));
+        let prey = app
+            .world_mut()
+            .spawn((founder_organism(&config, [1.0; 4]), Position { x: 0, y: 0 }))
+            .id();
+        app.world_mut()
+            .resource_mut::<SpatialIndex>()
+            .insert(0, 0, prey);
+        app.insert_resource(config);
+
+        app.update();
+        app.world().get_entity(prey).is_err()
+    }
+
+    #[test]
+    fn newborn_predators_miss_catches_adults_make() {
+        let mut config = default_config();
+        config.predator.predator_maturity_age = 5;
+        config.predator.juvenile_factor = 0.0;
+
+        assert!(!offspring_predator_kills(10, config.clone()));
+        assert!(offspring_predator_kills(2, config));
+    }
+
+    #[test]
+    fn missed_prey_is_injured_and_easier_to_catch_next_time() {
+        let mut config = default_config();
+        config.predator.predator_maturity_age = 5;
+        config.predator.juvenile_factor = 0.0;
+        config.organism.injury.enabled = true;
+        config.organism.injury.min_severity = 1.0;
+        config.organism.injury.max_severity = 1.0;
+        config.organism.injury.catch_bonus = 1.0;
+
+        let mut app = App::new();
+        app.insert_resource(grassland_world(1, 1))
+            .insert_resource(SpatialIndex::new(1, 1))
+            .insert_resource(DeathTally::default())
+            .insert_resource(Generation(10))
+            .add_event::<Killed>()
+            .add_event::<PreyInjured>()
+            .init_resource::<TerritoryMap>()
+            .add_systems(Update, hunting);
+        let mut predator = founder_predator(&config);
+        predator.satiation_threshold = f32::MAX;
+        app.world_mut().spawn((
+            predator,
+            Position { x: 0, y: 0 },
+            EntityRng(SmallRng::seed_from_u64(1)),
+            Identity::founder(0, 0).child(1, 10),
+        ));
+        let prey = app
+            .world_mut()
+            .spawn((
+                founder_organism(&config, [1.0; 4]),
+                Position { x: 0, y: 0 },
+                Identity::founder(2, 0),
+            ))
+            .id();
+        app.world_mut()
+            .resource_mut::<SpatialIndex>()
+            .insert(0, 0, prey);
+        app.insert_resource(config);
+
+        app.update();
+        let injured = *app.world().get::<Injured>(prey).unwrap();
+        assert_eq!(injured.severity, 1.0);
+        assert_eq!(injured.ticks_remaining, 20);
+        let events = app.world().resource::<Events<PreyInjured>>();
+        let event = events.iter_current_update_events().next().unwrap();
+        assert_eq!(event.id, Some(2));
+
+        app.update();
+        assert!(app.world().get_entity(prey).is_err());
+    }
+
+    #[test]
+    fn catch_bonus_only_applies_to_injured_prey() {
+        assert_eq!(catch_chance(0.3, 0.0, 0.5), 0.3);
+        assert!((catch_chance(0.3, 0.4, 0.5) - 0.5).abs() < 1e-6);
+        assert_eq!(catch_chance(0.9, 1.0, 0.5), 1.0);
+    }
+
+    #[test]
+    fn injured_organisms_move_less() {
+        assert_eq!(injured_moves(4, 0.5, 0.5), 3);
+        assert_eq!(injured_moves(3, 0.0, 0.5), 3);
+        assert_eq!(injured_moves(1, 1.0, 1.0), 0);
+
+        let energy_spent = |injured: Option<Injured>| {
+            let config = default_config();
+            let mut app = App::new();
+            app.insert_resource(grassland_world(9, 9))
+                .insert_resource(FoodGrid::new(9, vec![0.0; 81]))
+                .insert_resource(PredatorSpatialIndex::new(9, 9))
+                .insert_resource(KillScentGrid::new(81))
+                .init_resource::<MovementTrace>()
+                .insert_resource(Generation(0))
+                .insert_resource(RngStreams::new(0))
+                .insert_resource(config.clone())
+                .add_systems(Update, organism_movement);
+            let mut organism = founder_organism(&config, [1.0; 4]);
+            organism.speed = 3.0;
+            organism.size = 1.0;
+            organism.energy = 100.0;
+            let mut entity = app.world_mut().spawn((
+                organism,
+                Position { x: 4, y: 4 },
+                EntityRng(SmallRng::seed_from_u64(3)),
+            ));
+            if let Some(injured) = injured {
+                entity.insert(injured);
+            }
+            let entity = entity.id();
+            app.update();
+            100.0 - app.world().get::<Organism>(entity).unwrap().energy
+        };
+
+        let healthy = energy_spent(None);
+        let injured = energy_spent(Some(Injured {
+            severity: 1.0,
+            ticks_remaining: 5,
+        }));
+        assert!((healthy - 0.9).abs() < 1e-4, "{}", healthy);
+        assert!((injured - 0.6).abs() < 1e-4, "{}", injured);
+    }
+
+    #[test]
+    fn maturity_ramps_from_juvenile_factor_to_one() {
+        let mut config = default_config().predator;
+        config.predator_maturity_age = 4;
+        config.juvenile_factor = 0.2;
+        let child = Identity::founder(0, 0).child(1, 10);
+
+        let maturity = |generation| predator_maturity(Some(&child), generation, &config);
+        assert!((maturity(10) - 0.2).abs() < 1e-6);
+        assert!((maturity(12) - 0.6).abs() < 1e-6);
+        assert_eq!(maturity(14), 1.0);
+        assert_eq!(maturity(50), 1.0);
+        assert_eq!(
+            predator_maturity(Some(&Identity::founder(0, 10)), 10, &config),
+            1.0
+        );
+        assert_eq!(predator_maturity(None, 10, &config), 1.0);
+
+        config.predator_maturity_age = 0;
+        assert_eq!(predator_maturity(Some(&child), 10, &config), 1.0);
+    }
+
+    /// Runs `spread_panic` for a kill at `(4, 0)` with organisms at the given columns
+    /// of a 9x1 world, and returns the columns of the panicked ones.
+    fn panicked_after_kill(columns: &[usize], config: Config) -> (Vec<usize>, Vec<PanicSpread>) {
+        let mut app = App::new();
+        app.add_event::<Killed>()
+            .add_event::<PanicSpread>()
+            .insert_resource(grassland_world(9, 1))
+            .insert_resource(SpatialIndex::new(9, 1))
+            .insert_resource(Generation(0))
+            .add_systems(Update, spread_panic);
+
+        for &x in columns {
+            let entity = app
+                .world_mut()
+                .spawn((
+                    founder_organism(&config, [1.0; 4]),
+                    Position { x, y: 0 },
+                    EntityRng(SmallRng::seed_from_u64(x as u64)),
+                ))
+                .id();
+            app.world_mut()
+                .resource_mut::<SpatialIndex>()
+                .insert(x, 0, entity);
+        }
+        app.insert_resource(config);
+        app.world_mut().send_event(Killed {
+            predator: Entity::PLACEHOLDER,
+            prey: Entity::PLACEHOLDER,
+            x: 4,
+            y: 0,
+        });
+        app.update();
+
+        let mut panicked: Vec<_> = app
+            .world_mut()
+            .query_filtered::<&Position, With<Panicked>>()
+            .iter(app.world())
+            .map(|position| position.x)
+            .collect();
+        panicked.sort_unstable();
+        let spread = app
+            .world_mut()
+            .resource_mut::<Events<PanicSpread>>()
+            .drain()
+            .collect();
+        (panicked, spread)
+    }
+
+    #[test]
+    fn kill_panics_neighbours_within_radius_only() {
+        let mut config = default_config();
+        config.organism.panic.enabled = true;
+        config.organism.panic.radius = 2;
+        config.organism.panic.chain_probability = 0.0;
+
+        let (panicked, spread) = panicked_after_kill(&[0, 1, 2, 3, 4, 5, 6, 7, 8], config);
+        assert_eq!(panicked, vec![2, 3, 4, 5, 6]);
+        assert_eq!(spread.len(), 1);
+        assert_eq!(spread[0].panicked, 5);
+    }
+
+    #[test]
+    fn panic_chains_through_panicked_neighbours() {
+        let mut config = default_config();
+        config.organism.panic.enabled = true;
+        config.organism.panic.radius = 2;
+        config.organism.panic.chain_probability = 1.0;
+
+        // each organism is within the radius of the next one out from the kill
+        let (panicked, _) = panicked_after_kill(&[0, 2, 4, 6, 8], config.clone());
+        assert_eq!(panicked, vec![0, 2, 4, 6, 8]);
+
+        config.organism.panic.enabled = false;
+        let (panicked, spread) = panicked_after_kill(&[0, 2, 4, 6, 8], config);
+        assert!(panicked.is_empty());
+        assert!(spread.is_empty());
+    }
+
+    #[test]
+    fn panic_expires_after_its_duration() {
+        let mut app = App::new();
+        app.add_systems(Update, calm_panicked);
+        let organism = app
+            .world_mut()
+            .spawn((
+                Position { x: 0, y: 0 },
+                Panicked {
+                    ticks: 3,
+                    x: 0,
+                    y: 0,
+                },
+            ))
+            .id();
+
+        app.update();
+        app.update();
+        assert_eq!(app.world().get::<Panicked>(organism).unwrap().ticks, 1);
+        app.update();
+        assert!(app.world().get::<Panicked>(organism).is_none());
+    }
+
+    /// Energy one default organism gains from a tile of `biome` holding plenty of food,
+    /// along with the tally of the tick.
+    fn energy_gained_from_food(biome: Biome, config: Config) -> (f32, EnergyIntake) {
+        let mut world = grassland_world(1, 1);
+        world.grid[0].biome = biome;
+
+        let mut app = App::new();
+        app.insert_resource(world)
+            .insert_resource(FoodGrid::new(1, vec![10.0]))
+            .insert_resource(CarrionGrid::new(1))
+            .insert_resource(ActiveTiles::all(1))
+            .insert_resource(SpatialIndex::new(1, 1))
+            .insert_resource(EnergyIntake::default())
+            .add_systems(Update, consume_food);
+        let organism = app
+            .world_mut()
+            .spawn((founder_organism(&config, [1.0; 4]), Position { x: 0, y: 0 }))
+            .id();
+        app.world_mut()
+            .resource_mut::<SpatialIndex>()
+            .insert(0, 0, organism);
+        let energy = config.organism.initial_organism_energy;
+        app.insert_resource(config);
+
+        app.update();
+        let gained = app.world().get::<Organism>(organism).unwrap().energy - energy;
+        (gained, *app.world().resource::<EnergyIntake>())
+    }
+
+    /// Energy gained in one tick of eating by organisms at `spots` of a 3x3 grassland
+    /// world with water in the middle, and the water food left, starting with no food
+    /// on land and `water_food` in the water.
+    fn shoreline_gains(
+        spots: &[(usize, usize)],
+        grazing_rate: f32,
+        water_food: f32,
+    ) -> (Vec<f32>, f32) {
+        let mut config = default_config();
+        config.organism.shoreline_grazing_rate = grazing_rate;
+        let mut world = grassland_world(3, 3);
+        world.grid[4].biome = Biome::Water;
+        let mut food = vec![0.0; 9];
+        food[4] = water_food;
+
+        let mut app = App::new();
+        app.insert_resource(world)
+            .insert_resource(FoodGrid::new(3, food))
+            .insert_resource(CarrionGrid::new(9))
+            .insert_resource(ActiveTiles::all(9))
+            .insert_resource(SpatialIndex::new(3, 3))
+            .insert_resource(EnergyIntake::default())
+            .add_systems(Update, consume_food);
+        let organisms: Vec<_> = spots
+            .iter()
+            .map(|&(x, y)| {
+                let organism = app
+                    .world_mut()
+                    .spawn((founder_organism(&config, [1.0; 4]), Position { x, y }))
+                    .id();
+                app.world_mut()
+                    .resource_mut::<SpatialIndex>()
+                    .insert(x, y, organism);
+                organism
+            })
+            .collect();
+        let energy = config.organism.initial_organism_energy;
+        app.insert_resource(config);
+        app.update();
+
+        let gains = organisms
+            .iter()
+            .map(|&entity| app.world().get::<Organism>(entity).unwrap().energy - energy)
+            .collect();
+        (gains, app.world().resource::<FoodGrid>().get(4))
+    }
+
+    #[test]
+    fn only_organisms_orthogonally_next_to_water_graze_it() {
+        // an edge neighbour, a diagonal neighbour and one standing in the water
+        let (gains, water_food) = shoreline_gains(&[(0, 1), (0, 0), (1, 1)], 0.05, 10.0);
+        // 0.05 food at the default conversion of 2 energy per food
+        assert!((gains[0] - 0.1).abs() < 1e-5, "{:?}", gains);
+        assert_eq!(gains[1], 0.0);
+        assert_eq!(gains[2], 0.0);
+        assert!((water_food - 9.95).abs() < 1e-5);
+
+        let (gains, water_food) = shoreline_gains(&[(0, 1)], 0.0, 10.0);
+        assert_eq!(gains[0], 0.0);
+        assert_eq!(water_food, 10.0);
+    }
+
+    #[test]
+    fn shoreline_grazing_is_capped_by_rate_appetite_and_food() {
+        // a default organism needs 0.2 food per tick
+        let (gains, _) = shoreline_gains(&[(1, 0)], 0.15, 10.0);
+        assert!((gains[0] - 0.3).abs() < 1e-5);
+        let (gains, _) = shoreline_gains(&[(1, 0)], 5.0, 10.0);
+        assert!((gains[0] - 0.4).abs() < 1e-5);
+
+        let (gains, water_food) = shoreline_gains(&[(1, 0), (2, 1)], 5.0, 0.25);
+        let eaten: f32 = gains.iter().sum::<f32>() / 2.0;
+        assert!((eaten - 0.25).abs() < 1e-5, "{:?}", gains);
+        assert!(water_food.abs() < 1e-6);
+    }
+
+    /// Food eaten in one tick by three organisms of sizes 3, 1.5 and 0.5, needing
+    /// 0.6, 0.3 and 0.1, sharing a grassland tile with `food` on it, and the food left.
+    fn tile_meals(policy: FoodAllocation, food: f32) -> (Vec<f32>, f32) {
+        let mut config = default_config();
+        config.organism.food_allocation = policy;
+        config.organism.shoreline_grazing_rate = 0.0;
+
+        let mut app = App::new();
+        app.insert_resource(grassland_world(1, 1))
+            .insert_resource(FoodGrid::new(1, vec![food]))
+            .insert_resource(CarrionGrid::new(1))
+            .insert_resource(ActiveTiles::all(1))
+            .insert_resource(SpatialIndex::new(1, 1))
+            .insert_resource(EnergyIntake::default())
+            .add_systems(Update, consume_food);
+        let organisms: Vec<_> = [3.0, 1.5, 0.5]
+            .into_iter()
+            .map(|size| {
+                let mut organism = founder_organism(&config, [1.0; 4]);
+                organism.size = size;
+                let organism = app
+                    .world_mut()
+                    .spawn((organism, Position { x: 0, y: 0 }))
+                    .id();
+                app.world_mut()
+                    .resource_mut::<SpatialIndex>()
+                    .insert(0, 0, organism);
+                organism
+            })
+            .collect();
+        let energy = config.organism.initial_organism_energy;
+        let per_food = food_energy(1.0, Biome::Grassland, &config);
+        app.insert_resource(config);
+        app.update();
+
+        let meals = organisms
+            .iter()
+            .map(|&entity| {
+                (app.world().get::<Organism>(entity).unwrap().energy - energy) / per_food
+            })
+            .collect();
+        (meals, app.world().resource::<FoodGrid>().get(0))
+    }
+
+    fn assert_meals(meals: &[f32], expected: [f32; 3]) {
+        for (meal, expected) in meals.iter().zip(expected) {
+            assert!((meal - expected).abs() < 1e-5, "{:?}", meals);
+        }
+    }
+
+    #[test]
+    fn a_tile_short_of_food_is_split_by_the_allocation_policy() {
+        // 0.5 food for a need of 1
+        let (meals, left) = tile_meals(FoodAllocation::PriorityBySize, 0.5);
+        assert_meals(&meals, [0.5, 0.0, 0.0]);
+        assert!(left.abs() < 1e-6);
+
+        // half of each need
+        let (meals, left) = tile_meals(FoodAllocation::Proportional, 0.5);
+        assert_meals(&meals, [0.3, 0.15, 0.05]);
+        assert!(left.abs() < 1e-6);
+
+        // the smallest is full at 0.1 of its 1/6 share, the others split the rest
+        let (meals, left) = tile_meals(FoodAllocation::EqualSplit, 0.5);
+        assert_meals(&meals, [0.2, 0.2, 0.1]);
+        assert!(left.abs() < 1e-6);
+    }
+
+    #[test]
+    fn every_policy_feeds_all_to_their_fill_when_the_food_covers_it() {
+        for policy in [
+            FoodAllocation::PriorityBySize,
+            FoodAllocation::Proportional,
+            FoodAllocation::EqualSplit,
+        ] {
+            let (meals, left) = tile_meals(policy, 1.5);
+            assert_meals(&meals, [0.6, 0.3, 0.1]);
+            assert!((left - 0.5).abs() < 1e-5, "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn dead_predators_leave_carrion_that_rots() {
+        let mut config = default_config();
+        config.predator.carrion.food_per_size = 2.0;
+        config.predator.carrion.decay_rate = 0.5;
+
+        let mut app = App::new();
+        app.insert_resource(grassland_world(2, 1))
+            .insert_resource(CarrionGrid::new(2))
+            .add_systems(Update, (despawn_dead_predators, rot_carrion).chain());
+        let mut dead = founder_predator(&config);
+        dead.size = 1.5;
+        dead.energy = -1.0;
+        let dead = app.world_mut().spawn((dead, Position { x: 1, y: 0 })).id();
+        app.world_mut()
+            .spawn((founder_predator(&config), Position { x: 0, y: 0 }));
+        app.insert_resource(config);
+
+        app.update();
+        assert!(app.world().get_entity(dead).is_err());
+        assert_eq!(app.world().resource::<CarrionGrid>().0, vec![0.0, 1.5]);
+        app.update();
+        assert_eq!(app.world().resource::<CarrionGrid>().0, vec![0.0, 0.75]);
+        for _ in 0..20 {
+            app.update();
+        }
+        assert_eq!(app.world().resource::<CarrionGrid>().total(), 0.0);
+    }
+
+    #[test]
+    fn kills_leave_scent_that_fades() {
+        let mut config = default_config();
+        config.predator.kill_scent.enabled = true;
+        config.predator.kill_scent.deposit = 2.0;
+        config.predator.kill_scent.decay_ticks = 4;
+
+        let mut app = App::new();
+        app.insert_resource(grassland_world(2, 1))
+            .insert_resource(KillScentGrid::new(2))
+            .init_resource::<MovementTrace>()
+            .insert_resource(config)
+            .add_event::<Killed>()
+            .add_systems(Update, (mark_kill_sites, fade_kill_scent).chain());
+        app.world_mut().send_event(Killed {
+            predator: Entity::PLACEHOLDER,
+            prey: Entity::PLACEHOLDER,
+            x: 1,
+            y: 0,
+        });
+
+        // a tick's fading follows the kill
+        app.update();
+        assert_eq!(app.world().resource::<KillScentGrid>().0, vec![0.0, 1.5]);
+        app.update();
+        assert_eq!(app.world().resource::<KillScentGrid>().0, vec![0.0, 1.0]);
+        app.update();
+        app.update();
+        assert_eq!(app.world().resource::<KillScentGrid>().0, vec![0.0, 0.0]);
+
+        app.world_mut()
+            .resource_mut::<Config>()
+            .predator
+            .kill_scent
+            .enabled = false;
+        app.world_mut().send_event(Killed {
+            predator: Entity::PLACEHOLDER,
+            prey: Entity::PLACEHOLDER,
+            x: 0,
+            y: 0,
+        });
+        app.update();
+        assert_eq!(app.world().resource::<KillScentGrid>().0, vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn kill_scent_draws_predators_and_repels_organisms() {
+        let mut config = default_config();
+        let settings = &mut config.predator.kill_scent;
+        assert_eq!(settings.predator_bias(1.0), 0.0);
+        assert_eq!(settings.organism_bias(1.0), 0.0);
+        settings.enabled = true;
+        settings.predator_attraction = 100.0;
+        settings.organism_avoidance = 100.0;
+        assert!(settings.predator_bias(1.0) < 0.0);
+        assert!(settings.organism_bias(1.0) > 0.0);
+
+        // where an organism and a predator in the middle of a 3x3 grassland step
+        let step = |scent: KillScentGrid| {
+            let mut app = App::new();
+            app.insert_resource(grassland_world(3, 3))
+                .insert_resource(FoodGrid::new(3, vec![0.0; 9]))
+                .insert_resource(SpatialIndex::new(3, 3))
+                .insert_resource(PredatorSpatialIndex::new(3, 3))
+                .insert_resource(scent)
+                .init_resource::<MovementTrace>()
+                .insert_resource(Generation(0))
+                .insert_resource(RngStreams::new(0))
+                .insert_resource(config.clone())
+                .init_resource::<TerritoryMap>()
+                .add_systems(Update, (organism_movement, predator_movement));
+            let mut organism = founder_organism(&config, [1.0; 4]);
+            organism.speed = 1.0;
+            organism.exploration = 0.0;
+            let organism = app
+                .world_mut()
+                .spawn((
+                    organism,
+                    Position { x: 1, y: 1 },
+                    EntityRng(SmallRng::seed_from_u64(2)),
+                ))
+                .id();
+            let mut predator = founder_predator(&config);
+            predator.speed = 1.0;
+            let predator = app
+                .world_mut()
+                .spawn((
+                    predator,
+                    Position { x: 1, y: 1 },
+                    EntityRng(SmallRng::seed_from_u64(2)),
+                ))
+                .id();
+            app.update();
+            let at = |entity| {
+                let position = app.world().get::<Position>(entity).unwrap();
+                (position.x, position.y)
+            };
+            (at(organism), at(predator))
+        };
+
+        let mut right = KillScentGrid::new(9);
+        right.0[5] = 1.0;
+        assert_eq!(step(right).1, (2, 1));
+        for (clean, tile) in [(3, (0, 1)), (8, (2, 2))] {
+            let mut scent = KillScentGrid(vec![1.0; 9]);
+            scent.0[clean] = 0.0;
+            assert_eq!(step(scent).0, tile);
+        }
+    }
+
+    #[test]
+    fn organisms_eat_carrion_once_the_plant_food_is_gone() {
+        let config = default_config();
+        let mut app = App::new();
+        app.insert_resource(grassland_world(1, 1))
+            .insert_resource(FoodGrid::new(1, vec![0.05]))
+            .insert_resource(CarrionGrid(vec![1.0]))
+            .insert_resource(ActiveTiles::all(1))
+            .insert_resource(SpatialIndex::new(1, 1))
+            .insert_resource(EnergyIntake::default())
+            .add_systems(Update, consume_food);
+        let organism = app
+            .world_mut()
+            .spawn((founder_organism(&config, [1.0; 4]), Position { x: 0, y: 0 }))
+            .id();
+        app.world_mut()
+            .resource_mut::<SpatialIndex>()
+            .insert(0, 0, organism);
+        let energy = config.organism.initial_organism_energy;
+        app.insert_resource(config);
+
+        app.update();
+        // a default organism needs 0.2 food per tick: the plant food, then carrion
+        let gained = app.world().get::<Organism>(organism).unwrap().energy - energy;
+        assert!((gained - 0.4).abs() < 1e-5);
+        assert_eq!(app.world().resource::<FoodGrid>().get(0), 0.0);
+        assert!((app.world().resource::<CarrionGrid>().0[0] - 0.85).abs() < 1e-5);
+    }
+
+    #[test]
+    fn food_conversion_applies_biome_nutrition() {
+        // a size 1, speed 1 organism eats 0.2 food per tick
+        let mut config = default_config();
+        config.organism.food_energy_conversion = 3.0;
+
+        let (gained, intake) = energy_gained_from_food(Biome::Forest, config.clone());
+        assert!((gained - 0.6).abs() < 1e-5);
+        assert!((intake.gained - 0.6).abs() < 1e-5);
+        assert!(intake.digestion_loss.abs() < 1e-5);
+
+        config.world.desert.food_nutrition = 0.25;
+        let (gained, intake) = energy_gained_from_food(Biome::Desert, config);
+        assert!((gained - 0.15).abs() < 1e-5);
+        assert!((intake.gained - 0.15).abs() < 1e-5);
+        assert!((intake.digestion_loss - 0.45).abs() < 1e-5);
+    }
+
+    #[test]
+    fn hunted_prey_neither_eats_nor_reproduces_in_the_same_tick() {
+        let mut config = default_config();
+        config.world.max_total_entities = 100;
+
+        let mut app = App::new();
+        app.add_event::<Killed>()
+            .add_event::<PreyInjured>()
+            .insert_resource(grassland_world(1, 1))
+            .insert_resource(FoodGrid::new(1, vec![10.0]))
+            .insert_resource(CarrionGrid::new(1))
+            .insert_resource(ActiveTiles::all(1))
+            .insert_resource(SpatialIndex::new(1, 1))
+            .insert_resource(DeathTally::default())
+            .insert_resource(EnergyIntake::default())
+            .insert_resource(RngStreams::new(1))
+            .insert_resource(PopulationCount::default())
+            .insert_resource(Generation(0))
+            .insert_resource(NextId::default())
+            .insert_resource(ClampTally::default())
+            .init_resource::<TerritoryMap>()
+            // no sync points, so the victim is still around for the later systems
+            .add_systems(
+                Update,
+                (hunting, consume_food, reproduction).chain_ignore_deferred(),
+            );
+
+        let mut predator = founder_predator(&config);
+        predator.satiation_threshold = f32::MAX;
+        app.world_mut().spawn((
+            predator,
+            Position { x: 0, y: 0 },
+            EntityRng(SmallRng::seed_from_u64(1)),
+        ));
+        let mut prey = founder_organism(&config, [1.0; 4]);
+        prey.energy = 1_000.0;
+        prey.reproduction_cooldown = 0.0;
+        let prey = app
+            .world_mut()
+            .spawn((prey, Position { x: 0, y: 0 }, Identity::founder(0, 0)))
+            .id();
+        app.world_mut()
+            .resource_mut::<SpatialIndex>()
+            .insert(0, 0, prey);
+        app.insert_resource(config);
+
+        app.update();
+
+        assert!(app.world().get_entity(prey).is_err());
+        assert_eq!(app.world().resource::<FoodGrid>().get(0), 10.0);
+        let mut organisms = app.world_mut().query::<&Organism>();
+        assert_eq!(organisms.iter(app.world()).count(), 0);
+    }
+
+    #[test]
+    fn culling_leaves_exactly_the_cap_alive() {
+        let mut config = default_config();
+        config.world.max_total_entities = 5;
+
+        let mut app = App::new();
+        app.insert_resource(RngStreams::new(1))
+            .insert_resource(Generation(0))
+            .insert_resource(DeathTally::default())
+            // counted before this tick's births, which the cull has to see past
+            .insert_resource(PopulationCount::default())
+            .add_systems(Update, kill_over_limit_organisms);
+        for x in 0..10 {
+            let organism = founder_organism(&config, [1.0; 4]);
+            app.world_mut().spawn((organism, Position { x, y: 0 }));
+        }
+        for x in 0..3 {
+            let predator = founder_predator(&config);
+            app.world_mut().spawn((predator, Position { x, y: 0 }));
+        }
+        app.insert_resource(config);
+
+        app.update();
+        let ecs = app.world_mut();
+        let organisms = ecs.query::<&Organism>().iter(ecs).count();
+        let predators = ecs.query::<&Predator>().iter(ecs).count();
+        assert_eq!(organisms + predators, 5);
+        assert_eq!(ecs.resource::<DeathTally>().causes[&DeathCause::Culled], 8);
+
+        // at the cap nothing more is culled
+        app.update();
+        let ecs = app.world_mut();
+        assert_eq!(ecs.query::<&Organism>().iter(ecs).count(), organisms);
+    }
+
+    /// Reproduction thresholds of every organism after `ticks` of reproduction that
+    /// starts from parents at both relative bounds, with a high mutability.
+    fn thresholds_after_reproduction(relative: bool, ticks: usize) -> Vec<f32> {
+        let mut config = default_config();
+        config.world.max_total_entities = 1_000;
+        config.organism.organism_mutability = 0.5;
+        config.organism.relative_reproduction_threshold = relative;
+
+        let mut app = App::new();
+        app.insert_resource(grassland_world(4, 4))
+            .insert_resource(RngStreams::new(9))
+            .insert_resource(PopulationCount::default())
+            .insert_resource(Generation(0))
+            .insert_resource(NextId::default())
+            .insert_resource(ClampTally::default())
+            .insert_resource(SpatialIndex::new(4, 4))
+            .add_systems(Update, reproduction);
+
+        let (low, high) = RELATIVE_THRESHOLD_BOUNDS;
+        for (id, fraction) in [low, high].into_iter().enumerate() {
+            let mut parent = founder_organism(&config, [1.0; 4]);
+            parent.energy = 1.0e6;
+            parent.reproduction_cooldown = 0.0;
+            parent.reproduction_threshold = fraction * config.organism.max_organism_energy;
+            app.world_mut().spawn((
+                parent,
+                Position { x: 1, y: 1 },
+                Identity::founder(id as u64, 0),
+            ));
+        }
+        app.insert_resource(config);
+
+        for _ in 0..ticks {
+            app.update();
+        }
+        let mut organisms = app.world_mut().query::<&Organism>();
+        organisms
+            .iter(app.world())
+            .map(|organism| organism.reproduction_threshold)
+            .collect()
+    }
+
+    #[test]
+    fn relative_thresholds_stay_within_bounds_of_max_energy() {
+        let max = default_config().organism.max_organism_energy;
+        let (low, high) = RELATIVE_THRESHOLD_BOUNDS;
+        let (low, high) = (low * max - 1e-3, high * max + 1e-3);
+
+        let absolute = thresholds_after_reproduction(false, 8);
+        assert!(absolute.iter().any(|&threshold| threshold < low));
+        assert!(absolute.iter().any(|&threshold| threshold > high));
+
+        let relative = thresholds_after_reproduction(true, 8);
+        assert_eq!(relative.len(), absolute.len());
+        assert!(relative
+            .iter()
+            .all(|threshold| (low..=high).contains(threshold)));
+    }
+
+    #[test]
+    fn founders_convert_absolute_thresholds_to_relative_ones() {
+        let mut config = default_config();
+        config.organism.relative_reproduction_threshold = true;
+        config.organism.max_organism_energy = 200.0;
+        config.organism.initial_organism_reproduction_threshold = 500.0;
+        config.predator.initial_predator_reproduction_threshold = 1.0;
+
+        let organism = founder_organism(&config, [1.0; 4]);
+        assert!((organism.reproduction_threshold - 190.0).abs() < 1e-3);
+        let predator = founder_predator(&config);
+        let expected = 0.05 * config.predator.max_predator_energy;
+        assert!((predator.reproduction_threshold - expected).abs() < 1e-3);
+
+        config.organism.initial_organism_reproduction_threshold = 100.0;
+        let organism = founder_organism(&config, [1.0; 4]);
+        assert!((organism.reproduction_threshold - 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn morph_switches_at_the_configured_rate_to_any_other_morph() {
+        let mut rng = SmallRng::seed_from_u64(17);
+        let births = 100_000;
+        let mut counts = [0usize; 4];
+        for _ in 0..births {
+            counts[inherit_morph(2, 4, 0.05, &mut rng) as usize] += 1;
+        }
+
+        let switched = births - counts[2];
+        let rate = switched as f32 / births as f32;
+        assert!((rate - 0.05).abs() < 0.005, "{}", rate);
+        for morph in [0, 1, 3] {
+            let share = counts[morph] as f32 / switched as f32;
+            assert!((share - 1.0 / 3.0).abs() < 0.05, "{} {}", morph, share);
+        }
+
+        assert!((0..1000).all(|_| inherit_morph(1, 4, 0.0, &mut rng) == 1));
+        assert!((0..1000).all(|_| inherit_morph(0, 1, 1.0, &mut rng) == 0));
+    }
+
+    /// Predators after a well-fed predator that killed in `kills` tries to reproduce
+    /// in generation 30, needing one kill in the last 20 generations, and how many
+    /// births the rule blocked.
+    fn predators_after_kills_at(kills: &[usize]) -> (usize, usize) {
+        let mut config = default_config();
+        config.world.max_total_entities = 1_000;
+        config.predator.min_kills_for_reproduction = 1;
+        config.predator.kill_memory_ticks = 20;
+
+        let mut app = App::new();
+        app.insert_resource(grassland_world(4, 4))
+            .insert_resource(RngStreams::new(5))
+            .insert_resource(PopulationCount::default())
+            .insert_resource(Generation(30))
+            .insert_resource(NextId::default())
+            .insert_resource(ClampTally::default())
+            .insert_resource(PredatorSpatialIndex(SpatialIndex::new(4, 4)))
+            .insert_resource(config.clone())
+            .add_systems(Update, predator_reproduction);
+        let mut predator = founder_predator(&config);
+        predator.energy = 1_000.0;
+        predator.reproduction_threshold = 100.0;
+        predator.reproduction_cooldown = 0.0;
+        for &kill in kills {
+            predator.recent_kills.record(kill);
+        }
+        app.world_mut()
+            .spawn((predator, Position { x: 1, y: 1 }, Identity::founder(0, 0)));
+        app.update();
+
+        let ecs = app.world_mut();
+        let predators = ecs.query::<&Predator>().iter(ecs).count();
+        (
+            predators,
+            ecs.resource::<ClampTally>().predator_births_blocked,
+        )
+    }
+
+    #[test]
+    fn predators_need_a_recent_kill_to_reproduce() {
+        // energy alone would do, but the only kills are 25 and 30 generations old
+        assert_eq!(predators_after_kills_at(&[0, 5]), (1, 1));
+        assert_eq!(predators_after_kills_at(&[]), (1, 1));
+        assert_eq!(predators_after_kills_at(&[5, 25]), (2, 0));
+
+        // the memory keeps the latest kills, so old ones fall out of it
+        let mut memory = KillMemory::default();
+        for generation in 0..KILL_MEMORY + 2 {
+            memory.record(generation);
+        }
+        assert_eq!(memory.within(KILL_MEMORY + 1, 100), KILL_MEMORY);
+        assert_eq!(memory.within(KILL_MEMORY + 1, 2), 2);
+    }
+
+    #[test]
+    fn zero_mutability_breeds_true_without_drawing() {
+        let mut config = default_config();
+        config.world.max_total_entities = 1_000;
+        config.organism.organism_mutability = 0.0;
+        config.predator.predator_mutability = 0.0;
+
+        let mut app = App::new();
+        app.insert_resource(grassland_world(4, 4))
+            .insert_resource(RngStreams::new(5))
+            .insert_resource(PopulationCount::default())
+            .insert_resource(Generation(0))
+            .insert_resource(NextId::default())
+            .insert_resource(ClampTally::default())
+            .insert_resource(SpatialIndex::new(4, 4))
+            .insert_resource(PredatorSpatialIndex(SpatialIndex::new(4, 4)))
+            .insert_resource(config.clone())
+            .add_systems(Update, (reproduction, predator_reproduction).chain());
+        let mut organism = founder_organism(&config, [1.0; 4]);
+        organism.energy = 100.0;
+        organism.reproduction_threshold = 50.0;
+        organism.reproduction_cooldown = 0.0;
+        let mut predator = founder_predator(&config);
+        predator.energy = 1_000.0;
+        predator.reproduction_threshold = 100.0;
+        predator.reproduction_cooldown = 0.0;
+        let sizes = (organism.size, predator.size);
+        app.world_mut()
+            .spawn((organism, Position { x: 1, y: 1 }, Identity::founder(0, 0)));
+        app.world_mut()
+            .spawn((predator, Position { x: 2, y: 2 }, Identity::founder(1, 0)));
+        app.update();
+
+        let ecs = app.world_mut();
+        let organisms: Vec<_> = ecs.query::<&Organism>().iter(ecs).map(|o| o.size).collect();
+        assert_eq!(organisms, [sizes.0; 2]);
+        let predators: Vec<_> = ecs.query::<&Predator>().iter(ecs).map(|p| p.size).collect();
+        assert_eq!(predators, [sizes.1; 2]);
+    }
+
+    /// Energy of a parent with `investment` and of its one offspring after the parent,
+    /// starting with 100 energy, reproduces once.
+    fn energy_after_reproducing(investment: f32, evolve: bool) -> (f32, f32) {
+        let mut config = default_config();
+        config.world.max_total_entities = 1_000;
+        config.organism.evolve_parental_investment = evolve;
+
+        let mut app = App::new();
+        app.insert_resource(grassland_world(4, 4))
+            .insert_resource(RngStreams::new(5))
+            .insert_resource(PopulationCount::default())
+            .insert_resource(Generation(0))
+            .insert_resource(NextId::default())
+            .insert_resource(ClampTally::default())
+            .insert_resource(SpatialIndex::new(4, 4))
+            .add_systems(Update, reproduction);
+        let mut parent = founder_organism(&config, [1.0; 4]);
+        parent.energy = 100.0;
+        parent.reproduction_cooldown = 0.0;
+        parent.reproduction_threshold = 50.0;
+        parent.parental_investment = investment;
+        app.world_mut()
+            .spawn((parent, Position { x: 1, y: 1 }, Identity::founder(0, 0)));
+        app.insert_resource(config);
+        app.update();
+
+        let mut organisms = app.world_mut().query::<(&Organism, &Identity)>();
+        let energies: Vec<_> = organisms
+            .iter(app.world())
+            .map(|(organism, identity)| (identity.parent.is_some(), organism.energy))
+            .collect();
+        let energy_of = |is_child| {
+            energies
+                .iter()
+                .find(|(child, _)| *child == is_child)
+                .unwrap()
+                .1
+        };
+        (energy_of(false), energy_of(true))
+    }
+
+    #[test]
+    fn parental_investment_splits_energy_at_both_extremes() {
+        let (low, high) = PARENTAL_INVESTMENT_BOUNDS;
+
+        let (parent, child) = energy_after_reproducing(low, true);
+        assert!((child - 10.0).abs() < 1e-4, "{}", child);
+        assert!((parent - 90.0).abs() < 1e-4, "{}", parent);
+
+        let (parent, child) = energy_after_reproducing(high, true);
+        assert!((child - 90.0).abs() < 1e-4, "{}", child);
+        assert!((parent - 10.0).abs() < 1e-4, "{}", parent);
+
+        // without evolution every parent hands over `initial_parental_investment`
+        let (parent, child) = energy_after_reproducing(high, false);
+        assert_eq!((parent, child), (50.0, 50.0));
+    }
+
+    #[test]
+    fn evolved_parental_investment_stays_within_bounds() {
+        let (low, high) = PARENTAL_INVESTMENT_BOUNDS;
+        let mut config = default_config();
+        config.world.max_total_entities = 10_000;
+        config.organism.organism_mutability = 0.5;
+        config.organism.evolve_parental_investment = true;
+
+        let mut app = App::new();
+        app.insert_resource(grassland_world(4, 4))
+            .insert_resource(RngStreams::new(3))
+            .insert_resource(PopulationCount::default())
+            .insert_resource(Generation(0))
+            .insert_resource(NextId::default())
+            .insert_resource(ClampTally::default())
+            .insert_resource(SpatialIndex::new(4, 4))
+            .add_systems(Update, reproduction);
+        for (id, investment) in [low, high].into_iter().enumerate() {
+            let mut parent = founder_organism(&config, [1.0; 4]);
+            parent.energy = 1.0e9;
+            parent.reproduction_cooldown = 0.0;
+            parent.parental_investment = investment;
+            app.world_mut().spawn((
+                parent,
+                Position { x: 1, y: 1 },
+                Identity::founder(id as u64, 0),
+            ));
+        }
+        app.insert_resource(config);
+        for _ in 0..6 {
+            app.update();
+        }
+
+        let mut organisms = app.world_mut().query::<&Organism>();
+        let investments: Vec<_> = organisms
+            .iter(app.world())
+            .map(|organism| organism.parental_investment)
+            .collect();
+        assert!(investments.len() > 2);
+        assert!(investments
+            .iter()
+            .all(|investment| (low..=high).contains(investment)));
+        assert!(investments
+            .iter()
+            .any(|&investment| investment != low && investment != high));
+    }
+
+    #[test]
+    fn clamped_traits_are_counted_once_per_newborn() {
+        let mut config = default_config();
+        config.world.max_total_entities = 1_000;
+        config.organism.organism_mutability = 0.9;
+
+        let mut app = App::new();
+        app.insert_resource(grassland_world(4, 4))
+            .insert_resource(RngStreams::new(4))
+            .insert_resource(PopulationCount::default())
+            .insert_resource(Generation(0))
+            .insert_resource(NextId::default())
+            .insert_resource(ClampTally::default())
+            .insert_resource(SpatialIndex::new(4, 4))
+            .add_systems(Update, reproduction);
+        for id in 0..100 {
+            // parents at the bounds, so about half of the mutations overshoot them
+            let mut parent = founder_organism(&config, [1.0; 4]);
+            parent.energy = 1.0e6;
+            parent.reproduction_cooldown = 0.0;
+            parent.size = 0.1;
+            parent.boldness = 1.0;
+            app.world_mut()
+                .spawn((parent, Position { x: 1, y: 1 }, Identity::founder(id, 0)));
+        }
+        app.insert_resource(config);
+        app.update();
+
+        let clamps = app.world().resource::<ClampTally>();
+        assert_eq!(clamps.births(EntityKind::Organism), 100);
+        let hits = clamps.hits();
+        let (size_low, size_high) = hits["organism.size"];
+        assert!((25..=75).contains(&size_low), "{}", size_low);
+        assert_eq!(size_high, 0);
+        let (boldness_low, boldness_high) = hits["organism.boldness"];
+        assert_eq!(boldness_low, 0);
+        assert!((25..=75).contains(&boldness_high), "{}", boldness_high);
+        assert!(!hits.contains_key("organism.exploration"));
+
+        let excessive = clamps.excessive(0.2);
+        let flagged: Vec<_> = excessive
+            .iter()
+            .map(|(key, bound, ..)| (key.as_str(), *bound))
+            .collect();
+        assert_eq!(
+            flagged,
+            [
+                ("organism.boldness", Bound::High),
+                ("organism.size", Bound::Low)
+            ]
+        );
+        assert!(clamps.excessive(0.9).is_empty());
+    }
+
+    /// Morphs of the offspring of a morph 3 parent after one round of reproduction.
+    fn offspring_morphs(probability: f32) -> Vec<u8> {
+        let mut config = default_config();
+        config.world.max_total_entities = 1_000;
+        config.organism.morph_mutation_probability = probability;
+
+        let mut app = App::new();
+        app.insert_resource(grassland_world(4, 4))
+            .insert_resource(RngStreams::new(2))
+            .insert_resource(PopulationCount::default())
+            .insert_resource(Generation(0))
+            .insert_resource(NextId::default())
+            .insert_resource(ClampTally::default())
+            .insert_resource(SpatialIndex::new(4, 4))
+            .add_systems(Update, reproduction);
+        for id in 0..20 {
+            let mut parent = founder_organism(&config, [1.0; 4]);
+            parent.energy = 1.0e6;
+            parent.reproduction_cooldown = 0.0;
+            parent.morph = 3;
+            app.world_mut()
+                .spawn((parent, Position { x: 1, y: 1 }, Identity::founder(id, 0)));
+        }
+        app.insert_resource(config);
+        app.update();
+
+        let mut offspring = app.world_mut().query::<(&Organism, &Identity)>();
+        offspring
+            .iter(app.world())
+            .filter(|(_, identity)| identity.parent.is_some())
+            .map(|(organism, _)| organism.morph)
+            .collect()
+    }
+
+    #[test]
+    fn offspring_inherit_the_parent_morph() {
+        let kept = offspring_morphs(0.0);
+        assert_eq!(kept.len(), 20);
+        assert!(kept.iter().all(|&morph| morph == 3));
+
+        let switched = offspring_morphs(1.0);
+        assert_eq!(switched.len(), 20);
+        assert!(switched.iter().all(|&morph| morph < 3));
+    }
+
+    #[test]
+    fn morph_fitness_only_applies_when_configured() {
+        let mut config = default_config();
+        let mut app = App::new();
+        app.add_systems(Update, morph_selection);
+        let organisms: Vec<_> = (0..3)
+            .map(|morph| {
+                let mut organism = founder_organism(&config, [1.0; 4]);
+                organism.morph = morph;
+                app.world_mut().spawn(organism).id()
+            })
+            .collect();
+        let energy = config.organism.initial_organism_energy;
+
+        app.insert_resource(config.clone());
+        app.update();
+        for &entity in &organisms {
+            assert_eq!(app.world().get::<Organism>(entity).unwrap().energy, energy);
+        }
+
+        config.organism.morph_fitness = vec![0.0, 1.5];
+        app.insert_resource(config);
+        app.update();
+        let energies: Vec<_> = organisms
+            .iter()
+            .map(|&entity| app.world().get::<Organism>(entity).unwrap().energy)
+            .collect();
+        assert_eq!(energies, [energy, energy + 1.5, energy]);
+    }
+
+    /// Energies of two organisms sharing a tile after one tick of kin sharing, with
+    /// the energy the tick recorded as shared.
+    fn energies_after_sharing(
+        identities: [Identity; 2],
+        energies: [f32; 2],
+        enabled: bool,
+    ) -> ([f32; 2], f32) {
+        let mut config = default_config();
+        config.organism.kin_sharing = KinSharingConfig {
+            enabled,
+            rate: 2.0,
+            kin_generations: 1,
+            initial_altruism: 0.5,
+        };
+        let mut app = App::new();
+        app.insert_resource(SpatialIndex::new(1, 1))
+            .insert_resource(EnergyIntake::default())
+            .add_systems(Update, share_energy);
+        let entities = [0, 1].map(|k| {
+            let organism = Organism {
+                energy: energies[k],
+                reproduction_threshold: 100.0,
+                ..founder_organism(&config, [1.0; 4])
+            };
+            let entity = app
+                .world_mut()
+                .spawn((organism, Position { x: 0, y: 0 }, identities[k]))
+                .id();
+            app.world_mut()
+                .resource_mut::<SpatialIndex>()
+                .insert(0, 0, entity);
+            entity
+        });
+        app.insert_resource(config);
+
+        app.update();
+        let energy = |entity| app.world().get::<Organism>(entity).unwrap().energy;
+        (
+            entities.map(energy),
+            app.world().resource::<EnergyIntake>().shared,
+        )
+    }
+
+    #[test]
+    fn parents_feed_their_poorer_children() {
+        let parent = Identity::founder(1, 0);
+        let child = parent.child(2, 10);
+
+        // an altruism of 0.5 at a rate of 2 hands over 1 energy a tick
+        let (energies, shared) = energies_after_sharing([parent, child], [50.0, 10.0], true);
+        assert_eq!(energies, [49.0, 11.0]);
+        assert_eq!(shared, 1.0);
+        assert_eq!(energies.iter().sum::<f32>(), 60.0);
+
+        // the richer child gives back, but never more than evens them out
+        let (energies, _) = energies_after_sharing([parent, child], [10.0, 10.5], true);
+        assert_eq!(energies, [10.25, 10.25]);
+
+        let (energies, shared) = energies_after_sharing([parent, child], [50.0, 10.0], false);
+        assert_eq!(energies, [50.0, 10.0]);
+        assert_eq!(shared, 0.0);
+    }
+
+    #[test]
+    fn strangers_and_reproducing_kin_get_nothing() {
+        let stranger = Identity::founder(1, 0);
+        let other = Identity::founder(2, 0).child(3, 10);
+        let (energies, shared) = energies_after_sharing([stranger, other], [50.0, 10.0], true);
+        assert_eq!(energies, [50.0, 10.0]);
+        assert_eq!(shared, 0.0);
+
+        // same lineage, but born further apart than kin_generations
+        let founder = Identity::founder(1, 0);
+        let distant = founder.child(2, 1).child(3, 5);
+        assert!(!founder.is_kin(&distant, 1));
+        let siblings = [founder.child(4, 6), founder.child(5, 9)];
+        assert!(siblings[0].is_kin(&siblings[1], 1));
+
+        let parent = Identity::founder(1, 0);
+        let (energies, _) =
+            energies_after_sharing([parent, parent.child(2, 3)], [500.0, 150.0], true);
+        assert_eq!(energies, [500.0, 150.0]);
+    }
+
+    fn predator_energy_after_one_step(biome: Biome, config: &Config) -> f32 {
+        let mut world = grassland_world(3, 3);
+        for tile in world.grid.iter_mut() {
+            tile.biome = biome;
+        }
+
+        let mut app = App::new();
+        app.insert_resource(world)
+            .insert_resource(config.clone())
+            .insert_resource(SpatialIndex::new(3, 3))
+            .insert_resource(KillScentGrid::new(9))
+            .init_resource::<MovementTrace>()
+            .insert_resource(Generation(0))
+            .insert_resource(RngStreams::new(0))
+            .init_resource::<TerritoryMap>()
+            .add_systems(Update, predator_movement);
+        let predator = app
+            .world_mut()
+            .spawn((
+                Predator {
+                    energy: 10.0,
+                    speed: 1.0,
+                    size: 1.0,
+                    reproduction_threshold: 100.0,
+                    hunting_efficiency: 1.0,
+                    satiation_threshold: 100.0,
+                    reproduction_cooldown: 0.0,
+                    preferred_temperature: 20.0,
+                    hunt_cooldown_ticks: 0.0,
+                    hunt_cooldown_remaining: 0,
+                    patience: 0.0,
+                    stance: Stance::Roaming,
+                    heading: 0.0,
+                    aggression: 0.0,
+                    breeding_flexibility: 0.0,
+                    recent_kills: KillMemory::default(),
+                },
+                Position { x: 1, y: 1 },
+                EntityRng(SmallRng::seed_from_u64(1)),
+            ))
+            .id();
+
+        app.update();
+        app.world().get::<Predator>(predator).unwrap().energy
+    }
+
+    #[test]
+    fn predator_decay_depends_on_biome() {
+        let mut config = default_config();
+        config.predator.predator_energy_decay_rate = 0.5;
+        config.world.desert.predator_decay_factor = 2.0;
+        config.world.forest.predator_decay_factor = 1.0;
+
+        let desert_loss = 10.0 - predator_energy_after_one_step(Biome::Desert, &config);
+        let forest_loss = 10.0 - predator_energy_after_one_step(Biome::Forest, &config);
+        assert!((forest_loss - 0.5).abs() < 1e-5);
+        assert!((desert_loss - 1.0).abs() < 1e-5);
+    }
+
+    /// Prey that ten predators come across in 300 ticks of wandering a 60x60
+    /// grassland where they see one tile around them. Prey that is seen is taken
+    /// and another appears at random, so the density stays the same.
+    fn prey_encounters(wander: bool, seed: u64) -> usize {
+        const SIZE: usize = 60;
+        let mut config = default_config();
+        config.predator.predator_seek_radius = 1;
+        config.predator.wander.enabled = wander;
+        let mut app = App::new();
+        app.insert_resource(grassland_world(SIZE, SIZE))
+            .insert_resource(config)
+            .insert_resource(SpatialIndex::new(SIZE, SIZE))
+            .insert_resource(KillScentGrid::new(SIZE * SIZE))
+            .init_resource::<MovementTrace>()
+            .insert_resource(Generation(0))
+            .insert_resource(RngStreams::new(0))
+            .init_resource::<TerritoryMap>()
+            .add_systems(Update, predator_movement);
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let prey = app.world_mut().spawn_empty().id();
+        for i in 0..10 {
+            app.world_mut().spawn((
+                Predator {
+                    energy: 1e6,
+                    satiation_threshold: 1e7,
+                    heading: rng.gen_range(0.0..TAU),
+                    ..founder_predator(&default_config())
+                },
+                Position {
+                    x: rng.gen_range(0..SIZE),
+                    y: rng.gen_range(0..SIZE),
+                },
+                EntityRng(SmallRng::seed_from_u64(seed * 100 + i)),
+            ));
+        }
+        for _ in 0..30 {
+            let (x, y) = (rng.gen_range(0..SIZE), rng.gen_range(0..SIZE));
+            app.world_mut()
+                .resource_mut::<SpatialIndex>()
+                .insert(x, y, prey);
+        }
+
+        let mut encounters = 0;
+        for _ in 0..300 {
+            app.update();
+            let ecs = app.world_mut();
+            let seen: Vec<_> = ecs
+                .query::<&PredatorSight>()
+                .iter(ecs)
+                .filter_map(|sight| sight.target)
+                .collect();
+            let mut index = ecs.resource_mut::<SpatialIndex>();
+            for (x, y) in seen {
+                let cell = &mut index.cells[y * SIZE + x];
+                if cell.pop().is_some() {
+                    encounters += 1;
+                    let (x, y) = (rng.gen_range(0..SIZE), rng.gen_range(0..SIZE));
+                    index.insert(x, y, prey);
+                }
+            }
+        }
+        encounters
+    }
+
+    #[test]
+    fn wandering_on_a_heading_meets_more_prey() {
+        let (mut jittering, mut wandering) = (0, 0);
+        for seed in 1..=5 {
+            jittering += prey_encounters(false, seed);
+            wandering += prey_encounters(true, seed);
+        }
+        assert!(
+            wandering as f32 > 1.5 * jittering as f32,
+            "{} encounters on a heading, {} jittering",
+            wandering,
+            jittering
+        );
+    }
+
+    #[test]
+    fn headings_steer_around_water_and_the_edge() {
+        assert_eq!(heading_step(0.0), (1, 0));
+        assert_eq!(heading_step(FRAC_PI_4), (1, 1));
+        assert_eq!(heading_step(PI), (-1, 0));
+        assert_eq!(heading_step(TAU - 0.1), (1, 0));
+
+        // water straight ahead turns a predator that never turns by chance aside
+        let settings = WanderConfig {
+            enabled: true,
+            turn_noise: 0.0,
+            turn_chance: 0.0,
+            cost_repulsion: 0.02,
+        };
+        let mut costs = [5.0; DIRECTIONS.len()];
+        let east = DIRECTIONS.iter().position(|&d| d == (1, 0)).unwrap();
+        costs[east] = 100.0;
+        let mut rng = SmallRng::seed_from_u64(1);
+        let heading = wander_heading(0.1, &costs, &settings, &mut rng);
+        assert_ne!(heading_step(heading), (1, 0));
+        assert_eq!(wander_heading(0.1, &[5.0; 8], &settings, &mut rng), 0.1);
+
+        // at the edge it turns around rather than stand still
+        let mut config = default_config();
+        config.predator.wander = settings;
+        config.predator.predator_seek_radius = 0;
+        let mut app = App::new();
+        app.insert_resource(grassland_world(5, 5))
+            .insert_resource(config.clone())
+            .insert_resource(SpatialIndex::new(5, 5))
+            .insert_resource(KillScentGrid::new(25))
+            .init_resource::<MovementTrace>()
+            .insert_resource(Generation(0))
+            .insert_resource(RngStreams::new(0))
+            .init_resource::<TerritoryMap>()
+            .add_systems(Update, predator_movement);
+        let predator = app
+            .world_mut()
+            .spawn((
+                founder_predator(&config),
+                Position { x: 4, y: 2 },
+                EntityRng(SmallRng::seed_from_u64(1)),
+            ))
+            .id();
+        app.update();
+        let position = *app.world().get::<Position>(predator).unwrap();
+        assert_eq!((position.x, position.y), (3, 2));
+        let heading = app.world().get::<Predator>(predator).unwrap().heading;
+        assert!((heading - PI).abs() < 1e-5, "{}", heading);
+    }
+
+    #[test]
+    fn predator_sight_records_the_closest_prey_in_its_square() {
+        let mut config = default_config();
+        config.predator.predator_seek_radius = 2;
+        let mut app = App::new();
+        app.insert_resource(grassland_world(9, 9))
+            .insert_resource(config)
+            .insert_resource(SpatialIndex::new(9, 9))
+            .insert_resource(KillScentGrid::new(81))
+            .init_resource::<MovementTrace>()
+            .insert_resource(Generation(0))
+            .insert_resource(RngStreams::new(0))
+            .init_resource::<TerritoryMap>()
+            .add_systems(Update, predator_movement);
+        let predator = app
+            .world_mut()
+            .spawn((
+                Predator {
+                    energy: 10.0,
+                    speed: 1.0,
+                    size: 1.0,
+                    reproduction_threshold: 100.0,
+                    hunting_efficiency: 1.0,
+                    satiation_threshold: 50.0,
+                    reproduction_cooldown: 0.0,
+                    preferred_temperature: 20.0,
+                    hunt_cooldown_ticks: 0.0,
+                    hunt_cooldown_remaining: 0,
+                    patience: 0.0,
+                    stance: Stance::Roaming,
+                    heading: 0.0,
+                    aggression: 0.0,
+                    breeding_flexibility: 0.0,
+                    recent_kills: KillMemory::default(),
+                },
+                Position { x: 4, y: 4 },
+                EntityRng(SmallRng::seed_from_u64(1)),
+            ))
+            .id();
+        let prey = app.world_mut().spawn_empty().id();
+        let sight = |app: &App| *app.world().get::<PredatorSight>(predator).unwrap();
+
+        // a diagonal three tiles away lies outside the square
+        app.world_mut()
+            .resource_mut::<SpatialIndex>()
+            .insert(7, 7, prey);
+        app.update();
+        assert_eq!(sight(&app).radius, 2);
+        assert_eq!(sight(&app).target, None);
+        assert_eq!(sight(&app).state, HuntState::Searching);
+
+        // a corner of the square is still in it
+        let mut position = app.world_mut().get_mut::<Position>(predator).unwrap();
+        *position = Position { x: 4, y: 4 };
+        let mut index = app.world_mut().resource_mut::<SpatialIndex>();
+        index.clear();
+        index.insert(6, 2, prey);
+        app.update();
+        assert_eq!(sight(&app).target, Some((6, 2)));
+        assert_eq!(sight(&app).state, HuntState::Pursuing);
+
+        app.world_mut()
+            .get_mut::<Predator>(predator)
+            .unwrap()
+            .energy = 60.0;
+        app.update();
+        assert_eq!(sight(&app).target, Some((6, 2)));
+        assert_eq!(sight(&app).state, HuntState::Sated);
+    }
+
+    fn predator_sees_prey_three_tiles_away(biome: Biome, config: &Config) -> bool {
+        let mut world = grassland_world(9, 9);
+        for tile in world.grid.iter_mut() {
+            tile.biome = biome;
+        }
+
+        let mut app = App::new();
+        app.insert_resource(world)
+            .insert_resource(config.clone())
+            .insert_resource(SpatialIndex::new(9, 9))
+            .insert_resource(KillScentGrid::new(81))
+            .init_resource::<MovementTrace>()
+            .insert_resource(Generation(0))
+            .insert_resource(RngStreams::new(0))
+            .init_resource::<TerritoryMap>()
+            .add_systems(Update, predator_movement);
+        let predator = app
+            .world_mut()
+            .spawn((
+                Predator {
+                    energy: 10.0,
+                    speed: 1.0,
+                    size: 1.0,
+                    reproduction_threshold: 100.0,
+                    hunting_efficiency: 1.0,
+                    satiation_threshold: 50.0,
+                    reproduction_cooldown: 0.0,
+                    preferred_temperature: 20.0,
+                    hunt_cooldown_ticks: 0.0,
+                    hunt_cooldown_remaining: 0,
+                    patience: 0.0,
+                    stance: Stance::Roaming,
+                    heading: 0.0,
+                    aggression: 0.0,
+                    breeding_flexibility: 0.0,
+                    recent_kills: KillMemory::default(),
+                },
+                Position { x: 4, y: 4 },
+                EntityRng(SmallRng::seed_from_u64(1)),
+            ))
+            .id();
+        let prey = app.world_mut().spawn_empty().id();
+        app.world_mut()
+            .resource_mut::<SpatialIndex>()
+            .insert(7, 4, prey);
+
+        app.update();
+        app.world()
+            .get::<PredatorSight>(predator)
+            .unwrap()
+            .target
+            .is_some()
+    }
+
+    #[test]
+    fn predator_vision_depends_on_biome() {
+        let mut config = default_config();
+        config.predator.predator_seek_radius = 3;
+        config.world.forest.predator_vision = 0.6;
+        config.world.grassland.predator_vision = 1.0;
+
+        assert_eq!(sight_radius(3, 1.0, 0.6), 2);
+        assert!(!predator_sees_prey_three_tiles_away(Biome::Forest, &config));
+        assert!(predator_sees_prey_three_tiles_away(
+            Biome::Grassland,
+            &config
+        ));
+    }
+
+    fn ambush_config() -> Config {
+        let mut config = default_config();
+        config.predator.ambush.enabled = true;
+        config.predator.ambush.decay_factor = 0.2;
+        config.predator.predator_energy_decay_rate = 1.0;
+        config
+    }
+
+    /// A predator with `patience` after one move on an empty grassland.
+    fn predator_after_one_quiet_tick(patience: f32) -> Predator {
+        let mut app = App::new();
+        app.insert_resource(grassland_world(5, 5))
+            .insert_resource(ambush_config())
+            .insert_resource(SpatialIndex::new(5, 5))
+            .insert_resource(KillScentGrid::new(25))
+            .init_resource::<MovementTrace>()
+            .insert_resource(Generation(0))
+            .insert_resource(RngStreams::new(0))
+            .init_resource::<TerritoryMap>()
+            .add_systems(Update, predator_movement);
+        let mut predator = founder_predator(&ambush_config());
+        predator.energy = 10.0;
+        predator.speed = 1.0;
+        predator.size = 1.0;
+        predator.patience = patience;
+        let entity = app
+            .world_mut()
+            .spawn((
+                predator,
+                Position { x: 2, y: 2 },
+                EntityRng(SmallRng::seed_from_u64(1)),
+            ))
+            .id();
+
+        app.update();
+        *app.world().get::<Predator>(entity).unwrap()
+    }
+
+    #[test]
+    fn predators_in_ambush_pay_less_upkeep() {
+        let roaming = predator_after_one_quiet_tick(0.0);
+        let waiting = predator_after_one_quiet_tick(1.0);
+
+        assert_eq!(roaming.stance, Stance::Roaming);
+        assert_eq!(waiting.stance, Stance::Ambush);
+        assert!((10.0 - roaming.energy - 1.0).abs() < 1e-5);
+        assert!((10.0 - waiting.energy - 0.2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn ambushers_strike_prey_that_steps_next_to_them() {
+        let mut config = ambush_config();
+        config.predator.ambush.catch_bonus = 1.0;
+        // without the ambush nothing is ever caught here
+        config.world.grassland.predator_catch = 0.0;
+
+        let run = |stance: Stance| {
+            let mut app = App::new();
+            app.insert_resource(grassland_world(3, 3))
+                .insert_resource(config.clone())
+                .insert_resource(SpatialIndex::new(3, 3))
+                .insert_resource(KillScentGrid::new(9))
+                .init_resource::<MovementTrace>()
+                .insert_resource(DeathTally::default())
+                .insert_resource(Generation(0))
+                .insert_resource(RngStreams::new(0))
+                .init_resource::<TerritoryMap>()
+                .add_event::<Killed>()
+                .add_event::<PreyInjured>()
+                .add_systems(Update, (predator_movement, hunting).chain());
+            let mut predator = founder_predator(&config);
+            predator.speed = 1.0;
+            predator.satiation_threshold = f32::MAX;
+            predator.stance = stance;
+            let predator = app
+                .world_mut()
+                .spawn((
+                    predator,
+                    Position { x: 1, y: 1 },
+                    EntityRng(SmallRng::seed_from_u64(1)),
+                ))
+                .id();
+            let prey = app
+                .world_mut()
+                .spawn((founder_organism(&config, [1.0; 4]), Position { x: 2, y: 1 }))
+                .id();
+            app.world_mut()
+                .resource_mut::<SpatialIndex>()
+                .insert(2, 1, prey);
+
+            app.update();
+            let position = app.world().get::<Position>(predator).unwrap();
+            (
+                (position.x, position.y),
+                app.world().get_entity(prey).is_err(),
+            )
+        };
+
+        // the ambusher holds still and catches the prey next to it
+        assert_eq!(run(Stance::Ambush), ((1, 1), true));
+        // a roaming predator steps onto the prey's tile but cannot catch it
+        assert_eq!(run(Stance::Roaming), ((2, 1), false));
+    }
+
+    #[test]
+    fn nearest_free_tile_takes_the_closest_tile_breadth_first() {
+        let world = grassland_world(7, 7);
+        let free_tiles = [(1, 1), (5, 3), (6, 6)];
+        let free = |x, y| free_tiles.contains(&(x, y));
+
+        // (1, 1) and (5, 3) are both two steps away; (1, 1) is reached first
+        assert_eq!(nearest_free_tile(3, 3, 3, &world, free), Some((1, 1)));
+        assert_eq!(nearest_free_tile(3, 3, 3, &world, free), Some((1, 1)));
+        assert_eq!(nearest_free_tile(4, 3, 3, &world, free), Some((5, 3)));
+        assert_eq!(nearest_free_tile(3, 3, 1, &world, free), None);
+        assert_eq!(nearest_free_tile(6, 6, 0, &world, free), Some((6, 6)));
+        // the edges of the world bound the search
+        assert_eq!(nearest_free_tile(0, 0, 1, &world, |_, _| false), None);
+    }
+
+    #[test]
+    fn predator_upkeep_grows_with_thermal_stress() {
+        let mut config = default_config();
+        config.predator.predator_thermoregulation_cost = 0.1;
+        let predator = Predator {
+            energy: 10.0,
+            speed: 1.0,
+            size: 1.0,
+            reproduction_threshold: 100.0,
+            hunting_efficiency: 1.0,
+            satiation_threshold: 100.0,
+            reproduction_cooldown: 0.0,
+            preferred_temperature: 20.0,
+            hunt_cooldown_ticks: 0.0,
+            hunt_cooldown_remaining: 0,
+            patience: 0.0,
+            stance: Stance::Roaming,
+            heading: 0.0,
+            aggression: 0.0,
+            breeding_flexibility: 0.0,
+            recent_kills: KillMemory::default(),
+        };
+        let mut tile = Tile {
+            biome: Biome::Forest,
+            temperature: 20.0,
+            humidity: 0.5,
+            reserve: false,
+        };
+        assert!((predator_upkeep_factor(&tile, &predator, &config) - 1.0).abs() < 1e-6);
+        tile.temperature = 30.0;
+        assert!((predator_upkeep_factor(&tile, &predator, &config) - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn births_and_deaths_are_recorded_for_every_despawn() {
+        let mut app = App::new();
+        app.add_event::<Born>()
+            .add_event::<Died>()
+            .add_observer(record_birth)
+            .add_observer(record_death)
+            .insert_resource(NewbornSurvival::default())
+            .insert_resource(DeathTally::default())
+            .insert_resource(Generation(3));
+
+        let parent = Identity::founder(7, 0);
+        let parent_traits = TraitSnapshot::from([("size".to_string(), 2.0)]);
+        let organism = app
+            .world_mut()
+            .spawn((
+                Position { x: 0, y: 0 },
+                parent.child(8, 3),
+                ParentTraits(parent_traits.clone()),
+            ))
+            .id();
+        app.world_mut().flush();
+        assert!(app.world().get::<ParentTraits>(organism).is_none());
+        app.world_mut().resource_mut::<Generation>().0 = 5;
+        app.world_mut().despawn(organism);
+
+        let born: Vec<_> = app
+            .world_mut()
+            .resource_mut::<Events<Born>>()
+            .drain()
+            .collect();
+        let died: Vec<_> = app
+            .world_mut()
+            .resource_mut::<Events<Died>>()
+            .drain()
+            .collect();
+        assert_eq!(born.len(), 1);
+        assert_eq!(born[0].parent, Some(7));
+        assert_eq!(born[0].lineage, 7);
+        assert_eq!(born[0].generation, 3);
+        assert_eq!(born[0].parent_traits, Some(parent_traits));
+        assert_eq!(died.len(), 1);
+        assert_eq!(died[0].id, 8);
+        assert_eq!(died[0].kind, EntityKind::Organism);
+        assert_eq!(died[0].age, 2);
+        let mut newborns = app.world_mut().resource_mut::<NewbornSurvival>();
+        assert_eq!(newborns.close(100), 1.0);
+    }
+
+    /// An app that takes out the dead and lets the survivors reproduce, with births
+    /// and deaths recorded, and an entity pool of `pool_size`.
+    fn pooled_app(pool_size: usize) -> App {
+        let mut config = default_config();
+        config.world.entity_pool_size = pool_size;
+        let mut app = App::new();
+        app.add_event::<Born>()
+            .add_event::<Died>()
+            .add_observer(record_birth)
+            .add_observer(record_death)
+            .add_observer(record_retirement)
+            .insert_resource(grassland_world(4, 4))
+            .insert_resource(RngStreams::new(3))
+            .insert_resource(PopulationCount::default())
+            .insert_resource(Generation(2))
+            .insert_resource(NextId(10))
+            .insert_resource(ClampTally::default())
+            .insert_resource(SpatialIndex::new(4, 4))
+            .insert_resource(NewbornSurvival::default())
+            .insert_resource(DeathTally::default())
+            .insert_resource(config)
+            .add_systems(
+                Update,
+                (despawn_dead_organisms, reproduction, trim_entity_pool).chain(),
+            );
+        app
+    }
+
+    fn drain<E: Event>(app: &mut App) -> Vec<E> {
+        app.world_mut()
+            .resource_mut::<Events<E>>()
+            .drain()
+            .collect()
+    }
+
+    #[test]
+    fn recycled_entities_start_a_fresh_life() {
+        let mut app = pooled_app(4);
+        let config = app.world().resource::<Config>().clone();
+        let mut history = IntakeHistory::default();
+        history.meal = 3.0;
+        history.record(2);
+        let mut dead = founder_organism(&config, [1.0; 4]);
+        dead.energy = -1.0;
+        dead.morph = 3;
+        let dead = app
+            .world_mut()
+            .spawn((
+                dead,
+                Position { x: 3, y: 3 },
+                Identity::founder(0, 0),
+                SpeciesId::B,
+                history,
+                Panicked {
+                    ticks: 4,
+                    x: 3,
+                    y: 3,
+                },
+                Injured {
+                    severity: 0.5,
+                    ticks_remaining: 6,
+                },
+                Dispersing {
+                    ticks: 5,
+                    heading: 1,
+                },
+            ))
+            .id();
+        let mut parent = founder_organism(&config, [1.0; 4]);
+        parent.energy = 100.0;
+        parent.reproduction_threshold = 50.0;
+        parent.reproduction_cooldown = 0.0;
+        app.world_mut()
+            .spawn((parent, Position { x: 1, y: 1 }, Identity::founder(1, 0)));
+        drain::<Born>(&mut app);
+
+        app.update();
+
+        // the only child took over the dead organism's entity
+        let mut organisms = app.world_mut().query::<&Organism>();
+        assert_eq!(organisms.iter(app.world()).count(), 2);
+        let child = app.world().entity(dead);
+        assert!(!child.contains::<Inactive>());
+        assert!(!child.contains::<Panicked>());
+        assert!(!child.contains::<Injured>());
+        assert!(!child.contains::<Dispersing>());
+        assert!(!child.contains::<ParentTraits>());
+        assert_eq!(
+            child.get::<IntakeHistory>(),
+            Some(&IntakeHistory::default())
+        );
+        assert_eq!(child.get::<SpeciesId>(), Some(&SpeciesId::A));
+        assert_eq!(child.get::<Identity>().unwrap().id, 10);
+        assert_eq!(child.get::<Identity>().unwrap().parent, Some(1));
+        assert_eq!(child.get::<Organism>().unwrap().energy, 50.0);
+        assert_eq!(child.get::<Organism>().unwrap().morph, 0);
+
+        let died = drain::<Died>(&mut app);
+        let born = drain::<Born>(&mut app);
+        assert_eq!((died.len(), died[0].id), (1, 0));
+        assert_eq!((born.len(), born[0].id), (1, 10));
+    }
+
+    #[test]
+    fn the_pool_is_trimmed_without_recording_deaths_twice() {
+        let mut app = pooled_app(1);
+        let config = app.world().resource::<Config>().clone();
+        for id in 0..3 {
+            let mut dead = founder_organism(&config, [1.0; 4]);
+            dead.energy = -1.0;
+            app.world_mut()
+                .spawn((dead, Position { x: 0, y: 0 }, Identity::founder(id, 0)));
+        }
+
+        app.update();
+
+        let mut pooled = app
+            .world_mut()
+            .query_filtered::<&Organism, With<Inactive>>();
+        assert_eq!(pooled.iter(app.world()).count(), 1);
+        let mut organisms = app.world_mut().query::<&Organism>();
+        assert_eq!(organisms.iter(app.world()).count(), 1);
+        assert_eq!(drain::<Died>(&mut app).len(), 3);
+        let tally = app.world().resource::<DeathTally>().organism_offspring;
+        assert_eq!(tally.deaths, 3);
+    }
+
+    #[test]
+    fn a_parent_dies_with_the_offspring_it_had() {
+        let mut config = default_config();
+        config.organism.organism_reproduction_cooldown = 0.0;
+        let mut app = App::new();
+        app.add_event::<Died>()
+            .add_observer(record_death)
+            .insert_resource(grassland_world(4, 4))
+            .insert_resource(RngStreams::new(2))
+            .insert_resource(PopulationCount::default())
+            .insert_resource(Generation(0))
+            .insert_resource(NextId::default())
+            .insert_resource(ClampTally::default())
+            .insert_resource(SpatialIndex::new(4, 4))
+            .insert_resource(NewbornSurvival::default())
+            .insert_resource(DeathTally::default())
+            .add_systems(Update, reproduction);
+        let mut parent = founder_organism(&config, [1.0; 4]);
+        parent.energy = 1.0e6;
+        parent.reproduction_cooldown = 0.0;
+        let parent = app
+            .world_mut()
+            .spawn((parent, Position { x: 1, y: 1 }, Identity::founder(0, 0)))
+            .id();
+        app.insert_resource(config);
+
+        // one child a tick
+        for _ in 0..3 {
+            app.update();
+        }
+        let identity = app.world().get::<Identity>(parent).unwrap();
+        assert_eq!(identity.offspring_count, 3);
+        app.world_mut().resource_mut::<Generation>().0 = 3;
+        app.world_mut().despawn(parent);
+
+        let died: Vec<_> = app
+            .world_mut()
+            .resource_mut::<Events<Died>>()
+            .drain()
+            .collect();
+        assert_eq!(died.len(), 1);
+        assert_eq!((died[0].offspring, died[0].age), (3, 3));
+        assert_eq!(died[0].traits.get("size"), Some(&1.0));
+        let tally = app.world().resource::<DeathTally>().organism_offspring;
+        assert_eq!((tally.deaths, tally.max, tally.mean()), (1, 3, 3.0));
+    }
+
+    /// Births in one tick of generation `generation` by a parent with plenty of
+    /// energy and `flexibility`, in a year of 10-generation seasons that only breeds
+    /// in spring, along with the energy the parent keeps.
+    fn births_in_generation(generation: usize, flexibility: f32) -> (usize, f32) {
+        let mut config = default_config();
+        config.world.seasons.enabled = true;
+        config.world.seasons.length = 10;
+        config.organism.breeding.seasons = vec![Season::Spring];
+        let mut app = App::new();
+        app.insert_resource(grassland_world(4, 4))
+            .insert_resource(RngStreams::new(4))
+            .insert_resource(PopulationCount::default())
+            .insert_resource(Generation(generation))
+            .insert_resource(NextId::default())
+            .insert_resource(ClampTally::default())
+            .insert_resource(SpatialIndex::new(4, 4))
+            .add_systems(Update, reproduction);
+        let mut parent = founder_organism(&config, [1.0; 4]);
+        parent.energy = 1000.0;
+        parent.reproduction_cooldown = 0.0;
+        parent.breeding_flexibility = flexibility;
+        let parent = app
+            .world_mut()
+            .spawn((parent, Position { x: 1, y: 1 }, Identity::founder(0, 0)))
+            .id();
+        app.insert_resource(config);
+
+        app.update();
+        let births = app
+            .world()
+            .resource::<ClampTally>()
+            .births(EntityKind::Organism);
+        (births, app.world().get::<Organism>(parent).unwrap().energy)
+    }
+
+    #[test]
+    fn breeding_windows_hold_back_reproduction_off_season() {
+        // generation 5 is spring and 15 summer
+        assert_eq!(births_in_generation(5, 0.0), (1, 500.0));
+        assert_eq!(births_in_generation(15, 0.0), (0, 1000.0));
+        // a fully flexible parent breeds anyway and loses half of what it keeps
+        assert_eq!(births_in_generation(15, 1.0), (1, 250.0));
+    }
+
+    /// Births in one tick of generation `generation` by `parents` offspring born in
+    /// generation 0, under aging that matures them at 5 and halves their fertility
+    /// every generation past 10, along with the births by parent age.
+    fn births_at_age(generation: usize, parents: usize) -> (usize, Vec<usize>) {
+        let mut config = default_config();
+        config.organism.aging = AgingConfig {
+            enabled: true,
+            maturity_age: 5,
+            senescence_onset: 10,
+            fertility_decline: 0.5,
+            metabolic_increase: 0.0,
+        };
+        let mut app = App::new();
+        app.insert_resource(grassland_world(20, 20))
+            .insert_resource(RngStreams::new(4))
+            .insert_resource(PopulationCount::default())
+            .insert_resource(Generation(generation))
+            .insert_resource(NextId(1_000))
+            .insert_resource(ClampTally::default())
+            .insert_resource(SpatialIndex::new(20, 20))
+            .add_systems(Update, reproduction);
+        for i in 0..parents {
+            let mut parent = founder_organism(&config, [1.0; 4]);
+            parent.energy = 1000.0;
+            parent.reproduction_cooldown = 0.0;
+            let identity = Identity::founder(0, 0).child(i as u64 + 1, 0);
+            let position = Position {
+                x: i % 20,
+                y: i / 20 % 20,
+            };
+            app.world_mut().spawn((parent, position, identity));
+        }
+        app.insert_resource(config);
+
+        app.update();
+        let clamps = app.world().resource::<ClampTally>();
+        (
+            clamps.births(EntityKind::Organism),
+            clamps.births_by_parent_age(EntityKind::Organism),
+        )
+    }
+
+    #[test]
+    fn juveniles_never_reproduce_and_the_old_less_often() {
+        assert_eq!(births_at_age(4, 50), (0, vec![]));
+        // age 5 falls in the second bin of parent ages
+        assert_eq!(births_at_age(5, 50), (50, vec![0, 50]));
+        assert_eq!(births_at_age(10, 50).0, 50);
+        // two generations past the onset a quarter of them still breed
+        let (births, bins) = births_at_age(12, 400);
+        assert!((70..130).contains(&births), "{}", births);
+        assert_eq!(bins, vec![0, 0, births]);
+    }
+
+    #[test]
+    fn desertification_fires_once_per_conversion() {
+        let mut config = default_config();
+        config.world.desertification.enabled = true;
+        config.world.desertification.food_threshold = 1.0;
+        config.world.desertification.generations = 2;
+
+        let mut app = App::new();
+        app.add_event::<BiomeChanged>()
+            .insert_resource(grassland_world(4, 4))
+            .insert_resource(FoodGrid::new(4, vec![0.0; 16]))
+            .insert_resource(ActiveTiles::all(16))
+            .insert_resource(config)
+            .insert_resource(Generation(0))
+            .init_resource::<DesertificationPressure>()
+            .add_systems(Update, desertification);
+
+        let mut received = Vec::new();
+        for _ in 0..6 {
+            app.update();
+            received.extend(
+                app.world_mut()
+                    .resource_mut::<Events<BiomeChanged>>()
+                    .drain(),
+            );
+        }
+
+        assert_eq!(received.len(), 16);
+        let mut tiles: Vec<_> = received.iter().map(|e| (e.x, e.y)).collect();
+        tiles.sort();
+        tiles.dedup();
+        assert_eq!(tiles.len(), 16);
+        assert!(received
+            .iter()
+            .all(|e| e.from == Biome::Grassland && e.to == Biome::Desert));
+        assert!(app
+            .world()
+            .resource::<World>()
+            .grid
+            .iter()
+            .all(|tile| tile.biome == Biome::Desert));
+    }
+
+    #[test]
+    fn desertification_spares_tiles_with_food() {
+        let mut config = default_config();
+        config.world.desertification.enabled = true;
+        config.world.desertification.generations = 1;
+
+        let mut food = vec![50.0; 4];
+        food[3] = 0.0;
+
+        let mut app = App::new();
+        app.add_event::<BiomeChanged>()
+            .insert_resource(grassland_world(2, 2))
+            .insert_resource(FoodGrid::new(2, food))
+            .insert_resource(ActiveTiles::all(4))
+            .insert_resource(config)
+            .insert_resource(Generation(0))
+            .init_resource::<DesertificationPressure>()
+            .add_systems(Update, desertification);
+
+        app.update();
+        let received: Vec<_> = app
+            .world_mut()
+            .resource_mut::<Events<BiomeChanged>>()
+            .drain()
+            .collect();
+
+        assert_eq!(received.len(), 1);
+        assert_eq!((received[0].x, received[0].y), (1, 1));
+    }
+
+    #[test]
+    fn upkeep_counts_only_the_energy_the_living_had() {
+        fn spend(mut organisms: Query<&mut Organism>) {
+            for mut organism in organisms.iter_mut() {
+                organism.energy -= 1.5;
+            }
+        }
+
+        let config = default_config();
+        let mut app = App::new();
+        app.insert_resource(EnergyFlux::default())
+            .add_systems(Update, (open_energy_flux, spend, close_upkeep).chain());
+        for energy in [10.0, 1.0] {
+            let mut organism = founder_organism(&config, [1.0; 4]);
+            organism.energy = energy;
+            app.world_mut().spawn(organism);
+        }
+        app.world_mut().spawn(founder_predator(&config));
+
+        app.update();
+        // the second organism only had 1 left to spend
+        assert!((app.world().resource::<EnergyFlux>().upkeep - 2.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn active_tile_regrowth_matches_a_full_sweep() {
+        let config = default_config();
+        let (width, height) = (24, 24);
+        let (mut world, food_grid) = World::new(width, height, 11);
+        let mut sparse = food_grid.values().to_vec();
+        let mut full = sparse.clone();
+
+        let mut app = App::new();
+        app.insert_resource(world.clone())
+            .insert_resource(FoodGrid::new(width, sparse.clone()))
+            .insert_resource(ActiveTiles::all(width * height))
+            .insert_resource(EnergyFlux::default())
+            .insert_resource(config.clone())
+            .add_systems(Update, regenerate_food);
+
+        let mut rng = SmallRng::seed_from_u64(5);
+        for tick in 0..300usize {
+            // Graze a handful of tiles and, now and then, turn one into desert.
+            for _ in 0..8 {
+                let i = rng.gen_range(0..width * height);
+                let bite = rng.gen_range(0.0..20.0f32);
+                full[i] = (full[i] - bite).max(0.0);
+                sparse[i] = full[i];
+                app.world_mut().resource_mut::<ActiveTiles>().mark(i);
+            }
+            if tick.is_multiple_of(25) {
+                let i = rng.gen_range(0..width * height);
+                world.grid[i].biome = Biome::Desert;
+                app.world_mut().resource_mut::<World>().grid[i].biome = Biome::Desert;
+                app.world_mut().resource_mut::<ActiveTiles>().mark(i);
+            }
+            *app.world_mut().resource_mut::<FoodGrid>() = FoodGrid::new(width, sparse);
+
+            app.update();
+            for (tile, food) in world.grid.iter().zip(full.iter_mut()) {
+                let biome_config = config.world.biome(tile.biome);
+                if *food <= biome_config.max_food_availability {
+                    *food += biome_config.food_availability;
+                }
+            }
+
+            sparse = app.world().resource::<FoodGrid>().values().to_vec();
+            assert_eq!(sparse, full, "diverged at tick {}", tick);
+        }
+        assert!(app.world().resource::<ActiveTiles>().len() < width * height);
+    }
+
+    #[test]
+    fn reproduction_chance_is_logistic_around_the_threshold() {
+        assert!((reproduction_chance(100.0, 100.0, 10.0) - 0.5).abs() < 1e-6);
+        assert!(reproduction_chance(0.0, 100.0, 10.0) < 1e-4);
+        assert!(reproduction_chance(200.0, 100.0, 10.0) > 1.0 - 1e-4);
+
+        let mut world = default_config().world;
+        world.reproduction_model = ReproductionModel::Probabilistic;
+        let mut rng = SmallRng::seed_from_u64(5);
+        let rate = |energy: f32, rng: &mut SmallRng| {
+            (0..10_000)
+                .filter(|_| decides_to_reproduce(energy, 100.0, &world, rng))
+                .count() as f32
+                / 10_000.0
+        };
+        assert!((rate(100.0, &mut rng) - 0.5).abs() < 0.02);
+        assert!(rate(0.0, &mut rng) < 0.001);
+        assert!(rate(200.0, &mut rng) > 0.999);
+    }
+
+    #[test]
+    fn threshold_reproduction_is_a_cliff_and_draws_nothing() {
+        let world = default_config().world;
+        let mut rng = SmallRng::seed_from_u64(5);
+        assert!(!decides_to_reproduce(100.0, 100.0, &world, &mut rng));
+        assert!(decides_to_reproduce(100.5, 100.0, &world, &mut rng));
+        assert_eq!(
+            rng.gen::<u64>(),
+            SmallRng::seed_from_u64(5).gen::<u64>(),
+            "the threshold model must leave the stream untouched"
+        );
+    }
+
+    #[test]
+    fn dispersers_keep_their_heading_until_the_burst_expires() {
+        let mut config = default_config();
+        config.organism.starvation_dispersal.heading_persistence = 1.0;
+        let (width, height) = (20, 9);
+        let mut app = App::new();
+        app.insert_resource(grassland_world(width, height))
+            .insert_resource(FoodGrid::new(width, vec![50.0; width * height]))
+            .insert_resource(PredatorSpatialIndex::new(width, height))
+            .insert_resource(KillScentGrid::new(width * height))
+            .init_resource::<MovementTrace>()
+            .insert_resource(Generation(0))
+            .insert_resource(RngStreams::new(0))
+            .insert_resource(config.clone())
+            .add_systems(Update, (organism_movement, end_dispersal).chain());
+        let organism = app
+            .world_mut()
+            .spawn((
+                Organism {
+                    energy: 100.0,
+                    speed: 1.0,
+                    size: 1.0,
+                    ..founder_organism(&config, [1.0; 4])
+                },
+                Position { x: 2, y: 4 },
+                EntityRng(SmallRng::seed_from_u64(3)),
+                // east
+                Dispersing {
+                    ticks: 3,
+                    heading: 2,
+                },
+            ))
+            .id();
+
+        for tick in 1..=3 {
+            app.update();
+            let position = app.world().get::<Position>(organism).unwrap();
+            assert_eq!((position.x, position.y), (2 + tick, 4), "tick {}", tick);
+            assert_eq!(
+                app.world().get::<Dispersing>(organism).is_some(),
+                tick < 3,
+                "tick {}",
+                tick
+            );
+        }
+        // three steps at twice the usual cost of 0.1
+        let energy = app.world().get::<Organism>(organism).unwrap().energy;
+        assert!((energy - 99.4).abs() < 1e-4, "{}", energy);
+    }
+
+    #[test]
+    fn headings_turn_one_step_at_a_time() {
+        let mut rng = SmallRng::seed_from_u64(9);
+        let mut kept = 0;
+        for _ in 0..10_000 {
+            let heading = next_heading(0, 0.8, &mut rng);
+            assert!([7, 0, 1].contains(&heading), "{}", heading);
+            kept += usize::from(heading == 0);
+        }
+        assert!((kept as f32 / 10_000.0 - 0.8).abs() < 0.02);
+        assert_eq!(next_heading(5, 1.0, &mut rng), 5);
+    }
+
+    #[test]
+    fn starving_organisms_set_off_once_their_window_fills() {
+        let mut config = default_config();
+        config.organism.starvation_dispersal.enabled = true;
+        config.organism.starvation_dispersal.window = 3;
+        let mut app = App::new();
+        app.insert_resource(config.clone())
+            .add_systems(Update, start_dispersal);
+        let [starving, fed] = [0, 1].map(|_| {
+            app.world_mut()
+                .spawn((
+                    Organism {
+                        starvation_trigger: 0.1,
+                        ..founder_organism(&config, [1.0; 4])
+                    },
+                    EntityRng(SmallRng::seed_from_u64(1)),
+                ))
+                .id()
+        });
+
+        for tick in 1..=3 {
+            app.world_mut().get_mut::<IntakeHistory>(fed).unwrap().meal = 1.0;
+            app.update();
+            assert_eq!(
+                app.world().get::<Dispersing>(starving).is_some(),
+                tick == 3,
+                "tick {}",
+                tick
+            );
+            assert!(app.world().get::<Dispersing>(fed).is_none());
+        }
+        let burst = app.world().get::<Dispersing>(starving).unwrap();
+        assert_eq!(
+            burst.ticks,
+            config.organism.starvation_dispersal.initial_duration as u32
+        );
+    }
+
+    #[test]
+    fn food_and_biome_only_fill_the_reserve() {
+        let mut config = default_config();
+        config.organism.fat_storage.enabled = true;
+        let mut app = App::new();
+        app.insert_resource(grassland_world(1, 1))
+            .insert_resource(FoodGrid::new(1, vec![10.0]))
+            .insert_resource(CarrionGrid(vec![0.0]))
+            .insert_resource(ActiveTiles::all(1))
+            .insert_resource(SpatialIndex::new(1, 1))
+            .insert_resource(EnergyIntake::default())
+            .insert_resource(EnergyFlux::default())
+            .add_systems(Update, (consume_food, biome_adaptation).chain());
+        let organism = Organism {
+            energy: 49.9,
+            reserve_capacity: 50.0,
+            ..founder_organism(&config, [1.0; 4])
+        };
+        let organism = app
+            .world_mut()
+            .spawn((organism, Position { x: 0, y: 0 }))
+            .id();
+        app.world_mut()
+            .resource_mut::<SpatialIndex>()
+            .insert(0, 0, organism);
+        app.insert_resource(config);
+
+        for _ in 0..3 {
+            app.update();
+            let energy = app.world().get::<Organism>(organism).unwrap().energy;
+            assert!((energy - 50.0).abs() < 1e-4, "{}", energy);
+        }
+        // the 0.4 energy in the first meal past the cap is lost to digestion
+        let intake = app.world().resource::<EnergyIntake>();
+        assert!((intake.gained - 0.1).abs() < 1e-4, "{}", intake.gained);
+        assert!((intake.digestion_loss - 1.1).abs() < 1e-4);
+
+        assert_eq!(capped_gain(60.0, 1.0, 50.0), 0.0);
+        assert_eq!(capped_gain(10.0, 1.0, f32::INFINITY), 1.0);
+    }
+
+    /// Energies after a tick of `biome_adaptation` of organisms that start with 10
+    /// energy and a tolerance of 2 on a forest, desert, water and grassland tile,
+    /// and the passive energy recorded in the flux.
+    fn energies_after_passive_tick(config: Config) -> (Vec<f32>, f32) {
+        let mut world = grassland_world(4, 1);
+        for (tile, biome) in world.grid.iter_mut().zip(Biome::ALL) {
+            tile.biome = biome;
+        }
+        let mut app = App::new();
+        app.insert_resource(world)
+            .insert_resource(EnergyFlux::default())
+            .add_systems(Update, biome_adaptation);
+        let organisms: Vec<_> = (0..4)
+            .map(|x| {
+                let organism = Organism {
+                    energy: 10.0,
+                    ..founder_organism(&config, [2.0; 4])
+                };
+                app.world_mut().spawn((organism, Position { x, y: 0 })).id()
+            })
+            .collect();
+        app.insert_resource(config);
+        app.update();
+        let energies = organisms
+            .iter()
+            .map(|&organism| app.world().get::<Organism>(organism).unwrap().energy)
+            .collect();
+        (energies, app.world().resource::<EnergyFlux>().passive)
+    }
+
+    #[test]
+    fn passive_energy_follows_the_biome_table() {
+        let close = |(energies, passive): (Vec<f32>, f32), expected: [f32; 4], net: f32| {
+            for (energy, expected) in energies.iter().zip(expected) {
+                assert!((energy - expected).abs() < 1e-5, "{:?}", energies);
+            }
+            assert!((passive - net).abs() < 1e-5, "{}", passive);
+        };
+
+        // gains scale with the tolerance of 2, the desert's loss shrinks with it
+        let defaults = energies_after_passive_tick(default_config());
+        close(defaults.clone(), [10.2, 9.95, -1.0, 10.1], 0.25);
+
+        // a config written before the table behaves as the built-in values did
+        let mut unset = default_config();
+        for biome in [
+            &mut unset.world.forest,
+            &mut unset.world.desert,
+            &mut unset.world.water,
+            &mut unset.world.grassland,
+        ] {
+            biome.passive_energy_delta = None;
+        }
+        assert_eq!(energies_after_passive_tick(unset), defaults);
+
+        let mut config = default_config();
+        config.world.forest.passive_energy_delta = Some(0.5);
+        config.world.grassland.passive_energy_delta = Some(-0.4);
+        close(
+            energies_after_passive_tick(config),
+            [11.0, 9.95, -1.0, 9.8],
+            0.75,
+        );
+
+        // switched off only the water still drowns
+        let mut config = default_config();
+        config.world.passive_energy = false;
+        close(
+            energies_after_passive_tick(config),
+            [10.0, 10.0, -1.0, 10.0],
+            0.0,
+        );
+    }
+
+    #[test]
+    fn broad_tolerance_drains_more_energy_than_a_specialist_pays() {
+        let mut world = grassland_world(2, 1);
+        world.grid[0].biome = Biome::Forest;
+        world.grid[1].biome = Biome::Forest;
+        let mut config = default_config();
+        config.world.passive_energy = false;
+        let specialist = [1.4, 0.3, 0.2, 0.5];
+        let generalist = [1.2, 1.2, 0.2, 1.2];
+
+        let energies = |rate: f32| {
+            let mut config = config.clone();
+            config.organism.tolerance_cost.rate = rate;
+            let mut app = App::new();
+            app.insert_resource(world.clone())
+                .insert_resource(EnergyFlux::default())
+                .add_systems(Update, biome_adaptation);
+            let organisms = [(0, specialist), (1, generalist)].map(|(x, tolerance)| {
+                let organism = Organism {
+                    energy: 10.0,
+                    ..founder_organism(&config, tolerance)
+                };
+                app.world_mut().spawn((organism, Position { x, y: 0 })).id()
+            });
+            app.insert_resource(config);
+            app.update();
+            let upkeep = app.world().resource::<EnergyFlux>().upkeep;
+            let energies =
+                organisms.map(|organism| app.world().get::<Organism>(organism).unwrap().energy);
+            (energies, upkeep)
+        };
+
+        // free by default
+        assert_eq!(energies(0.0), ([10.0, 10.0], 0.0));
+        // 0.6 above the baseline of 0.8 against 3 * 0.4
+        let ([specialist, generalist], upkeep) = energies(0.5);
+        assert!((specialist - 9.7).abs() < 1e-5, "{}", specialist);
+        assert!((generalist - 9.4).abs() < 1e-5, "{}", generalist);
+        assert!((upkeep - 0.9).abs() < 1e-5, "{}", upkeep);
+    }
+
+    #[test]
+    fn a_larger_reserve_costs_more_to_move() {
+        let energy_spent = |fat_storage: bool, reserve_capacity: f32| {
+            let mut config = default_config();
+            config.organism.fat_storage.enabled = fat_storage;
+            let mut app = App::new();
+            app.insert_resource(grassland_world(9, 9))
+                .insert_resource(FoodGrid::new(9, vec![0.0; 81]))
+                .insert_resource(PredatorSpatialIndex::new(9, 9))
+                .insert_resource(KillScentGrid::new(81))
+                .init_resource::<MovementTrace>()
+                .insert_resource(Generation(0))
+                .insert_resource(RngStreams::new(0))
+                .insert_resource(config.clone())
+                .add_systems(Update, organism_movement);
+            let organism = Organism {
+                energy: 100.0,
+                speed: 1.0,
+                size: 1.0,
+                reserve_capacity,
+                ..founder_organism(&config, [1.0; 4])
+            };
+            let entity = app
+                .world_mut()
+                .spawn((
+                    organism,
+                    Position { x: 4, y: 4 },
+                    EntityRng(SmallRng::seed_from_u64(3)),
+                ))
+                .id();
+            app.update();
+            100.0 - app.world().get::<Organism>(entity).unwrap().energy
+        };
+
+        // a reserve of max_organism_energy adds weight_coupling of 0.5 to the size
+        let full = energy_spent(true, 200.0);
+        let half = energy_spent(true, 100.0);
+        assert!((full - 0.15).abs() < 1e-4, "{}", full);
+        assert!((half - 0.125).abs() < 1e-4, "{}", half);
+        assert!((energy_spent(false, 200.0) - 0.1).abs() < 1e-4);
+    }
+
+    fn territory_config() -> Config {
+        let mut config = default_config();
+        config.predator.territory.enabled = true;
+        config.predator.territory.radius = 2.0;
+        config
+    }
+
+    #[test]
+    fn territories_are_claimed_and_dissolve_with_their_owners() {
+        let config = territory_config();
+        let mut app = App::new();
+        app.insert_resource(TerritoryMap::new(20, 20))
+            .insert_resource(config.clone())
+            .add_systems(Update, claim_territories);
+        let mut spawn = |x, y| {
+            app.world_mut()
+                .spawn((founder_predator(&config), Position { x, y }))
+                .id()
+        };
+        let first = spawn(3, 3);
+        let second = spawn(15, 15);
+        // inside the first one's territory, so it has to wait
+        let floater = spawn(4, 3);
+        app.update();
+
+        let map = app.world().resource::<TerritoryMap>();
+        assert_eq!(map.territories.len(), 2);
+        assert_eq!(
+            map.territories[&first],
+            Territory {
+                x: 3,
+                y: 3,
+                radius: 2.0
+            }
+        );
+        assert!(map.territories.contains_key(&second));
+        assert!(!map.territories.contains_key(&floater));
+
+        app.world_mut().get_mut::<Predator>(first).unwrap().energy = -1.0;
+        app.update();
+        let map = app.world().resource::<TerritoryMap>();
+        assert!(!map.territories.contains_key(&first));
+        assert_eq!(map.territories[&floater].x, 4);
+        assert_eq!(map.owners(2, 3), &[floater]);
+
+        app.world_mut().despawn(second);
+        app.update();
+        let map = app.world().resource::<TerritoryMap>();
+        assert_eq!(map.territories.keys().collect::<Vec<_>>(), [&floater]);
+        assert!(map.owners(15, 15).is_empty());
+    }
+
+    #[test]
+    fn owners_fight_rivals_that_come_next_to_them() {
+        let config = territory_config();
+        let mut app = App::new();
+        app.insert_resource(TerritoryMap::new(10, 10))
+            .insert_resource(config.clone())
+            .insert_resource(RngStreams::new(1))
+            .insert_resource(Generation(0))
+            .add_systems(Update, territorial_contests);
+        let mut spawn = |x, y, aggression| {
+            let predator = Predator {
+                energy: 50.0,
+                aggression,
+                ..founder_predator(&config)
+            };
+            app.world_mut().spawn((predator, Position { x, y })).id()
+        };
+        let owner = spawn(5, 5, 1.0);
+        let rival = spawn(6, 6, 0.0);
+        let distant = spawn(5, 8, 1.0);
+        app.world_mut()
+            .resource_mut::<TerritoryMap>()
+            .claim(owner, 5, 5, 4.0);
+        app.update();
+
+        // a rival without aggression always loses
+        let energy = |entity| app.world().get::<Predator>(entity).unwrap().energy;
+        let settings = &config.predator.territory;
+        assert_eq!(energy(owner), 50.0 - settings.aggression_cost);
+        assert_eq!(energy(rival), 50.0 - settings.contest_cost);
+        assert_eq!(energy(distant), 50.0);
+    }
+
+    /// Ticks an organism with a giving-up density of 1 spends on the middle tile of
+    /// an otherwise bare world, starting there with `food` and grazing 1 a tick.
+    fn residence_on_tile_with(food: f32, giving_up: bool) -> u32 {
+        let mut config = default_config();
+        config.organism.giving_up.enabled = giving_up;
+        config.organism.giving_up.initial_density = 1.0;
+        let mut grid = vec![0.0; 81];
+        grid[4 * 9 + 4] = food;
+        let mut app = App::new();
+        app.insert_resource(grassland_world(9, 9))
+            .insert_resource(FoodGrid::new(9, grid))
+            .insert_resource(PredatorSpatialIndex::new(9, 9))
+            .insert_resource(KillScentGrid::new(81))
+            .init_resource::<MovementTrace>()
+            .insert_resource(RngStreams::new(5))
+            .insert_resource(Generation(0))
+            .insert_resource(ResidenceTally::default())
+            .insert_resource(config.clone())
+            .add_systems(Update, (organism_movement, track_residence).chain());
+        let organism = Organism {
+            energy: 100.0,
+            ..founder_organism(&config, [1.0; 4])
+        };
+        let entity = app
+            .world_mut()
+            .spawn((
+                organism,
+                Position { x: 4, y: 4 },
+                EntityRng(SmallRng::seed_from_u64(3)),
+                Residence {
+                    tile: Some((4, 4)),
+                    ticks: 1,
+                },
+            ))
+            .id();
+
+        for _ in 0..20 {
+            app.update();
+            if let Some(&ticks) = app.world().resource::<ResidenceTally>().0.first() {
+                return ticks;
+            }
+            let position = *app.world().get::<Position>(entity).unwrap();
+            let mut food_grid = app.world_mut().resource_mut::<FoodGrid>();
+            let i = position.y * 9 + position.x;
+            let left = (food_grid.get(i) - 1.0).max(0.0);
+            food_grid.set(i, left);
+        }
+        panic!("the organism never left the tile");
+    }
+
+    #[test]
+    fn organisms_stay_on_rich_tiles_until_grazed_down_to_their_giving_up_density() {
+        let rich = residence_on_tile_with(5.0, true);
+        let poor = residence_on_tile_with(0.5, true);
+        // it leaves the rich tile once 4 ticks of grazing bring it to 1
+        assert_eq!(rich, 5);
+        assert_eq!(poor, 1);
+        assert_eq!(residence_on_tile_with(5.0, false), 1);
+
+        let mut residence = Residence::default();
+        assert_eq!(residence.stay((0, 0)), None);
+        assert_eq!(residence.stay((0, 0)), None);
+        assert_eq!(residence.stay((1, 0)), Some(2));
+    }
+
+    /// Records the level and target of every message logged while it is the
+    /// subscriber of the thread.
+    #[derive(Clone, Default)]
+    struct Captured(std::sync::Arc<std::sync::Mutex<Vec<(bevy::log::Level, String)>>>);
+
+    impl<S: bevy::utils::tracing::Subscriber> bevy::log::tracing_subscriber::Layer<S> for Captured {
+        fn on_event(
+            &self,
+            event: &bevy::utils::tracing::Event<'_>,
+            _: bevy::log::tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let metadata = event.metadata();
+            self.0
+                .lock()
+                .unwrap()
+                .push((*metadata.level(), metadata.target().to_string()));
+        }
+    }
+
+    #[test]
+    fn reaching_the_entity_cap_warns_once_a_generation() {
+        use bevy::ecs::schedule::ExecutorKind;
+        use bevy::log::tracing_subscriber::layer::SubscriberExt;
+
+        let mut config = default_config();
+        config.world.max_total_entities = 3;
+        let mut app = App::new();
+        app.insert_resource(grassland_world(4, 4))
+            .insert_resource(RngStreams::new(5))
+            .insert_resource(PopulationCount {
+                organisms: 2,
+                predators: 1,
+            })
+            .insert_resource(Generation(0))
+            .insert_resource(NextId::default())
+            .insert_resource(ClampTally::default())
+            .insert_resource(SpatialIndex::new(4, 4))
+            .insert_resource(PredatorSpatialIndex(SpatialIndex::new(4, 4)))
+            .insert_resource(config)
+            .add_systems(
+                Update,
+                (
+                    reset_clamp_tally,
+                    reproduction,
+                    predator_reproduction,
+                    increment_generation,
+                )
+                    .chain(),
+            );
+        // on this thread, where the subscriber below is
+        app.edit_schedule(Update, |schedule| {
+            schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+        });
+
+        let captured = Captured::default();
+        let subscriber = bevy::log::tracing_subscriber::registry().with(captured.clone());
+        bevy::utils::tracing::subscriber::with_default(subscriber, || {
+            for _ in 0..3 {
+                app.update();
+            }
+        });
+
+        let warnings: Vec<_> = captured
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(level, _)| *level == bevy::log::Level::WARN)
+            .map(|(_, target)| target.clone())
+            .collect();
+        // both kinds run into the cap in each of the three generations
+        assert_eq!(warnings, vec!["sim::reproduction"; 3]);
     }
 }
@@ -0,0 +1,411 @@
+//! Notices a run that stopped making progress: when the generation has not advanced
+//! with anyone alive for `watchdog_timeout_seconds`, a diagnostics bundle is written
+//! and the run carries on, finishes or panics as `watchdog_action` says.
+//!
+//! The schedule bumps a heartbeat every tick that makes progress, and a thread of
+//! its own watches it, so a frame that never returns is caught as well as a run
+//! that idles. The bundle holds the state as of the last frame that finished.
+
+use std::collections::VecDeque;
+use std::fmt::{Debug, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+
+use bevy::log::tracing_subscriber::layer::Context;
+use bevy::log::tracing_subscriber::Layer;
+use bevy::log::BoxedLayer;
+use bevy::prelude::*;
+use bevy::utils::tracing::field::{Field, Visit};
+use bevy::utils::tracing::{self, Subscriber};
+use bevy::utils::{Duration, Instant};
+use serde::Serialize;
+
+use crate::components::{Organism, Predator};
+use crate::plugins::logging::{living_state_hash, Living};
+use crate::provenance::{write_artifact, Provenance, RunId};
+use crate::resources::{AppState, Config, Generation, TickClock, WatchdogAction, World};
+
+/// Log lines kept for the diagnostics bundle.
+const RECENT_LOG_LINES: usize = 100;
+
+static RECENT_LOGS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+pub struct WatchdogPlugin;
+
+impl Plugin for WatchdogPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, start_watchdog)
+            .add_systems(Last, watch_generation.run_if(in_state(AppState::Simulate)));
+    }
+}
+
+/// The census of the last finished frame, which the watchdog thread reports.
+#[derive(Debug, Clone, Copy, Default)]
+struct Census {
+    generation: usize,
+    organisms: usize,
+    predators: usize,
+    state_hash: u64,
+}
+
+/// What the schedule and the watchdog thread share.
+#[derive(Default)]
+struct Pulse {
+    /// Bumped every tick that makes progress.
+    beats: AtomicU64,
+    census: Mutex<Census>,
+    /// Set by the thread when the run stalled, for the schedule to act on.
+    tripped: AtomicBool,
+    stop: AtomicBool,
+}
+
+/// The watchdog thread of a run with `watchdog_timeout_seconds`, stopped when the
+/// resource is dropped.
+#[derive(Resource)]
+pub struct Watchdog {
+    pulse: Arc<Pulse>,
+    /// The generation of the last beat, `None` before the first.
+    generation: Option<usize>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.pulse.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// What the watchdog writes to `watchdog_<generation>.json` when the run stalls.
+#[derive(Serialize)]
+struct Diagnostics {
+    generation: usize,
+    stalled_seconds: f32,
+    organisms: usize,
+    predators: usize,
+    state_hash: String,
+    /// The last log lines at or above the log level, oldest first.
+    recent_logs: Vec<String>,
+}
+
+fn start_watchdog(mut commands: Commands, config: Res<Config>, run_id: Res<RunId>) {
+    let Some(timeout) = config.logging.watchdog_timeout_seconds else {
+        return;
+    };
+    let pulse = Arc::new(Pulse::default());
+    let watched = Arc::clone(&pulse);
+    let config = config.clone();
+    let run_id = run_id.clone();
+    let thread = thread::Builder::new()
+        .name("watchdog".into())
+        .spawn(move || watch(&watched, Duration::from_secs_f32(timeout), &config, &run_id))
+        .expect("Failed to start the watchdog thread");
+    commands.insert_resource(Watchdog {
+        pulse,
+        generation: None,
+        thread: Some(thread),
+    });
+}
+
+/// Beats for every tick that advanced the generation with someone alive, or while
+/// the GUI holds the run still, and acts on a stall the thread found.
+#[allow(clippy::too_many_arguments)]
+fn watch_generation(
+    watchdog: Option<ResMut<Watchdog>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    generation: Res<Generation>,
+    clock: Res<TickClock>,
+    config: Res<Config>,
+    world: Res<World>,
+    organisms: Living<Organism>,
+    predators: Living<Predator>,
+) {
+    let Some(mut watchdog) = watchdog else {
+        return;
+    };
+    let census = Census {
+        generation: generation.0,
+        organisms: organisms.iter().len(),
+        predators: predators.iter().len(),
+        state_hash: living_state_hash(&world, &organisms, &predators, generation.0),
+    };
+    *watchdog
+        .pulse
+        .census
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = census;
+    let advanced =
+        watchdog.generation != Some(generation.0) && census.organisms + census.predators > 0;
+    // a run held still in the GUI is not stalled
+    if advanced || clock.paused {
+        watchdog.generation = Some(generation.0);
+        watchdog.pulse.beats.fetch_add(1, Ordering::Relaxed);
+    }
+
+    if !watchdog.pulse.tripped.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    match config.logging.watchdog_action {
+        WatchdogAction::Warn => {}
+        WatchdogAction::Shutdown => next_state.set(AppState::Finished),
+        WatchdogAction::Panic => panic!("watchdog: generation {} stalled", generation.0),
+    }
+}
+
+/// The watchdog thread: reports a stall every `timeout` without a beat, until told
+/// to stop. A panic the schedule never got to, since its frame did not return,
+/// ends the process once the next timeout passes.
+fn watch(pulse: &Pulse, timeout: Duration, config: &Config, run_id: &RunId) {
+    let poll = (timeout / 4).clamp(Duration::from_millis(1), Duration::from_secs(1));
+    let mut seen = pulse.beats.load(Ordering::Relaxed);
+    let mut since = Instant::now();
+    while !pulse.stop.load(Ordering::Relaxed) {
+        thread::sleep(poll);
+        let beats = pulse.beats.load(Ordering::Relaxed);
+        if beats != seen {
+            seen = beats;
+            since = Instant::now();
+            continue;
+        }
+        let stalled = since.elapsed();
+        if stalled < timeout {
+            continue;
+        }
+        // a stall that goes on is reported again after every timeout
+        since = Instant::now();
+        let census = *pulse.census.lock().unwrap_or_else(PoisonError::into_inner);
+        report(&census, stalled, config, run_id);
+        let untaken = pulse.tripped.swap(true, Ordering::Relaxed);
+        if untaken && config.logging.watchdog_action == WatchdogAction::Panic {
+            error!(
+                target: "sim::watchdog",
+                "Generation {} is stuck in a frame, exiting",
+                census.generation
+            );
+            std::process::exit(101);
+        }
+    }
+}
+
+/// Writes the diagnostics bundle of a stall of `stalled` at `census` and warns.
+fn report(census: &Census, stalled: Duration, config: &Config, run_id: &RunId) {
+    let diagnostics = Diagnostics {
+        generation: census.generation,
+        stalled_seconds: stalled.as_secs_f32(),
+        organisms: census.organisms,
+        predators: census.predators,
+        state_hash: format!("{:016x}", census.state_hash),
+        recent_logs: recent_logs(),
+    };
+    let path = diagnostics_path(config, census.generation);
+    let provenance = Provenance::new(
+        run_id,
+        config.world.seed,
+        census.generation,
+        Some(census.state_hash),
+    );
+    let json = serde_json::to_string_pretty(&diagnostics).expect("Failed to serialize diagnostics");
+    match config
+        .logging
+        .create_output_dir()
+        .and_then(|()| write_artifact(&path, json, &provenance))
+    {
+        Ok(()) => warn!(
+            target: "sim::watchdog",
+            "Generation {} has not advanced for {:.1}s, diagnostics written to {}",
+            census.generation,
+            diagnostics.stalled_seconds,
+            path.display()
+        ),
+        Err(err) => warn!(
+            target: "sim::watchdog",
+            "Generation {} has not advanced for {:.1}s, failed to write {}: {}",
+            census.generation,
+            diagnostics.stalled_seconds,
+            path.display(),
+            err
+        ),
+    }
+}
+
+fn diagnostics_path(config: &Config, generation: usize) -> PathBuf {
+    config
+        .logging
+        .output_path(&format!("watchdog_{}.json", generation))
+}
+
+/// A layer for [`LogPlugin::custom_layer`](bevy::log::LogPlugin::custom_layer)
+/// keeping the last log lines for the watchdog's diagnostics.
+pub fn recent_logs_layer(_app: &mut App) -> Option<BoxedLayer> {
+    Some(Box::new(RecentLogs))
+}
+
+/// The log lines [`recent_logs_layer`] kept, oldest first.
+pub fn recent_logs() -> Vec<String> {
+    let recent = RECENT_LOGS.lock().unwrap_or_else(PoisonError::into_inner);
+    recent.iter().cloned().collect()
+}
+
+struct RecentLogs;
+
+impl<S: Subscriber> Layer<S> for RecentLogs {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut line = format!("{} {}:", metadata.level(), metadata.target());
+        event.record(&mut LineVisitor(&mut line));
+        let mut recent = RECENT_LOGS.lock().unwrap_or_else(PoisonError::into_inner);
+        if recent.len() == RECENT_LOG_LINES {
+            recent.pop_front();
+        }
+        recent.push_back(line);
+    }
+}
+
+struct LineVisitor<'a>(&'a mut String);
+
+impl Visit for LineVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        let _ = if field.name() == "message" {
+            write!(self.0, " {:?}", value)
+        } else {
+            write!(self.0, " {}={:?}", field.name(), value)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::log::tracing_subscriber::layer::SubscriberExt;
+    use bevy::log::tracing_subscriber::Registry;
+    use bevy::state::app::StatesPlugin;
+
+    use super::*;
+    use crate::components::Position;
+    use crate::plugins::simulation::founder_organism;
+    use crate::utils::default_config;
+
+    fn watched_app(name: &str, action: WatchdogAction) -> App {
+        let mut config = default_config();
+        config.world.width = 8;
+        config.world.height = 8;
+        config.logging.output_dir = Some(std::env::temp_dir().join(name));
+        config.logging.watchdog_timeout_seconds = Some(0.1);
+        config.logging.watchdog_action = action;
+        let _ = std::fs::remove_dir_all(config.logging.output_dir.as_ref().unwrap());
+
+        let mut app = App::new();
+        app.add_plugins(StatesPlugin)
+            .insert_resource(World::generate(&config.world).0)
+            .insert_resource(RunId::new(config.world.seed))
+            .insert_resource(config)
+            .insert_resource(Generation(0))
+            .insert_resource(TickClock::default())
+            .init_state::<AppState>()
+            .add_plugins(WatchdogPlugin);
+        app
+    }
+
+    fn populated_app(name: &str, action: WatchdogAction) -> App {
+        let mut app = watched_app(name, action);
+        let organism = founder_organism(app.world().resource::<Config>(), [1.0; 4]);
+        app.world_mut().spawn((organism, Position { x: 1, y: 1 }));
+        app
+    }
+
+    fn diagnostics(app: &App, generation: usize) -> PathBuf {
+        diagnostics_path(app.world().resource::<Config>(), generation)
+    }
+
+    /// Runs `frames` frames `pause` apart, advancing the generation in each.
+    fn advance(app: &mut App, frames: usize, pause: Duration) {
+        for _ in 0..frames {
+            app.world_mut().resource_mut::<Generation>().0 += 1;
+            app.update();
+            std::thread::sleep(pause);
+        }
+    }
+
+    #[test]
+    fn a_generation_that_stands_still_is_reported() {
+        let mut app = populated_app("evolution_watchdog_stall", WatchdogAction::Shutdown);
+        // advancing every frame keeps it quiet however long the run takes
+        advance(&mut app, 8, Duration::from_millis(40));
+        assert!(!diagnostics(&app, 8).exists());
+
+        // holding the generation still past the timeout writes the bundle
+        app.update();
+        std::thread::sleep(Duration::from_millis(300));
+        let path = diagnostics(&app, 8);
+        let bundle: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(bundle["generation"], 8);
+        assert_eq!(bundle["organisms"], 1);
+        assert!(bundle["stalled_seconds"].as_f64().unwrap() >= 0.1);
+        assert_eq!(Provenance::read(&path).unwrap().unwrap().generation, 8);
+
+        // and shuts the run down
+        app.update();
+        app.update();
+        assert_eq!(
+            app.world().resource::<State<AppState>>().get(),
+            &AppState::Finished
+        );
+    }
+
+    #[test]
+    fn a_frame_that_never_returns_is_reported() {
+        let mut app = populated_app("evolution_watchdog_hung", WatchdogAction::Warn);
+        advance(&mut app, 2, Duration::ZERO);
+        // no update runs while the thread waits, as if the frame hung
+        std::thread::sleep(Duration::from_millis(300));
+        assert!(diagnostics(&app, 2).exists());
+    }
+
+    #[test]
+    fn an_empty_world_is_stalled_however_far_the_generation_gets() {
+        let mut app = watched_app("evolution_watchdog_empty", WatchdogAction::Warn);
+        advance(&mut app, 10, Duration::from_millis(30));
+        let dir = app.world().resource::<Config>().logging.output_path("");
+        let reported = std::fs::read_dir(dir).unwrap().any(|entry| {
+            let name = entry.unwrap().file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("watchdog_") && name.ends_with(".json")
+        });
+        assert!(reported);
+    }
+
+    #[test]
+    fn a_paused_run_is_not_stalled() {
+        let mut app = watched_app("evolution_watchdog_paused", WatchdogAction::Shutdown);
+        app.world_mut().resource_mut::<TickClock>().paused = true;
+        for _ in 0..10 {
+            app.update();
+            std::thread::sleep(Duration::from_millis(30));
+        }
+        assert!(!diagnostics(&app, 0).exists());
+        assert_eq!(
+            app.world().resource::<State<AppState>>().get(),
+            &AppState::Simulate
+        );
+    }
+
+    #[test]
+    fn the_last_log_lines_are_kept() {
+        let subscriber = Registry::default().with(RecentLogs);
+        tracing::subscriber::with_default(subscriber, || {
+            for i in 0..RECENT_LOG_LINES + 5 {
+                warn!(target: "sim::watchdog", "line {}", i);
+            }
+        });
+        let recent = recent_logs();
+        assert_eq!(recent.len(), RECENT_LOG_LINES);
+        assert_eq!(
+            recent.last().unwrap(),
+            &format!("WARN sim::watchdog: line {}", RECENT_LOG_LINES + 4)
+        );
+    }
+}
@@ -0,0 +1,17 @@
+//! The types an app embedding the simulation works with, so that it does not depend
+//! on where they live in the crate: `use evolution::prelude::*;`.
+
+pub use crate::app::{build_app, try_build_app, EvolutionPlugin};
+pub use crate::components::{Active, Identity, Inactive, Organism, Position, Predator, SpeciesId};
+pub use crate::config::{ConfigBuilder, ConfigError};
+pub use crate::events::{
+    BiomeChangeCause, BiomeChanged, Born, CatastropheKind, CatastropheStruck, Died,
+    FoodPatchAppeared, Killed, PanicSpread, PreyInjured,
+};
+pub use crate::plugins::simulation::SimulationSet;
+pub use crate::plugins::SimulationPlugin;
+pub use crate::resources::{
+    AppState, Biome, Config, Generation, PopulationCount, PredatorSpatialIndex, SpatialIndex,
+    StatsHistory, Tile, World,
+};
+pub use crate::schema::{GenerationStats, PredatorStats, Report, SpeciesStats};
@@ -0,0 +1,79 @@
+//! Configs built into the binary, picked by name with `--preset`. Each preset is a
+//! TOML file under `presets/` that only lists what it changes from the defaults;
+//! see [`layer_config`](crate::config::layer_config) for how it combines with a
+//! config file and `--set` overrides.
+
+/// A named config shipped with the binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub toml: &'static str,
+}
+
+pub const PRESETS: [Preset; 5] = [
+    Preset {
+        name: "balanced",
+        description: "a mid-sized world where organisms and predators coexist",
+        toml: include_str!("../presets/balanced.toml"),
+    },
+    Preset {
+        name: "predator_heavy",
+        description: "many efficient predators keeping prey under pressure",
+        toml: include_str!("../presets/predator_heavy.toml"),
+    },
+    Preset {
+        name: "harsh_desert",
+        description: "mostly desert, with scarce and poor food",
+        toml: include_str!("../presets/harsh_desert.toml"),
+    },
+    Preset {
+        name: "island_world",
+        description: "islands in open water, grazed along their shores",
+        toml: include_str!("../presets/island_world.toml"),
+    },
+    Preset {
+        name: "teaching_small",
+        description: "a small, slow world that is easy to follow",
+        toml: include_str!("../presets/teaching_small.toml"),
+    },
+];
+
+/// The preset called `name`.
+pub fn find(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| preset.name == name)
+}
+
+/// One line per preset with its name and description, for `--list-presets`.
+pub fn list() -> String {
+    let width = PRESETS
+        .iter()
+        .map(|preset| preset.name.len())
+        .max()
+        .unwrap_or(0);
+    PRESETS
+        .iter()
+        .map(|preset| format!("{:width$}  {}\n", preset.name, preset.description))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::layer_config;
+    use crate::utils::default_config;
+
+    #[test]
+    fn every_preset_is_valid_and_differs_from_the_defaults() {
+        for preset in &PRESETS {
+            let (config, defaulted) = layer_config(Some(preset), None, &[])
+                .unwrap_or_else(|err| panic!("{}: {}", preset.name, err));
+            assert_eq!(config.validate(), Ok(()), "{}", preset.name);
+            assert_ne!(config, default_config(), "{}", preset.name);
+            assert!(defaulted.0.is_empty(), "{}: {:?}", preset.name, defaulted);
+        }
+        assert_eq!(find("harsh_desert").unwrap().name, "harsh_desert");
+        assert_eq!(find("nonexistent"), None);
+        assert_eq!(list().lines().count(), PRESETS.len());
+    }
+}
@@ -0,0 +1,414 @@
+//! Where an artifact came from: the run that wrote it, its seed, the generation it
+//! was written at, the crate version and the [`state_hash`](crate::self_check::state_hash)
+//! when there was one. [`write_artifact`] keeps it with every file the crate writes,
+//! as `# key: value` lines heading CSV and TOML files, `tEXt` chunks in PNGs and a
+//! `<name>.meta.json` sidecar next to anything else, and [`Provenance::read`] finds
+//! it again.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::resources::{Config, Generation};
+use crate::self_check::app_state_hash;
+use crate::utils::safe_write;
+
+/// Appended to the name of a file for the sidecar holding its provenance.
+pub const META_SUFFIX: &str = ".meta.json";
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Tells the runs apart whose artifacts end up side by side: the seed and the
+/// milliseconds since the epoch the run started at.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct RunId(pub String);
+
+impl RunId {
+    pub fn new(seed: u64) -> Self {
+        #[cfg(target_arch = "wasm32")]
+        let millis = js_sys::Date::now() as u64;
+        #[cfg(not(target_arch = "wasm32"))]
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        Self(format!("{}-{}", seed, millis))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub run_id: String,
+    pub seed: u64,
+    pub generation: usize,
+    pub crate_version: String,
+    /// The state hash in hex, left out for files written before there was a state
+    /// to hash or by something without access to it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_hash: Option<String>,
+}
+
+impl Provenance {
+    pub fn new(run_id: &RunId, seed: u64, generation: usize, state_hash: Option<u64>) -> Self {
+        Self {
+            run_id: run_id.0.clone(),
+            seed,
+            generation,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            state_hash: state_hash.map(|hash| format!("{:016x}", hash)),
+        }
+    }
+
+    /// The provenance of what `app` writes now, with the hash of its current state.
+    pub fn of_app(app: &mut App) -> Self {
+        let hash = app_state_hash(app);
+        let ecs = app.world();
+        Self::new(
+            ecs.resource::<RunId>(),
+            ecs.resource::<Config>().world.seed,
+            ecs.resource::<Generation>().0,
+            Some(hash),
+        )
+    }
+
+    /// The same run at `generation`, with `state_hash`.
+    pub fn at(&self, generation: usize, state_hash: Option<String>) -> Self {
+        Self {
+            generation,
+            state_hash,
+            ..self.clone()
+        }
+    }
+
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![
+            ("run_id", self.run_id.clone()),
+            ("seed", self.seed.to_string()),
+            ("generation", self.generation.to_string()),
+            ("crate_version", self.crate_version.clone()),
+        ];
+        if let Some(hash) = &self.state_hash {
+            fields.push(("state_hash", hash.clone()));
+        }
+        fields
+    }
+
+    fn from_fields(fields: impl IntoIterator<Item = (String, String)>) -> Option<Self> {
+        let fields: BTreeMap<_, _> = fields.into_iter().collect();
+        Some(Self {
+            run_id: fields.get("run_id")?.clone(),
+            seed: fields.get("seed")?.parse().ok()?,
+            generation: fields.get("generation")?.parse().ok()?,
+            crate_version: fields.get("crate_version")?.clone(),
+            state_hash: fields.get("state_hash").cloned(),
+        })
+    }
+
+    /// The `# key: value` lines heading a CSV or TOML file.
+    pub fn header(&self) -> String {
+        self.fields()
+            .into_iter()
+            .map(|(key, value)| format!("# {}: {}\n", key, value))
+            .collect()
+    }
+
+    /// The provenance in the comment lines heading `text`, `None` without one.
+    pub fn from_header(text: &str) -> Option<Self> {
+        Self::from_fields(
+            text.lines()
+                .map_while(|line| line.strip_prefix('#'))
+                .filter_map(|comment| comment.split_once(':'))
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string())),
+        )
+    }
+
+    /// The provenance in the `tEXt` chunks of `png`.
+    pub fn from_png(png: &[u8]) -> Option<Self> {
+        Self::from_fields(png_text(png))
+    }
+
+    /// The provenance kept with the file at `path`, `None` when it has none.
+    pub fn read(path: &Path) -> io::Result<Option<Self>> {
+        match Kind::of(path) {
+            Kind::Commented => Ok(Self::from_header(&fs::read_to_string(path)?)),
+            Kind::Png => Ok(Self::from_png(&fs::read(path)?)),
+            Kind::Sidecar => {
+                let sidecar = meta_path(path);
+                if !sidecar.exists() {
+                    return Ok(None);
+                }
+                serde_json::from_str(&fs::read_to_string(sidecar)?)
+                    .map(Some)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+            }
+        }
+    }
+}
+
+/// How a file of some type keeps its provenance.
+enum Kind {
+    Commented,
+    Png,
+    Sidecar,
+}
+
+impl Kind {
+    fn of(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv" | "toml") => Kind::Commented,
+            Some("png") => Kind::Png,
+            _ => Kind::Sidecar,
+        }
+    }
+}
+
+/// The sidecar next to `path`, `<name>.meta.json`.
+pub fn meta_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(META_SUFFIX);
+    path.with_file_name(name)
+}
+
+/// Writes `bytes` to `path` with [`safe_write`], keeping `provenance` in a header,
+/// the PNG's `tEXt` chunks or a sidecar, depending on the type of file.
+pub fn write_artifact(
+    path: &Path,
+    bytes: impl AsRef<[u8]>,
+    provenance: &Provenance,
+) -> io::Result<()> {
+    let bytes = bytes.as_ref();
+    match Kind::of(path) {
+        Kind::Commented => {
+            let mut headed = provenance.header().into_bytes();
+            headed.extend_from_slice(bytes);
+            safe_write(path, headed)
+        }
+        Kind::Png => safe_write(path, embed_png_text(bytes, &provenance.fields())?),
+        Kind::Sidecar => {
+            safe_write(path, bytes)?;
+            write_sidecar(path, provenance)
+        }
+    }
+}
+
+/// Writes the sidecar of a file written some other way, like a log that grows as
+/// the run goes on.
+pub fn write_sidecar(path: &Path, provenance: &Provenance) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(provenance).expect("Failed to serialize provenance");
+    safe_write(&meta_path(path), json)
+}
+
+/// The warning for `artifacts` that do not all come from the same run, naming the
+/// files of each run, `None` when they do.
+pub fn mixed_runs(artifacts: &[(String, Provenance)]) -> Option<String> {
+    let mut runs: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (name, provenance) in artifacts {
+        runs.entry(&provenance.run_id).or_default().push(name);
+    }
+    if runs.len() < 2 {
+        return None;
+    }
+    let runs: Vec<_> = runs
+        .into_iter()
+        .map(|(run, names)| format!("{} from run {}", names.join(", "), run))
+        .collect();
+    Some(format!(
+        "the files come from different runs: {}",
+        runs.join("; ")
+    ))
+}
+
+/// `png` with a `tEXt` chunk for each of `entries` right after its header chunk.
+pub fn embed_png_text(png: &[u8], entries: &[(&str, String)]) -> io::Result<Vec<u8>> {
+    let invalid = |reason| io::Error::new(io::ErrorKind::InvalidData, reason);
+    if !png.starts_with(&PNG_SIGNATURE) {
+        return Err(invalid("not a PNG"));
+    }
+    // signature, then the IHDR chunk: length, type, 13 bytes of data and the CRC
+    let header_end = PNG_SIGNATURE.len() + 4 + 4 + 13 + 4;
+    if png.get(12..16) != Some(b"IHDR") || png.len() < header_end {
+        return Err(invalid("PNG does not start with a header chunk"));
+    }
+
+    let mut embedded = png[..header_end].to_vec();
+    for (keyword, text) in entries {
+        let mut data = keyword.as_bytes().to_vec();
+        data.push(0);
+        data.extend_from_slice(text.as_bytes());
+        let mut chunk = b"tEXt".to_vec();
+        chunk.extend_from_slice(&data);
+
+        embedded.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        embedded.extend_from_slice(&chunk);
+        embedded.extend_from_slice(&crc32(&chunk).to_be_bytes());
+    }
+    embedded.extend_from_slice(&png[header_end..]);
+    Ok(embedded)
+}
+
+/// The keywords and texts of the `tEXt` chunks in `png`, up to the first chunk that
+/// is cut short or fails its CRC.
+pub fn png_text(png: &[u8]) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+    if !png.starts_with(&PNG_SIGNATURE) {
+        return entries;
+    }
+    let mut at = PNG_SIGNATURE.len();
+    while let Some(length) = png.get(at..at + 4) {
+        let length = u32::from_be_bytes(length.try_into().unwrap()) as usize;
+        let Some(chunk) = png.get(at + 4..at + 8 + length) else {
+            break;
+        };
+        let Some(crc) = png.get(at + 8 + length..at + 12 + length) else {
+            break;
+        };
+        if crc32(chunk).to_be_bytes() != crc {
+            break;
+        }
+        let (kind, data) = chunk.split_at(4);
+        if kind == b"tEXt" {
+            if let Some(nul) = data.iter().position(|&byte| byte == 0) {
+                entries.push((
+                    String::from_utf8_lossy(&data[..nul]).into_owned(),
+                    String::from_utf8_lossy(&data[nul + 1..]).into_owned(),
+                ));
+            }
+        }
+        at += 12 + length;
+    }
+    entries
+}
+
+/// The CRC-32 PNG chunks end with, over their type and data.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provenance() -> Provenance {
+        Provenance::new(&RunId("7-1700000000000".into()), 7, 120, Some(0xabc))
+    }
+
+    fn chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut typed = kind.to_vec();
+        typed.extend_from_slice(data);
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(&typed);
+        chunk.extend_from_slice(&crc32(&typed).to_be_bytes());
+        chunk
+    }
+
+    /// A 1x1 RGB image, its pixel data in a stored deflate block.
+    fn tiny_png() -> Vec<u8> {
+        let ihdr = [0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0, 0, 0];
+        let idat = [
+            0x78, 0x01, 0x01, 0x04, 0x00, 0xfb, 0xff, 0x00, 0xff, 0x00, 0x00, 0x03, 0x01, 0x01,
+            0x00,
+        ];
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend(chunk(b"IHDR", &ihdr));
+        png.extend(chunk(b"IDAT", &idat));
+        png.extend(chunk(b"IEND", &[]));
+        png
+    }
+
+    #[test]
+    fn crc_matches_the_png_reference() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn provenance_embedded_in_a_png_reads_back_and_keeps_the_image() {
+        let png = tiny_png();
+        let embedded = embed_png_text(&png, &provenance().fields()).unwrap();
+
+        assert_eq!(Provenance::from_png(&embedded), Some(provenance()));
+        assert_eq!(Provenance::from_png(&png), None);
+        // the header stays first and the image chunks follow the text unchanged
+        assert_eq!(embedded[..33], png[..33]);
+        assert!(embedded.ends_with(&png[33..]));
+        assert_eq!(png_text(&embedded).len(), 5);
+
+        let mut corrupted = embedded.clone();
+        corrupted[45] ^= 1;
+        assert_eq!(Provenance::from_png(&corrupted), None);
+        assert!(embed_png_text(b"GIF89a", &[]).is_err());
+    }
+
+    #[test]
+    fn csv_header_comments_parse_back_and_stop_at_the_data() {
+        let dir = std::env::temp_dir().join("evolution_provenance_csv");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("survivorship.csv");
+        write_artifact(&path, "cohort,age\n1,# not a comment\n", &provenance()).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("# run_id: 7-1700000000000\n# seed: 7\n"));
+        assert!(text.ends_with("# state_hash: 0000000000000abc\ncohort,age\n1,# not a comment\n"));
+        assert_eq!(Provenance::read(&path).unwrap(), Some(provenance()));
+        assert!(!meta_path(&path).exists());
+
+        let without_hash = provenance().at(3, None);
+        assert_eq!(
+            Provenance::from_header(&without_hash.header()),
+            Some(without_hash)
+        );
+        assert_eq!(Provenance::from_header("cohort,age\n# run_id: 1\n"), None);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn other_files_get_a_sidecar() {
+        let dir = std::env::temp_dir().join("evolution_provenance_sidecar");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("report.json");
+        write_artifact(&path, "{}", &provenance()).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{}");
+        assert_eq!(meta_path(&path), dir.join("report.json.meta.json"));
+        assert_eq!(Provenance::read(&path).unwrap(), Some(provenance()));
+        assert_eq!(Provenance::read(&dir.join("events.jsonl")).unwrap(), None);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn artifacts_of_different_runs_are_named_by_run() {
+        let other = Provenance {
+            run_id: "8-1700000000001".into(),
+            ..provenance()
+        };
+        let artifacts = [
+            ("events.jsonl".to_string(), provenance()),
+            ("summary_data.jsonl".to_string(), provenance().at(50, None)),
+        ];
+        assert_eq!(mixed_runs(&artifacts), None);
+
+        let mut mixed = artifacts.to_vec();
+        mixed.push(("samples.jsonl".to_string(), other));
+        assert_eq!(
+            mixed_runs(&mixed).as_deref(),
+            Some(
+                "the files come from different runs: events.jsonl, summary_data.jsonl from run \
+                 7-1700000000000; samples.jsonl from run 8-1700000000001"
+            )
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! Statistics of a rectangle of the world, for the box selection of the GUI: who
+//! lives in it, their average traits and the food on its tiles.
+
+use std::fmt::{self, Display};
+
+use bevy::prelude::Entity;
+
+use crate::components::TraitSnapshot;
+use crate::resources::{FoodGrid, ReserveRect, SpatialIndex};
+
+/// A rectangle of tiles, both corners included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub min_x: usize,
+    pub min_y: usize,
+    pub max_x: usize,
+    pub max_y: usize,
+}
+
+impl Region {
+    /// The tiles between two corners given in any order, clamped to a `width` x
+    /// `height` world. `None` when the box lies entirely outside it.
+    pub fn between(
+        a: (isize, isize),
+        b: (isize, isize),
+        width: usize,
+        height: usize,
+    ) -> Option<Self> {
+        let (min_x, max_x) = (a.0.min(b.0), a.0.max(b.0));
+        let (min_y, max_y) = (a.1.min(b.1), a.1.max(b.1));
+        let (right, top) = (width as isize - 1, height as isize - 1);
+        if max_x < 0 || max_y < 0 || min_x > right || min_y > top {
+            return None;
+        }
+        Some(Self {
+            min_x: min_x.max(0) as usize,
+            min_y: min_y.max(0) as usize,
+            max_x: max_x.min(right) as usize,
+            max_y: max_y.min(top) as usize,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.max_x - self.min_x + 1
+    }
+
+    pub fn height(&self) -> usize {
+        self.max_y - self.min_y + 1
+    }
+
+    /// Every tile of the region, row by row.
+    pub fn tiles(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.min_y..=self.max_y).flat_map(move |y| (self.min_x..=self.max_x).map(move |x| (x, y)))
+    }
+
+    /// The region as an entry of `reserves.rectangles`.
+    pub fn as_reserve(&self) -> ReserveRect {
+        ReserveRect {
+            x: self.min_x,
+            y: self.min_y,
+            width: self.width(),
+            height: self.height(),
+        }
+    }
+}
+
+/// Who lives in a region and how much food lies on it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RegionStats {
+    pub region: Option<Region>,
+    pub organisms: usize,
+    pub predators: usize,
+    /// Average of each trait over the organisms in the region.
+    pub organism_traits: TraitSnapshot,
+    pub predator_traits: TraitSnapshot,
+    pub food: f32,
+}
+
+/// Adds up `traits` of every entity on the tiles of `region` in `index`, skipping
+/// entities `traits` does not know, such as ones that died since the index was
+/// built. Returns the count and the averages.
+fn average_traits(
+    region: &Region,
+    index: &SpatialIndex,
+    traits: impl Fn(Entity) -> Option<TraitSnapshot>,
+) -> (usize, TraitSnapshot) {
+    let mut count = 0;
+    let mut sums = TraitSnapshot::new();
+    for (x, y) in region.tiles() {
+        for snapshot in index.get(x, y).iter().filter_map(|&entity| traits(entity)) {
+            count += 1;
+            for (name, value) in snapshot {
+                *sums.entry(name).or_insert(0.0) += value;
+            }
+        }
+    }
+    for sum in sums.values_mut() {
+        *sum /= count as f32;
+    }
+    (count, sums)
+}
+
+/// Statistics of `region`, finding its organisms and predators through their
+/// spatial indices and the traits of each through `organism_traits` and
+/// `predator_traits`.
+pub fn region_stats(
+    region: Region,
+    organisms: &SpatialIndex,
+    predators: &SpatialIndex,
+    food: &FoodGrid,
+    organism_traits: impl Fn(Entity) -> Option<TraitSnapshot>,
+    predator_traits: impl Fn(Entity) -> Option<TraitSnapshot>,
+) -> RegionStats {
+    let (organism_count, organism_traits) = average_traits(&region, organisms, organism_traits);
+    let (predator_count, predator_traits) = average_traits(&region, predators, predator_traits);
+    RegionStats {
+        region: Some(region),
+        organisms: organism_count,
+        predators: predator_count,
+        organism_traits,
+        predator_traits,
+        food: region
+            .tiles()
+            .map(|(x, y)| food.get(y * organisms.width + x))
+            .sum(),
+    }
+}
+
+impl Display for RegionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(region) = self.region {
+            writeln!(
+                f,
+                "tiles ({}, {}) to ({}, {}), {} x {}",
+                region.min_x,
+                region.min_y,
+                region.max_x,
+                region.max_y,
+                region.width(),
+                region.height()
+            )?;
+        }
+        writeln!(f, "food: {:.1}", self.food)?;
+        for (kind, count, traits) in [
+            ("organisms", self.organisms, &self.organism_traits),
+            ("predators", self.predators, &self.predator_traits),
+        ] {
+            writeln!(f, "{}: {}", kind, count)?;
+            for (name, value) in traits {
+                writeln!(f, "  {:<24} {:.3}", name, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::hashbrown::HashMap;
+
+    use super::*;
+
+    fn snapshot(size: f32) -> TraitSnapshot {
+        TraitSnapshot::from([("size".to_string(), size)])
+    }
+
+    #[test]
+    fn selections_are_clamped_to_the_world() {
+        let region = Region::between((7, -3), (2, 4), 6, 10).unwrap();
+        assert_eq!(
+            region,
+            Region {
+                min_x: 2,
+                min_y: 0,
+                max_x: 5,
+                max_y: 4
+            }
+        );
+        assert_eq!(region.tiles().count(), 4 * 5);
+        let reserve = region.as_reserve();
+        assert_eq!(
+            (reserve.x, reserve.y, reserve.width, reserve.height),
+            (2, 0, 4, 5)
+        );
+        assert_eq!(Region::between((-5, -5), (-1, 3), 6, 10), None);
+        assert_eq!(Region::between((6, 0), (9, 3), 6, 10), None);
+    }
+
+    #[test]
+    fn region_stats_cover_only_the_tiles_inside() {
+        let (width, height) = (8, 8);
+        let mut organisms = SpatialIndex::new(width, height);
+        let mut predators = SpatialIndex::new(width, height);
+        let mut traits = HashMap::new();
+        for (i, (x, y, size)) in [
+            (1, 1, 1.0),
+            (2, 3, 3.0),
+            (2, 3, 5.0),
+            (6, 6, 100.0),
+            (3, 2, 9.0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let entity = Entity::from_raw(i as u32);
+            organisms.insert(x, y, entity);
+            traits.insert(entity, snapshot(size));
+        }
+        // the last one has died since the index was built
+        traits.remove(&Entity::from_raw(4));
+        let predator = Entity::from_raw(10);
+        predators.insert(0, 0, predator);
+        let food = FoodGrid::new(width, (0..width * height).map(|i| i as f32).collect());
+
+        let region = Region::between((1, 1), (3, 3), width, height).unwrap();
+        let stats = region_stats(
+            region,
+            &organisms,
+            &predators,
+            &food,
+            |entity| traits.get(&entity).cloned(),
+            |entity| (entity == predator).then(|| snapshot(2.0)),
+        );
+        assert_eq!(stats.organisms, 3);
+        assert_eq!(stats.organism_traits, snapshot(3.0));
+        assert_eq!(stats.predators, 0);
+        assert!(stats.predator_traits.is_empty());
+        // rows 1 to 3, columns 1 to 3 of an 8 wide grid numbered row by row
+        let expected: f32 = [9, 10, 11, 17, 18, 19, 25, 26, 27].iter().sum::<i32>() as f32;
+        assert_eq!(stats.food, expected);
+        assert!(stats.to_string().contains("organisms: 3"));
+    }
+}
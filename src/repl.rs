@@ -0,0 +1,436 @@
+//! Interactive headless mode, started with `evolution --repl`. Input lines are parsed
+//! into a [`Command`] and applied with [`dispatch`], which other front-ends can call
+//! directly with commands of their own.
+
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use bevy::app::PluginsState;
+use bevy::prelude::*;
+
+use crate::components::{
+    Active, EntityKind, EntityRng, Identity, Organism, Position, Predator, SpeciesId,
+};
+use crate::plugins::simulation::{founder_organism, founder_predator, random_morph};
+use crate::provenance::{write_artifact, Provenance};
+use crate::resources::{AppState, Config, FoodGrid, Generation, NextId, RngStreams, World};
+use crate::schema::{ExportData, OrganismWithPosition, PredatorWithPosition, SCHEMA_VERSION};
+use crate::seed_bank::SeedBank;
+use crate::utils::get_biome_tolerance;
+
+pub const USAGE: &str = "\
+commands:
+  step [n]                                  advance n generations (default 1)
+  stats                                     population counts and trait averages
+  show <x> <y>                              tile details and occupants
+  top <organisms|predators> <trait> [k]     the k individuals with the highest trait
+  spawn <organism|predator> <x> <y>         add a founder at a tile
+  save <path>                               write a world snapshot as JSON
+  export_population <path>                  write the living population's traits
+  quit";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Step(usize),
+    Stats,
+    Show {
+        x: usize,
+        y: usize,
+    },
+    Top {
+        kind: EntityKind,
+        trait_name: String,
+        k: usize,
+    },
+    Spawn {
+        kind: EntityKind,
+        x: usize,
+        y: usize,
+    },
+    Save(PathBuf),
+    ExportPopulation(PathBuf),
+    Quit,
+}
+
+fn parse_kind(word: &str) -> Result<EntityKind, String> {
+    match word {
+        "organism" | "organisms" => Ok(EntityKind::Organism),
+        "predator" | "predators" => Ok(EntityKind::Predator),
+        _ => Err(format!("unknown kind `{}`", word)),
+    }
+}
+
+fn parse_number(word: Option<&str>, name: &str) -> Result<usize, String> {
+    let word = word.ok_or_else(|| format!("missing {}", name))?;
+    word.parse()
+        .map_err(|_| format!("{} must be a non-negative integer, got `{}`", name, word))
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some("step") => match words.next() {
+                Some(n) => Command::Step(parse_number(Some(n), "step count")?),
+                None => Command::Step(1),
+            },
+            Some("stats") => Command::Stats,
+            Some("show") => Command::Show {
+                x: parse_number(words.next(), "x")?,
+                y: parse_number(words.next(), "y")?,
+            },
+            Some("top") => Command::Top {
+                kind: parse_kind(words.next().ok_or("missing kind")?)?,
+                trait_name: words.next().ok_or("missing trait")?.to_string(),
+                k: match words.next() {
+                    Some(k) => parse_number(Some(k), "k")?,
+                    None => 5,
+                },
+            },
+            Some("spawn") => Command::Spawn {
+                kind: parse_kind(words.next().ok_or("missing kind")?)?,
+                x: parse_number(words.next(), "x")?,
+                y: parse_number(words.next(), "y")?,
+            },
+            Some("save") => Command::Save(words.next().ok_or("missing path")?.into()),
+            Some("export_population") => {
+                Command::ExportPopulation(words.next().ok_or("missing path")?.into())
+            }
+            Some("quit") | Some("exit") => Command::Quit,
+            Some(other) => return Err(format!("unknown command `{}`", other)),
+            None => return Err("empty command".to_string()),
+        };
+
+        match words.next() {
+            Some(extra) => Err(format!("unexpected argument `{}`", extra)),
+            None => Ok(command),
+        }
+    }
+}
+
+fn is_finished(app: &App) -> bool {
+    *app.world().resource::<State<AppState>>().get() == AppState::Finished
+}
+
+/// Applies `command` to `app` and returns the text to show the user.
+pub fn dispatch(app: &mut App, command: &Command) -> String {
+    if app.plugins_state() != PluginsState::Cleaned {
+        app.finish();
+        app.cleanup();
+    }
+
+    match command {
+        Command::Step(n) => step(app, *n),
+        Command::Stats => stats(app),
+        Command::Show { x, y } => show(app, *x, *y),
+        Command::Top {
+            kind,
+            trait_name,
+            k,
+        } => top(app, *kind, trait_name, *k),
+        Command::Spawn { kind, x, y } => spawn(app, *kind, *x, *y),
+        Command::Save(path) => save(app, path),
+        Command::ExportPopulation(path) => export_population(app, path),
+        Command::Quit => "bye".to_string(),
+    }
+}
+
+fn step(app: &mut App, n: usize) -> String {
+    for _ in 0..n {
+        if is_finished(app) {
+            break;
+        }
+        app.update();
+    }
+
+    let generation = app.world().resource::<Generation>().0;
+    if is_finished(app) {
+        format!("generation {} (generation limit reached)", generation)
+    } else {
+        format!("generation {}", generation)
+    }
+}
+
+fn trait_averages(out: &mut String, names: &[&str], traits: impl Iterator<Item = Vec<f32>>) {
+    let mut sums = vec![0.0; names.len()];
+    let mut count = 0;
+    for values in traits {
+        count += 1;
+        for (sum, value) in sums.iter_mut().zip(values) {
+            *sum += value;
+        }
+    }
+    for (name, sum) in names.iter().zip(sums) {
+        let _ = writeln!(out, "  avg {}: {:.3}", name, sum / count.max(1) as f32);
+    }
+}
+
+fn stats(app: &mut App) -> String {
+    let generation = app.world().resource::<Generation>().0;
+    let food = app.world().resource::<FoodGrid>().values();
+    let average_food = food.iter().sum::<f32>() / food.len().max(1) as f32;
+    let species = app.world().resource::<Config>().species();
+
+    let world = app.world_mut();
+    let members: Vec<_> = world
+        .query_filtered::<Option<&SpeciesId>, (With<Organism>, Active)>()
+        .iter(world)
+        .map(SpeciesId::of)
+        .collect();
+    // only broken down when there is a second species
+    let species_counts: Vec<_> = match species.len() {
+        1 => Vec::new(),
+        _ => species
+            .iter()
+            .map(|species| {
+                let count = members.iter().filter(|&of| of == species).count();
+                format!("species {}: {}", species.0, count)
+            })
+            .collect(),
+    };
+    let organisms: Vec<_> = world
+        .query_filtered::<&Organism, Active>()
+        .iter(world)
+        .map(|organism| organism.traits().into_values().collect())
+        .collect();
+    let predators: Vec<_> = world
+        .query_filtered::<&Predator, Active>()
+        .iter(world)
+        .map(|predator| predator.traits().into_values().collect())
+        .collect();
+
+    let mut out = format!(
+        "generation {}\naverage food: {:.2}\n",
+        generation, average_food
+    );
+    let _ = writeln!(out, "organisms: {}", organisms.len());
+    for species in species_counts {
+        let _ = writeln!(out, "  {}", species);
+    }
+    let mut organism_names = Organism::TRAITS;
+    organism_names.sort_unstable();
+    trait_averages(&mut out, &organism_names, organisms.into_iter());
+    let _ = writeln!(out, "predators: {}", predators.len());
+    let mut predator_names = Predator::TRAITS;
+    predator_names.sort_unstable();
+    trait_averages(&mut out, &predator_names, predators.into_iter());
+    out.trim_end().to_string()
+}
+
+fn out_of_bounds(world: &World, x: usize, y: usize) -> Option<String> {
+    (x >= world.width || y >= world.height).then(|| {
+        format!(
+            "({}, {}) is outside the {}x{} world",
+            x, y, world.width, world.height
+        )
+    })
+}
+
+fn show(app: &mut App, x: usize, y: usize) -> String {
+    let world = app.world().resource::<World>();
+    if let Some(err) = out_of_bounds(world, x, y) {
+        return err;
+    }
+    let tile = world.tile(x, y);
+    let food = app.world().resource::<FoodGrid>().get(y * world.width + x);
+    let mut out = format!(
+        "tile ({}, {}): {} temperature {:.1} humidity {:.2} food {:.2}{}",
+        x,
+        y,
+        tile.biome,
+        tile.temperature,
+        tile.humidity,
+        food,
+        if tile.reserve { " [reserve]" } else { "" }
+    );
+
+    let ecs = app.world_mut();
+    let mut organisms = ecs.query_filtered::<(&Identity, &Organism, &Position), Active>();
+    for (identity, organism, _) in organisms
+        .iter(ecs)
+        .filter(|(_, _, position)| position.x == x && position.y == y)
+    {
+        let _ = write!(
+            out,
+            "\n  organism #{} energy {:.2} {:?}",
+            identity.id,
+            organism.energy,
+            organism.traits()
+        );
+    }
+    let mut predators = ecs.query_filtered::<(&Identity, &Predator, &Position), Active>();
+    for (identity, predator, _) in predators
+        .iter(ecs)
+        .filter(|(_, _, position)| position.x == x && position.y == y)
+    {
+        let _ = write!(
+            out,
+            "\n  predator #{} energy {:.2} {:?}",
+            identity.id,
+            predator.energy,
+            predator.traits()
+        );
+    }
+    out
+}
+
+fn top(app: &mut App, kind: EntityKind, trait_name: &str, k: usize) -> String {
+    let names: &[&str] = match kind {
+        EntityKind::Organism => &Organism::TRAITS,
+        EntityKind::Predator => &Predator::TRAITS,
+    };
+    if !names.contains(&trait_name) {
+        return format!(
+            "unknown trait `{}`, expected one of: {}",
+            trait_name,
+            names.join(", ")
+        );
+    }
+
+    let world = app.world_mut();
+    let mut ranked: Vec<(u64, f32)> = match kind {
+        EntityKind::Organism => world
+            .query_filtered::<(&Identity, &Organism), Active>()
+            .iter(world)
+            .map(|(identity, organism)| (identity.id, organism.traits()[trait_name]))
+            .collect(),
+        EntityKind::Predator => world
+            .query_filtered::<(&Identity, &Predator), Active>()
+            .iter(world)
+            .map(|(identity, predator)| (identity.id, predator.traits()[trait_name]))
+            .collect(),
+    };
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    if ranked.is_empty() {
+        return format!("no {:?} alive", kind);
+    }
+    ranked
+        .iter()
+        .take(k)
+        .enumerate()
+        .map(|(rank, (id, value))| format!("{}. #{} {} {:.3}", rank + 1, id, trait_name, value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn spawn(app: &mut App, kind: EntityKind, x: usize, y: usize) -> String {
+    let world = app.world().resource::<World>();
+    if let Some(err) = out_of_bounds(world, x, y) {
+        return err;
+    }
+    let biome = world.tile(x, y).biome;
+    let config = app.world().resource::<Config>().clone();
+    let generation = app.world().resource::<Generation>().0;
+
+    let ecs = app.world_mut();
+    // the traits come from the start of the newcomer's own stream
+    let id = ecs.resource_mut::<NextId>().allocate();
+    let mut rng = ecs.resource::<RngStreams>().for_entity(id, generation);
+    let biome_tolerance = get_biome_tolerance(biome, &mut rng);
+    let morph = random_morph(&config, &mut rng);
+    let common = (
+        Position { x, y },
+        EntityRng(rng),
+        Identity::founder(id, generation),
+    );
+    match kind {
+        EntityKind::Organism => {
+            let mut organism = founder_organism(&config, biome_tolerance);
+            organism.morph = morph;
+            ecs.spawn((organism, common))
+        }
+        EntityKind::Predator => ecs.spawn((founder_predator(&config), common)),
+    };
+
+    format!("spawned {:?} #{} at ({}, {})", kind, id, x, y)
+}
+
+fn save(app: &mut App, path: &Path) -> String {
+    let provenance = Provenance::of_app(app);
+    let config = app.world().resource::<Config>().clone();
+    let ecs = app.world_mut();
+    let organisms = ecs
+        .query_filtered::<(&Organism, &Position, Option<&SpeciesId>), Active>()
+        .iter(ecs)
+        .map(|(organism, position, species)| {
+            OrganismWithPosition::new(organism, SpeciesId::of(species), *position, &config)
+        })
+        .collect();
+    let predators = ecs
+        .query_filtered::<(&Predator, &Position), Active>()
+        .iter(ecs)
+        .map(|(predator, position)| PredatorWithPosition::new(predator, *position, &config))
+        .collect();
+
+    let export = ExportData {
+        schema_version: SCHEMA_VERSION,
+        run_id: provenance.run_id.clone(),
+        organisms,
+        predators,
+        world: Cow::Borrowed(ecs.resource::<World>()),
+        food: Cow::Borrowed(ecs.resource::<FoodGrid>().values()),
+        generation: ecs.resource::<Generation>().0,
+    };
+
+    let result = serde_json::to_string(&export)
+        .map_err(|err| err.to_string())
+        .and_then(|json| write_artifact(path, json, &provenance).map_err(|err| err.to_string()));
+    match result {
+        Ok(()) => format!("saved snapshot to {}", path.display()),
+        Err(err) => format!("failed to save {}: {}", path.display(), err),
+    }
+}
+
+fn export_population(app: &mut App, path: &Path) -> String {
+    let generation = app.world().resource::<Generation>().0;
+    let bank = SeedBank::from_ecs(app.world_mut(), generation);
+    match bank.save(path, &Provenance::of_app(app)) {
+        Ok(()) => format!(
+            "exported {} organisms and {} predators to {}",
+            bank.organisms.len(),
+            bank.predators.len(),
+            path.display()
+        ),
+        Err(err) => format!("failed to export {}: {}", path.display(), err),
+    }
+}
+
+/// Reads commands from stdin until `quit` or end of input, then finishes the
+/// simulation so the logs are flushed.
+pub fn run(app: &mut App) {
+    println!("{}", USAGE);
+    let stdin = io::stdin();
+    let mut line = String::new();
+    loop {
+        print!("> ");
+        io::stdout().flush().ok();
+
+        line.clear();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match line.parse::<Command>() {
+            Ok(Command::Quit) => break,
+            Ok(command) => println!("{}", dispatch(app, &command)),
+            Err(err) => println!("{}\n{}", err, USAGE),
+        }
+    }
+
+    if app.plugins_state() == PluginsState::Cleaned && !is_finished(app) {
+        app.world_mut()
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::Finished);
+        app.update();
+    }
+}
@@ -0,0 +1,204 @@
+//! Random access to the world records of a finished run for scrubbing through it,
+//! through the `index.json` written next to `world_data.jsonl`.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{layer_config, EFFECTIVE_CONFIG_FILE};
+use crate::provenance::{write_artifact, Provenance};
+use crate::resources::Config;
+use crate::schema::{
+    build_world_index, read_world_index, read_world_record_at, ExportData, SchemaError,
+    WorldIndexEntry,
+};
+use crate::utils::safe_write;
+
+pub struct Replay {
+    log: PathBuf,
+    index: Vec<WorldIndexEntry>,
+}
+
+impl Replay {
+    /// Opens the world log in `log_dir`. Logs written without an index, or with one
+    /// cut short by a crash, are indexed now and the index saved next to them, with
+    /// the provenance of the log when it has one.
+    pub fn open(log_dir: &Path) -> Result<Self, SchemaError> {
+        let log = log_dir.join("world_data.jsonl");
+        let index_path = log_dir.join("index.json");
+        let index = match read_world_index(&index_path) {
+            Ok(index) => index,
+            Err(_) => {
+                let index = build_world_index(&log)?;
+                let json = serde_json::to_string(&index).expect("Failed to serialize index");
+                let written = match Provenance::read(&log) {
+                    Ok(Some(provenance)) => write_artifact(&index_path, json, &provenance),
+                    _ => safe_write(&index_path, json),
+                };
+                if let Err(err) = written {
+                    eprintln!("Failed to write {}: {}", index_path.display(), err);
+                }
+                index
+            }
+        };
+        Ok(Self { log, index })
+    }
+
+    /// Every recorded generation in order, with the numbers a timeline is annotated
+    /// with.
+    pub fn timeline(&self) -> &[WorldIndexEntry] {
+        &self.index
+    }
+
+    /// The record of `generation`, read without going through the ones before it.
+    /// `None` when the generation was not logged.
+    pub fn seek(&self, generation: usize) -> Result<Option<ExportData<'static>>, SchemaError> {
+        let Ok(i) = self
+            .index
+            .binary_search_by_key(&generation, |entry| entry.generation)
+        else {
+            return Ok(None);
+        };
+        read_world_record_at(&self.log, self.index[i].offset).map(Some)
+    }
+
+    /// The config `record` was written with, from the `effective_config.toml` next
+    /// to the log. `None` without one, or with one of another run, which records
+    /// from before they named their run always are.
+    pub fn config(&self, record: &ExportData) -> Result<Option<Config>, Box<dyn Error>> {
+        let path = self.log.with_file_name(EFFECTIVE_CONFIG_FILE);
+        if record.run_id.is_empty() || !path.exists() {
+            return Ok(None);
+        }
+        match Provenance::read(&path)? {
+            Some(provenance) if provenance.run_id == record.run_id => {
+                let (config, _) = layer_config(None, Some(&fs::read_to_string(&path)?), &[])?;
+                Ok(Some(config))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The first generation where `count` peaked, say the predators.
+    pub fn peak(&self, count: impl Fn(&WorldIndexEntry) -> usize) -> Option<usize> {
+        self.index
+            .iter()
+            .rev()
+            .max_by_key(|entry| count(entry))
+            .map(|entry| entry.generation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::components::{KillMemory, Position, Predator, Stance};
+    use crate::provenance::RunId;
+    use crate::resources::World;
+    use crate::schema::{read_world_records, PredatorWithPosition, SCHEMA_VERSION};
+    use crate::utils::default_config;
+
+    /// A log of generations 0, 5, ..., 45 with one more predator each time.
+    fn write_log(dir: &Path) {
+        let config = default_config();
+        let (world, food) = World::new(3, 3, 1);
+        let predator = Predator {
+            energy: 10.0,
+            speed: 1.0,
+            size: 1.0,
+            reproduction_threshold: 100.0,
+            hunting_efficiency: 1.0,
+            satiation_threshold: 50.0,
+            reproduction_cooldown: 0.0,
+            preferred_temperature: 20.0,
+            hunt_cooldown_ticks: 0.0,
+            hunt_cooldown_remaining: 0,
+            patience: 0.0,
+            stance: Stance::Roaming,
+            heading: 0.0,
+            aggression: 0.0,
+            breeding_flexibility: 0.0,
+            recent_kills: KillMemory::default(),
+        };
+        let lines: Vec<String> = (0..10)
+            .map(|i| {
+                let record = ExportData {
+                    schema_version: SCHEMA_VERSION,
+                    run_id: "1-0".into(),
+                    organisms: Vec::new(),
+                    predators: (0..i)
+                        .map(|_| {
+                            PredatorWithPosition::new(&predator, Position { x: 1, y: 1 }, &config)
+                        })
+                        .collect(),
+                    world: Cow::Borrowed(&world),
+                    food: Cow::Owned(vec![i as f32; food.len()]),
+                    generation: i * 5,
+                };
+                serde_json::to_string(&record).unwrap()
+            })
+            .collect();
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("world_data.jsonl"), lines.join("\n") + "\n").unwrap();
+        fs::remove_file(dir.join("index.json")).ok();
+    }
+
+    #[test]
+    fn seeking_matches_sequential_reads_and_builds_missing_index() {
+        let dir = std::env::temp_dir().join("evolution_replay_index");
+        write_log(&dir);
+        let records = read_world_records(&dir.join("world_data.jsonl")).unwrap();
+
+        let replay = Replay::open(&dir).unwrap();
+        assert!(dir.join("index.json").exists());
+        // the saved index is used as is the next time
+        let reopened = Replay::open(&dir).unwrap();
+        assert_eq!(reopened.timeline(), replay.timeline());
+
+        for generation in [0, 5, 25, 45] {
+            let sought = replay.seek(generation).unwrap().unwrap();
+            let read = records
+                .iter()
+                .find(|record| record.generation == generation)
+                .unwrap();
+            assert_eq!(sought.generation, read.generation);
+            assert_eq!(sought.predators.len(), read.predators.len());
+            assert_eq!(sought.food, read.food);
+        }
+        assert!(replay.seek(7).unwrap().is_none());
+        assert_eq!(replay.peak(|entry| entry.predators), Some(45));
+        assert_eq!(replay.timeline()[3].average_food, 3.0);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn the_config_comes_from_the_file_of_the_same_run() {
+        let dir = std::env::temp_dir().join("evolution_replay_config");
+        fs::remove_dir_all(&dir).ok();
+        write_log(&dir);
+        let replay = Replay::open(&dir).unwrap();
+        let mut record = replay.seek(5).unwrap().unwrap();
+        assert_eq!(record.run_id, "1-0");
+        assert!(replay.config(&record).unwrap().is_none());
+
+        let mut config = default_config();
+        config.world.width = 13;
+        let path = dir.join(EFFECTIVE_CONFIG_FILE);
+        config
+            .save(&path, &Provenance::new(&RunId("1-0".into()), 1, 0, None))
+            .unwrap();
+        let read = replay.config(&record).unwrap().unwrap();
+        assert_eq!(read.world.width, 13);
+
+        // another run's config, or a record from before run ids, gets none
+        config
+            .save(&path, &Provenance::new(&RunId("2-0".into()), 1, 0, None))
+            .unwrap();
+        assert!(replay.config(&record).unwrap().is_none());
+        record.run_id.clear();
+        assert!(replay.config(&record).unwrap().is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,12 +1,19 @@
+use std::collections::{BTreeMap, VecDeque};
 use std::fmt::Display;
+use std::path::PathBuf;
 
 use bevy::prelude::*;
+use bevy::utils::hashbrown::{HashMap, HashSet};
+use bevy::utils::{Duration, Instant};
 use noise::NoiseFn;
 use noise::Perlin;
 use rand::prelude::*;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::components::EntityKind;
+use crate::schema::{GenerationStats, TraceRecord};
+
 #[derive(States, Debug, Clone, Eq, PartialEq, Hash, Default)]
 pub enum AppState {
     #[default]
@@ -14,259 +21,3054 @@ pub enum AppState {
     Finished,
 }
 
-#[derive(Deserialize, Debug, Serialize, Clone)]
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
 pub struct BiomeDataConfig {
+    #[serde(default = "default_food_availability")]
     pub food_availability: f32,
+    #[serde(default = "default_max_food_availability")]
     pub max_food_availability: f32,
+    /// Multiplier on predator energy upkeep while standing in this biome.
+    #[serde(default = "default_predator_decay_factor")]
+    pub predator_decay_factor: f32,
+    /// Fraction of `food_energy_conversion` an organism gets from this biome's food;
+    /// the rest is lost to digestion.
+    #[serde(default = "default_food_nutrition")]
+    pub food_nutrition: f32,
+    /// Multiplier on how far a predator standing in this biome sees prey, e.g. below
+    /// 1 for forest cover and above 1 for open grassland.
+    #[serde(default = "default_predator_vision")]
+    pub predator_vision: f32,
+    /// Multiplier on a predator's chance to catch the prey it attacks in this biome.
+    #[serde(default = "default_predator_catch")]
+    pub predator_catch: f32,
+    /// Energy an organism gains each tick just for standing in this biome, times its
+    /// tolerance to it, or loses when negative, divided by its tolerance. Left out,
+    /// the biome keeps [`Biome::default_passive_energy_delta`]. Water drowns
+    /// organisms whatever it is.
+    #[serde(default)]
+    pub passive_energy_delta: Option<f32>,
+    /// Base cost of an organism stepping onto this biome, divided by its tolerance to
+    /// it before the food, predators and noise of the tile are weighed in. Left out,
+    /// the biome keeps [`Biome::default_organism_movement_cost`].
+    #[serde(default)]
+    pub organism_movement_cost: Option<f32>,
+    /// Base cost of a predator stepping onto this biome, times its upkeep there. Left
+    /// out, the biome keeps [`Biome::default_predator_movement_cost`].
+    #[serde(default)]
+    pub predator_movement_cost: Option<f32>,
+}
+
+impl BiomeDataConfig {
+    /// The table a config without one gets for `biome`.
+    pub fn defaults(biome: Biome) -> Self {
+        Self {
+            food_availability: default_food_availability(),
+            max_food_availability: default_max_food_availability(),
+            predator_decay_factor: default_predator_decay_factor(),
+            food_nutrition: default_food_nutrition(),
+            predator_vision: default_predator_vision(),
+            predator_catch: default_predator_catch(),
+            passive_energy_delta: Some(biome.default_passive_energy_delta()),
+            organism_movement_cost: Some(biome.default_organism_movement_cost()),
+            predator_movement_cost: Some(biome.default_predator_movement_cost()),
+        }
+    }
+}
+
+fn default_food_availability() -> f32 {
+    1.0
+}
+
+fn default_max_food_availability() -> f32 {
+    100.0
+}
+
+fn default_predator_decay_factor() -> f32 {
+    1.0
+}
+
+fn default_food_nutrition() -> f32 {
+    1.0
+}
+
+fn default_predator_vision() -> f32 {
+    1.0
 }
 
-#[derive(Deserialize, Debug, Serialize, Clone)]
+fn default_predator_catch() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
 pub struct WorldConfig {
     pub width: usize,
     pub height: usize,
     pub seed: u64,
+    /// Left out, a run is headless unless the `gui` feature is on.
+    #[serde(default = "default_headless")]
     pub headless: bool,
+    #[serde(default)]
     pub printing: bool,
+    /// Least severe log messages shown, unless `RUST_LOG` says otherwise; see
+    /// [`WorldConfig::verbosity`] for the level without one.
+    #[serde(default)]
+    pub log_level: Option<LogLevel>,
+    #[serde(default)]
     pub generation_limit: Option<usize>,
+    /// Seeds a headless batch runs the config with one after another, each to the
+    /// `generation_limit` and logging into its own `seed_NNNN/`; see [`crate::batch`].
+    #[serde(default)]
+    pub seeds: Vec<u64>,
+    /// Runs of a batch with the seeds `seed`, `seed + 1`, ..., in place of `seeds`.
+    #[serde(default)]
+    pub replicates: usize,
+    /// Generations between time-lapse frames saved to `frames/`; GUI mode only.
+    #[serde(default)]
+    pub screenshot_interval: Option<usize>,
+    /// Most generations simulated per second, for presentation runs; as fast as
+    /// possible without one. Changed at runtime with `+` and `-`.
+    #[serde(default)]
+    pub target_ticks_per_second: Option<f32>,
+    /// Seed bank the founders are drawn from instead of the `initial_*` traits, see
+    /// [`crate::seed_bank`].
+    #[serde(default)]
+    pub seed_population: Option<PathBuf>,
+    #[serde(default = "default_max_total_entities")]
     pub max_total_entities: usize,
+    #[serde(default = "default_forest")]
     pub forest: BiomeDataConfig,
+    #[serde(default = "default_desert")]
     pub desert: BiomeDataConfig,
+    #[serde(default = "default_water")]
     pub water: BiomeDataConfig,
+    #[serde(default = "default_grassland")]
     pub grassland: BiomeDataConfig,
+    #[serde(default)]
+    pub desertification: DesertificationConfig,
+    #[serde(default)]
+    pub catastrophes: CatastropheConfig,
+    #[serde(default)]
+    pub seasons: SeasonsConfig,
+    #[serde(default)]
+    pub food_patches: FoodPatchConfig,
+    #[serde(default)]
+    pub reserves: ReserveConfig,
+    #[serde(default)]
+    pub biome_model: BiomeModel,
+    #[serde(default)]
+    pub whittaker: WhittakerConfig,
+    #[serde(default)]
+    pub display: DisplayConfig,
+    #[serde(default)]
+    pub density_policy: DensityPolicy,
+    /// Most initial organisms and predators together per tile that is not water.
+    #[serde(default = "default_max_initial_density")]
+    pub max_initial_density: f32,
+    #[serde(default)]
+    pub offspring_placement: OffspringPlacement,
+    /// Farthest `nearest_free` looks for a tile, in steps.
+    #[serde(default = "default_offspring_search_radius")]
+    pub offspring_search_radius: usize,
+    #[serde(default)]
+    pub reproduction_model: ReproductionModel,
+    /// Energy above the threshold over which the reproduction chance of the
+    /// `probabilistic` model rises from 50% to about 73%.
+    #[serde(default = "default_reproduction_scale")]
+    pub reproduction_scale: f32,
+    /// Most dead organisms and, separately, predators kept as [`Inactive`] entities
+    /// for births to reuse; 0 despawns the dead.
+    ///
+    /// [`Inactive`]: crate::components::Inactive
+    #[serde(default)]
+    pub entity_pool_size: usize,
+    /// Whether organisms gain or lose the `passive_energy_delta` of their biome each
+    /// tick; `false` leaves food as their only source of energy.
+    #[serde(default = "default_passive_energy")]
+    pub passive_energy: bool,
 }
 
-#[derive(Deserialize, Debug, Serialize, Clone)]
-pub struct OrganismConfig {
-    pub initial_organisms: usize,
-    pub initial_organism_energy: f32,
-    pub initial_organism_speed: f32,
-    pub initial_organism_size: f32,
-    pub initial_organism_reproduction_threshold: f32,
-    pub organism_mutability: f32,
-    pub overcrowding_threshold_for_organisms: usize,
-    pub organism_reproduction_cooldown: f32,
+/// Severity of log messages, from the run-stopping to the tick-by-tick.
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    /// Something that degrades the run, like the entity cap turning births away,
+    /// traits clamped at their bounds or stats that could not be saved.
+    Warn,
+    /// A summary of each generation, and catastrophes and food patches.
+    Info,
+    /// Each individual that dies of overcrowding.
+    Debug,
+    Trace,
 }
 
-#[derive(Deserialize, Debug, Serialize, Clone)]
-pub struct PredatorConfig {
-    pub initial_predators: usize,
-    pub initial_predator_energy: f32,
-    pub initial_predator_speed: f32,
-    pub initial_predator_size: f32,
-    pub initial_predator_reproduction_threshold: f32,
-    pub initial_predator_hunting_efficiency: f32,
-    pub initial_predator_satiation_threshold: f32,
-    pub predator_mutability: f32,
-    pub overcrowding_threshold_for_predators: usize,
-    pub max_predator_energy: f32,
-    pub predator_energy_decay_rate: f32,
-    pub predator_reproduction_cooldown: f32,
-    #[serde(default = "default_predator_seek_radius")]
-    pub predator_seek_radius: usize,
+impl From<LogLevel> for bevy::log::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => Self::ERROR,
+            LogLevel::Warn => Self::WARN,
+            LogLevel::Info => Self::INFO,
+            LogLevel::Debug => Self::DEBUG,
+            LogLevel::Trace => Self::TRACE,
+        }
+    }
 }
 
-fn default_predator_seek_radius() -> usize {
+impl Default for WorldConfig {
+    /// A 10x10 world with seed 0 and every other field at its serde default.
+    fn default() -> Self {
+        Self {
+            width: 10,
+            height: 10,
+            seed: 0,
+            headless: default_headless(),
+            printing: false,
+            log_level: None,
+            generation_limit: None,
+            seeds: Vec::new(),
+            replicates: 0,
+            screenshot_interval: None,
+            target_ticks_per_second: None,
+            seed_population: None,
+            max_total_entities: default_max_total_entities(),
+            forest: default_forest(),
+            desert: default_desert(),
+            water: default_water(),
+            grassland: default_grassland(),
+            desertification: DesertificationConfig::default(),
+            catastrophes: CatastropheConfig::default(),
+            seasons: SeasonsConfig::default(),
+            food_patches: FoodPatchConfig::default(),
+            reserves: ReserveConfig::default(),
+            biome_model: BiomeModel::default(),
+            whittaker: WhittakerConfig::default(),
+            display: DisplayConfig::default(),
+            density_policy: DensityPolicy::default(),
+            max_initial_density: default_max_initial_density(),
+            offspring_placement: OffspringPlacement::default(),
+            offspring_search_radius: default_offspring_search_radius(),
+            reproduction_model: ReproductionModel::default(),
+            reproduction_scale: default_reproduction_scale(),
+            entity_pool_size: 0,
+            passive_energy: default_passive_energy(),
+        }
+    }
+}
+
+fn default_headless() -> bool {
+    !cfg!(feature = "gui")
+}
+
+fn default_max_total_entities() -> usize {
+    1000
+}
+
+fn default_forest() -> BiomeDataConfig {
+    BiomeDataConfig::defaults(Biome::Forest)
+}
+
+fn default_desert() -> BiomeDataConfig {
+    BiomeDataConfig::defaults(Biome::Desert)
+}
+
+fn default_water() -> BiomeDataConfig {
+    BiomeDataConfig::defaults(Biome::Water)
+}
+
+fn default_grassland() -> BiomeDataConfig {
+    BiomeDataConfig::defaults(Biome::Grassland)
+}
+
+fn default_passive_energy() -> bool {
+    true
+}
+
+fn default_max_initial_density() -> f32 {
+    5.0
+}
+
+/// What happens when the initial populations are denser than `max_initial_density`.
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DensityPolicy {
+    /// Refuse to start.
+    Error,
+    /// Shrink both populations in proportion until they fit, with a warning.
+    Cap,
+    /// Start as configured.
+    #[default]
+    Allow,
+}
+
+fn default_offspring_search_radius() -> usize {
     3
 }
 
-#[derive(Deserialize, Debug, Serialize, Clone)]
-pub struct LoggingConfig {
-    pub log_data: bool,
-    #[serde(default = "default_log_interval")]
-    pub log_interval: usize,
+/// Where offspring are placed around their parent.
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OffspringPlacement {
+    /// A random tile at most one step away, the parent's own included.
+    #[default]
+    AdjacentRandom,
+    /// The closest tile within `offspring_search_radius` steps that takes one more
+    /// individual without overcrowding; a random adjacent tile when none does.
+    NearestFree,
+    /// A random tile at most the parent's rounded `dispersal` steps away. Predators
+    /// have no dispersal trait and are placed as with `adjacent_random`.
+    DispersalTrait,
 }
 
-fn default_log_interval() -> usize {
-    1
+fn default_reproduction_scale() -> f32 {
+    10.0
 }
 
-#[derive(Deserialize, Debug, Resource, Serialize, Clone)]
-pub struct Config {
-    #[serde(flatten)]
-    pub world: WorldConfig,
-    #[serde(flatten)]
-    pub organism: OrganismConfig,
-    #[serde(flatten)]
-    pub predator: PredatorConfig,
-    #[serde(flatten)]
-    pub logging: LoggingConfig,
+/// How an individual's energy decides whether it reproduces in a tick.
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReproductionModel {
+    /// Always once its energy is above its reproduction threshold.
+    #[default]
+    Threshold,
+    /// With a chance rising logistically with its energy, even odds at its
+    /// reproduction threshold.
+    Probabilistic,
 }
 
-#[derive(Resource, Default)]
-pub struct PopulationCount {
-    pub organisms: usize,
-    pub predators: usize,
+/// How the organisms on a tile share its food, each wanting `size * 0.2 * speed`.
+/// They all eat their fill when the food covers it.
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FoodAllocation {
+    /// Larger organisms eat their fill first, equal sizes in order of id.
+    #[default]
+    PriorityBySize,
+    /// Each gets the same fraction of its need.
+    Proportional,
+    /// Each gets the same amount, but never more than its need, which the
+    /// others share.
+    EqualSplit,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Copy)]
-pub enum Biome {
-    Forest,
-    Desert,
-    Water,
-    Grassland,
+/// Smallest and largest `entity_scale`.
+pub const ENTITY_SCALE_BOUNDS: (u32, u32) = (1, 8);
+
+/// What the GUI draws when it starts; the layers are toggled with `1`, `2` and `3`
+/// and the scale changed with `[` and `]` while it runs. `Space` pauses it, and
+/// while paused `Left` and `Right` step through the history.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct DisplayConfig {
+    pub show_tiles: bool,
+    pub show_organisms: bool,
+    pub show_predators: bool,
+    /// Side in tiles of the square the organisms and predators of a tile are drawn
+    /// over, centered on it. Purely visual; the `size` trait is untouched.
+    pub entity_scale: u32,
+    /// Draw the food of every tile as up to `food_dots_per_tile` dots. Each dot is an
+    /// entity, so the mode refuses worlds of more than `food_dots_max_tiles` tiles.
+    pub food_dots: bool,
+    pub food_dots_per_tile: u8,
+    pub food_dots_max_tiles: usize,
+    /// States kept in memory to step back through while paused, one every
+    /// `history_interval` generations; each holds every individual and the grids of
+    /// the world. 0 keeps none.
+    pub history_snapshots: usize,
+    pub history_interval: usize,
 }
 
-impl Display for Biome {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Biome::Forest => write!(f, "Forest"),
-            Biome::Desert => write!(f, "Desert"),
-            Biome::Water => write!(f, "Water"),
-            Biome::Grassland => write!(f, "Grassland"),
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            show_tiles: true,
+            show_organisms: true,
+            show_predators: true,
+            entity_scale: 1,
+            food_dots: false,
+            food_dots_per_tile: 4,
+            food_dots_max_tiles: 10_000,
+            history_snapshots: 20,
+            history_interval: 10,
         }
     }
 }
 
-impl Biome {
-    pub fn idx(self) -> usize {
-        self as usize
-        // Forest=0, Desert=1, Water=2, Grassland=3
+/// How world generation assigns biomes to tiles.
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BiomeModel {
+    /// Thresholds on a single Perlin field.
+    #[default]
+    Threshold,
+    /// A lookup table over independent temperature and humidity fields.
+    Whittaker,
+}
+
+/// Settings of the `whittaker` biome model. `table` rows go from cold to hot and its
+/// columns from dry to wet; each row may have its own number of humidity bins.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct WhittakerConfig {
+    pub noise_scale: f64,
+    pub min_temperature: f32,
+    pub max_temperature: f32,
+    /// Share of a tile's temperature decided by its distance from the equator rather
+    /// than by noise.
+    pub latitude_weight: f32,
+    pub table: Vec<Vec<Biome>>,
+}
+
+impl Default for WhittakerConfig {
+    fn default() -> Self {
+        Self {
+            noise_scale: 10.0,
+            min_temperature: -10.0,
+            max_temperature: 40.0,
+            latitude_weight: 0.7,
+            table: vec![
+                vec![Biome::Grassland, Biome::Forest, Biome::Water],
+                vec![Biome::Grassland, Biome::Forest, Biome::Water],
+                vec![Biome::Desert, Biome::Grassland, Biome::Forest],
+            ],
+        }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct Tile {
-    pub biome: Biome,
-    pub temperature: f32,
-    pub humidity: f32,
+/// Looks up the biome for a normalized `temperature` and `humidity` in `[0, 1]`.
+/// Values on or beyond the edges fall into the outermost bins. An empty table or
+/// row, which validation rejects but a table built in code may have, gives grassland.
+pub fn whittaker_biome(table: &[Vec<Biome>], temperature: f32, humidity: f32) -> Biome {
+    let bin = |value: f32, bins: usize| {
+        ((value.clamp(0.0, 1.0) * bins as f32) as usize).min(bins.saturating_sub(1))
+    };
+    table
+        .get(bin(temperature, table.len()))
+        .and_then(|row| row.get(bin(humidity, row.len())))
+        .copied()
+        .unwrap_or(Biome::Grassland)
 }
 
-#[derive(Debug, Resource, Serialize, Clone)]
-pub struct World {
+/// A `width` x `height` block of tiles with its top-left corner at `(x, y)`.
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq)]
+pub struct ReserveRect {
+    pub x: usize,
+    pub y: usize,
     pub width: usize,
     pub height: usize,
-    pub grid: Vec<Tile>,
 }
 
-impl World {
-    pub fn new(width: usize, height: usize, random_seed: u64) -> (Self, FoodGrid) {
-        let mut rng = StdRng::seed_from_u64(random_seed);
-        let seed = rng.gen::<u32>();
+/// Protected areas where predators cannot kill. Besides the listed `rectangles`,
+/// every tile is marked with probability `random_fraction` at world generation.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct ReserveConfig {
+    pub rectangles: Vec<ReserveRect>,
+    pub random_fraction: f32,
+    /// Added to a predator's cost of wandering onto a reserve tile.
+    pub predator_movement_cost: f32,
+}
 
-        let perlin = Perlin::new(seed);
-        let scale = 10.0;
+/// Rare meteors and floods. `flood_share` is the fraction of catastrophes that are
+/// floods; floods with `flood_converts_biomes` turn shoreline tiles into water.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct CatastropheConfig {
+    pub probability: f32,
+    pub min_radius: usize,
+    pub max_radius: usize,
+    pub mortality: f32,
+    pub food_destruction: f32,
+    pub flood_share: f32,
+    pub flood_converts_biomes: bool,
+}
 
-        let mut grid = Vec::with_capacity(width * height);
-        let mut food = Vec::with_capacity(width * height);
+impl Default for CatastropheConfig {
+    fn default() -> Self {
+        Self {
+            probability: 0.0,
+            min_radius: 3,
+            max_radius: 8,
+            mortality: 0.8,
+            food_destruction: 1.0,
+            flood_share: 0.5,
+            flood_converts_biomes: false,
+        }
+    }
+}
 
-        for y in 0..height {
-            for x in 0..width {
-                let noise_value = perlin.get([x as f64 / scale, y as f64 / scale]);
+/// Rare, rich, temporary food patches. With `probability` per generation, the land
+/// tiles within `radius` of a random land tile get `bonus_food` on top of their
+/// biome's maximum, which lasts for `duration` generations; afterwards the excess
+/// loses `decay_rate` of itself every generation until the tile is back at its
+/// maximum.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct FoodPatchConfig {
+    pub probability: f32,
+    pub radius: usize,
+    pub bonus_food: f32,
+    pub duration: usize,
+    pub decay_rate: f32,
+}
 
-                let biome = if noise_value < -0.3 {
-                    Biome::Water
-                } else if noise_value < -0.1 {
-                    Biome::Desert
-                } else if noise_value < 0.5 {
-                    Biome::Grassland
-                } else {
-                    Biome::Forest
-                };
+impl Default for FoodPatchConfig {
+    fn default() -> Self {
+        Self {
+            probability: 0.0,
+            radius: 3,
+            bonus_food: 50.0,
+            duration: 20,
+            decay_rate: 0.2,
+        }
+    }
+}
 
-                grid.push(Tile {
-                    biome,
-                    temperature: 20.0,
-                    humidity: 0.5,
-                });
-                food.push(rng.gen_range(1.0..100.0_f32));
-            }
+impl WorldConfig {
+    /// `log_level`, or without one `debug` with `printing` on, as it printed
+    /// everything, and otherwise `info` headless and `warn` in the GUI.
+    pub fn verbosity(&self) -> LogLevel {
+        match self.log_level {
+            Some(level) => level,
+            None if self.printing => LogLevel::Debug,
+            None if self.headless => LogLevel::Info,
+            None => LogLevel::Warn,
         }
+    }
 
-        (
-            Self {
-                width,
-                height,
-                grid,
-            },
-            FoodGrid(food),
-        )
+    pub fn biome(&self, biome: Biome) -> &BiomeDataConfig {
+        match biome {
+            Biome::Forest => &self.forest,
+            Biome::Desert => &self.desert,
+            Biome::Water => &self.water,
+            Biome::Grassland => &self.grassland,
+        }
     }
 
-    #[inline]
-    pub fn tile(&self, x: usize, y: usize) -> &Tile {
-        &self.grid[y * self.width + x]
+    /// The `passive_energy_delta` of `biome`, or its default when the config leaves
+    /// it out.
+    pub fn passive_energy_delta(&self, biome: Biome) -> f32 {
+        self.biome(biome)
+            .passive_energy_delta
+            .unwrap_or_else(|| biome.default_passive_energy_delta())
+    }
+
+    /// The `organism_movement_cost` of `biome`, or its default when the config leaves
+    /// it out.
+    pub fn organism_movement_cost(&self, biome: Biome) -> f32 {
+        self.biome(biome)
+            .organism_movement_cost
+            .unwrap_or_else(|| biome.default_organism_movement_cost())
+    }
+
+    /// The `predator_movement_cost` of `biome`, or its default when the config leaves
+    /// it out.
+    pub fn predator_movement_cost(&self, biome: Biome) -> f32 {
+        self.biome(biome)
+            .predator_movement_cost
+            .unwrap_or_else(|| biome.default_predator_movement_cost())
     }
 }
 
-impl Default for World {
+/// Overgrazed grassland turns into desert once its food stays below
+/// `food_threshold` for `generations` consecutive generations.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct DesertificationConfig {
+    pub enabled: bool,
+    pub food_threshold: f32,
+    pub generations: usize,
+}
+
+impl Default for DesertificationConfig {
     fn default() -> Self {
-        World::new(10, 10, 0).0
+        Self {
+            enabled: false,
+            food_threshold: 1.0,
+            generations: 50,
+        }
     }
 }
 
-#[derive(Default, Resource, Serialize)]
-pub struct Generation(pub usize);
-
-#[derive(Resource)]
-pub struct ReproductionRng(pub SmallRng);
+/// A quarter of the year, see [`SeasonsConfig`].
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
 
-#[derive(Resource)]
-pub struct SpawnRng(pub SmallRng);
+impl Season {
+    pub const ALL: [Season; 4] = [
+        Season::Spring,
+        Season::Summer,
+        Season::Autumn,
+        Season::Winter,
+    ];
+}
 
-#[derive(Resource)]
-pub struct SpatialIndex {
-    pub cells: Vec<Vec<Entity>>,
-    pub width: usize,
+/// A year of four seasons, each `length` generations long, starting with spring.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct SeasonsConfig {
+    pub enabled: bool,
+    pub length: usize,
 }
 
-impl SpatialIndex {
-    pub fn new(width: usize, height: usize) -> Self {
+impl Default for SeasonsConfig {
+    fn default() -> Self {
         Self {
-            cells: vec![Vec::new(); width * height],
-            width,
+            enabled: false,
+            length: 25,
         }
     }
+}
 
-    #[inline]
-    pub fn get(&self, x: usize, y: usize) -> &[Entity] {
-        &self.cells[y * self.width + x]
+impl SeasonsConfig {
+    /// The season of `generation`, `None` without seasons.
+    pub fn season(&self, generation: usize) -> Option<Season> {
+        self.enabled
+            .then(|| Season::ALL[(generation / self.length.max(1)) % Season::ALL.len()])
     }
+}
 
-    #[inline]
-    pub fn insert(&mut self, x: usize, y: usize, entity: Entity) {
-        self.cells[y * self.width + x].push(entity);
-    }
+/// A second organism species sharing the food and the predators with the first one,
+/// read from `[species_b]`. Traits left out are the ones of the first species.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq, Default)]
+pub struct SpeciesConfig {
+    pub initial_organisms: usize,
+    pub initial_organism_energy: Option<f32>,
+    pub initial_organism_speed: Option<f32>,
+    pub initial_organism_size: Option<f32>,
+    pub initial_organism_reproduction_threshold: Option<f32>,
+    pub initial_organism_boldness: Option<f32>,
+    pub initial_organism_exploration: Option<f32>,
+    pub organism_mutability: Option<f32>,
+    /// Seasons this species breeds in, instead of the first species' `breeding.seasons`.
+    #[serde(default)]
+    pub breeding_seasons: Option<Vec<Season>>,
+}
 
-    pub fn clear(&mut self) {
-        for cell in &mut self.cells {
-            cell.clear();
+impl SpeciesConfig {
+    /// `base` with the settings this species overrides.
+    pub fn organism_config(&self, base: &OrganismConfig) -> OrganismConfig {
+        OrganismConfig {
+            initial_organisms: self.initial_organisms,
+            initial_organism_energy: self
+                .initial_organism_energy
+                .unwrap_or(base.initial_organism_energy),
+            initial_organism_speed: self
+                .initial_organism_speed
+                .unwrap_or(base.initial_organism_speed),
+            initial_organism_size: self
+                .initial_organism_size
+                .unwrap_or(base.initial_organism_size),
+            initial_organism_reproduction_threshold: self
+                .initial_organism_reproduction_threshold
+                .unwrap_or(base.initial_organism_reproduction_threshold),
+            initial_organism_boldness: self
+                .initial_organism_boldness
+                .unwrap_or(base.initial_organism_boldness),
+            initial_organism_exploration: self
+                .initial_organism_exploration
+                .unwrap_or(base.initial_organism_exploration),
+            organism_mutability: self.organism_mutability.unwrap_or(base.organism_mutability),
+            breeding: BreedingConfig {
+                seasons: self
+                    .breeding_seasons
+                    .clone()
+                    .unwrap_or_else(|| base.breeding.seasons.clone()),
+                ..base.breeding.clone()
+            },
+            ..base.clone()
         }
     }
 }
 
-#[derive(Resource)]
-pub struct PredatorSpatialIndex(pub SpatialIndex);
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+pub struct OrganismConfig {
+    #[serde(default = "default_initial_organisms")]
+    pub initial_organisms: usize,
+    #[serde(default = "default_initial_organism_energy")]
+    pub initial_organism_energy: f32,
+    #[serde(default = "default_initial_organism_speed")]
+    pub initial_organism_speed: f32,
+    #[serde(default = "default_initial_organism_size")]
+    pub initial_organism_size: f32,
+    #[serde(default = "default_initial_organism_reproduction_threshold")]
+    pub initial_organism_reproduction_threshold: f32,
+    #[serde(default = "default_organism_mutability")]
+    pub organism_mutability: f32,
+    #[serde(default = "default_overcrowding_threshold_for_organisms")]
+    pub overcrowding_threshold_for_organisms: usize,
+    #[serde(default = "default_organism_reproduction_cooldown")]
+    pub organism_reproduction_cooldown: f32,
+    #[serde(default = "default_initial_organism_boldness")]
+    pub initial_organism_boldness: f32,
+    /// Extra movement cost per predator on a candidate tile, scaled by `1 - boldness`.
+    #[serde(default)]
+    pub predator_fear_weight: f32,
+    /// Movement cost reduction per unit of tile food, scaled by `1 + boldness`.
+    #[serde(default)]
+    pub food_attraction_weight: f32,
+    /// How much more likely a fully bold organism is to be picked by a predator.
+    #[serde(default)]
+    pub boldness_exposure: f32,
+    #[serde(default = "default_initial_organism_exploration")]
+    pub initial_organism_exploration: f32,
+    /// Pick the cheapest direction after a uniform 0..5 jitter instead of sampling by
+    /// the evolvable exploration temperature.
+    #[serde(default)]
+    pub legacy_movement_jitter: bool,
+    #[serde(default)]
+    pub panic: PanicConfig,
+    /// Energy an organism gains per unit of food eaten, before the biome's
+    /// `food_nutrition`.
+    #[serde(default = "default_food_energy_conversion")]
+    pub food_energy_conversion: f32,
+    /// Energy that relative reproduction thresholds are fractions of. Organism energy
+    /// is not capped at it; `fat_storage` caps it at each organism's own
+    /// `reserve_capacity` instead.
+    #[serde(default = "default_max_organism_energy")]
+    pub max_organism_energy: f32,
+    /// Evolve reproduction thresholds as fractions of the maximum energy, kept within
+    /// `RELATIVE_THRESHOLD_BOUNDS`, instead of as absolute energies. Predators use
+    /// `max_predator_energy` as their maximum.
+    #[serde(default)]
+    pub relative_reproduction_threshold: bool,
+    /// Number of color morphs; founders get one at random.
+    #[serde(default = "default_morphs")]
+    pub morphs: u8,
+    /// Chance that offspring switch to another morph than their parent's.
+    #[serde(default = "default_morph_mutation_probability")]
+    pub morph_mutation_probability: f32,
+    /// Energy gained each tick by organisms of each morph, for demonstrating
+    /// selection. Morphs past the end of the list get 0, so by default the marker is
+    /// neutral.
+    #[serde(default)]
+    pub morph_fitness: Vec<f32>,
+    /// Food an organism on land grazes per tick from orthogonally adjacent water
+    /// tiles, on top of what it eats on its own tile and never more than its
+    /// appetite. 0 leaves water food untouched.
+    #[serde(default = "default_shoreline_grazing_rate")]
+    pub shoreline_grazing_rate: f32,
+    /// How the organisms on a tile split its food when there is not enough for all.
+    #[serde(default)]
+    pub food_allocation: FoodAllocation,
+    /// Founders' `parental_investment`, and the share of energy every parent hands
+    /// to its offspring unless `evolve_parental_investment` is on.
+    #[serde(default = "default_initial_parental_investment")]
+    pub initial_parental_investment: f32,
+    /// Let each organism hand its offspring its own, heritable share of energy.
+    #[serde(default)]
+    pub evolve_parental_investment: bool,
+    /// Founders' `dispersal`, used by the `dispersal_trait` offspring placement.
+    #[serde(default = "default_initial_organism_dispersal")]
+    pub initial_organism_dispersal: f32,
+    #[serde(default = "default_initial_organism_preferred_temperature")]
+    pub initial_organism_preferred_temperature: f32,
+    /// Founders' `migratory_tendency`. Mutations scale it, so at 0 organisms never
+    /// migrate.
+    #[serde(default)]
+    pub initial_organism_migratory_tendency: f32,
+    /// Degrees a tile may be off an organism's preferred temperature before it
+    /// starts to migrate.
+    #[serde(default = "default_temperature_tolerance")]
+    pub temperature_tolerance: f32,
+    #[serde(default)]
+    pub kin_sharing: KinSharingConfig,
+    #[serde(default)]
+    pub starvation_dispersal: StarvationDispersalConfig,
+    #[serde(default)]
+    pub injury: InjuryConfig,
+    #[serde(default)]
+    pub fat_storage: FatStorageConfig,
+    #[serde(default)]
+    pub giving_up: GivingUpConfig,
+    #[serde(default)]
+    pub tolerance_cost: ToleranceCostConfig,
+    #[serde(default)]
+    pub breeding: BreedingConfig,
+    #[serde(default)]
+    pub aging: AgingConfig,
+}
 
-impl PredatorSpatialIndex {
-    pub fn new(width: usize, height: usize) -> Self {
-        Self(SpatialIndex::new(width, height))
+impl Default for OrganismConfig {
+    fn default() -> Self {
+        Self {
+            initial_organisms: default_initial_organisms(),
+            initial_organism_energy: default_initial_organism_energy(),
+            initial_organism_speed: default_initial_organism_speed(),
+            initial_organism_size: default_initial_organism_size(),
+            initial_organism_reproduction_threshold:
+                default_initial_organism_reproduction_threshold(),
+            organism_mutability: default_organism_mutability(),
+            overcrowding_threshold_for_organisms: default_overcrowding_threshold_for_organisms(),
+            organism_reproduction_cooldown: default_organism_reproduction_cooldown(),
+            initial_organism_boldness: default_initial_organism_boldness(),
+            predator_fear_weight: 0.0,
+            food_attraction_weight: 0.0,
+            boldness_exposure: 0.0,
+            initial_organism_exploration: default_initial_organism_exploration(),
+            legacy_movement_jitter: false,
+            panic: PanicConfig::default(),
+            food_energy_conversion: default_food_energy_conversion(),
+            max_organism_energy: default_max_organism_energy(),
+            relative_reproduction_threshold: false,
+            morphs: default_morphs(),
+            morph_mutation_probability: default_morph_mutation_probability(),
+            morph_fitness: Vec::new(),
+            shoreline_grazing_rate: default_shoreline_grazing_rate(),
+            food_allocation: FoodAllocation::default(),
+            initial_parental_investment: default_initial_parental_investment(),
+            evolve_parental_investment: false,
+            initial_organism_dispersal: default_initial_organism_dispersal(),
+            initial_organism_preferred_temperature: default_initial_organism_preferred_temperature(
+            ),
+            initial_organism_migratory_tendency: 0.0,
+            temperature_tolerance: default_temperature_tolerance(),
+            kin_sharing: KinSharingConfig::default(),
+            starvation_dispersal: StarvationDispersalConfig::default(),
+            injury: InjuryConfig::default(),
+            fat_storage: FatStorageConfig::default(),
+            giving_up: GivingUpConfig::default(),
+            tolerance_cost: ToleranceCostConfig::default(),
+            breeding: BreedingConfig::default(),
+            aging: AgingConfig::default(),
+        }
     }
 }
 
-#[derive(Resource, Debug, Clone, Serialize)]
-pub struct FoodGrid(pub Vec<f32>);
+fn default_initial_organisms() -> usize {
+    10
+}
 
-pub const TILE_SIZE_IN_PIXELS: f32 = 32.0;
+fn default_initial_organism_energy() -> f32 {
+    100.0
+}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+fn default_initial_organism_speed() -> f32 {
+    1.0
+}
 
-    #[test]
-    fn predator_spatial_index_insert_and_get() {
-        let mut idx = SpatialIndex::new(5, 5);
-        let e = Entity::from_raw(42);
-        idx.insert(2, 3, e);
-        assert_eq!(idx.get(2, 3), &[e]);
-        assert_eq!(idx.get(0, 0), &[] as &[Entity]);
-    }
+fn default_initial_organism_size() -> f32 {
+    1.0
+}
 
-    #[test]
-    fn predator_spatial_index_clear() {
-        let mut idx = SpatialIndex::new(5, 5);
-        let e = Entity::from_raw(1);
+fn default_initial_organism_reproduction_threshold() -> f32 {
+    100.0
+}
+
+fn default_organism_mutability() -> f32 {
+    0.1
+}
+
+fn default_overcrowding_threshold_for_organisms() -> usize {
+    10
+}
+
+fn default_organism_reproduction_cooldown() -> f32 {
+    0.5
+}
+
+fn default_food_energy_conversion() -> f32 {
+    2.0
+}
+
+fn default_max_organism_energy() -> f32 {
+    200.0
+}
+
+fn default_shoreline_grazing_rate() -> f32 {
+    0.1
+}
+
+fn default_initial_parental_investment() -> f32 {
+    0.5
+}
+
+fn default_initial_organism_dispersal() -> f32 {
+    1.0
+}
+
+fn default_initial_organism_preferred_temperature() -> f32 {
+    20.0
+}
+
+fn default_temperature_tolerance() -> f32 {
+    5.0
+}
+
+fn default_morphs() -> u8 {
+    4
+}
+
+fn default_morph_mutation_probability() -> f32 {
+    0.01
+}
+
+/// Lowest and highest reproduction threshold, as fractions of the maximum energy,
+/// with `relative_reproduction_threshold`.
+pub const RELATIVE_THRESHOLD_BOUNDS: (f32, f32) = (0.05, 0.95);
+
+/// Lowest and highest share of a parent's energy its offspring can receive.
+pub const PARENTAL_INVESTMENT_BOUNDS: (f32, f32) = (0.1, 0.9);
+
+/// Organisms within `radius` of a kill panic for `duration` ticks, fleeing with
+/// `speed_multiplier` times their usual moves. Every panicked organism in turn panics
+/// those within `radius` of it, with a chance that is multiplied by
+/// `chain_probability` at each hop.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct PanicConfig {
+    pub enabled: bool,
+    pub radius: usize,
+    pub duration: u32,
+    pub speed_multiplier: f32,
+    pub chain_probability: f32,
+}
+
+impl Default for PanicConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            radius: 2,
+            duration: 5,
+            speed_multiplier: 1.5,
+            chain_probability: 0.3,
+        }
+    }
+}
+
+/// Organisms a juvenile predator attacks and misses are injured with a severity
+/// between `min_severity` and `max_severity`, for `heal_ticks_per_severity` ticks per
+/// unit of severity. An injury of severity 1 costs `movement_penalty` of the moves
+/// and `energy_drain` energy per tick, and adds `catch_bonus` to the chance of the
+/// next attack.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct InjuryConfig {
+    pub enabled: bool,
+    pub min_severity: f32,
+    pub max_severity: f32,
+    pub heal_ticks_per_severity: f32,
+    pub movement_penalty: f32,
+    pub energy_drain: f32,
+    pub catch_bonus: f32,
+}
+
+impl Default for InjuryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_severity: 0.2,
+            max_severity: 0.8,
+            heal_ticks_per_severity: 20.0,
+            movement_penalty: 0.5,
+            energy_drain: 0.5,
+            catch_bonus: 0.5,
+        }
+    }
+}
+
+/// Each organism stores at most its evolvable `reserve_capacity` of energy, from
+/// food and from its biome. Carrying the reserve makes it heavier: a capacity of
+/// `max_organism_energy` adds `weight_coupling` times its size to the size it pays
+/// movement for and is spotted by predators with.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct FatStorageConfig {
+    pub enabled: bool,
+    /// Founders' `reserve_capacity`.
+    pub initial_capacity: f32,
+    pub weight_coupling: f32,
+}
+
+impl Default for FatStorageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            initial_capacity: 200.0,
+            weight_coupling: 0.5,
+        }
+    }
+}
+
+/// Organisms graze a tile down to their evolvable `giving_up_density` of food before
+/// moving on: while the food on their tile is above it they stay put for the tick,
+/// unless panicked or dispersing.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct GivingUpConfig {
+    pub enabled: bool,
+    /// Founders' `giving_up_density`.
+    pub initial_density: f32,
+}
+
+impl Default for GivingUpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            initial_density: 1.0,
+        }
+    }
+}
+
+/// Organisms pay `rate` energy a tick for every unit of biome tolerance above
+/// `baseline`, added up over the biomes, so that coping everywhere costs more than
+/// thriving in one biome. The default rate of 0 leaves tolerance free.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ToleranceCostConfig {
+    pub rate: f32,
+    /// Tolerance of each biome that costs nothing to keep.
+    pub baseline: f32,
+}
+
+impl Default for ToleranceCostConfig {
+    fn default() -> Self {
+        Self {
+            rate: 0.0,
+            baseline: 0.8,
+        }
+    }
+}
+
+impl ToleranceCostConfig {
+    /// Energy an organism with `tolerance` spends keeping it up each tick.
+    pub fn cost(&self, tolerance: &[f32; 4]) -> f32 {
+        let excess: f32 = tolerance
+            .iter()
+            .map(|tolerance| (tolerance - self.baseline).max(0.0))
+            .sum();
+        self.rate * excess
+    }
+}
+
+/// Organisms whose energy from food, averaged over the last `window` ticks, falls
+/// below their `starvation_trigger` leave for `burst_duration` ticks on a persistent
+/// heading, ignoring food and paying `energy_cost_multiplier` times the usual
+/// movement cost. Each step keeps the heading with chance `heading_persistence` and
+/// otherwise turns one eighth of a circle left or right.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct StarvationDispersalConfig {
+    pub enabled: bool,
+    pub window: usize,
+    /// Founders' `starvation_trigger`, in energy per tick.
+    pub initial_trigger: f32,
+    /// Founders' `burst_duration`, in ticks.
+    pub initial_duration: f32,
+    pub energy_cost_multiplier: f32,
+    pub heading_persistence: f32,
+    /// Whether offspring inherit mutated triggers and durations.
+    pub evolvable: bool,
+}
+
+impl Default for StarvationDispersalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window: 5,
+            initial_trigger: 0.1,
+            initial_duration: 10.0,
+            energy_cost_multiplier: 2.0,
+            heading_persistence: 0.8,
+            evolvable: false,
+        }
+    }
+}
+
+/// Organisms sharing a tile with close kin hand the poorer one up to `rate` times
+/// their `altruism` energy per tick. Kin are parent and child, siblings, and members
+/// of the same lineage born at most `kin_generations` generations apart.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct KinSharingConfig {
+    pub enabled: bool,
+    pub rate: f32,
+    pub kin_generations: usize,
+    /// Founders' `altruism`.
+    pub initial_altruism: f32,
+}
+
+impl Default for KinSharingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rate: 1.0,
+            kin_generations: 2,
+            initial_altruism: 0.5,
+        }
+    }
+}
+
+fn default_initial_organism_boldness() -> f32 {
+    0.5
+}
+
+fn default_initial_organism_exploration() -> f32 {
+    2.0
+}
+
+fn default_kill_memory_ticks() -> usize {
+    20
+}
+
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+pub struct PredatorConfig {
+    /// `false` makes a prey-only run: no predator is spawned, none of their systems
+    /// is scheduled and the generation stats leave out the predator fields.
+    #[serde(default = "default_predators_enabled")]
+    pub predators_enabled: bool,
+    #[serde(default = "default_initial_predators")]
+    pub initial_predators: usize,
+    #[serde(default = "default_initial_predator_energy")]
+    pub initial_predator_energy: f32,
+    #[serde(default = "default_initial_predator_speed")]
+    pub initial_predator_speed: f32,
+    #[serde(default = "default_initial_predator_size")]
+    pub initial_predator_size: f32,
+    #[serde(default = "default_initial_predator_reproduction_threshold")]
+    pub initial_predator_reproduction_threshold: f32,
+    #[serde(default = "default_initial_predator_hunting_efficiency")]
+    pub initial_predator_hunting_efficiency: f32,
+    #[serde(default = "default_initial_predator_satiation_threshold")]
+    pub initial_predator_satiation_threshold: f32,
+    #[serde(default = "default_predator_mutability")]
+    pub predator_mutability: f32,
+    #[serde(default = "default_overcrowding_threshold_for_predators")]
+    pub overcrowding_threshold_for_predators: usize,
+    #[serde(default = "default_max_predator_energy")]
+    pub max_predator_energy: f32,
+    #[serde(default = "default_predator_energy_decay_rate")]
+    pub predator_energy_decay_rate: f32,
+    #[serde(default = "default_predator_reproduction_cooldown")]
+    pub predator_reproduction_cooldown: f32,
+    #[serde(default = "default_predator_seek_radius")]
+    pub predator_seek_radius: usize,
+    #[serde(default = "default_initial_predator_preferred_temperature")]
+    pub initial_predator_preferred_temperature: f32,
+    /// Extra upkeep per degree between a tile's temperature and a predator's preference.
+    #[serde(default)]
+    pub predator_thermoregulation_cost: f32,
+    /// Ticks founders wait after a kill before they can kill again; 0 disables it.
+    #[serde(default)]
+    pub hunt_cooldown_ticks: usize,
+    /// Let offspring inherit a mutated cooldown instead of the configured one.
+    #[serde(default)]
+    pub hunt_cooldown_evolvable: bool,
+    /// Generations a newborn predator takes to mature; 0 makes them adults at birth.
+    #[serde(default)]
+    pub predator_maturity_age: usize,
+    /// Catch chance and seek radius of a just-born predator relative to an adult's,
+    /// rising linearly to 1 at `predator_maturity_age`.
+    #[serde(default = "default_juvenile_factor")]
+    pub juvenile_factor: f32,
+    /// Kills a predator must have made in the last `kill_memory_ticks` generations,
+    /// on top of its energy, to reproduce; 0 lets energy alone decide. A predator
+    /// remembers at most [`KILL_MEMORY`](crate::components::KILL_MEMORY) kills.
+    #[serde(default)]
+    pub min_kills_for_reproduction: usize,
+    #[serde(default = "default_kill_memory_ticks")]
+    pub kill_memory_ticks: usize,
+    #[serde(default)]
+    pub carrion: CarrionConfig,
+    #[serde(default)]
+    pub kill_scent: KillScentConfig,
+    #[serde(default)]
+    pub ambush: AmbushConfig,
+    #[serde(default)]
+    pub territory: TerritoryConfig,
+    #[serde(default)]
+    pub wander: WanderConfig,
+    #[serde(default)]
+    pub predator_breeding: BreedingConfig,
+}
+
+/// Food left where a predator dies, `food_per_size` per unit of its size, which
+/// organisms eat once the plant food of the tile is gone. Unlike plant food it rots:
+/// each tick `decay_rate` of what is left disappears.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct CarrionConfig {
+    /// 0 leaves no corpses.
+    pub food_per_size: f32,
+    pub decay_rate: f32,
+}
+
+impl Default for CarrionConfig {
+    fn default() -> Self {
+        Self {
+            food_per_size: 0.0,
+            decay_rate: 0.1,
+        }
+    }
+}
+
+/// Scent left on the tile of every kill. Each kill adds `deposit`, and the scent
+/// fades by `deposit / decay_ticks` a tick, so a single kill's is gone after
+/// `decay_ticks` ticks. Wandering predators are drawn towards scented tiles,
+/// expecting prey or leftovers there, by `predator_attraction` per unit of scent,
+/// while organisms shun them like tiles with predators on, by `organism_avoidance`.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct KillScentConfig {
+    pub enabled: bool,
+    pub deposit: f32,
+    pub decay_ticks: usize,
+    pub predator_attraction: f32,
+    pub organism_avoidance: f32,
+}
+
+impl Default for KillScentConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            deposit: 1.0,
+            decay_ticks: 20,
+            predator_attraction: 5.0,
+            organism_avoidance: 10.0,
+        }
+    }
+}
+
+impl KillScentConfig {
+    /// What `scent` adds to a predator's cost of stepping onto its tile, never more
+    /// than zero.
+    pub fn predator_bias(&self, scent: f32) -> f32 {
+        if self.enabled {
+            -self.predator_attraction * scent
+        } else {
+            0.0
+        }
+    }
+
+    /// What `scent` adds to an organism's cost of stepping onto its tile, never less
+    /// than zero.
+    pub fn organism_bias(&self, scent: f32) -> f32 {
+        if self.enabled {
+            self.organism_avoidance * scent
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Sit-and-wait hunting. A predator with no prey in sight lies in ambush with a
+/// chance of its evolvable `patience`, which costs it only `decay_factor` of its
+/// usual upkeep and adds `catch_bonus` to its catch chance against prey that walks
+/// onto or next to its tile. Moving ends the ambush.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct AmbushConfig {
+    pub enabled: bool,
+    pub initial_patience: f32,
+    pub decay_factor: f32,
+    pub catch_bonus: f32,
+}
+
+impl Default for AmbushConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            initial_patience: 0.3,
+            decay_factor: 0.2,
+            catch_bonus: 0.5,
+        }
+    }
+}
+
+/// Predators without a territory claim one around their tile, `radius` plus
+/// `radius_per_size` per unit of their size across, unless the tile already lies in
+/// another's. Rivals pay `intrusion_cost` extra to wander into a foreign territory,
+/// and one that comes next to the owner fights it: each side wins with odds of its
+/// evolvable `aggression`, the loser pays `contest_cost` energy and both pay
+/// `aggression_cost` per unit of their aggression. Owners add `home_catch_bonus` to
+/// their catch chance inside their own territory, which dissolves when they die.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct TerritoryConfig {
+    pub enabled: bool,
+    pub radius: f32,
+    pub radius_per_size: f32,
+    pub intrusion_cost: f32,
+    /// Founders' `aggression`.
+    pub initial_aggression: f32,
+    pub contest_cost: f32,
+    pub aggression_cost: f32,
+    pub home_catch_bonus: f32,
+}
+
+impl Default for TerritoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            radius: 3.0,
+            radius_per_size: 0.0,
+            intrusion_cost: 10.0,
+            initial_aggression: 0.5,
+            contest_cost: 5.0,
+            aggression_cost: 1.0,
+            home_catch_bonus: 0.1,
+        }
+    }
+}
+
+/// Predators with no prey in sight keep a `heading` in radians that drifts by up to
+/// `turn_noise` each step and, with a chance of `turn_chance`, swings to a random
+/// new direction, and step to the neighbouring tile nearest it. Tiles around them
+/// that cost more than average, water most of all, turn the heading away with a
+/// strength of `cost_repulsion` per unit of extra cost. Off, they step to the
+/// cheapest neighbouring tile give or take some noise.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct WanderConfig {
+    pub enabled: bool,
+    pub turn_noise: f32,
+    pub turn_chance: f32,
+    pub cost_repulsion: f32,
+}
+
+impl Default for WanderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            turn_noise: 0.3,
+            turn_chance: 0.05,
+            cost_repulsion: 0.02,
+        }
+    }
+}
+
+/// Breeding windows under `seasons`, read from `[breeding]` for organisms and from
+/// `[predator_breeding]` for predators: outside the listed seasons individuals that
+/// qualify keep their energy instead of reproducing, unless a draw against their
+/// evolvable `breeding_flexibility` lets them breed anyway, at the cost of
+/// `off_season_cost` of the energy they keep. An empty list breeds all year.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct BreedingConfig {
+    pub seasons: Vec<Season>,
+    /// Founders' `breeding_flexibility`.
+    pub initial_flexibility: f32,
+    pub off_season_cost: f32,
+}
+
+impl Default for BreedingConfig {
+    fn default() -> Self {
+        Self {
+            seasons: Vec::new(),
+            initial_flexibility: 0.0,
+            off_season_cost: 0.5,
+        }
+    }
+}
+
+impl BreedingConfig {
+    /// Whether breeding is limited to windows in a run where it is now `season`.
+    pub fn windowed(&self, season: Option<Season>) -> bool {
+        season.is_some() && !self.seasons.is_empty()
+    }
+
+    /// Whether `season` lies outside the breeding windows; never without seasons or
+    /// windows.
+    pub fn off_season(&self, season: Option<Season>) -> bool {
+        self.windowed(season) && season.is_some_and(|season| !self.seasons.contains(&season))
+    }
+}
+
+/// Age structure of organisms, ages counted in generations since birth. Offspring
+/// cannot reproduce before `maturity_age`, while founders start out as adults. Past
+/// `senescence_onset` each further generation of age multiplies the chance an
+/// organism reproduces when it otherwise would by `1 - fertility_decline`, and adds
+/// `metabolic_increase` times the usual cost to every step it takes.
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct AgingConfig {
+    pub enabled: bool,
+    pub maturity_age: usize,
+    pub senescence_onset: usize,
+    pub fertility_decline: f32,
+    pub metabolic_increase: f32,
+}
+
+impl Default for AgingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            maturity_age: 5,
+            senescence_onset: 50,
+            fertility_decline: 0.05,
+            metabolic_increase: 0.02,
+        }
+    }
+}
+
+impl AgingConfig {
+    /// Generations of age past `senescence_onset`.
+    fn senescence(&self, age: usize) -> usize {
+        age.saturating_sub(self.senescence_onset)
+    }
+
+    /// Chance an organism of `age` reproduces when its energy says it should: 0 for
+    /// offspring younger than `maturity_age`, 1 up to `senescence_onset` and falling
+    /// after it. Always 1 with aging off.
+    pub fn fertility(&self, age: usize, founder: bool) -> f32 {
+        if !self.enabled {
+            return 1.0;
+        }
+        if age < self.maturity_age && !founder {
+            return 0.0;
+        }
+        let senescence = self.senescence(age).min(i32::MAX as usize) as i32;
+        (1.0 - self.fertility_decline).powi(senescence)
+    }
+
+    /// What the steps of an organism of `age` cost relative to the usual cost.
+    pub fn metabolic_factor(&self, age: usize) -> f32 {
+        if !self.enabled {
+            return 1.0;
+        }
+        1.0 + self.metabolic_increase * self.senescence(age) as f32
+    }
+}
+
+impl Default for PredatorConfig {
+    fn default() -> Self {
+        Self {
+            predators_enabled: default_predators_enabled(),
+            initial_predators: default_initial_predators(),
+            initial_predator_energy: default_initial_predator_energy(),
+            initial_predator_speed: default_initial_predator_speed(),
+            initial_predator_size: default_initial_predator_size(),
+            initial_predator_reproduction_threshold:
+                default_initial_predator_reproduction_threshold(),
+            initial_predator_hunting_efficiency: default_initial_predator_hunting_efficiency(),
+            initial_predator_satiation_threshold: default_initial_predator_satiation_threshold(),
+            predator_mutability: default_predator_mutability(),
+            overcrowding_threshold_for_predators: default_overcrowding_threshold_for_predators(),
+            max_predator_energy: default_max_predator_energy(),
+            predator_energy_decay_rate: default_predator_energy_decay_rate(),
+            predator_reproduction_cooldown: default_predator_reproduction_cooldown(),
+            predator_seek_radius: default_predator_seek_radius(),
+            initial_predator_preferred_temperature: default_initial_predator_preferred_temperature(
+            ),
+            predator_thermoregulation_cost: 0.0,
+            hunt_cooldown_ticks: 0,
+            hunt_cooldown_evolvable: false,
+            predator_maturity_age: 0,
+            juvenile_factor: default_juvenile_factor(),
+            min_kills_for_reproduction: 0,
+            kill_memory_ticks: default_kill_memory_ticks(),
+            carrion: CarrionConfig::default(),
+            kill_scent: KillScentConfig::default(),
+            ambush: AmbushConfig::default(),
+            territory: TerritoryConfig::default(),
+            wander: WanderConfig::default(),
+            predator_breeding: BreedingConfig::default(),
+        }
+    }
+}
+
+fn default_predators_enabled() -> bool {
+    true
+}
+
+fn default_initial_predators() -> usize {
+    1
+}
+
+fn default_initial_predator_energy() -> f32 {
+    100.0
+}
+
+fn default_initial_predator_speed() -> f32 {
+    1.0
+}
+
+fn default_initial_predator_size() -> f32 {
+    1.0
+}
+
+fn default_initial_predator_reproduction_threshold() -> f32 {
+    100.0
+}
+
+fn default_initial_predator_hunting_efficiency() -> f32 {
+    1.0
+}
+
+fn default_initial_predator_satiation_threshold() -> f32 {
+    100.0
+}
+
+fn default_predator_mutability() -> f32 {
+    0.1
+}
+
+fn default_overcrowding_threshold_for_predators() -> usize {
+    10
+}
+
+fn default_max_predator_energy() -> f32 {
+    1500.0
+}
+
+fn default_predator_energy_decay_rate() -> f32 {
+    0.5
+}
+
+fn default_predator_reproduction_cooldown() -> f32 {
+    0.5
+}
+
+fn default_juvenile_factor() -> f32 {
+    0.5
+}
+
+fn default_predator_seek_radius() -> usize {
+    3
+}
+
+fn default_initial_predator_preferred_temperature() -> f32 {
+    20.0
+}
+
+#[derive(Deserialize, Debug, Serialize, Clone, PartialEq)]
+pub struct LoggingConfig {
+    #[serde(default)]
+    pub log_data: bool,
+    #[serde(default = "default_log_interval")]
+    pub log_interval: usize,
+    /// Generations between heritability estimates; 0 disables them.
+    #[serde(default = "default_heritability_interval")]
+    pub heritability_interval: usize,
+    /// Most recent births per kind kept for each estimate.
+    #[serde(default = "default_heritability_window")]
+    pub heritability_window: usize,
+    /// Most generation stats kept in memory for the final report, whether or not
+    /// `log_data` is on. Older records are thinned out beyond it.
+    #[serde(default = "default_stats_history_cap")]
+    pub stats_history_cap: usize,
+    /// Generations between spatial statistics; 0 disables them.
+    #[serde(default = "default_spatial_interval")]
+    pub spatial_interval: usize,
+    /// Most individuals per population measured for the nearest-neighbour distance.
+    #[serde(default = "default_spatial_sample")]
+    pub spatial_sample: usize,
+    /// Generations between splits of the trait variance between and within
+    /// lineages; 0 leaves them to the final report.
+    #[serde(default)]
+    pub variance_interval: usize,
+    /// Seed bank written with the living population when the run finishes.
+    #[serde(default)]
+    pub export_population: Option<PathBuf>,
+    /// Share of a generation's births with a trait clamped at the same bound above
+    /// which a warning is printed.
+    #[serde(default = "default_clamp_warning_fraction")]
+    pub clamp_warning_fraction: f32,
+    /// Organisms and predators, each, whose traits are written to `samples.jsonl`
+    /// every generation, whether or not `log_data` is on; 0 disables sampling.
+    #[serde(default)]
+    pub sample_individuals_per_generation: usize,
+    /// What organisms and predators are ranked by for the individuals at fixed
+    /// percentiles written to `percentile_individuals.jsonl` every generation, whether
+    /// or not `log_data` is on; empty disables them.
+    #[serde(default)]
+    pub percentile_individuals: Vec<PercentileRank>,
+    /// Directory the logs, report and summary are written to instead of the working
+    /// directory; created if it is missing.
+    #[serde(default)]
+    pub output_dir: Option<PathBuf>,
+    /// Seconds the generation may stand still before the watchdog writes a
+    /// diagnostics bundle; absent, the watchdog is off.
+    #[serde(default)]
+    pub watchdog_timeout_seconds: Option<f32>,
+    /// What the watchdog does once it has written the bundle.
+    #[serde(default)]
+    pub watchdog_action: WatchdogAction,
+    /// `Identity` id of an organism whose movement decisions are written to
+    /// `trace.jsonl` every tick, whether or not `log_data` is on.
+    #[serde(default)]
+    pub trace_entity: Option<u64>,
+}
+
+/// What the individuals followed by `percentile_individuals` are ranked by.
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum PercentileRank {
+    Energy,
+    Size,
+}
+
+/// What the watchdog does when the generation stood still for
+/// `watchdog_timeout_seconds`, after writing its diagnostics.
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchdogAction {
+    /// Warn and keep waiting, warning again after every further timeout.
+    #[default]
+    Warn,
+    /// Finish the run as if it reached its generation limit.
+    Shutdown,
+    /// Panic, for runs supervised by something that restarts them.
+    Panic,
+}
+
+impl LoggingConfig {
+    /// Where the output file `name` goes, in `output_dir` if there is one.
+    pub fn output_path(&self, name: &str) -> PathBuf {
+        match &self.output_dir {
+            Some(dir) => dir.join(name),
+            None => PathBuf::from(name),
+        }
+    }
+
+    pub fn create_output_dir(&self) -> std::io::Result<()> {
+        match &self.output_dir {
+            Some(dir) => std::fs::create_dir_all(dir),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            log_data: false,
+            log_interval: default_log_interval(),
+            heritability_interval: default_heritability_interval(),
+            heritability_window: default_heritability_window(),
+            stats_history_cap: default_stats_history_cap(),
+            spatial_interval: default_spatial_interval(),
+            spatial_sample: default_spatial_sample(),
+            variance_interval: 0,
+            export_population: None,
+            clamp_warning_fraction: default_clamp_warning_fraction(),
+            sample_individuals_per_generation: 0,
+            percentile_individuals: Vec::new(),
+            output_dir: None,
+            watchdog_timeout_seconds: None,
+            watchdog_action: WatchdogAction::default(),
+            trace_entity: None,
+        }
+    }
+}
+
+fn default_clamp_warning_fraction() -> f32 {
+    0.25
+}
+
+fn default_log_interval() -> usize {
+    1
+}
+
+fn default_heritability_interval() -> usize {
+    100
+}
+
+fn default_heritability_window() -> usize {
+    1000
+}
+
+fn default_stats_history_cap() -> usize {
+    10_000
+}
+
+fn default_spatial_interval() -> usize {
+    10
+}
+
+fn default_spatial_sample() -> usize {
+    500
+}
+
+#[derive(Deserialize, Debug, Resource, Serialize, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Config {
+    #[serde(flatten)]
+    pub world: WorldConfig,
+    #[serde(flatten)]
+    pub organism: OrganismConfig,
+    #[serde(flatten)]
+    pub predator: PredatorConfig,
+    #[serde(flatten)]
+    pub logging: LoggingConfig,
+    /// Absent in single-species runs.
+    #[serde(default)]
+    pub species_b: Option<SpeciesConfig>,
+}
+
+/// How many organisms and predators were alive when the tick started, counted in
+/// [`SimulationSet::Start`](crate::plugins::simulation::SimulationSet::Start).
+#[derive(Resource, Default)]
+pub struct PopulationCount {
+    pub organisms: usize,
+    pub predators: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Copy)]
+pub enum Biome {
+    Forest,
+    Desert,
+    Water,
+    Grassland,
+}
+
+impl Display for Biome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Biome::Forest => write!(f, "Forest"),
+            Biome::Desert => write!(f, "Desert"),
+            Biome::Water => write!(f, "Water"),
+            Biome::Grassland => write!(f, "Grassland"),
+        }
+    }
+}
+
+impl Biome {
+    /// Every biome in `idx` order.
+    pub const ALL: [Biome; 4] = [Biome::Forest, Biome::Desert, Biome::Water, Biome::Grassland];
+
+    /// The passive energy delta a biome has when its config leaves it out.
+    pub fn default_passive_energy_delta(self) -> f32 {
+        match self {
+            Biome::Forest => 0.1,
+            Biome::Desert => -0.1,
+            Biome::Water => 0.0,
+            Biome::Grassland => 0.05,
+        }
+    }
+
+    /// The organism movement cost a biome has when its config leaves it out.
+    pub fn default_organism_movement_cost(self) -> f32 {
+        match self {
+            Biome::Forest => 20.0,
+            Biome::Desert => 50.0,
+            Biome::Water => 100.0,
+            Biome::Grassland => 10.0,
+        }
+    }
+
+    /// The predator movement cost a biome has when its config leaves it out.
+    pub fn default_predator_movement_cost(self) -> f32 {
+        match self {
+            Biome::Forest => 6.0,
+            Biome::Desert => 10.0,
+            Biome::Water => 100.0,
+            Biome::Grassland => 5.0,
+        }
+    }
+
+    pub fn idx(self) -> usize {
+        self as usize
+        // Forest=0, Desert=1, Water=2, Grassland=3
+    }
+
+    /// The biome an organism with `biome_tolerance` is most tolerant of, and how
+    /// dominant that tolerance is: 0 when tied with the runner-up, 1 when every other
+    /// tolerance is 0. Ties go to the biome that comes first in `ALL`.
+    pub fn dominant(biome_tolerance: &[f32; 4]) -> (Biome, f32) {
+        let mut best = Biome::Forest;
+        for biome in Biome::ALL {
+            if biome_tolerance[biome.idx()] > biome_tolerance[best.idx()] {
+                best = biome;
+            }
+        }
+        let top = biome_tolerance[best.idx()];
+        let runner_up = Biome::ALL
+            .iter()
+            .filter(|&&biome| biome != best)
+            .map(|&biome| biome_tolerance[biome.idx()])
+            .fold(f32::MIN, f32::max);
+        let dominance = if top > 0.0 {
+            ((top - runner_up) / top).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        (best, dominance)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tile {
+    pub biome: Biome,
+    pub temperature: f32,
+    pub humidity: f32,
+    /// Predators cannot kill prey standing on a reserve tile.
+    #[serde(default)]
+    pub reserve: bool,
+}
+
+#[derive(Debug, Resource, Serialize, Deserialize, Clone)]
+pub struct World {
+    pub width: usize,
+    pub height: usize,
+    pub grid: Vec<Tile>,
+}
+
+impl World {
+    pub fn new(width: usize, height: usize, random_seed: u64) -> (Self, FoodGrid) {
+        let mut rng = StdRng::seed_from_u64(random_seed);
+        let seed = rng.gen::<u32>();
+
+        let perlin = Perlin::new(seed);
+        let scale = 10.0;
+
+        let mut grid = Vec::with_capacity(width * height);
+        let mut food = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let noise_value = perlin.get([x as f64 / scale, y as f64 / scale]);
+
+                let biome = if noise_value < -0.3 {
+                    Biome::Water
+                } else if noise_value < -0.1 {
+                    Biome::Desert
+                } else if noise_value < 0.5 {
+                    Biome::Grassland
+                } else {
+                    Biome::Forest
+                };
+
+                grid.push(Tile {
+                    biome,
+                    temperature: 20.0,
+                    humidity: 0.5,
+                    reserve: false,
+                });
+                food.push(rng.gen_range(1.0..100.0_f32));
+            }
+        }
+
+        (
+            Self {
+                width,
+                height,
+                grid,
+            },
+            FoodGrid::new(width, food),
+        )
+    }
+
+    /// Generates a world with the biome model selected in `config`.
+    /// Tiles that are not water.
+    pub fn habitable_tiles(&self) -> usize {
+        self.grid
+            .iter()
+            .filter(|tile| tile.biome != Biome::Water)
+            .count()
+    }
+
+    pub fn generate(config: &WorldConfig) -> (Self, FoodGrid) {
+        match config.biome_model {
+            BiomeModel::Threshold => Self::new(config.width, config.height, config.seed),
+            BiomeModel::Whittaker => {
+                Self::whittaker(config.width, config.height, config.seed, &config.whittaker)
+            }
+        }
+    }
+
+    fn whittaker(
+        width: usize,
+        height: usize,
+        random_seed: u64,
+        config: &WhittakerConfig,
+    ) -> (Self, FoodGrid) {
+        let mut rng = StdRng::seed_from_u64(random_seed);
+        let temperature_noise = Perlin::new(rng.gen::<u32>());
+        let humidity_noise = Perlin::new(rng.gen::<u32>());
+        let scale = config.noise_scale;
+        let sample = |noise: &Perlin, x: usize, y: usize| {
+            ((noise.get([x as f64 / scale, y as f64 / scale]) as f32 + 1.0) / 2.0).clamp(0.0, 1.0)
+        };
+
+        let mut grid = Vec::with_capacity(width * height);
+        let mut food = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            // 1 on the equator (middle row), 0 at the poles
+            let latitude = if height > 1 {
+                1.0 - (2.0 * y as f32 / (height - 1) as f32 - 1.0).abs()
+            } else {
+                1.0
+            };
+            for x in 0..width {
+                let temperature = (config.latitude_weight * latitude
+                    + (1.0 - config.latitude_weight) * sample(&temperature_noise, x, y))
+                .clamp(0.0, 1.0);
+                let humidity = sample(&humidity_noise, x, y);
+
+                grid.push(Tile {
+                    biome: whittaker_biome(&config.table, temperature, humidity),
+                    temperature: config.min_temperature
+                        + temperature * (config.max_temperature - config.min_temperature),
+                    humidity,
+                    reserve: false,
+                });
+                food.push(rng.gen_range(1.0..100.0_f32));
+            }
+        }
+
+        (
+            Self {
+                width,
+                height,
+                grid,
+            },
+            FoodGrid::new(width, food),
+        )
+    }
+
+    #[inline]
+    pub fn tile(&self, x: usize, y: usize) -> &Tile {
+        &self.grid[y * self.width + x]
+    }
+
+    pub fn mark_reserves(&mut self, reserves: &ReserveConfig, rng: &mut impl Rng) {
+        for rect in &reserves.rectangles {
+            for y in rect.y..(rect.y + rect.height).min(self.height) {
+                for x in rect.x..(rect.x + rect.width).min(self.width) {
+                    self.grid[y * self.width + x].reserve = true;
+                }
+            }
+        }
+
+        if reserves.random_fraction > 0.0 {
+            for tile in self.grid.iter_mut() {
+                if rng.gen::<f32>() < reserves.random_fraction {
+                    tile.reserve = true;
+                }
+            }
+        }
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        World::new(10, 10, 0).0
+    }
+}
+
+#[derive(Default, Resource, Serialize)]
+pub struct Generation(pub usize);
+
+/// When the simulation ticked lately, for holding it to `target_ticks_per_second`
+/// and for reporting the rate it actually reached.
+#[derive(Resource, Debug, Default)]
+pub struct TickClock {
+    next: Option<Instant>,
+    recent: VecDeque<Instant>,
+    /// Whether the simulation ticks this frame.
+    pub due: bool,
+    /// Set while the GUI holds the simulation still; no tick is due then.
+    pub paused: bool,
+}
+
+impl TickClock {
+    /// Whether a tick is due at `now` when ticks are at least `interval` apart,
+    /// recording it if so. A clock that fell more than a tick behind starts over
+    /// from `now` rather than catching up in a burst.
+    pub fn tick(&mut self, now: Instant, interval: Option<Duration>) -> bool {
+        match (interval, self.next) {
+            (None, _) => self.next = None,
+            (Some(_), Some(next)) if now < next => return false,
+            (Some(interval), Some(next)) if now < next + interval => {
+                self.next = Some(next + interval);
+            }
+            (Some(interval), _) => self.next = Some(now + interval),
+        }
+        self.recent.push_back(now);
+        while self
+            .recent
+            .front()
+            .is_some_and(|&tick| now.duration_since(tick) >= Duration::from_secs(1))
+        {
+            self.recent.pop_front();
+        }
+        true
+    }
+
+    /// Time left at `now` before the next tick is due.
+    pub fn until_next(&self, now: Instant) -> Duration {
+        self.next
+            .map_or(Duration::ZERO, |next| next.saturating_duration_since(now))
+    }
+
+    /// Ticks in the second before `now`.
+    pub fn achieved(&self, now: Instant) -> usize {
+        self.recent
+            .iter()
+            .filter(|&&tick| now.duration_since(tick) < Duration::from_secs(1))
+            .count()
+    }
+}
+
+/// Hands out the ids stored in `Identity`.
+#[derive(Resource, Default)]
+pub struct NextId(pub u64);
+
+impl NextId {
+    pub fn allocate(&mut self) -> u64 {
+        let id = self.0;
+        self.0 += 1;
+        id
+    }
+}
+
+/// Independent random number streams derived from the world seed. A system draws
+/// from `for_system(name, tick)` and an individual from `for_entity(id, tick)`, so
+/// systems never wait on each other for a shared generator and a change to how many
+/// numbers one of them draws leaves every other stream as it was.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct RngStreams {
+    seed: u64,
+}
+
+const SYSTEM_STREAMS: u64 = 0x5359_5354_454d_5321;
+const ENTITY_STREAMS: u64 = 0x454e_5449_5459_2121;
+const TIE_BREAKS: u64 = 0x5449_4542_5245_414b;
+
+/// SplitMix64 finalizer, a bijection that scatters nearby inputs.
+fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// FNV-1a, spelled out so stream seeds stay the same across Rust releases.
+fn hash_name(name: &str) -> u64 {
+    name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl RngStreams {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    fn stream_seed(&self, domain: u64, stream: u64, tick: usize) -> u64 {
+        mix(mix(mix(self.seed ^ domain) ^ stream) ^ tick as u64)
+    }
+
+    /// Seed of the stream `for_system` hands out.
+    pub fn system_seed(&self, name: &str, tick: usize) -> u64 {
+        self.stream_seed(SYSTEM_STREAMS, hash_name(name), tick)
+    }
+
+    /// Seed of the stream `for_entity` hands out.
+    pub fn entity_seed(&self, id: u64, tick: usize) -> u64 {
+        self.stream_seed(ENTITY_STREAMS, id, tick)
+    }
+
+    /// The draws of system `name` on `tick`.
+    pub fn for_system(&self, name: &str, tick: usize) -> SmallRng {
+        SmallRng::seed_from_u64(self.system_seed(name, tick))
+    }
+
+    /// The draws of the individual with `Identity` id `id` on `tick`.
+    pub fn for_entity(&self, id: u64, tick: usize) -> SmallRng {
+        SmallRng::seed_from_u64(self.entity_seed(id, tick))
+    }
+
+    /// Rank of `choice` among choices the individual with id `id` finds equally good
+    /// on `tick`, lowest first. The same in every run of a seed whatever order the
+    /// individuals were spawned or are iterated in, and draws from no stream.
+    pub fn tie_break(&self, id: u64, tick: usize, choice: u64) -> u64 {
+        mix(self.stream_seed(TIE_BREAKS, id, tick) ^ choice)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DeathCause {
+    Predation,
+    Overcrowding,
+    Culled,
+    Catastrophe,
+}
+
+/// Energy organisms took in from food during the current generation, and the part
+/// of it lost to digestion.
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct EnergyIntake {
+    pub gained: f32,
+    pub digestion_loss: f32,
+    /// Energy handed between kin, which moves energy around without adding any.
+    pub shared: f32,
+}
+
+/// Ticks organisms spent on a tile in each visit that ended during the current
+/// generation, by moving to another tile.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct ResidenceTally(pub Vec<u32>);
+
+/// Generations in a row each grassland tile has held less food than
+/// `desertification.food_threshold`, by tile index.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct DesertificationPressure(pub Vec<usize>);
+
+/// Energy that grew back on tiles or was spent by the living during the current
+/// generation, the rest of the energy budget beside [`EnergyIntake`] and
+/// [`DeathTally::energy`].
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct EnergyFlux {
+    /// Food regrown on tiles, converted to the energy it would give.
+    pub food_regenerated: f32,
+    /// Energy organisms and predators spent on moving and upkeep. Until movement
+    /// ends it holds the energy they had before it.
+    pub upkeep: f32,
+    /// Net energy organisms gained from the `passive_energy_delta` of their biomes.
+    pub passive: f32,
+}
+
+/// Deaths attributed to each cause during the current generation, the energy the
+/// dead took with them and how many offspring they had.
+#[derive(Resource, Default)]
+pub struct DeathTally {
+    pub causes: HashMap<DeathCause, usize>,
+    pub energy: f32,
+    pub organism_offspring: OffspringTally,
+    pub predator_offspring: OffspringTally,
+}
+
+/// Lifetime offspring counts of the individuals of one kind that died.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct OffspringTally {
+    pub deaths: usize,
+    pub total: u64,
+    pub max: u32,
+}
+
+impl OffspringTally {
+    pub fn record(&mut self, offspring: u32) {
+        self.deaths += 1;
+        self.total += u64::from(offspring);
+        self.max = self.max.max(offspring);
+    }
+
+    /// Mean offspring per death, 0 without deaths.
+    pub fn mean(&self) -> f32 {
+        self.total as f32 / self.deaths.max(1) as f32
+    }
+}
+
+impl DeathTally {
+    pub fn record(&mut self, cause: DeathCause, energy: f32) {
+        *self.causes.entry(cause).or_insert(0) += 1;
+        self.energy += energy.max(0.0);
+    }
+}
+
+/// Ticks after its birth within which an offspring's death counts as a newborn
+/// death.
+pub const NEWBORN_WINDOW: usize = 5;
+
+/// Offspring born in each generation, along with how many of them died within
+/// `NEWBORN_WINDOW` ticks. Founders are left out.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct NewbornSurvival(BTreeMap<usize, (usize, usize)>);
+
+impl NewbornSurvival {
+    pub fn born(&mut self, birth_generation: usize) {
+        self.0.entry(birth_generation).or_default().0 += 1;
+    }
+
+    pub fn died(&mut self, birth_generation: usize, age: usize) {
+        if age > NEWBORN_WINDOW {
+            return;
+        }
+        if let Some((_, died)) = self.0.get_mut(&birth_generation) {
+            *died += 1;
+        }
+    }
+
+    /// Share of the offspring whose window ended before `generation` that died
+    /// within it, forgetting them. 0 when there were none.
+    pub fn close(&mut self, generation: usize) -> f32 {
+        let open = self.0.split_off(&generation.saturating_sub(NEWBORN_WINDOW));
+        let closed = std::mem::replace(&mut self.0, open);
+        let (born, died) = closed
+            .values()
+            .fold((0, 0), |(born, died), &(b, d)| (born + b, died + d));
+        if born == 0 {
+            0.0
+        } else {
+            died as f32 / born as f32
+        }
+    }
+}
+
+/// The bound a mutated trait was clamped to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bound {
+    Low,
+    High,
+}
+
+impl Display for Bound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Bound::Low => write!(f, "lower"),
+            Bound::High => write!(f, "upper"),
+        }
+    }
+}
+
+/// `value` held within `low..=high`, along with the bound it was clamped to, if any.
+pub fn clamp_trait(value: f32, low: f32, high: f32) -> (f32, Option<Bound>) {
+    if value < low {
+        (low, Some(Bound::Low))
+    } else if value > high {
+        (high, Some(Bound::High))
+    } else {
+        (value, None)
+    }
+}
+
+/// Generations of parent age each bin of the births by parent age spans.
+pub const PARENT_AGE_BIN: usize = 5;
+
+/// Births during the current generation, also binned by the age of the parent, the
+/// predator births `min_kills_for_reproduction` held back, and how many of the
+/// newborns had each trait clamped at its lower and upper bound.
+#[derive(Resource, Default, Debug)]
+pub struct ClampTally {
+    births: HashMap<EntityKind, usize>,
+    births_by_parent_age: HashMap<EntityKind, Vec<usize>>,
+    /// Predators with the energy to reproduce but too few recent kills.
+    pub predator_births_blocked: usize,
+    /// Whether `max_total_entities` turned births away, warned about once.
+    pub cap_reached: bool,
+    hits: HashMap<(EntityKind, &'static str), (usize, usize)>,
+}
+
+impl ClampTally {
+    pub fn birth(&mut self, kind: EntityKind, parent_age: usize) {
+        *self.births.entry(kind).or_insert(0) += 1;
+        let bins = self.births_by_parent_age.entry(kind).or_default();
+        let bin = parent_age / PARENT_AGE_BIN;
+        if bins.len() <= bin {
+            bins.resize(bin + 1, 0);
+        }
+        bins[bin] += 1;
+    }
+
+    pub fn record(&mut self, kind: EntityKind, trait_name: &'static str, bound: Option<Bound>) {
+        let Some(bound) = bound else { return };
+        let (low, high) = self.hits.entry((kind, trait_name)).or_insert((0, 0));
+        match bound {
+            Bound::Low => *low += 1,
+            Bound::High => *high += 1,
+        }
+    }
+
+    /// [`clamp_trait`] for a newborn of `kind`, recording the bound it hit.
+    pub fn clamp(
+        &mut self,
+        kind: EntityKind,
+        trait_name: &'static str,
+        value: f32,
+        low: f32,
+        high: f32,
+    ) -> f32 {
+        let (value, bound) = clamp_trait(value, low, high);
+        self.record(kind, trait_name, bound);
+        value
+    }
+
+    pub fn births(&self, kind: EntityKind) -> usize {
+        self.births.get(&kind).copied().unwrap_or(0)
+    }
+
+    /// Births of `kind` by the age of the parent, in bins of [`PARENT_AGE_BIN`]
+    /// generations from age 0 up to the oldest parent's.
+    pub fn births_by_parent_age(&self, kind: EntityKind) -> Vec<usize> {
+        self.births_by_parent_age
+            .get(&kind)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Hits keyed by kind and trait, such as `organism.size`.
+    pub fn hits(&self) -> HashMap<String, (usize, usize)> {
+        self.hits
+            .iter()
+            .map(|(&(kind, trait_name), &hits)| (clamp_key(kind, trait_name), hits))
+            .collect()
+    }
+
+    /// Each trait and bound clamped for more than `fraction` of the births of its
+    /// kind, with the hits and births.
+    pub fn excessive(&self, fraction: f32) -> Vec<(String, Bound, usize, usize)> {
+        let mut excessive = Vec::new();
+        for (&(kind, trait_name), &(low, high)) in self.hits.iter() {
+            let births = self.births(kind);
+            for (bound, hits) in [(Bound::Low, low), (Bound::High, high)] {
+                if hits as f32 > fraction * births as f32 {
+                    excessive.push((clamp_key(kind, trait_name), bound, hits, births));
+                }
+            }
+        }
+        excessive.sort_by(|a, b| a.0.cmp(&b.0));
+        excessive
+    }
+
+    pub fn clear(&mut self) {
+        self.births.clear();
+        self.births_by_parent_age.clear();
+        self.predator_births_blocked = 0;
+        self.cap_reached = false;
+        self.hits.clear();
+    }
+}
+
+/// Traits a run has warned about piling up at one of their bounds, keyed like
+/// `organism.size`, each warned about once.
+#[derive(Resource, Default, Debug)]
+pub struct ClampWarnings(pub HashSet<(String, Bound)>);
+
+fn clamp_key(kind: EntityKind, trait_name: &str) -> String {
+    let kind = match kind {
+        EntityKind::Organism => "organism",
+        EntityKind::Predator => "predator",
+    };
+    format!("{}.{}", kind, trait_name)
+}
+
+/// Generation stats of the whole run, kept in bounded memory. Once more than `cap`
+/// records are held every other one is dropped and only every `stride`-th record is
+/// kept from then on, so the history stays evenly spaced from the first generation.
+/// The most recent record is always kept.
+#[derive(Resource, Debug, Clone)]
+pub struct StatsHistory {
+    cap: usize,
+    stride: usize,
+    seen: usize,
+    records: Vec<GenerationStats>,
+    latest: Option<GenerationStats>,
+}
+
+impl StatsHistory {
+    pub fn new(cap: usize) -> Self {
+        Self {
+            cap: cap.max(2),
+            stride: 1,
+            seen: 0,
+            records: Vec::new(),
+            latest: None,
+        }
+    }
+
+    pub fn push(&mut self, stats: GenerationStats) {
+        if self.seen.is_multiple_of(self.stride) {
+            self.records.push(stats);
+            self.latest = None;
+            if self.records.len() > self.cap {
+                if self.records.len().is_multiple_of(2) {
+                    // the record just pushed falls between the thinned-out ones
+                    self.latest = self.records.pop();
+                }
+                self.records = std::mem::take(&mut self.records)
+                    .into_iter()
+                    .step_by(2)
+                    .collect();
+                self.stride *= 2;
+            }
+        } else {
+            self.latest = Some(stats);
+        }
+        self.seen += 1;
+    }
+
+    /// Every `stride`-th record pushed so far.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &GenerationStats> {
+        self.records.iter().chain(self.latest.as_ref())
+    }
+
+    /// Forgets the records after `generation`, for a run resumed from an earlier
+    /// state.
+    pub fn truncate(&mut self, generation: usize) {
+        self.records
+            .retain(|record| record.generation as usize <= generation);
+        self.latest = self
+            .latest
+            .take()
+            .filter(|record| record.generation as usize <= generation);
+    }
+}
+
+/// The organisms on each tile, rebuilt in
+/// [`SimulationSet::Movement`](crate::plugins::simulation::SimulationSet::Movement)
+/// once everything has moved. Organisms that die later in the tick stay in it until
+/// the next rebuild, so systems reading it skip entities that no longer exist.
+#[derive(Resource)]
+pub struct SpatialIndex {
+    pub cells: Vec<Vec<Entity>>,
+    pub width: usize,
+}
+
+impl SpatialIndex {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            cells: vec![Vec::new(); width * height],
+            width,
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, x: usize, y: usize) -> &[Entity] {
+        &self.cells[y * self.width + x]
+    }
+
+    #[inline]
+    pub fn insert(&mut self, x: usize, y: usize, entity: Entity) {
+        self.cells[y * self.width + x].push(entity);
+    }
+
+    pub fn clear(&mut self) {
+        for cell in &mut self.cells {
+            cell.clear();
+        }
+    }
+}
+
+/// The predators on each tile, rebuilt alongside [`SpatialIndex`].
+#[derive(Resource)]
+pub struct PredatorSpatialIndex(pub SpatialIndex);
+
+impl PredatorSpatialIndex {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self(SpatialIndex::new(width, height))
+    }
+}
+
+/// The circle of tiles a predator claimed, see [`TerritoryConfig`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Territory {
+    pub x: usize,
+    pub y: usize,
+    pub radius: f32,
+}
+
+impl Territory {
+    pub fn covers(&self, x: usize, y: usize) -> bool {
+        let dx = x as f32 - self.x as f32;
+        let dy = y as f32 - self.y as f32;
+        dx * dx + dy * dy <= self.radius * self.radius
+    }
+}
+
+/// The territory of every predator that holds one, in entity order so that walking
+/// them is deterministic, and the owners claiming each tile, indexed like
+/// `World::grid`.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct TerritoryMap {
+    pub territories: BTreeMap<Entity, Territory>,
+    owners: Vec<Vec<Entity>>,
+    width: usize,
+    height: usize,
+}
+
+impl TerritoryMap {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            territories: BTreeMap::new(),
+            owners: vec![Vec::new(); width * height],
+            width,
+            height,
+        }
+    }
+
+    /// Indices of the tiles `territory` covers.
+    fn tiles(&self, territory: Territory) -> impl Iterator<Item = usize> + '_ {
+        let reach = territory.radius.max(0.0) as usize;
+        let xs = territory.x.saturating_sub(reach)..(territory.x + reach + 1).min(self.width);
+        let ys = territory.y.saturating_sub(reach)..(territory.y + reach + 1).min(self.height);
+        ys.flat_map(move |y| xs.clone().map(move |x| (x, y)))
+            .filter(move |&(x, y)| territory.covers(x, y))
+            .map(|(x, y)| y * self.width + x)
+    }
+
+    /// Gives `owner` the territory of `radius` around `(x, y)`, replacing any it held.
+    pub fn claim(&mut self, owner: Entity, x: usize, y: usize, radius: f32) {
+        self.dissolve(owner);
+        let territory = Territory { x, y, radius };
+        let tiles: Vec<_> = self.tiles(territory).collect();
+        for i in tiles {
+            self.owners[i].push(owner);
+        }
+        self.territories.insert(owner, territory);
+    }
+
+    pub fn dissolve(&mut self, owner: Entity) {
+        let Some(territory) = self.territories.remove(&owner) else {
+            return;
+        };
+        let tiles: Vec<_> = self.tiles(territory).collect();
+        for i in tiles {
+            self.owners[i].retain(|&claimant| claimant != owner);
+        }
+    }
+
+    /// The predators claiming `(x, y)`, none on a map with no tiles.
+    pub fn owners(&self, x: usize, y: usize) -> &[Entity] {
+        self.owners
+            .get(y * self.width + x)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether a predator other than `entity` claims `(x, y)`.
+    pub fn is_foreign(&self, x: usize, y: usize, entity: Entity) -> bool {
+        self.owners(x, y).iter().any(|&owner| owner != entity)
+    }
+
+    /// Share of the tiles of a territory that others claim too, averaged over the
+    /// territories; 0 without any.
+    pub fn mean_overlap(&self) -> f32 {
+        if self.territories.is_empty() {
+            return 0.0;
+        }
+        let total: f32 = self
+            .territories
+            .values()
+            .map(|&territory| {
+                let (shared, tiles) = self.tiles(territory).fold((0, 0), |(shared, tiles), i| {
+                    (shared + usize::from(self.owners[i].len() > 1), tiles + 1)
+                });
+                shared as f32 / tiles.max(1) as f32
+            })
+            .sum();
+        total / self.territories.len() as f32
+    }
+}
+
+/// Side of the square chunks `FoodGrid` tracks changes in, in tiles.
+pub const CHUNK_SIZE: usize = 16;
+
+/// Food on every tile, indexed like `World::grid`. Writes go through `set`, which
+/// flags the tile's chunk so the heatmap only repaints the chunks that changed.
+#[derive(Resource, Debug, Clone)]
+pub struct FoodGrid {
+    food: Vec<f32>,
+    width: usize,
+    dirty: Vec<bool>,
+}
+
+impl FoodGrid {
+    /// A grid `width` tiles wide, every chunk dirty.
+    pub fn new(width: usize, food: Vec<f32>) -> Self {
+        let chunks_x = width.div_ceil(CHUNK_SIZE);
+        let chunks_y = food.len().div_ceil(width.max(1)).div_ceil(CHUNK_SIZE);
+        Self {
+            food,
+            width,
+            dirty: vec![true; chunks_x * chunks_y],
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, i: usize) -> f32 {
+        self.food[i]
+    }
+
+    pub fn values(&self) -> &[f32] {
+        &self.food
+    }
+
+    pub fn len(&self) -> usize {
+        self.food.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.food.is_empty()
+    }
+
+    /// Changes the food of tile `i`, flagging its chunk if the value differs.
+    #[inline]
+    pub fn set(&mut self, i: usize, value: f32) {
+        if self.food[i] != value {
+            self.food[i] = value;
+            let chunk = self.chunk_of(i);
+            self.dirty[chunk] = true;
+        }
+    }
+
+    /// Chunks in one row of the grid.
+    pub fn chunks_x(&self) -> usize {
+        self.width.div_ceil(CHUNK_SIZE)
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.dirty.len()
+    }
+
+    #[inline]
+    pub fn chunk_of(&self, i: usize) -> usize {
+        let (x, y) = (i % self.width, i / self.width);
+        (y / CHUNK_SIZE) * self.chunks_x() + x / CHUNK_SIZE
+    }
+
+    /// Tiles of `chunk`, row by row.
+    pub fn chunk_tiles(&self, chunk: usize) -> impl Iterator<Item = usize> + '_ {
+        let chunks_x = self.chunks_x().max(1);
+        let (x0, y0) = (chunk % chunks_x * CHUNK_SIZE, chunk / chunks_x * CHUNK_SIZE);
+        let height = self.food.len() / self.width.max(1);
+        (y0..(y0 + CHUNK_SIZE).min(height)).flat_map(move |y| {
+            (x0..(x0 + CHUNK_SIZE).min(self.width)).map(move |x| y * self.width + x)
+        })
+    }
+
+    /// Whether any tile in `chunk` changed since the last `clear_dirty`.
+    pub fn is_dirty(&self, chunk: usize) -> bool {
+        self.dirty[chunk]
+    }
+
+    pub fn clear_dirty(&mut self) {
+        self.dirty.fill(false);
+    }
+}
+
+/// Food from predator corpses, indexed like `FoodGrid`, see [`CarrionConfig`].
+#[derive(Resource, Debug, Clone, Default)]
+pub struct CarrionGrid(pub Vec<f32>);
+
+impl CarrionGrid {
+    pub fn new(tiles: usize) -> Self {
+        Self(vec![0.0; tiles])
+    }
+
+    pub fn total(&self) -> f32 {
+        self.0.iter().sum()
+    }
+}
+
+/// Scent of recent kills, indexed like `FoodGrid`, see [`KillScentConfig`].
+#[derive(Resource, Debug, Clone, Default)]
+pub struct KillScentGrid(pub Vec<f32>);
+
+impl KillScentGrid {
+    pub fn new(tiles: usize) -> Self {
+        Self(vec![0.0; tiles])
+    }
+}
+
+/// Movement decisions of the `trace_entity` not yet written to `trace.jsonl`.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MovementTrace(pub Vec<TraceRecord>);
+
+/// A food patch still in place, see [`FoodPatchConfig`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoodPatch {
+    pub x: usize,
+    pub y: usize,
+    pub radius: usize,
+    /// Generations left before its excess food starts to decay.
+    pub remaining: usize,
+}
+
+impl FoodPatch {
+    pub fn covers(&self, x: usize, y: usize) -> bool {
+        let dx = x as isize - self.x as isize;
+        let dy = y as isize - self.y as isize;
+        dx * dx + dy * dy <= (self.radius * self.radius) as isize
+    }
+}
+
+/// Food patches in place, and the tiles of expired ones whose food is still above
+/// their biome's maximum.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct FoodPatches {
+    pub active: Vec<FoodPatch>,
+    pub decaying: Vec<usize>,
+}
+
+/// Tiles whose food may still regrow, indexed like `FoodGrid`. A tile leaves the set
+/// once its food settles, so every system that lowers food or changes a biome must
+/// `mark` the tile again.
+#[derive(Resource, Debug, Clone)]
+pub struct ActiveTiles {
+    active: Vec<bool>,
+    list: Vec<usize>,
+}
+
+impl ActiveTiles {
+    /// Every one of `len` tiles active.
+    pub fn all(len: usize) -> Self {
+        Self {
+            active: vec![true; len],
+            list: (0..len).collect(),
+        }
+    }
+
+    #[inline]
+    pub fn mark(&mut self, i: usize) {
+        if !self.active[i] {
+            self.active[i] = true;
+            self.list.push(i);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// Keeps only the tiles for which `f` returns true.
+    pub fn retain(&mut self, mut f: impl FnMut(usize) -> bool) {
+        let active = &mut self.active;
+        self.list.retain(|&i| {
+            let keep = f(i);
+            active[i] = keep;
+            keep
+        });
+    }
+}
+
+pub const TILE_SIZE_IN_PIXELS: f32 = 32.0;
+
+/// Directory time-lapse frames and manual screenshots are written to.
+pub(crate) const FRAMES_DIR: &str = "frames";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_tolerance_above_the_baseline_costs_energy() {
+        let cost = ToleranceCostConfig {
+            rate: 2.0,
+            baseline: 0.5,
+        };
+        assert_eq!(cost.cost(&[0.5, 0.1, 0.0, 0.4]), 0.0);
+        assert!((cost.cost(&[1.5, 0.75, 0.1, 0.5]) - 2.5).abs() < 1e-6);
+        assert_eq!(ToleranceCostConfig::default().cost(&[1.5; 4]), 0.0);
+    }
+
+    #[test]
+    fn movement_costs_fall_back_to_the_built_in_ones() {
+        let mut world = crate::utils::default_config().world;
+        world.forest.predator_movement_cost = None;
+        world.forest.organism_movement_cost = None;
+        world.grassland.predator_movement_cost = Some(1.5);
+        assert_eq!(world.organism_movement_cost(Biome::Forest), 20.0);
+        assert_eq!(world.predator_movement_cost(Biome::Forest), 6.0);
+        assert_eq!(world.predator_movement_cost(Biome::Grassland), 1.5);
+        assert_eq!(world.organism_movement_cost(Biome::Grassland), 10.0);
+
+        let toml = "food_availability = 0.1\nmax_food_availability = 10.0\n";
+        let data: BiomeDataConfig = toml::from_str(toml).unwrap();
+        assert_eq!(
+            (data.organism_movement_cost, data.predator_movement_cost),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn aging_curves_start_at_maturity_and_senescence() {
+        let aging = AgingConfig {
+            enabled: true,
+            maturity_age: 3,
+            senescence_onset: 10,
+            fertility_decline: 0.2,
+            metabolic_increase: 0.1,
+        };
+        assert_eq!(aging.fertility(2, false), 0.0);
+        // founders start as adults
+        assert_eq!(aging.fertility(2, true), 1.0);
+        assert_eq!(aging.fertility(10, false), 1.0);
+        assert!((aging.fertility(12, false) - 0.64).abs() < 1e-6);
+        assert_eq!(aging.metabolic_factor(10), 1.0);
+        assert!((aging.metabolic_factor(15) - 1.5).abs() < 1e-6);
+
+        let off = AgingConfig::default();
+        assert_eq!(
+            (off.fertility(0, false), off.metabolic_factor(500)),
+            (1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn tick_clock_spaces_ticks_and_starts_over_when_behind() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let interval = Some(Duration::from_millis(200));
+        let mut clock = TickClock::default();
+
+        assert!(clock.tick(at(0), interval));
+        assert!(!clock.tick(at(150), interval));
+        assert_eq!(clock.until_next(at(150)), Duration::from_millis(50));
+        // a late tick keeps the schedule
+        assert!(clock.tick(at(250), interval));
+        assert!(!clock.tick(at(390), interval));
+        assert!(clock.tick(at(400), interval));
+        // one more than a tick behind, the next is an interval from now
+        assert!(clock.tick(at(1_000), interval));
+        assert!(!clock.tick(at(1_100), interval));
+        assert!(clock.tick(at(1_200), interval));
+        assert_eq!(clock.achieved(at(1_200)), 4);
+
+        assert!(clock.tick(at(1_201), None));
+        assert_eq!(clock.until_next(at(1_201)), Duration::ZERO);
+    }
+
+    #[test]
+    fn stream_seeds_do_not_collide() {
+        let streams = RngStreams::new(42);
+        let systems = [
+            "spawn_organisms",
+            "spawn_predators",
+            "reproduction",
+            "predator_reproduction",
+            "catastrophes",
+            "food_patches",
+            "kill_over_limit_organisms",
+            "log_samples",
+        ];
+        let mut seeds = std::collections::HashSet::new();
+        for tick in 0..1000 {
+            for name in systems {
+                assert!(seeds.insert(streams.system_seed(name, tick)));
+            }
+        }
+        for tick in 0..100 {
+            for id in 0..1000 {
+                assert!(seeds.insert(streams.entity_seed(id, tick)));
+            }
+        }
+
+        assert_eq!(
+            streams.system_seed("reproduction", 7),
+            RngStreams::new(42).system_seed("reproduction", 7)
+        );
+        assert_ne!(
+            streams.system_seed("reproduction", 7),
+            RngStreams::new(43).system_seed("reproduction", 7)
+        );
+    }
+
+    #[test]
+    fn newborn_deaths_count_once_the_window_ends() {
+        let mut newborns = NewbornSurvival::default();
+        for _ in 0..4 {
+            newborns.born(10);
+        }
+        newborns.died(10, 1);
+        newborns.died(10, NEWBORN_WINDOW);
+        newborns.died(10, NEWBORN_WINDOW + 1);
+        newborns.born(12);
+        newborns.died(12, 2);
+
+        // the cohort of generation 10 is still open at 15
+        assert_eq!(newborns.close(15), 0.0);
+        assert_eq!(newborns.close(16), 0.5);
+        assert_eq!(newborns.close(16), 0.0);
+        assert_eq!(newborns.close(100), 1.0);
+    }
+
+    #[test]
+    fn dominant_biome_classifies_tolerance_maps() {
+        let (biome, dominance) = Biome::dominant(&[0.2, 1.0, 0.5, 0.25]);
+        assert_eq!(biome, Biome::Desert);
+        assert!((dominance - 0.5).abs() < 1e-6);
+
+        assert_eq!(
+            Biome::dominant(&[0.0, 0.0, 0.0, 2.0]),
+            (Biome::Grassland, 1.0)
+        );
+        assert_eq!(Biome::dominant(&[0.3, 0.9, 0.2, 0.9]), (Biome::Desert, 0.0));
+        assert_eq!(Biome::dominant(&[0.7; 4]), (Biome::Forest, 0.0));
+        assert_eq!(Biome::dominant(&[0.0; 4]), (Biome::Forest, 0.0));
+    }
+
+    #[test]
+    fn stats_history_keeps_first_last_and_evenly_spaced_records() {
+        for (cap, pushed) in [(10, 1_000), (7, 100), (4, 5)] {
+            let mut history = StatsHistory::new(cap);
+            for generation in 0..pushed {
+                history.push(GenerationStats {
+                    generation,
+                    ..Default::default()
+                });
+            }
+
+            let generations: Vec<_> = history.records().map(|stats| stats.generation).collect();
+            assert!(generations.len() <= cap + 1);
+            assert_eq!(generations[0], 0);
+            assert_eq!(*generations.last().unwrap(), pushed - 1);
+            let stride = history.stride() as u32;
+            let spaced = &generations[..generations.len() - 1];
+            assert!(spaced.windows(2).all(|pair| pair[1] - pair[0] == stride));
+        }
+    }
+
+    #[test]
+    fn predator_spatial_index_insert_and_get() {
+        let mut idx = SpatialIndex::new(5, 5);
+        let e = Entity::from_raw(42);
+        idx.insert(2, 3, e);
+        assert_eq!(idx.get(2, 3), &[e]);
+        assert_eq!(idx.get(0, 0), &[] as &[Entity]);
+    }
+
+    #[test]
+    fn whittaker_lookup_uses_edge_bins() {
+        let table = vec![
+            vec![Biome::Grassland, Biome::Water],
+            vec![Biome::Desert, Biome::Grassland, Biome::Forest],
+        ];
+        assert_eq!(whittaker_biome(&table, 0.0, 0.0), Biome::Grassland);
+        assert_eq!(whittaker_biome(&table, 0.0, 1.0), Biome::Water);
+        assert_eq!(whittaker_biome(&table, 0.49, 0.5), Biome::Water);
+        assert_eq!(whittaker_biome(&table, 0.5, 0.0), Biome::Desert);
+        assert_eq!(whittaker_biome(&table, 1.0, 1.0), Biome::Forest);
+        assert_eq!(whittaker_biome(&table, 1.0, 0.5), Biome::Grassland);
+        assert_eq!(whittaker_biome(&table, -3.0, 7.0), Biome::Water);
+    }
+
+    #[test]
+    fn whittaker_falls_back_to_grassland_without_biomes() {
+        assert_eq!(whittaker_biome(&[], 0.5, 0.5), Biome::Grassland);
+        let table = vec![vec![Biome::Desert], vec![]];
+        assert_eq!(whittaker_biome(&table, 0.0, 0.5), Biome::Desert);
+        assert_eq!(whittaker_biome(&table, 1.0, 0.5), Biome::Grassland);
+
+        let mut config = crate::utils::default_config().world;
+        config.biome_model = BiomeModel::Whittaker;
+        config.whittaker.table.clear();
+        let (world, _) = World::generate(&config);
+        assert!(world.grid.iter().all(|tile| tile.biome == Biome::Grassland));
+    }
+
+    #[test]
+    fn threshold_model_output_is_unchanged_for_seed_zero() {
+        let mut config = crate::utils::default_config().world;
+        config.width = 20;
+        config.height = 20;
+        config.seed = 0;
+        let (world, food) = World::generate(&config);
+
+        let mut counts = [0; 4];
+        for tile in &world.grid {
+            counts[tile.biome.idx()] += 1;
+        }
+        assert_eq!(counts, [128, 50, 90, 132]);
+        let first_row: String = world.grid[..20]
+            .iter()
+            .map(|tile| tile.biome.to_string().remove(0))
+            .collect();
+        assert_eq!(first_row, "GGGFFFFFGGGDDDDGGGGG");
+        assert_eq!(food.get(0), 73.38022);
+        assert_eq!(food.get(399), 22.903826);
+    }
+
+    #[test]
+    fn whittaker_model_keeps_the_climate_fields() {
+        let mut config = crate::utils::default_config().world;
+        config.width = 20;
+        config.height = 21;
+        config.biome_model = BiomeModel::Whittaker;
+        let (world, _) = World::generate(&config);
+        let whittaker = &config.whittaker;
+
+        assert!(world.grid.iter().all(|tile| {
+            (whittaker.min_temperature..=whittaker.max_temperature).contains(&tile.temperature)
+                && (0.0..=1.0).contains(&tile.humidity)
+        }));
+        let row_temperature =
+            |y: usize| (0..20).map(|x| world.tile(x, y).temperature).sum::<f32>() / 20.0;
+        assert!(row_temperature(10) > row_temperature(0));
+        assert!(row_temperature(10) > row_temperature(20));
+    }
+
+    #[test]
+    fn predator_spatial_index_clear() {
+        let mut idx = SpatialIndex::new(5, 5);
+        let e = Entity::from_raw(1);
         idx.insert(0, 0, e);
         idx.clear();
         assert_eq!(idx.get(0, 0), &[] as &[Entity]);
     }
+
+    #[test]
+    fn food_grid_flags_only_chunks_whose_food_changed() {
+        // 20x20 tiles make 2x2 chunks, the right and top ones partial
+        let mut food = FoodGrid::new(20, vec![1.0; 400]);
+        assert_eq!(food.chunk_count(), 4);
+        assert!((0..4).all(|chunk| food.is_dirty(chunk)));
+        food.clear_dirty();
+
+        food.set(0, 1.0);
+        assert!((0..4).all(|chunk| !food.is_dirty(chunk)));
+
+        food.set(19 * 20 + 17, 0.5);
+        assert_eq!(food.chunk_of(19 * 20 + 17), 3);
+        let dirty: Vec<bool> = (0..4).map(|chunk| food.is_dirty(chunk)).collect();
+        assert_eq!(dirty, vec![false, false, false, true]);
+    }
+
+    #[test]
+    fn territory_map_tracks_claims_and_overlap() {
+        let (a, b) = (Entity::from_raw(1), Entity::from_raw(2));
+        let mut map = TerritoryMap::new(10, 10);
+        map.claim(a, 2, 2, 1.0);
+        assert_eq!(map.owners(2, 1), &[a]);
+        assert!(
+            map.owners(1, 1).is_empty(),
+            "diagonals lie outside radius 1"
+        );
+        assert_eq!(map.mean_overlap(), 0.0);
+
+        // each covers five tiles, two of them shared
+        map.claim(b, 3, 2, 1.0);
+        assert_eq!(map.owners(2, 2), &[a, b]);
+        assert!(map.is_foreign(3, 3, a) && !map.is_foreign(3, 3, b));
+        assert!((map.mean_overlap() - 0.4).abs() < 1e-6);
+
+        map.dissolve(a);
+        assert_eq!(map.owners(2, 2), &[b]);
+        assert!(map.owners(1, 2).is_empty());
+        assert_eq!(map.territories.len(), 1);
+        assert_eq!(map.mean_overlap(), 0.0);
+    }
+
+    #[test]
+    fn seasons_cycle_and_species_keep_their_own_breeding_windows() {
+        let mut seasons = SeasonsConfig {
+            enabled: true,
+            length: 3,
+        };
+        let year: Vec<_> = (0..13).step_by(3).map(|g| seasons.season(g)).collect();
+        assert_eq!(
+            year,
+            [Season::ALL.map(Some).as_slice(), &[Some(Season::Spring)]].concat()
+        );
+        assert_eq!(seasons.season(2), Some(Season::Spring));
+
+        let mut base = crate::utils::default_config().organism;
+        base.breeding.seasons = vec![Season::Spring];
+        let species_b = SpeciesConfig {
+            breeding_seasons: Some(vec![Season::Summer]),
+            ..SpeciesConfig::default()
+        };
+        let b = species_b.organism_config(&base);
+        assert!(!base.breeding.off_season(Some(Season::Spring)));
+        assert!(base.breeding.off_season(Some(Season::Summer)));
+        assert!(b.breeding.off_season(Some(Season::Spring)));
+        assert!(!b.breeding.off_season(None), "no windows without seasons");
+
+        seasons.enabled = false;
+        assert_eq!(seasons.season(4), None);
+    }
 }
@@ -0,0 +1,1056 @@
+//! Versioned on-disk records written by the logging plugin, and a reader that can
+//! load the current and older schema versions.
+//!
+//! Version history:
+//! - 1: original format without a `schema_version` field.
+//! - 2: adds `schema_version`, organism `boldness`, and the `biome_changes` and
+//!   `death_causes` generation stats.
+//! - 3: adds predator `preferred_temperature` and its generation average.
+//! - 4: adds tile `reserve` and the in-reserve population counts.
+//! - 5: adds organism `exploration` and its generation average.
+//! - 6: adds the predator hunt cooldown, its generation average and kills per predator.
+//! - 7: adds the number of organisms panicked by kills.
+//! - 8: adds organism energy intake per capita and digestion losses.
+//! - 9: adds each organism's `dominant_biome` to world records.
+//! - 10: adds `reproduction_fraction` to organisms and predators in world records.
+//! - 11: adds the `spatial` generation stats.
+//! - 12: adds organism `morph` and the per-morph `morph_counts` generation stats.
+//! - 13: adds `average_water_food`.
+//! - 14: adds the juvenile and adult predator counts.
+//! - 15: adds organism `parental_investment` and its generation average.
+//! - 16: adds the `density_adjustment` made by the `cap` density policy to the report.
+//! - 17: adds the `clamp_hits` generation stats.
+//! - 18: adds organism `species` to world records and the per-species generation stats.
+//! - 19: adds `total_carrion`.
+//! - 20: adds the `samples.jsonl` trait samples.
+//! - 21: adds organism `dispersal` and `newborn_death_rate`.
+//! - 22: adds the config's `defaulted_fields` to the report.
+//! - 23: adds organism `preferred_temperature` and `migratory_tendency`, and
+//!   `organism_mean_y`.
+//! - 24: adds `index.json`, the byte offset and headline numbers of every world record.
+//! - 25: adds organism `altruism`, its generation average and `energy_shared`.
+//! - 26: adds organism `starvation_trigger` and `burst_duration`, and
+//!   `organisms_dispersing_fraction`.
+//! - 27: adds `organisms_injured`.
+//! - 28: adds `kills_per_biome`.
+//! - 29: adds predator `patience` and `stance`, and `predators_ambushing_fraction`.
+//! - 30: adds the `energy` totals and fluxes.
+//! - 31: adds organism `reserve_capacity` and its distribution.
+//! - 32: adds predator `aggression`, `territories` and `mean_territory_overlap`.
+//! - 33: adds `offspring` and `traits` to deaths, `Survived` events at the end of a
+//!   run, and the mean and most lifetime offspring of each generation's dead.
+//! - 34: adds `breeding_flexibility` to organisms and predators, and the `season`
+//!   and birth counts of each generation.
+//! - 35: adds predator `heading`.
+//! - 36: writes `biome_tally` and `kills_per_biome` with one lowercase field per
+//!   biome, in the order of `Biome::ALL`, instead of maps keyed by the biome name.
+//! - 37: adds `energy.passive`, and `passive_energy` and each biome's
+//!   `passive_energy_delta` to the config.
+//! - 38: adds the `parent_age` of births, `organism_births_by_parent_age` and the
+//!   `aging` config.
+//! - 39: adds `organisms_per_biome`.
+//! - 40: adds predator `recent_kills`, `predator_births_blocked` and the
+//!   `min_kills_for_reproduction` and `kill_memory_ticks` config.
+//! - 41: adds `state_hash` to the report.
+//! - 42: adds `organism_avg_giving_up_density` and `organism_residence_ticks`.
+//! - 43: adds `trait_variance` to the generation stats and the report.
+//! - 44: adds the `percentile_individuals.jsonl` percentile tracks.
+//! - 45: adds each biome's `organism_movement_cost` and `predator_movement_cost` to
+//!   the config.
+//! - 46: leaves the predator fields out of the generation stats of prey-only runs.
+//! - 47: adds the `trace.jsonl` movement decisions of the `trace_entity`.
+//! - 48: replaces the `config` of every world record by the `run_id` of the run,
+//!   whose config is written once to `effective_config.toml` next to the logs.
+//! - 49: adds `organism_intake_gini`.
+//!
+//! Logs of every version from 47 on are kept in `tests/fixtures` and checked to
+//! replay and analyze by `tests/golden.rs`, which also writes those of the current
+//! version before a bump.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::ops::{Index, IndexMut};
+use std::path::Path;
+
+use bevy::prelude::Event;
+use bevy::utils::hashbrown::HashMap;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::components::{EntityKind, Organism, Position, Predator, SpeciesId, TraitSnapshot};
+use crate::config::DensityAdjustment;
+use crate::resources::{Biome, Config, DeathCause, PercentileRank, Season, World};
+use crate::utils::default_config;
+
+pub const SCHEMA_VERSION: u32 = 49;
+
+/// Boldness assumed for organisms recorded before the trait existed.
+const LEGACY_BOLDNESS: f32 = 0.5;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrganismWithPosition {
+    pub organism: Organism,
+    pub position: Position,
+    /// The biome the organism is most tolerant of, see [`Biome::dominant`].
+    #[serde(default)]
+    pub dominant_biome: Option<Biome>,
+    /// The reproduction threshold as a fraction of `max_organism_energy`.
+    pub reproduction_fraction: f32,
+    #[serde(default)]
+    pub species: SpeciesId,
+}
+
+impl OrganismWithPosition {
+    pub fn new(
+        organism: &Organism,
+        species: SpeciesId,
+        position: Position,
+        config: &Config,
+    ) -> Self {
+        Self {
+            dominant_biome: Some(Biome::dominant(&organism.biome_tolerance).0),
+            reproduction_fraction: organism.reproduction_threshold
+                / config.organism.max_organism_energy,
+            organism: organism.clone(),
+            position,
+            species,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PredatorWithPosition {
+    pub predator: Predator,
+    pub position: Position,
+    /// The reproduction threshold as a fraction of `max_predator_energy`.
+    pub reproduction_fraction: f32,
+}
+
+impl PredatorWithPosition {
+    pub fn new(predator: &Predator, position: Position, config: &Config) -> Self {
+        Self {
+            predator: *predator,
+            position,
+            reproduction_fraction: predator.reproduction_threshold
+                / config.predator.max_predator_energy,
+        }
+    }
+}
+
+/// One line of `world_data.jsonl`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportData<'a> {
+    pub schema_version: u32,
+    /// The run that wrote the record, named in the provenance of the
+    /// `effective_config.toml` it ran with. Empty for records from before version 48.
+    pub run_id: String,
+    pub organisms: Vec<OrganismWithPosition>,
+    pub predators: Vec<PredatorWithPosition>,
+    pub world: Cow<'a, World>,
+    pub food: Cow<'a, [f32]>,
+    pub generation: usize,
+}
+
+/// One line of `summary_data.jsonl`, also sent as an event every `log_interval`
+/// generations.
+#[derive(Event, Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct GenerationStats {
+    pub schema_version: u32,
+    pub generation: u32,
+    /// `None` without `seasons`.
+    pub season: Option<Season>,
+    pub organism_count: usize,
+    /// `None` in a prey-only run, whose records leave every predator field out.
+    #[serde(flatten)]
+    pub predators: Option<PredatorStats>,
+    pub organisms_in_reserves: usize,
+    pub organism_avg_size: f32,
+    pub organism_avg_speed: f32,
+    pub organism_avg_energy: f32,
+    pub organism_avg_reproduction_threshold: f32,
+    pub organism_avg_boldness: f32,
+    pub organism_avg_exploration: f32,
+    pub organism_avg_parental_investment: f32,
+    pub organism_avg_altruism: f32,
+    pub organism_reserve_capacity: Distribution,
+    pub organism_avg_giving_up_density: f32,
+    /// Ticks organisms spent on a tile in the visits that ended this generation.
+    pub organism_residence_ticks: Distribution,
+    /// Average row of the organisms, which shifts as they migrate along a
+    /// north-south temperature gradient.
+    pub organism_mean_y: f32,
+    /// Organisms of each morph, indexed by morph.
+    pub morph_counts: Vec<usize>,
+    /// Energy organisms gained from food this generation divided by their count.
+    pub organism_energy_intake_per_capita: f32,
+    /// Gini coefficient of what each organism ate in the generation's tick, 0 when
+    /// they all ate alike; see `food_allocation`.
+    pub organism_intake_gini: f32,
+    /// Energy lost to digestion this generation; negative where a biome's
+    /// `food_nutrition` is above 1.
+    pub digestion_loss: f32,
+    /// Energy organisms handed to kin this generation.
+    pub energy_shared: f32,
+    /// Tolerance of all the organisms to each biome, added up.
+    pub biome_tally: PerBiome<f32>,
+    /// Organisms living on tiles of each biome.
+    pub organisms_per_biome: PerBiome<usize>,
+    pub average_food: f32,
+    /// Average food on water tiles, which organisms can only graze from the shore.
+    pub average_water_food: f32,
+    pub biome_changes: usize,
+    /// Organisms panicked by kills this generation, directly or by other organisms.
+    pub organisms_panicked: usize,
+    /// Share of the organisms on a starvation dispersal burst.
+    pub organisms_dispersing_fraction: f32,
+    /// Organisms still carrying an injury from a predator's failed attack.
+    pub organisms_injured: usize,
+    pub death_causes: HashMap<DeathCause, usize>,
+    /// Offspring born this generation.
+    pub organism_births: usize,
+    /// Organism births this generation by the age of the parent, in bins of
+    /// [`PARENT_AGE_BIN`](crate::resources::PARENT_AGE_BIN) generations from age 0.
+    pub organism_births_by_parent_age: Vec<usize>,
+    /// Mean lifetime offspring of the organisms that died this generation.
+    pub organism_mean_lifetime_offspring: f32,
+    /// Most lifetime offspring of an organism that died this generation.
+    pub organism_max_lifetime_offspring: u32,
+    /// Share of the offspring whose first `NEWBORN_WINDOW` ticks ended since the
+    /// previous record that died within them.
+    pub newborn_death_rate: f32,
+    /// Newborns this generation with each trait clamped at its (lower, upper) bound
+    /// during mutation, keyed like `organism.size`.
+    pub clamp_hits: HashMap<String, (usize, usize)>,
+    /// Count and trait averages of each organism species, empty without `[species_b]`.
+    pub species: Vec<SpeciesStats>,
+    /// Only on generations that are a multiple of `spatial_interval`.
+    pub spatial: Option<SpatialSummary>,
+    /// Only on generations that are a multiple of `variance_interval`.
+    pub trait_variance: Option<VarianceSummary>,
+    pub energy: EnergyStats,
+}
+
+/// The predator fields of [`GenerationStats`], written inline with the rest.
+/// `predator_count` is the one field every record with predators has, so it alone
+/// has no default: a record without it reads back as a prey-only one.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PredatorStats {
+    pub predator_count: usize,
+    #[serde(default)]
+    pub predators_in_reserves: usize,
+    /// Predators younger than `predator_maturity_age`.
+    #[serde(default)]
+    pub juvenile_predators: usize,
+    #[serde(default)]
+    pub adult_predators: usize,
+    #[serde(default)]
+    pub predator_avg_size: f32,
+    #[serde(default)]
+    pub predator_avg_speed: f32,
+    #[serde(default)]
+    pub predator_avg_energy: f32,
+    #[serde(default)]
+    pub predator_avg_reproduction_threshold: f32,
+    #[serde(default)]
+    pub predator_avg_hunting_efficiency: f32,
+    #[serde(default)]
+    pub predator_avg_satiation_threshold: f32,
+    #[serde(default)]
+    pub predator_avg_preferred_temperature: f32,
+    #[serde(default)]
+    pub predator_avg_hunt_cooldown: f32,
+    /// Predation deaths this generation divided by the predator count.
+    #[serde(default)]
+    pub predator_avg_kills: f32,
+    /// Share of the predators lying in ambush.
+    #[serde(default)]
+    pub predators_ambushing_fraction: f32,
+    /// Predators holding a territory.
+    #[serde(default)]
+    pub territories: usize,
+    /// Share of a territory's tiles that others claim too, averaged over territories.
+    #[serde(default)]
+    pub mean_territory_overlap: f32,
+    /// Organisms caught by predators this generation in each biome, by the biome of
+    /// the tile the prey was caught on.
+    #[serde(default)]
+    pub kills_per_biome: PerBiome<usize>,
+    /// Food from predator corpses left in the world.
+    #[serde(default)]
+    pub total_carrion: f32,
+    #[serde(default)]
+    pub predator_births: usize,
+    /// Predators that had the energy to reproduce but too few recent kills under
+    /// `min_kills_for_reproduction`.
+    #[serde(default)]
+    pub predator_births_blocked: usize,
+    #[serde(default)]
+    pub predator_mean_lifetime_offspring: f32,
+    #[serde(default)]
+    pub predator_max_lifetime_offspring: u32,
+}
+
+impl GenerationStats {
+    /// Living predators, 0 in a prey-only run.
+    pub fn predator_count(&self) -> usize {
+        self.predators
+            .as_ref()
+            .map_or(0, |predators| predators.predator_count)
+    }
+}
+
+/// A value for each biome, written as one field per biome in the order of
+/// [`Biome::ALL`], so that every line of a log has the same keys in the same order.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct PerBiome<T> {
+    pub forest: T,
+    pub desert: T,
+    pub water: T,
+    pub grassland: T,
+}
+
+impl<T> Index<Biome> for PerBiome<T> {
+    type Output = T;
+
+    fn index(&self, biome: Biome) -> &T {
+        match biome {
+            Biome::Forest => &self.forest,
+            Biome::Desert => &self.desert,
+            Biome::Water => &self.water,
+            Biome::Grassland => &self.grassland,
+        }
+    }
+}
+
+impl<T> IndexMut<Biome> for PerBiome<T> {
+    fn index_mut(&mut self, biome: Biome) -> &mut T {
+        match biome {
+            Biome::Forest => &mut self.forest,
+            Biome::Desert => &mut self.desert,
+            Biome::Water => &mut self.water,
+            Biome::Grassland => &mut self.grassland,
+        }
+    }
+}
+
+/// Smallest, largest and quartile values of a trait across a population, all 0
+/// for an empty one.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(default)]
+pub struct Distribution {
+    pub min: f32,
+    pub lower_quartile: f32,
+    pub median: f32,
+    pub upper_quartile: f32,
+    pub max: f32,
+}
+
+impl Distribution {
+    /// The distribution of `values`, which it sorts, by the nearest-rank method.
+    pub fn of(values: &mut [f32]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_by(f32::total_cmp);
+        let n = values.len();
+        let rank = |q: f32| values[((q * n as f32).ceil() as usize).clamp(1, n) - 1];
+        Self {
+            min: values[0],
+            lower_quartile: rank(0.25),
+            median: rank(0.5),
+            upper_quartile: rank(0.75),
+            max: values[n - 1],
+        }
+    }
+}
+
+/// Energy held at the end of a generation and the flows during it, with food
+/// counted as the energy it would give at `food_energy_conversion`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(default)]
+pub struct EnergyStats {
+    pub organisms: f32,
+    pub predators: f32,
+    /// Food on all tiles.
+    pub food: f32,
+    pub food_regenerated: f32,
+    /// Plant food and carrion eaten, before the digestion losses.
+    pub food_consumed: f32,
+    /// Spent by organisms and predators on moving and upkeep.
+    pub upkeep: f32,
+    /// Held by the individuals that were killed, crowded out or culled.
+    pub removed_by_deaths: f32,
+    /// Net energy organisms gained, or lost when negative, from the
+    /// `passive_energy_delta` of the biomes they stood in.
+    pub passive: f32,
+}
+
+/// Count and trait averages of the organisms of one species.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SpeciesStats {
+    pub species: SpeciesId,
+    pub count: usize,
+    pub avg_size: f32,
+    pub avg_speed: f32,
+    pub avg_energy: f32,
+    pub avg_reproduction_threshold: f32,
+    pub avg_boldness: f32,
+    pub avg_exploration: f32,
+    pub avg_parental_investment: f32,
+}
+
+impl SpeciesStats {
+    /// The stats of the `species` members of `organisms`.
+    pub fn new<'a>(
+        species: SpeciesId,
+        organisms: impl IntoIterator<Item = (&'a Organism, SpeciesId)>,
+    ) -> Self {
+        let mut stats = Self {
+            species,
+            ..Self::default()
+        };
+        for (organism, _) in organisms.into_iter().filter(|(_, of)| *of == species) {
+            stats.count += 1;
+            stats.avg_size += organism.size;
+            stats.avg_speed += organism.speed;
+            stats.avg_energy += organism.energy;
+            stats.avg_reproduction_threshold += organism.reproduction_threshold;
+            stats.avg_boldness += organism.boldness;
+            stats.avg_exploration += organism.exploration;
+            stats.avg_parental_investment += organism.parental_investment;
+        }
+        let count = stats.count.max(1) as f32;
+        stats.avg_size /= count;
+        stats.avg_speed /= count;
+        stats.avg_energy /= count;
+        stats.avg_reproduction_threshold /= count;
+        stats.avg_boldness /= count;
+        stats.avg_exploration /= count;
+        stats.avg_parental_investment /= count;
+        stats
+    }
+}
+
+/// Spatial statistics of each population, see [`crate::spatial`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SpatialSummary {
+    pub organisms: SpatialStats,
+    pub predators: SpatialStats,
+}
+
+/// The variance of every trait of each population split between and within
+/// lineages, see [`crate::variance`]. Traits are missing where a population has
+/// fewer than two lineages.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct VarianceSummary {
+    pub organisms: BTreeMap<String, VarianceComponents>,
+    pub predators: BTreeMap<String, VarianceComponents>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct VarianceComponents {
+    pub lineages: usize,
+    /// Variance of the lineage means, weighted by lineage size.
+    pub between: f32,
+    /// Mean variance inside the lineages.
+    pub within: f32,
+    /// `between` as a share of the total: near 1 when the lineages have diverged,
+    /// near 0 when the variation is standing within each. `None` without variance.
+    pub between_share: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SpatialStats {
+    pub occupied_tiles: usize,
+    /// Tiles in the bounding box of the population's positions.
+    pub bounding_box_area: usize,
+    /// Estimated from up to `spatial_sample` individuals.
+    pub mean_nearest_neighbor: Option<f32>,
+    /// Morisita's index over tile counts: 1 at random, above 1 when clustered.
+    pub morisita_index: Option<f32>,
+}
+
+/// Contents of `report.json`, written when the run finishes: the generation stats
+/// history, of which only every `stride`-th record and the last one are kept on long
+/// runs.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Report {
+    pub schema_version: u32,
+    pub generation: usize,
+    pub stride: usize,
+    pub history: Vec<GenerationStats>,
+    /// How the initial populations were shrunk to fit the world, if they were.
+    #[serde(default)]
+    pub density_adjustment: Option<DensityAdjustment>,
+    /// Config keys the run took from the defaults, see
+    /// [`DefaultedFields`](crate::config::DefaultedFields).
+    #[serde(default)]
+    pub defaulted_fields: Vec<String>,
+    /// [`state_hash`](crate::self_check::state_hash) of the final state in hex, the
+    /// same for a config and seed on every machine.
+    #[serde(default)]
+    pub state_hash: Option<String>,
+    /// Trait variance between and within the lineages alive at the end.
+    #[serde(default)]
+    pub trait_variance: Option<VarianceSummary>,
+}
+
+/// One line of `heritability.jsonl`: offspring-on-parent regression slope per trait
+/// over the latest `births` of `kind`, `None` where there was too little data.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HeritabilityRecord {
+    pub schema_version: u32,
+    pub generation: usize,
+    pub kind: EntityKind,
+    pub births: usize,
+    pub slopes: BTreeMap<String, Option<f32>>,
+}
+
+/// Why an individual was sampled.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum SamplePick {
+    /// Most energy of its kind.
+    Best,
+    /// Least energy of its kind.
+    Worst,
+    Random,
+}
+
+/// One line of `samples.jsonl`: one individual sampled in `generation`, see
+/// `sample_individuals_per_generation`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SampleRecord {
+    pub schema_version: u32,
+    pub generation: usize,
+    pub kind: EntityKind,
+    pub pick: SamplePick,
+    /// `Identity` id, missing for individuals without one.
+    pub id: Option<u64>,
+    pub position: Position,
+    pub energy: f32,
+    pub traits: TraitSnapshot,
+}
+
+/// Percentiles of the individuals in `percentile_individuals.jsonl`.
+pub const PERCENTILES: [u8; 5] = [5, 25, 50, 75, 95];
+
+/// One line of `percentile_individuals.jsonl`: the individual of `kind` at
+/// `percentile` of its kind when ranked by `rank` in `generation`, see
+/// `percentile_individuals`. Populations of fewer than five repeat individuals
+/// across percentiles.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PercentileRecord {
+    pub schema_version: u32,
+    pub generation: usize,
+    pub kind: EntityKind,
+    pub rank: PercentileRank,
+    pub percentile: u8,
+    /// `Identity` id, missing for individuals without one.
+    pub id: Option<u64>,
+    pub position: Position,
+    pub energy: f32,
+    pub size: f32,
+    pub traits: TraitSnapshot,
+}
+
+/// What a traced organism did with its moves in a tick.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceState {
+    /// Weighed its neighbouring tiles for each move.
+    Foraging,
+    /// Fled the kill that panicked it.
+    Panicked,
+    /// Wandered along its dispersal heading instead of weighing tiles.
+    Dispersing,
+    /// Stayed on a tile with more food than its giving-up density.
+    Grazing,
+}
+
+/// The cost of stepping onto one neighbouring tile, split into the terms that add up
+/// to `total`. Terms that lower the cost are negative.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TraceCandidate {
+    pub dx: isize,
+    pub dy: isize,
+    pub biome: Biome,
+    /// The biome's `organism_movement_cost`.
+    pub base_cost: f32,
+    /// The organism's tolerance to the biome, which `base_cost` is divided by.
+    pub tolerance: f32,
+    /// `base_cost / tolerance`.
+    pub terrain: f32,
+    pub fear: f32,
+    /// Minus the attraction of the tile's food.
+    pub food: f32,
+    pub kill_scent: f32,
+    /// Minus the pull towards the preferred temperature.
+    pub migration: f32,
+    /// Minus the distance from the kill, while panicked.
+    pub flee: f32,
+    /// The `legacy_movement_jitter`.
+    pub noise: f32,
+    pub total: f32,
+}
+
+impl TraceCandidate {
+    /// The terms added up in the order the movement adds them.
+    pub fn sum(&self) -> f32 {
+        self.terrain
+            + self.fear
+            + self.food
+            + self.kill_scent
+            + self.migration
+            + self.flee
+            + self.noise
+    }
+}
+
+/// One move of a traced organism: the tiles it weighed from `from` and the
+/// direction it took.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TraceStep {
+    pub from: Position,
+    /// Softmax temperature the direction was drawn at; 0 takes the cheapest.
+    pub temperature: f32,
+    pub candidates: Vec<TraceCandidate>,
+    /// Index into `candidates` of the direction taken.
+    pub chosen: usize,
+}
+
+/// One line of `trace.jsonl`: how the organism with the `trace_entity` id decided
+/// where to move in `generation`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TraceRecord {
+    pub schema_version: u32,
+    pub generation: usize,
+    pub id: u64,
+    pub state: TraceState,
+    pub energy: f32,
+    pub steps: Vec<TraceStep>,
+}
+
+/// One entry of `index.json`: where the record of `generation` starts in
+/// `world_data.jsonl`, with its headline numbers so a timeline can be drawn without
+/// reading the records.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct WorldIndexEntry {
+    pub generation: usize,
+    /// Byte offset of the record's line.
+    pub offset: u64,
+    pub organisms: usize,
+    pub predators: usize,
+    pub average_food: f32,
+}
+
+impl WorldIndexEntry {
+    /// The entry of `record`, found at `offset`.
+    pub fn new(record: &ExportData, offset: u64) -> Self {
+        Self {
+            generation: record.generation,
+            offset,
+            organisms: record.organisms.len(),
+            predators: record.predators.len(),
+            average_food: record.food.iter().sum::<f32>() / record.food.len().max(1) as f32,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SchemaError {
+    Io(std::io::Error),
+    Json {
+        line: usize,
+        source: serde_json::Error,
+    },
+    UnsupportedVersion {
+        found: u32,
+        supported: u32,
+    },
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaError::Io(err) => write!(f, "failed to read log: {}", err),
+            SchemaError::Json { line, source } => {
+                write!(f, "malformed record on line {}: {}", line, source)
+            }
+            SchemaError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "record has schema version {}, but this build only reads up to version {}",
+                found, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl From<std::io::Error> for SchemaError {
+    fn from(err: std::io::Error) -> Self {
+        SchemaError::Io(err)
+    }
+}
+
+fn schema_version(value: &Value) -> u32 {
+    value
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .map_or(1, |version| version as u32)
+}
+
+fn migrate_world_v1(value: &mut Value) {
+    if let Some(organisms) = value.get_mut("organisms").and_then(Value::as_array_mut) {
+        for entry in organisms {
+            if let Some(organism) = entry.get_mut("organism").and_then(Value::as_object_mut) {
+                organism
+                    .entry("boldness")
+                    .or_insert(Value::from(LEGACY_BOLDNESS));
+            }
+        }
+    }
+}
+
+/// Derives each individual's `reproduction_fraction` from its absolute threshold and
+/// the maximum energy in the record's own config.
+fn migrate_world_v9(value: &mut Value) {
+    let defaults = default_config();
+    let max_energy = |key: &str, default: f32| {
+        value
+            .get("config")
+            .and_then(|config| config.get(key))
+            .and_then(Value::as_f64)
+            .map_or(default, |max| max as f32)
+    };
+    let organism_max = max_energy("max_organism_energy", defaults.organism.max_organism_energy);
+    let predator_max = max_energy("max_predator_energy", defaults.predator.max_predator_energy);
+
+    for (list, kind, max) in [
+        ("organisms", "organism", organism_max),
+        ("predators", "predator", predator_max),
+    ] {
+        let Some(entries) = value.get_mut(list).and_then(Value::as_array_mut) else {
+            continue;
+        };
+        for entry in entries {
+            let threshold = entry
+                .get(kind)
+                .and_then(|individual| individual.get("reproduction_threshold"))
+                .and_then(Value::as_f64);
+            if let (Some(threshold), Some(entry)) = (threshold, entry.as_object_mut()) {
+                entry
+                    .entry("reproduction_fraction")
+                    .or_insert(Value::from(threshold as f32 / max));
+            }
+        }
+    }
+}
+
+/// Drops the config embedded in every record; the run id it is replaced by was not
+/// recorded, so it stays empty.
+fn migrate_world_v47(value: &mut Value) {
+    if let Some(record) = value.as_object_mut() {
+        record.remove("config");
+        record.entry("run_id").or_insert(Value::from(""));
+    }
+}
+
+fn migrate_stats_v1(value: &mut Value) {
+    if let Some(stats) = value.as_object_mut() {
+        stats
+            .entry("organism_avg_boldness")
+            .or_insert(Value::from(LEGACY_BOLDNESS));
+    }
+}
+
+/// Renames the keys of the biome maps from the `Biome` variant names to the fields
+/// of [`PerBiome`].
+fn migrate_stats_v35(value: &mut Value) {
+    for key in ["biome_tally", "kills_per_biome"] {
+        let Some(map) = value.get_mut(key).and_then(Value::as_object_mut) else {
+            continue;
+        };
+        *map = std::mem::take(map)
+            .into_iter()
+            .map(|(biome, value)| (biome.to_lowercase(), value))
+            .collect();
+    }
+}
+
+/// Migrations of one record type, each paired with the schema version that made it
+/// necessary: records older than that version go through it.
+type Migrations = &'static [(u32, fn(&mut Value))];
+
+const WORLD_MIGRATIONS: Migrations = &[
+    (2, migrate_world_v1),
+    (10, migrate_world_v9),
+    (48, migrate_world_v47),
+];
+const STATS_MIGRATIONS: Migrations = &[(2, migrate_stats_v1), (36, migrate_stats_v35)];
+
+/// Upgrades `value` in place to the current schema, running the migration for every
+/// version step it is behind.
+fn migrate(value: &mut Value, migrations: Migrations) -> Result<(), SchemaError> {
+    let version = schema_version(value);
+    if version > SCHEMA_VERSION {
+        return Err(SchemaError::UnsupportedVersion {
+            found: version,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    for &(introduced, migration) in migrations {
+        if version < introduced {
+            migration(value);
+        }
+    }
+    if let Some(record) = value.as_object_mut() {
+        record.insert("schema_version".into(), Value::from(SCHEMA_VERSION));
+    }
+    Ok(())
+}
+
+fn parse_line<T: DeserializeOwned>(
+    line: &str,
+    line_number: usize,
+    migrations: Migrations,
+) -> Result<T, SchemaError> {
+    let json_error = |source| SchemaError::Json {
+        line: line_number,
+        source,
+    };
+    let mut value: Value = serde_json::from_str(line).map_err(json_error)?;
+    migrate(&mut value, migrations)?;
+    serde_json::from_value(value).map_err(json_error)
+}
+
+pub fn parse_world_record(line: &str) -> Result<ExportData<'static>, SchemaError> {
+    parse_line(line, 1, WORLD_MIGRATIONS)
+}
+
+pub fn parse_generation_stats(line: &str) -> Result<GenerationStats, SchemaError> {
+    parse_line(line, 1, STATS_MIGRATIONS)
+}
+
+fn read_lines<T: DeserializeOwned>(
+    path: &Path,
+    migrations: Migrations,
+) -> Result<Vec<T>, SchemaError> {
+    fs::read_to_string(path)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_line(line, i + 1, migrations))
+        .collect()
+}
+
+pub fn read_world_records(path: &Path) -> Result<Vec<ExportData<'static>>, SchemaError> {
+    read_lines(path, WORLD_MIGRATIONS)
+}
+
+pub fn read_generation_stats(path: &Path) -> Result<Vec<GenerationStats>, SchemaError> {
+    read_lines(path, STATS_MIGRATIONS)
+}
+
+pub fn read_samples(path: &Path) -> Result<Vec<SampleRecord>, SchemaError> {
+    read_lines(path, &[])
+}
+
+pub fn read_percentiles(path: &Path) -> Result<Vec<PercentileRecord>, SchemaError> {
+    read_lines(path, &[])
+}
+
+pub fn read_trace(path: &Path) -> Result<Vec<TraceRecord>, SchemaError> {
+    read_lines(path, &[])
+}
+
+/// Index of a world log written before `index.json` existed, built by reading every
+/// record once.
+pub fn build_world_index(path: &Path) -> Result<Vec<WorldIndexEntry>, SchemaError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut index = Vec::new();
+    let mut offset = 0;
+    let mut line = String::new();
+    for line_number in 1.. {
+        line.clear();
+        let read = reader.read_line(&mut line)?;
+        if read == 0 {
+            break;
+        }
+        if !line.trim().is_empty() {
+            let record: ExportData = parse_line(&line, line_number, WORLD_MIGRATIONS)?;
+            index.push(WorldIndexEntry::new(&record, offset));
+        }
+        offset += read as u64;
+    }
+    Ok(index)
+}
+
+pub fn read_world_index(path: &Path) -> Result<Vec<WorldIndexEntry>, SchemaError> {
+    let json = fs::read_to_string(path)?;
+    serde_json::from_str(&json).map_err(|source| SchemaError::Json { line: 1, source })
+}
+
+/// The world record starting at `offset` of the log at `path`.
+pub fn read_world_record_at(path: &Path, offset: u64) -> Result<ExportData<'static>, SchemaError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    parse_world_record(&line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORLD_V1: &str = include_str!("../tests/fixtures/world_data_v1.jsonl");
+    const WORLD_V2: &str = include_str!("../tests/fixtures/world_data_v2.jsonl");
+    const SUMMARY_V1: &str = include_str!("../tests/fixtures/summary_data_v1.jsonl");
+    const SUMMARY_V2: &str = include_str!("../tests/fixtures/summary_data_v2.jsonl");
+    const BIOME_TALLIES: &str = include_str!("../tests/fixtures/biome_tallies.json");
+
+    #[test]
+    fn reads_v1_world_records() {
+        let records: Vec<_> = WORLD_V1
+            .lines()
+            .map(|line| parse_world_record(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].schema_version, SCHEMA_VERSION);
+        assert_eq!(records[0].world.width, 2);
+        assert_eq!(records[1].generation, 2);
+        assert_eq!(records[0].organisms[0].organism.boldness, LEGACY_BOLDNESS);
+        assert_eq!(records[0].predators.len(), 1);
+    }
+
+    #[test]
+    fn reads_v2_world_records() {
+        let record = parse_world_record(WORLD_V2.lines().next().unwrap()).unwrap();
+        assert_eq!(record.schema_version, SCHEMA_VERSION);
+        assert_eq!(record.organisms[0].organism.boldness, 0.9);
+        assert_eq!(record.food.len(), 4);
+    }
+
+    #[test]
+    fn migration_derives_reproduction_fractions_from_the_record_config() {
+        let record = parse_world_record(WORLD_V2.lines().next().unwrap()).unwrap();
+        let organism = &record.organisms[0];
+        let expected = organism.organism.reproduction_threshold
+            / default_config().organism.max_organism_energy;
+        assert_eq!(organism.reproduction_fraction, expected);
+        let predator = &record.predators[0];
+        assert_eq!(
+            predator.reproduction_fraction,
+            predator.predator.reproduction_threshold / 1500.0
+        );
+
+        let mut value: Value = serde_json::from_str(WORLD_V2.lines().next().unwrap()).unwrap();
+        value["schema_version"] = Value::from(9);
+        value["config"]["max_organism_energy"] = Value::from(40.0);
+        let record = parse_world_record(&value.to_string()).unwrap();
+        let organism = &record.organisms[0];
+        assert_eq!(
+            organism.reproduction_fraction,
+            organism.organism.reproduction_threshold / 40.0
+        );
+        // the config it was derived from is dropped with the version 48 migration
+        assert_eq!(record.run_id, "");
+    }
+
+    #[test]
+    fn reads_stats_of_both_versions() {
+        let v1 = parse_generation_stats(SUMMARY_V1.lines().next().unwrap()).unwrap();
+        assert_eq!(v1.schema_version, SCHEMA_VERSION);
+        assert_eq!(v1.organism_count, 3);
+        assert_eq!(v1.organism_avg_boldness, LEGACY_BOLDNESS);
+        assert_eq!(v1.biome_changes, 0);
+
+        let v2 = parse_generation_stats(SUMMARY_V2.lines().next().unwrap()).unwrap();
+        assert_eq!(v2.biome_changes, 4);
+        assert_eq!(v2.death_causes[&DeathCause::Predation], 2);
+        assert_eq!(v2.biome_tally[Biome::Forest], 3.6);
+        assert_eq!(v2.biome_tally[Biome::Grassland], 2.1);
+    }
+
+    #[test]
+    fn biome_maps_keep_their_keys_and_order() {
+        let mut stats = GenerationStats::default();
+        let mut kills_per_biome = PerBiome::default();
+        for (i, biome) in Biome::ALL.into_iter().enumerate() {
+            stats.biome_tally[biome] = i as f32 + 0.5;
+            kills_per_biome[biome] = i;
+        }
+        stats.predators = Some(PredatorStats {
+            kills_per_biome,
+            ..Default::default()
+        });
+        let line = serde_json::to_string(&stats).unwrap();
+        let biome_tally = format!(
+            "\"biome_tally\":{}",
+            serde_json::to_string(&stats.biome_tally).unwrap()
+        );
+        let kills = format!(
+            "\"kills_per_biome\":{}",
+            serde_json::to_string(&kills_per_biome).unwrap()
+        );
+        assert_eq!(
+            format!("{{{},{}}}", biome_tally, kills),
+            BIOME_TALLIES.trim()
+        );
+        for written in [biome_tally, kills] {
+            assert!(line.contains(&written), "{} not in {}", written, line);
+        }
+
+        // a line from before the fields reads the same as one written now
+        let mut old = serde_json::to_value(&stats).unwrap();
+        old["schema_version"] = Value::from(35);
+        old["biome_tally"] =
+            serde_json::json!({"Water": 2.5, "Forest": 0.5, "Grassland": 3.5, "Desert": 1.5});
+        old["kills_per_biome"] = serde_json::json!({"Desert": 1, "Grassland": 3, "Water": 2});
+        let migrated = parse_generation_stats(&old.to_string()).unwrap();
+        assert_eq!(migrated.biome_tally, stats.biome_tally);
+        assert_eq!(migrated.predators.unwrap().kills_per_biome, kills_per_biome);
+    }
+
+    #[test]
+    fn rejects_future_versions() {
+        let line = format!(
+            "{{\"schema_version\":{},\"generation\":1}}",
+            SCHEMA_VERSION + 1
+        );
+        match parse_generation_stats(&line) {
+            Err(SchemaError::UnsupportedVersion { found, supported }) => {
+                assert_eq!(found, SCHEMA_VERSION + 1);
+                assert_eq!(supported, SCHEMA_VERSION);
+            }
+            other => panic!("expected unsupported version error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn distribution_takes_nearest_rank_quartiles() {
+        let mut values = [8.0, 2.0, 6.0, 4.0, 10.0, 1.0, 3.0, 9.0];
+        let distribution = Distribution::of(&mut values);
+        assert_eq!(
+            distribution,
+            Distribution {
+                min: 1.0,
+                lower_quartile: 2.0,
+                median: 4.0,
+                upper_quartile: 8.0,
+                max: 10.0,
+            }
+        );
+        assert_eq!(Distribution::of(&mut []), Distribution::default());
+    }
+}
@@ -0,0 +1,340 @@
+//! Populations carried from one run into another. A seed bank holds the traits of
+//! every living organism and predator, without positions or the world they lived
+//! in; a run with `seed_population` set draws its founders from one.
+
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Active, EntityKind, Organism, Predator};
+use crate::provenance::{write_artifact, Provenance};
+use crate::resources::PARENTAL_INVESTMENT_BOUNDS;
+
+pub const SEED_BANK_VERSION: u32 = 1;
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+pub struct SeedBank {
+    pub version: u32,
+    /// Generation the population was exported at.
+    pub generation: usize,
+    pub organisms: Vec<Organism>,
+    pub predators: Vec<Predator>,
+}
+
+#[derive(Debug)]
+pub enum SeedBankError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    UnsupportedVersion {
+        found: u32,
+        supported: u32,
+    },
+    /// Every trait outside the range the simulation can run with.
+    InvalidTraits(Vec<String>),
+}
+
+impl Display for SeedBankError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SeedBankError::Io(err) => write!(f, "{}", err),
+            SeedBankError::Json(err) => write!(f, "malformed seed bank: {}", err),
+            SeedBankError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "seed bank has version {}, but this build only reads up to version {}",
+                found, supported
+            ),
+            SeedBankError::InvalidTraits(errors) => {
+                write!(f, "invalid traits: {}", errors.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for SeedBankError {}
+
+impl From<std::io::Error> for SeedBankError {
+    fn from(err: std::io::Error) -> Self {
+        SeedBankError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SeedBankError {
+    fn from(err: serde_json::Error) -> Self {
+        SeedBankError::Json(err)
+    }
+}
+
+fn check_positive(errors: &mut Vec<String>, who: &str, name: &str, value: f32) {
+    if !value.is_finite() || value <= 0.0 {
+        errors.push(format!("{}: {} must be positive, got {}", who, name, value));
+    }
+}
+
+fn check_non_negative(errors: &mut Vec<String>, who: &str, name: &str, value: f32) {
+    if !value.is_finite() || value < 0.0 {
+        errors.push(format!(
+            "{}: {} must not be negative, got {}",
+            who, name, value
+        ));
+    }
+}
+
+impl SeedBank {
+    /// The living individuals among `organisms` and `predators`.
+    pub fn new<'a>(
+        organisms: impl IntoIterator<Item = &'a Organism>,
+        predators: impl IntoIterator<Item = &'a Predator>,
+        generation: usize,
+    ) -> Self {
+        Self {
+            version: SEED_BANK_VERSION,
+            generation,
+            organisms: organisms
+                .into_iter()
+                .filter(|organism| organism.energy > 0.0)
+                .cloned()
+                .collect(),
+            predators: predators
+                .into_iter()
+                .filter(|predator| predator.energy > 0.0)
+                .copied()
+                .collect(),
+        }
+    }
+
+    /// Collects the living population of a running simulation.
+    pub fn from_ecs(ecs: &mut bevy::prelude::World, generation: usize) -> Self {
+        let organisms: Vec<_> = ecs
+            .query_filtered::<&Organism, Active>()
+            .iter(ecs)
+            .cloned()
+            .collect();
+        let predators: Vec<_> = ecs
+            .query_filtered::<&Predator, Active>()
+            .iter(ecs)
+            .copied()
+            .collect();
+        Self::new(&organisms, &predators, generation)
+    }
+
+    /// Writes the bank as JSON to `path`, with a sidecar holding `provenance`.
+    pub fn save(&self, path: &Path, provenance: &Provenance) -> Result<(), SeedBankError> {
+        write_artifact(path, serde_json::to_string(self)?, provenance)?;
+        Ok(())
+    }
+
+    /// Reads a seed bank and checks its version and traits.
+    pub fn load(path: &Path) -> Result<Self, SeedBankError> {
+        let bank: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if bank.version > SEED_BANK_VERSION {
+            return Err(SeedBankError::UnsupportedVersion {
+                found: bank.version,
+                supported: SEED_BANK_VERSION,
+            });
+        }
+        bank.validate()?;
+        Ok(bank)
+    }
+
+    /// Collects every trait the simulation cannot run with, rather than stopping at
+    /// the first one.
+    pub fn validate(&self) -> Result<(), SeedBankError> {
+        let mut errors = Vec::new();
+        for (i, organism) in self.organisms.iter().enumerate() {
+            let who = format!("{:?} {}", EntityKind::Organism, i);
+            check_positive(&mut errors, &who, "size", organism.size);
+            check_positive(&mut errors, &who, "speed", organism.speed);
+            check_positive(
+                &mut errors,
+                &who,
+                "reproduction_threshold",
+                organism.reproduction_threshold,
+            );
+            check_non_negative(&mut errors, &who, "exploration", organism.exploration);
+            check_non_negative(&mut errors, &who, "dispersal", organism.dispersal);
+            check_non_negative(
+                &mut errors,
+                &who,
+                "starvation_trigger",
+                organism.starvation_trigger,
+            );
+            check_positive(&mut errors, &who, "burst_duration", organism.burst_duration);
+            check_positive(
+                &mut errors,
+                &who,
+                "reserve_capacity",
+                organism.reserve_capacity,
+            );
+            check_non_negative(
+                &mut errors,
+                &who,
+                "migratory_tendency",
+                organism.migratory_tendency,
+            );
+            check_non_negative(
+                &mut errors,
+                &who,
+                "giving_up_density",
+                organism.giving_up_density,
+            );
+            if !organism.preferred_temperature.is_finite() {
+                errors.push(format!(
+                    "{}: preferred_temperature must be finite, got {}",
+                    who, organism.preferred_temperature
+                ));
+            }
+            if !(0.0..=1.0).contains(&organism.boldness) {
+                errors.push(format!(
+                    "{}: boldness must be between 0 and 1, got {}",
+                    who, organism.boldness
+                ));
+            }
+            if !(0.0..=1.0).contains(&organism.altruism) {
+                errors.push(format!(
+                    "{}: altruism must be between 0 and 1, got {}",
+                    who, organism.altruism
+                ));
+            }
+            let (low, high) = PARENTAL_INVESTMENT_BOUNDS;
+            if !(low..=high).contains(&organism.parental_investment) {
+                errors.push(format!(
+                    "{}: parental_investment must be between {} and {}, got {}",
+                    who, low, high, organism.parental_investment
+                ));
+            }
+            for tolerance in organism.biome_tolerance {
+                check_positive(&mut errors, &who, "biome_tolerance", tolerance);
+            }
+        }
+        for (i, predator) in self.predators.iter().enumerate() {
+            let who = format!("{:?} {}", EntityKind::Predator, i);
+            check_positive(&mut errors, &who, "size", predator.size);
+            check_positive(&mut errors, &who, "speed", predator.speed);
+            check_positive(
+                &mut errors,
+                &who,
+                "reproduction_threshold",
+                predator.reproduction_threshold,
+            );
+            check_non_negative(
+                &mut errors,
+                &who,
+                "hunting_efficiency",
+                predator.hunting_efficiency,
+            );
+            check_non_negative(
+                &mut errors,
+                &who,
+                "satiation_threshold",
+                predator.satiation_threshold,
+            );
+            check_non_negative(
+                &mut errors,
+                &who,
+                "hunt_cooldown_ticks",
+                predator.hunt_cooldown_ticks,
+            );
+            if !(0.0..=1.0).contains(&predator.patience) {
+                errors.push(format!(
+                    "{}: patience must be between 0 and 1, got {}",
+                    who, predator.patience
+                ));
+            }
+            if !(0.0..=1.0).contains(&predator.aggression) {
+                errors.push(format!(
+                    "{}: aggression must be between 0 and 1, got {}",
+                    who, predator.aggression
+                ));
+            }
+            if !predator.preferred_temperature.is_finite() {
+                errors.push(format!(
+                    "{}: preferred_temperature must be finite, got {}",
+                    who, predator.preferred_temperature
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(SeedBankError::InvalidTraits(errors))
+        }
+    }
+}
+
+/// `count` individuals drawn from `pool`: each exactly once when the counts match,
+/// otherwise with replacement. Empty when the pool is.
+pub fn sample<'a, T>(pool: &'a [T], count: usize, rng: &mut impl Rng) -> Vec<&'a T> {
+    if count == pool.len() {
+        return pool.iter().collect();
+    }
+    (0..count).filter_map(|_| pool.choose(rng)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+
+    use super::*;
+    use crate::plugins::simulation::{founder_organism, founder_predator};
+    use crate::provenance::{meta_path, RunId};
+    use crate::utils::default_config;
+
+    fn bank() -> SeedBank {
+        let config = default_config();
+        let organisms = [founder_organism(&config, [1.0; 4])];
+        let predators = [founder_predator(&config)];
+        SeedBank::new(&organisms, &predators, 7)
+    }
+
+    #[test]
+    fn invalid_traits_are_all_reported() {
+        assert!(bank().validate().is_ok());
+
+        let mut bank = bank();
+        bank.organisms[0].size = -1.0;
+        bank.organisms[0].boldness = 2.0;
+        bank.predators[0].speed = f32::NAN;
+        match bank.validate() {
+            Err(SeedBankError::InvalidTraits(errors)) => {
+                assert_eq!(errors.len(), 3, "{:?}", errors);
+                assert!(errors[0].starts_with("Organism 0: size"));
+                assert!(errors[2].starts_with("Predator 0: speed"));
+            }
+            other => panic!("expected invalid traits, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let path = std::env::temp_dir().join("evolution_seed_bank_version.json");
+        let mut bank = bank();
+        bank.version = SEED_BANK_VERSION + 1;
+        let provenance = Provenance::new(&RunId("1-0".into()), 1, bank.generation, None);
+        bank.save(&path, &provenance).unwrap();
+
+        let result = SeedBank::load(&path);
+        std::fs::remove_file(&path).ok();
+        std::fs::remove_file(meta_path(&path)).ok();
+        assert!(matches!(
+            result,
+            Err(SeedBankError::UnsupportedVersion { found, .. }) if found == SEED_BANK_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn sampling_takes_everyone_once_when_counts_match() {
+        let pool = [1, 2, 3];
+        let mut rng = SmallRng::seed_from_u64(0);
+        assert_eq!(sample(&pool, 3, &mut rng), vec![&1, &2, &3]);
+
+        let drawn = sample(&pool, 10, &mut rng);
+        assert_eq!(drawn.len(), 10);
+        assert!(drawn.iter().all(|value| pool.contains(value)));
+        assert!(sample::<u8>(&[], 4, &mut rng).is_empty());
+    }
+}
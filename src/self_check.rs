@@ -0,0 +1,462 @@
+//! Determinism self-check, started with `--self-check N`: the first `N` generations
+//! are run twice from the same config and the state hashed after every generation,
+//! so a run that would not replay is caught before it is trusted.
+//!
+//! The hash is [`state_hash`], also printed when a run finishes and kept in
+//! `report.json` as a fingerprint to compare runs on different machines by.
+
+use std::fmt::Display;
+use std::hash::Hasher;
+
+use bevy::app::PluginsState;
+use bevy::prelude::*;
+use serde_json::{json, Value};
+
+use crate::app::try_build_app;
+use crate::bench::bench_config;
+use crate::components::{Active, Identity, Organism, Position, Predator, SpeciesId};
+use crate::config::ConfigError;
+use crate::resources::{AppState, Config, FoodGrid, Generation, World};
+
+/// FNV-1a, which unlike the std hashers is guaranteed to stay the same across Rust
+/// versions and platforms, so hashes can be compared between builds.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Step floats are rounded to before [`state_hash`] hashes them, so that the last
+/// bits of a value, which may differ between platforms, do not change the hash.
+pub const HASH_PRECISION: f64 = 1e-4;
+
+fn write_quantized(hasher: &mut Fnv, value: f64) {
+    if value.is_finite() {
+        hasher.write_u8(0);
+        hasher.write_i64((value / HASH_PRECISION).round() as i64);
+    } else {
+        hasher.write_u8(1);
+        hasher.write_u64(value.to_bits());
+    }
+}
+
+/// Feeds `value` to `hasher`, tagging every kind of value and length so that
+/// different values cannot run together, with object keys in sorted order and
+/// floats quantized to [`HASH_PRECISION`].
+fn write_value(hasher: &mut Fnv, value: &Value) {
+    match value {
+        Value::Null => hasher.write_u8(0),
+        Value::Bool(value) => {
+            hasher.write_u8(1);
+            hasher.write_u8(*value as u8);
+        }
+        Value::Number(number) => {
+            if let Some(value) = number.as_i64() {
+                hasher.write_u8(2);
+                hasher.write_i64(value);
+            } else if let Some(value) = number.as_u64() {
+                hasher.write_u8(3);
+                hasher.write_u64(value);
+            } else {
+                hasher.write_u8(4);
+                write_quantized(hasher, number.as_f64().unwrap_or(f64::NAN));
+            }
+        }
+        Value::String(value) => {
+            hasher.write_u8(5);
+            hasher.write_u64(value.len() as u64);
+            hasher.write(value.as_bytes());
+        }
+        Value::Array(values) => {
+            hasher.write_u8(6);
+            hasher.write_u64(values.len() as u64);
+            for value in values {
+                write_value(hasher, value);
+            }
+        }
+        Value::Object(fields) => {
+            hasher.write_u8(7);
+            hasher.write_u64(fields.len() as u64);
+            let mut fields: Vec<_> = fields.iter().collect();
+            fields.sort_by_key(|(key, _)| *key);
+            for (key, value) in fields {
+                write_value(hasher, &Value::String(key.clone()));
+                write_value(hasher, value);
+            }
+        }
+    }
+}
+
+/// Hash of the fields of one individual.
+fn individual_hash(individual: &impl serde::Serialize) -> u64 {
+    let mut hasher = Fnv::default();
+    let value = serde_json::to_value(individual).expect("individuals serialize to JSON");
+    write_value(&mut hasher, &value);
+    hasher.finish()
+}
+
+/// A fingerprint of a simulation state that stays the same across machines, Rust
+/// releases and the order the individuals are listed in.
+///
+/// It is FNV-1a over, in order: `generation`; the width and height of `world`, then
+/// the biome, temperature, humidity and reserve flag of each tile row by row; then
+/// the organisms and after them the predators, each sorted by position and then by
+/// the hash of its fields. Every float is first rounded to a multiple of
+/// [`HASH_PRECISION`], so states a rounding step apart hash alike, except for
+/// values that happen to straddle a step.
+pub fn state_hash(
+    world: &World,
+    organisms: &[(Organism, Position)],
+    predators: &[(Predator, Position)],
+    generation: usize,
+) -> u64 {
+    let mut hasher = Fnv::default();
+    hasher.write_u64(generation as u64);
+    hasher.write_u64(world.width as u64);
+    hasher.write_u64(world.height as u64);
+    for tile in &world.grid {
+        hasher.write_u8(tile.biome.idx() as u8);
+        write_quantized(&mut hasher, tile.temperature as f64);
+        write_quantized(&mut hasher, tile.humidity as f64);
+        hasher.write_u8(tile.reserve as u8);
+    }
+
+    let mut sorted = |individuals: Vec<(&Position, u64)>| {
+        let mut keys: Vec<_> = individuals
+            .into_iter()
+            .map(|(position, hash)| (position.x, position.y, hash))
+            .collect();
+        keys.sort_unstable();
+        hasher.write_u64(keys.len() as u64);
+        for (x, y, hash) in keys {
+            hasher.write_u64(x as u64);
+            hasher.write_u64(y as u64);
+            hasher.write_u64(hash);
+        }
+    };
+    sorted(
+        organisms
+            .iter()
+            .map(|(organism, position)| (position, individual_hash(organism)))
+            .collect(),
+    );
+    sorted(
+        predators
+            .iter()
+            .map(|(predator, position)| (position, individual_hash(predator)))
+            .collect(),
+    );
+    hasher.finish()
+}
+
+/// [`state_hash`] of the living in `app`.
+pub fn app_state_hash(app: &mut App) -> u64 {
+    let ecs = app.world_mut();
+    let organisms: Vec<(Organism, Position)> = ecs
+        .query_filtered::<(&Organism, &Position), Active>()
+        .iter(ecs)
+        .map(|(organism, position)| (organism.clone(), *position))
+        .collect();
+    let predators: Vec<(Predator, Position)> = ecs
+        .query_filtered::<(&Predator, &Position), Active>()
+        .iter(ecs)
+        .map(|(predator, position)| (*predator, *position))
+        .collect();
+    state_hash(
+        ecs.resource::<World>(),
+        &organisms,
+        &predators,
+        ecs.resource::<Generation>().0,
+    )
+}
+
+/// Everything a generation leaves behind that the next one depends on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateSnapshot {
+    pub generation: usize,
+    /// [`state_hash`] of the state.
+    pub fingerprint: u64,
+    pub organisms: usize,
+    pub predators: usize,
+    /// Every individual as JSON, sorted by id.
+    pub entities: Vec<(u64, Value)>,
+    pub food: Vec<f32>,
+    pub biomes: Vec<usize>,
+}
+
+impl StateSnapshot {
+    pub fn capture(app: &mut App) -> Self {
+        let fingerprint = app_state_hash(app);
+        let generation = app.world().resource::<Generation>().0;
+        let world = app.world().resource::<World>();
+        let biomes = world.grid.iter().map(|tile| tile.biome.idx()).collect();
+        let food = app.world().resource::<FoodGrid>().values().to_vec();
+
+        let ecs = app.world_mut();
+        let mut entities: Vec<(u64, Value)> = ecs
+            .query_filtered::<(
+                &Identity,
+                &Position,
+                Option<&SpeciesId>,
+                Option<&Organism>,
+                Option<&Predator>,
+            ), Active>()
+            .iter(ecs)
+            .map(|(identity, position, species, organism, predator)| {
+                let state = json!({
+                    "identity": identity,
+                    "position": position,
+                    "species": species.map(|species| species.0),
+                    "organism": organism,
+                    "predator": predator,
+                });
+                (identity.id, state)
+            })
+            .collect();
+        entities.sort_by_key(|(id, _)| *id);
+        let predators = entities
+            .iter()
+            .filter(|(_, state)| !state["predator"].is_null())
+            .count();
+
+        Self {
+            generation,
+            fingerprint,
+            organisms: entities.len() - predators,
+            predators,
+            entities,
+            food,
+            biomes,
+        }
+    }
+
+    /// [`state_hash`] extended with the exact values of every individual, its
+    /// identity and the food, for two runs on one machine that must match bit for
+    /// bit.
+    pub fn hash(&self) -> u64 {
+        let mut hasher = Fnv::default();
+        hasher.write_u64(self.fingerprint);
+        for (id, state) in &self.entities {
+            hasher.write_u64(*id);
+            hasher.write(state.to_string().as_bytes());
+        }
+        for food in &self.food {
+            hasher.write_u32(food.to_bits());
+        }
+        for &biome in &self.biomes {
+            hasher.write_u8(biome as u8);
+        }
+        hasher.finish()
+    }
+
+    /// The first thing that differs from `other`, described for a person.
+    pub fn first_difference(&self, other: &StateSnapshot) -> Option<String> {
+        if (self.organisms, self.predators) != (other.organisms, other.predators) {
+            return Some(format!(
+                "{} organisms and {} predators vs {} organisms and {} predators",
+                self.organisms, self.predators, other.organisms, other.predators
+            ));
+        }
+        for ((id, state), (other_id, other_state)) in self.entities.iter().zip(&other.entities) {
+            if id != other_id {
+                return Some(format!("individual {} vs individual {}", id, other_id));
+            }
+            if let Some(field) = field_difference(state, other_state, "") {
+                return Some(format!("individual {}: {}", id, field));
+            }
+        }
+        for (i, (food, other_food)) in self.food.iter().zip(&other.food).enumerate() {
+            if food.to_bits() != other_food.to_bits() {
+                return Some(format!("food of tile {}: {} vs {}", i, food, other_food));
+            }
+        }
+        for (i, (biome, other_biome)) in self.biomes.iter().zip(&other.biomes).enumerate() {
+            if biome != other_biome {
+                return Some(format!("biome of tile {}: {} vs {}", i, biome, other_biome));
+            }
+        }
+        None
+    }
+}
+
+/// The dotted path and both values of the first leaf at which `a` and `b` differ.
+fn field_difference(a: &Value, b: &Value, path: &str) -> Option<String> {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => a.iter().find_map(|(key, value)| {
+            let path = match path {
+                "" => key.clone(),
+                _ => format!("{}.{}", path, key),
+            };
+            field_difference(value, b.get(key).unwrap_or(&Value::Null), &path)
+        }),
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => a
+            .iter()
+            .zip(b)
+            .enumerate()
+            .find_map(|(i, (a, b))| field_difference(a, b, &format!("{}[{}]", path, i))),
+        _ if a == b => None,
+        _ => Some(format!("{} {} vs {}", path, a, b)),
+    }
+}
+
+/// Where two runs from the same config first parted ways.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub generation: usize,
+    pub first: (usize, usize),
+    pub second: (usize, usize),
+    pub difference: String,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "runs diverged at generation {}", self.generation)?;
+        writeln!(
+            f,
+            "first run:  {} organisms, {} predators",
+            self.first.0, self.first.1
+        )?;
+        writeln!(
+            f,
+            "second run: {} organisms, {} predators",
+            self.second.0, self.second.1
+        )?;
+        write!(f, "first difference: {}", self.difference)
+    }
+}
+
+#[derive(Debug)]
+pub enum SelfCheckError {
+    Config(ConfigError),
+    Diverged(Divergence),
+}
+
+impl Display for SelfCheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelfCheckError::Config(err) => write!(f, "invalid config: {}", err),
+            SelfCheckError::Diverged(divergence) => write!(f, "{}", divergence),
+        }
+    }
+}
+
+impl std::error::Error for SelfCheckError {}
+
+/// Runs the first `generations` generations of `config` twice, quietly and without
+/// writing any files, failing at the first generation whose states differ.
+pub fn run(config: &Config, generations: usize) -> Result<(), SelfCheckError> {
+    let build = || try_build_app(bench_config(config)).map_err(SelfCheckError::Config);
+    compare(build()?, build()?, generations).map_err(SelfCheckError::Diverged)
+}
+
+/// Steps `first` and `second` in lockstep for `generations` generations, or until
+/// the first one finishes, comparing their state hashes after each one.
+pub fn compare(mut first: App, mut second: App, generations: usize) -> Result<(), Divergence> {
+    for app in [&mut first, &mut second] {
+        if app.plugins_state() != PluginsState::Cleaned {
+            app.finish();
+            app.cleanup();
+        }
+    }
+    let finished =
+        |app: &App| *app.world().resource::<State<AppState>>().get() == AppState::Finished;
+    while first.world().resource::<Generation>().0 < generations && !finished(&first) {
+        first.update();
+        second.update();
+        let a = StateSnapshot::capture(&mut first);
+        let b = StateSnapshot::capture(&mut second);
+        if a.hash() != b.hash() {
+            return Err(Divergence {
+                generation: a.generation,
+                first: (a.organisms, a.predators),
+                second: (b.organisms, b.predators),
+                difference: a
+                    .first_difference(&b)
+                    .unwrap_or_else(|| "states hash differently".to_string()),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::simulation::{founder_organism, founder_predator};
+
+    #[test]
+    fn field_differences_name_the_leaf() {
+        let a = json!({"organism": {"energy": 1.5, "biome_tolerance": [1.0, 2.0]}});
+        let b = json!({"organism": {"energy": 1.5, "biome_tolerance": [1.0, 2.5]}});
+        assert_eq!(field_difference(&a, &a, ""), None);
+        assert_eq!(
+            field_difference(&a, &b, "").unwrap(),
+            "organism.biome_tolerance[1] 2.0 vs 2.5"
+        );
+    }
+
+    type State = (World, Vec<(Organism, Position)>, Vec<(Predator, Position)>);
+
+    /// A small world with three organisms and a predator.
+    fn state() -> State {
+        let config = crate::utils::default_config();
+        let (world, _) = World::new(4, 4, 3);
+        let organisms = (0..3)
+            .map(|i| {
+                let mut organism = founder_organism(&config, [1.0; 4]);
+                organism.energy = 5.0 + i as f32;
+                (organism, Position { x: i, y: 1 })
+            })
+            .collect();
+        let predators = vec![(founder_predator(&config), Position { x: 2, y: 2 })];
+        (world, organisms, predators)
+    }
+
+    #[test]
+    fn state_hashes_ignore_order_and_noise_below_the_precision() {
+        let (world, organisms, predators) = state();
+        let hash = state_hash(&world, &organisms, &predators, 7);
+        assert_eq!(hash, state_hash(&world, &organisms, &predators, 7));
+        assert_ne!(hash, state_hash(&world, &organisms, &predators, 8));
+
+        let mut reversed = organisms.clone();
+        reversed.reverse();
+        assert_eq!(hash, state_hash(&world, &reversed, &predators, 7));
+
+        let mut noisy = organisms.clone();
+        noisy[1].0.energy += (HASH_PRECISION / 10.0) as f32;
+        assert_eq!(hash, state_hash(&world, &noisy, &predators, 7));
+
+        let mut changed = organisms.clone();
+        changed[1].0.energy += (HASH_PRECISION * 10.0) as f32;
+        assert_ne!(hash, state_hash(&world, &changed, &predators, 7));
+
+        let mut moved = predators.clone();
+        moved[0].1.x = 3;
+        assert_ne!(hash, state_hash(&world, &organisms, &moved, 7));
+        // an organism is not a predator standing on the same tile
+        assert_ne!(hash, state_hash(&world, &organisms, &[], 7));
+    }
+
+    #[test]
+    fn hashes_are_stable() {
+        let mut hasher = Fnv::default();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -0,0 +1,182 @@
+//! Spatial statistics of a population, computed from its positions alone.
+
+use bevy::utils::hashbrown::HashMap;
+
+use crate::components::Position;
+use crate::schema::SpatialStats;
+
+/// Number of individuals on each occupied tile.
+fn tile_counts(positions: &[Position]) -> HashMap<(usize, usize), usize> {
+    let mut counts = HashMap::new();
+    for position in positions {
+        *counts.entry((position.x, position.y)).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Number of distinct tiles with at least one individual.
+pub fn occupied_tiles(positions: &[Position]) -> usize {
+    tile_counts(positions).len()
+}
+
+/// Tiles covered by the smallest axis-aligned box around every position, 0 for an
+/// empty population.
+pub fn bounding_box_area(positions: &[Position]) -> usize {
+    let Some(first) = positions.first() else {
+        return 0;
+    };
+    let (mut min_x, mut max_x, mut min_y, mut max_y) = (first.x, first.x, first.y, first.y);
+    for position in positions {
+        min_x = min_x.min(position.x);
+        max_x = max_x.max(position.x);
+        min_y = min_y.min(position.y);
+        max_y = max_y.max(position.y);
+    }
+    (max_x - min_x + 1) * (max_y - min_y + 1)
+}
+
+/// Mean distance in tiles from an individual to its nearest neighbour. Only every
+/// k-th individual is measured when there are more than `sample`, though always
+/// against the whole population. `None` with fewer than two individuals.
+pub fn mean_nearest_neighbor_distance(positions: &[Position], sample: usize) -> Option<f32> {
+    if positions.len() < 2 {
+        return None;
+    }
+    let step = positions.len().div_ceil(sample.max(1));
+
+    let mut total = 0.0;
+    let mut measured = 0;
+    for (i, a) in positions.iter().enumerate().step_by(step) {
+        let nearest = positions
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| j != i)
+            .map(|(_, b)| {
+                let dx = a.x as f32 - b.x as f32;
+                let dy = a.y as f32 - b.y as f32;
+                dx * dx + dy * dy
+            })
+            .fold(f32::INFINITY, f32::min);
+        total += nearest.sqrt();
+        measured += 1;
+    }
+    Some(total / measured as f32)
+}
+
+/// Morisita's index of dispersion over the `tiles` tiles of the world: about 1 for
+/// individuals placed at random, above 1 when they cluster and below 1 when they
+/// spread out evenly. `None` with fewer than two individuals.
+pub fn morisita_index(positions: &[Position], tiles: usize) -> Option<f32> {
+    let n = positions.len();
+    if n < 2 {
+        return None;
+    }
+    let same_tile_pairs: usize = tile_counts(positions)
+        .values()
+        .map(|&count| count * (count - 1))
+        .sum();
+    Some(tiles as f32 * same_tile_pairs as f32 / (n * (n - 1)) as f32)
+}
+
+/// Every statistic of one population in a `width` x `height` world.
+pub fn spatial_stats(
+    positions: &[Position],
+    width: usize,
+    height: usize,
+    sample: usize,
+) -> SpatialStats {
+    SpatialStats {
+        occupied_tiles: occupied_tiles(positions),
+        bounding_box_area: bounding_box_area(positions),
+        mean_nearest_neighbor: mean_nearest_neighbor_distance(positions, sample),
+        morisita_index: morisita_index(positions, width * height),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    /// One individual on every `spacing`-th tile of a `size` x `size` world.
+    fn uniform(size: usize, spacing: usize) -> Vec<Position> {
+        (0..size)
+            .step_by(spacing)
+            .flat_map(|y| (0..size).step_by(spacing).map(move |x| Position { x, y }))
+            .collect()
+    }
+
+    /// `per_cluster` individuals on each of the four tiles around the corners of the
+    /// `size` x `size` world.
+    fn clustered(size: usize, per_cluster: usize) -> Vec<Position> {
+        let corners = [(1, 1), (size - 2, 1), (1, size - 2), (size - 2, size - 2)];
+        corners
+            .iter()
+            .flat_map(|&(x, y)| std::iter::repeat_n(Position { x, y }, per_cluster))
+            .collect()
+    }
+
+    #[test]
+    fn occupied_tiles_and_extent() {
+        let uniform = uniform(20, 4);
+        assert_eq!(uniform.len(), 25);
+        assert_eq!(occupied_tiles(&uniform), 25);
+        assert_eq!(bounding_box_area(&uniform), 17 * 17);
+
+        let clustered = clustered(20, 5);
+        assert_eq!(occupied_tiles(&clustered), 4);
+        assert_eq!(bounding_box_area(&clustered), 18 * 18);
+
+        assert_eq!(occupied_tiles(&[]), 0);
+        assert_eq!(bounding_box_area(&[]), 0);
+        assert_eq!(bounding_box_area(&[Position { x: 3, y: 7 }]), 1);
+    }
+
+    #[test]
+    fn nearest_neighbor_distance_separates_uniform_from_clustered() {
+        assert_eq!(
+            mean_nearest_neighbor_distance(&uniform(20, 4), 1000),
+            Some(4.0)
+        );
+        assert_eq!(
+            mean_nearest_neighbor_distance(&clustered(20, 5), 1000),
+            Some(0.0)
+        );
+        assert_eq!(
+            mean_nearest_neighbor_distance(&[Position { x: 0, y: 0 }], 10),
+            None
+        );
+    }
+
+    #[test]
+    fn subsampled_nearest_neighbor_distance_stays_close() {
+        let positions = uniform(40, 2);
+        let full = mean_nearest_neighbor_distance(&positions, positions.len()).unwrap();
+        let sampled = mean_nearest_neighbor_distance(&positions, 37).unwrap();
+        assert_eq!(full, 2.0);
+        assert_eq!(sampled, 2.0);
+    }
+
+    #[test]
+    fn morisita_index_separates_uniform_from_clustered() {
+        let tiles = 20 * 20;
+        assert_eq!(morisita_index(&uniform(20, 4), tiles), Some(0.0));
+
+        let clustered = morisita_index(&clustered(20, 5), tiles).unwrap();
+        assert!(clustered > 50.0, "{}", clustered);
+
+        let mut rng = SmallRng::seed_from_u64(4);
+        let random: Vec<_> = (0..2000)
+            .map(|_| Position {
+                x: rng.gen_range(0..20),
+                y: rng.gen_range(0..20),
+            })
+            .collect();
+        let random = morisita_index(&random, tiles).unwrap();
+        assert!((random - 1.0).abs() < 0.1, "{}", random);
+
+        assert_eq!(morisita_index(&[Position { x: 0, y: 0 }], tiles), None);
+    }
+}
@@ -0,0 +1,466 @@
+//! Where the stats of a run are kept besides [`StatsHistory`](crate::resources::StatsHistory):
+//! `summary_data.jsonl` and `report.json` in the output directory natively, the
+//! browser's local storage on the web, where there are no files and a refresh loses
+//! the page. The logging plugin only talks to a [`StatsSink`], so it runs the same
+//! on either target.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use crate::provenance::{write_artifact, write_sidecar, Provenance, RunId};
+use crate::resources::{Config, LoggingConfig};
+use crate::schema::{GenerationStats, Report};
+
+#[derive(Debug)]
+pub enum SinkError {
+    Io(io::Error),
+    /// The storage has no room left, which stays so for the rest of the run.
+    QuotaExceeded,
+    Unavailable(String),
+}
+
+impl fmt::Display for SinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SinkError::Io(err) => write!(f, "{}", err),
+            SinkError::QuotaExceeded => write!(f, "storage quota exceeded"),
+            SinkError::Unavailable(reason) => write!(f, "storage unavailable: {}", reason),
+        }
+    }
+}
+
+impl Error for SinkError {}
+
+impl From<io::Error> for SinkError {
+    fn from(err: io::Error) -> Self {
+        SinkError::Io(err)
+    }
+}
+
+/// Somewhere the stats of a run are kept.
+pub trait StatsSink: Send + Sync {
+    /// Keeps the stats of a logged generation.
+    fn record(&mut self, stats: &GenerationStats) -> Result<(), SinkError>;
+
+    /// Keeps the report of the finished run.
+    fn finish(&mut self, report: &Report) -> Result<(), SinkError>;
+
+    /// Hands `history` to the user as JSON and says where it went.
+    fn export(&mut self, history: &[GenerationStats]) -> Result<String, SinkError>;
+}
+
+/// The sink of the run, and what to warn about once it failed. After the storage
+/// ran out of room no more generations are offered to it, but the run goes on.
+#[derive(Resource)]
+pub struct StatsStore {
+    sink: Box<dyn StatsSink>,
+    full: bool,
+    warning: Option<String>,
+}
+
+impl StatsStore {
+    pub fn new(sink: Box<dyn StatsSink>) -> Self {
+        Self {
+            sink,
+            full: false,
+            warning: None,
+        }
+    }
+
+    /// A [`FileSink`] natively, a `LocalStorageSink` on the web.
+    #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+    pub fn for_config(config: &Config, run_id: &RunId) -> Self {
+        #[cfg(target_arch = "wasm32")]
+        let sink = LocalStorageSink::new(run_id);
+        #[cfg(not(target_arch = "wasm32"))]
+        let sink = FileSink::new(
+            &config.logging,
+            Provenance::new(run_id, config.world.seed, 0, None),
+        );
+        Self::new(Box::new(sink))
+    }
+
+    /// [`StatsStore::for_config`] for a run resumed from an earlier state at
+    /// `generation`, adding to the stats kept so far instead of starting them over.
+    #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+    pub fn resumed(config: &Config, run_id: &RunId, generation: usize) -> Self {
+        #[cfg(target_arch = "wasm32")]
+        let sink = LocalStorageSink::new(run_id);
+        #[cfg(not(target_arch = "wasm32"))]
+        let sink = FileSink::appending(
+            &config.logging,
+            Provenance::new(run_id, config.world.seed, generation, None),
+        );
+        Self::new(Box::new(sink))
+    }
+
+    pub fn record(&mut self, stats: &GenerationStats) {
+        if self.full {
+            return;
+        }
+        if let Err(err) = self.sink.record(stats) {
+            self.fail(err);
+        }
+    }
+
+    pub fn finish(&mut self, report: &Report) {
+        if let Err(err) = self.sink.finish(report) {
+            self.fail(err);
+        }
+    }
+
+    /// Where `history` went, `None` if exporting it failed.
+    pub fn export(&mut self, history: &[GenerationStats]) -> Option<String> {
+        self.sink
+            .export(history)
+            .inspect_err(|err| warn!(target: "sim::logging", "Failed to export the stats: {}", err))
+            .ok()
+    }
+
+    /// What went wrong with keeping the stats, for the window title.
+    pub fn warning(&self) -> Option<&str> {
+        self.warning.as_deref()
+    }
+
+    fn fail(&mut self, err: SinkError) {
+        warn!(target: "sim::logging", "Failed to save the stats: {}", err);
+        self.warning = Some(match err {
+            SinkError::QuotaExceeded => {
+                self.full = true;
+                "storage full, stats are no longer saved".to_string()
+            }
+            err => format!("stats not saved: {}", err),
+        });
+    }
+}
+
+/// `summary_data.jsonl`, written with `log_data`, and `report.json` in the output
+/// directory. Exports go to `stats_export.json` there. Each gets a sidecar with the
+/// `provenance` of the run, the summary's updated when the run finishes.
+pub struct FileSink {
+    summary: Option<BufWriter<File>>,
+    summary_path: PathBuf,
+    report: PathBuf,
+    export: PathBuf,
+    provenance: Provenance,
+}
+
+impl FileSink {
+    pub fn new(logging: &LoggingConfig, provenance: Provenance) -> Self {
+        Self::open(logging, provenance, false)
+    }
+
+    /// A sink adding to the `summary_data.jsonl` already there.
+    pub fn appending(logging: &LoggingConfig, provenance: Provenance) -> Self {
+        Self::open(logging, provenance, true)
+    }
+
+    fn open(logging: &LoggingConfig, provenance: Provenance, append: bool) -> Self {
+        let path = logging.output_path("summary_data.jsonl");
+        let summary = logging.log_data.then(|| {
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(append)
+                .truncate(!append)
+                .open(&path)
+                .inspect_err(|err| {
+                    warn!(target: "sim::logging", "Failed to create {}: {}", path.display(), err)
+                })
+                .ok()
+                .map(BufWriter::new)
+        });
+        let summary = summary.flatten();
+        if summary.is_some() {
+            if let Err(err) = write_sidecar(&path, &provenance) {
+                warn!(target: "sim::logging", "Failed to describe {}: {}", path.display(), err);
+            }
+        }
+        Self {
+            summary,
+            summary_path: path,
+            report: logging.output_path("report.json"),
+            export: logging.output_path("stats_export.json"),
+            provenance,
+        }
+    }
+}
+
+impl StatsSink for FileSink {
+    fn record(&mut self, stats: &GenerationStats) -> Result<(), SinkError> {
+        let Some(summary) = &mut self.summary else {
+            return Ok(());
+        };
+        let line = serde_json::to_string(stats).expect("Failed to serialize summary data");
+        writeln!(summary, "{}", line)?;
+        Ok(())
+    }
+
+    fn finish(&mut self, report: &Report) -> Result<(), SinkError> {
+        let provenance = self
+            .provenance
+            .at(report.generation, report.state_hash.clone());
+        if let Some(summary) = &mut self.summary {
+            summary.flush()?;
+            write_sidecar(&self.summary_path, &provenance)?;
+        }
+        let json = serde_json::to_string(report).expect("Failed to serialize report");
+        write_artifact(&self.report, json, &provenance)?;
+        Ok(())
+    }
+
+    fn export(&mut self, history: &[GenerationStats]) -> Result<String, SinkError> {
+        let json = serde_json::to_string(history).expect("Failed to serialize stats");
+        let generation = history.last().map_or(self.provenance.generation, |stats| {
+            stats.generation as usize
+        });
+        write_artifact(&self.export, json, &self.provenance.at(generation, None))?;
+        Ok(self.export.display().to_string())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub use browser::LocalStorageSink;
+
+#[cfg(target_arch = "wasm32")]
+mod browser {
+    use wasm_bindgen::{JsCast, JsValue};
+    use web_sys::{Blob, BlobPropertyBag, DomException, HtmlAnchorElement, Storage, Url};
+
+    use super::{SinkError, StatsSink};
+    use crate::provenance::RunId;
+    use crate::schema::{GenerationStats, Report};
+
+    /// Generations recorded between two saves of the history, which is stored whole.
+    const SAVE_EVERY: usize = 25;
+
+    /// The browser's local storage, under keys starting with `evolution/<run id>/`.
+    /// `evolution/latest` holds the id of the last run that finished. Exports are
+    /// downloads.
+    pub struct LocalStorageSink {
+        run_id: String,
+        history: Vec<GenerationStats>,
+        unsaved: usize,
+    }
+
+    impl LocalStorageSink {
+        pub fn new(run_id: &RunId) -> Self {
+            Self {
+                run_id: run_id.0.clone(),
+                history: Vec::new(),
+                unsaved: 0,
+            }
+        }
+
+        fn key(&self, name: &str) -> String {
+            format!("evolution/{}/{}", self.run_id, name)
+        }
+
+        fn save_history(&mut self) -> Result<(), SinkError> {
+            let json = serde_json::to_string(&self.history).expect("Failed to serialize stats");
+            set_item(&self.key("history"), &json)?;
+            self.unsaved = 0;
+            Ok(())
+        }
+    }
+
+    fn storage() -> Result<Storage, SinkError> {
+        web_sys::window()
+            .ok_or_else(|| SinkError::Unavailable("no window".to_string()))?
+            .local_storage()
+            .map_err(js_error)?
+            .ok_or_else(|| SinkError::Unavailable("no local storage".to_string()))
+    }
+
+    fn set_item(key: &str, value: &str) -> Result<(), SinkError> {
+        storage()?.set_item(key, value).map_err(js_error)
+    }
+
+    fn js_error(err: JsValue) -> SinkError {
+        match err.dyn_ref::<DomException>() {
+            Some(exception) if exception.name() == "QuotaExceededError" => SinkError::QuotaExceeded,
+            Some(exception) => SinkError::Unavailable(exception.message()),
+            None => SinkError::Unavailable(format!("{:?}", err)),
+        }
+    }
+
+    /// Makes the browser download `json` as `name`.
+    fn download(json: &str, name: &str) -> Result<(), SinkError> {
+        let parts = js_sys::Array::of1(&JsValue::from_str(json));
+        let options = BlobPropertyBag::new();
+        options.set_type("application/json");
+        let blob = Blob::new_with_str_sequence_and_options(&parts, &options).map_err(js_error)?;
+        let url = Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or_else(|| SinkError::Unavailable("no document".to_string()))?;
+        let link: HtmlAnchorElement = document
+            .create_element("a")
+            .map_err(js_error)?
+            .dyn_into()
+            .map_err(|_| SinkError::Unavailable("no anchor element".to_string()))?;
+        link.set_href(&url);
+        link.set_download(name);
+        link.click();
+        Url::revoke_object_url(&url).map_err(js_error)
+    }
+
+    impl StatsSink for LocalStorageSink {
+        fn record(&mut self, stats: &GenerationStats) -> Result<(), SinkError> {
+            self.history.push(stats.clone());
+            self.unsaved += 1;
+            if self.unsaved >= SAVE_EVERY {
+                self.save_history()?;
+            }
+            Ok(())
+        }
+
+        fn finish(&mut self, report: &Report) -> Result<(), SinkError> {
+            self.save_history()?;
+            let json = serde_json::to_string(report).expect("Failed to serialize report");
+            set_item(&self.key("report"), &json)?;
+            set_item("evolution/latest", &self.run_id)
+        }
+
+        fn export(&mut self, history: &[GenerationStats]) -> Result<String, SinkError> {
+            let json = serde_json::to_string(history).expect("Failed to serialize stats");
+            let name = format!("evolution_{}.json", self.run_id);
+            download(&json, &name)?;
+            Ok(name)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::schema::SCHEMA_VERSION;
+    use crate::utils::default_config;
+
+    /// What [`MemorySink`] holds, shared with the test.
+    #[derive(Default)]
+    struct Stored {
+        bytes: usize,
+        generations: Vec<u32>,
+        attempts: usize,
+        report: Option<usize>,
+    }
+
+    /// Keeps serialized stats in memory up to `quota` bytes, like local storage.
+    struct MemorySink {
+        quota: usize,
+        stored: Arc<Mutex<Stored>>,
+    }
+
+    impl StatsSink for MemorySink {
+        fn record(&mut self, stats: &GenerationStats) -> Result<(), SinkError> {
+            let mut stored = self.stored.lock().unwrap();
+            stored.attempts += 1;
+            let bytes = serde_json::to_string(stats).unwrap().len();
+            if stored.bytes + bytes > self.quota {
+                return Err(SinkError::QuotaExceeded);
+            }
+            stored.bytes += bytes;
+            stored.generations.push(stats.generation);
+            Ok(())
+        }
+
+        fn finish(&mut self, report: &Report) -> Result<(), SinkError> {
+            self.stored.lock().unwrap().report = Some(report.history.len());
+            Ok(())
+        }
+
+        fn export(&mut self, history: &[GenerationStats]) -> Result<String, SinkError> {
+            Ok(format!("{} generations", history.len()))
+        }
+    }
+
+    fn stats(generation: u32) -> GenerationStats {
+        GenerationStats {
+            schema_version: SCHEMA_VERSION,
+            generation,
+            ..Default::default()
+        }
+    }
+
+    fn report(history: Vec<GenerationStats>) -> Report {
+        Report {
+            schema_version: SCHEMA_VERSION,
+            generation: history.len(),
+            stride: 1,
+            history,
+            density_adjustment: None,
+            defaulted_fields: Vec::new(),
+            state_hash: None,
+            trait_variance: None,
+        }
+    }
+
+    #[test]
+    fn a_full_store_warns_once_and_stops_offering_generations() {
+        let stored = Arc::new(Mutex::new(Stored::default()));
+        let quota = 2 * serde_json::to_string(&stats(1)).unwrap().len();
+        let mut store = StatsStore::new(Box::new(MemorySink {
+            quota,
+            stored: stored.clone(),
+        }));
+
+        for generation in 1..=5 {
+            store.record(&stats(generation));
+        }
+        assert_eq!(
+            store.warning(),
+            Some("storage full, stats are no longer saved")
+        );
+        let report = report(vec![stats(1), stats(2)]);
+        store.finish(&report);
+        assert_eq!(
+            store.export(&report.history).as_deref(),
+            Some("2 generations")
+        );
+
+        let stored = stored.lock().unwrap();
+        assert_eq!(stored.generations, [1, 2]);
+        // the third generation hit the quota and the rest were never tried
+        assert_eq!(stored.attempts, 3);
+        assert_eq!(stored.report, Some(2));
+    }
+
+    #[test]
+    fn the_file_sink_writes_the_summary_and_the_report() {
+        let dir = std::env::temp_dir().join("evolution_file_sink");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut config = default_config();
+        config.logging.log_data = true;
+        config.logging.output_dir = Some(dir.clone());
+
+        let run_id = RunId("3-1700000000000".into());
+        let mut store = StatsStore::for_config(&config, &run_id);
+        store.record(&stats(1));
+        store.record(&stats(2));
+        store.finish(&report(vec![stats(1), stats(2)]));
+        assert_eq!(store.warning(), None);
+
+        let summary = std::fs::read_to_string(dir.join("summary_data.jsonl")).unwrap();
+        assert_eq!(summary.lines().count(), 2);
+        assert!(dir.join("report.json").exists());
+        for name in ["summary_data.jsonl", "report.json"] {
+            let provenance = Provenance::read(&dir.join(name)).unwrap().unwrap();
+            assert_eq!(
+                (provenance.run_id.as_str(), provenance.generation),
+                (run_id.0.as_str(), 2)
+            );
+        }
+        let exported = store.export(&[stats(1)]).unwrap();
+        assert!(exported.ends_with("stats_export.json"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
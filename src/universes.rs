@@ -0,0 +1,186 @@
+//! Several independent worlds from one config advancing in lockstep in one process,
+//! started with `--universes N`, for estimating how much a run varies by seed.
+//! Universe `k` runs with seed `seed + k` and writes its logs to `universe_k/`, and
+//! `universes.csv` collects the populations of every universe and their total each
+//! generation.
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use bevy::app::PluginsState;
+use bevy::prelude::*;
+
+use crate::app::try_build_app;
+use crate::components::{Active, Organism, Predator};
+use crate::config::EFFECTIVE_CONFIG_FILE;
+use crate::provenance::{Provenance, RunId};
+use crate::resources::{AppState, Config, LogLevel};
+
+/// Living organisms and predators of one universe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Populations {
+    pub organisms: usize,
+    pub predators: usize,
+}
+
+impl Populations {
+    pub(crate) fn count(app: &mut App) -> Self {
+        let ecs = app.world_mut();
+        Self {
+            organisms: ecs
+                .query_filtered::<(), (With<Organism>, Active)>()
+                .iter(ecs)
+                .count(),
+            predators: ecs
+                .query_filtered::<(), (With<Predator>, Active)>()
+                .iter(ecs)
+                .count(),
+        }
+    }
+}
+
+/// The populations of every universe after one generation, `None` for universes
+/// that failed by then.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationRow {
+    pub generation: usize,
+    pub universes: Vec<Option<Populations>>,
+}
+
+impl GenerationRow {
+    /// Populations of all the universes still running, added up.
+    pub fn total(&self) -> Populations {
+        self.universes
+            .iter()
+            .flatten()
+            .fold(Populations::default(), |total, populations| Populations {
+                organisms: total.organisms + populations.organisms,
+                predators: total.predators + populations.predators,
+            })
+    }
+}
+
+/// Directory universe `k` writes its logs to.
+pub fn universe_dir(dir: &Path, k: usize) -> PathBuf {
+    dir.join(format!("universe_{}", k))
+}
+
+/// The config of universe `k`: `config` with the seed moved on by `k`, without a
+/// window or progress lines unless its `log_level` asks for them, and logging into
+/// its own directory.
+pub fn universe_config(config: &Config, k: usize, generations: usize, dir: &Path) -> Config {
+    let mut config = config.clone();
+    config.world.seed = config.world.seed.wrapping_add(k as u64);
+    config.world.headless = true;
+    config.world.printing = false;
+    config.world.log_level.get_or_insert(LogLevel::Warn);
+    config.world.target_ticks_per_second = None;
+    config.world.generation_limit = Some(generations);
+    config.logging.output_dir = Some(universe_dir(dir, k));
+    config
+}
+
+fn finished(app: &App) -> bool {
+    *app.world().resource::<State<AppState>>().get() == AppState::Finished
+}
+
+/// Advances the universe one generation, or drops it if that panics, so that one
+/// failing universe leaves the others running.
+fn step(universe: &mut Option<App>, k: usize) {
+    let Some(app) = universe else { return };
+    if finished(app) {
+        return;
+    }
+    if panic::catch_unwind(AssertUnwindSafe(|| app.update())).is_err() {
+        eprintln!("universe {} failed, the others carry on", k);
+        *universe = None;
+    }
+}
+
+/// Runs `count` universes of `config` round-robin for `generations` generations,
+/// printing and appending to `dir/universes.csv` the populations of each and their
+/// total after every generation. The table is headed by the provenance of the whole
+/// batch, and universe `k` runs as `<batch run id>-universe_k`.
+pub fn run(
+    config: &Config,
+    count: usize,
+    generations: usize,
+    dir: &Path,
+) -> Result<Vec<GenerationRow>, Box<dyn Error>> {
+    let batch = RunId::new(config.world.seed);
+    let mut universes = Vec::with_capacity(count);
+    for k in 0..count {
+        let config = universe_config(config, k, generations, dir);
+        let run_id = RunId(format!("{}-universe_{}", batch.0, k));
+        config.logging.create_output_dir()?;
+        // with `log_data` the logging plugin writes it next to the logs
+        if !config.logging.log_data {
+            config.save(
+                &config.logging.output_path(EFFECTIVE_CONFIG_FILE),
+                &Provenance::new(&run_id, config.world.seed, 0, None),
+            )?;
+        }
+        let mut app = try_build_app(config)?;
+        app.insert_resource(run_id);
+        if app.plugins_state() != PluginsState::Cleaned {
+            app.finish();
+            app.cleanup();
+        }
+        universes.push(Some(app));
+    }
+
+    fs::create_dir_all(dir)?;
+    let mut csv = BufWriter::new(File::create(dir.join("universes.csv"))?);
+    let provenance = Provenance::new(&batch, config.world.seed, 0, None);
+    write!(csv, "{}", provenance.header())?;
+    writeln!(csv, "generation,universe,organisms,predators")?;
+
+    let mut rows = Vec::with_capacity(generations);
+    for generation in 1..=generations {
+        for (k, universe) in universes.iter_mut().enumerate() {
+            step(universe, k);
+        }
+        let row = GenerationRow {
+            generation,
+            universes: universes
+                .iter_mut()
+                .map(|universe| universe.as_mut().map(Populations::count))
+                .collect(),
+        };
+
+        let mut line = format!("generation {}:", generation);
+        for (k, populations) in row.universes.iter().enumerate() {
+            let Some(populations) = populations else {
+                line.push_str(&format!(" [{} failed]", k));
+                continue;
+            };
+            line.push_str(&format!(
+                " [{}] {}/{}",
+                k, populations.organisms, populations.predators
+            ));
+            writeln!(
+                csv,
+                "{},{},{},{}",
+                generation, k, populations.organisms, populations.predators
+            )?;
+        }
+        let total = row.total();
+        println!("{} total {}/{}", line, total.organisms, total.predators);
+        writeln!(
+            csv,
+            "{},total,{},{}",
+            generation, total.organisms, total.predators
+        )?;
+        csv.flush()?;
+        rows.push(row);
+    }
+
+    // one more update lets every universe finish and write its report and summary
+    for (k, universe) in universes.iter_mut().enumerate() {
+        step(universe, k);
+    }
+    Ok(rows)
+}
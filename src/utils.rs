@@ -1,11 +1,15 @@
 use std::error::Error;
 use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
 use rand::prelude::*;
 
-use crate::resources::{
-    Biome, BiomeDataConfig, Config, LoggingConfig, OrganismConfig, PredatorConfig, WorldConfig,
+use crate::config::{
+    env_overrides, layer_config_with_env, ConfigErrors, ConfigFlags, DefaultedFields,
 };
+use crate::presets::Preset;
+use crate::resources::{Biome, Config, LoggingConfig, OrganismConfig, PredatorConfig, WorldConfig};
 
 pub const DIRECTIONS: [(isize, isize); 8] = [
     (-1, -1),
@@ -18,6 +22,22 @@ pub const DIRECTIONS: [(isize, isize); 8] = [
     (1, 1),
 ];
 
+/// [`DIRECTIONS`] in clockwise order starting north, so that neighbouring indices
+/// are neighbouring headings.
+pub const COMPASS: [(isize, isize); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+/// The four neighbours sharing an edge with a tile.
+pub const ORTHOGONAL_DIRECTIONS: [(isize, isize); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+
 pub fn get_biome_tolerance(tile_biome: Biome, rng: &mut impl Rng) -> [f32; 4] {
     let mut tolerances = [0.0f32; 4];
     for biome in [Biome::Forest, Biome::Desert, Biome::Water, Biome::Grassland] {
@@ -30,89 +50,111 @@ pub fn get_biome_tolerance(tile_biome: Biome, rng: &mut impl Rng) -> [f32; 4] {
     tolerances
 }
 
-pub fn load_config() -> Result<Config, Box<dyn Error>> {
-    let exe_dir = std::env::current_exe()
-        .expect("Failed to get current executable path")
-        .parent()
-        .expect("Executable must be in a directory")
-        .to_path_buf();
+/// Replaces the file at `path` with `bytes` so that a crash leaves either the old
+/// file or the new one, never a truncated mix: the bytes go to `<name>.tmp` next to
+/// it, are synced to disk and then renamed over the target.
+pub fn safe_write(path: &Path, bytes: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?
+        .to_os_string();
+    name.push(".tmp");
+    let tmp = path.with_file_name(name);
 
-    let config_path = exe_dir.join("config.toml");
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(bytes.as_ref())?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&tmp, path)
+}
 
-    let config = fs::read_to_string(config_path)?;
-    let config: Config = toml::from_str(&config)?;
+/// Reads the config at `path`, or `config.toml` next to the executable without one,
+/// along with the fields it left to the defaults.
+pub fn load_config(path: Option<&Path>) -> Result<(Config, DefaultedFields), Box<dyn Error>> {
+    load_layered_config(None, path, &[], &ConfigFlags::default())
+}
 
-    Ok(config)
+/// Reads the config layered from `preset`, the file at `path`, the `ECA_`
+/// environment variables and the `--set` `overrides`, with the shorthand `flags`
+/// set on top, see [`layer_config_with_env`]. Without a path the file is
+/// `config.toml` next to the executable, unless a preset stands in for it. A
+/// relative `path` is taken from the working directory, and a file that cannot be
+/// read fails with the path it was looked for at.
+pub fn load_layered_config(
+    preset: Option<&Preset>,
+    path: Option<&Path>,
+    overrides: &[String],
+    flags: &ConfigFlags,
+) -> Result<(Config, DefaultedFields), Box<dyn Error>> {
+    let config_path = match (path, preset) {
+        (Some(path), _) => Some(path.to_path_buf()),
+        (None, Some(_)) => None,
+        (None, None) => Some(executable_config_path()),
+    };
+    let toml = config_path
+        .map(|path| {
+            fs::read_to_string(&path).map_err(|err| {
+                let attempted = std::path::absolute(&path).unwrap_or(path);
+                format!("cannot read {}: {}", attempted.display(), err)
+            })
+        })
+        .transpose()?;
+    let env = env_overrides(std::env::vars())?;
+    layer_config_with_env(preset, toml.as_deref(), &env, overrides, flags)
 }
 
-#[allow(dead_code, unused)]
+/// The config every field of `config.toml` falls back to when it is left out, see
+/// the `Default` impls of [`WorldConfig`] and its siblings.
 pub fn default_config() -> Config {
     Config {
-        world: WorldConfig {
-            width: 10,
-            height: 10,
-            seed: 0,
-            headless: false,
-            printing: false,
-            generation_limit: None,
-            max_total_entities: 1000,
-            forest: BiomeDataConfig {
-                food_availability: 1.0,
-                max_food_availability: 100.0,
-            },
-            desert: BiomeDataConfig {
-                food_availability: 1.0,
-                max_food_availability: 100.0,
-            },
-            water: BiomeDataConfig {
-                food_availability: 1.0,
-                max_food_availability: 100.0,
-            },
-            grassland: BiomeDataConfig {
-                food_availability: 1.0,
-                max_food_availability: 100.0,
-            },
-        },
-        organism: OrganismConfig {
-            initial_organisms: 10,
-            initial_organism_energy: 100.0,
-            initial_organism_speed: 1.0,
-            initial_organism_size: 1.0,
-            initial_organism_reproduction_threshold: 100.0,
-            organism_mutability: 0.1,
-            overcrowding_threshold_for_organisms: 10,
-            organism_reproduction_cooldown: 0.5,
-        },
-        predator: PredatorConfig {
-            initial_predators: 1,
-            initial_predator_energy: 100.0,
-            initial_predator_speed: 1.0,
-            initial_predator_size: 1.0,
-            initial_predator_reproduction_threshold: 100.0,
-            initial_predator_hunting_efficiency: 1.0,
-            initial_predator_satiation_threshold: 100.0,
-            predator_mutability: 0.1,
-            overcrowding_threshold_for_predators: 10,
-            max_predator_energy: 1500.0,
-            predator_energy_decay_rate: 0.5,
-            predator_reproduction_cooldown: 0.5,
-            predator_seek_radius: 3,
-        },
-        logging: LoggingConfig {
-            log_data: false,
-            log_interval: 1,
-        },
+        world: WorldConfig::default(),
+        organism: OrganismConfig::default(),
+        predator: PredatorConfig::default(),
+        logging: LoggingConfig::default(),
+        species_b: None,
     }
 }
 
-pub fn get_config() -> Config {
+/// The config of a run without a preset, file or overrides: the one at `path`, or
+/// `config.toml` next to the executable without one, or the defaults when there is
+/// no such file, with the shorthand `flags` set on it. A file that fails to parse
+/// or validate ends the process with every problem found.
+pub fn get_config(path: Option<&Path>, flags: &ConfigFlags) -> (Config, DefaultedFields) {
     #[cfg(target_arch = "wasm32")]
-    let config = default_config();
+    let loaded = {
+        let _ = path;
+        let mut loaded = (default_config(), DefaultedFields::default());
+        flags.apply(&mut loaded.0, &mut loaded.1);
+        loaded
+    };
     #[cfg(not(target_arch = "wasm32"))]
-    let config = load_config().unwrap_or_else(|err| {
-        eprintln!("Failed to load config: {}. Using default config.", err);
-        default_config()
-    });
+    let loaded = {
+        let path = path.map_or_else(executable_config_path, Path::to_path_buf);
+        if path.exists() {
+            load_layered_config(None, Some(&path), &[], flags).unwrap_or_else(|err| {
+                eprintln!("Failed to load config: {}", err);
+                std::process::exit(1);
+            })
+        } else {
+            eprintln!("No config at {}. Using default config.", path.display());
+            let mut loaded = (default_config(), DefaultedFields::default());
+            flags.apply(&mut loaded.0, &mut loaded.1);
+            if let Err(errors) = loaded.0.validate() {
+                eprintln!("Failed to load config: {}", ConfigErrors(errors));
+                std::process::exit(1);
+            }
+            loaded
+        }
+    };
 
-    config
+    loaded
+}
+
+/// `config.toml` next to the executable.
+fn executable_config_path() -> PathBuf {
+    std::env::current_exe()
+        .expect("Failed to get current executable path")
+        .parent()
+        .expect("Executable must be in a directory")
+        .join("config.toml")
 }
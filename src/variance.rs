@@ -0,0 +1,138 @@
+//! How much of the variance of each trait lies between lineages and how much within
+//! them, a one-way ANOVA over the lineage ids: whether a population has split into
+//! diverging lineages or holds its variation inside each of them.
+
+use std::collections::BTreeMap;
+
+use bevy::utils::hashbrown::HashMap;
+
+use crate::components::TraitSnapshot;
+use crate::schema::VarianceComponents;
+
+/// Splits the variance of `groups` pooled into the variance of the group means
+/// around the overall mean, weighted by group size, and the mean variance inside
+/// the groups; the two add up to the variance of the pool. `None` with fewer than
+/// two non-empty groups, where there is nothing to tell apart. Groups of one
+/// individual have no variance within.
+pub fn decompose(groups: &[Vec<f32>]) -> Option<VarianceComponents> {
+    let groups: Vec<_> = groups.iter().filter(|group| !group.is_empty()).collect();
+    if groups.len() < 2 {
+        return None;
+    }
+    let count: usize = groups.iter().map(|group| group.len()).sum();
+    let mean = groups.iter().flat_map(|group| group.iter()).sum::<f32>() / count as f32;
+
+    let mut between = 0.0;
+    let mut within = 0.0;
+    for group in &groups {
+        let group_mean = group.iter().sum::<f32>() / group.len() as f32;
+        between += group.len() as f32 * (group_mean - mean).powi(2);
+        within += group
+            .iter()
+            .map(|value| (value - group_mean).powi(2))
+            .sum::<f32>();
+    }
+    let (between, within) = (between / count as f32, within / count as f32);
+    let total = between + within;
+    Some(VarianceComponents {
+        lineages: groups.len(),
+        between,
+        within,
+        between_share: (total > 0.0).then(|| between / total),
+    })
+}
+
+/// [`decompose`] of each trait of `individuals`, given as their lineage and traits,
+/// grouped by lineage. Empty with fewer than two lineages.
+pub fn trait_variance(
+    individuals: impl IntoIterator<Item = (u64, TraitSnapshot)>,
+) -> BTreeMap<String, VarianceComponents> {
+    // trait -> lineage -> values
+    let mut values: BTreeMap<String, HashMap<u64, Vec<f32>>> = BTreeMap::new();
+    for (lineage, traits) in individuals {
+        for (name, value) in traits {
+            values
+                .entry(name)
+                .or_default()
+                .entry(lineage)
+                .or_default()
+                .push(value);
+        }
+    }
+    values
+        .into_iter()
+        .filter_map(|(name, lineages)| {
+            let mut lineages: Vec<_> = lineages.into_iter().collect();
+            // summing in lineage order keeps the result the same on every run
+            lineages.sort_unstable_by_key(|(lineage, _)| *lineage);
+            let groups: Vec<_> = lineages.into_iter().map(|(_, values)| values).collect();
+            decompose(&groups).map(|components| (name, components))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn variance_splits_into_between_and_within_lineages() {
+        // means 2 and 6 around 3.6; between (3 * 1.6^2 + 2 * 2.4^2) / 5 = 3.84,
+        // within (2 + 2) / 5 = 0.8
+        let components = decompose(&[vec![1.0, 2.0, 3.0], vec![5.0, 7.0]]).unwrap();
+        assert_eq!(components.lineages, 2);
+        close(components.between, 3.84);
+        close(components.within, 0.8);
+        close(components.between_share.unwrap(), 3.84 / 4.64);
+    }
+
+    #[test]
+    fn singletons_and_single_lineages_are_handled() {
+        // lineages of one have no variance within
+        let singletons = decompose(&[vec![1.0], vec![3.0], vec![]]).unwrap();
+        assert_eq!(singletons.lineages, 2);
+        close(singletons.between, 1.0);
+        assert_eq!(singletons.within, 0.0);
+        assert_eq!(singletons.between_share, Some(1.0));
+
+        assert_eq!(decompose(&[vec![1.0, 2.0, 3.0]]), None);
+        assert_eq!(decompose(&[vec![1.0], vec![]]), None);
+        assert_eq!(decompose(&[]), None);
+
+        // no variance at all leaves the share undefined
+        let uniform = decompose(&[vec![2.0, 2.0], vec![2.0]]).unwrap();
+        assert_eq!((uniform.between, uniform.within), (0.0, 0.0));
+        assert_eq!(uniform.between_share, None);
+    }
+
+    #[test]
+    fn traits_are_grouped_by_lineage() {
+        let individual = |lineage, size: f32, speed: f32| {
+            let traits = [("size".to_string(), size), ("speed".to_string(), speed)];
+            (lineage, traits.into_iter().collect::<TraitSnapshot>())
+        };
+        let variance = trait_variance([
+            individual(7, 1.0, 2.0),
+            individual(3, 5.0, 2.0),
+            individual(7, 3.0, 2.0),
+            individual(3, 5.0, 4.0),
+        ]);
+        let size = &variance["size"];
+        close(size.between, 2.25);
+        close(size.within, 0.5);
+        let speed = &variance["speed"];
+        close(speed.between, 0.25);
+        close(speed.within, 0.5);
+
+        assert!(trait_variance([individual(1, 1.0, 2.0), individual(1, 3.0, 2.0)]).is_empty());
+    }
+}
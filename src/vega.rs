@@ -0,0 +1,352 @@
+//! A single HTML page charting `summary_data.jsonl` with Vega-Lite, written by
+//! `evolution analyze --emit-vega` for people without Rust or Python to look at a
+//! run by opening the file in a browser. The data sits in the page as JSON, next to
+//! one Vega-Lite spec per chart.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+
+use crate::resources::Biome;
+use crate::schema::GenerationStats;
+
+/// Charts of the page, each as its data block `data-{name}` and its spec block
+/// `spec-{name}`.
+pub const CHARTS: [&str; 4] = ["population", "traits", "death_causes", "biomes"];
+
+/// Scripts the page loads from a CDN unless `scripts` names a directory to inline
+/// them from.
+const SCRIPTS: [(&str, &str); 3] = [
+    ("vega.min.js", "https://cdn.jsdelivr.net/npm/vega@5"),
+    (
+        "vega-lite.min.js",
+        "https://cdn.jsdelivr.net/npm/vega-lite@5",
+    ),
+    (
+        "vega-embed.min.js",
+        "https://cdn.jsdelivr.net/npm/vega-embed@6",
+    ),
+];
+
+/// Settings of `--emit-vega`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VegaOptions {
+    /// Most generations embedded in the page; longer runs are thinned evenly,
+    /// keeping the first and the last.
+    pub max_generations: usize,
+    /// Directory with `vega.min.js`, `vega-lite.min.js` and `vega-embed.min.js` to
+    /// copy into the page, so that it opens without a network.
+    pub scripts: Option<PathBuf>,
+}
+
+impl Default for VegaOptions {
+    fn default() -> Self {
+        Self {
+            max_generations: 500,
+            scripts: None,
+        }
+    }
+}
+
+/// At most `max` of `records`, evenly spaced and including the first and the last.
+pub fn downsample<T>(records: &[T], max: usize) -> Vec<&T> {
+    let len = records.len();
+    if len <= max {
+        return records.iter().collect();
+    }
+    if max < 2 {
+        return records.last().into_iter().take(max).collect();
+    }
+    (0..max)
+        .map(|k| &records[k * (len - 1) / (max - 1)])
+        .collect()
+}
+
+/// One point of a chart: the value of `series` in `generation`.
+fn point(generation: u32, series: &str, value: impl Into<Value>) -> Value {
+    json!({ "generation": generation, "series": series, "value": value.into() })
+}
+
+/// The points of every chart in `CHARTS` order.
+fn chart_data(stats: &[&GenerationStats]) -> [Vec<Value>; 4] {
+    let mut population = Vec::new();
+    let mut traits = Vec::new();
+    let mut deaths = Vec::new();
+    let mut biomes = Vec::new();
+    for record in stats {
+        let generation = record.generation;
+        population.push(point(generation, "organisms", record.organism_count));
+        for (name, value) in [
+            ("organism size", record.organism_avg_size),
+            ("organism speed", record.organism_avg_speed),
+            ("organism boldness", record.organism_avg_boldness),
+            ("organism exploration", record.organism_avg_exploration),
+        ] {
+            // serde_json writes a value that is not finite as null
+            traits.push(point(generation, name, value));
+        }
+        // a prey-only run has no predator series
+        if let Some(predators) = &record.predators {
+            population.push(point(generation, "predators", predators.predator_count));
+            for (name, value) in [
+                ("predator size", predators.predator_avg_size),
+                ("predator speed", predators.predator_avg_speed),
+                (
+                    "predator hunting efficiency",
+                    predators.predator_avg_hunting_efficiency,
+                ),
+            ] {
+                traits.push(point(generation, name, value));
+            }
+        }
+        let causes: BTreeMap<String, usize> = record
+            .death_causes
+            .iter()
+            .map(|(cause, &count)| (format!("{:?}", cause), count))
+            .collect();
+        for (cause, count) in causes {
+            deaths.push(point(generation, &cause, count));
+        }
+        for biome in Biome::ALL {
+            let name = format!("{:?}", biome);
+            biomes.push(point(generation, &name, record.organisms_per_biome[biome]));
+        }
+    }
+    [population, traits, deaths, biomes]
+}
+
+/// The Vega-Lite spec of chart `name`, without its data.
+fn chart_spec(name: &str) -> Value {
+    let x = json!({ "field": "generation", "type": "quantitative" });
+    let colour = json!({ "field": "series", "type": "nominal", "title": null });
+    let lines = |title: &str, y_title: &str| {
+        json!({
+            "title": title,
+            "width": 700,
+            "height": 300,
+            "mark": "line",
+            "encoding": {
+                "x": x,
+                "y": { "field": "value", "type": "quantitative", "title": y_title },
+                "color": colour,
+            },
+        })
+    };
+    let mut spec = match name {
+        "population" => lines("Population", "individuals"),
+        "biomes" => lines("Organisms by biome", "organisms"),
+        // the traits differ in scale, so each gets its own panel
+        "traits" => json!({
+            "title": "Average traits",
+            "facet": { "field": "series", "type": "nominal", "title": null },
+            "columns": 3,
+            "resolve": { "scale": { "y": "independent" } },
+            "spec": {
+                "width": 220,
+                "height": 150,
+                "mark": "line",
+                "encoding": {
+                    "x": x,
+                    "y": { "field": "value", "type": "quantitative", "title": null },
+                },
+            },
+        }),
+        "death_causes" => json!({
+            "title": "Deaths by cause",
+            "width": 700,
+            "height": 300,
+            "mark": "area",
+            "encoding": {
+                "x": x,
+                "y": {
+                    "field": "value",
+                    "type": "quantitative",
+                    "stack": "zero",
+                    "title": "deaths",
+                },
+                "color": colour,
+            },
+        }),
+        _ => unreachable!("not a chart: {}", name),
+    };
+    spec["$schema"] = json!("https://vega.github.io/schema/vega-lite/v5.json");
+    spec
+}
+
+/// `json` made safe to place between `<script>` tags: a `</` inside a string would
+/// end the block early, and `<\/` means the same to JSON.
+fn script_text(json: &str) -> String {
+    json.replace("</", "<\\/")
+}
+
+/// The page charting `stats`, with its scripts linked from a CDN or, with
+/// `options.scripts`, copied into it.
+pub fn vega_html(
+    stats: &[GenerationStats],
+    options: &VegaOptions,
+) -> Result<String, Box<dyn Error>> {
+    let kept = downsample(stats, options.max_generations);
+    let data = chart_data(&kept);
+
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>evolution run</title>\n",
+    );
+    for (file, url) in SCRIPTS {
+        match &options.scripts {
+            Some(dir) => {
+                let script = fs::read_to_string(dir.join(file))
+                    .map_err(|err| format!("{}: {}", dir.join(file).display(), err))?;
+                writeln!(
+                    html,
+                    "<script>{}</script>",
+                    script.replace("</script", "<\\/script")
+                )?;
+            }
+            None => writeln!(html, "<script src=\"{}\"></script>", url)?,
+        }
+    }
+    html.push_str("</head>\n<body>\n");
+    if let (Some(first), Some(last)) = (kept.first(), kept.last()) {
+        writeln!(
+            html,
+            "<p>Generations {} to {}, {} of {} shown.</p>",
+            first.generation,
+            last.generation,
+            kept.len(),
+            stats.len()
+        )?;
+    }
+    for (name, points) in CHARTS.iter().zip(&data) {
+        writeln!(html, "<div id=\"chart-{}\"></div>", name)?;
+        writeln!(
+            html,
+            "<script type=\"application/json\" id=\"data-{}\">{}</script>",
+            name,
+            script_text(&serde_json::to_string(points)?)
+        )?;
+        writeln!(
+            html,
+            "<script type=\"application/json\" id=\"spec-{}\">{}</script>",
+            name,
+            script_text(&serde_json::to_string(&chart_spec(name))?)
+        )?;
+    }
+    writeln!(
+        html,
+        "<script>\nfor (const name of {}) {{\n  const block = (kind) => JSON.parse(document.getElementById(kind + \"-\" + name).textContent);\n  const spec = block(\"spec\");\n  spec.data = {{ values: block(\"data\") }};\n  vegaEmbed(\"#chart-\" + name, spec);\n}}\n</script>",
+        serde_json::to_string(&CHARTS)?
+    )?;
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resources::DeathCause;
+    use crate::schema::PredatorStats;
+
+    use super::*;
+
+    /// The contents of the `<script>` block with id `id` in `html`.
+    fn block<'a>(html: &'a str, id: &str) -> Option<&'a str> {
+        let open = format!("<script type=\"application/json\" id=\"{}\">", id);
+        let start = html.find(&open)? + open.len();
+        let end = start + html[start..].find("</script>")?;
+        Some(&html[start..end])
+    }
+
+    #[test]
+    fn long_runs_are_thinned_to_the_cap_keeping_both_ends() {
+        let records: Vec<usize> = (0..1000).collect();
+        let kept = downsample(&records, 100);
+        assert_eq!(kept.len(), 100);
+        assert_eq!((*kept[0], *kept[99]), (0, 999));
+        assert!(kept.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(downsample(&records[..10], 100).len(), 10);
+        assert_eq!(downsample(&records, 1), vec![&999]);
+        assert!(downsample(&records, 0).is_empty());
+    }
+
+    #[test]
+    fn the_page_holds_a_data_block_and_a_spec_for_every_chart() {
+        let stats: Vec<GenerationStats> = (1..=300)
+            .map(|generation| {
+                let mut record = GenerationStats {
+                    generation,
+                    organism_count: generation as usize,
+                    predators: Some(PredatorStats {
+                        predator_count: 3,
+                        ..Default::default()
+                    }),
+                    organism_avg_size: 1.5,
+                    ..Default::default()
+                };
+                record.death_causes.insert(DeathCause::Predation, 2);
+                record.organisms_per_biome[Biome::Forest] = 7;
+                record
+            })
+            .collect();
+        let options = VegaOptions {
+            max_generations: 50,
+            scripts: None,
+        };
+        let html = vega_html(&stats, &options).unwrap();
+
+        let specs = html.matches("id=\"spec-").count();
+        assert_eq!(specs, CHARTS.len());
+        for name in CHARTS {
+            let spec: Value =
+                serde_json::from_str(block(&html, &format!("spec-{}", name)).unwrap())
+                    .unwrap_or_else(|err| panic!("spec of {}: {}", name, err));
+            assert!(spec["$schema"].as_str().unwrap().contains("vega-lite"));
+
+            let data: Vec<Value> =
+                serde_json::from_str(block(&html, &format!("data-{}", name)).unwrap())
+                    .unwrap_or_else(|err| panic!("data of {}: {}", name, err));
+            let generations: std::collections::BTreeSet<u64> = data
+                .iter()
+                .map(|point| point["generation"].as_u64().unwrap())
+                .collect();
+            assert_eq!(generations.len(), 50, "{}", name);
+            assert_eq!(generations.last(), Some(&300), "{}", name);
+        }
+
+        let population: Vec<Value> =
+            serde_json::from_str(block(&html, "data-population").unwrap()).unwrap();
+        assert_eq!(population.last().unwrap()["value"], 3);
+        let biomes: Vec<Value> =
+            serde_json::from_str(block(&html, "data-biomes").unwrap()).unwrap();
+        assert!(biomes
+            .iter()
+            .any(|point| point["series"] == "Forest" && point["value"] == 7));
+        assert!(html.contains("https://cdn.jsdelivr.net/npm/vega-lite@5"));
+    }
+
+    #[test]
+    fn vendored_scripts_are_copied_into_the_page() {
+        let dir = std::env::temp_dir().join("evolution_vega_scripts");
+        fs::create_dir_all(&dir).unwrap();
+        for (file, _) in SCRIPTS {
+            fs::write(
+                dir.join(file),
+                format!("/* {} */ var s = '</script>';", file),
+            )
+            .unwrap();
+        }
+        let options = VegaOptions {
+            max_generations: 10,
+            scripts: Some(dir.clone()),
+        };
+        let html = vega_html(&[GenerationStats::default()], &options).unwrap();
+        assert!(html.contains("/* vega-embed.min.js */ var s = '<\\/script>';"));
+        assert!(!html.contains("cdn.jsdelivr.net"));
+
+        fs::remove_file(dir.join("vega.min.js")).unwrap();
+        assert!(vega_html(&[], &options).is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
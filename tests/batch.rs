@@ -0,0 +1,122 @@
+use std::fs;
+
+use bevy::prelude::App;
+use bevy::prelude::State;
+use evolution::batch::{self, seed_dir};
+use evolution::provenance::{Provenance, RunId};
+use evolution::{build_app, default_config, AppState, ClampWarnings, Config};
+
+fn run_to_the_end(app: &mut App) {
+    while *app.world().resource::<State<AppState>>().get() != AppState::Finished {
+        app.update();
+    }
+}
+
+#[test]
+fn every_seed_of_a_batch_runs_as_it_would_alone() {
+    let dir = std::env::temp_dir().join("evolution_batch");
+    let alone_dir = std::env::temp_dir().join("evolution_batch_alone");
+    fs::remove_dir_all(&dir).ok();
+    fs::remove_dir_all(&alone_dir).ok();
+
+    let mut config = default_config();
+    config.world.headless = true;
+    config.world.width = 16;
+    config.world.height = 16;
+    config.world.generation_limit = Some(30);
+    config.world.seeds = vec![3, 4];
+    config.organism.initial_organisms = 30;
+    config.predator.initial_predators = 3;
+    config.logging.log_data = true;
+    // state kept across generations, which a seed must not inherit from the last:
+    // grassland under pressure that only turns to desert after 40 generations, and
+    // births still waiting for a heritability record when a seed ends
+    config.world.desertification.enabled = true;
+    config.world.desertification.food_threshold = 1e9;
+    config.world.desertification.generations = 40;
+    config.logging.heritability_interval = 7;
+
+    let replicates = batch::run(&config, &dir).unwrap();
+    assert_eq!(
+        replicates.iter().map(|run| run.seed).collect::<Vec<_>>(),
+        [3, 4]
+    );
+
+    for replicate in &replicates {
+        // a run of its own from a fresh app, the second one following nothing
+        let alone = batch::replicate_config(&config, replicate.seed, &alone_dir);
+        let mut app = build_app(alone);
+        run_to_the_end(&mut app);
+        drop(app);
+
+        let logs = seed_dir(&dir, replicate.seed);
+        let own = seed_dir(&alone_dir, replicate.seed);
+        let report = Provenance::read(&logs.join("report.json"))
+            .unwrap()
+            .unwrap();
+        let expected = Provenance::read(&own.join("report.json")).unwrap().unwrap();
+        assert_eq!((report.seed, report.generation), (replicate.seed, 30));
+        assert_eq!(report.state_hash, expected.state_hash);
+        assert_eq!(report.state_hash.as_ref(), Some(&replicate.state_hash));
+        for log in [
+            "events.jsonl",
+            "summary_data.jsonl",
+            "world_data.jsonl",
+            "heritability.jsonl",
+        ] {
+            // the runs only differ in the time they started at, part of the run id
+            let batch_log = fs::read_to_string(logs.join(log)).unwrap();
+            let own_log = fs::read_to_string(own.join(log)).unwrap();
+            assert!(
+                batch_log.replace(&report.run_id, &expected.run_id) == own_log,
+                "{} of seed {}",
+                log,
+                replicate.seed
+            );
+        }
+    }
+
+    let csv = fs::read_to_string(dir.join("batch.csv")).unwrap();
+    let rows = csv.lines().filter(|line| !line.starts_with('#'));
+    assert_eq!(rows.count(), 1 + 2);
+}
+
+#[test]
+fn every_seed_of_a_batch_warns_about_clamped_traits() {
+    let clamped = |seed| {
+        let mut config: Config = default_config();
+        config.world.headless = true;
+        config.world.seed = seed;
+        config.world.width = 16;
+        config.world.height = 16;
+        config.world.generation_limit = Some(30);
+        config.organism.initial_organisms = 60;
+        config.organism.organism_mutability = 1.0;
+        // a single clamped birth is enough to warn
+        config.logging.clamp_warning_fraction = 0.0;
+        config.logging.output_dir = Some(std::env::temp_dir().join("evolution_batch_clamped"));
+        config
+    };
+    let warned = |app: &App| app.world().resource::<ClampWarnings>().0.len();
+
+    let mut app = build_app(clamped(3));
+    run_to_the_end(&mut app);
+    assert!(warned(&app) > 0);
+
+    batch::reset(&mut app, clamped(4), RunId::new(4)).unwrap();
+    assert_eq!(warned(&app), 0);
+    run_to_the_end(&mut app);
+    assert!(warned(&app) > 0);
+}
+
+#[test]
+fn replicates_derive_their_seeds() {
+    let mut config = default_config();
+    config.world.seed = 10;
+    config.world.replicates = 3;
+    assert!(batch::is_batch(&config));
+    assert_eq!(batch::seeds(&config), [10, 11, 12]);
+
+    config.world.replicates = 0;
+    assert!(!batch::is_batch(&config));
+}
@@ -0,0 +1,84 @@
+//! Worlds one tile across: `cargo test --no-default-features --test corridor`.
+
+use std::collections::BTreeSet;
+
+use bevy::prelude::With;
+use evolution::{
+    build_app, default_config, try_build_app, Active, Config, Generation, Organism, Position,
+    Predator,
+};
+
+fn corridor(width: usize, height: usize) -> Config {
+    let mut config = default_config();
+    config.world.headless = true;
+    config.world.seed = 11;
+    config.world.width = width;
+    config.world.height = height;
+    config.organism.initial_organisms = 30;
+    config.predator.initial_predators = 3;
+    config.logging.log_data = false;
+    config
+}
+
+/// Runs a hundred generations and returns every tile an organism or predator stood
+/// on at the end of one.
+fn visited(config: Config) -> BTreeSet<(usize, usize)> {
+    let mut app = build_app(config);
+    let mut visited = BTreeSet::new();
+    for _ in 0..100 {
+        app.update();
+        let world = app.world_mut();
+        let mut organisms = world.query_filtered::<&Position, (With<Organism>, Active)>();
+        let mut predators = world.query_filtered::<&Position, (With<Predator>, Active)>();
+        visited.extend(
+            organisms
+                .iter(world)
+                .map(|position| (position.x, position.y)),
+        );
+        visited.extend(
+            predators
+                .iter(world)
+                .map(|position| (position.x, position.y)),
+        );
+    }
+    assert_eq!(app.world().resource::<Generation>().0, 100);
+    visited
+}
+
+#[test]
+fn a_column_world_runs_a_hundred_generations() {
+    let visited = visited(corridor(1, 50));
+    assert!(
+        visited.iter().all(|&(x, y)| x == 0 && y < 50),
+        "{:?}",
+        visited
+    );
+    // individuals spread along the corridor rather than staying on their tiles
+    assert!(visited.len() > 10, "{:?}", visited);
+}
+
+#[test]
+fn a_row_world_runs_a_hundred_generations() {
+    let visited = visited(corridor(50, 1));
+    assert!(
+        visited.iter().all(|&(x, y)| x < 50 && y == 0),
+        "{:?}",
+        visited
+    );
+    assert!(visited.len() > 10, "{:?}", visited);
+}
+
+#[test]
+fn a_single_tile_world_keeps_everyone_on_it() {
+    let visited = visited(corridor(1, 1));
+    assert!(visited.iter().all(|&tile| tile == (0, 0)), "{:?}", visited);
+}
+
+#[test]
+fn a_world_without_tiles_is_rejected() {
+    for (width, height) in [(0, 50), (50, 0)] {
+        let err = try_build_app(corridor(width, height)).unwrap_err();
+        assert_eq!(err.field, "width");
+        assert!(err.message.contains("must not be empty"), "{}", err);
+    }
+}
@@ -0,0 +1,87 @@
+use bevy::ecs::schedule::{ExecutorKind, ScheduleLabel};
+use bevy::prelude::*;
+use evolution::repl::{dispatch, Command};
+use evolution::{
+    build_app, default_config, Biome, BiomeModel, Config, Identity, Organism, Position, Predator,
+    StatsHistory,
+};
+
+fn config(seed: u64) -> Config {
+    let mut config = default_config();
+    config.world.headless = true;
+    config.world.seed = seed;
+    config.world.catastrophes.probability = 0.05;
+    config.organism.initial_organisms = 60;
+    config.predator.initial_predators = 6;
+    config
+}
+
+/// A world where choices are often ties: grassland everywhere, so every step costs
+/// the same, founders that never explore and all start with the same size and
+/// energy, and crowded enough for several to share a tile.
+fn tied_config(seed: u64) -> Config {
+    let mut config = config(seed);
+    config.world.width = 24;
+    config.world.height = 24;
+    config.world.biome_model = BiomeModel::Whittaker;
+    config.world.whittaker.table = vec![vec![Biome::Grassland]];
+    config.organism.initial_organisms = 200;
+    config.organism.initial_organism_exploration = 0.0;
+    config
+}
+
+/// Stats of every generation and the state of every individual after `ticks` ticks
+/// of a run of `config`, with the systems free to run on any thread. `padding`
+/// entities spawned and despawned first shift the entity indices of everyone after.
+fn run(config: Config, ticks: usize, padding: usize) -> (String, Vec<String>) {
+    let mut app = build_app(config);
+    for label in [Startup.intern(), Update.intern()] {
+        app.edit_schedule(label, |schedule| {
+            schedule.set_executor_kind(ExecutorKind::MultiThreaded);
+        });
+    }
+    let padding: Vec<Entity> = (0..padding)
+        .map(|_| app.world_mut().spawn_empty().id())
+        .collect();
+    for entity in padding {
+        app.world_mut().despawn(entity);
+    }
+    dispatch(&mut app, &Command::Step(ticks));
+
+    let stats = serde_json::to_string(
+        &app.world()
+            .resource::<StatsHistory>()
+            .records()
+            .collect::<Vec<_>>(),
+    )
+    .unwrap();
+    let ecs = app.world_mut();
+    let mut individuals: Vec<String> = ecs
+        .query::<(&Identity, &Position, Option<&Organism>, Option<&Predator>)>()
+        .iter(ecs)
+        .map(|(identity, position, organism, predator)| {
+            format!(
+                "{:?} {:?} {:?} {:?}",
+                identity, position, organism, predator
+            )
+        })
+        .collect();
+    individuals.sort();
+    (stats, individuals)
+}
+
+#[test]
+fn runs_with_the_same_seed_are_identical() {
+    let first = run(config(7), 30, 0);
+    assert!(!first.1.is_empty());
+    assert_eq!(first, run(config(7), 30, 0));
+    assert_ne!(first, run(config(8), 30, 0));
+}
+
+#[test]
+fn ties_break_the_same_way_whatever_the_entity_indices() {
+    let first = run(tied_config(7), 30, 0);
+    assert!(!first.1.is_empty());
+    assert_eq!(first, run(tied_config(7), 30, 0));
+    assert_eq!(first, run(tied_config(7), 30, 97));
+}
@@ -0,0 +1,49 @@
+//! A system from outside the crate joining the tick in one of the public phases.
+
+use bevy::ecs::event::Events;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use evolution::prelude::*;
+
+/// Takes every organism's energy, as a hazard of the embedding app would.
+fn famine(mut organisms: Query<&mut Organism>) {
+    for mut organism in &mut organisms {
+        organism.energy = -1.0;
+    }
+}
+
+/// The stats of the first generation, with `famine` running in
+/// `SimulationSet::Metabolism` if `with_famine`.
+fn first_generation(with_famine: bool) -> GenerationStats {
+    let config = Config::builder()
+        .headless(true)
+        .seed(3)
+        .initial_organisms(40)
+        .initial_predators(4)
+        .build()
+        .unwrap();
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin, EvolutionPlugin { config }));
+    if with_famine {
+        app.add_systems(Update, famine.in_set(SimulationSet::Metabolism));
+    }
+    while app.world().resource::<Generation>().0 == 0 {
+        app.update();
+    }
+    assert_eq!(app.world().resource::<PopulationCount>().organisms, 40);
+    let events = app.world().resource::<Events<GenerationStats>>();
+    let mut reader = events.get_cursor();
+    let stats = reader.read(events).last().cloned();
+    stats.unwrap()
+}
+
+#[test]
+fn a_system_in_a_public_phase_shapes_the_generation_stats() {
+    let normal = first_generation(false);
+    assert!(normal.organism_count > 0);
+
+    // the dead are despawned in `Population`, which runs after `Metabolism`
+    let famine = first_generation(true);
+    assert_eq!(famine.organism_count, 0);
+    assert_eq!(famine.predator_count(), normal.predator_count());
+}
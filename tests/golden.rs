@@ -0,0 +1,219 @@
+//! Logs written by earlier schema versions, kept as fixtures so that every later
+//! version still replays and analyzes them:
+//! `cargo test --no-default-features --test golden`. Before bumping
+//! `SCHEMA_VERSION`, add the fixtures of the version being left with
+//! `cargo test --no-default-features --test golden -- --ignored`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use evolution::analyze::{self, AnalyzeOptions};
+use evolution::replay::Replay;
+use evolution::schema::{read_generation_stats, SCHEMA_VERSION};
+use evolution::{build_app, default_config, EFFECTIVE_CONFIG_FILE};
+use serde_json::Value;
+
+/// The first version with golden fixtures; the ones before have their own tests in
+/// `schema.rs`.
+const FIRST_GOLDEN: u32 = 47;
+/// Fields a version dropped, by the version and their path, which the records of
+/// older versions lose when they are read.
+const DROPPED: [(u32, &str); 1] = [(48, "world.config")];
+/// The version from which world records name their run, whose config is kept as
+/// the golden `effective_config_vN.toml`.
+const RUN_CONFIG: u32 = 48;
+/// The logs a golden fixture is kept of, each cut to its first lines.
+const LOGS: [(&str, usize); 3] = [
+    ("world_data.jsonl", 1),
+    ("summary_data.jsonl", 2),
+    ("events.jsonl", 8),
+];
+
+fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// `stem_vN.jsonl` of fixture version `version`.
+fn fixture(log: &str, version: u32) -> PathBuf {
+    let stem = log.trim_end_matches(".jsonl");
+    fixtures().join(format!("{}_v{}.jsonl", stem, version))
+}
+
+fn run_config(version: u32) -> PathBuf {
+    fixtures().join(format!("effective_config_v{}.toml", version))
+}
+
+/// Versions from [`FIRST_GOLDEN`] on that have a golden world log.
+fn golden_versions() -> Vec<u32> {
+    let mut versions: Vec<u32> = fs::read_dir(fixtures())
+        .unwrap()
+        .filter_map(|entry| {
+            let name = entry.unwrap().file_name().into_string().ok()?;
+            name.strip_prefix("world_data_v")?
+                .strip_suffix(".jsonl")?
+                .parse()
+                .ok()
+        })
+        .filter(|version| *version >= FIRST_GOLDEN)
+        .collect();
+    versions.sort();
+    versions
+}
+
+/// Whether every value of `old`, written by `version`, is in `new` unchanged,
+/// leaving out the schema version and the fields [`DROPPED`] since: fields a later
+/// version adds may only come on top.
+fn kept(old: &Value, new: &Value, path: &str, version: u32) -> Result<(), String> {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, value) in old {
+                let path = format!("{}.{}", path, key);
+                let dropped = DROPPED
+                    .iter()
+                    .any(|&(since, field)| version < since && field == path);
+                if key == "schema_version" || dropped {
+                    continue;
+                }
+                match new.get(key) {
+                    Some(new) => kept(value, new, &path, version)?,
+                    None => return Err(format!("{} is gone", path)),
+                }
+            }
+            Ok(())
+        }
+        (Value::Array(old), Value::Array(new)) if old.len() == new.len() => old
+            .iter()
+            .zip(new)
+            .enumerate()
+            .try_for_each(|(i, (old, new))| kept(old, new, &format!("{}[{}]", path, i), version)),
+        // floats are written as the f32 they were, and read back widened
+        (Value::Number(old), Value::Number(new))
+            if old.as_f64().map(|old| old as f32) == new.as_f64().map(|new| new as f32) =>
+        {
+            Ok(())
+        }
+        _ if old == new => Ok(()),
+        _ => Err(format!("{} was {} and reads as {}", path, old, new)),
+    }
+}
+
+fn lines(path: &Path) -> Vec<Value> {
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+/// A log directory holding the golden fixtures of `version` under their log names.
+fn log_dir(version: u32) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("evolution_golden_v{}", version));
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+    for (log, _) in LOGS {
+        fs::copy(fixture(log, version), dir.join(log)).unwrap();
+    }
+    if version >= RUN_CONFIG {
+        fs::copy(run_config(version), dir.join(EFFECTIVE_CONFIG_FILE)).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn golden_logs_replay_and_analyze_in_the_current_version() {
+    let versions = golden_versions();
+    assert!(
+        versions.contains(&SCHEMA_VERSION),
+        "no golden fixtures of schema version {}, add them with `--ignored`",
+        SCHEMA_VERSION
+    );
+
+    for version in versions {
+        let dir = log_dir(version);
+
+        let written = lines(&dir.join("world_data.jsonl"));
+        let replay = Replay::open(&dir).unwrap();
+        assert_eq!(replay.timeline().len(), written.len());
+        for line in &written {
+            let generation = line["generation"].as_u64().unwrap() as usize;
+            let record = replay.seek(generation).unwrap().unwrap();
+            assert_eq!(record.schema_version, SCHEMA_VERSION);
+            assert_eq!(
+                record.organisms.len(),
+                line["organisms"].as_array().unwrap().len()
+            );
+            if version >= RUN_CONFIG {
+                let config = replay.config(&record).unwrap();
+                assert!(config.is_some(), "world_data_v{} has no config", version);
+            }
+            let read = serde_json::to_value(&record).unwrap();
+            if let Err(err) = kept(line, &read, "world", version) {
+                panic!("world_data_v{}: {}", version, err);
+            }
+            // a fixture of the current version reads back as written, or the format
+            // changed without a schema bump
+            if version == SCHEMA_VERSION {
+                if let Err(err) = kept(&read, line, "world", version) {
+                    panic!("world_data_v{} reads with {}", version, err);
+                }
+            }
+        }
+
+        let written = lines(&dir.join("summary_data.jsonl"));
+        let stats = read_generation_stats(&dir.join("summary_data.jsonl")).unwrap();
+        assert_eq!(stats.len(), written.len());
+        for (line, stats) in written.iter().zip(&stats) {
+            let read = serde_json::to_value(stats).unwrap();
+            if let Err(err) = kept(line, &read, "stats", version) {
+                panic!("summary_data_v{}: {}", version, err);
+            }
+            if version == SCHEMA_VERSION {
+                if let Err(err) = kept(&read, line, "stats", version) {
+                    panic!("summary_data_v{} reads with {}", version, err);
+                }
+            }
+        }
+
+        let options = AnalyzeOptions {
+            log_dir: dir.clone(),
+            ..AnalyzeOptions::default()
+        };
+        if let Err(err) = analyze::run(&options) {
+            panic!("analyze of the v{} fixtures failed: {}", version, err);
+        }
+        assert!(dir.join("survivorship.csv").exists());
+        assert!(dir.join("energy_flux.csv").exists());
+    }
+}
+
+/// Writes the golden fixtures of the current version from a short run.
+#[test]
+#[ignore]
+fn write_golden_fixtures() {
+    let dir = std::env::temp_dir().join("evolution_golden_run");
+    fs::remove_dir_all(&dir).ok();
+    let mut config = default_config();
+    config.world.headless = true;
+    config.world.printing = false;
+    config.world.seed = 5;
+    config.world.width = 4;
+    config.world.height = 3;
+    config.organism.initial_organisms = 4;
+    config.predator.initial_predators = 1;
+    config.logging.log_data = true;
+    config.logging.output_dir = Some(dir.clone());
+
+    let mut app = build_app(config);
+    for _ in 0..3 {
+        app.update();
+    }
+    drop(app);
+
+    for (log, count) in LOGS {
+        let written = fs::read_to_string(dir.join(log)).unwrap();
+        let kept: Vec<&str> = written.lines().take(count).collect();
+        assert_eq!(kept.len(), count, "{} is too short", log);
+        fs::write(fixture(log, SCHEMA_VERSION), kept.join("\n") + "\n").unwrap();
+    }
+    fs::copy(dir.join(EFFECTIVE_CONFIG_FILE), run_config(SCHEMA_VERSION)).unwrap();
+}
@@ -0,0 +1,88 @@
+//! The sweep build: `cargo test --no-default-features --test headless` runs the
+//! simulation without any of the rendering code compiled in.
+
+use std::time::{Duration, Instant};
+
+use bevy::prelude::With;
+use evolution::{build_app, default_config, Active, Generation, Inactive, Organism, StatsHistory};
+
+#[test]
+fn headless_runs_a_hundred_generations() {
+    let mut config = default_config();
+    config.world.headless = true;
+    config.world.seed = 5;
+    config.world.width = 20;
+    config.world.height = 20;
+    config.organism.initial_organisms = 60;
+    config.predator.initial_predators = 5;
+    config.logging.log_data = false;
+
+    let mut app = build_app(config);
+    for _ in 0..100 {
+        app.update();
+    }
+
+    assert_eq!(app.world().resource::<Generation>().0, 100);
+    assert!(app.world().resource::<StatsHistory>().records().count() > 0);
+}
+
+#[cfg(not(feature = "gui"))]
+#[test]
+fn a_window_is_rejected_without_the_gui_feature() {
+    let mut config = default_config();
+    config.world.headless = false;
+    let err = evolution::try_build_app(config).unwrap_err();
+    assert_eq!(err.field, "headless");
+}
+
+#[test]
+fn a_tick_rate_cap_holds_headless_runs_back() {
+    let mut config = default_config();
+    config.world.headless = true;
+    config.world.target_ticks_per_second = Some(5.0);
+    config.logging.log_data = false;
+
+    let mut app = build_app(config);
+    let start = Instant::now();
+    for _ in 0..6 {
+        app.update();
+    }
+    let elapsed = start.elapsed();
+
+    // the first tick is immediate, the five after it at least 200 ms apart
+    assert_eq!(app.world().resource::<Generation>().0, 6);
+    assert!(elapsed >= Duration::from_secs(1), "{:?}", elapsed);
+}
+
+#[test]
+fn pooled_runs_count_only_the_living() {
+    let mut config = default_config();
+    config.world.headless = true;
+    config.world.seed = 5;
+    config.world.width = 20;
+    config.world.height = 20;
+    config.world.entity_pool_size = 30;
+    config.organism.initial_organisms = 60;
+    config.predator.initial_predators = 5;
+    config.logging.log_data = false;
+
+    let mut app = build_app(config);
+    let mut most_pooled = 0;
+    for _ in 0..60 {
+        app.update();
+        let ecs = app.world_mut();
+        let living = ecs
+            .query_filtered::<(), (With<Organism>, Active)>()
+            .iter(ecs)
+            .count();
+        let pooled = ecs
+            .query_filtered::<(), (With<Organism>, With<Inactive>)>()
+            .iter(ecs)
+            .count();
+        let stats = ecs.resource::<StatsHistory>().records().last().unwrap();
+        assert_eq!(stats.organism_count, living);
+        assert!(pooled <= 30);
+        most_pooled = most_pooled.max(pooled);
+    }
+    assert!(most_pooled > 0);
+}
@@ -0,0 +1,78 @@
+//! Resuming a run from a state of its history, the way the GUI does when it is
+//! unpaused on an earlier generation: the abandoned future's logs go to a branch
+//! directory and the logs carry on from the generation resumed at.
+
+use std::fs;
+use std::path::PathBuf;
+
+use evolution::history::{History, Snapshot};
+use evolution::plugins::logging::branch_logs;
+use evolution::replay::Replay;
+use evolution::schema::read_generation_stats;
+use evolution::{build_app, default_config, Generation, StatsHistory};
+
+#[test]
+fn a_branched_run_logs_on_from_the_generation_it_resumed_at() {
+    let dir = std::env::temp_dir().join("evolution_history_logs");
+    fs::remove_dir_all(&dir).ok();
+    let mut config = default_config();
+    config.world.headless = true;
+    config.world.printing = false;
+    config.world.seed = 9;
+    config.world.generation_limit = Some(12);
+    config.organism.initial_organisms = 40;
+    config.predator.initial_predators = 4;
+    config.logging.log_data = true;
+    config.logging.output_dir = Some(dir.clone());
+
+    let mut app = build_app(config);
+    let mut history = History::new(4, 3);
+    for _ in 0..10 {
+        app.update();
+        let generation = app.world().resource::<Generation>().0;
+        if history.due(generation) {
+            history.record(Snapshot::capture(app.world_mut()));
+        }
+    }
+
+    let ecs = app.world_mut();
+    assert!(history.back(ecs) && history.back(ecs));
+    assert_eq!(history.branch(ecs), Some(6));
+    let branch = branch_logs(ecs, 6).unwrap().unwrap();
+    assert_eq!(branch, dir.join("branch_1"));
+    assert!(ecs
+        .resource::<StatsHistory>()
+        .records()
+        .all(|record| record.generation <= 6));
+
+    // up to the limit, then one more update finishes the run
+    for _ in 0..7 {
+        app.update();
+    }
+
+    let generations = |path: PathBuf| -> Vec<u32> {
+        read_generation_stats(&path)
+            .unwrap()
+            .iter()
+            .map(|record| record.generation)
+            .collect()
+    };
+    assert_eq!(
+        generations(branch.join("summary_data.jsonl")),
+        (1..=10).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        generations(dir.join("summary_data.jsonl")),
+        (1..=12).collect::<Vec<_>>()
+    );
+
+    // the index was reopened where the branch cut it, so its offsets still hold
+    let replay = Replay::open(&dir).unwrap();
+    assert_eq!(replay.timeline().len(), 12);
+    for generation in [6, 7, 12] {
+        let record = replay.seek(generation).unwrap().unwrap();
+        assert_eq!(record.generation, generation);
+    }
+    assert_eq!(Replay::open(&branch).unwrap().timeline().len(), 10);
+    fs::remove_dir_all(&dir).ok();
+}
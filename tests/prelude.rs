@@ -0,0 +1,82 @@
+//! The prelude is the API embedding apps are told to use, so removing or moving
+//! anything in it is a breaking change; this lists what it must keep exporting.
+
+use std::any::type_name;
+
+use bevy::app::Plugin;
+use bevy::ecs::event::Event;
+use evolution::prelude::*;
+
+fn plugin<T: Plugin>() -> &'static str {
+    type_name::<T>()
+}
+
+fn event<T: Event>() -> &'static str {
+    type_name::<T>()
+}
+
+#[test]
+fn prelude_exports_the_embedding_api() {
+    let exports = [
+        plugin::<SimulationPlugin>(),
+        plugin::<EvolutionPlugin>(),
+        type_name::<SimulationSet>(),
+        type_name::<Config>(),
+        type_name::<ConfigBuilder>(),
+        type_name::<ConfigError>(),
+        type_name::<World>(),
+        type_name::<Tile>(),
+        type_name::<Biome>(),
+        type_name::<Organism>(),
+        type_name::<Predator>(),
+        type_name::<Position>(),
+        type_name::<Identity>(),
+        type_name::<SpeciesId>(),
+        type_name::<Generation>(),
+        type_name::<PopulationCount>(),
+        type_name::<SpatialIndex>(),
+        type_name::<PredatorSpatialIndex>(),
+        type_name::<AppState>(),
+        event::<BiomeChanged>(),
+        type_name::<BiomeChangeCause>(),
+        event::<CatastropheStruck>(),
+        type_name::<CatastropheKind>(),
+        event::<FoodPatchAppeared>(),
+        event::<Born>(),
+        event::<Died>(),
+        event::<Killed>(),
+        event::<PanicSpread>(),
+        event::<GenerationStats>(),
+        type_name::<StatsHistory>(),
+        type_name::<SpeciesStats>(),
+        type_name::<Report>(),
+    ];
+    assert!(exports.iter().all(|name| name.starts_with("evolution::")));
+
+    let build: fn(Config) -> bevy::app::App = build_app;
+    let try_build: fn(Config) -> Result<bevy::app::App, ConfigError> = try_build_app;
+    let _ = (build, try_build);
+}
+
+#[test]
+fn embedded_simulation_sends_generation_stats() {
+    let config = Config::builder()
+        .headless(true)
+        .initial_organisms(20)
+        .build()
+        .unwrap();
+    let mut app = bevy::app::App::new();
+    app.add_plugins((
+        bevy::prelude::MinimalPlugins,
+        bevy::state::app::StatesPlugin,
+        EvolutionPlugin { config },
+    ));
+    for _ in 0..3 {
+        app.update();
+    }
+    let stats = app
+        .world()
+        .resource::<bevy::ecs::event::Events<GenerationStats>>();
+    let mut reader = stats.get_cursor();
+    assert!(reader.read(stats).any(|stats| stats.organism_count > 0));
+}
@@ -0,0 +1,112 @@
+//! Runs without predators: `cargo test --no-default-features --test prey_only`.
+
+use bevy::ecs::schedule::{ScheduleLabel, Schedules};
+use bevy::prelude::{Startup, Update};
+use evolution::schema::parse_generation_stats;
+use evolution::{build_app, default_config, Config, Generation, StatsHistory};
+
+fn prey_only() -> Config {
+    let mut config = default_config();
+    config.world.headless = true;
+    config.world.seed = 3;
+    config.world.width = 20;
+    config.world.height = 20;
+    config.organism.initial_organisms = 40;
+    config.predator.predators_enabled = false;
+    config.predator.initial_predators = 0;
+    config.logging.log_data = false;
+    config
+}
+
+/// Names of the systems in `label` of the app's schedules.
+fn system_names(app: &bevy::app::App, label: impl ScheduleLabel) -> Vec<String> {
+    let schedule = app.world().resource::<Schedules>().get(label).unwrap();
+    schedule
+        .systems()
+        .unwrap()
+        .map(|(_, system)| system.name().to_string())
+        .collect()
+}
+
+#[test]
+fn no_predator_system_is_scheduled() {
+    let mut app = build_app(prey_only());
+    for _ in 0..20 {
+        app.update();
+    }
+    assert_eq!(app.world().resource::<Generation>().0, 20);
+
+    let predator_systems = [
+        "spawn_predators",
+        "predator_movement",
+        "territorial_contests",
+        "rebuild_predator_spatial_index",
+        "hunting",
+        "mark_kill_sites",
+        "rot_carrion",
+        "fade_kill_scent",
+        "despawn_dead_predators",
+        "predator_overcrowding",
+        "predator_reproduction",
+        "claim_territories",
+    ];
+    let mut scheduled = system_names(&app, Startup);
+    scheduled.extend(system_names(&app, Update));
+    assert!(scheduled
+        .iter()
+        .any(|name| name.ends_with("::organism_movement")));
+    for name in &scheduled {
+        assert!(
+            !predator_systems
+                .iter()
+                .any(|system| name.ends_with(&format!("::{}", system))),
+            "{} is scheduled",
+            name
+        );
+    }
+
+    // the same run with predators has every one of them
+    let mut config = prey_only();
+    config.predator.predators_enabled = true;
+    config.predator.initial_predators = 3;
+    let mut app = build_app(config);
+    app.update();
+    let mut scheduled = system_names(&app, Startup);
+    scheduled.extend(system_names(&app, Update));
+    for system in predator_systems {
+        let suffix = format!("::{}", system);
+        assert!(
+            scheduled.iter().any(|name| name.ends_with(&suffix)),
+            "{} is missing",
+            system
+        );
+    }
+}
+
+#[test]
+fn prey_only_stats_leave_the_predator_fields_out() {
+    let mut app = build_app(prey_only());
+    for _ in 0..10 {
+        app.update();
+    }
+    let history = app.world().resource::<StatsHistory>();
+    assert!(history.records().count() > 0);
+    for record in history.records() {
+        assert!(record.predators.is_none());
+        let line = serde_json::to_string(record).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        for field in [
+            "predator_count",
+            "predator_avg_size",
+            "predator_births",
+            "territories",
+            "kills_per_biome",
+            "total_carrion",
+        ] {
+            assert!(value.get(field).is_none(), "{} in {}", field, line);
+        }
+        assert!(value.get("organism_count").is_some());
+        // and reads back as a prey-only record
+        assert!(parse_generation_stats(&line).unwrap().predators.is_none());
+    }
+}
@@ -0,0 +1,72 @@
+use evolution::repl::{dispatch, Command, USAGE};
+use evolution::{build_app, default_config};
+
+fn run_script(script: &[&str]) -> Vec<String> {
+    let mut config = default_config();
+    config.world.headless = true;
+    config.organism.initial_organisms = 5;
+    config.predator.initial_predators = 0;
+    let mut app = build_app(config);
+
+    script
+        .iter()
+        .map(|line| match line.parse::<Command>() {
+            Ok(command) => dispatch(&mut app, &command),
+            Err(err) => format!("{}\n{}", err, USAGE),
+        })
+        .collect()
+}
+
+#[test]
+fn scripted_session() {
+    let snapshot = std::env::temp_dir().join("evolution_repl_snapshot.json");
+    let save = format!("save {}", snapshot.display());
+    let output = run_script(&[
+        "step 3",
+        "stats",
+        "spawn predator 2 3",
+        "show 2 3",
+        "top predators speed 1",
+        "top organisms wingspan",
+        "show 50 50",
+        "fly away",
+        "step two",
+        &save,
+        "quit",
+    ]);
+
+    assert_eq!(output[0], "generation 3");
+    assert!(output[1].starts_with("generation 3\n"));
+    assert!(output[1].contains("predators: 0"));
+    assert!(output[2].starts_with("spawned Predator #"));
+    assert!(output[3].starts_with("tile (2, 3):"));
+    assert!(output[3].contains("predator #"));
+    assert!(output[4].starts_with("1. #"));
+    assert!(output[5].starts_with("unknown trait `wingspan`"));
+    assert!(output[6].contains("outside the 10x10 world"));
+    assert!(output[7].starts_with("unknown command `fly`"));
+    assert!(output[7].ends_with(USAGE));
+    assert!(output[8].starts_with("step count must be a non-negative integer"));
+    assert!(output[9].starts_with("saved snapshot"));
+    assert_eq!(output[10], "bye");
+
+    let saved = std::fs::read_to_string(&snapshot).unwrap();
+    let record = evolution::schema::parse_world_record(&saved).unwrap();
+    assert_eq!(record.generation, 3);
+    assert_eq!(record.predators.len(), 1);
+    std::fs::remove_file(snapshot).ok();
+}
+
+#[test]
+fn parses_command_arguments() {
+    assert_eq!("step".parse::<Command>(), Ok(Command::Step(1)));
+    assert_eq!("step 10".parse::<Command>(), Ok(Command::Step(10)));
+    assert!("show 1".parse::<Command>().is_err());
+    assert!("stats now".parse::<Command>().is_err());
+    assert!("spawn dragon 1 1".parse::<Command>().is_err());
+    assert_eq!(
+        "export_population seeds.json".parse::<Command>(),
+        Ok(Command::ExportPopulation("seeds.json".into()))
+    );
+    assert!("export_population".parse::<Command>().is_err());
+}
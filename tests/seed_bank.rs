@@ -0,0 +1,77 @@
+use evolution::repl::{dispatch, Command};
+use evolution::seed_bank::{SeedBank, SEED_BANK_VERSION};
+use evolution::{build_app, default_config, StatsHistory};
+
+#[test]
+fn exported_traits_seed_the_next_run() {
+    let path = std::env::temp_dir().join("evolution_seed_bank_round_trip.json");
+
+    let mut config = default_config();
+    config.world.headless = true;
+    config.organism.initial_organisms = 20;
+    config.organism.initial_organism_size = 2.5;
+    config.organism.initial_organism_boldness = 0.9;
+    config.organism.initial_organism_reproduction_threshold = 1000.0;
+    config.predator.initial_predators = 3;
+    config.predator.initial_predator_hunting_efficiency = 4.0;
+    config.predator.initial_predator_reproduction_threshold = 1000.0;
+    let mut app = build_app(config);
+
+    let export = format!("export_population {}", path.display());
+    let output: Vec<_> = ["step 2", export.as_str()]
+        .iter()
+        .map(|line| dispatch(&mut app, &line.parse::<Command>().unwrap()))
+        .collect();
+    assert!(output[1].starts_with("exported "), "{}", output[1]);
+
+    let bank = SeedBank::load(&path).unwrap();
+    assert_eq!(bank.version, SEED_BANK_VERSION);
+    assert_eq!(bank.generation, 2);
+    assert!(!bank.organisms.is_empty());
+    assert!(!bank.predators.is_empty());
+
+    // a fresh run with different initial traits and counts; the seeded thresholds
+    // keep anyone from reproducing, so no mutation blurs the averages
+    let mut config = default_config();
+    config.world.headless = true;
+    config.world.seed = 99;
+    config.world.seed_population = Some(path.clone());
+    config.organism.initial_organisms = 50;
+    config.predator.initial_predators = 5;
+    let mut app = build_app(config);
+    dispatch(&mut app, &Command::Step(1));
+    std::fs::remove_file(&path).ok();
+
+    let history = app.world().resource::<StatsHistory>();
+    let first = history.records().next().unwrap();
+    assert!((first.organism_avg_size - 2.5).abs() < 1e-5);
+    assert!((first.organism_avg_boldness - 0.9).abs() < 1e-5);
+    let predators = first.predators.as_ref().unwrap();
+    assert!((predators.predator_avg_hunting_efficiency - 4.0).abs() < 1e-5);
+}
+
+#[test]
+fn a_save_cut_short_leaves_the_previous_bank_intact() {
+    let path = std::env::temp_dir().join("evolution_seed_bank_interrupted.json");
+    let tmp = std::env::temp_dir().join("evolution_seed_bank_interrupted.json.tmp");
+
+    let mut config = default_config();
+    config.world.headless = true;
+    config.organism.initial_organisms = 10;
+    config.predator.initial_predators = 2;
+    let mut app = build_app(config);
+    let export = Command::ExportPopulation(path.clone());
+    dispatch(&mut app, &Command::Step(1));
+    dispatch(&mut app, &export);
+
+    // a crash halfway through the next save leaves only a truncated temporary file
+    let json = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&tmp, &json[..json.len() / 2]).unwrap();
+    assert_eq!(SeedBank::load(&path).unwrap().generation, 1);
+
+    dispatch(&mut app, &Command::Step(1));
+    dispatch(&mut app, &export);
+    assert_eq!(SeedBank::load(&path).unwrap().generation, 2);
+    assert!(!tmp.exists());
+    std::fs::remove_file(&path).ok();
+}
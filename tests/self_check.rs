@@ -0,0 +1,52 @@
+use bevy::prelude::*;
+use evolution::bench::bench_config;
+use evolution::plugins::simulation::SimulationSet;
+use evolution::self_check::{app_state_hash, compare, run};
+use evolution::{build_app, default_config, Config, Organism};
+use rand::Rng;
+
+fn config() -> Config {
+    let mut config = default_config();
+    config.world.seed = 11;
+    config.organism.initial_organisms = 40;
+    config.predator.initial_predators = 4;
+    bench_config(&config)
+}
+
+/// Nudges the energy of every organism by an amount drawn from the unseeded thread
+/// RNG, the kind of mistake the self-check exists to catch.
+fn unseeded_nudge(mut organisms: Query<&mut Organism>) {
+    let mut rng = rand::thread_rng();
+    for mut organism in organisms.iter_mut() {
+        organism.energy += rng.gen_range(0.0..1.0);
+    }
+}
+
+#[test]
+fn seeded_runs_pass_the_self_check() {
+    assert!(run(&config(), 15).is_ok());
+
+    let mut first = build_app(config());
+    let mut second = build_app(config());
+    for _ in 0..5 {
+        first.update();
+        second.update();
+    }
+    assert_eq!(app_state_hash(&mut first), app_state_hash(&mut second));
+}
+
+#[test]
+fn a_thread_rng_call_fails_the_self_check() {
+    let tampered = || {
+        let mut app = build_app(config());
+        app.add_systems(Update, unseeded_nudge.after(SimulationSet::End));
+        app
+    };
+    let divergence = compare(tampered(), tampered(), 15).unwrap_err();
+    assert!(divergence.generation <= 2, "{}", divergence);
+    assert!(
+        divergence.difference.contains("energy"),
+        "{}",
+        divergence.difference
+    );
+}
@@ -0,0 +1,146 @@
+//! Runs every config in `examples/configs/` headless for 200 generations on top of
+//! the `balanced` preset, the way `--preset balanced --config <file>` does. The
+//! pathological ones among them drown everything, starve everything, push traits
+//! against their bounds and cull down to an entity cap of one. None of them may
+//! panic, record a value that is not finite, outgrow the cap or end without a
+//! report.
+
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use bevy::prelude::With;
+use evolution::schema::{read_generation_stats, read_world_records};
+use evolution::{
+    layer_config, presets, try_build_app, Active, Config, Organism, Predator, StatsHistory,
+};
+
+const GENERATIONS: usize = 200;
+
+/// The first `NaN`, `inf` or `-inf` in the `Debug` output of a value.
+fn non_finite(debug: &str) -> Option<&str> {
+    debug
+        .split(|c: char| !c.is_alphanumeric() && c != '-')
+        .find(|word| matches!(*word, "NaN" | "inf" | "-inf"))
+}
+
+/// The config in `examples/configs/{name}.toml`, layered over the `balanced`
+/// preset and logging into `dir`.
+fn smoke_config(name: &str, toml: &str, dir: &Path) -> Config {
+    let (mut config, _) = layer_config(presets::find("balanced"), Some(toml), &[])
+        .unwrap_or_else(|err| panic!("{}: {}", name, err));
+    assert_eq!(config.validate(), Ok(()), "{}", name);
+    config.world.headless = true;
+    config.world.printing = false;
+    config.world.generation_limit = Some(GENERATIONS);
+    config.logging.log_data = true;
+    config.logging.output_dir = Some(dir.to_path_buf());
+    config
+}
+
+fn smoke_test(name: &str, toml: &str) {
+    let dir = std::env::temp_dir().join(format!("evolution_smoke_{}", name));
+    fs::remove_dir_all(&dir).ok();
+    let config = smoke_config(name, toml, &dir);
+    let cap = config.world.max_total_entities;
+    config.logging.create_output_dir().unwrap();
+    let mut app = try_build_app(config).unwrap_or_else(|err| panic!("{}: {}", name, err));
+
+    for generation in 1..=GENERATIONS {
+        if panic::catch_unwind(AssertUnwindSafe(|| app.update())).is_err() {
+            panic!("{}: panicked in generation {}", name, generation);
+        }
+        let ecs = app.world_mut();
+        let living = ecs
+            .query_filtered::<(), (With<Organism>, Active)>()
+            .iter(ecs)
+            .count()
+            + ecs
+                .query_filtered::<(), (With<Predator>, Active)>()
+                .iter(ecs)
+                .count();
+        assert!(
+            living <= cap,
+            "{}: {} alive in generation {} with a cap of {}",
+            name,
+            living,
+            generation,
+            cap
+        );
+        if let Some(stats) = ecs.resource::<StatsHistory>().records().last() {
+            let debug = format!("{:?}", stats);
+            if let Some(value) = non_finite(&debug) {
+                panic!(
+                    "{}: {} recorded in generation {}: {}",
+                    name, value, generation, debug
+                );
+            }
+        }
+    }
+    // one more update finishes the run and writes the report
+    if panic::catch_unwind(AssertUnwindSafe(|| app.update())).is_err() {
+        panic!("{}: panicked while finishing", name);
+    }
+
+    // a value that was not finite is written as null, which fails to parse
+    let stats = read_generation_stats(&dir.join("summary_data.jsonl"))
+        .unwrap_or_else(|err| panic!("{}: summary_data.jsonl: {}", name, err));
+    assert_eq!(stats.len(), GENERATIONS, "{}", name);
+    read_world_records(&dir.join("world_data.jsonl"))
+        .unwrap_or_else(|err| panic!("{}: world_data.jsonl: {}", name, err));
+    let report = fs::read_to_string(dir.join("report.json"))
+        .unwrap_or_else(|err| panic!("{}: report.json: {}", name, err));
+    serde_json::from_str::<serde_json::Value>(&report)
+        .unwrap_or_else(|err| panic!("{}: report.json: {}", name, err));
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn small() {
+    smoke_test("small", include_str!("../examples/configs/small.toml"));
+}
+
+#[test]
+fn medium() {
+    smoke_test("medium", include_str!("../examples/configs/medium.toml"));
+}
+
+#[test]
+fn all_water() {
+    smoke_test(
+        "all_water",
+        include_str!("../examples/configs/all_water.toml"),
+    );
+}
+
+#[test]
+fn no_regrowth() {
+    smoke_test(
+        "no_regrowth",
+        include_str!("../examples/configs/no_regrowth.toml"),
+    );
+}
+
+#[test]
+fn huge_mutability() {
+    smoke_test(
+        "huge_mutability",
+        include_str!("../examples/configs/huge_mutability.toml"),
+    );
+}
+
+#[test]
+fn entity_cap_one() {
+    smoke_test(
+        "entity_cap_one",
+        include_str!("../examples/configs/entity_cap_one.toml"),
+    );
+}
+
+#[test]
+fn non_finite_values_are_found_in_debug_output() {
+    assert_eq!(non_finite("Stats { a: 1.0, b: NaN }"), Some("NaN"));
+    assert_eq!(non_finite("[inf, 2.0]"), Some("inf"));
+    assert_eq!(non_finite("x: -inf }"), Some("-inf"));
+    assert_eq!(non_finite("Info { infection: 1e-5 }"), None);
+}
@@ -0,0 +1,77 @@
+use bevy::utils::hashbrown::HashMap;
+use evolution::repl::{dispatch, Command};
+use evolution::{
+    build_app, default_config, Identity, Organism, SpeciesConfig, SpeciesId, StatsHistory,
+};
+
+#[test]
+fn species_stay_apart_and_are_counted_separately() {
+    let mut config = default_config();
+    config.world.headless = true;
+    config.world.max_total_entities = 2_000;
+    config.organism.initial_organisms = 30;
+    config.organism.initial_organism_reproduction_threshold = 4.0;
+    config.predator.initial_predators = 0;
+    config.species_b = Some(SpeciesConfig {
+        initial_organisms: 20,
+        initial_organism_size: Some(2.0),
+        organism_mutability: Some(0.3),
+        ..SpeciesConfig::default()
+    });
+    let mut app = build_app(config);
+    dispatch(&mut app, &Command::Step(1));
+
+    let ecs = app.world_mut();
+    let founders: HashMap<u64, SpeciesId> = ecs
+        .query::<(&Identity, &SpeciesId)>()
+        .iter(ecs)
+        .filter(|(identity, _)| identity.parent.is_none())
+        .map(|(identity, species)| (identity.id, *species))
+        .collect();
+    let founded = |species| founders.values().filter(|&&of| of == species).count();
+    assert_eq!((founded(SpeciesId::A), founded(SpeciesId::B)), (30, 20));
+
+    dispatch(&mut app, &Command::Step(10));
+
+    // every descendant belongs to the species its founder did
+    let ecs = app.world_mut();
+    let descendants: Vec<_> = ecs
+        .query::<(&Organism, &Identity, &SpeciesId)>()
+        .iter(ecs)
+        .filter(|(_, identity, _)| identity.parent.is_some())
+        .map(|(_, identity, species)| (identity.lineage, *species))
+        .collect();
+    assert!(descendants.len() > 10, "{} descendants", descendants.len());
+    for (lineage, species) in &descendants {
+        assert_eq!(founders[lineage], *species, "lineage {}", lineage);
+    }
+
+    let history = app.world().resource::<StatsHistory>();
+    let last = history.records().last().unwrap();
+    assert_eq!(last.species.len(), 2);
+    assert_eq!(last.species[0].species, SpeciesId::A);
+    assert_eq!(last.species[1].species, SpeciesId::B);
+    assert_eq!(
+        last.species[0].count + last.species[1].count,
+        last.organism_count
+    );
+    assert!(last.species[0].count > 0 && last.species[1].count > 0);
+    assert!(last.species[1].avg_size > last.species[0].avg_size);
+
+    let output = dispatch(&mut app, &Command::Stats);
+    assert!(output.contains("species 1: "), "{}", output);
+}
+
+#[test]
+fn single_species_runs_report_no_species_breakdown() {
+    let mut config = default_config();
+    config.world.headless = true;
+    config.organism.initial_organisms = 10;
+    let mut app = build_app(config);
+    dispatch(&mut app, &Command::Step(2));
+    let output = dispatch(&mut app, &Command::Stats);
+    assert!(!output.contains("species"), "{}", output);
+
+    let history = app.world().resource::<StatsHistory>();
+    assert!(history.records().all(|record| record.species.is_empty()));
+}
@@ -0,0 +1,62 @@
+use evolution::provenance::Provenance;
+use evolution::universes::{self, universe_dir, Populations};
+use evolution::{build_app, default_config, Organism, Predator};
+
+#[test]
+fn universes_run_independently_and_add_up() {
+    let dir = std::env::temp_dir().join("evolution_universes");
+    std::fs::remove_dir_all(&dir).ok();
+
+    let mut config = default_config();
+    config.world.seed = 7;
+    config.world.width = 20;
+    config.world.height = 20;
+    config.organism.initial_organisms = 40;
+    config.predator.initial_predators = 4;
+    config.logging.log_data = true;
+
+    let rows = universes::run(&config, 3, 50, &dir).unwrap();
+    assert_eq!(rows.len(), 50);
+    for row in &rows {
+        assert!(row.universes.iter().all(Option::is_some));
+        let total = row.total();
+        let sum = |count: fn(&Populations) -> usize| {
+            row.universes.iter().flatten().map(count).sum::<usize>()
+        };
+        assert_eq!(total.organisms, sum(|p| p.organisms));
+        assert_eq!(total.predators, sum(|p| p.predators));
+    }
+
+    // universe 1 matches a run of its own with the seed after the shared one
+    let mut alone = universes::universe_config(&config, 1, 50, &dir);
+    alone.logging.log_data = false;
+    let mut app = build_app(alone);
+    for row in &rows {
+        app.update();
+        let ecs = app.world_mut();
+        let populations = Populations {
+            organisms: ecs.query::<&Organism>().iter(ecs).count(),
+            predators: ecs.query::<&Predator>().iter(ecs).count(),
+        };
+        assert_eq!(row.universes[1], Some(populations), "{}", row.generation);
+    }
+
+    let batch = Provenance::read(&dir.join("universes.csv"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(batch.seed, 7);
+    for k in 0..3 {
+        let logs = universe_dir(&dir, k);
+        assert!(logs.join("summary_data.jsonl").exists());
+        let report = Provenance::read(&logs.join("report.json"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(report.run_id, format!("{}-universe_{}", batch.run_id, k));
+        assert_eq!((report.seed, report.generation), (7 + k as u64, 50));
+        assert!(report.state_hash.is_some());
+    }
+    let csv = std::fs::read_to_string(dir.join("universes.csv")).unwrap();
+    let rows = csv.lines().filter(|line| !line.starts_with('#'));
+    assert_eq!(rows.count(), 1 + 50 * 4);
+    std::fs::remove_dir_all(&dir).ok();
+}